@@ -6,6 +6,7 @@
 //! - Player movement
 //! - Basic game state management
 //! - Custom components
+//! - Spawning entities from a prefab
 
 use my_engine::prelude::*;
 use my_engine::ecs::Component;
@@ -20,7 +21,7 @@ struct Player {
 
 impl Component for Player {}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Enemy {
     speed: f32,
     patrol_radius: f32,
@@ -115,32 +116,39 @@ fn setup_scene(engine: &mut Engine) {
     let scene = engine.scene_mut();
 
     // Create player
-    let player_id = scene.create_entity("Player".to_string());
-    if let Some(entity) = scene.get_entity_mut(player_id) {
-        entity.add_component(Transform::from_position(Vec3::new(0.0, 0.5, 0.0)));
-        entity.add_component(Player {
-            speed: 5.0,
-            rotation_speed: 3.0,
-        });
-    }
+    scene.spawn_named(
+        "Player",
+        (
+            Transform::from_position(Vec3::new(0.0, 0.5, 0.0)),
+            Player {
+                speed: 5.0,
+                rotation_speed: 3.0,
+            },
+        ),
+    );
     println!("Created player entity");
 
-    // Create enemies in a circle
+    // Create enemies in a circle from one template
+    let radius = 8.0;
+    let enemy_prefab = Prefab::new("Enemy")
+        .with(Transform::new())
+        .with(Enemy {
+            speed: 2.0,
+            patrol_radius: radius,
+            angle: 0.0,
+        });
     let num_enemies = 5;
     for i in 0..num_enemies {
         let angle = (i as f32 / num_enemies as f32) * 2.0 * PI;
-        let radius = 8.0;
         let x = angle.cos() * radius;
         let z = angle.sin() * radius;
 
-        let enemy_id = scene.create_entity(format!("Enemy_{}", i));
-        if let Some(entity) = scene.get_entity_mut(enemy_id) {
-            entity.add_component(Transform::from_position(Vec3::new(x, 0.5, z)));
-            entity.add_component(Enemy {
-                speed: 2.0,
-                patrol_radius: radius,
-                angle,
-            });
+        let enemy_id = scene.spawn_prefab(&enemy_prefab);
+        if let Some(transform) = scene.get_component_mut::<Transform>(enemy_id) {
+            transform.position = Vec3::new(x, 0.5, z);
+        }
+        if let Some(enemy) = scene.get_component_mut::<Enemy>(enemy_id) {
+            enemy.angle = angle;
         }
     }
     println!("Created {} enemies", num_enemies);
@@ -152,31 +160,30 @@ fn setup_scene(engine: &mut Engine) {
         let x = angle.cos() * radius;
         let z = angle.sin() * radius;
 
-        let platform_id = scene.create_entity(format!("Platform_{}", i));
-        if let Some(entity) = scene.get_entity_mut(platform_id) {
-            entity.add_component(Transform::from_position(Vec3::new(x, 0.0, z)));
-            entity.add_component(Rotator {
-                speed: 0.5 + i as f32 * 0.2,
-            });
-        }
+        scene.spawn_named(
+            &format!("Platform_{}", i),
+            (
+                Transform::from_position(Vec3::new(x, 0.0, z)),
+                Rotator {
+                    speed: 0.5 + i as f32 * 0.2,
+                },
+            ),
+        );
     }
     println!("Created 4 rotating platforms");
 
     // Create ground plane entity
-    let ground_id = scene.create_entity("Ground".to_string());
-    if let Some(entity) = scene.get_entity_mut(ground_id) {
-        let mut transform = Transform::new();
-        transform.position = Vec3::new(0.0, -0.5, 0.0);
-        transform.scale = Vec3::new(20.0, 0.1, 20.0);
-        entity.add_component(transform);
-    }
+    let mut ground = Transform::new();
+    ground.position = Vec3::new(0.0, -0.5, 0.0);
+    ground.scale = Vec3::new(20.0, 0.1, 20.0);
+    scene.spawn_named("Ground", ground);
     println!("Created ground plane");
 
     println!("Scene setup complete! Total entities: {}", scene.entity_count());
 }
 
 fn update_player(scene: &mut Scene, input: &InputManager, delta: f32) {
-    for entity in scene.active_entities_mut() {
+    for mut entity in scene.active_entities_mut() {
         if let Some(player) = entity.get_component::<Player>() {
             let speed = player.speed;
             
@@ -218,33 +225,22 @@ fn update_player(scene: &mut Scene, input: &InputManager, delta: f32) {
 }
 
 fn update_enemies(scene: &mut Scene, delta: f32) {
-    for entity in scene.active_entities_mut() {
-        if let Some(enemy) = entity.get_component_mut::<Enemy>() {
-            // Update patrol angle
-            enemy.angle += enemy.speed * delta * 0.3;
+    for (enemy, transform) in scene.query_mut::<(&mut Enemy, &mut Transform)>() {
+        // Update patrol angle
+        enemy.angle += enemy.speed * delta * 0.3;
 
-            let x = enemy.angle.cos() * enemy.patrol_radius;
-            let z = enemy.angle.sin() * enemy.patrol_radius;
+        transform.position.x = enemy.angle.cos() * enemy.patrol_radius;
+        transform.position.z = enemy.angle.sin() * enemy.patrol_radius;
 
-            if let Some(transform) = entity.get_component_mut::<Transform>() {
-                transform.position.x = x;
-                transform.position.z = z;
-
-                // Face movement direction
-                transform.rotation = Quat::from_rotation_y(enemy.angle + PI / 2.0);
-            }
-        }
+        // Face movement direction
+        transform.rotation = Quat::from_rotation_y(enemy.angle + PI / 2.0);
     }
 }
 
 fn update_rotators(scene: &mut Scene, delta: f32) {
-    for entity in scene.active_entities_mut() {
-        if let Some(rotator) = entity.get_component::<Rotator>() {
-            if let Some(transform) = entity.get_component_mut::<Transform>() {
-                let rotation = Quat::from_rotation_y(rotator.speed * delta);
-                transform.rotation = rotation * transform.rotation;
-            }
-        }
+    for (rotator, transform) in scene.query_mut::<(&Rotator, &mut Transform)>() {
+        let rotation = Quat::from_rotation_y(rotator.speed * delta);
+        transform.rotation = rotation * transform.rotation;
     }
 }
 
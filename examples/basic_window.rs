@@ -9,8 +9,9 @@
 use my_engine::prelude::*;
 
 fn main() {
-    // Create default configuration
-    let config = EngineConfig::default();
+    // Create default configuration, with a ground grid so the view isn't empty
+    let mut config = EngineConfig::default();
+    config.renderer.grid = true;
 
     // Create engine
     let engine = Engine::new(config);
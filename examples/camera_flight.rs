@@ -16,6 +16,7 @@ fn main() {
 
     let mut config = EngineConfig::default();
     config.window.title = "Camera Flight".to_string();
+    config.renderer.grid = true;
     
     let mut engine = Engine::new(config);
 
@@ -23,10 +24,7 @@ fn main() {
     let scene = engine.scene_mut();
     
     // Central sphere
-    let center = scene.create_entity("Center".to_string());
-    if let Some(entity) = scene.get_entity_mut(center) {
-        entity.add_component(Transform::from_position(Vec3::ZERO));
-    }
+    scene.spawn_named("Center", Transform::from_position(Vec3::ZERO));
 
     // Ring of cubes
     for i in 0..8 {
@@ -34,10 +32,7 @@ fn main() {
         let radius = 5.0;
         let pos = Vec3::new(angle.cos() * radius, 0.0, angle.sin() * radius);
         
-        let cube = scene.create_entity(format!("Cube_{}", i));
-        if let Some(entity) = scene.get_entity_mut(cube) {
-            entity.add_component(Transform::from_position(pos));
-        }
+        scene.spawn_named(&format!("Cube_{}", i), Transform::from_position(pos));
     }
 
     println!("Scene created with {} entities", scene.entity_count());
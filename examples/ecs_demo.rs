@@ -36,22 +36,26 @@ fn main() {
         let scene = engine.scene_mut();
 
         // Create player entity
-        let player = scene.create_entity("Player".to_string());
-        if let Some(entity) = scene.get_entity_mut(player) {
-            entity.add_component(Transform::from_position(Vec3::ZERO));
-            entity.add_component(Velocity { x: 0.0, y: 0.0, z: 0.0 });
-            entity.add_component(Health { current: 100.0, max: 100.0 });
-        }
+        scene.spawn_named(
+            "Player",
+            (
+                Transform::from_position(Vec3::ZERO),
+                Velocity { x: 0.0, y: 0.0, z: 0.0 },
+                Health { current: 100.0, max: 100.0 },
+            ),
+        );
 
         // Create some enemies
         for i in 0..5 {
-            let enemy = scene.create_entity(format!("Enemy_{}", i));
-            if let Some(entity) = scene.get_entity_mut(enemy) {
-                let pos = Vec3::new(i as f32 * 2.0, 0.0, -5.0);
-                entity.add_component(Transform::from_position(pos));
-                entity.add_component(Velocity { x: 0.0, y: 0.0, z: 1.0 });
-                entity.add_component(Health { current: 50.0, max: 50.0 });
-            }
+            let pos = Vec3::new(i as f32 * 2.0, 0.0, -5.0);
+            scene.spawn_named(
+                &format!("Enemy_{}", i),
+                (
+                    Transform::from_position(pos),
+                    Velocity { x: 0.0, y: 0.0, z: 1.0 },
+                    Health { current: 50.0, max: 50.0 },
+                ),
+            );
         }
 
         println!("Created {} entities", scene.entity_count());
@@ -60,15 +64,10 @@ fn main() {
     // Run game loop
     engine.run(|scene, input, delta| {
         // Update all entities with velocity
-        for entity in scene.active_entities_mut() {
-            if let (Some(transform), Some(velocity)) = (
-                entity.get_component_mut::<Transform>(),
-                entity.get_component::<Velocity>(),
-            ) {
-                transform.position.x += velocity.x * delta;
-                transform.position.y += velocity.y * delta;
-                transform.position.z += velocity.z * delta;
-            }
+        for (transform, velocity) in scene.query_mut::<(&mut Transform, &Velocity)>() {
+            transform.position.x += velocity.x * delta;
+            transform.position.y += velocity.y * delta;
+            transform.position.z += velocity.z * delta;
         }
 
         // Find and update entities with health
@@ -87,14 +86,16 @@ fn main() {
             scene.remove_entity(id);
         }
 
-        // Press Space to damage a random entity
+        // Press Space to damage (and slow down) the first entity with health
         if input.key_just_pressed(Key::Space) {
-            for entity in scene.active_entities_mut() {
-                if let Some(health) = entity.get_component_mut::<Health>() {
+            let target = scene.query::<(EntityId, &Health)>().map(|(id, _)| id).next();
+            if let Some(mut entity) = target.and_then(|id| scene.get_entity_mut(id)) {
+                let name = entity.name().to_string();
+                if let Some((health, velocity)) = entity.get_components_mut::<(&mut Health, &mut Velocity)>() {
                     health.current -= 25.0;
-                    println!("{} took damage! Health: {}/{}", 
-                        entity.name(), health.current, health.max);
-                    break;
+                    velocity.z *= 0.5;
+                    println!("{} took damage! Health: {}/{}",
+                        name, health.current, health.max);
                 }
             }
         }
@@ -20,23 +20,15 @@ fn main() {
     // Create engine
     let mut engine = Engine::new(config);
 
+    // Create cube mesh (uploaded to the GPU once the renderer exists)
+    let cube_mesh = MeshBuilder::cube(2.0);
+    let cube_handle = engine
+        .resource_manager_mut()
+        .insert_mesh("cube".to_string(), cube_mesh);
+
     // Create a scene and add a spinning cube entity
     let scene = engine.scene_mut();
-    let cube_entity = scene.create_entity("SpinningCube".to_string());
-    
-    if let Some(entity) = scene.get_entity_mut(cube_entity) {
-        let mut transform = Transform::new();
-        transform.position = Vec3::new(0.0, 0.0, 0.0);
-        entity.add_component(transform);
-    }
-
-    // Create cube mesh
-    let cube_mesh = MeshBuilder::cube(2.0);
-    let cube_handle = engine.resource_manager_mut().add_mesh(
-        "cube".to_string(),
-        cube_mesh,
-        engine.renderer().unwrap().device(),
-    );
+    let cube_entity = scene.spawn_named("SpinningCube", (Transform::new(), MeshRenderer::new(cube_handle)));
 
     let mut rotation = 0.0f32;
 
@@ -46,7 +38,7 @@ fn main() {
         rotation += delta * 1.0; // 1 radian per second
 
         // Update cube transform
-        if let Some(entity) = scene.get_entity_mut(cube_entity) {
+        if let Some(mut entity) = scene.get_entity_mut(cube_entity) {
             if let Some(transform) = entity.get_component_mut::<Transform>() {
                 transform.rotation = Quat::from_rotation_y(rotation) * Quat::from_rotation_x(rotation * 0.5);
             }
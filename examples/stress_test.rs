@@ -29,30 +29,31 @@ fn main() {
     let scene = engine.scene_mut();
 
     for i in 0..num_entities {
-        let entity_id = scene.create_entity(format!("Particle_{}", i));
-        
-        if let Some(entity) = scene.get_entity_mut(entity_id) {
-            let angle = (i as f32 / num_entities as f32) * std::f32::consts::PI * 2.0;
-            let radius = (i as f32 / num_entities as f32) * 10.0;
-            
-            let pos = Vec3::new(
-                angle.cos() * radius,
-                (i % 10) as f32,
-                angle.sin() * radius,
-            );
-
-            let vel = Vec3::new(
-                (angle + std::f32::consts::PI / 2.0).cos() * 2.0,
-                0.0,
-                (angle + std::f32::consts::PI / 2.0).sin() * 2.0,
-            );
-
-            entity.add_component(Transform::from_position(pos));
-            entity.add_component(Particle {
-                velocity: vel,
-                lifetime: 10.0 + (i % 20) as f32,
-            });
-        }
+        let angle = (i as f32 / num_entities as f32) * std::f32::consts::PI * 2.0;
+        let radius = (i as f32 / num_entities as f32) * 10.0;
+
+        let pos = Vec3::new(
+            angle.cos() * radius,
+            (i % 10) as f32,
+            angle.sin() * radius,
+        );
+
+        let vel = Vec3::new(
+            (angle + std::f32::consts::PI / 2.0).cos() * 2.0,
+            0.0,
+            (angle + std::f32::consts::PI / 2.0).sin() * 2.0,
+        );
+
+        scene.spawn_named(
+            &format!("Particle_{}", i),
+            (
+                Transform::from_position(pos),
+                Particle {
+                    velocity: vel,
+                    lifetime: 10.0 + (i % 20) as f32,
+                },
+            ),
+        );
     }
 
     println!("Created {} entities", scene.entity_count());
@@ -64,33 +65,27 @@ fn main() {
     engine.run(move |scene, input, delta| {
         frame_count += 1;
 
-        // Update all particles
-        let mut expired_entities = Vec::new();
-
-        for entity in scene.active_entities_mut() {
-            if let Some(particle) = entity.get_component_mut::<Particle>() {
+        // Update all particles across cores
+        scene
+            .query_mut::<(&mut Particle, &mut Transform)>()
+            .par_for_each(|(particle, transform)| {
                 particle.lifetime -= delta;
+                transform.position += particle.velocity * delta;
 
-                if particle.lifetime <= 0.0 {
-                    expired_entities.push(entity.id());
-                    continue;
+                // Bounce off boundaries
+                if transform.position.length() > 15.0 {
+                    particle.velocity *= -0.9;
                 }
+            });
 
-                if let Some(transform) = entity.get_component_mut::<Transform>() {
-                    transform.position += particle.velocity * delta;
-
-                    // Bounce off boundaries
-                    if transform.position.length() > 15.0 {
-                        particle.velocity *= -0.9;
-                    }
-                }
+        // Remove expired particles once the query is done
+        let commands = Commands::new();
+        for (id, particle) in scene.query::<(EntityId, &Particle)>() {
+            if particle.lifetime <= 0.0 {
+                commands.despawn(id);
             }
         }
-
-        // Remove expired particles
-        for id in expired_entities {
-            scene.remove_entity(id);
-        }
+        commands.apply(scene);
 
         // Track FPS statistics
         let fps = 1.0 / delta;
@@ -0,0 +1,109 @@
+//! Derive macros for rgame
+//!
+//! Re-exported by `rgame` (and its prelude); depend on `rgame` rather than this crate.
+//!
+//! - `#[derive(Component)]` implements `Component`. `#[component(name = "health")]`
+//!   also implements `NamedComponent`, so `registry.register_named::<T>()` registers
+//!   the type for scene files and prefabs under that name. `#[component(storage =
+//!   "sparse")]` stores the type in a sparse set instead of archetype tables.
+//! - `#[derive(Bundle)]` implements `Bundle` for a struct of components, spawned with
+//!   `scene.spawn(bundle)`. Fields marked `#[bundle]` are nested bundles.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, LitStr};
+
+/// Implement `Component`, and `NamedComponent` if `#[component(name = "...")]` is given
+///
+/// `#[component(storage = "table" | "sparse")]` picks the storage (tables by default).
+#[proc_macro_derive(Component, attributes(component))]
+pub fn derive_component(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut name: Option<LitStr> = None;
+    let mut storage = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("component")) {
+        let result = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("storage") {
+                let value: LitStr = meta.value()?.parse()?;
+                storage = Some(match value.value().as_str() {
+                    "table" => quote!(::rgame::ecs::StorageType::Table),
+                    "sparse" => quote!(::rgame::ecs::StorageType::Sparse),
+                    _ => return Err(syn::Error::new_spanned(value, "expected \"table\" or \"sparse\"")),
+                });
+                Ok(())
+            } else {
+                Err(meta.error("expected `name = \"...\"` or `storage = \"...\"`"))
+            }
+        });
+        if let Err(e) = result {
+            return e.to_compile_error().into();
+        }
+    }
+
+    let named = name.map(|name| {
+        quote! {
+            impl #impl_generics ::rgame::registry::NamedComponent for #ident #type_generics #where_clause {
+                const NAME: &'static str = #name;
+            }
+        }
+    });
+
+    let storage = storage.map(|storage| quote!(const STORAGE: ::rgame::ecs::StorageType = #storage;));
+
+    quote! {
+        impl #impl_generics ::rgame::ecs::Component for #ident #type_generics #where_clause {
+            #storage
+        }
+        #named
+    }
+    .into()
+}
+
+/// Implement `Bundle` for a struct whose fields are components or `#[bundle]` bundles
+#[proc_macro_derive(Bundle, attributes(bundle))]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let Data::Struct(data) = &input.data else {
+        return syn::Error::new_spanned(ident, "Bundle can only be derived for structs")
+            .to_compile_error()
+            .into();
+    };
+
+    let inserts = data.fields.iter().enumerate().map(|(i, field)| {
+        let member = match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = syn::Index::from(i);
+                quote!(#index)
+            }
+        };
+        if field.attrs.iter().any(|a| a.path().is_ident("bundle")) {
+            quote!(::rgame::bundle::Bundle::insert_into(self.#member, entity);)
+        } else {
+            quote!(entity.add_component(self.#member);)
+        }
+    });
+    let entity = if data.fields.is_empty() {
+        quote!(_entity)
+    } else {
+        quote!(entity)
+    };
+
+    quote! {
+        impl #impl_generics ::rgame::bundle::Bundle for #ident #type_generics #where_clause {
+            fn insert_into(self, #entity: &mut ::rgame::ecs::EntityMut) {
+                #(#inserts)*
+            }
+        }
+    }
+    .into()
+}
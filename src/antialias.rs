@@ -0,0 +1,351 @@
+//! Post-process anti-aliasing
+//!
+//! MSAA only smooths geometry edges. These passes run on the resolved HDR scene color
+//! after the post-processing stack and before tone mapping. FXAA blurs along edges
+//! found in the image in a single pass. TAA jitters the projection by a sub-pixel
+//! offset each frame and blends the result into an accumulated history, clamped to the
+//! current frame's neighborhood to limit ghosting. Pick one with
+//! `RendererConfig::aa_mode` or `Renderer::set_anti_aliasing`.
+
+use crate::postprocess::HDR_FORMAT;
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use wgpu::util::DeviceExt;
+
+/// Number of jitter offsets TAA cycles through
+const JITTER_SAMPLES: u32 = 8;
+
+/// Share of the current frame in the TAA blend once a history exists
+const CURRENT_WEIGHT: f32 = 0.1;
+
+/// Post-process anti-aliasing mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AntiAliasing {
+    /// MSAA only
+    #[default]
+    None,
+    /// Fast approximate anti-aliasing: cheap, slightly softens the image
+    Fxaa,
+    /// Temporal anti-aliasing: smooths edges and shading, may blur fast motion
+    Taa,
+}
+
+/// Anti-aliasing uniform buffer data
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct AntiAliasUniform {
+    texel_size: [f32; 2],
+    current_weight: f32,
+    _padding: f32,
+}
+
+/// Element of the Halton low-discrepancy sequence (`index` starts at 1)
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Sub-pixel offset of a TAA frame, in pixels within [-0.5, 0.5)
+pub(crate) fn jitter_offset(frame: u32) -> Vec2 {
+    let index = frame % JITTER_SAMPLES + 1;
+    Vec2::new(halton(index, 2), halton(index, 3)) - 0.5
+}
+
+/// Clip-space translation shifting a view-projection by a pixel offset in a viewport
+pub(crate) fn jitter_matrix(offset: Vec2, width: u32, height: u32) -> Mat4 {
+    Mat4::from_translation(Vec3::new(
+        2.0 * offset.x / width.max(1) as f32,
+        -2.0 * offset.y / height.max(1) as f32,
+        0.0,
+    ))
+}
+
+/// Targets sized to the surface
+struct AntiAliasTargets {
+    output_texture: wgpu::Texture,
+    output: wgpu::TextureView,
+    history_texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    size: (u32, u32),
+}
+
+/// GPU pipelines and targets for the anti-aliasing pass
+pub(crate) struct AntiAliasPipeline {
+    fxaa: wgpu::RenderPipeline,
+    taa: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    buffer: wgpu::Buffer,
+    targets: AntiAliasTargets,
+    /// The history holds a previous TAA frame
+    history_valid: bool,
+    frame: u32,
+}
+
+impl AntiAliasPipeline {
+    pub(crate) fn new(device: &wgpu::Device, source: &wgpu::TextureView, width: u32, height: u32) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Anti-Alias Buffer"),
+            contents: bytemuck::cast_slice(&[AntiAliasUniform::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("anti_alias_bind_group_layout"),
+            entries: &[
+                texture_entry(0),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Anti-Alias Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Anti-Alias Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/antialias.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Anti-Alias Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label: &str, entry_point: &str| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: HDR_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let fxaa = create_pipeline("FXAA Pipeline", "fs_fxaa");
+        let taa = create_pipeline("TAA Pipeline", "fs_taa");
+
+        let targets = Self::create_targets(device, &layout, &sampler, &buffer, source, width, height);
+        Self {
+            fxaa,
+            taa,
+            layout,
+            sampler,
+            buffer,
+            targets,
+            history_valid: false,
+            frame: 0,
+        }
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        buffer: &wgpu::Buffer,
+        source: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+    ) -> AntiAliasTargets {
+        let size = (width.max(1), height.max(1));
+        let create_texture = |label, usage| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: HDR_FORMAT,
+                usage: usage | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+        let output_texture = create_texture(
+            "Anti-Alias Output Texture",
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        );
+        let history_texture = create_texture("TAA History Texture", wgpu::TextureUsages::COPY_DST);
+        let output = output_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let history = history_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("anti_alias_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&history),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        });
+        AntiAliasTargets {
+            output_texture,
+            output,
+            history_texture,
+            bind_group,
+            size,
+        }
+    }
+
+    /// Recreate the targets for a new HDR target, dropping the TAA history
+    pub(crate) fn resize(&mut self, device: &wgpu::Device, source: &wgpu::TextureView, width: u32, height: u32) {
+        self.targets = Self::create_targets(device, &self.layout, &self.sampler, &self.buffer, source, width, height);
+        self.history_valid = false;
+    }
+
+    /// Forget the TAA history (after a camera cut or switching modes)
+    pub(crate) fn reset_history(&mut self) {
+        self.history_valid = false;
+    }
+
+    /// Anti-aliased HDR color, read by tone mapping
+    pub(crate) fn output(&self) -> &wgpu::TextureView {
+        &self.targets.output
+    }
+
+    /// Sub-pixel projection offset for the next TAA frame
+    pub(crate) fn jitter(&self) -> Vec2 {
+        jitter_offset(self.frame)
+    }
+
+    /// Record the anti-aliasing pass for `mode` into the output target
+    pub(crate) fn draw(&mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, mode: AntiAliasing) {
+        let temporal = mode == AntiAliasing::Taa;
+        let (width, height) = self.targets.size;
+        let uniform = AntiAliasUniform {
+            texel_size: [1.0 / width as f32, 1.0 / height as f32],
+            current_weight: if temporal && self.history_valid { CURRENT_WEIGHT } else { 1.0 },
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(if temporal { "TAA Pass" } else { "FXAA Pass" }),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.targets.output,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(if temporal { &self.taa } else { &self.fxaa });
+            pass.set_bind_group(0, &self.targets.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        if temporal {
+            encoder.copy_texture_to_texture(
+                self.targets.output_texture.as_image_copy(),
+                self.targets.history_texture.as_image_copy(),
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+            self.frame = self.frame.wrapping_add(1);
+        }
+        self.history_valid = temporal;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_sequence() {
+        // Matches AntiAliasUniform in antialias.wgsl
+        assert_eq!(std::mem::size_of::<AntiAliasUniform>(), 16);
+        assert_eq!(halton(1, 2), 0.5);
+        assert!((halton(3, 3) - 1.0 / 9.0).abs() < 1e-7);
+
+        let offsets: Vec<Vec2> = (0..JITTER_SAMPLES).map(jitter_offset).collect();
+        assert!(offsets.iter().all(|o| o.abs().max_element() <= 0.5));
+        assert_eq!(jitter_offset(JITTER_SAMPLES), offsets[0]);
+        for (i, a) in offsets.iter().enumerate() {
+            assert!(offsets[i + 1..].iter().all(|b| a != b));
+        }
+
+        // Half a pixel on an 800 pixel wide viewport is 1/800 in NDC
+        let shift = jitter_matrix(Vec2::new(0.5, 0.0), 800, 600).transform_point3(Vec3::ZERO);
+        assert!((shift.x - 1.0 / 800.0).abs() < 1e-7);
+    }
+}
@@ -1,13 +1,72 @@
 //! Audio playback using rodio
 //!
 //! Provides simple audio playback for music and sound effects.
+//! Sources are loaded from files or generated: [`AudioSource::sine`],
+//! [`AudioSource::square`], and [`AudioSource::white_noise`] need no assets.
+//!
+//! Sound effects played with [`AudioManager::play_sfx_at`] are positioned in the world:
+//! they're panned between the ears of a listener (the engine moves it with the camera
+//! every frame) and fade with distance along a configurable [`Rolloff`] curve.
+//!
+//! Playing a sound effect returns a [`SoundHandle`] for stopping, pausing, or turning
+//! it down while it plays; the manager forgets sounds once they finish. Only
+//! [`AudioManager::max_sounds`] play at once: a new sound replaces the lowest-priority
+//! one (see [`PlaybackParams::priority`]).
+//!
+//! The `_with` variants take [`PlaybackParams`] to change a sound's volume, pitch, and
+//! pan; a random pitch variation keeps repeated footsteps or gunshots from sounding
+//! identical.
+//!
+//! Music and sound effects play through separate [`EffectChain`]s for filters, reverb,
+//! and echo; see [`crate::audio_effects`].
+//!
+//! [`AudioManager::music_position`] tracks where the music is, and beat or marker
+//! callbacks keep gameplay in time with it; see [`crate::audio_sync`].
+//!
+//! Output goes to the system's default device, or one picked with
+//! [`AudioManager::set_output_device`]. When the device disappears or the default
+//! changes, [`AudioManager::update`] moves playback over without restarting sounds.
 
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use crate::audio_effects::{EffectChain, EffectSource};
+use crate::audio_emitter::EmitterSound;
+use crate::audio_sync::{MusicClock, MusicSource, MusicSync};
+use crate::renderer::Camera;
+use crate::ecs::EntityId;
+use crate::utils::Random;
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use crate::audio_output::{self, Mix, Output, MIX_CHANNELS, MIX_SAMPLE_RATE};
+use rodio::dynamic_mixer::DynamicMixerController;
+use rodio::source::{ChannelVolume, Spatial};
+use rodio::{Decoder, Sink, Source};
 use std::io::BufReader;
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::path::Path;
-use std::sync::Arc;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default number of sound effects that can play at once
+const DEFAULT_MAX_SOUNDS: usize = 32;
+
+/// Time between checks for a lost or changed output device
+const DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sample rate of generated tones and noise
+const GENERATED_SAMPLE_RATE: u32 = 44100;
+/// Peak level of generated sounds, leaving headroom for mixing
+const GENERATED_AMPLITUDE: f32 = 0.5;
+/// Fade at both ends of generated sounds, so they start and stop without a click
+const GENERATED_FADE: f32 = 0.005;
+
+/// Decoder reading an [`AudioSource`]
+pub(crate) type AudioDecoder = Decoder<BufReader<std::io::Cursor<Vec<u8>>>>;
 
 /// Audio source that can be played
+///
+/// Clones share the encoded data.
+#[derive(Clone)]
 pub struct AudioSource {
     data: Arc<Vec<u8>>,
 }
@@ -17,7 +76,7 @@ impl AudioSource {
     ///
     /// Supports: WAV, MP3, OGG, FLAC
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
-        let data = std::fs::read(path.as_ref())
+        let data = crate::platform::read_asset(path.as_ref())
             .map_err(|e| format!("Failed to read audio file: {}", e))?;
         
         log::info!("Loaded audio file: {:?}", path.as_ref());
@@ -26,81 +85,751 @@ impl AudioSource {
         })
     }
 
+    /// Wrap encoded audio already in memory, e.g. from `include_bytes!`
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self { data: Arc::new(data) }
+    }
+
+    /// Wrap mono 16-bit samples as a sound (stored as an in-memory WAV file)
+    pub fn from_samples(sample_rate: u32, samples: &[i16]) -> Self {
+        let data_len = samples.len() as u32 * 2;
+        let mut wav = Vec::with_capacity(44 + data_len as usize);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        Self::from_bytes(wav)
+    }
+
+    /// Generate a sine tone, e.g. for UI blips
+    pub fn sine(frequency: f32, duration: f32) -> Self {
+        Self::generate(duration, |t| (t * frequency * std::f32::consts::TAU).sin())
+    }
+
+    /// Generate a square wave, the classic retro game sound
+    pub fn square(frequency: f32, duration: f32) -> Self {
+        Self::generate(duration, |t| if (t * frequency).fract() < 0.5 { 1.0 } else { -1.0 })
+    }
+
+    /// Generate white noise, for explosions, hits, and static
+    pub fn white_noise(duration: f32) -> Self {
+        let mut rng = Random::new(0x5eed);
+        Self::generate(duration, move |_| rng.gen_range_f32(-1.0, 1.0))
+    }
+
+    /// Sample `wave` (given the time in seconds, returning -1 to 1) for `duration` seconds
+    fn generate(duration: f32, mut wave: impl FnMut(f32) -> f32) -> Self {
+        let rate = GENERATED_SAMPLE_RATE as f32;
+        let count = (duration.max(0.0) * rate) as usize;
+        let fade = (GENERATED_FADE * rate).max(1.0);
+        let samples: Vec<i16> = (0..count)
+            .map(|i| {
+                let envelope = (i as f32 / fade).min((count - i) as f32 / fade).min(1.0);
+                let value = wave(i as f32 / rate) * envelope * GENERATED_AMPLITUDE;
+                (value * i16::MAX as f32) as i16
+            })
+            .collect();
+        Self::from_samples(GENERATED_SAMPLE_RATE, &samples)
+    }
+
+    /// Size of the encoded data in bytes
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Check if the source holds no data
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
     /// Create a decoder for this audio source
-    fn decoder(&self) -> Result<Decoder<BufReader<std::io::Cursor<Vec<u8>>>>, String> {
+    pub(crate) fn decoder(&self) -> Result<AudioDecoder, String> {
         let cursor = std::io::Cursor::new(self.data.as_ref().clone());
         let buf_reader = BufReader::new(cursor);
         Decoder::new(buf_reader).map_err(|e| format!("Failed to decode audio: {}", e))
     }
 }
 
+/// Volume, pitch, and pan of one playing sound
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackParams {
+    /// Multiplier on top of the master and music/SFX volumes
+    pub volume: f32,
+    /// Playback speed; 2.0 plays an octave higher and twice as fast
+    pub pitch: f32,
+    /// Each play picks a random pitch within `pitch ± pitch_variation`
+    pub pitch_variation: f32,
+    /// Stereo position from -1.0 (left) to 1.0 (right); positional sounds ignore it
+    pub pan: f32,
+    /// Repeat a sound effect until it's stopped (music takes its own flag)
+    pub looping: bool,
+    /// Sound effects of higher priority are kept when too many play at once
+    pub priority: i32,
+}
+
+impl Default for PlaybackParams {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pitch: 1.0,
+            pitch_variation: 0.0,
+            pan: 0.0,
+            looping: false,
+            priority: 0,
+        }
+    }
+}
+
+impl PlaybackParams {
+    /// Set the volume multiplier
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume.max(0.0);
+        self
+    }
+
+    /// Set the pitch (playback speed)
+    pub fn with_pitch(mut self, pitch: f32) -> Self {
+        self.pitch = pitch;
+        self
+    }
+
+    /// Set the priority used to pick which sounds to cut when too many play
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Repeat a sound effect until it's stopped
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Randomize the pitch by up to `variation` either way on every play
+    pub fn with_pitch_variation(mut self, variation: f32) -> Self {
+        self.pitch_variation = variation.abs();
+        self
+    }
+
+    /// Set the stereo pan (-1.0 to 1.0)
+    pub fn with_pan(mut self, pan: f32) -> Self {
+        self.pan = pan.clamp(-1.0, 1.0);
+        self
+    }
+
+    /// Pick the pitch for one play
+    fn roll_pitch(&self, rng: &mut Random) -> f32 {
+        let pitch = if self.pitch_variation > 0.0 {
+            self.pitch + rng.gen_range_f32(-self.pitch_variation, self.pitch_variation)
+        } else {
+            self.pitch
+        };
+        pitch.max(0.01)
+    }
+
+    /// Left and right channel volumes for the pan
+    fn channel_volumes(&self) -> [f32; 2] {
+        let pan = self.pan.clamp(-1.0, 1.0);
+        [(1.0 - pan).min(1.0), (1.0 + pan).min(1.0)]
+    }
+}
+
+/// Decoded samples ready for a sink
+type BoxedSource = Box<dyn Source<Item = i16> + Send>;
+
+/// Decode a source, optionally repeating it, and run it through an effect chain
+fn effect_source(source: &AudioSource, looping: bool, effects: &EffectChain) -> Result<BoxedSource, String> {
+    let decoder = source.decoder()?;
+    Ok(if looping {
+        Box::new(EffectSource::new(decoder.repeat_infinite(), effects.clone()))
+    } else {
+        Box::new(EffectSource::new(decoder, effects.clone()))
+    })
+}
+
+/// Apply a pan to a source (mixing it down to mono first, as panning a stereo
+/// recording would otherwise drop one side)
+fn apply_pan(source: BoxedSource, params: &PlaybackParams) -> BoxedSource {
+    if params.pan == 0.0 {
+        return source;
+    }
+    Box::new(ChannelVolume::new(source, params.channel_volumes().to_vec()))
+}
+
+/// How a positional sound fades with distance from the listener
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rolloff {
+    /// Fades linearly from full volume at `min_distance` to silence at `max_distance`
+    Linear,
+    /// `min_distance / distance`, like sound in open air
+    Inverse,
+    /// `(min_distance / distance)²`, fading faster than `Inverse`
+    InverseSquare,
+}
+
+/// Distance attenuation of a positional sound
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpatialSettings {
+    pub rolloff: Rolloff,
+    /// Distance up to which the sound plays at full volume
+    pub min_distance: f32,
+    /// Distance beyond which the sound is silent
+    pub max_distance: f32,
+}
+
+impl Default for SpatialSettings {
+    fn default() -> Self {
+        Self {
+            rolloff: Rolloff::Inverse,
+            min_distance: 1.0,
+            max_distance: 50.0,
+        }
+    }
+}
+
+impl SpatialSettings {
+    /// Volume factor (0.0 to 1.0) at a distance from the listener
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        if distance >= self.max_distance {
+            return 0.0;
+        }
+        if distance <= self.min_distance {
+            return 1.0;
+        }
+        match self.rolloff {
+            Rolloff::Linear => 1.0 - (distance - self.min_distance) / (self.max_distance - self.min_distance),
+            Rolloff::Inverse => self.min_distance / distance,
+            Rolloff::InverseSquare => (self.min_distance / distance).powi(2),
+        }
+    }
+}
+
+/// Position and orientation sounds are heard from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Listener {
+    pub position: Vec3,
+    /// Unit vector pointing out of the listener's right ear
+    pub right: Vec3,
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            right: Vec3::X,
+        }
+    }
+}
+
+/// Half the distance between the ears, in the listener space given to rodio
+const EAR_OFFSET: f32 = 0.1;
+/// Emitters are placed this far from the head, so that no ear is more than one unit
+/// away and rodio's own distance falloff stays out of the way of the rolloff curves
+const PAN_RADIUS: f32 = 1.0 - EAR_OFFSET;
+
+/// Emitter position in listener space that pans a sound like one at `position`
+fn pan_position(listener: &Listener, position: Vec3) -> [f32; 3] {
+    let offset = position - listener.position;
+    let distance = offset.length();
+    let side = if distance > f32::EPSILON {
+        (offset.dot(listener.right) / distance).clamp(-1.0, 1.0)
+    } else {
+        0.0
+    };
+    [side * PAN_RADIUS, 0.0, -(1.0 - side * side).sqrt() * PAN_RADIUS]
+}
+
+/// Sink of a sound effect
+struct SoundSink {
+    sink: Sink,
+    /// Emitter position in listener space of a positional sound, read by the audio thread
+    emitter: Option<Arc<Mutex<[f32; 3]>>>,
+}
+
+impl SoundSink {
+    fn set_volume(&self, volume: f32) {
+        self.sink.set_volume(volume);
+    }
+
+    fn set_emitter_position(&self, position: [f32; 3]) {
+        if let Some(emitter) = &self.emitter {
+            *emitter.lock().unwrap_or_else(|e| e.into_inner()) = position;
+        }
+    }
+
+    fn play(&self) {
+        self.sink.play();
+    }
+
+    fn pause(&self) {
+        self.sink.pause();
+    }
+
+    fn is_paused(&self) -> bool {
+        self.sink.is_paused()
+    }
+
+    fn stop(&self) {
+        self.sink.stop();
+    }
+
+    fn empty(&self) -> bool {
+        self.sink.empty()
+    }
+}
+
+/// Pan a source between the ears from an emitter position that can change while it plays
+fn spatialize(source: BoxedSource, emitter: Arc<Mutex<[f32; 3]>>) -> impl Source<Item = i16> + Send {
+    // rodio 0.18 turns a channel up as its ear gets farther from the emitter, so
+    // the ears are passed mirrored
+    let (left_ear, right_ear) = ([EAR_OFFSET, 0.0, 0.0], [-EAR_OFFSET, 0.0, 0.0]);
+    let start = *emitter.lock().unwrap_or_else(|e| e.into_inner());
+    Spatial::new(source, start, left_ear, right_ear).periodic_access(Duration::from_millis(10), move |spatial| {
+        let position = *emitter.lock().unwrap_or_else(|e| e.into_inner());
+        spatial.set_positions(position, left_ear, right_ear);
+    })
+}
+
+/// State shared between a playing sound effect and its handles
+struct SoundState {
+    /// `None` for sounds skipped while audio was suspended
+    sink: Option<SoundSink>,
+    /// Volume set through the handle
+    volume: Cell<f32>,
+    /// Master, SFX, and distance factor set by the manager
+    gain: Cell<f32>,
+    /// World position and attenuation of positional sounds
+    spatial: Cell<Option<(Vec3, SpatialSettings)>>,
+    priority: i32,
+    /// Order the sound started in, to cut the oldest first
+    started: u64,
+}
+
+impl SoundState {
+    fn apply_volume(&self) {
+        if let Some(sink) = &self.sink {
+            sink.set_volume(self.volume.get() * self.gain.get());
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.sink.as_ref().is_none_or(|sink| sink.empty())
+    }
+
+    fn loudness(&self) -> f32 {
+        self.volume.get() * self.gain.get()
+    }
+}
+
+/// Sound to cut for a new one of `priority`: the lowest priority, then the quietest,
+/// then the oldest (`None` if every sound outranks the new one)
+fn pick_voice_to_steal(sounds: &[Rc<SoundState>], priority: i32) -> Option<usize> {
+    sounds
+        .iter()
+        .enumerate()
+        .filter(|(_, sound)| sound.priority <= priority)
+        .min_by(|(_, a), (_, b)| {
+            a.priority
+                .cmp(&b.priority)
+                .then(a.loudness().total_cmp(&b.loudness()))
+                .then(a.started.cmp(&b.started))
+        })
+        .map(|(index, _)| index)
+}
+
+/// Control over a playing sound effect
+///
+/// Dropping the handle doesn't stop the sound.
+#[derive(Clone)]
+pub struct SoundHandle(Rc<SoundState>);
+
+impl SoundHandle {
+    /// Handle of a sound that never played
+    pub(crate) fn finished() -> Self {
+        Self(Rc::new(SoundState {
+            sink: None,
+            volume: Cell::new(0.0),
+            gain: Cell::new(0.0),
+            spatial: Cell::new(None),
+            priority: 0,
+            started: 0,
+        }))
+    }
+
+    /// Stop the sound for good
+    pub fn stop(&self) {
+        if let Some(sink) = &self.0.sink {
+            sink.stop();
+        }
+    }
+
+    /// Pause the sound
+    pub fn pause(&self) {
+        if let Some(sink) = &self.0.sink {
+            sink.pause();
+        }
+    }
+
+    /// Continue a paused sound
+    pub fn resume(&self) {
+        if let Some(sink) = &self.0.sink {
+            sink.play();
+        }
+    }
+
+    /// Check if the sound is paused
+    pub fn is_paused(&self) -> bool {
+        self.0.sink.as_ref().is_some_and(|sink| sink.is_paused())
+    }
+
+    /// Set the sound's own volume, on top of the master and SFX volumes
+    pub fn set_volume(&self, volume: f32) {
+        self.0.volume.set(volume.max(0.0));
+        self.0.apply_volume();
+    }
+
+    /// Get the sound's own volume
+    pub fn volume(&self) -> f32 {
+        self.0.volume.get()
+    }
+
+    /// Check if the sound has played to the end or was stopped
+    pub fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+
+    /// Move a positional sound; it's re-panned when the listener is next updated
+    /// (every frame under the engine)
+    pub fn set_position(&self, position: Vec3) {
+        if let Some((_, settings)) = self.0.spatial.get() {
+            self.0.spatial.set(Some((position, settings)));
+        }
+    }
+
+    /// World position of a positional sound
+    pub fn position(&self) -> Option<Vec3> {
+        self.0.spatial.get().map(|(position, _)| position)
+    }
+
+    /// Move a positional sound and change its distance attenuation
+    pub(crate) fn set_spatial(&self, position: Vec3, settings: SpatialSettings) {
+        if self.0.spatial.get().is_some() {
+            self.0.spatial.set(Some((position, settings)));
+        }
+    }
+}
+
+/// What happens to audio while the window is unfocused or minimized
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum BackgroundAudio {
+    /// Keep playing as usual
+    #[default]
+    Play,
+    /// Pause music and sound effects until the window is back
+    Pause,
+    /// Turn everything down to this fraction of its volume
+    Duck(f32),
+}
+
 /// Manages audio playback
 pub struct AudioManager {
-    _stream: OutputStream,
-    stream_handle: OutputStreamHandle,
+    /// Adds sinks to the mix
+    mixer: Arc<DynamicMixerController<f32>>,
+    mix: Mix,
+    /// Device stream playing the mix (`None` while no device is available)
+    output: Option<Output>,
+    /// Device picked with `set_output_device` (`None` follows the system default)
+    preferred_device: Option<String>,
+    last_device_check: Instant,
+    /// Chunks the output had pulled at the last check, to notice a dead stream
+    last_pulls: usize,
     music_sink: Option<Sink>,
-    sfx_sinks: Vec<Sink>,
+    music_params: PlaybackParams,
+    /// Progress of the playing music
+    pub(crate) music_clock: Option<MusicClock>,
+    /// Beat and marker callbacks
+    pub(crate) music_sync: MusicSync,
+    sounds: Vec<Rc<SoundState>>,
+    max_sounds: usize,
+    /// Counts started sound effects
+    sounds_started: u64,
+    /// Sounds started by `AudioEmitter` entities
+    pub(crate) emitters: HashMap<EntityId, EmitterSound>,
+    listener: Listener,
+    spatial_settings: SpatialSettings,
     master_volume: f32,
     music_volume: f32,
     sfx_volume: f32,
+    background_audio: BackgroundAudio,
+    window_focused: bool,
+    window_visible: bool,
+    /// Volume factor while ducked in the background (1 otherwise)
+    duck: f32,
+    /// Whether playback is paused because the window is in the background
+    background_paused: bool,
+    /// Set while the app is in the background; `Some(true)` if music was playing
+    suspended: Option<bool>,
+    /// Sound effects paused by `suspend`, resumed by `resume`
+    suspended_sounds: Vec<Rc<SoundState>>,
+    /// Picks pitch variations
+    rng: Random,
+    music_effects: EffectChain,
+    sfx_effects: EffectChain,
 }
 
 impl AudioManager {
     /// Create a new audio manager
     pub fn new() -> Result<Self, String> {
-        let (stream, stream_handle) = OutputStream::try_default()
-            .map_err(|e| format!("Failed to create audio output stream: {}", e))?;
+        let (mixer, mix) = rodio::dynamic_mixer::mixer(MIX_CHANNELS, MIX_SAMPLE_RATE);
+        let mix = Arc::new(Mutex::new(mix));
+        let output = Output::open(None, &mix)?;
 
         log::info!("Audio system initialized");
 
         Ok(Self {
-            _stream: stream,
-            stream_handle,
+            mixer,
+            mix,
+            output: Some(output),
+            preferred_device: None,
+            last_device_check: Instant::now(),
+            last_pulls: 0,
             music_sink: None,
-            sfx_sinks: Vec::new(),
+            music_params: PlaybackParams::default(),
+            music_clock: None,
+            music_sync: MusicSync::default(),
+            sounds: Vec::new(),
+            max_sounds: DEFAULT_MAX_SOUNDS,
+            sounds_started: 0,
+            emitters: HashMap::new(),
+            listener: Listener::default(),
+            spatial_settings: SpatialSettings::default(),
             master_volume: 1.0,
             music_volume: 0.8,
             sfx_volume: 1.0,
+            background_audio: BackgroundAudio::Play,
+            window_focused: true,
+            window_visible: true,
+            duck: 1.0,
+            background_paused: false,
+            suspended: None,
+            suspended_sounds: Vec::new(),
+            rng: Random::from_time(),
+            music_effects: EffectChain::new(),
+            sfx_effects: EffectChain::new(),
         })
     }
 
     /// Play a sound effect
-    pub fn play_sfx(&mut self, source: &AudioSource) -> Result<(), String> {
-        let sink = Sink::try_new(&self.stream_handle)
-            .map_err(|e| format!("Failed to create sink: {}", e))?;
+    pub fn play_sfx(&mut self, source: &AudioSource) -> Result<SoundHandle, String> {
+        self.play_sfx_with(source, PlaybackParams::default())
+    }
 
-        let decoder = source.decoder()?;
-        sink.set_volume(self.master_volume * self.sfx_volume);
-        sink.append(decoder);
-        
-        // Detach the sink so it plays independently
-        // Note: We don't track detached sinks as they manage their own lifecycle
-        sink.detach();
+    /// Play a sound effect with its own volume, pitch, and pan
+    ///
+    /// While audio is suspended, or when the sound loses out to higher-priority ones at
+    /// the [voice limit](AudioManager::set_max_sounds), the sound is skipped and the
+    /// handle reports it finished.
+    pub fn play_sfx_with(&mut self, source: &AudioSource, params: PlaybackParams) -> Result<SoundHandle, String> {
+        if self.suspended.is_some() || !self.make_room(params.priority) {
+            return Ok(SoundHandle::finished());
+        }
 
-        // Clean up finished sinks
-        self.sfx_sinks.retain(|s| !s.empty());
+        let decoder = effect_source(source, params.looping, &self.sfx_effects)?;
+        let sink = self.new_sink();
+        sink.set_speed(params.roll_pitch(&mut self.rng));
+        sink.append(apply_pan(decoder, &params));
 
-        Ok(())
+        Ok(self.track(SoundSink { sink, emitter: None }, &params, None))
+    }
+
+    /// Set how many sound effects can play at once (music doesn't count)
+    ///
+    /// A new sound past the limit replaces the lowest-priority sound, choosing the
+    /// quietest and then the oldest among equals; it's skipped if all the others have
+    /// a higher priority.
+    pub fn set_max_sounds(&mut self, max_sounds: usize) {
+        self.max_sounds = max_sounds.max(1);
+        self.sounds.retain(|sound| !sound.is_finished());
+        while self.sounds.len() > self.max_sounds {
+            if let Some(index) = pick_voice_to_steal(&self.sounds, i32::MAX) {
+                if let Some(sink) = &self.sounds.remove(index).sink {
+                    sink.stop();
+                }
+            }
+        }
+    }
+
+    /// Get how many sound effects can play at once
+    pub fn max_sounds(&self) -> usize {
+        self.max_sounds
+    }
+
+    /// Stop a sound if the limit is reached, returning whether a new one may start
+    fn make_room(&mut self, priority: i32) -> bool {
+        self.sounds.retain(|sound| !sound.is_finished());
+        if self.sounds.len() < self.max_sounds {
+            return true;
+        }
+        match pick_voice_to_steal(&self.sounds, priority) {
+            Some(index) => {
+                if let Some(sink) = &self.sounds.remove(index).sink {
+                    sink.stop();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Create a sink that plays into the mix
+    fn new_sink(&self) -> Sink {
+        let (sink, queue) = Sink::new_idle();
+        self.mixer.add(queue);
+        sink
+    }
+
+    /// Start tracking a new sound effect, forgetting finished ones
+    fn track(&mut self, sink: SoundSink, params: &PlaybackParams, spatial: Option<(Vec3, SpatialSettings)>) -> SoundHandle {
+        self.sounds.retain(|sound| !sound.is_finished());
+        self.sounds_started += 1;
+        let sound = Rc::new(SoundState {
+            sink: Some(sink),
+            volume: Cell::new(params.volume.max(0.0)),
+            gain: Cell::new(0.0),
+            spatial: Cell::new(spatial),
+            priority: params.priority,
+            started: self.sounds_started,
+        });
+        self.update_sound(&sound);
+        self.sounds.push(sound.clone());
+        SoundHandle(sound)
+    }
+
+    /// Number of sound effects still playing (or paused)
+    pub fn playing_sounds(&self) -> usize {
+        self.sounds.iter().filter(|sound| !sound.is_finished()).count()
+    }
+
+    /// Play a sound effect at a world position, using the default spatial settings
+    pub fn play_sfx_at(&mut self, source: &AudioSource, position: Vec3) -> Result<SoundHandle, String> {
+        self.play_sfx_at_with(source, position, self.spatial_settings, PlaybackParams::default())
+    }
+
+    /// Play a sound effect at a world position with its own distance attenuation,
+    /// volume, and pitch
+    pub fn play_sfx_at_with(
+        &mut self,
+        source: &AudioSource,
+        position: Vec3,
+        settings: SpatialSettings,
+        params: PlaybackParams,
+    ) -> Result<SoundHandle, String> {
+        if self.suspended.is_some() || !self.make_room(params.priority) {
+            return Ok(SoundHandle::finished());
+        }
+
+        let decoder = effect_source(source, params.looping, &self.sfx_effects)?;
+        let emitter = Arc::new(Mutex::new(pan_position(&self.listener, position)));
+        let sink = self.new_sink();
+        sink.set_speed(params.roll_pitch(&mut self.rng));
+        sink.append(spatialize(decoder, emitter.clone()));
+
+        let sink = SoundSink {
+            sink,
+            emitter: Some(emitter),
+        };
+        Ok(self.track(sink, &params, Some((position, settings))))
+    }
+
+    /// Move the listener; positional sounds are re-panned and re-attenuated
+    pub fn set_listener(&mut self, position: Vec3, rotation: Quat) {
+        self.listener = Listener {
+            position,
+            right: rotation * Vec3::X,
+        };
+        self.update_sounds();
+    }
+
+    /// Put the listener at a camera, facing where it looks
+    pub fn set_listener_from_camera(&mut self, camera: &Camera) {
+        let forward = (camera.target - camera.position).normalize_or_zero();
+        let right = forward.cross(camera.up).normalize_or_zero();
+        self.listener = Listener {
+            position: camera.position,
+            right: if right == Vec3::ZERO { Vec3::X } else { right },
+        };
+        self.update_sounds();
+    }
+
+    /// Get the listener
+    pub fn listener(&self) -> Listener {
+        self.listener
+    }
+
+    /// Set the attenuation used by [`AudioManager::play_sfx_at`]
+    pub fn set_spatial_settings(&mut self, settings: SpatialSettings) {
+        self.spatial_settings = settings;
+    }
+
+    /// Get the attenuation used by [`AudioManager::play_sfx_at`]
+    pub fn spatial_settings(&self) -> SpatialSettings {
+        self.spatial_settings
+    }
+
+    /// Recompute the gain of a sound, and the pan of a positional one
+    fn update_sound(&self, sound: &SoundState) {
+        let mut gain = self.master_gain() * self.sfx_volume;
+        if let Some((position, settings)) = sound.spatial.get() {
+            gain *= settings.attenuation(position.distance(self.listener.position));
+            if let Some(sink) = &sound.sink {
+                sink.set_emitter_position(pan_position(&self.listener, position));
+            }
+        }
+        sound.gain.set(gain);
+        sound.apply_volume();
+    }
+
+    /// Forget finished sound effects and update the rest
+    pub(crate) fn update_sounds(&mut self) {
+        self.sounds.retain(|sound| !sound.is_finished());
+        for sound in &self.sounds {
+            self.update_sound(sound);
+        }
     }
 
     /// Play background music (loops)
     pub fn play_music(&mut self, source: &AudioSource, looping: bool) -> Result<(), String> {
+        self.play_music_with(source, looping, PlaybackParams::default())
+    }
+
+    /// Play background music with its own volume, pitch, and pan
+    pub fn play_music_with(&mut self, source: &AudioSource, looping: bool, params: PlaybackParams) -> Result<(), String> {
         // Stop existing music
         self.stop_music();
 
-        let sink = Sink::try_new(&self.stream_handle)
-            .map_err(|e| format!("Failed to create sink: {}", e))?;
-
-        let decoder = source.decoder()?;
-        sink.set_volume(self.master_volume * self.music_volume);
-        
-        if looping {
-            sink.append(decoder.repeat_infinite());
-        } else {
-            sink.append(decoder);
-        }
+        let clock = MusicClock::default();
+        let music = MusicSource::new(source, looping, clock.clone())?;
+        let decoder: BoxedSource = Box::new(EffectSource::new(music, self.music_effects.clone()));
+        let sink = self.new_sink();
+        sink.set_volume(self.master_gain() * self.music_volume * params.volume);
+        sink.set_speed(params.roll_pitch(&mut self.rng));
+        sink.append(apply_pan(decoder, &params));
 
         self.music_sink = Some(sink);
+        self.music_params = params;
+        self.music_clock = Some(clock);
+        self.music_sync.restart();
         log::info!("Playing music (looping: {})", looping);
 
         Ok(())
@@ -111,6 +840,7 @@ impl AudioManager {
         if let Some(sink) = self.music_sink.take() {
             sink.stop();
         }
+        self.music_clock = None;
     }
 
     /// Pause background music
@@ -127,6 +857,43 @@ impl AudioManager {
         }
     }
 
+    /// Pause music and mute new sound effects while the app is in the background
+    pub fn suspend(&mut self) {
+        if self.suspended.is_some() {
+            return;
+        }
+        let music_playing = self.music_sink.as_ref().is_some_and(|s| !s.is_paused());
+        self.pause_music();
+        for sound in &self.sounds {
+            if let Some(sink) = sound.sink.as_ref().filter(|sink| !sink.is_paused()) {
+                sink.pause();
+                self.suspended_sounds.push(sound.clone());
+            }
+        }
+        self.suspended = Some(music_playing);
+        log::info!("Audio suspended");
+    }
+
+    /// Check if audio is suspended (or paused in the background)
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
+
+    /// Restore playback after [`AudioManager::suspend`]
+    pub fn resume(&mut self) {
+        if let Some(music_playing) = self.suspended.take() {
+            if music_playing {
+                self.resume_music();
+            }
+            for sound in self.suspended_sounds.drain(..) {
+                if let Some(sink) = &sound.sink {
+                    sink.play();
+                }
+            }
+            log::info!("Audio resumed");
+        }
+    }
+
     /// Set master volume (0.0 to 1.0)
     pub fn set_master_volume(&mut self, volume: f32) {
         self.master_volume = volume.clamp(0.0, 1.0);
@@ -142,12 +909,70 @@ impl AudioManager {
     /// Set sound effects volume (0.0 to 1.0)
     pub fn set_sfx_volume(&mut self, volume: f32) {
         self.sfx_volume = volume.clamp(0.0, 1.0);
+        self.update_volumes();
+    }
+
+    /// Master volume including background ducking
+    fn master_gain(&self) -> f32 {
+        self.master_volume * self.duck
+    }
+
+    /// Choose what happens to audio while the window is unfocused or minimized
+    pub fn set_background_audio(&mut self, behavior: BackgroundAudio) {
+        let in_background = self.in_background();
+        self.set_in_background(false);
+        self.background_audio = behavior;
+        self.set_in_background(in_background);
+    }
+
+    /// Get what happens to audio while the window is in the background
+    pub fn background_audio(&self) -> BackgroundAudio {
+        self.background_audio
+    }
+
+    /// Tell the manager whether the window has focus (the engine forwards this)
+    pub fn set_window_focused(&mut self, focused: bool) {
+        self.window_focused = focused;
+        self.set_in_background(self.in_background());
+    }
+
+    /// Tell the manager whether the window can be seen, i.e. isn't minimized or covered
+    pub fn set_window_visible(&mut self, visible: bool) {
+        self.window_visible = visible;
+        self.set_in_background(self.in_background());
+    }
+
+    fn in_background(&self) -> bool {
+        !self.window_focused || !self.window_visible
+    }
+
+    fn set_in_background(&mut self, background: bool) {
+        let duck = match self.background_audio {
+            BackgroundAudio::Duck(factor) if background => factor.clamp(0.0, 1.0),
+            _ => 1.0,
+        };
+        if duck != self.duck {
+            self.duck = duck;
+            self.update_volumes();
+        }
+
+        let pause = background && self.background_audio == BackgroundAudio::Pause;
+        if pause && !self.background_paused && self.suspended.is_none() {
+            self.suspend();
+            self.background_paused = true;
+        } else if !pause && self.background_paused {
+            self.background_paused = false;
+            self.resume();
+        }
     }
 
     /// Update volume for all active sinks
     fn update_volumes(&self) {
         if let Some(sink) = &self.music_sink {
-            sink.set_volume(self.master_volume * self.music_volume);
+            sink.set_volume(self.master_gain() * self.music_volume * self.music_params.volume);
+        }
+        for sound in &self.sounds {
+            self.update_sound(sound);
         }
     }
 
@@ -165,6 +990,83 @@ impl AudioManager {
     pub fn sfx_volume(&self) -> f32 {
         self.sfx_volume
     }
+
+    /// Names of the available output devices
+    pub fn devices(&self) -> Vec<String> {
+        audio_output::output_devices()
+    }
+
+    /// Name of the device audio currently plays on
+    pub fn output_device(&self) -> Option<&str> {
+        self.output.as_ref().map(|output| output.name())
+    }
+
+    /// Play on a specific output device; music and sounds carry on where they were
+    ///
+    /// If the device goes away, playback falls back to the default device and returns
+    /// once it's available again.
+    pub fn set_output_device(&mut self, name: &str) -> Result<(), String> {
+        self.output = Some(Output::open(Some(name), &self.mix)?);
+        self.preferred_device = Some(name.to_string());
+        self.last_pulls = 0;
+        Ok(())
+    }
+
+    /// Go back to following the system's default output device
+    pub fn use_default_output_device(&mut self) {
+        self.preferred_device = None;
+        self.check_output();
+    }
+
+    /// Fire music beat and marker callbacks, recover from a lost output device, and
+    /// follow changes of the default device
+    ///
+    /// The engine calls this every frame; devices are checked at most once a second.
+    pub fn update(&mut self) {
+        self.update_music_sync();
+        if self.suspended.is_some() || self.last_device_check.elapsed() < DEVICE_CHECK_INTERVAL {
+            return;
+        }
+        self.check_output();
+    }
+
+    fn check_output(&mut self) {
+        self.last_device_check = Instant::now();
+        // A stream that stopped pulling samples lost its device
+        let pulls = self.output.as_ref().map_or(0, |output| output.pulls());
+        let stalled = self.output.is_some() && pulls == self.last_pulls;
+        self.last_pulls = pulls;
+
+        let target = self
+            .preferred_device
+            .clone()
+            .filter(|name| audio_output::output_devices().contains(name))
+            .or_else(audio_output::default_output_device);
+        if !stalled && target.as_deref() == self.output_device() {
+            return;
+        }
+
+        // Detach from the old stream first so the two don't share the mix
+        self.output = None;
+        self.last_pulls = 0;
+        match target {
+            Some(name) => match Output::open(Some(&name), &self.mix) {
+                Ok(output) => self.output = Some(output),
+                Err(e) => log::warn!("{}", e),
+            },
+            None => log::warn!("No audio output device available"),
+        }
+    }
+
+    /// Effects applied to the music, including music that's already playing
+    pub fn music_effects(&self) -> &EffectChain {
+        &self.music_effects
+    }
+
+    /// Effects applied to every sound effect, including ones that are already playing
+    pub fn sfx_effects(&self) -> &EffectChain {
+        &self.sfx_effects
+    }
 }
 
 impl Default for AudioManager {
@@ -172,3 +1074,86 @@ impl Default for AudioManager {
         Self::new().expect("Failed to initialize audio manager")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_sources_decode() {
+        let decoder = AudioSource::sine(441.0, 0.1).decoder().unwrap();
+        assert_eq!((decoder.channels(), decoder.sample_rate()), (1, 44100));
+        let samples: Vec<i16> = decoder.collect();
+        assert_eq!(samples.len(), 4410);
+        // Faded in from silence, peaking at half scale
+        assert_eq!(samples[0], 0);
+        let peak = samples.iter().map(|s| s.abs()).max().unwrap();
+        assert!((peak - i16::MAX / 2).abs() < 50);
+
+        let square: Vec<i16> = AudioSource::square(50.0, 0.05).decoder().unwrap().collect();
+        assert_eq!((square[300], square[600]), (i16::MAX / 2, -i16::MAX / 2));
+        let noise: Vec<i16> = AudioSource::white_noise(0.05).decoder().unwrap().collect();
+        assert!(noise.iter().any(|&s| s > 1000) && noise.iter().any(|&s| s < -1000));
+    }
+
+    #[test]
+    fn test_rolloff_and_panning() {
+        let linear = SpatialSettings {
+            rolloff: Rolloff::Linear,
+            min_distance: 2.0,
+            max_distance: 12.0,
+        };
+        assert_eq!(linear.attenuation(1.0), 1.0);
+        assert_eq!(linear.attenuation(7.0), 0.5);
+        assert_eq!(linear.attenuation(20.0), 0.0);
+        let inverse = SpatialSettings::default();
+        assert_eq!(inverse.attenuation(4.0), 0.25);
+        let square = SpatialSettings {
+            rolloff: Rolloff::InverseSquare,
+            ..inverse
+        };
+        assert_eq!(square.attenuation(4.0), 0.0625);
+
+        let listener = Listener {
+            position: Vec3::new(0.0, 0.0, 5.0),
+            right: Vec3::NEG_Z,
+        };
+        let [x, _, z] = pan_position(&listener, Vec3::new(0.0, 0.0, -20.0));
+        assert!((x - PAN_RADIUS).abs() < 1e-6 && z.abs() < 1e-6);
+        assert_eq!(pan_position(&listener, listener.position), [0.0, 0.0, -PAN_RADIUS]);
+    }
+
+    #[test]
+    fn test_playback_params() {
+        let mut rng = Random::new(7);
+        let footstep = PlaybackParams::default().with_pitch(1.2).with_pitch_variation(0.1);
+        let pitches: Vec<f32> = (0..20).map(|_| footstep.roll_pitch(&mut rng)).collect();
+        assert!(pitches.iter().all(|p| (1.1..=1.3).contains(p)));
+        assert!(pitches.iter().any(|&p| p != pitches[0]));
+        assert_eq!(PlaybackParams::default().roll_pitch(&mut rng), 1.0);
+
+        assert_eq!(PlaybackParams::default().with_pan(-0.5).channel_volumes(), [1.0, 0.5]);
+        assert_eq!(PlaybackParams::default().with_pan(3.0).channel_volumes(), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_voice_stealing_order() {
+        let sound = |priority, volume, started| {
+            Rc::new(SoundState {
+                sink: None,
+                volume: Cell::new(volume),
+                gain: Cell::new(1.0),
+                spatial: Cell::new(None),
+                priority,
+                started,
+            })
+        };
+        let sounds = vec![sound(1, 0.2, 1), sound(0, 0.8, 2), sound(0, 0.5, 3), sound(0, 0.5, 4), sound(5, 0.1, 5)];
+        // Lowest priority first, then the quietest, then the oldest
+        assert_eq!(pick_voice_to_steal(&sounds, 0), Some(2));
+        assert_eq!(pick_voice_to_steal(&sounds[..2], 3), Some(1));
+        // Sounds of higher priority than the new one are never cut
+        assert_eq!(pick_voice_to_steal(&sounds[4..], 1), None);
+        assert_eq!(pick_voice_to_steal(&sounds[4..], 5), Some(0));
+    }
+}
@@ -0,0 +1,423 @@
+//! Audio effects applied per bus
+//!
+//! Music and sound effects each run through an [`EffectChain`]: an ordered list of slots
+//! holding a filter, reverb, or echo. The chain is shared with the audio thread, so
+//! changing a slot (say, muffling everything while the player is underwater) also
+//! affects sounds that are already playing, within a few milliseconds.
+//!
+//! ```ignore
+//! let underwater = audio.sfx_effects().add(Effect::LowPass { cutoff: 600.0 });
+//! // Later, when the player surfaces
+//! audio.sfx_effects().set_enabled(underwater, false);
+//! ```
+
+use rodio::Source;
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Frames between checks for changed effect settings
+const POLL_FRAMES: usize = 512;
+
+/// Comb filter lengths of the reverb at 44.1 kHz (from Freeverb)
+const COMB_TUNING: [usize; 4] = [1116, 1188, 1277, 1356];
+/// All-pass filter lengths of the reverb at 44.1 kHz
+const ALLPASS_TUNING: [usize; 2] = [556, 441];
+/// Extra delay per channel so the reverb tails of the channels differ
+const STEREO_SPREAD: usize = 23;
+
+/// One effect in a chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effect {
+    /// Remove frequencies above `cutoff` Hz (muffled, underwater, behind a wall)
+    LowPass { cutoff: f32 },
+    /// Remove frequencies below `cutoff` Hz (radio, telephone)
+    HighPass { cutoff: f32 },
+    /// Room reverb; `room_size` (0 to 1) sets the decay, `mix` the level of the reverb
+    Reverb { room_size: f32, mix: f32 },
+    /// Echo after `delay` seconds, each repeat scaled by `feedback`
+    Delay { delay: f32, feedback: f32, mix: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Slot {
+    effect: Effect,
+    enabled: bool,
+}
+
+#[derive(Default)]
+struct ChainData {
+    slots: Vec<Slot>,
+    /// Bumped on every change, so playing sounds know to copy the slots again
+    version: u64,
+}
+
+/// Ordered effect slots of an audio bus, shared with the sounds playing on it
+#[derive(Clone, Default)]
+pub struct EffectChain(Arc<Mutex<ChainData>>);
+
+impl EffectChain {
+    /// Create an empty chain
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an effect, returning its slot
+    pub fn add(&self, effect: Effect) -> usize {
+        let mut data = self.edit();
+        data.slots.push(Slot { effect, enabled: true });
+        data.slots.len() - 1
+    }
+
+    /// Replace the effect in a slot, e.g. to change its parameters
+    pub fn set(&self, slot: usize, effect: Effect) -> bool {
+        self.edit().slots.get_mut(slot).map(|s| s.effect = effect).is_some()
+    }
+
+    /// Get the effect in a slot
+    pub fn get(&self, slot: usize) -> Option<Effect> {
+        self.lock().slots.get(slot).map(|s| s.effect)
+    }
+
+    /// Bypass or re-enable a slot, keeping its settings
+    pub fn set_enabled(&self, slot: usize, enabled: bool) -> bool {
+        self.edit().slots.get_mut(slot).map(|s| s.enabled = enabled).is_some()
+    }
+
+    /// Check if a slot exists and isn't bypassed
+    pub fn is_enabled(&self, slot: usize) -> bool {
+        self.lock().slots.get(slot).is_some_and(|s| s.enabled)
+    }
+
+    /// Remove every effect
+    pub fn clear(&self) {
+        self.edit().slots.clear();
+    }
+
+    /// Number of slots
+    pub fn len(&self) -> usize {
+        self.lock().slots.len()
+    }
+
+    /// Check if the chain has no slots
+    pub fn is_empty(&self) -> bool {
+        self.lock().slots.is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ChainData> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn edit(&self) -> MutexGuard<'_, ChainData> {
+        let mut data = self.lock();
+        data.version += 1;
+        data
+    }
+}
+
+/// A fixed-length circular buffer of samples
+#[derive(Default)]
+struct DelayLine {
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl DelayLine {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.0; length.max(1)],
+            position: 0,
+        }
+    }
+
+    /// The sample written `length` samples ago
+    fn read(&self) -> f32 {
+        self.buffer[self.position]
+    }
+
+    fn write(&mut self, sample: f32) {
+        self.buffer[self.position] = sample;
+        self.position = (self.position + 1) % self.buffer.len();
+    }
+}
+
+/// Second-order low- or high-pass filter (RBJ cookbook coefficients)
+#[derive(Default)]
+struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+    x: [f32; 2],
+    y: [f32; 2],
+}
+
+impl Biquad {
+    fn configure(&mut self, cutoff: f32, high_pass: bool, sample_rate: u32) {
+        let rate = sample_rate as f32;
+        let w0 = 2.0 * PI * cutoff.clamp(10.0, rate * 0.45) / rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * FRAC_1_SQRT_2);
+        let a0 = 1.0 + alpha;
+        let b = if high_pass {
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0]
+        } else {
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0]
+        };
+        self.b = b.map(|b| b / a0);
+        self.a = [-2.0 * cos / a0, (1.0 - alpha) / a0];
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// Schroeder reverb: parallel damped combs into series all-passes
+struct Reverb {
+    combs: Vec<(DelayLine, f32)>,
+    allpasses: Vec<DelayLine>,
+    feedback: f32,
+}
+
+impl Reverb {
+    const DAMPING: f32 = 0.2;
+
+    fn new(sample_rate: u32, channel: usize) -> Self {
+        let scale = |length: usize| (length + channel * STEREO_SPREAD) * sample_rate as usize / 44100;
+        Self {
+            combs: COMB_TUNING.iter().map(|&l| (DelayLine::new(scale(l)), 0.0)).collect(),
+            allpasses: ALLPASS_TUNING.iter().map(|&l| DelayLine::new(scale(l))).collect(),
+            feedback: 0.0,
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let input = x * 0.05;
+        let mut out = 0.0;
+        for (line, store) in &mut self.combs {
+            let delayed = line.read();
+            *store = delayed * (1.0 - Self::DAMPING) + *store * Self::DAMPING;
+            line.write(input + *store * self.feedback);
+            out += delayed;
+        }
+        for line in &mut self.allpasses {
+            let delayed = line.read();
+            line.write(out + delayed * 0.5);
+            out = delayed - out;
+        }
+        out
+    }
+}
+
+/// Running state of one effect on one channel
+enum EffectState {
+    Filter(Biquad),
+    Reverb(Reverb),
+    Delay(DelayLine),
+}
+
+impl EffectState {
+    fn new(effect: &Effect, sample_rate: u32, channel: usize) -> Self {
+        let mut state = match effect {
+            Effect::LowPass { .. } | Effect::HighPass { .. } => Self::Filter(Biquad::default()),
+            Effect::Reverb { .. } => Self::Reverb(Reverb::new(sample_rate, channel)),
+            Effect::Delay { .. } => Self::Delay(DelayLine::default()),
+        };
+        state.configure(effect, sample_rate, channel);
+        state
+    }
+
+    /// Take new parameters, keeping the filter history unless the effect changed kind
+    fn configure(&mut self, effect: &Effect, sample_rate: u32, channel: usize) {
+        match (self, *effect) {
+            (Self::Filter(filter), Effect::LowPass { cutoff }) => filter.configure(cutoff, false, sample_rate),
+            (Self::Filter(filter), Effect::HighPass { cutoff }) => filter.configure(cutoff, true, sample_rate),
+            (Self::Reverb(reverb), Effect::Reverb { room_size, .. }) => {
+                reverb.feedback = 0.7 + 0.28 * room_size.clamp(0.0, 1.0);
+            }
+            (Self::Delay(line), Effect::Delay { delay, .. }) => {
+                let length = ((delay.max(0.0) * sample_rate as f32) as usize).max(1);
+                if line.buffer.len() != length {
+                    *line = DelayLine::new(length);
+                }
+            }
+            (state, effect) => *state = Self::new(&effect, sample_rate, channel),
+        }
+    }
+
+    fn process(&mut self, effect: &Effect, x: f32) -> f32 {
+        match (self, effect) {
+            (Self::Filter(filter), _) => filter.process(x),
+            (Self::Reverb(reverb), Effect::Reverb { mix, .. }) => x + (reverb.process(x) - x) * mix,
+            (Self::Delay(line), Effect::Delay { feedback, mix, .. }) => {
+                let echo = line.read();
+                line.write(x + echo * feedback.clamp(0.0, 0.95));
+                x + echo * mix
+            }
+            _ => x,
+        }
+    }
+}
+
+/// Runs a source through the effects of a chain, picking up changes while it plays
+pub(crate) struct EffectSource<S> {
+    input: S,
+    chain: EffectChain,
+    version: u64,
+    slots: Vec<Slot>,
+    /// State of each slot, one per channel
+    states: Vec<Vec<EffectState>>,
+    channels: u16,
+    sample_rate: u32,
+    /// Channel of the next sample
+    channel: usize,
+    frames_until_poll: usize,
+}
+
+impl<S: Source<Item = i16>> EffectSource<S> {
+    pub(crate) fn new(input: S, chain: EffectChain) -> Self {
+        let mut source = Self {
+            input,
+            chain,
+            version: 0,
+            slots: Vec::new(),
+            states: Vec::new(),
+            channels: 0,
+            sample_rate: 0,
+            channel: 0,
+            frames_until_poll: POLL_FRAMES,
+        };
+        let data = source.chain.lock();
+        let (version, slots) = (data.version, data.slots.clone());
+        drop(data);
+        source.update(version, slots);
+        source
+    }
+
+    /// Copy the chain's slots if they changed, without ever blocking the audio thread
+    fn poll(&mut self) {
+        let format_changed = self.input.channels() != self.channels || self.input.sample_rate() != self.sample_rate;
+        let Ok(data) = self.chain.0.try_lock() else {
+            return;
+        };
+        if data.version == self.version && !format_changed {
+            return;
+        }
+        let (version, slots) = (data.version, data.slots.clone());
+        drop(data);
+        self.update(version, slots);
+    }
+
+    fn update(&mut self, version: u64, slots: Vec<Slot>) {
+        let (channels, sample_rate) = (self.input.channels().max(1), self.input.sample_rate());
+        if channels != self.channels || sample_rate != self.sample_rate {
+            self.states.clear();
+        }
+        self.version = version;
+        self.channels = channels;
+        self.sample_rate = sample_rate;
+        self.states.resize_with(slots.len(), Vec::new);
+        for (slot, states) in slots.iter().zip(&mut self.states) {
+            for (channel, state) in states.iter_mut().enumerate() {
+                state.configure(&slot.effect, sample_rate, channel);
+            }
+            for channel in states.len()..channels as usize {
+                states.push(EffectState::new(&slot.effect, sample_rate, channel));
+            }
+        }
+        self.slots = slots;
+    }
+}
+
+impl<S: Source<Item = i16>> Iterator for EffectSource<S> {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.channel == 0 {
+            if self.frames_until_poll == 0 {
+                self.poll();
+                self.frames_until_poll = POLL_FRAMES;
+            }
+            self.frames_until_poll -= 1;
+        }
+        let sample = self.input.next()?;
+        let mut x = sample as f32 / 32768.0;
+        for (slot, states) in self.slots.iter().zip(&mut self.states) {
+            if let Some(state) = states.get_mut(self.channel).filter(|_| slot.enabled) {
+                x = state.process(&slot.effect, x);
+            }
+        }
+        self.channel = (self.channel + 1) % self.channels as usize;
+        Some((x * 32768.0).clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+    }
+}
+
+impl<S: Source<Item = i16>> Source for EffectSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn peak(samples: &[i16]) -> i16 {
+        samples.iter().map(|s| s.saturating_abs()).max().unwrap_or(0)
+    }
+
+    #[test]
+    fn test_low_pass_changes_while_playing() {
+        // A tone at the Nyquist frequency, which a low-pass filter removes
+        let tone: Vec<i16> = (0..4 * POLL_FRAMES).map(|i| if i % 2 == 0 { 10000 } else { -10000 }).collect();
+        let chain = EffectChain::new();
+        let output: Vec<i16> = EffectSource::new(SamplesBuffer::new(1, 44100, tone), chain.clone())
+            .enumerate()
+            .inspect(|(i, _)| {
+                if *i == POLL_FRAMES {
+                    chain.add(Effect::LowPass { cutoff: 500.0 });
+                } else if *i == 2 * POLL_FRAMES + 1 {
+                    chain.set_enabled(0, false);
+                }
+            })
+            .map(|(_, sample)| sample)
+            .collect();
+
+        assert_eq!(peak(&output[..2 * POLL_FRAMES]), 10000);
+        assert!(peak(&output[2 * POLL_FRAMES + 128..3 * POLL_FRAMES]) < 100);
+        assert_eq!(peak(&output[3 * POLL_FRAMES + 128..]), 10000);
+        assert_eq!(chain.get(0), Some(Effect::LowPass { cutoff: 500.0 }));
+        assert!(!chain.is_enabled(0));
+    }
+
+    #[test]
+    fn test_delay_repeats_with_feedback() {
+        let mut impulse = vec![0i16; 40];
+        impulse[0] = 16384;
+        let chain = EffectChain::new();
+        chain.add(Effect::Delay { delay: 0.01, feedback: 0.5, mix: 0.5 });
+        let output: Vec<i16> = EffectSource::new(SamplesBuffer::new(1, 1000, impulse), chain).collect();
+
+        assert_eq!(output[0], 16384);
+        assert_eq!(output[10], 8192);
+        assert_eq!(output[20], 4096);
+        assert_eq!(output[5], 0);
+    }
+}
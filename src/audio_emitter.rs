@@ -0,0 +1,198 @@
+//! Sounds attached to entities
+//!
+//! An entity with an [`AudioEmitter`] plays a sound from the [`ResourceManager`] at its
+//! position, and the sound follows the entity as it moves. An entity tagged
+//! [`AudioListener`] (usually the player or camera rig) takes over the listener from the
+//! camera. The engine runs [`AudioManager::update_scene_audio`] every frame.
+//!
+//! ```ignore
+//! let hum = resources.load_audio("hum".to_string(), "assets/sounds/hum.ogg")?;
+//! scene.spawn_named("Generator", (Transform::from_position(pos), AudioEmitter::new(hum).with_looping(true)));
+//! ```
+
+use crate::audio::{AudioManager, PlaybackParams, SoundHandle, SpatialSettings};
+use crate::ecs::{Component, EntityId, EntityRef, Scene};
+use crate::hierarchy::GlobalTransform;
+use crate::math::Transform;
+use crate::resource::{AudioHandle, ResourceManager};
+use glam::{Quat, Vec3};
+use std::collections::HashMap;
+
+/// Plays a sound at the entity's position
+///
+/// A one-shot sound plays once when the emitter appears (or its sound changes); a
+/// looping one plays until the emitter is removed or its entity deactivated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioEmitter {
+    pub sound: AudioHandle,
+    pub looping: bool,
+    /// Distance beyond which the sound is silent
+    pub max_distance: f32,
+    /// Volume on top of the master and SFX volumes
+    pub volume: f32,
+}
+
+impl Component for AudioEmitter {}
+
+impl AudioEmitter {
+    /// Play a sound once, audible up to the default distance
+    pub fn new(sound: AudioHandle) -> Self {
+        Self {
+            sound,
+            looping: false,
+            max_distance: SpatialSettings::default().max_distance,
+            volume: 1.0,
+        }
+    }
+
+    /// Repeat the sound while the emitter exists
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Set the distance beyond which the sound is silent
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Set the volume
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume.max(0.0);
+        self
+    }
+}
+
+/// Marks the entity sounds are heard from, instead of the camera
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioListener;
+
+impl Component for AudioListener {}
+
+/// The sound an emitter started
+pub(crate) struct EmitterSound {
+    sound: AudioHandle,
+    handle: SoundHandle,
+}
+
+/// World position and rotation of an entity
+fn world_pose(entity: &EntityRef) -> Option<(Vec3, Quat)> {
+    if let Some(global) = entity.get_component::<GlobalTransform>() {
+        let (_, rotation, translation) = global.to_scale_rotation_translation();
+        return Some((translation, rotation));
+    }
+    entity
+        .get_component::<Transform>()
+        .map(|transform| (transform.position, transform.rotation))
+}
+
+/// Pose of the active `AudioListener` entity (the lowest id if there are several)
+fn listener_pose(scene: &Scene) -> Option<(Vec3, Quat)> {
+    scene
+        .active_entities()
+        .filter(|entity| entity.has_component::<AudioListener>())
+        .filter_map(|entity| Some((entity.id(), world_pose(&entity)?)))
+        .min_by_key(|(id, _)| *id)
+        .map(|(_, pose)| pose)
+}
+
+impl AudioManager {
+    /// Play, move, and stop the sounds of `AudioEmitter`s, and follow the `AudioListener`
+    ///
+    /// Returns whether a listener entity was found; otherwise the listener stays where it
+    /// was put, e.g. at the camera.
+    pub fn update_scene_audio(&mut self, scene: &Scene, resources: &ResourceManager) -> bool {
+        let listener = listener_pose(scene);
+        if let Some((position, rotation)) = listener {
+            self.set_listener(position, rotation);
+        }
+
+        let mut previous = std::mem::take(&mut self.emitters);
+        let mut current: HashMap<EntityId, EmitterSound> = HashMap::new();
+        for entity in scene.active_entities() {
+            let (Some(emitter), Some((position, _))) = (entity.get_component::<AudioEmitter>(), world_pose(&entity)) else {
+                continue;
+            };
+            let settings = SpatialSettings {
+                max_distance: emitter.max_distance,
+                ..self.spatial_settings()
+            };
+            let playing = match previous.remove(&entity.id()) {
+                Some(playing) if playing.sound == emitter.sound => playing,
+                stale => {
+                    if let Some(stale) = stale {
+                        stale.handle.stop();
+                    }
+                    // Started once audio is back
+                    if self.is_suspended() {
+                        continue;
+                    }
+                    self.start_emitter(emitter, position, settings, resources)
+                }
+            };
+            playing.handle.set_spatial(position, settings);
+            playing.handle.set_volume(emitter.volume);
+            current.insert(entity.id(), playing);
+        }
+
+        // Emitters that were removed or deactivated
+        for stale in previous.into_values() {
+            stale.handle.stop();
+        }
+        self.emitters = current;
+        self.update_sounds();
+        listener.is_some()
+    }
+
+    fn start_emitter(
+        &mut self,
+        emitter: &AudioEmitter,
+        position: Vec3,
+        settings: SpatialSettings,
+        resources: &ResourceManager,
+    ) -> EmitterSound {
+        let params = PlaybackParams::default()
+            .with_volume(emitter.volume)
+            .with_looping(emitter.looping);
+        let handle = match resources.get_audio(emitter.sound) {
+            Some(source) => self.play_sfx_at_with(source, position, settings, params),
+            None => Err(format!("Sound {} is not loaded", emitter.sound)),
+        };
+        // A failed sound counts as played, so it isn't retried every frame
+        let handle = handle.unwrap_or_else(|e| {
+            log::warn!("Failed to play audio emitter: {}", e);
+            SoundHandle::finished()
+        });
+        EmitterSound {
+            sound: emitter.sound,
+            handle,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listener_uses_world_transform() {
+        let mut scene = Scene::new("Test".to_string());
+        assert_eq!(listener_pose(&scene), None);
+
+        let rig = scene.create_entity("Rig".to_string());
+        let head = scene.create_entity("Head".to_string());
+        scene.get_entity_mut(rig).unwrap().add_component(Transform::from_position(Vec3::new(10.0, 0.0, 0.0)));
+        let mut entity = scene.get_entity_mut(head).unwrap();
+        entity.add_component(Transform::from_position(Vec3::Y));
+        entity.add_component(AudioListener);
+        scene.set_parent(head, rig);
+        crate::hierarchy::propagate_transforms(&mut scene);
+
+        let (position, _) = listener_pose(&scene).unwrap();
+        assert!(position.distance(Vec3::new(10.0, 1.0, 0.0)) < 1e-5);
+
+        scene.get_entity_mut(head).unwrap().set_active(false);
+        assert_eq!(listener_pose(&scene), None);
+    }
+}
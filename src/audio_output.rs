@@ -0,0 +1,188 @@
+//! Audio output devices
+//!
+//! Sinks don't play straight into a device stream. They all feed one mix owned by the
+//! [`AudioManager`](crate::audio::AudioManager), and only a relay that pulls from that
+//! mix is attached to the device. Switching devices (or recovering when headphones are
+//! unplugged) attaches a new relay to a new stream, so sounds keep playing from where
+//! they were.
+
+use rodio::cpal::traits::HostTrait;
+use rodio::dynamic_mixer::DynamicMixer;
+use rodio::{DeviceTrait, OutputStream, Source};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Channels of the mix
+pub(crate) const MIX_CHANNELS: u16 = 2;
+/// Sample rate of the mix (devices running at another rate resample it)
+pub(crate) const MIX_SAMPLE_RATE: u32 = 44100;
+/// Samples the relay pulls from the mix per lock
+const RELAY_CHUNK: usize = 512;
+
+/// The mix every sink plays into
+pub(crate) type Mix = Arc<Mutex<DynamicMixer<f32>>>;
+
+/// Names of the available output devices
+pub(crate) fn output_devices() -> Vec<String> {
+    match rodio::cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            log::warn!("Failed to list audio devices: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Name of the system's default output device
+pub(crate) fn default_output_device() -> Option<String> {
+    rodio::cpal::default_host()
+        .default_output_device()
+        .and_then(|device| device.name().ok())
+}
+
+/// Plays the mix on one device stream until detached
+struct MixRelay {
+    mix: Mix,
+    buffer: Vec<f32>,
+    position: usize,
+    detached: Arc<AtomicBool>,
+    /// Chunks pulled so far, watched to notice a stream that stopped
+    pulls: Arc<AtomicUsize>,
+}
+
+impl Iterator for MixRelay {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position == self.buffer.len() {
+            if self.detached.load(Ordering::Relaxed) {
+                return None;
+            }
+            let mut mix = self.mix.lock().unwrap_or_else(|e| e.into_inner());
+            self.buffer.clear();
+            // The mix ends whenever nothing plays; the device gets silence instead
+            self.buffer.extend((0..RELAY_CHUNK).map(|_| mix.next().unwrap_or(0.0)));
+            self.position = 0;
+            self.pulls.fetch_add(1, Ordering::Relaxed);
+        }
+        self.position += 1;
+        Some(self.buffer[self.position - 1])
+    }
+}
+
+impl Source for MixRelay {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        MIX_CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        MIX_SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// An open device stream playing the mix; dropping it detaches the mix again
+pub(crate) struct Output {
+    _stream: OutputStream,
+    name: String,
+    detached: Arc<AtomicBool>,
+    pulls: Arc<AtomicUsize>,
+}
+
+impl Output {
+    /// Open a device by name (or the default device) and start playing the mix on it
+    pub(crate) fn open(name: Option<&str>, mix: &Mix) -> Result<Self, String> {
+        let host = rodio::cpal::default_host();
+        let device = match name {
+            Some(name) => host
+                .output_devices()
+                .map_err(|e| format!("Failed to list audio devices: {}", e))?
+                .find(|device| device.name().is_ok_and(|n| n == name)),
+            None => host.default_output_device(),
+        }
+        .ok_or_else(|| format!("Audio device '{}' not found", name.unwrap_or("default")))?;
+        let name = device.name().unwrap_or_default();
+
+        let (stream, handle) = OutputStream::try_from_device(&device)
+            .map_err(|e| format!("Failed to open audio device '{}': {}", name, e))?;
+        let relay = MixRelay {
+            mix: mix.clone(),
+            buffer: Vec::with_capacity(RELAY_CHUNK),
+            position: 0,
+            detached: Arc::new(AtomicBool::new(false)),
+            pulls: Arc::new(AtomicUsize::new(0)),
+        };
+        let (detached, pulls) = (relay.detached.clone(), relay.pulls.clone());
+        handle
+            .play_raw(relay)
+            .map_err(|e| format!("Failed to play on audio device '{}': {}", name, e))?;
+
+        log::info!("Audio output: {}", name);
+        Ok(Self {
+            _stream: stream,
+            name,
+            detached,
+            pulls,
+        })
+    }
+
+    /// Name of the device
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Chunks of the mix the device has pulled so far
+    pub(crate) fn pulls(&self) -> usize {
+        self.pulls.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        self.detached.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn relay(mix: &Mix) -> MixRelay {
+        MixRelay {
+            mix: mix.clone(),
+            buffer: Vec::new(),
+            position: 0,
+            detached: Arc::new(AtomicBool::new(false)),
+            pulls: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    #[test]
+    fn test_new_relay_continues_the_mix() {
+        let (controller, mixer) = rodio::dynamic_mixer::mixer::<f32>(MIX_CHANNELS, MIX_SAMPLE_RATE);
+        let mix: Mix = Arc::new(Mutex::new(mixer));
+        let ramp: Vec<f32> = (0..4 * RELAY_CHUNK).map(|i| i as f32 / 10000.0).collect();
+        controller.add(SamplesBuffer::new(MIX_CHANNELS, MIX_SAMPLE_RATE, ramp));
+
+        let mut old = relay(&mix);
+        assert_eq!(old.next(), Some(0.0));
+        old.detached.store(true, Ordering::Relaxed);
+        // The rest of the chunk already pulled plays out, then the relay ends
+        assert_eq!(old.by_ref().count(), RELAY_CHUNK - 1);
+
+        let mut new = relay(&mix);
+        assert_eq!(new.next(), Some(RELAY_CHUNK as f32 / 10000.0));
+        assert_eq!(new.pulls.load(Ordering::Relaxed), 1);
+        // Past the end of every sound, the device hears silence
+        assert_eq!(new.nth(4 * RELAY_CHUNK), Some(0.0));
+    }
+}
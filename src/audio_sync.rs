@@ -0,0 +1,316 @@
+//! Music position and beat callbacks
+//!
+//! Music plays through a source that counts the frames it hands to the mix, so the
+//! manager knows exactly where in the track playback is, including across loops.
+//! Callbacks registered for a tempo or a list of markers fire from
+//! [`AudioManager::update`] once the music passes them. A frame may cover several
+//! cues, or fire one a little late; each [`MusicCue`] says how far playback had moved
+//! past it, so rhythm checks can compensate.
+//!
+//! ```ignore
+//! audio.on_music_beat(128.0, 0.12, |cue| pulse_lights(cue.index, cue.late));
+//! audio.on_music_markers(&[12.0, 45.5], |cue| start_chorus(cue.index));
+//! audio.play_music(&song, true)?;
+//! ```
+
+use crate::audio::{AudioDecoder, AudioManager, AudioSource};
+use rodio::Source;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A beat or marker the music reached
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MusicCue {
+    /// Beat number from the start of the track, or the marker's index in its list
+    pub index: usize,
+    /// Position in the track the cue belongs to, in seconds
+    pub time: f32,
+    /// How far the music had played past the cue when it fired, in seconds of track time
+    pub late: f32,
+}
+
+/// Progress of the playing music, shared with the audio thread
+#[derive(Clone, Default)]
+pub(crate) struct MusicClock(Arc<ClockData>);
+
+#[derive(Default)]
+struct ClockData {
+    /// Frames played over all passes of the track
+    frames: AtomicU64,
+    /// Frames in one pass (0 until the first pass ends)
+    length: AtomicU64,
+    sample_rate: AtomicU32,
+}
+
+impl MusicClock {
+    /// Seconds played over all passes, and the length of one pass if known
+    fn progress(&self) -> (f64, Option<f64>) {
+        let rate = self.0.sample_rate.load(Ordering::Relaxed).max(1) as f64;
+        let length = self.0.length.load(Ordering::Relaxed);
+        let played = self.0.frames.load(Ordering::Relaxed) as f64 / rate;
+        (played, (length > 0).then(|| length as f64 / rate))
+    }
+
+    /// Position in the track in seconds
+    fn position(&self) -> f64 {
+        match self.progress() {
+            (played, Some(length)) => played % length,
+            (played, None) => played,
+        }
+    }
+}
+
+/// Decodes music, starting over at the end when looping, and counts the frames played
+pub(crate) struct MusicSource {
+    source: AudioSource,
+    decoder: AudioDecoder,
+    looping: bool,
+    /// Samples played in the current pass
+    samples: u64,
+    clock: MusicClock,
+}
+
+impl MusicSource {
+    pub(crate) fn new(source: &AudioSource, looping: bool, clock: MusicClock) -> Result<Self, String> {
+        let decoder = source.decoder()?;
+        clock.0.sample_rate.store(decoder.sample_rate(), Ordering::Relaxed);
+        Ok(Self {
+            source: source.clone(),
+            decoder,
+            looping,
+            samples: 0,
+            clock,
+        })
+    }
+}
+
+impl Iterator for MusicSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = match self.decoder.next() {
+            Some(sample) => sample,
+            // An empty track would start over forever
+            None if !self.looping || self.samples == 0 => return None,
+            None => {
+                let channels = self.decoder.channels().max(1) as u64;
+                self.clock.0.length.store(self.samples / channels, Ordering::Relaxed);
+                self.decoder = self.source.decoder().ok()?;
+                self.samples = 0;
+                self.decoder.next()?
+            }
+        };
+        self.samples += 1;
+        if self.samples.is_multiple_of(self.decoder.channels().max(1) as u64) {
+            self.clock.0.frames.fetch_add(1, Ordering::Relaxed);
+        }
+        Some(sample)
+    }
+}
+
+impl Source for MusicSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.decoder.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.decoder.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.decoder.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        if self.looping {
+            None
+        } else {
+            self.decoder.total_duration()
+        }
+    }
+}
+
+/// Where in a track a callback fires
+enum Cues {
+    /// Every `interval` seconds from `offset`
+    Beats { interval: f64, offset: f64 },
+    /// At these positions, in seconds
+    Markers(Vec<f64>),
+}
+
+struct CueCallback {
+    cues: Cues,
+    callback: Box<dyn FnMut(&MusicCue)>,
+}
+
+impl CueCallback {
+    /// Fire for the cues in `[start, end)` of one pass, with the music now at `now`
+    fn fire(&mut self, start: f64, end: f64, now: f64) {
+        let mut fire = |index: usize, time: f64| {
+            (self.callback)(&MusicCue {
+                index,
+                time: time as f32,
+                late: (now - time) as f32,
+            })
+        };
+        match &self.cues {
+            Cues::Beats { interval, offset } => {
+                let first = ((start - offset) / interval).ceil().max(0.0) as usize;
+                for index in first.. {
+                    let time = offset + index as f64 * interval;
+                    if time >= end {
+                        break;
+                    }
+                    fire(index, time);
+                }
+            }
+            Cues::Markers(times) => {
+                for (index, &time) in times.iter().enumerate() {
+                    if time >= start && time < end {
+                        fire(index, time);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Beat and marker callbacks, and how far they've fired
+#[derive(Default)]
+pub(crate) struct MusicSync {
+    callbacks: Vec<CueCallback>,
+    /// Seconds played at the last update, over all passes
+    reached: f64,
+}
+
+impl MusicSync {
+    /// Start counting from the beginning of a new track
+    pub(crate) fn restart(&mut self) {
+        self.reached = 0.0;
+    }
+
+    /// Fire the callbacks for cues between the last update and `played` seconds
+    fn advance(&mut self, played: f64, length: Option<f64>) {
+        let from = std::mem::replace(&mut self.reached, played);
+        if played <= from {
+            return;
+        }
+        let Some(length) = length else {
+            for callback in &mut self.callbacks {
+                callback.fire(from, played, played);
+            }
+            return;
+        };
+        // Each pass of a looping track fires its cues again
+        let first_pass = (from / length).floor() as u64;
+        let last_pass = (played / length).floor() as u64;
+        for pass in first_pass..=last_pass {
+            let pass_start = pass as f64 * length;
+            let start = (from - pass_start).max(0.0);
+            let end = (played - pass_start).min(length);
+            if start < end {
+                for callback in &mut self.callbacks {
+                    callback.fire(start, end, played - pass_start);
+                }
+            }
+        }
+    }
+}
+
+impl AudioManager {
+    /// Position in the playing music in seconds (`None` without music)
+    ///
+    /// This counts what has been handed to the mix, which runs a few milliseconds
+    /// ahead of what the speakers play. Looping music starts over at 0.
+    pub fn music_position(&self) -> Option<f32> {
+        self.music_clock.as_ref().map(|clock| clock.position() as f32)
+    }
+
+    /// Call `callback` on every beat of the music at `bpm`, the first `offset` seconds in
+    ///
+    /// Callbacks stay registered across tracks until [`AudioManager::clear_music_callbacks`].
+    pub fn on_music_beat(&mut self, bpm: f32, offset: f32, callback: impl FnMut(&MusicCue) + 'static) {
+        if bpm <= 0.0 {
+            log::warn!("Ignoring music beat callback at {} BPM", bpm);
+            return;
+        }
+        self.music_sync.callbacks.push(CueCallback {
+            cues: Cues::Beats {
+                interval: 60.0 / bpm as f64,
+                offset: offset.max(0.0) as f64,
+            },
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Call `callback` when the music reaches each of `markers` (in seconds)
+    pub fn on_music_markers(&mut self, markers: &[f32], callback: impl FnMut(&MusicCue) + 'static) {
+        self.music_sync.callbacks.push(CueCallback {
+            cues: Cues::Markers(markers.iter().map(|&time| time as f64).collect()),
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Remove all beat and marker callbacks
+    pub fn clear_music_callbacks(&mut self) {
+        self.music_sync.callbacks.clear();
+    }
+
+    /// Fire the callbacks for the beats and markers the music passed since the last call
+    pub(crate) fn update_music_sync(&mut self) {
+        if let Some(clock) = &self.music_clock {
+            let (played, length) = clock.progress();
+            self.music_sync.advance(played, length);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_music_source_counts_loops() {
+        let source = AudioSource::from_samples(1000, &[1000; 100]);
+        let clock = MusicClock::default();
+        let music = MusicSource::new(&source, true, clock.clone()).unwrap();
+        assert_eq!(music.take(250).count(), 250);
+
+        let (played, length) = clock.progress();
+        assert!((played - 0.25).abs() < 1e-9);
+        assert_eq!(length, Some(0.1));
+        assert!((clock.position() - 0.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cues_fire_once_per_pass() {
+        let fired = Rc::new(RefCell::new(Vec::new()));
+        let mut sync = MusicSync::default();
+        for (name, cues) in [
+            ("beat", Cues::Beats { interval: 0.5, offset: 0.0 }),
+            ("marker", Cues::Markers(vec![0.25, 1.9])),
+        ] {
+            let fired = fired.clone();
+            sync.callbacks.push(CueCallback {
+                cues,
+                callback: Box::new(move |cue| fired.borrow_mut().push((name, cue.index, cue.late))),
+            });
+        }
+
+        sync.advance(1.2, None);
+        let names: Vec<_> = fired.borrow().iter().map(|&(name, index, _)| (name, index)).collect();
+        assert_eq!(names, [("beat", 0), ("beat", 1), ("beat", 2), ("marker", 0)]);
+        assert!((fired.borrow()[2].2 - 0.2).abs() < 1e-6);
+
+        // The track turns out to be 2 seconds long and loops
+        fired.borrow_mut().clear();
+        sync.advance(2.3, Some(2.0));
+        let names: Vec<_> = fired.borrow().iter().map(|&(name, index, _)| (name, index)).collect();
+        assert_eq!(names, [("beat", 3), ("marker", 1), ("beat", 0), ("marker", 0)]);
+        assert!((fired.borrow()[1].2 - 0.4).abs() < 1e-6);
+        assert!((fired.borrow()[3].2 - 0.05).abs() < 1e-6);
+    }
+}
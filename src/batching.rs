@@ -0,0 +1,235 @@
+//! Static batching
+//!
+//! Entities marked [`StaticMesh`] never move, so meshes sharing a texture and material
+//! can be merged into one world-space mesh and drawn with a single call. Call
+//! [`build_static_batches`] once the level is loaded (and again after editing it); the
+//! merged meshes are drawn by new entities holding a [`StaticBatch`], and the source
+//! entities' `MeshRenderer`s are hidden.
+
+use crate::ecs::{Component, EntityId, Scene};
+use crate::lod::LodGroup;
+use crate::math::Transform;
+use crate::renderer::{MaterialHandle, MeshRenderer};
+use crate::resource::{Mesh, MeshHandle, ResourceManager, TextureHandle};
+use crate::skinning::Skeleton;
+use glam::{Mat4, Vec3};
+use std::collections::BTreeMap;
+
+/// Marks an entity's mesh as never moving, so it can be merged into a static batch
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StaticMesh;
+
+impl Component for StaticMesh {}
+
+/// An entity drawing merged static meshes
+#[derive(Debug, Clone, Default)]
+pub struct StaticBatch {
+    /// Entities whose meshes were merged (their `MeshRenderer`s are hidden)
+    pub sources: Vec<EntityId>,
+}
+
+impl Component for StaticBatch {}
+
+/// Texture, material, and grid cell shared by the meshes of a batch
+type BatchKey = (Option<TextureHandle>, Option<MaterialHandle>, Option<[i32; 3]>);
+
+/// Merge meshes into one, baking each one's model matrix into its vertices
+pub fn merge_meshes(parts: &[(&Mesh, Mat4)]) -> Mesh {
+    let mut vertices = Vec::with_capacity(parts.iter().map(|(mesh, _)| mesh.vertices.len()).sum());
+    let mut indices = Vec::with_capacity(parts.iter().map(|(mesh, _)| mesh.indices.len()).sum());
+
+    for (mesh, model) in parts {
+        // Inverse transpose keeps normals perpendicular under non-uniform scale
+        let normal_matrix = if model.determinant().abs() > f32::EPSILON {
+            model.inverse().transpose()
+        } else {
+            *model
+        };
+        // Mirroring flips the bitangent
+        let handedness = model.determinant().signum();
+
+        let base = vertices.len() as u32;
+        indices.extend(mesh.indices.iter().map(|index| base + index));
+        vertices.extend(mesh.vertices.iter().map(|vertex| {
+            let mut vertex = *vertex;
+            let tangent = model.transform_vector3(Vec3::from_slice(&vertex.tangent)).normalize_or_zero();
+            vertex.position = model.transform_point3(Vec3::from(vertex.position)).to_array();
+            vertex.normal = normal_matrix
+                .transform_vector3(Vec3::from(vertex.normal))
+                .normalize_or_zero()
+                .to_array();
+            vertex.tangent = tangent.extend(vertex.tangent[3] * handedness).to_array();
+            vertex
+        }));
+    }
+
+    Mesh::new(vertices, indices)
+}
+
+/// Merge the meshes of active [`StaticMesh`] entities sharing a texture and material,
+/// returning the number of batches created
+///
+/// With a `cell_size`, meshes are also grouped by grid cell so batches stay small enough
+/// to be frustum culled. Transparent entities and those with an `LodGroup` or `Skeleton`
+/// are left alone, as are groups of a single mesh. Existing batches are cleared first.
+pub fn build_static_batches(scene: &mut Scene, resources: &mut ResourceManager, cell_size: Option<f32>) -> usize {
+    clear_static_batches(scene);
+
+    let mut groups: BTreeMap<BatchKey, Vec<(EntityId, MeshHandle, Mat4)>> = BTreeMap::new();
+    for entity in scene.active_entities() {
+        if !entity.has_component::<StaticMesh>()
+            || entity.has_component::<LodGroup>()
+            || entity.has_component::<Skeleton>()
+        {
+            continue;
+        }
+        let (Some(renderer), Some(transform)) = (
+            entity.get_component::<MeshRenderer>().filter(|r| r.visible && !r.transparent),
+            entity.get_component::<Transform>(),
+        ) else {
+            continue;
+        };
+        let cell = cell_size
+            .filter(|size| *size > 0.0)
+            .map(|size| (transform.position / size).floor().as_ivec3().to_array());
+        groups
+            .entry((renderer.texture, renderer.material, cell))
+            .or_default()
+            .push((entity.id(), renderer.mesh, transform.matrix()));
+    }
+
+    let mut count = 0;
+    for ((texture, material, _), mut members) in groups {
+        if members.len() < 2 {
+            continue;
+        }
+        // Scene iteration order is arbitrary; keep the merged layout stable
+        members.sort_by_key(|(id, _, _)| *id);
+        let parts: Vec<(&Mesh, Mat4)> = members
+            .iter()
+            .filter_map(|(_, mesh, model)| Some((resources.get_mesh(*mesh)?, *model)))
+            .collect();
+        let merged = merge_meshes(&parts);
+        log::debug!(
+            "Static batch {}: {} meshes, {} vertices",
+            count,
+            parts.len(),
+            merged.vertices.len()
+        );
+
+        // Reuse the mesh slot of a previous build
+        let name = format!("static_batch_{}", count);
+        let handle = match resources.get_mesh_handle(&name) {
+            Some(handle) => {
+                if let Some(mesh) = resources.get_mesh_mut(handle) {
+                    *mesh = merged;
+                }
+                handle
+            }
+            None => resources.insert_mesh(name, merged),
+        };
+
+        let sources: Vec<EntityId> = members.iter().map(|(id, _, _)| *id).collect();
+        for id in &sources {
+            if let Some(renderer) = scene.get_component_mut::<MeshRenderer>(*id) {
+                renderer.visible = false;
+            }
+        }
+
+        let batch = scene.create_entity(format!("Static Batch {}", count));
+        if let Some(mut entity) = scene.get_entity_mut(batch) {
+            entity.add_component(Transform::new());
+            entity.add_component(MeshRenderer {
+                mesh: handle,
+                texture,
+                material,
+                visible: true,
+                transparent: false,
+            });
+            entity.add_component(StaticBatch { sources });
+        }
+        count += 1;
+    }
+
+    log::info!("Built {} static batches", count);
+    count
+}
+
+/// Remove the entities created by [`build_static_batches`] and show their sources again
+pub fn clear_static_batches(scene: &mut Scene) {
+    let batches = scene.find_entities_with::<StaticBatch>();
+    for batch in batches {
+        let sources = scene
+            .get_entity(batch)
+            .and_then(|entity| entity.get_component::<StaticBatch>())
+            .map(|batch| batch.sources.clone())
+            .unwrap_or_default();
+        for id in sources {
+            if let Some(renderer) = scene.get_component_mut::<MeshRenderer>(id) {
+                renderer.visible = true;
+            }
+        }
+        scene.remove_entity(batch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::MeshBuilder;
+
+    #[test]
+    fn test_merge_meshes_bakes_transforms() {
+        let quad = MeshBuilder::quad(1.0, 1.0);
+        let offset = Mat4::from_translation(Vec3::new(10.0, 0.0, 0.0));
+        let merged = merge_meshes(&[(&quad, Mat4::IDENTITY), (&quad, offset)]);
+
+        assert_eq!(merged.vertices.len(), quad.vertices.len() * 2);
+        assert_eq!(merged.indices.len(), quad.indices.len() * 2);
+        let base = quad.vertices.len() as u32;
+        assert_eq!(merged.indices[quad.indices.len()], quad.indices[0] + base);
+        assert_eq!(merged.bounds.max.x, quad.bounds.max.x + 10.0);
+
+        // Mirroring flips the tangent's handedness
+        let mirrored = merge_meshes(&[(&quad, Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0)))]);
+        assert_eq!(mirrored.vertices[0].tangent[3], -quad.vertices[0].tangent[3]);
+    }
+
+    #[test]
+    fn test_build_static_batches() {
+        let mut scene = Scene::new("Level".to_string());
+        let mut resources = ResourceManager::new();
+        let cube = resources.insert_mesh("cube".to_string(), MeshBuilder::cube(1.0));
+
+        let mut spawn = |x: f32, texture: Option<TextureHandle>| {
+            let id = scene.create_entity("Prop".to_string());
+            let mut entity = scene.get_entity_mut(id).unwrap();
+            entity.add_component(Transform::from_position(Vec3::new(x, 0.0, 0.0)));
+            entity.add_component(MeshRenderer::new(cube));
+            if let Some(renderer) = entity.get_component_mut::<MeshRenderer>() {
+                renderer.texture = texture;
+            }
+            entity.add_component(StaticMesh);
+            id
+        };
+        let props = [spawn(0.0, None), spawn(2.0, None), spawn(4.0, None)];
+        let lone = spawn(6.0, Some(3));
+
+        assert_eq!(build_static_batches(&mut scene, &mut resources, None), 1);
+        let batch = scene.find_entities_with::<StaticBatch>()[0];
+        let entity = scene.get_entity(batch).unwrap();
+        assert_eq!(entity.get_component::<StaticBatch>().unwrap().sources, props);
+        let merged = resources.get_mesh(entity.get_component::<MeshRenderer>().unwrap().mesh).unwrap();
+        assert_eq!(merged.vertices.len(), 24 * 3);
+
+        let visible = |scene: &Scene, id| scene.get_entity(id).unwrap().get_component::<MeshRenderer>().unwrap().visible;
+        assert!(!visible(&scene, props[0]));
+        assert!(visible(&scene, lone));
+
+        // Rebuilding replaces the batch; splitting by cell leaves only single meshes
+        assert_eq!(build_static_batches(&mut scene, &mut resources, None), 1);
+        assert_eq!(build_static_batches(&mut scene, &mut resources, Some(1.0)), 0);
+        assert!(scene.find_entities_with::<StaticBatch>().is_empty());
+        assert!(props.iter().all(|id| visible(&scene, *id)));
+    }
+}
@@ -0,0 +1,318 @@
+//! Bloom post effect
+//!
+//! Bright parts of the HDR scene are extracted with a soft threshold, blurred by
+//! downsampling through a chain of smaller targets and upsampling back with a tent
+//! filter, then added onto the scene before tone mapping. Enable it by adding
+//! `PostEffect::Bloom` to the renderer's post-processing stack.
+
+use crate::postprocess::HDR_FORMAT;
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+/// Maximum number of targets in the downsample chain
+const MAX_LEVELS: usize = 6;
+
+/// Bloom controls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    /// HDR brightness above which pixels bloom
+    pub threshold: f32,
+    /// Width of the soft transition below the threshold (0 = hard cutoff)
+    pub knee: f32,
+    /// Strength of the glow added onto the scene
+    pub intensity: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.3,
+        }
+    }
+}
+
+/// Bloom uniform buffer data
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct BloomUniform {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    _padding: f32,
+}
+
+/// Get the sizes of the downsample chain, starting at half resolution
+fn level_sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let mut sizes = Vec::new();
+    let (mut w, mut h) = (width / 2, height / 2);
+    while sizes.len() < MAX_LEVELS && w >= 4 && h >= 4 {
+        sizes.push((w, h));
+        w /= 2;
+        h /= 2;
+    }
+    if sizes.is_empty() {
+        sizes.push((w.max(1), h.max(1)));
+    }
+    sizes
+}
+
+/// A level of the downsample chain with a bind group sampling it
+struct BloomLevel {
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+/// GPU pipelines and targets for the bloom passes
+pub(crate) struct BloomPipeline {
+    prefilter: wgpu::RenderPipeline,
+    downsample: wgpu::RenderPipeline,
+    upsample: wgpu::RenderPipeline,
+    composite: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    buffer: wgpu::Buffer,
+    source_bind_group: wgpu::BindGroup,
+    levels: Vec<BloomLevel>,
+}
+
+impl BloomPipeline {
+    pub(crate) fn new(device: &wgpu::Device, source: &wgpu::TextureView, width: u32, height: u32) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Bloom Buffer"),
+            contents: bytemuck::cast_slice(&[BloomUniform::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bloom_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Bloom Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Bloom Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/bloom.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::OVER,
+        };
+        let create_pipeline = |label: &str, entry_point: &str, blend: Option<wgpu::BlendState>| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: HDR_FORMAT,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        let prefilter = create_pipeline("Bloom Prefilter Pipeline", "fs_prefilter", None);
+        let downsample = create_pipeline("Bloom Downsample Pipeline", "fs_downsample", None);
+        let upsample = create_pipeline("Bloom Upsample Pipeline", "fs_upsample", Some(additive));
+        let composite = create_pipeline("Bloom Composite Pipeline", "fs_composite", Some(additive));
+
+        let source_bind_group = Self::create_bind_group(device, &layout, &sampler, &buffer, source);
+        let mut pipeline = Self {
+            prefilter,
+            downsample,
+            upsample,
+            composite,
+            layout,
+            sampler,
+            buffer,
+            source_bind_group,
+            levels: Vec::new(),
+        };
+        pipeline.resize(device, source, width, height);
+        pipeline
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        buffer: &wgpu::Buffer,
+        source: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bloom_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// Recreate the downsample chain for a new HDR target
+    pub(crate) fn resize(&mut self, device: &wgpu::Device, source: &wgpu::TextureView, width: u32, height: u32) {
+        self.source_bind_group = Self::create_bind_group(device, &self.layout, &self.sampler, &self.buffer, source);
+        self.levels = level_sizes(width, height)
+            .into_iter()
+            .map(|(w, h)| {
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Bloom Texture"),
+                    size: wgpu::Extent3d {
+                        width: w,
+                        height: h,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: HDR_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                });
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                let bind_group = Self::create_bind_group(device, &self.layout, &self.sampler, &self.buffer, &view);
+                BloomLevel { view, bind_group }
+            })
+            .collect();
+    }
+
+    /// Upload the bloom settings for this frame
+    pub(crate) fn update(&self, queue: &wgpu::Queue, settings: &BloomSettings) {
+        let uniform = BloomUniform {
+            threshold: settings.threshold,
+            knee: settings.knee,
+            intensity: settings.intensity,
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Record every bloom pass, adding the glow onto `target` (the HDR scene color)
+    pub(crate) fn draw(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let pass = |encoder: &mut wgpu::CommandEncoder,
+                    label: &str,
+                    view: &wgpu::TextureView,
+                    load: wgpu::LoadOp<wgpu::Color>,
+                    pipeline: &wgpu::RenderPipeline,
+                    source: &wgpu::BindGroup| {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, source, &[]);
+            pass.draw(0..3, 0..1);
+        };
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+
+        let Some(first) = self.levels.first() else {
+            return;
+        };
+        pass(encoder, "Bloom Prefilter Pass", &first.view, clear, &self.prefilter, &self.source_bind_group);
+        for pair in self.levels.windows(2) {
+            pass(encoder, "Bloom Downsample Pass", &pair[1].view, clear, &self.downsample, &pair[0].bind_group);
+        }
+        for pair in self.levels.windows(2).rev() {
+            pass(encoder, "Bloom Upsample Pass", &pair[0].view, wgpu::LoadOp::Load, &self.upsample, &pair[1].bind_group);
+        }
+        pass(encoder, "Bloom Composite Pass", target, wgpu::LoadOp::Load, &self.composite, &first.bind_group);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_level_sizes() {
+        let sizes = level_sizes(1280, 720);
+        assert_eq!(sizes.len(), MAX_LEVELS);
+        assert_eq!(sizes[0], (640, 360));
+        assert_eq!(sizes[5], (20, 11));
+
+        // Tiny targets still get one level
+        assert_eq!(level_sizes(6, 6), vec![(3, 3)]);
+        assert_eq!(level_sizes(1, 1), vec![(1, 1)]);
+    }
+}
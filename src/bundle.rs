@@ -0,0 +1,143 @@
+//! Component bundles
+//!
+//! A [`Bundle`] is a group of components inserted together, so an entity can be
+//! spawned in one call instead of creating it and adding each component. Single
+//! components and tuples of up to twelve components are bundles, and structs can
+//! derive the trait:
+//!
+//! ```ignore
+//! let id = scene.spawn((Transform::new(), Velocity(Vec3::X), Health(3)));
+//!
+//! #[derive(Bundle)]
+//! struct EnemyBundle {
+//!     transform: Transform,
+//!     health: Health,
+//! }
+//!
+//! let id = scene.spawn_named("Enemy", EnemyBundle { transform, health: Health(3) });
+//! ```
+
+use crate::ecs::{Component, EntityId, EntityMut, Scene};
+
+pub use rgame_derive::Bundle;
+
+/// A group of components added to an entity at once
+pub trait Bundle {
+    /// Add every component of the bundle to an entity
+    fn insert_into(self, entity: &mut EntityMut);
+}
+
+impl<T: Component> Bundle for T {
+    fn insert_into(self, entity: &mut EntityMut) {
+        entity.add_component(self);
+    }
+}
+
+macro_rules! impl_bundle_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Component),+> Bundle for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn insert_into(self, entity: &mut EntityMut) {
+                let ($($name,)+) = self;
+                $(entity.add_component($name);)+
+            }
+        }
+    };
+}
+
+impl_bundle_tuple!(A);
+impl_bundle_tuple!(A, B);
+impl_bundle_tuple!(A, B, C);
+impl_bundle_tuple!(A, B, C, D);
+impl_bundle_tuple!(A, B, C, D, E);
+impl_bundle_tuple!(A, B, C, D, E, F);
+impl_bundle_tuple!(A, B, C, D, E, F, G);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H, I);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+impl Scene {
+    /// Create an entity named "Entity" with the components of a bundle
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> EntityId {
+        self.spawn_named("Entity", bundle)
+    }
+
+    /// Create a named entity with the components of a bundle
+    pub fn spawn_named<B: Bundle>(&mut self, name: &str, bundle: B) -> EntityId {
+        let id = self.create_entity(name.to_string());
+        if let Some(mut entity) = self.get_entity_mut(id) {
+            bundle.insert_into(&mut entity);
+        }
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::Component;
+    use crate::math::Transform;
+    use crate::registry::ComponentRegistry;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[component(name = "health")]
+    struct Health(i32);
+
+    #[derive(Component, Debug, PartialEq)]
+    struct Speed(f32);
+
+    #[derive(Bundle)]
+    struct Mover {
+        transform: Transform,
+        speed: Speed,
+    }
+
+    #[derive(Bundle)]
+    struct Enemy {
+        health: Health,
+        #[bundle]
+        mover: Mover,
+    }
+
+    #[test]
+    fn test_spawn_tuple() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.spawn((Transform::new(), Speed(1.5), Health(2)));
+        let entity = scene.get_entity(id).unwrap();
+        assert_eq!(entity.name(), "Entity");
+        assert_eq!(entity.get_component::<Speed>(), Some(&Speed(1.5)));
+        assert_eq!(entity.get_component::<Health>(), Some(&Health(2)));
+
+        let single = scene.spawn_named("Marker", Speed(0.0));
+        assert!(scene.get_entity(single).unwrap().has_component::<Speed>());
+        assert_eq!(scene.query::<(&Transform, &Speed)>().count(), 1);
+    }
+
+    #[test]
+    fn test_spawn_derived_bundle() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.spawn_named(
+            "Enemy",
+            Enemy {
+                health: Health(3),
+                mover: Mover {
+                    transform: Transform::new(),
+                    speed: Speed(2.0),
+                },
+            },
+        );
+
+        let entity = scene.get_entity(id).unwrap();
+        assert_eq!(entity.name(), "Enemy");
+        assert_eq!(entity.get_component::<Health>(), Some(&Health(3)));
+        assert_eq!(entity.get_component::<Speed>(), Some(&Speed(2.0)));
+        assert!(entity.has_component::<Transform>());
+
+        let mut registry = ComponentRegistry::new();
+        registry.register_named::<Health>();
+        assert_eq!(registry.name_of::<Health>(), Some("health"));
+    }
+}
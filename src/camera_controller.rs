@@ -0,0 +1,296 @@
+//! Camera controllers driven by input
+//!
+//! [`FlyCameraController`] flies a [`Camera`] freely like a first-person editor camera:
+//! WASD or the arrow keys move, E/Space and Q rise and sink, Shift boosts, the mouse
+//! looks around while the right button is held, and the scroll wheel changes speed.
+//! Set one on the engine with `Engine::set_camera_controller` to drive the main camera
+//! every frame, or call `update` yourself for other cameras.
+//!
+//! [`FollowCamera`] and [`OrbitCamera`] are components instead: put one on a camera
+//! entity and the engine points the main camera at the target entity after game logic.
+
+use crate::ecs::{Component, EntityId, Scene};
+use crate::input::{InputManager, Key, MouseButton};
+use crate::math::Transform;
+use crate::renderer::Camera;
+use glam::Vec3;
+
+/// Pitch limit short of straight up or down, where the view direction would flip
+const MAX_PITCH: f32 = 89.0_f32.to_radians();
+
+/// Speed multiplier per scroll wheel line
+const SCROLL_SPEED_FACTOR: f32 = 1.2;
+
+/// Free-flying first-person camera controller
+#[derive(Debug, Clone)]
+pub struct FlyCameraController {
+    /// Movement speed in units per second
+    pub speed: f32,
+    /// Speed range the scroll wheel stays within
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Speed multiplier while Shift is held
+    pub boost: f32,
+    /// Degrees turned per pixel of mouse movement
+    pub sensitivity: f32,
+    /// Mouse button held to look around, or `None` to always look (e.g. with a grabbed cursor)
+    pub look_button: Option<MouseButton>,
+    /// Heading in radians, 0 looking down -Z and increasing to the left
+    yaw: f32,
+    /// Elevation in radians, positive looking up
+    pitch: f32,
+}
+
+impl FlyCameraController {
+    /// Create a controller looking the same way as `camera`
+    pub fn new(camera: &Camera) -> Self {
+        let mut controller = Self {
+            speed: 5.0,
+            min_speed: 0.1,
+            max_speed: 500.0,
+            boost: 4.0,
+            sensitivity: 0.15,
+            look_button: Some(MouseButton::Right),
+            yaw: 0.0,
+            pitch: 0.0,
+        };
+        controller.look_at(camera.position, camera.target);
+        controller
+    }
+
+    /// Heading in radians, 0 looking down -Z and increasing to the left
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    /// Elevation in radians, positive looking up
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Point the controller from `position` towards `target`
+    pub fn look_at(&mut self, position: Vec3, target: Vec3) {
+        let direction = (target - position).normalize_or_zero();
+        if direction == Vec3::ZERO {
+            return;
+        }
+        self.yaw = (-direction.x).atan2(-direction.z);
+        self.pitch = direction.y.clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Unit view direction
+    pub fn forward(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        Vec3::new(-sin_yaw * cos_pitch, sin_pitch, -cos_yaw * cos_pitch)
+    }
+
+    /// Apply this frame's input to `camera`, `delta` seconds after the last update
+    pub fn update(&mut self, camera: &mut Camera, input: &InputManager, delta: f32) {
+        if self.look_button.is_none_or(|button| input.mouse_button_pressed(button)) {
+            let turn = input.mouse_delta() * self.sensitivity.to_radians();
+            self.yaw = (self.yaw - turn.x).rem_euclid(std::f32::consts::TAU);
+            self.pitch = (self.pitch - turn.y).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        let scroll = input.scroll_delta();
+        if scroll != 0.0 {
+            self.speed = (self.speed * SCROLL_SPEED_FACTOR.powf(scroll)).clamp(self.min_speed, self.max_speed);
+        }
+
+        let forward = self.forward();
+        let right = Vec3::new(-forward.z, 0.0, forward.x).normalize_or_zero();
+        let mut movement = forward * input.axis_vertical() + right * input.axis_horizontal();
+        if input.key_pressed(Key::KeyE) || input.key_pressed(Key::Space) {
+            movement += Vec3::Y;
+        }
+        if input.key_pressed(Key::KeyQ) {
+            movement -= Vec3::Y;
+        }
+        let mut speed = self.speed;
+        if input.key_pressed(Key::ShiftLeft) || input.key_pressed(Key::ShiftRight) {
+            speed *= self.boost;
+        }
+
+        camera.position += movement.normalize_or_zero() * speed * delta;
+        camera.target = camera.position + forward;
+        camera.up = Vec3::Y;
+    }
+}
+
+/// Follows an entity from an offset that turns with it, catching up smoothly
+#[derive(Debug, Clone)]
+pub struct FollowCamera {
+    pub target: EntityId,
+    /// Camera position relative to the target, in the target's local space
+    pub offset: Vec3,
+    /// How quickly the camera catches up, per second (0 sticks to the target)
+    pub smoothing: f32,
+}
+
+impl Component for FollowCamera {}
+
+impl FollowCamera {
+    /// Follow `target` from behind and above
+    pub fn new(target: EntityId) -> Self {
+        Self {
+            target,
+            offset: Vec3::new(0.0, 3.0, 8.0),
+            smoothing: 5.0,
+        }
+    }
+
+    /// Move `camera` towards its place behind `target`, looking at it
+    pub fn apply(&self, camera: &mut Camera, target: &Transform, delta: f32) {
+        let desired = target.position + target.rotation * self.offset;
+        let t = if self.smoothing > 0.0 { 1.0 - (-self.smoothing * delta).exp() } else { 1.0 };
+        camera.position = camera.position.lerp(desired, t);
+        camera.target = target.position;
+        camera.up = Vec3::Y;
+    }
+}
+
+/// Circles an entity at a distance, with the angles set by game logic
+#[derive(Debug, Clone)]
+pub struct OrbitCamera {
+    pub target: EntityId,
+    pub distance: f32,
+    /// Angle around the target in radians, 0 placing the camera on its +Z side
+    pub yaw: f32,
+    /// Elevation above the target in radians, positive looking down at it
+    pub pitch: f32,
+}
+
+impl Component for OrbitCamera {}
+
+impl OrbitCamera {
+    /// Orbit `target` at `distance`, slightly above it
+    pub fn new(target: EntityId, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 30.0_f32.to_radians(),
+        }
+    }
+
+    /// Place `camera` on the orbit around `target`, looking at it
+    pub fn apply(&self, camera: &mut Camera, target: &Transform) {
+        let pitch = self.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = pitch.sin_cos();
+        let direction = Vec3::new(sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch);
+        camera.position = target.position + direction * self.distance.max(0.0);
+        camera.target = target.position;
+        camera.up = Vec3::Y;
+    }
+}
+
+/// Drive `camera` from the camera entity with a `FollowCamera` or `OrbitCamera`
+/// (the lowest id if there are several); returns whether one with a target was found
+pub fn update_scene_camera(scene: &Scene, camera: &mut Camera, delta: f32) -> bool {
+    let target_transform = |id| scene.get_entity(id)?.get_component::<Transform>();
+    let mut cameras: Vec<_> = scene
+        .active_entities()
+        .filter(|entity| entity.has_component::<FollowCamera>() || entity.has_component::<OrbitCamera>())
+        .collect();
+    cameras.sort_by_key(|entity| entity.id());
+    for entity in cameras {
+        if let Some(follow) = entity.get_component::<FollowCamera>() {
+            if let Some(target) = target_transform(follow.target) {
+                follow.apply(camera, target, delta);
+                return true;
+            }
+        }
+        if let Some(orbit) = entity.get_component::<OrbitCamera>() {
+            if let Some(target) = target_transform(orbit.target) {
+                orbit.apply(camera, target);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Quat;
+    use winit::event::ElementState;
+
+    #[test]
+    fn test_look_at_round_trips() {
+        let camera = Camera::new(Vec3::new(0.0, 2.0, 5.0), Vec3::new(5.0, 2.0, 5.0), 1.0);
+        let controller = FlyCameraController::new(&camera);
+        assert!(controller.forward().distance(Vec3::X) < 1e-5);
+
+        // Looking straight down stops at the pitch limit
+        let mut controller = FlyCameraController::new(&Camera::new(Vec3::Y, Vec3::ZERO, 1.0));
+        assert_eq!(controller.pitch(), -MAX_PITCH);
+        controller.look_at(Vec3::ZERO, Vec3::new(0.0, 0.0, -1.0));
+        assert!(controller.forward().distance(Vec3::NEG_Z) < 1e-5);
+    }
+
+    #[test]
+    fn test_mouse_look_and_scroll_speed() {
+        let mut camera = Camera::new(Vec3::ZERO, Vec3::NEG_Z, 1.0);
+        let mut controller = FlyCameraController::new(&camera);
+        controller.sensitivity = 1.0;
+        let mut input = InputManager::new();
+
+        // Without the look button held, moving the mouse doesn't turn
+        input.handle_mouse_motion((90.0, 0.0));
+        controller.update(&mut camera, &input, 0.016);
+        assert!(controller.forward().distance(Vec3::NEG_Z) < 1e-5);
+
+        // Moving the mouse right turns right, and up is clamped short of vertical
+        input.update();
+        input.handle_mouse_motion((90.0, 0.0));
+        input.handle_mouse_button(MouseButton::Right, ElementState::Pressed);
+        input.handle_mouse_motion((0.0, -180.0));
+        controller.update(&mut camera, &input, 0.016);
+        assert!(camera.target.distance(camera.position + Vec3::X * MAX_PITCH.cos() + Vec3::Y * MAX_PITCH.sin()) < 1e-4);
+        assert_eq!(controller.pitch(), MAX_PITCH);
+
+        input.update();
+        input.handle_scroll(2.0);
+        controller.update(&mut camera, &input, 0.016);
+        assert!((controller.speed - 5.0 * 1.44).abs() < 1e-4);
+        assert_eq!(camera.position, Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_follow_and_orbit_cameras() {
+        let mut scene = Scene::new("Test".to_string());
+        let player = scene.create_entity("Player".to_string());
+        let mut transform = Transform::from_position(Vec3::new(10.0, 0.0, 0.0));
+        transform.rotation = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        scene.get_entity_mut(player).unwrap().add_component(transform);
+        let mut camera = Camera::new(Vec3::ZERO, Vec3::NEG_Z, 1.0);
+        assert!(!update_scene_camera(&scene, &mut camera, 0.1));
+
+        // Without smoothing the camera sits behind the target, turned with it
+        let rig = scene.create_entity("Camera".to_string());
+        let mut follow = FollowCamera::new(player);
+        follow.smoothing = 0.0;
+        scene.get_entity_mut(rig).unwrap().add_component(follow);
+        assert!(update_scene_camera(&scene, &mut camera, 0.1));
+        assert!(camera.position.distance(Vec3::new(18.0, 3.0, 0.0)) < 1e-4);
+        assert_eq!(camera.target, Vec3::new(10.0, 0.0, 0.0));
+
+        // Smoothing only closes part of the gap each frame
+        scene.get_entity_mut(rig).unwrap().get_component_mut::<FollowCamera>().unwrap().smoothing = 5.0;
+        camera.position = Vec3::ZERO;
+        update_scene_camera(&scene, &mut camera, 0.1);
+        let expected = Vec3::new(18.0, 3.0, 0.0) * (1.0 - (-0.5f32).exp());
+        assert!(camera.position.distance(expected) < 1e-4);
+
+        let mut orbit = OrbitCamera::new(player, 4.0);
+        orbit.yaw = std::f32::consts::FRAC_PI_2;
+        orbit.pitch = 0.0;
+        let mut orbiter = scene.get_entity_mut(rig).unwrap();
+        orbiter.remove_component::<FollowCamera>();
+        orbiter.add_component(orbit);
+        update_scene_camera(&scene, &mut camera, 0.1);
+        assert!(camera.position.distance(Vec3::new(14.0, 0.0, 0.0)) < 1e-4);
+    }
+}
@@ -0,0 +1,490 @@
+//! Video and GIF capture
+//!
+//! [`FrameCapture`] receives RGBA frames and encodes them on a background
+//! thread to a PNG sequence, an animated GIF, or an MP4 (by piping raw frames
+//! into an `ffmpeg` executable). The renderer copies the swapchain image into
+//! a capture while one is running; see `Renderer::start_capture`.
+//!
+//! Reading frames back from the GPU waits for the frame to finish rendering,
+//! so expect a lower frame rate while recording.
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, RgbaImage};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Output format of a capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat {
+    /// Numbered PNG files in a directory
+    ImageSequence,
+    /// Looping animated GIF
+    Gif,
+    /// H.264 MP4 encoded by an external `ffmpeg`
+    Mp4,
+}
+
+impl CaptureFormat {
+    fn extension(&self) -> Option<&'static str> {
+        match self {
+            CaptureFormat::ImageSequence => None,
+            CaptureFormat::Gif => Some("gif"),
+            CaptureFormat::Mp4 => Some("mp4"),
+        }
+    }
+}
+
+/// Capture settings
+#[derive(Debug, Clone)]
+pub struct CaptureConfig {
+    pub format: CaptureFormat,
+    /// Directory clips are written to
+    pub output_dir: PathBuf,
+    /// Frames per second of the clip; rendered frames are sampled at this rate
+    pub fps: u32,
+    /// Downscale frames wider than this (useful to keep GIFs small)
+    pub max_width: Option<u32>,
+    /// Path or name of the ffmpeg executable used for MP4
+    pub ffmpeg: String,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            format: CaptureFormat::Mp4,
+            output_dir: PathBuf::from("captures"),
+            fps: 30,
+            max_width: None,
+            ffmpeg: "ffmpeg".to_string(),
+        }
+    }
+}
+
+/// A captured frame in tightly packed RGBA8
+#[derive(Debug, Clone)]
+pub struct CaptureFrame {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Encoder state owned by the capture thread
+enum Encoder {
+    Images { dir: PathBuf, next: u32 },
+    Gif { encoder: GifEncoder<BufWriter<File>>, delay: Delay },
+    Ffmpeg {
+        program: String,
+        fps: u32,
+        output: PathBuf,
+        /// Started on the first frame, once the size is known
+        process: Option<(Child, u32, u32)>,
+    },
+}
+
+impl Encoder {
+    fn open(config: &CaptureConfig, path: &Path) -> Result<Self, String> {
+        match config.format {
+            CaptureFormat::ImageSequence => {
+                std::fs::create_dir_all(path)
+                    .map_err(|e| format!("Failed to create capture directory: {}", e))?;
+                Ok(Encoder::Images {
+                    dir: path.to_path_buf(),
+                    next: 0,
+                })
+            }
+            CaptureFormat::Gif => {
+                let file = File::create(path).map_err(|e| format!("Failed to create GIF file: {}", e))?;
+                let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), 10);
+                encoder
+                    .set_repeat(Repeat::Infinite)
+                    .map_err(|e| format!("Failed to write GIF header: {}", e))?;
+                Ok(Encoder::Gif {
+                    encoder,
+                    delay: Delay::from_numer_denom_ms(1000, config.fps.max(1)),
+                })
+            }
+            CaptureFormat::Mp4 => Ok(Encoder::Ffmpeg {
+                program: config.ffmpeg.clone(),
+                fps: config.fps.max(1),
+                output: path.to_path_buf(),
+                process: None,
+            }),
+        }
+    }
+
+    fn write(&mut self, frame: CaptureFrame) -> Result<(), String> {
+        let image = RgbaImage::from_raw(frame.width, frame.height, frame.rgba)
+            .ok_or_else(|| "Captured frame has the wrong size".to_string())?;
+
+        match self {
+            Encoder::Images { dir, next } => {
+                let path = dir.join(format!("frame_{:05}.png", next));
+                image.save(&path).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+                *next += 1;
+            }
+            Encoder::Gif { encoder, delay } => {
+                encoder
+                    .encode_frame(image::Frame::from_parts(image, 0, 0, *delay))
+                    .map_err(|e| format!("Failed to encode GIF frame: {}", e))?;
+            }
+            Encoder::Ffmpeg { program, fps, output, process } => {
+                if process.is_none() {
+                    let child = Command::new(program.as_str())
+                        .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba"])
+                        .arg("-s")
+                        .arg(format!("{}x{}", image.width(), image.height()))
+                        .arg("-r")
+                        .arg(fps.to_string())
+                        .args(["-i", "-", "-c:v", "libx264", "-pix_fmt", "yuv420p"])
+                        // yuv420p needs even dimensions
+                        .args(["-vf", "crop=trunc(iw/2)*2:trunc(ih/2)*2", "-movflags", "+faststart"])
+                        .arg(output.as_path())
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::null())
+                        .spawn()
+                        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+                    *process = Some((child, image.width(), image.height()));
+                }
+                let (child, width, height) = process.as_mut().unwrap();
+                // ffmpeg can't change resolution mid-stream
+                let image = if image.dimensions() != (*width, *height) {
+                    image::imageops::resize(&image, *width, *height, image::imageops::FilterType::Triangle)
+                } else {
+                    image
+                };
+                child
+                    .stdin
+                    .as_mut()
+                    .ok_or_else(|| "ffmpeg stdin is closed".to_string())?
+                    .write_all(image.as_raw())
+                    .map_err(|e| format!("Failed to send frame to ffmpeg: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            Encoder::Images { .. } => Ok(()),
+            Encoder::Gif { encoder, .. } => {
+                // Dropping the encoder writes the GIF trailer and flushes the file
+                drop(encoder);
+                Ok(())
+            }
+            Encoder::Ffmpeg { process, .. } => {
+                let Some((mut child, _, _)) = process else {
+                    return Ok(());
+                };
+                drop(child.stdin.take());
+                let status = child.wait().map_err(|e| format!("Failed to wait for ffmpeg: {}", e))?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(format!("ffmpeg exited with {}", status))
+                }
+            }
+        }
+    }
+}
+
+/// A capture in progress
+struct Recording {
+    path: PathBuf,
+    sender: SyncSender<CaptureFrame>,
+    worker: JoinHandle<Result<u32, String>>,
+    started: Instant,
+    last_frame: Option<Instant>,
+}
+
+/// Encodes frames into a clip on a background thread
+pub struct FrameCapture {
+    config: CaptureConfig,
+    recording: Option<Recording>,
+}
+
+impl FrameCapture {
+    /// Create a new, stopped capture
+    pub fn new(config: CaptureConfig) -> Self {
+        Self { config, recording: None }
+    }
+
+    /// Get the capture settings
+    pub fn config(&self) -> &CaptureConfig {
+        &self.config
+    }
+
+    /// Check if a clip is being recorded
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Get how long the current clip has been recording
+    pub fn elapsed(&self) -> Duration {
+        self.recording.as_ref().map_or(Duration::ZERO, |r| r.started.elapsed())
+    }
+
+    /// Start a new clip, returning the path it will be written to
+    pub fn start(&mut self) -> Result<PathBuf, String> {
+        if self.recording.is_some() {
+            return Err("Capture is already recording".to_string());
+        }
+
+        std::fs::create_dir_all(&self.config.output_dir)
+            .map_err(|e| format!("Failed to create capture directory: {}", e))?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let mut path = self.config.output_dir.join(format!("capture_{}", timestamp));
+        if let Some(extension) = self.config.format.extension() {
+            path.set_extension(extension);
+        }
+
+        let mut encoder = Encoder::open(&self.config, &path)?;
+        let max_width = self.config.max_width;
+        let (sender, receiver) = sync_channel::<CaptureFrame>(8);
+        let worker = std::thread::Builder::new()
+            .name("rgame-capture".to_string())
+            .spawn(move || {
+                let mut frames = 0;
+                for frame in receiver {
+                    encoder.write(downscale(frame, max_width))?;
+                    frames += 1;
+                }
+                encoder.finish()?;
+                Ok(frames)
+            })
+            .map_err(|e| format!("Failed to start capture thread: {}", e))?;
+
+        log::info!("Started capture to {:?}", path);
+        self.recording = Some(Recording {
+            path: path.clone(),
+            sender,
+            worker,
+            started: Instant::now(),
+            last_frame: None,
+        });
+        Ok(path)
+    }
+
+    /// Finish the current clip, returning its path
+    pub fn stop(&mut self) -> Result<PathBuf, String> {
+        let recording = self
+            .recording
+            .take()
+            .ok_or_else(|| "Capture is not recording".to_string())?;
+        drop(recording.sender);
+        let frames = recording
+            .worker
+            .join()
+            .map_err(|_| "Capture thread panicked".to_string())??;
+        log::info!("Captured {} frames to {:?}", frames, recording.path);
+        Ok(recording.path)
+    }
+
+    /// Check if enough time has passed since the last frame to capture another
+    pub fn wants_frame(&self) -> bool {
+        let interval = Duration::from_secs_f64(1.0 / self.config.fps.max(1) as f64);
+        self.recording
+            .as_ref()
+            .is_some_and(|r| r.last_frame.is_none_or(|last| last.elapsed() >= interval))
+    }
+
+    /// Queue a frame for encoding
+    ///
+    /// Blocks when the encoder falls behind rather than dropping frames.
+    pub fn push_frame(&mut self, frame: CaptureFrame) -> Result<(), String> {
+        let recording = self
+            .recording
+            .as_mut()
+            .ok_or_else(|| "Capture is not recording".to_string())?;
+        recording.last_frame = Some(Instant::now());
+        if recording.sender.send(frame).is_err() {
+            // The worker stopped early; surface its error
+            return self.stop().map(|_| ());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FrameCapture {
+    fn drop(&mut self) {
+        if self.recording.is_some() {
+            if let Err(e) = self.stop() {
+                log::warn!("Failed to finish capture: {}", e);
+            }
+        }
+    }
+}
+
+fn downscale(frame: CaptureFrame, max_width: Option<u32>) -> CaptureFrame {
+    let Some(max_width) = max_width.filter(|w| frame.width > *w && *w > 0) else {
+        return frame;
+    };
+    let height = ((frame.height as u64 * max_width as u64) / frame.width as u64).max(1) as u32;
+    let Some(image) = RgbaImage::from_raw(frame.width, frame.height, frame.rgba) else {
+        return CaptureFrame { width: 0, height: 0, rgba: Vec::new() };
+    };
+    let resized = image::imageops::resize(&image, max_width, height, image::imageops::FilterType::Triangle);
+    CaptureFrame {
+        width: max_width,
+        height,
+        rgba: resized.into_raw(),
+    }
+}
+
+/// Copies a texture into a mappable buffer and reads it back as RGBA8
+pub(crate) struct CaptureReadback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+}
+
+impl CaptureReadback {
+    pub(crate) fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (width * 4).div_ceil(align) * align;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Readback Buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+        }
+    }
+
+    pub(crate) fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Check if frames in a texture format can be converted to RGBA8
+    pub(crate) fn supports(format: wgpu::TextureFormat) -> bool {
+        matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        )
+    }
+
+    /// Record a copy of the texture into the readback buffer
+    pub(crate) fn copy(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Wait for the copy to finish and read the frame
+    pub(crate) fn read(&self, device: &wgpu::Device, format: wgpu::TextureFormat) -> Result<CaptureFrame, String> {
+        let slice = self.buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|e| format!("Failed to read back frame: {}", e))?
+            .map_err(|e| format!("Failed to read back frame: {}", e))?;
+
+        let row_bytes = (self.width * 4) as usize;
+        let mut rgba = Vec::with_capacity(row_bytes * self.height as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                rgba.extend_from_slice(&row[..row_bytes]);
+            }
+        }
+        self.buffer.unmap();
+
+        if matches!(
+            format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Ok(CaptureFrame {
+            width: self.width,
+            height: self.height,
+            rgba,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_frame(shade: u8) -> CaptureFrame {
+        CaptureFrame {
+            width: 64,
+            height: 32,
+            rgba: vec![shade; 64 * 32 * 4],
+        }
+    }
+
+    #[test]
+    fn test_capture_image_sequence_and_gif() {
+        let dir = std::env::temp_dir().join(format!("rgame_capture_test_{}", std::process::id()));
+
+        let mut capture = FrameCapture::new(CaptureConfig {
+            format: CaptureFormat::ImageSequence,
+            output_dir: dir.clone(),
+            max_width: Some(32),
+            ..Default::default()
+        });
+        assert!(!capture.wants_frame());
+        capture.start().unwrap();
+        assert!(capture.wants_frame());
+        capture.push_frame(test_frame(0)).unwrap();
+        capture.push_frame(test_frame(255)).unwrap();
+        let frames = capture.stop().unwrap();
+        let second = image::open(frames.join("frame_00001.png")).unwrap();
+        assert_eq!((second.width(), second.height()), (32, 16));
+
+        let mut capture = FrameCapture::new(CaptureConfig {
+            format: CaptureFormat::Gif,
+            output_dir: dir.clone(),
+            ..Default::default()
+        });
+        capture.start().unwrap();
+        capture.push_frame(test_frame(128)).unwrap();
+        let gif = capture.stop().unwrap();
+        assert!(std::fs::read(&gif).unwrap().starts_with(b"GIF89a"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,336 @@
+//! Color grading with 3D lookup tables
+//!
+//! A [`ColorLut`] maps every display color to a graded one. LUTs are authored as the
+//! usual PNG strips: `size` square slices side by side (1024x32 for a 32³ LUT), red
+//! increasing to the right within a slice, green downwards, and blue from slice to
+//! slice. Export [`ColorLut::identity`] with [`ColorLut::to_strip`], grade it next to a
+//! screenshot in an image editor, and load the result back.
+//!
+//! LUTs added to the renderer are applied in the tone mapping pass, after the curve, to
+//! the sRGB-encoded colors they were authored against. [`ColorGrading`] picks the active
+//! LUT and can blend towards a second one, e.g. to shift the mood from day to night.
+
+use glam::Vec3;
+use image::RgbaImage;
+use std::path::Path;
+
+/// Handle to a LUT added with `Renderer::add_color_lut`
+pub type ColorLutHandle = usize;
+
+/// Edge length of the LUTs created by [`ColorLut::identity`] by default
+pub const DEFAULT_LUT_SIZE: u32 = 32;
+
+/// A color lookup table of `size`³ RGBA8 texels
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorLut {
+    size: u32,
+    /// Texels with red varying fastest, then green, then blue
+    texels: Vec<[u8; 4]>,
+}
+
+impl ColorLut {
+    /// Create a LUT that leaves colors unchanged
+    pub fn identity(size: u32) -> Self {
+        let size = size.max(2);
+        let scale = 255.0 / (size - 1) as f32;
+        let level = |i: u32| (i as f32 * scale).round() as u8;
+        let mut texels = Vec::with_capacity((size * size * size) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    texels.push([level(r), level(g), level(b), 255]);
+                }
+            }
+        }
+        Self { size, texels }
+    }
+
+    /// Read a LUT from a strip of `size` slices, `size * size` pixels wide and `size` high
+    pub fn from_strip(image: &RgbaImage) -> Result<Self, String> {
+        let (width, height) = image.dimensions();
+        if height < 2 || width != height * height {
+            return Err(format!(
+                "Invalid LUT strip: {}x{} (expected N*N x N, e.g. 1024x32)",
+                width, height
+            ));
+        }
+        let size = height;
+        let mut texels = Vec::with_capacity((size * size * size) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    texels.push(image.get_pixel(b * size + r, g).0);
+                }
+            }
+        }
+        Ok(Self { size, texels })
+    }
+
+    /// Load a LUT strip from a PNG (or any other image format)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let image = image::open(path.as_ref())
+            .map_err(|e| format!("Failed to load color LUT: {}", e))?
+            .into_rgba8();
+        let lut = Self::from_strip(&image)?;
+        log::info!("Loaded color LUT: {:?} ({}³)", path.as_ref(), lut.size);
+        Ok(lut)
+    }
+
+    /// Lay the LUT out as a strip, the format `from_strip` reads
+    pub fn to_strip(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.size * self.size, self.size, |x, y| {
+            image::Rgba(self.texel(x % self.size, y, x / self.size))
+        })
+    }
+
+    /// Get the number of entries along each axis
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Get the texel at integer coordinates (clamped to the table)
+    pub fn texel(&self, r: u32, g: u32, b: u32) -> [u8; 4] {
+        let max = self.size - 1;
+        let (r, g, b) = (r.min(max), g.min(max), b.min(max));
+        self.texels[((b * self.size + g) * self.size + r) as usize]
+    }
+
+    /// Look up a display color (components 0.0..=1.0) with trilinear filtering, as the
+    /// GPU does
+    pub fn apply(&self, color: Vec3) -> Vec3 {
+        let position = color.clamp(Vec3::ZERO, Vec3::ONE) * (self.size - 1) as f32;
+        let base = position.floor().as_uvec3();
+        let t = position - position.floor();
+        let fetch = |dr: u32, dg: u32, db: u32| {
+            let [r, g, b, _] = self.texel(base.x + dr, base.y + dg, base.z + db);
+            Vec3::new(r as f32, g as f32, b as f32) / 255.0
+        };
+        let lerp_r = |dg, db| fetch(0, dg, db).lerp(fetch(1, dg, db), t.x);
+        let lerp_g = |db| lerp_r(0, db).lerp(lerp_r(1, db), t.y);
+        lerp_g(0).lerp(lerp_g(1), t.z)
+    }
+
+    /// Raw texel data for a 3D texture, rows of red along X, green along Y, blue along Z
+    pub(crate) fn bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.texels)
+    }
+}
+
+/// Which LUTs the renderer grades with, and how strongly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorGrading {
+    pub lut: ColorLutHandle,
+    /// Second LUT blended in by `blend` (0.0 is only `lut`, 1.0 only this one)
+    pub blend_lut: Option<ColorLutHandle>,
+    pub blend: f32,
+    /// Mix between the ungraded (0.0) and the fully graded (1.0) image
+    pub intensity: f32,
+}
+
+impl ColorGrading {
+    /// Grade fully with one LUT
+    pub fn new(lut: ColorLutHandle) -> Self {
+        Self {
+            lut,
+            blend_lut: None,
+            blend: 0.0,
+            intensity: 1.0,
+        }
+    }
+
+    /// Blend from this grading's LUT towards `lut` by `blend`
+    pub fn blended(mut self, lut: ColorLutHandle, blend: f32) -> Self {
+        self.blend_lut = Some(lut);
+        self.blend = blend.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Scale how strongly the grading applies
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity.clamp(0.0, 1.0);
+        self
+    }
+}
+
+/// LUT textures on the GPU and the bind group of the two being blended
+pub(crate) struct ColorLuts {
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Identity LUT bound while grading is off
+    identity: wgpu::TextureView,
+    luts: Vec<wgpu::TextureView>,
+    bind_group: wgpu::BindGroup,
+    /// LUT handles in the bind group (`None` for the identity LUT)
+    bound: (Option<ColorLutHandle>, Option<ColorLutHandle>),
+}
+
+impl ColorLuts {
+    pub(crate) fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D3,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("color_grading_bind_group_layout"),
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Color LUT Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let identity = Self::create_view(device, queue, &ColorLut::identity(2));
+        let bind_group = Self::create_bind_group(device, &layout, &sampler, &identity, &identity);
+        Self {
+            layout,
+            sampler,
+            identity,
+            luts: Vec::new(),
+            bind_group,
+            bound: (None, None),
+        }
+    }
+
+    fn create_view(device: &wgpu::Device, queue: &wgpu::Queue, lut: &ColorLut) -> wgpu::TextureView {
+        let size = wgpu::Extent3d {
+            width: lut.size(),
+            height: lut.size(),
+            depth_or_array_layers: lut.size(),
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Color LUT Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            // Not sRGB: the shader looks up and returns encoded values itself
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            lut.bytes(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * lut.size()),
+                rows_per_image: Some(lut.size()),
+            },
+            size,
+        );
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        from: &wgpu::TextureView,
+        to: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("color_grading_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(from),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(to),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+
+    pub(crate) fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    /// Upload a LUT, returning its handle
+    pub(crate) fn add(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, lut: &ColorLut) -> ColorLutHandle {
+        self.luts.push(Self::create_view(device, queue, lut));
+        self.luts.len() - 1
+    }
+
+    /// Check that a handle refers to an uploaded LUT
+    pub(crate) fn contains(&self, handle: ColorLutHandle) -> bool {
+        handle < self.luts.len()
+    }
+
+    /// Bind the LUTs of `grading` (identity LUTs without one), rebuilding the bind group
+    /// only when they change
+    pub(crate) fn bind(&mut self, device: &wgpu::Device, grading: Option<&ColorGrading>) {
+        let from = grading.map(|g| g.lut).filter(|&h| self.contains(h));
+        let to = grading.and_then(|g| g.blend_lut).filter(|&h| self.contains(h)).or(from);
+        if self.bound != (from, to) {
+            let view = |handle: Option<ColorLutHandle>| handle.map_or(&self.identity, |h| &self.luts[h]);
+            self.bind_group = Self::create_bind_group(device, &self.layout, &self.sampler, view(from), view(to));
+            self.bound = (from, to);
+        }
+    }
+
+    pub(crate) fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_round_trip() {
+        let mut lut = ColorLut::identity(4);
+        assert_eq!(lut.texel(3, 0, 1), [255, 0, 85, 255]);
+        lut.texels[0] = [10, 20, 30, 255];
+        let strip = lut.to_strip();
+        assert_eq!(strip.dimensions(), (16, 4));
+        // Blue picks the slice, red the column within it, green the row
+        assert_eq!(strip.get_pixel(7, 2).0, [255, 170, 85, 255]);
+        assert_eq!(ColorLut::from_strip(&strip).unwrap(), lut);
+        assert!(ColorLut::from_strip(&RgbaImage::new(32, 32)).is_err());
+    }
+
+    #[test]
+    fn test_apply_filters_between_entries() {
+        let identity = ColorLut::identity(DEFAULT_LUT_SIZE);
+        let color = Vec3::new(0.2, 0.55, 0.9);
+        assert!(identity.apply(color).distance(color) < 0.01);
+
+        // Swapping red and blue
+        let strip = identity.to_strip();
+        let swapped = RgbaImage::from_fn(strip.width(), strip.height(), |x, y| {
+            let [r, g, b, a] = strip.get_pixel(x, y).0;
+            image::Rgba([b, g, r, a])
+        });
+        let swapped = ColorLut::from_strip(&swapped).unwrap();
+        assert!(swapped.apply(color).distance(Vec3::new(0.9, 0.55, 0.2)) < 0.01);
+    }
+}
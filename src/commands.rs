@@ -0,0 +1,212 @@
+//! Deferred scene changes
+//!
+//! Entities can't be created or removed while a query or `active_entities_mut()` loop
+//! borrows the scene. [`Commands`] records those changes instead and applies them
+//! later, in order. Systems get a buffer through `SystemCtx::commands` that is applied
+//! as soon as the system returns; elsewhere, call [`Commands::apply`] after the loop.
+//!
+//! ```ignore
+//! for (id, health) in scene.query::<(EntityId, &Health)>() {
+//!     if health.0 <= 0 {
+//!         commands.despawn(id);
+//!         commands.spawn("Explosion").insert(Transform::new());
+//!     }
+//! }
+//! commands.apply(scene);
+//! ```
+
+use crate::ecs::{Component, EntityId, EntityMut, Scene};
+use crate::prefab::Prefab;
+use std::cell::RefCell;
+
+type InsertFn = Box<dyn FnOnce(&mut EntityMut)>;
+
+enum Command {
+    Spawn {
+        name: String,
+        components: Vec<InsertFn>,
+        parent: Option<EntityId>,
+    },
+    Run(Box<dyn FnOnce(&mut Scene)>),
+}
+
+/// A queue of scene changes applied later
+///
+/// Methods take `&self` so the buffer can be shared with code that also borrows the scene.
+#[derive(Default)]
+pub struct Commands {
+    queue: RefCell<Vec<Command>>,
+}
+
+impl Commands {
+    /// Create an empty buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a new entity; add components to it through the returned builder
+    pub fn spawn(&self, name: &str) -> EntityCommands<'_> {
+        let mut queue = self.queue.borrow_mut();
+        queue.push(Command::Spawn {
+            name: name.to_string(),
+            components: Vec::new(),
+            parent: None,
+        });
+        EntityCommands {
+            commands: self,
+            index: queue.len() - 1,
+        }
+    }
+
+    /// Queue spawning a prefab
+    pub fn spawn_prefab(&self, prefab: &Prefab) {
+        let prefab = prefab.clone();
+        self.add(move |scene| {
+            scene.spawn_prefab(&prefab);
+        });
+    }
+
+    /// Queue removing an entity (its children become roots)
+    pub fn despawn(&self, id: EntityId) {
+        self.add(move |scene| {
+            scene.remove_entity(id);
+        });
+    }
+
+    /// Queue removing an entity and all of its descendants
+    pub fn despawn_recursive(&self, id: EntityId) {
+        self.add(move |scene| {
+            scene.despawn_recursive(id);
+        });
+    }
+
+    /// Queue adding a component to an existing entity
+    pub fn add_component<T: Component>(&self, id: EntityId, component: T) {
+        self.add(move |scene| {
+            if let Some(mut entity) = scene.get_entity_mut(id) {
+                entity.add_component(component);
+            }
+        });
+    }
+
+    /// Queue removing a component from an entity
+    pub fn remove_component<T: Component>(&self, id: EntityId) {
+        self.add(move |scene| {
+            if let Some(mut entity) = scene.get_entity_mut(id) {
+                entity.remove_component::<T>();
+            }
+        });
+    }
+
+    /// Queue any other scene change
+    pub fn add<F: FnOnce(&mut Scene) + 'static>(&self, command: F) {
+        self.queue.borrow_mut().push(Command::Run(Box::new(command)));
+    }
+
+    /// Number of queued commands
+    pub fn len(&self) -> usize {
+        self.queue.borrow().len()
+    }
+
+    /// Check if no commands are queued
+    pub fn is_empty(&self) -> bool {
+        self.queue.borrow().is_empty()
+    }
+
+    /// Apply the queued commands in order, leaving the buffer empty
+    pub fn apply(&self, scene: &mut Scene) {
+        let queue = self.queue.take();
+        for command in queue {
+            match command {
+                Command::Spawn {
+                    name,
+                    components,
+                    parent,
+                } => {
+                    let id = scene.create_entity(name);
+                    if let Some(mut entity) = scene.get_entity_mut(id) {
+                        for insert in components {
+                            insert(&mut entity);
+                        }
+                    }
+                    if let Some(parent) = parent {
+                        scene.set_parent(id, parent);
+                    }
+                }
+                Command::Run(command) => command(scene),
+            }
+        }
+    }
+}
+
+/// Builder for an entity queued with [`Commands::spawn`]
+pub struct EntityCommands<'a> {
+    commands: &'a Commands,
+    index: usize,
+}
+
+impl EntityCommands<'_> {
+    /// Add a component to the queued entity
+    pub fn insert<T: Component>(self, component: T) -> Self {
+        self.with_spawn(|components, _| {
+            components.push(Box::new(move |entity: &mut EntityMut| entity.add_component(component)))
+        });
+        self
+    }
+
+    /// Attach the queued entity to an existing parent once spawned
+    pub fn set_parent(self, parent: EntityId) -> Self {
+        self.with_spawn(|_, slot| *slot = Some(parent));
+        self
+    }
+
+    fn with_spawn(&self, f: impl FnOnce(&mut Vec<InsertFn>, &mut Option<EntityId>)) {
+        if let Some(Command::Spawn {
+            components, parent, ..
+        }) = self.commands.queue.borrow_mut().get_mut(self.index)
+        {
+            f(components, parent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Transform;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(i32);
+    impl Component for Health {}
+
+    #[test]
+    fn test_commands_applied_after_iteration() {
+        let mut scene = Scene::new("Test".to_string());
+        let root = scene.create_entity("Root".to_string());
+        for hp in [0, 5, 0] {
+            let id = scene.create_entity("Unit".to_string());
+            scene.get_entity_mut(id).unwrap().add_component(Health(hp));
+        }
+
+        let commands = Commands::new();
+        for (id, health) in scene.query_mut::<(EntityId, &mut Health)>() {
+            if health.0 <= 0 {
+                commands.despawn(id);
+                commands
+                    .spawn("Explosion")
+                    .insert(Transform::new())
+                    .set_parent(root);
+            } else {
+                commands.add_component(id, Transform::new());
+            }
+        }
+        assert_eq!(commands.len(), 5);
+        assert_eq!(scene.entity_count(), 4);
+
+        commands.apply(&mut scene);
+        assert!(commands.is_empty());
+        assert_eq!(scene.query::<&Health>().count(), 1);
+        assert_eq!(scene.query::<&Transform>().count(), 3);
+        assert_eq!(scene.children(root).len(), 2);
+    }
+}
@@ -0,0 +1,657 @@
+//! Block-compressed textures in KTX2 and DDS containers
+//!
+//! BC1 (RGB with 1-bit alpha), BC3 (RGBA), BC5 (two channels, for normal maps), and
+//! BC7 (high quality RGBA) payloads are parsed with their mip levels. When the device
+//! supports `TEXTURE_COMPRESSION_BC` they are uploaded as-is; otherwise each level is
+//! decompressed to RGBA8 on the CPU. Supercompressed KTX2 files (Basis, Zstandard) are
+//! not supported.
+
+use crate::mipmap::mip_level_count;
+
+/// Size of a compression block in texels
+const BLOCK_SIZE: u32 = 4;
+
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+
+/// A block compression format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockFormat {
+    Bc1,
+    Bc3,
+    Bc5,
+    Bc7,
+}
+
+impl BlockFormat {
+    /// Bytes per 4x4 block
+    pub fn block_bytes(self) -> usize {
+        match self {
+            BlockFormat::Bc1 => 8,
+            BlockFormat::Bc3 | BlockFormat::Bc5 | BlockFormat::Bc7 => 16,
+        }
+    }
+
+    /// Matching GPU format (BC5 has no sRGB variant)
+    pub fn wgpu_format(self, srgb: bool) -> wgpu::TextureFormat {
+        match (self, srgb) {
+            (BlockFormat::Bc1, false) => wgpu::TextureFormat::Bc1RgbaUnorm,
+            (BlockFormat::Bc1, true) => wgpu::TextureFormat::Bc1RgbaUnormSrgb,
+            (BlockFormat::Bc3, false) => wgpu::TextureFormat::Bc3RgbaUnorm,
+            (BlockFormat::Bc3, true) => wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+            (BlockFormat::Bc5, _) => wgpu::TextureFormat::Bc5RgUnorm,
+            (BlockFormat::Bc7, false) => wgpu::TextureFormat::Bc7RgbaUnorm,
+            (BlockFormat::Bc7, true) => wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+        }
+    }
+
+    fn decode_block(self, block: &[u8], out: &mut [[u8; 4]; 16]) {
+        match self {
+            BlockFormat::Bc1 => decode_bc1(block, out, false),
+            BlockFormat::Bc3 => {
+                decode_bc1(&block[8..], out, true);
+                let mut alpha = [0u8; 16];
+                decode_bc4(&block[..8], &mut alpha);
+                for (texel, a) in out.iter_mut().zip(alpha) {
+                    texel[3] = a;
+                }
+            }
+            BlockFormat::Bc5 => {
+                let (mut red, mut green) = ([0u8; 16], [0u8; 16]);
+                decode_bc4(&block[..8], &mut red);
+                decode_bc4(&block[8..], &mut green);
+                for (i, texel) in out.iter_mut().enumerate() {
+                    *texel = [red[i], green[i], 0, 255];
+                }
+            }
+            BlockFormat::Bc7 => decode_bc7(block, out),
+        }
+    }
+}
+
+/// A block-compressed image with its mip chain
+#[derive(Debug, Clone)]
+pub struct CompressedImage {
+    pub format: BlockFormat,
+    /// Whether the container declares sRGB data (`None` for legacy DDS files)
+    pub srgb: Option<bool>,
+    pub width: u32,
+    pub height: u32,
+    /// Block data of each mip level, largest first
+    pub levels: Vec<Vec<u8>>,
+}
+
+/// Size in texels of a mip level
+pub fn level_size(width: u32, height: u32, level: u32) -> (u32, u32) {
+    ((width >> level).max(1), (height >> level).max(1))
+}
+
+/// Number of blocks across and down a level
+fn block_count(width: u32, height: u32) -> (u32, u32) {
+    (width.div_ceil(BLOCK_SIZE), height.div_ceil(BLOCK_SIZE))
+}
+
+/// Check for a KTX2 or DDS header
+pub fn is_compressed_container(bytes: &[u8]) -> bool {
+    bytes.starts_with(&KTX2_IDENTIFIER) || bytes.starts_with(DDS_MAGIC)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, String> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| "Failed to parse texture: header truncated".to_string())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, String> {
+    Ok(read_u32(bytes, offset)? as u64 | (read_u32(bytes, offset + 4)? as u64) << 32)
+}
+
+impl CompressedImage {
+    /// Parse a KTX2 or DDS file
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.starts_with(&KTX2_IDENTIFIER) {
+            Self::parse_ktx2(bytes)
+        } else if bytes.starts_with(DDS_MAGIC) {
+            Self::parse_dds(bytes)
+        } else {
+            Err("Failed to parse texture: not a KTX2 or DDS file".to_string())
+        }
+    }
+
+    fn parse_ktx2(bytes: &[u8]) -> Result<Self, String> {
+        let vk_format = read_u32(bytes, 12)?;
+        let width = read_u32(bytes, 20)?;
+        let height = read_u32(bytes, 24)?;
+        let depth = read_u32(bytes, 28)?;
+        let layers = read_u32(bytes, 32)?;
+        let faces = read_u32(bytes, 36)?;
+        let level_count = read_u32(bytes, 40)?.max(1);
+        let supercompression = read_u32(bytes, 44)?;
+
+        let (format, srgb) = match vk_format {
+            131..=134 => (BlockFormat::Bc1, vk_format % 2 == 0),
+            137 | 138 => (BlockFormat::Bc3, vk_format == 138),
+            141 => (BlockFormat::Bc5, false),
+            145 | 146 => (BlockFormat::Bc7, vk_format == 146),
+            _ => return Err(format!("Unsupported KTX2 format: VkFormat {}", vk_format)),
+        };
+        if supercompression != 0 {
+            return Err(format!("Unsupported KTX2 supercompression scheme: {}", supercompression));
+        }
+        if depth > 1 || layers > 1 || faces > 1 {
+            return Err("Unsupported KTX2 texture: only single 2D images are supported".to_string());
+        }
+
+        // The level index follows the 80-byte header
+        let mut levels = Vec::with_capacity(level_count as usize);
+        for level in 0..level_count {
+            let entry = 80 + level as usize * 24;
+            let offset = read_u64(bytes, entry)? as usize;
+            let length = read_u64(bytes, entry + 8)? as usize;
+            let data = bytes
+                .get(offset..offset.saturating_add(length))
+                .ok_or_else(|| format!("Failed to parse KTX2: level {} out of bounds", level))?;
+            levels.push(data.to_vec());
+        }
+        Self::new(format, Some(srgb), width, height, levels)
+    }
+
+    fn parse_dds(bytes: &[u8]) -> Result<Self, String> {
+        let height = read_u32(bytes, 12)?;
+        let width = read_u32(bytes, 16)?;
+        let level_count = read_u32(bytes, 28)?.max(1);
+        let four_cc = bytes.get(84..88).ok_or("Failed to parse DDS: header truncated")?;
+
+        let (format, srgb, data_offset) = match four_cc {
+            b"DXT1" => (BlockFormat::Bc1, None, 128),
+            b"DXT5" => (BlockFormat::Bc3, None, 128),
+            b"ATI2" | b"BC5U" => (BlockFormat::Bc5, Some(false), 128),
+            b"DX10" => {
+                let dxgi_format = read_u32(bytes, 128)?;
+                let (format, srgb) = match dxgi_format {
+                    71 | 72 => (BlockFormat::Bc1, dxgi_format == 72),
+                    77 | 78 => (BlockFormat::Bc3, dxgi_format == 78),
+                    83 => (BlockFormat::Bc5, false),
+                    98 | 99 => (BlockFormat::Bc7, dxgi_format == 99),
+                    _ => return Err(format!("Unsupported DDS format: DXGI format {}", dxgi_format)),
+                };
+                (format, Some(srgb), 148)
+            }
+            _ => return Err(format!("Unsupported DDS format: {}", String::from_utf8_lossy(four_cc))),
+        };
+
+        // Levels are stored back to back, largest first
+        let mut levels = Vec::with_capacity(level_count as usize);
+        let mut offset = data_offset;
+        for level in 0..level_count {
+            let (w, h) = level_size(width, height, level);
+            let (blocks_x, blocks_y) = block_count(w, h);
+            let length = (blocks_x * blocks_y) as usize * format.block_bytes();
+            let data = bytes
+                .get(offset..offset + length)
+                .ok_or_else(|| format!("Failed to parse DDS: level {} out of bounds", level))?;
+            levels.push(data.to_vec());
+            offset += length;
+        }
+        Self::new(format, srgb, width, height, levels)
+    }
+
+    fn new(
+        format: BlockFormat,
+        srgb: Option<bool>,
+        width: u32,
+        height: u32,
+        mut levels: Vec<Vec<u8>>,
+    ) -> Result<Self, String> {
+        if width == 0 || height == 0 {
+            return Err("Failed to parse texture: zero size".to_string());
+        }
+        // Ignore levels past 1x1
+        levels.truncate(mip_level_count(width, height) as usize);
+        for (level, data) in levels.iter().enumerate() {
+            let (w, h) = level_size(width, height, level as u32);
+            let (blocks_x, blocks_y) = block_count(w, h);
+            if data.len() < (blocks_x * blocks_y) as usize * format.block_bytes() {
+                return Err(format!("Failed to parse texture: level {} is too small", level));
+            }
+        }
+        Ok(Self {
+            format,
+            srgb,
+            width,
+            height,
+            levels,
+        })
+    }
+
+    /// Bytes per row of blocks, as uploaded with `write_texture`
+    pub fn bytes_per_row(&self, level: u32) -> u32 {
+        let (w, h) = level_size(self.width, self.height, level);
+        block_count(w, h).0 * self.format.block_bytes() as u32
+    }
+
+    /// Whether the GPU can sample the blocks directly (wgpu needs a block-aligned size)
+    pub fn is_block_aligned(&self) -> bool {
+        self.width.is_multiple_of(BLOCK_SIZE) && self.height.is_multiple_of(BLOCK_SIZE)
+    }
+
+    /// Decompress a mip level to tightly packed RGBA8
+    pub fn decompress(&self, level: u32) -> Vec<u8> {
+        let (width, height) = level_size(self.width, self.height, level);
+        let (blocks_x, blocks_y) = block_count(width, height);
+        let data = &self.levels[level as usize];
+        let block_bytes = self.format.block_bytes();
+        let mut rgba = vec![0u8; (width * height * 4) as usize];
+        let mut texels = [[0u8; 4]; 16];
+        for by in 0..blocks_y {
+            for bx in 0..blocks_x {
+                let index = (by * blocks_x + bx) as usize * block_bytes;
+                self.format.decode_block(&data[index..index + block_bytes], &mut texels);
+                // Blocks on the right and bottom edges may hang over the image
+                for (i, texel) in texels.iter().enumerate() {
+                    let x = bx * BLOCK_SIZE + i as u32 % BLOCK_SIZE;
+                    let y = by * BLOCK_SIZE + i as u32 / BLOCK_SIZE;
+                    if x < width && y < height {
+                        let offset = ((y * width + x) * 4) as usize;
+                        rgba[offset..offset + 4].copy_from_slice(texel);
+                    }
+                }
+            }
+        }
+        rgba
+    }
+}
+
+/// Expand an RGB565 color to RGBA8
+fn rgb565(color: u16) -> [u8; 4] {
+    let r = (color >> 11) as u8 & 0x1F;
+    let g = (color >> 5) as u8 & 0x3F;
+    let b = color as u8 & 0x1F;
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2), 255]
+}
+
+/// BC1 color block; BC3 always uses the four-color mode
+fn decode_bc1(block: &[u8], out: &mut [[u8; 4]; 16], four_color: bool) {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (a, b) = (rgb565(c0), rgb565(c1));
+    let mix = |wa: u16, wb: u16, div: u16| {
+        let mut color = [0u8; 4];
+        for i in 0..3 {
+            color[i] = ((a[i] as u16 * wa + b[i] as u16 * wb) / div) as u8;
+        }
+        color[3] = 255;
+        color
+    };
+    let palette = if four_color || c0 > c1 {
+        [a, b, mix(2, 1, 3), mix(1, 2, 3)]
+    } else {
+        // Three colors and transparent black
+        [a, b, mix(1, 1, 2), [0, 0, 0, 0]]
+    };
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    for (i, texel) in out.iter_mut().enumerate() {
+        *texel = palette[(indices >> (i * 2)) as usize & 3];
+    }
+}
+
+/// BC4 single-channel block (BC3 alpha, BC5 red and green)
+fn decode_bc4(block: &[u8], out: &mut [u8; 16]) {
+    let (a, b) = (block[0] as u32, block[1] as u32);
+    let mut palette = [0u8; 8];
+    palette[0] = a as u8;
+    palette[1] = b as u8;
+    if a > b {
+        for i in 1..7 {
+            palette[i + 1] = (((7 - i as u32) * a + i as u32 * b) / 7) as u8;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = (((5 - i as u32) * a + i as u32 * b) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+    let mut bits = 0u64;
+    for (i, byte) in block[2..8].iter().enumerate() {
+        bits |= (*byte as u64) << (i * 8);
+    }
+    for (i, value) in out.iter_mut().enumerate() {
+        *value = palette[(bits >> (i * 3)) as usize & 7];
+    }
+}
+
+/// Reads a BC7 block least significant bit first
+struct BitReader {
+    bits: u128,
+    position: u32,
+}
+
+impl BitReader {
+    fn read(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+        let value = (self.bits >> self.position) as u32 & ((1u32 << count) - 1);
+        self.position += count;
+        value
+    }
+}
+
+/// Per-mode layout: subsets, partition bits, rotation bits, index selection bit, color
+/// bits, alpha bits, per-endpoint p-bits, shared p-bits, index bits, secondary index bits
+const BC7_MODES: [[u32; 10]; 8] = [
+    [3, 4, 0, 0, 4, 0, 1, 0, 3, 0],
+    [2, 6, 0, 0, 6, 0, 0, 1, 3, 0],
+    [3, 6, 0, 0, 5, 0, 0, 0, 2, 0],
+    [2, 6, 0, 0, 7, 0, 1, 0, 2, 0],
+    [1, 0, 2, 1, 5, 6, 0, 0, 2, 3],
+    [1, 0, 2, 0, 7, 8, 0, 0, 2, 2],
+    [1, 0, 0, 0, 7, 7, 1, 0, 4, 0],
+    [2, 6, 0, 0, 5, 5, 1, 0, 2, 0],
+];
+
+const BC7_WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const BC7_WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Two-subset partitions, one bit per texel (set = second subset)
+const BC7_PARTITIONS_2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80, 0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00,
+    0xFFF0, 0xF000, 0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE, 0x088C, 0x3110, 0x6666, 0x366C,
+    0x17E8, 0x0FF0, 0x718E, 0x399C, 0xAAAA, 0xF0F0, 0x5A5A, 0x33CC, 0x3C3C, 0x55AA, 0x9696, 0xA55A, 0x73CE, 0x13C8,
+    0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660, 0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C,
+    0x9336, 0x9CC6, 0x817E, 0xE718, 0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// Three-subset partitions, one row of four texels per string
+const BC7_PARTITIONS_3: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+    [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2],
+    [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0],
+    [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0],
+    [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0],
+    [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+    [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2],
+    [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2],
+    [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0],
+    [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0],
+    [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0],
+    [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1],
+    [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1],
+    [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1],
+    [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2],
+    [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2],
+    [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2],
+    [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2],
+    [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1],
+    [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0],
+];
+
+/// Anchor texel of the second subset in two-subset partitions
+const BC7_ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Anchor texels of the second and third subsets in three-subset partitions
+const BC7_ANCHORS_3: [[u8; 64]; 2] = [
+    [
+        3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3, 3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15, 8, 15,
+        3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15, 3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+    ],
+    [
+        15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8, 15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10,
+        8, 15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8, 15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15,
+        15, 8,
+    ],
+];
+
+/// Subset of a texel and whether it's its subset's anchor (stored with one bit less)
+fn bc7_subset(subsets: u32, partition: usize, texel: usize) -> (usize, bool) {
+    match subsets {
+        2 => {
+            let subset = (BC7_PARTITIONS_2[partition] >> texel) as usize & 1;
+            let anchor = if subset == 0 { 0 } else { BC7_ANCHORS_2[partition] as usize };
+            (subset, texel == anchor)
+        }
+        3 => {
+            let subset = BC7_PARTITIONS_3[partition][texel] as usize;
+            let anchor = if subset == 0 { 0 } else { BC7_ANCHORS_3[subset - 1][partition] as usize };
+            (subset, texel == anchor)
+        }
+        _ => (0, texel == 0),
+    }
+}
+
+fn bc7_interpolate(a: u32, b: u32, index: u32, index_bits: u32) -> u8 {
+    let weight = match index_bits {
+        2 => BC7_WEIGHTS_2[index as usize],
+        3 => BC7_WEIGHTS_3[index as usize],
+        _ => BC7_WEIGHTS_4[index as usize],
+    };
+    (((64 - weight) * a + weight * b + 32) >> 6) as u8
+}
+
+fn decode_bc7(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    let mut reader = BitReader {
+        bits: u128::from_le_bytes(block[..16].try_into().unwrap_or([0; 16])),
+        position: 0,
+    };
+    let Some(mode) = (0..8).find(|bit| block[0] & (1 << bit) != 0) else {
+        // Reserved mode: transparent black
+        *out = [[0; 4]; 16];
+        return;
+    };
+    reader.read(mode + 1);
+    let [subsets, partition_bits, rotation_bits, selection_bits, color_bits, alpha_bits, endpoint_pbits, shared_pbits, index_bits, index_bits_2] =
+        BC7_MODES[mode as usize];
+    let partition = reader.read(partition_bits) as usize;
+    let rotation = reader.read(rotation_bits);
+    let selection = reader.read(selection_bits);
+
+    // endpoints[subset * 2 + end][channel]
+    let endpoint_count = subsets as usize * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[channel] = reader.read(color_bits);
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        endpoint[3] = if alpha_bits > 0 { reader.read(alpha_bits) } else { 255 };
+    }
+
+    let (mut color_bits, mut alpha_bits) = (color_bits, alpha_bits);
+    if endpoint_pbits > 0 || shared_pbits > 0 {
+        let mut pbits = [0u32; 6];
+        if endpoint_pbits > 0 {
+            for pbit in pbits.iter_mut().take(endpoint_count) {
+                *pbit = reader.read(1);
+            }
+        } else {
+            for subset in 0..subsets as usize {
+                let pbit = reader.read(1);
+                pbits[subset * 2] = pbit;
+                pbits[subset * 2 + 1] = pbit;
+            }
+        }
+        for (endpoint, pbit) in endpoints.iter_mut().zip(pbits).take(endpoint_count) {
+            for value in &mut endpoint[..3] {
+                *value = (*value << 1) | pbit;
+            }
+            if alpha_bits > 0 {
+                endpoint[3] = (endpoint[3] << 1) | pbit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+
+    // Expand to 8 bits by replicating the high bits
+    let expand = |value: u32, bits: u32| (value << (8 - bits)) | (value >> (2 * bits - 8));
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        for value in &mut endpoint[..3] {
+            *value = expand(*value, color_bits);
+        }
+        if alpha_bits > 0 {
+            endpoint[3] = expand(endpoint[3], alpha_bits);
+        }
+    }
+
+    let mut indices = [0u32; 16];
+    for (texel, index) in indices.iter_mut().enumerate() {
+        let (_, anchor) = bc7_subset(subsets, partition, texel);
+        *index = reader.read(if anchor { index_bits - 1 } else { index_bits });
+    }
+    let mut indices_2 = [0u32; 16];
+    if index_bits_2 > 0 {
+        for (texel, index) in indices_2.iter_mut().enumerate() {
+            *index = reader.read(if texel == 0 { index_bits_2 - 1 } else { index_bits_2 });
+        }
+    }
+
+    for (texel, color) in out.iter_mut().enumerate() {
+        let (subset, _) = bc7_subset(subsets, partition, texel);
+        let (a, b) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        // Modes 4 and 5 index color and alpha separately; the selection bit swaps them
+        let (color_index, color_index_bits, alpha_index, alpha_index_bits) = if index_bits_2 == 0 {
+            (indices[texel], index_bits, indices[texel], index_bits)
+        } else if selection == 0 {
+            (indices[texel], index_bits, indices_2[texel], index_bits_2)
+        } else {
+            (indices_2[texel], index_bits_2, indices[texel], index_bits)
+        };
+        for channel in 0..3 {
+            color[channel] = bc7_interpolate(a[channel], b[channel], color_index, color_index_bits);
+        }
+        color[3] = bc7_interpolate(a[3], b[3], alpha_index, alpha_index_bits);
+        match rotation {
+            1 => color.swap(0, 3),
+            2 => color.swap(1, 3),
+            3 => color.swap(2, 3),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pack values least significant bit first
+    fn pack_bits(fields: &[(u32, u32)]) -> [u8; 16] {
+        let mut bits = 0u128;
+        let mut position = 0;
+        for (value, count) in fields {
+            bits |= (*value as u128) << position;
+            position += count;
+        }
+        bits.to_le_bytes()
+    }
+
+    #[test]
+    fn test_decode_bc_blocks() {
+        let mut texels = [[0u8; 4]; 16];
+
+        // BC1: pure red and blue endpoints, texel 0 uses the 2/3 red mix
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0b10, 0, 0, 0];
+        BlockFormat::Bc1.decode_block(&block, &mut texels);
+        assert_eq!(texels[0], [170, 0, 85, 255]);
+        assert_eq!(texels[1], [255, 0, 0, 255]);
+
+        // BC7 mode 6: equal endpoints give a constant color (7 bits + p-bit per channel)
+        let mut fields = vec![(1 << 6, 7)];
+        for value in [100u32, 50, 25, 127] {
+            fields.push((value >> 1, 7));
+            fields.push((value >> 1, 7));
+        }
+        fields.push((1, 1));
+        fields.push((1, 1));
+        let block = pack_bits(&fields);
+        BlockFormat::Bc7.decode_block(&block, &mut texels);
+        assert!(texels.iter().all(|t| *t == [101, 51, 25, 127]));
+    }
+
+    #[test]
+    fn test_bc7_anchors_belong_to_their_subsets() {
+        for partition in 0..64 {
+            assert_eq!(BC7_PARTITIONS_2[partition] & 1, 0);
+            assert_eq!(bc7_subset(2, partition, BC7_ANCHORS_2[partition] as usize), (1, true));
+            for subset in 1..3 {
+                let anchor = BC7_ANCHORS_3[subset - 1][partition] as usize;
+                assert_eq!(bc7_subset(3, partition, anchor), (subset, true));
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_dds_levels() {
+        let mut bytes = vec![0u8; 128];
+        bytes[..4].copy_from_slice(DDS_MAGIC);
+        bytes[12..16].copy_from_slice(&8u32.to_le_bytes());
+        bytes[16..20].copy_from_slice(&8u32.to_le_bytes());
+        bytes[28..32].copy_from_slice(&4u32.to_le_bytes());
+        bytes[84..88].copy_from_slice(b"DXT1");
+        // 8x8 -> 4 blocks, then 1 block each for 4x4, 2x2, and 1x1
+        bytes.extend(std::iter::repeat_n(0, 8 * (4 + 1 + 1 + 1)));
+
+        assert!(is_compressed_container(&bytes));
+        let image = CompressedImage::parse(&bytes).unwrap();
+        assert_eq!(image.format, BlockFormat::Bc1);
+        assert_eq!(image.srgb, None);
+        assert_eq!(image.levels.len(), 4);
+        assert_eq!(image.bytes_per_row(0), 16);
+        assert_eq!(image.decompress(3).len(), 4);
+
+        bytes.truncate(140);
+        assert!(CompressedImage::parse(&bytes).is_err());
+    }
+}
@@ -2,6 +2,9 @@
 //!
 //! Loads settings from JSON files to configure window size, rendering options, etc.
 
+use crate::antialias::AntiAliasing;
+use crate::audio::BackgroundAudio;
+use crate::renderer::{Color, GpuBackend, PowerPreference};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -39,14 +42,49 @@ pub struct WindowConfig {
 pub struct RendererConfig {
     /// Maximum frames per second (0 = unlimited)
     pub target_fps: u32,
-    /// Enable MSAA (multisampling anti-aliasing)
+    /// MSAA sample count (1 disables it; unsupported counts fall back to the next lower one)
     pub msaa_samples: u32,
+    /// Post-process anti-aliasing applied on top of MSAA
+    #[serde(default)]
+    pub aa_mode: AntiAliasing,
     /// Field of view in degrees
     pub fov: f32,
     /// Near clipping plane
     pub near_plane: f32,
     /// Far clipping plane
     pub far_plane: f32,
+    /// Maximum number of point and spot lights per frame
+    #[serde(default = "default_max_lights")]
+    pub max_lights: u32,
+    /// Directory of the engine's WGSL shaders to hot reload from (for development)
+    #[serde(default)]
+    pub shader_dir: Option<String>,
+    /// Graphics API to render with
+    #[serde(default)]
+    pub backend: GpuBackend,
+    /// Whether to prefer the discrete or the integrated GPU
+    #[serde(default)]
+    pub power_preference: PowerPreference,
+    /// Use the first GPU whose name contains this text (case-insensitive), e.g. "NVIDIA"
+    #[serde(default)]
+    pub adapter_name: Option<String>,
+    /// Color the frame is cleared to before the cameras draw (without a sky)
+    #[serde(default = "default_clear_color")]
+    pub clear_color: Color,
+    /// Draw opaque meshes' depth before shading them, for scenes with a lot of overdraw
+    #[serde(default)]
+    pub depth_prepass: bool,
+    /// Draw an infinite ground grid with origin axes under the scene
+    #[serde(default)]
+    pub grid: bool,
+}
+
+fn default_max_lights() -> u32 {
+    64
+}
+
+fn default_clear_color() -> Color {
+    Color::new(0.1, 0.2, 0.3, 1.0)
 }
 
 /// Audio configuration
@@ -58,6 +96,9 @@ pub struct AudioConfig {
     pub music_volume: f32,
     /// Sound effects volume (0.0 to 1.0)
     pub sfx_volume: f32,
+    /// Pause or duck audio while the window is unfocused or minimized
+    #[serde(default)]
+    pub background: BackgroundAudio,
 }
 
 impl Default for EngineConfig {
@@ -74,14 +115,24 @@ impl Default for EngineConfig {
             renderer: RendererConfig {
                 target_fps: 60,
                 msaa_samples: 4,
+                aa_mode: AntiAliasing::None,
                 fov: 70.0,
                 near_plane: 0.1,
                 far_plane: 1000.0,
+                max_lights: default_max_lights(),
+                shader_dir: None,
+                backend: GpuBackend::Auto,
+                power_preference: PowerPreference::HighPerformance,
+                adapter_name: None,
+                clear_color: default_clear_color(),
+                depth_prepass: false,
+                grid: false,
             },
             audio: AudioConfig {
                 master_volume: 1.0,
                 music_volume: 0.8,
                 sfx_volume: 1.0,
+                background: BackgroundAudio::Play,
             },
         }
     }
@@ -132,4 +183,17 @@ mod tests {
         assert_eq!(config.window.height, 720);
         assert_eq!(config.renderer.target_fps, 60);
     }
+
+    #[test]
+    fn test_audio_background_setting() {
+        let audio: AudioConfig =
+            serde_json::from_str(r#"{"master_volume": 1.0, "music_volume": 0.5, "sfx_volume": 1.0}"#).unwrap();
+        assert_eq!(audio.background, BackgroundAudio::Play);
+
+        let audio: AudioConfig = serde_json::from_str(
+            r#"{"master_volume": 1.0, "music_volume": 0.5, "sfx_volume": 1.0, "background": {"Duck": 0.2}}"#,
+        )
+        .unwrap();
+        assert_eq!(audio.background, BackgroundAudio::Duck(0.2));
+    }
 }
@@ -0,0 +1,400 @@
+//! Crash handler and diagnostic reports
+//!
+//! [`install`] sets a panic hook that writes a crash report to disk (panic
+//! message, backtrace, engine and GPU info, the last frame's stats, and the
+//! most recent log lines) and tells the player where it is, instead of only
+//! printing to stderr. The engine adds a summary of the scene when a panic
+//! escapes the game loop.
+//!
+//! Recent log lines are kept by the logger installed with [`init_logger`],
+//! which `Engine::new` uses.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::ecs::Scene;
+
+/// Crash handler settings
+#[derive(Debug, Clone)]
+pub struct CrashConfig {
+    /// Name shown in the report and the crash message
+    pub app_name: String,
+    /// Directory crash reports are written to
+    pub report_dir: PathBuf,
+    /// Show a native message box (best effort) in addition to printing to stderr
+    pub show_dialog: bool,
+    /// Number of recent log lines included in reports
+    pub log_capacity: usize,
+}
+
+impl Default for CrashConfig {
+    fn default() -> Self {
+        Self {
+            app_name: "My Engine Game".to_string(),
+            report_dir: PathBuf::from("crash_reports"),
+            show_dialog: true,
+            log_capacity: 200,
+        }
+    }
+}
+
+/// Stats of the most recent frame
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameStats {
+    pub frame: u64,
+    pub fps: f32,
+    pub delta: f32,
+    pub entity_count: usize,
+}
+
+struct CrashState {
+    config: CrashConfig,
+    info: BTreeMap<String, String>,
+    frame: Option<FrameStats>,
+    last_report: Option<PathBuf>,
+}
+
+static STATE: Mutex<Option<CrashState>> = Mutex::new(None);
+static RECENT_LOGS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static LOG_CAPACITY: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(200);
+
+/// Lock ignoring poisoning; the crash handler must keep working after a panic
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn push_log(line: String) {
+    let capacity = LOG_CAPACITY.load(std::sync::atomic::Ordering::Relaxed);
+    let mut logs = lock(&RECENT_LOGS);
+    logs.push_back(line);
+    while logs.len() > capacity {
+        logs.pop_front();
+    }
+}
+
+/// Logger that forwards to `env_logger` and keeps recent lines for crash reports
+struct RingLogger {
+    inner: env_logger::Logger,
+}
+
+impl log::Log for RingLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.inner.matches(record) {
+            push_log(format!("[{} {}] {}", record.level(), record.target(), record.args()));
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install a logger built from `builder` that also records lines for crash reports
+///
+/// Does nothing if a logger is already installed.
+pub fn init_logger(mut builder: env_logger::Builder) {
+    let inner = builder.build();
+    let max_level = inner.filter();
+    if log::set_boxed_logger(Box::new(RingLogger { inner })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Install the crash handler's panic hook
+///
+/// The previous hook still runs afterwards, so panics are printed as usual.
+pub fn install(config: CrashConfig) {
+    LOG_CAPACITY.store(config.log_capacity, std::sync::atomic::Ordering::Relaxed);
+    let mut state = lock(&STATE);
+    let already_installed = state.is_some();
+    *state = Some(CrashState {
+        config,
+        info: BTreeMap::new(),
+        frame: None,
+        last_report: None,
+    });
+    drop(state);
+
+    if already_installed {
+        return;
+    }
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        handle_panic(info);
+        previous(info);
+    }));
+    log::info!("Crash handler installed");
+}
+
+/// Check if the crash handler is installed
+pub fn is_installed() -> bool {
+    lock(&STATE).is_some()
+}
+
+/// Add a line to the "info" section of crash reports (e.g. "gpu")
+pub fn set_info(key: &str, value: &str) {
+    if let Some(state) = lock(&STATE).as_mut() {
+        state.info.insert(key.to_string(), value.to_string());
+    }
+}
+
+/// Record the stats of the frame that just finished
+pub fn record_frame(stats: FrameStats) {
+    if let Some(state) = lock(&STATE).as_mut() {
+        state.frame = Some(stats);
+    }
+}
+
+/// Get the path of the most recent crash report written by the hook
+pub fn last_report() -> Option<PathBuf> {
+    lock(&STATE).as_ref().and_then(|s| s.last_report.clone())
+}
+
+/// A crash report ready to be written
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    pub app_name: String,
+    pub timestamp: u64,
+    pub message: String,
+    pub location: Option<String>,
+    pub thread: String,
+    pub backtrace: String,
+    pub info: BTreeMap<String, String>,
+    pub frame: Option<FrameStats>,
+    pub logs: Vec<String>,
+    /// Extra sections appended by the engine or game, as (title, text)
+    pub sections: Vec<(String, String)>,
+}
+
+impl CrashReport {
+    /// Collect a report from the crash handler's current state
+    pub fn capture(message: &str, location: Option<String>) -> Self {
+        // try_lock: the panic may have happened while a lock was held
+        let (app_name, info, frame) = match STATE.try_lock().ok().and_then(|s| {
+            s.as_ref()
+                .map(|s| (s.config.app_name.clone(), s.info.clone(), s.frame))
+        }) {
+            Some(state) => state,
+            None => (CrashConfig::default().app_name, BTreeMap::new(), None),
+        };
+        let logs = RECENT_LOGS
+            .try_lock()
+            .map(|logs| logs.iter().cloned().collect())
+            .unwrap_or_default();
+
+        Self {
+            app_name,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            message: message.to_string(),
+            location,
+            thread: std::thread::current().name().unwrap_or("<unnamed>").to_string(),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            info,
+            frame,
+            logs,
+            sections: Vec::new(),
+        }
+    }
+
+    /// Format the report as text
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "=== {} crash report ===", self.app_name);
+        let _ = writeln!(text, "Time: {} (UNIX)", self.timestamp);
+        let _ = writeln!(text, "Panic: {}", self.message);
+        if let Some(location) = &self.location {
+            let _ = writeln!(text, "Location: {}", location);
+        }
+        let _ = writeln!(text, "Thread: {}", self.thread);
+        let _ = writeln!(text, "Engine: {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        let _ = writeln!(text, "Platform: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+
+        if !self.info.is_empty() {
+            let _ = writeln!(text, "\n[info]");
+            for (key, value) in &self.info {
+                let _ = writeln!(text, "{}: {}", key, value);
+            }
+        }
+
+        if let Some(frame) = &self.frame {
+            let _ = writeln!(text, "\n[last frame]");
+            let _ = writeln!(text, "frame: {}", frame.frame);
+            let _ = writeln!(text, "fps: {:.1}", frame.fps);
+            let _ = writeln!(text, "delta: {:.4}s", frame.delta);
+            let _ = writeln!(text, "entities: {}", frame.entity_count);
+        }
+
+        for (title, section) in &self.sections {
+            let _ = writeln!(text, "\n[{}]\n{}", title, section.trim_end());
+        }
+
+        let _ = writeln!(text, "\n[backtrace]\n{}", self.backtrace.trim_end());
+
+        let _ = writeln!(text, "\n[recent log]");
+        for line in &self.logs {
+            let _ = writeln!(text, "{}", line);
+        }
+        text
+    }
+
+    /// Write the report into a directory, returning the file path
+    pub fn write<P: AsRef<Path>>(&self, dir: P) -> Result<PathBuf, String> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create crash report directory: {}", e))?;
+        let path = dir.join(format!("crash_{}.txt", self.timestamp));
+        std::fs::write(&path, self.to_text()).map_err(|e| format!("Failed to write crash report: {}", e))?;
+        Ok(path)
+    }
+}
+
+/// Append a section to the most recent crash report
+pub fn append_to_report(title: &str, text: &str) -> Result<(), String> {
+    let path = last_report().ok_or_else(|| "No crash report has been written".to_string())?;
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open crash report: {}", e))?;
+    writeln!(file, "\n[{}]\n{}", title, text.trim_end()).map_err(|e| format!("Failed to write crash report: {}", e))
+}
+
+/// Summarize a scene's entities for a crash report
+pub fn scene_summary(scene: &Scene) -> String {
+    let mut entities: Vec<_> = scene.entities().collect();
+    entities.sort_by_key(|e| e.id());
+
+    let mut text = format!("scene: {} ({} entities)\n", scene.name(), entities.len());
+    for entity in entities {
+        let _ = writeln!(
+            text,
+            "{} {:?}{}",
+            entity.id(),
+            entity.name(),
+            if entity.is_active() { "" } else { " (inactive)" }
+        );
+    }
+    text
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+fn handle_panic(info: &PanicHookInfo) {
+    let message = panic_message(info);
+    let location = info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+    let report = CrashReport::capture(&message, location);
+
+    let Some((report_dir, show_dialog)) = STATE
+        .try_lock()
+        .ok()
+        .and_then(|s| s.as_ref().map(|s| (s.config.report_dir.clone(), s.config.show_dialog)))
+    else {
+        return;
+    };
+
+    let notice = match report.write(&report_dir) {
+        Ok(path) => {
+            if let Ok(mut state) = STATE.try_lock() {
+                if let Some(state) = state.as_mut() {
+                    state.last_report = Some(path.clone());
+                }
+            }
+            format!(
+                "{} crashed: {}\n\nA crash report was saved to:\n{}",
+                report.app_name,
+                message,
+                path.display()
+            )
+        }
+        Err(e) => format!("{} crashed: {}\n\n({})", report.app_name, message, e),
+    };
+
+    eprintln!("\n{}\n", notice);
+    if show_dialog {
+        show_message_box(&report.app_name, &notice);
+    }
+}
+
+/// Show a native message box using tools that ship with the OS (best effort)
+fn show_message_box(title: &str, message: &str) {
+    use std::process::Command;
+
+    let status = if cfg!(target_os = "windows") {
+        let script = format!(
+            "Add-Type -AssemblyName PresentationFramework; [System.Windows.MessageBox]::Show('{}', '{}', 'OK', 'Error')",
+            message.replace('\'', "''"),
+            title.replace('\'', "''")
+        );
+        Command::new("powershell").args(["-NoProfile", "-Command", &script]).status()
+    } else if cfg!(target_os = "macos") {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let script = format!(
+            "display dialog \"{}\" with title \"{}\" buttons {{\"OK\"}} with icon stop",
+            escape(message),
+            escape(title)
+        );
+        Command::new("osascript").args(["-e", &script]).status()
+    } else {
+        Command::new("zenity")
+            .args(["--error", "--no-markup", "--title", title, "--text", message])
+            .status()
+            .or_else(|_| Command::new("kdialog").args(["--title", title, "--error", message]).status())
+    };
+
+    if status.is_err() {
+        log::debug!("No native message box available for crash notice");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_contains_diagnostics() {
+        push_log("[INFO rgame] loading level 3".to_string());
+
+        let mut scene = Scene::new("Level3".to_string());
+        scene.create_entity("Player".to_string());
+
+        let mut report = CrashReport::capture("index out of bounds", Some("src/game.rs:10:5".to_string()));
+        report.frame = Some(FrameStats {
+            frame: 1234,
+            fps: 59.9,
+            delta: 0.016,
+            entity_count: 1,
+        });
+        report.sections.push(("scene".to_string(), scene_summary(&scene)));
+
+        let dir = std::env::temp_dir().join(format!("rgame_crash_test_{}", std::process::id()));
+        let path = report.write(&dir).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("Panic: index out of bounds"));
+        assert!(text.contains("Location: src/game.rs:10:5"));
+        assert!(text.contains("frame: 1234"));
+        assert!(text.contains("\"Player\""));
+        assert!(text.contains("loading level 3"));
+        assert!(text.contains("[backtrace]"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,299 @@
+//! Immediate-mode debug line drawing
+//!
+//! Queue lines, boxes, spheres, and normals on the renderer's [`DebugDraw`] during
+//! a frame; they are drawn on top of the scene and cleared after presenting.
+
+use crate::renderer::{Color, PosColorVertex, VertexLayout};
+use crate::resource::Mesh;
+use glam::{Mat4, Quat, Vec3};
+use wgpu::util::DeviceExt;
+
+/// Vertex of a debug line
+pub type DebugVertex = PosColorVertex;
+
+/// Lines queued for the current frame
+#[derive(Debug, Default)]
+pub struct DebugDraw {
+    vertices: Vec<DebugVertex>,
+}
+
+impl DebugDraw {
+    /// Create an empty debug draw list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw a line segment
+    pub fn draw_line(&mut self, start: Vec3, end: Vec3, color: Color) {
+        let color = color.to_array();
+        self.vertices.push(DebugVertex {
+            position: start.to_array(),
+            color,
+        });
+        self.vertices.push(DebugVertex {
+            position: end.to_array(),
+            color,
+        });
+    }
+
+    /// Draw a ray from `origin` along `direction` (not normalized)
+    pub fn draw_ray(&mut self, origin: Vec3, direction: Vec3, color: Color) {
+        self.draw_line(origin, origin + direction, color);
+    }
+
+    /// Draw a connected line strip
+    pub fn draw_polyline(&mut self, points: &[Vec3], color: Color) {
+        for pair in points.windows(2) {
+            self.draw_line(pair[0], pair[1], color);
+        }
+    }
+
+    /// Draw a circle around `center` in the plane with the given normal
+    pub fn draw_circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Color) {
+        const SEGMENTS: usize = 32;
+        let rotation = Quat::from_rotation_arc(Vec3::Z, normal.normalize_or_zero());
+        let point = |i: usize| {
+            let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+            center + rotation * Vec3::new(angle.cos() * radius, angle.sin() * radius, 0.0)
+        };
+        for i in 0..SEGMENTS {
+            self.draw_line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// Draw a wireframe sphere as three circles
+    pub fn draw_wire_sphere(&mut self, center: Vec3, radius: f32, color: Color) {
+        self.draw_circle(center, Vec3::X, radius, color);
+        self.draw_circle(center, Vec3::Y, radius, color);
+        self.draw_circle(center, Vec3::Z, radius, color);
+    }
+
+    /// Draw an axis-aligned box
+    pub fn draw_aabb(&mut self, min: Vec3, max: Vec3, color: Color) {
+        self.box_edges(
+            |i| {
+                Vec3::new(
+                    if i & 1 == 0 { min.x } else { max.x },
+                    if i & 2 == 0 { min.y } else { max.y },
+                    if i & 4 == 0 { min.z } else { max.z },
+                )
+            },
+            color,
+        );
+    }
+
+    /// Draw a box with the given half extents, oriented and placed by `transform`
+    /// (e.g. a box collider)
+    pub fn draw_wire_box(&mut self, transform: Mat4, half_extents: Vec3, color: Color) {
+        self.box_edges(
+            |i| {
+                let sign = Vec3::new(
+                    if i & 1 == 0 { -1.0 } else { 1.0 },
+                    if i & 2 == 0 { -1.0 } else { 1.0 },
+                    if i & 4 == 0 { -1.0 } else { 1.0 },
+                );
+                transform.transform_point3(sign * half_extents)
+            },
+            color,
+        );
+    }
+
+    /// Draw the 12 edges between corners whose indices differ by one bit
+    fn box_edges(&mut self, corner: impl Fn(usize) -> Vec3, color: Color) {
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.draw_line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    /// Draw a mesh's vertex normals, placed by its model matrix
+    pub fn draw_normals(&mut self, mesh: &Mesh, model: Mat4, length: f32, color: Color) {
+        let normal_matrix = model.inverse().transpose();
+        for vertex in &mesh.vertices {
+            let position = model.transform_point3(Vec3::from(vertex.position));
+            let normal = normal_matrix.transform_vector3(Vec3::from(vertex.normal)).normalize_or_zero();
+            self.draw_ray(position, normal * length, color);
+        }
+    }
+
+    /// Draw the local axes of a transform matrix (X red, Y green, Z blue)
+    pub fn draw_axes(&mut self, transform: Mat4, length: f32) {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        self.draw_line(origin, transform.transform_point3(Vec3::X * length), Color::RED);
+        self.draw_line(origin, transform.transform_point3(Vec3::Y * length), Color::GREEN);
+        self.draw_line(origin, transform.transform_point3(Vec3::Z * length), Color::BLUE);
+    }
+
+    /// Get the queued vertices (two per line)
+    pub fn vertices(&self) -> &[DebugVertex] {
+        &self.vertices
+    }
+
+    /// Check if nothing is queued
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// Remove all queued lines
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+/// GPU pipeline drawing debug lines over the scene
+pub(crate) struct DebugDrawPipeline {
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    capacity: usize,
+    vertex_count: u32,
+}
+
+impl DebugDrawPipeline {
+    const INITIAL_CAPACITY: usize = 4096;
+
+    pub(crate) fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Debug Draw Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[Mat4::IDENTITY.to_cols_array_2d()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("debug_draw_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("debug_draw_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Debug Line Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/debug_lines.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Draw Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Debug Draw Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[DebugVertex::layout()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        let vertex_buffer = Self::create_vertex_buffer(device, Self::INITIAL_CAPACITY);
+
+        Self {
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            vertex_buffer,
+            capacity: Self::INITIAL_CAPACITY,
+            vertex_count: 0,
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Draw Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<DebugVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Upload this frame's lines, growing the vertex buffer if needed
+    pub(crate) fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, lines: &DebugDraw, view_proj: Mat4) {
+        let vertices = lines.vertices();
+        if vertices.len() > self.capacity {
+            self.capacity = vertices.len().next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(device, self.capacity);
+        }
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[view_proj.to_cols_array_2d()]));
+        if !vertices.is_empty() {
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(vertices));
+        }
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    /// Record the line draw into a pass targeting the surface
+    pub(crate) fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_box_edges() {
+        let mut draw = DebugDraw::new();
+        let transform = Mat4::from_rotation_translation(Quat::from_rotation_y(0.5), Vec3::new(0.0, 2.0, 0.0));
+        draw.draw_wire_box(transform, Vec3::new(1.0, 2.0, 3.0), Color::GREEN);
+        assert_eq!(draw.vertices().len(), 24);
+
+        // Every edge has the length of one of the box's sides
+        for edge in draw.vertices().chunks(2) {
+            let length = Vec3::from(edge[0].position).distance(Vec3::from(edge[1].position));
+            assert!([2.0, 4.0, 6.0].iter().any(|side| (length - side).abs() < 1e-4));
+        }
+
+        draw.clear();
+        draw.draw_aabb(Vec3::ZERO, Vec3::ONE, Color::WHITE);
+        assert_eq!(draw.vertices().len(), 24);
+    }
+}
@@ -0,0 +1,315 @@
+//! In-game debug tooling built on egui
+//!
+//! Only available with the `egui` feature. Wires egui's winit input handling
+//! and wgpu renderer into the engine frame and provides a built-in entity
+//! inspector window.
+
+use winit::event::WindowEvent;
+use winit::window::Window as WinitWindow;
+use crate::ecs::{EntityId, Scene};
+use crate::math::{Transform, Transform2D};
+use crate::renderer::RenderStats;
+
+/// Per-frame statistics shown in the debug window
+#[derive(Debug, Clone, Default)]
+pub struct DebugStats {
+    pub fps: f32,
+    pub delta: f32,
+    pub frame_count: u64,
+    /// Draw counts and GPU timings of the previous frame
+    pub render: RenderStats,
+}
+
+/// Tessellated UI waiting to be painted
+struct PendingFrame {
+    paint_jobs: Vec<egui::ClippedPrimitive>,
+    textures: egui::TexturesDelta,
+    screen: egui_wgpu::ScreenDescriptor,
+}
+
+/// egui context, input state, and GPU renderer for debug tooling
+pub struct DebugUi {
+    context: egui::Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    visible: bool,
+    selected: Option<EntityId>,
+    filter: String,
+    pending: Option<PendingFrame>,
+}
+
+impl DebugUi {
+    /// Create the debug UI for a window rendering into `surface_format`
+    pub fn new(
+        window: &WinitWindow,
+        device: &wgpu::Device,
+        surface_format: wgpu::TextureFormat,
+    ) -> Self {
+        let context = egui::Context::default();
+        let state = egui_winit::State::new(
+            context.clone(),
+            egui::ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+        let renderer = egui_wgpu::Renderer::new(device, surface_format, None, 1);
+
+        log::info!("Debug UI initialized");
+
+        Self {
+            context,
+            state,
+            renderer,
+            visible: true,
+            selected: None,
+            filter: String::new(),
+            pending: None,
+        }
+    }
+
+    /// Get the egui context (for drawing custom debug windows)
+    pub fn context(&self) -> &egui::Context {
+        &self.context
+    }
+
+    /// Check if the debug UI is visible
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the debug UI
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Get the entity currently selected in the inspector
+    pub fn selected_entity(&self) -> Option<EntityId> {
+        self.selected
+    }
+
+    /// Forward a window event to egui
+    ///
+    /// Returns `true` if egui consumed the event and the game should ignore it
+    pub fn handle_event(&mut self, window: &WinitWindow, event: &WindowEvent) -> bool {
+        if !self.visible {
+            return false;
+        }
+        self.state.on_window_event(window, event).consumed
+    }
+
+    /// Build the UI for this frame; call [`DebugUi::paint`] afterwards to draw it
+    pub fn prepare(&mut self, window: &WinitWindow, scene: &mut Scene, stats: DebugStats) {
+        if !self.visible {
+            self.pending = None;
+            return;
+        }
+
+        let raw_input = self.state.take_egui_input(window);
+        let selected = &mut self.selected;
+        let filter = &mut self.filter;
+        let output = self.context.run(raw_input, |ctx| {
+            stats_window(ctx, scene, &stats);
+            inspector_window(ctx, scene, selected, filter);
+        });
+
+        self.state
+            .handle_platform_output(window, output.platform_output);
+
+        let paint_jobs = self
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+        let size = window.inner_size();
+        let screen = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [size.width, size.height],
+            pixels_per_point: output.pixels_per_point,
+        };
+
+        self.pending = Some(PendingFrame {
+            paint_jobs,
+            textures: output.textures_delta,
+            screen,
+        });
+    }
+
+    /// Record the draw commands for the last prepared frame into `encoder`
+    pub fn paint(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let Some(frame) = self.pending.take() else {
+            return;
+        };
+
+        for (id, delta) in &frame.textures.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        let commands = self.renderer.update_buffers(
+            device,
+            queue,
+            encoder,
+            &frame.paint_jobs,
+            &frame.screen,
+        );
+        queue.submit(commands);
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Debug UI Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            self.renderer
+                .render(&mut render_pass, &frame.paint_jobs, &frame.screen);
+        }
+
+        for id in &frame.textures.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
+
+/// Frame timing and scene statistics
+fn stats_window(ctx: &egui::Context, scene: &Scene, stats: &DebugStats) {
+    egui::Window::new("Stats")
+        .default_pos([10.0, 10.0])
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!("FPS: {:.0}", stats.fps));
+            ui.label(format!("Frame time: {:.2} ms", stats.delta * 1000.0));
+            ui.label(format!("Frame: {}", stats.frame_count));
+            ui.label(format!(
+                "Drawn: {} (culled {}, occluded {})",
+                stats.render.drawn, stats.render.culled, stats.render.occluded
+            ));
+            ui.label(format!("Triangles: {}", stats.render.triangles));
+            if !stats.render.pass_timings.is_empty() {
+                ui.separator();
+                let total: f32 = stats.render.pass_timings.iter().map(|t| t.milliseconds).sum();
+                ui.label(format!("GPU: {:.2} ms", total));
+                for timing in &stats.render.pass_timings {
+                    ui.label(format!("  {}: {:.2} ms", timing.label, timing.milliseconds));
+                }
+            }
+            ui.separator();
+            ui.label(format!("Scene: {}", scene.name()));
+            ui.label(format!("Entities: {}", scene.entity_count()));
+        });
+}
+
+/// Entity list with an editor for the selected entity
+fn inspector_window(
+    ctx: &egui::Context,
+    scene: &mut Scene,
+    selected: &mut Option<EntityId>,
+    filter: &mut String,
+) {
+    egui::Window::new("Inspector")
+        .default_pos([10.0, 160.0])
+        .default_width(280.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Filter:");
+                ui.text_edit_singleline(filter);
+            });
+            ui.separator();
+
+            let mut entities: Vec<(EntityId, String)> = scene
+                .entities()
+                .filter(|e| e.name().contains(filter.as_str()))
+                .map(|e| (e.id(), e.name().to_string()))
+                .collect();
+            entities.sort_by_key(|(id, _)| *id);
+
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for (id, name) in &entities {
+                        let label = format!("[{}] {}", id, name);
+                        if ui.selectable_label(*selected == Some(*id), label).clicked() {
+                            *selected = Some(*id);
+                        }
+                    }
+                });
+
+            ui.separator();
+
+            let Some(mut entity) = selected.and_then(|id| scene.get_entity_mut(id)) else {
+                ui.label("No entity selected");
+                return;
+            };
+
+            ui.heading(entity.name().to_string());
+            let mut active = entity.is_self_active();
+            if ui.checkbox(&mut active, "Active").changed() {
+                entity.set_active(active);
+            }
+
+            if let Some(transform) = entity.get_component_mut::<Transform>() {
+                transform_editor(ui, transform);
+            }
+            if let Some(transform) = entity.get_component_mut::<Transform2D>() {
+                transform_2d_editor(ui, transform);
+            }
+        });
+}
+
+/// Editable fields for a 3D transform
+fn transform_editor(ui: &mut egui::Ui, transform: &mut Transform) {
+    ui.collapsing("Transform", |ui| {
+        vec3_row(ui, "Position", &mut transform.position);
+
+        let (x, y, z) = transform.rotation.to_euler(glam::EulerRot::XYZ);
+        let mut euler = glam::Vec3::new(x, y, z) * (180.0 / std::f32::consts::PI);
+        if vec3_row(ui, "Rotation", &mut euler) {
+            let r = euler * (std::f32::consts::PI / 180.0);
+            transform.rotation = glam::Quat::from_euler(glam::EulerRot::XYZ, r.x, r.y, r.z);
+        }
+
+        vec3_row(ui, "Scale", &mut transform.scale);
+    });
+}
+
+/// Editable fields for a 2D transform
+fn transform_2d_editor(ui: &mut egui::Ui, transform: &mut Transform2D) {
+    ui.collapsing("Transform2D", |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Position");
+            ui.add(egui::DragValue::new(&mut transform.position.x).speed(0.1));
+            ui.add(egui::DragValue::new(&mut transform.position.y).speed(0.1));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Rotation");
+            ui.drag_angle(&mut transform.rotation);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Scale");
+            ui.add(egui::DragValue::new(&mut transform.scale.x).speed(0.01));
+            ui.add(egui::DragValue::new(&mut transform.scale.y).speed(0.01));
+        });
+    });
+}
+
+/// A labelled row of three drag values; returns `true` if any changed
+fn vec3_row(ui: &mut egui::Ui, label: &str, value: &mut glam::Vec3) -> bool {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let x = ui.add(egui::DragValue::new(&mut value.x).speed(0.05)).changed();
+        let y = ui.add(egui::DragValue::new(&mut value.y).speed(0.05)).changed();
+        let z = ui.add(egui::DragValue::new(&mut value.z).speed(0.05)).changed();
+        x || y || z
+    })
+    .inner
+}
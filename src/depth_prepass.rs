@@ -0,0 +1,117 @@
+//! Depth-only pre-pass
+//!
+//! With the pre-pass enabled, each camera first draws its opaque meshes into the depth
+//! buffer without a fragment shader. The scene pass then keeps that depth and tests
+//! against it, so the GPU shades only the nearest surface of each pixel, not every
+//! mesh covering it. This pays off for scenes with lots of overdraw and expensive
+//! materials, and costs an extra vertex pass otherwise.
+//!
+//! Meshes fading between LOD levels and instanced batches are left out of the pre-pass.
+//! Custom material shaders that `discard` pixels of opaque meshes would leave holes, so
+//! such meshes should be drawn transparent.
+
+use crate::renderer::{Vertex, DEPTH_FORMAT};
+use crate::resource::Mesh;
+use crate::skinning::SkinVertex;
+
+/// A mesh draw to write depth for, using the object slot of its scene draw
+pub(crate) struct PrepassDraw<'a> {
+    pub(crate) mesh: &'a Mesh,
+    pub(crate) object_offset: u32,
+    pub(crate) skinned: bool,
+}
+
+/// Depth-only pipelines for static and, if supported, skinned meshes
+pub(crate) struct DepthPrepass {
+    mesh: wgpu::RenderPipeline,
+    skinned: Option<wgpu::RenderPipeline>,
+}
+
+impl DepthPrepass {
+    /// Create the pipelines, binding the camera (group 0) and the objects (group 1)
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        camera_layout: &wgpu::BindGroupLayout,
+        object_layout: &wgpu::BindGroupLayout,
+        common_shader: &str,
+        sample_count: u32,
+        skinning: bool,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Depth Pre-Pass Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                format!("{}\n{}", common_shader, include_str!("shaders/depth_prepass.wgsl")).into(),
+            ),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Depth Pre-Pass Pipeline Layout"),
+            bind_group_layouts: &[camera_layout, object_layout],
+            push_constant_ranges: &[],
+        });
+        let create = |label: &str, vs_entry: &str, buffers: &[wgpu::VertexBufferLayout]| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vs_entry,
+                    buffers,
+                    compilation_options: Default::default(),
+                },
+                fragment: None,
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
+        };
+        Self {
+            mesh: create("Depth Pre-Pass Pipeline", "vs_main", &[Vertex::desc()]),
+            skinned: skinning.then(|| {
+                create(
+                    "Depth Pre-Pass Pipeline (Skinned)",
+                    "vs_skinned",
+                    &[Vertex::desc(), SkinVertex::desc()],
+                )
+            }),
+        }
+    }
+
+    /// Write the depth of `draws` into a pass with the camera (group 0) bound; skinned
+    /// meshes are skipped without skinning support
+    pub(crate) fn draw<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        object_bind_group: &'a wgpu::BindGroup,
+        draws: &[PrepassDraw<'a>],
+    ) {
+        for draw in draws {
+            let pipeline = if draw.skinned { self.skinned.as_ref() } else { Some(&self.mesh) };
+            let (Some(pipeline), Some(vertex_buffer), Some(index_buffer)) =
+                (pipeline, &draw.mesh.vertex_buffer, &draw.mesh.index_buffer)
+            else {
+                continue;
+            };
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(1, object_bind_group, &[draw.object_offset]);
+            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            if let Some(skin_buffer) = draw.mesh.skin_buffer.as_ref().filter(|_| draw.skinned) {
+                pass.set_vertex_buffer(1, skin_buffer.slice(..));
+            }
+            pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            pass.draw_indexed(0..draw.mesh.indices.len() as u32, 0, 0..1);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Entity cloning
+//!
+//! Components are stored type-erased, so a scene only clones component types it has a
+//! clone function for. Common built-in components are registered up front; register
+//! game components with `scene.register_clone::<T>()`. Components without one (and the
+//! hierarchy links, which are rebuilt) are left off the copy.
+
+use crate::ecs::{Component, EntityId, EntityRef, Scene};
+use crate::registry::ComponentValue;
+use std::any::TypeId;
+
+/// Copies one component type off an entity
+pub(crate) type CloneFn = fn(&EntityRef) -> Option<Box<dyn ComponentValue>>;
+
+fn clone_component<T: Component + Clone>(entity: &EntityRef) -> Option<Box<dyn ComponentValue>> {
+    entity
+        .get_component::<T>()
+        .map(|component| Box::new(component.clone()) as Box<dyn ComponentValue>)
+}
+
+/// Register the built-in components that make sense to copy
+pub(crate) fn register_builtin_clones(scene: &mut Scene) {
+    scene.register_clone::<crate::math::Transform>();
+    scene.register_clone::<crate::math::Transform2D>();
+    scene.register_clone::<crate::renderer::MeshRenderer>();
+    scene.register_clone::<crate::renderer::RenderLayers>();
+    scene.register_clone::<crate::light::Light>();
+    scene.register_clone::<crate::lod::LodGroup>();
+    scene.register_clone::<crate::skinning::Skeleton>();
+    scene.register_clone::<crate::steering::Steering>();
+    scene.register_clone::<crate::navmesh::NavAgent>();
+    scene.register_clone::<crate::dynamic::DynamicComponents>();
+    scene.register_clone::<crate::audio_emitter::AudioEmitter>();
+    scene.register_clone::<crate::audio_emitter::AudioListener>();
+}
+
+impl Scene {
+    /// Let `clone_entity` copy a component type
+    pub fn register_clone<T: Component + Clone>(&mut self) {
+        self.cloners.insert(TypeId::of::<T>(), clone_component::<T>);
+    }
+
+    /// Duplicate an entity with its clonable components, returning the copy
+    ///
+    /// The copy gets the same name, active state, and parent, but no children.
+    pub fn clone_entity(&mut self, id: EntityId) -> Option<EntityId> {
+        let parent = self.parent(id);
+        self.duplicate(id, parent, false)
+    }
+
+    /// Duplicate an entity and all of its descendants, returning the copied root
+    pub fn clone_entity_recursive(&mut self, id: EntityId) -> Option<EntityId> {
+        let parent = self.parent(id);
+        self.duplicate(id, parent, true)
+    }
+
+    fn duplicate(&mut self, id: EntityId, parent: Option<EntityId>, recursive: bool) -> Option<EntityId> {
+        let source = self.get_entity(id)?;
+        let name = source.name().to_string();
+        let active = source.is_self_active();
+        let components: Vec<Box<dyn ComponentValue>> =
+            self.cloners.values().filter_map(|clone| clone(&source)).collect();
+
+        let copy = self.create_entity(name);
+        if let Some(mut entity) = self.get_entity_mut(copy) {
+            for component in &components {
+                component.insert_into(&mut entity);
+            }
+            entity.set_active(active);
+        }
+        if let Some(parent) = parent {
+            self.set_parent(copy, parent);
+        }
+        if recursive {
+            for child in self.children(id).to_vec() {
+                self.duplicate(child, Some(copy), true);
+            }
+        }
+        Some(copy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Transform;
+    use glam::Vec3;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Health(i32);
+    impl Component for Health {}
+
+    #[derive(Debug)]
+    struct Unique;
+    impl Component for Unique {}
+
+    #[test]
+    fn test_clone_entity() {
+        let mut scene = Scene::new("Test".to_string());
+        scene.register_clone::<Health>();
+        let ship = scene.create_entity("Ship".to_string());
+        let bullet = scene.create_entity("Bullet".to_string());
+        let mut entity = scene.get_entity_mut(bullet).unwrap();
+        entity.add_component(Transform::from_position(Vec3::X));
+        entity.add_component(Health(1));
+        entity.add_component(Unique);
+        scene.set_parent(bullet, ship);
+
+        let copy = scene.clone_entity(bullet).unwrap();
+        let entity = scene.get_entity(copy).unwrap();
+        assert_eq!(entity.name(), "Bullet");
+        assert_eq!(entity.get_component::<Health>(), Some(&Health(1)));
+        assert_eq!(entity.get_component::<Transform>().unwrap().position, Vec3::X);
+        assert!(!entity.has_component::<Unique>());
+        assert_eq!(scene.children(ship), [bullet, copy]);
+        assert_eq!(scene.clone_entity(999), None);
+    }
+
+    #[test]
+    fn test_clone_entity_recursive() {
+        let mut scene = Scene::new("Test".to_string());
+        let root = scene.create_entity("Enemy".to_string());
+        let gun = scene.create_entity("Gun".to_string());
+        let muzzle = scene.create_entity("Muzzle".to_string());
+        scene.set_parent(gun, root);
+        scene.set_parent(muzzle, gun);
+
+        let copy = scene.clone_entity_recursive(root).unwrap();
+        assert_eq!(scene.entity_count(), 6);
+        assert_eq!(scene.parent(copy), None);
+        let gun_copy = scene.children(copy)[0];
+        assert_ne!(gun_copy, gun);
+        assert_eq!(scene.get_entity(gun_copy).unwrap().name(), "Gun");
+        assert_eq!(scene.children(gun_copy).len(), 1);
+        assert_eq!(scene.children(root), [gun]);
+    }
+}
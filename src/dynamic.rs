@@ -0,0 +1,370 @@
+//! Dynamic components
+//!
+//! Scripts and data files can attach data to entities without a compiled Rust type.
+//! A [`DynamicComponent`] is a map of named fields holding numbers, booleans, strings,
+//! or vectors; a field keeps the type of its first value. Every entity stores its
+//! dynamic components by name in one [`DynamicComponents`] component, so they can be
+//! queried alongside typed components:
+//!
+//! ```ignore
+//! let health = DynamicSchema::new().field("hp", 100.0).field("regen", false);
+//! scene.insert_dynamic(id, "health", health.from_json(&json!({ "hp": 40 }))?);
+//!
+//! for (id, health) in scene.query_dynamic("health") {
+//!     let hp = health.number("hp").unwrap_or(0.0);
+//! }
+//! for (transform, dynamic) in scene.query::<(&Transform, &DynamicComponents)>() { /* ... */ }
+//! ```
+//!
+//! `DynamicComponents` serializes to JSON, so registering it in the
+//! [`ComponentRegistry`](crate::registry::ComponentRegistry) saves it in scene files and
+//! exposes it to scripts.
+
+use crate::ecs::{Component, EntityId, Scene};
+use glam::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Type of a dynamic field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Number,
+    Bool,
+    String,
+    Vec2,
+    Vec3,
+}
+
+/// Value of a dynamic field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DynamicValue {
+    Number(f64),
+    Bool(bool),
+    String(String),
+    Vec2(Vec2),
+    Vec3(Vec3),
+}
+
+impl DynamicValue {
+    /// Get the type of the value
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            DynamicValue::Number(_) => ValueKind::Number,
+            DynamicValue::Bool(_) => ValueKind::Bool,
+            DynamicValue::String(_) => ValueKind::String,
+            DynamicValue::Vec2(_) => ValueKind::Vec2,
+            DynamicValue::Vec3(_) => ValueKind::Vec3,
+        }
+    }
+
+    /// Convert a JSON value (numbers, booleans, strings, and arrays of 2 or 3 numbers)
+    pub fn from_json(value: &serde_json::Value) -> Result<Self, String> {
+        serde_json::from_value(value.clone()).map_err(|_| format!("Unsupported dynamic value {}", value))
+    }
+}
+
+impl From<f64> for DynamicValue {
+    fn from(value: f64) -> Self {
+        DynamicValue::Number(value)
+    }
+}
+
+impl From<i32> for DynamicValue {
+    fn from(value: i32) -> Self {
+        DynamicValue::Number(value as f64)
+    }
+}
+
+impl From<bool> for DynamicValue {
+    fn from(value: bool) -> Self {
+        DynamicValue::Bool(value)
+    }
+}
+
+impl From<&str> for DynamicValue {
+    fn from(value: &str) -> Self {
+        DynamicValue::String(value.to_string())
+    }
+}
+
+impl From<Vec2> for DynamicValue {
+    fn from(value: Vec2) -> Self {
+        DynamicValue::Vec2(value)
+    }
+}
+
+impl From<Vec3> for DynamicValue {
+    fn from(value: Vec3) -> Self {
+        DynamicValue::Vec3(value)
+    }
+}
+
+/// A set of named, typed fields defined at runtime
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DynamicComponent {
+    fields: BTreeMap<String, DynamicValue>,
+}
+
+impl DynamicComponent {
+    /// Create a component without fields
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field (builder)
+    pub fn with(mut self, field: &str, value: impl Into<DynamicValue>) -> Self {
+        self.fields.insert(field.to_string(), value.into());
+        self
+    }
+
+    /// Get a field
+    pub fn get(&self, field: &str) -> Option<&DynamicValue> {
+        self.fields.get(field)
+    }
+
+    /// Set a field; an existing field only accepts values of the same type
+    pub fn set(&mut self, field: &str, value: impl Into<DynamicValue>) -> Result<(), String> {
+        let value = value.into();
+        if let Some(current) = self.fields.get(field) {
+            if current.kind() != value.kind() {
+                return Err(format!(
+                    "Field '{}' is {:?}, not {:?}",
+                    field,
+                    current.kind(),
+                    value.kind()
+                ));
+            }
+        }
+        self.fields.insert(field.to_string(), value);
+        Ok(())
+    }
+
+    /// Remove a field
+    pub fn remove(&mut self, field: &str) -> Option<DynamicValue> {
+        self.fields.remove(field)
+    }
+
+    /// Iterate the fields by name
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &DynamicValue)> {
+        self.fields.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Get a number field
+    pub fn number(&self, field: &str) -> Option<f64> {
+        match self.get(field)? {
+            DynamicValue::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get a boolean field
+    pub fn bool(&self, field: &str) -> Option<bool> {
+        match self.get(field)? {
+            DynamicValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get a string field
+    pub fn string(&self, field: &str) -> Option<&str> {
+        match self.get(field)? {
+            DynamicValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Get a 2D vector field
+    pub fn vec2(&self, field: &str) -> Option<Vec2> {
+        match self.get(field)? {
+            DynamicValue::Vec2(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get a 3D vector field
+    pub fn vec3(&self, field: &str) -> Option<Vec3> {
+        match self.get(field)? {
+            DynamicValue::Vec3(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Field names, types, and defaults of a kind of dynamic component
+#[derive(Debug, Clone, Default)]
+pub struct DynamicSchema {
+    defaults: DynamicComponent,
+}
+
+impl DynamicSchema {
+    /// Create a schema without fields
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field; its type is the type of the default value
+    pub fn field(mut self, name: &str, default: impl Into<DynamicValue>) -> Self {
+        self.defaults = self.defaults.with(name, default);
+        self
+    }
+
+    /// Create a component with every field set to its default
+    pub fn instantiate(&self) -> DynamicComponent {
+        self.defaults.clone()
+    }
+
+    /// Create a component from a JSON object, using defaults for missing fields
+    pub fn from_json(&self, value: &serde_json::Value) -> Result<DynamicComponent, String> {
+        let object = value
+            .as_object()
+            .ok_or_else(|| format!("Expected an object, got {}", value))?;
+        let mut component = self.instantiate();
+        for (field, value) in object {
+            if component.get(field).is_none() {
+                return Err(format!("Unknown field '{}'", field));
+            }
+            component.set(field, DynamicValue::from_json(value)?)?;
+        }
+        Ok(component)
+    }
+
+    /// Check that a component has exactly the schema's fields and types
+    pub fn validate(&self, component: &DynamicComponent) -> Result<(), String> {
+        for (field, default) in self.defaults.fields() {
+            match component.get(field) {
+                Some(value) if value.kind() == default.kind() => {}
+                Some(value) => {
+                    return Err(format!("Field '{}' is {:?}, expected {:?}", field, value.kind(), default.kind()))
+                }
+                None => return Err(format!("Missing field '{}'", field)),
+            }
+        }
+        match component.fields().find(|(field, _)| self.defaults.get(field).is_none()) {
+            Some((field, _)) => Err(format!("Unknown field '{}'", field)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The dynamic components of an entity, keyed by name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DynamicComponents(BTreeMap<String, DynamicComponent>);
+
+impl Component for DynamicComponents {}
+
+impl DynamicComponents {
+    /// Get a dynamic component
+    pub fn get(&self, name: &str) -> Option<&DynamicComponent> {
+        self.0.get(name)
+    }
+
+    /// Get a dynamic component (mutable)
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut DynamicComponent> {
+        self.0.get_mut(name)
+    }
+
+    /// Check if a dynamic component is present
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// Names of the dynamic components
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+impl Scene {
+    /// Add a dynamic component to an entity, replacing one of the same name
+    pub fn insert_dynamic(&mut self, id: EntityId, name: &str, component: DynamicComponent) -> bool {
+        if let Some(components) = self.get_component_mut::<DynamicComponents>(id) {
+            components.0.insert(name.to_string(), component);
+            return true;
+        }
+        let Some(mut entity) = self.get_entity_mut(id) else {
+            return false;
+        };
+        entity.add_component(DynamicComponents(BTreeMap::from([(name.to_string(), component)])));
+        true
+    }
+
+    /// Get a dynamic component of an entity
+    pub fn get_dynamic(&self, id: EntityId, name: &str) -> Option<&DynamicComponent> {
+        self.get_component::<DynamicComponents>(id)?.get(name)
+    }
+
+    /// Get a dynamic component of an entity (mutable)
+    pub fn get_dynamic_mut(&mut self, id: EntityId, name: &str) -> Option<&mut DynamicComponent> {
+        self.get_component_mut::<DynamicComponents>(id)?.get_mut(name)
+    }
+
+    /// Remove a dynamic component from an entity
+    pub fn remove_dynamic(&mut self, id: EntityId, name: &str) -> Option<DynamicComponent> {
+        let components = self.get_component_mut::<DynamicComponents>(id)?;
+        let removed = components.0.remove(name);
+        if components.0.is_empty() {
+            if let Some(mut entity) = self.get_entity_mut(id) {
+                entity.remove_component::<DynamicComponents>();
+            }
+        }
+        removed
+    }
+
+    /// Iterate the active entities that have a dynamic component
+    pub fn query_dynamic<'a>(&'a self, name: &'a str) -> impl Iterator<Item = (EntityId, &'a DynamicComponent)> {
+        self.query::<(EntityId, &DynamicComponents)>()
+            .filter_map(move |(id, components)| components.get(name).map(|component| (id, component)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Transform;
+    use serde_json::json;
+
+    #[test]
+    fn test_schema_typed_fields() {
+        let schema = DynamicSchema::new()
+            .field("hp", 100)
+            .field("label", "Crate")
+            .field("spawn", Vec3::ZERO);
+        let mut component = schema
+            .from_json(&json!({ "hp": 40, "spawn": [1.0, 2.0, 3.0] }))
+            .unwrap();
+        assert_eq!(component.number("hp"), Some(40.0));
+        assert_eq!(component.string("label"), Some("Crate"));
+        assert_eq!(component.vec3("spawn"), Some(Vec3::new(1.0, 2.0, 3.0)));
+        assert!(schema.validate(&component).is_ok());
+
+        assert!(component.set("hp", "lots").is_err());
+        assert!(schema.from_json(&json!({ "armor": 1 })).is_err());
+        component.set("armor", 5).unwrap();
+        assert!(schema.validate(&component).is_err());
+    }
+
+    #[test]
+    fn test_dynamic_components_on_entities() {
+        let mut scene = Scene::new("Test".to_string());
+        let barrel = scene.create_entity("Barrel".to_string());
+        let rock = scene.create_entity("Rock".to_string());
+        scene.get_entity_mut(barrel).unwrap().add_component(Transform::new());
+        scene.insert_dynamic(barrel, "health", DynamicComponent::new().with("hp", 10));
+        scene.insert_dynamic(barrel, "loot", DynamicComponent::new().with("item", "coin"));
+        scene.insert_dynamic(rock, "loot", DynamicComponent::new().with("item", "gem"));
+
+        scene.get_dynamic_mut(barrel, "health").unwrap().set("hp", 4).unwrap();
+        let damaged: Vec<_> = scene.query_dynamic("health").map(|(id, c)| (id, c.number("hp"))).collect();
+        assert_eq!(damaged, [(barrel, Some(4.0))]);
+        assert_eq!(scene.query::<(&Transform, &DynamicComponents)>().count(), 1);
+
+        let json = serde_json::to_value(scene.get_component::<DynamicComponents>(barrel).unwrap()).unwrap();
+        assert_eq!(json, json!({ "health": { "hp": 4.0 }, "loot": { "item": "coin" } }));
+
+        assert!(scene.remove_dynamic(rock, "loot").is_some());
+        assert!(!scene.get_entity(rock).unwrap().has_component::<DynamicComponents>());
+    }
+}
@@ -1,112 +1,1245 @@
 //! Simple Entity Component System (ECS)
 //!
 //! Provides a basic ECS architecture for organizing game objects.
+//!
+//! Components are stored in archetype tables: every distinct set of component types
+//! gets a table with one contiguous column per type, and an entity is a row in the
+//! table matching its components. Adding or removing a component moves the entity's
+//! row to another table, so queries over a few component types walk dense arrays.
+//! Components with [`StorageType::Sparse`] live in sparse sets instead (see
+//! [`crate::sparse`]), so adding and removing them leaves the entity's row alone.
+
+use crate::duplicate::{self, CloneFn};
+use crate::snapshot::SceneSnapshot;
+use crate::sparse::SparseStorage;
+use rayon::prelude::*;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub use rgame_derive::Component;
+
+/// Unique identifier for entities
+pub type EntityId = u64;
+
+/// How values of a component type are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageType {
+    /// Archetype table columns: fastest to iterate, but adding or removing the
+    /// component moves the entity to another table
+    Table,
+    /// A sparse set keyed by entity: cheap to add and remove, slower to iterate
+    Sparse,
+}
+
+/// Trait that all components must implement
+pub trait Component: Any + 'static {
+    /// Where values of this type are stored
+    const STORAGE: StorageType = StorageType::Table;
+
+    /// Returns a unique identifier for this component type
+    fn type_id(&self) -> TypeId {
+        TypeId::of::<Self>()
+    }
+}
+
+// Implement Component for common types
+impl Component for crate::math::Transform {}
+impl Component for crate::math::Transform2D {}
+
+/// Type-erased storage of one component type in an archetype
+trait Column: Any {
+    /// Drop the value at `row`, moving the last value into its place
+    fn swap_remove(&mut self, row: usize);
+    /// Move the value at `row` to the end of `dst` (a column of the same type)
+    fn move_row(&mut self, row: usize, dst: &mut dyn Column);
+    /// Create an empty column of the same type
+    fn empty(&self) -> Box<dyn Column>;
+    /// Pointer to the first value, for row access while iterating mutably
+    fn data_ptr(&mut self) -> *mut u8;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> Column for Vec<T> {
+    fn swap_remove(&mut self, row: usize) {
+        Vec::swap_remove(self, row);
+    }
+
+    fn move_row(&mut self, row: usize, dst: &mut dyn Column) {
+        let value = Vec::swap_remove(self, row);
+        if let Some(dst) = dst.as_any_mut().downcast_mut::<Vec<T>>() {
+            dst.push(value);
+        }
+    }
+
+    fn empty(&self) -> Box<dyn Column> {
+        Box::new(Vec::<T>::new())
+    }
+
+    fn data_ptr(&mut self) -> *mut u8 {
+        self.as_mut_ptr() as *mut u8
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// When a component was added and last mutably accessed
+#[derive(Debug, Clone, Copy)]
+pub struct ComponentTicks {
+    /// Tick the component was added at
+    pub added: u32,
+    /// Tick of the last mutable access
+    pub changed: u32,
+}
+
+impl ComponentTicks {
+    pub(crate) fn new(tick: u32) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+}
+
+/// Change ticks a query compares component ticks against
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeTicks {
+    /// Tick of the last tracker reset; later ticks count as changes
+    pub last_change_tick: u32,
+    /// Tick recorded by mutable access
+    pub change_tick: u32,
+}
+
+/// Per-row data every entity has regardless of its components
+#[derive(Debug)]
+struct EntityInfo {
+    id: EntityId,
+    name: String,
+    /// The entity's own active flag
+    active: bool,
+    /// Whether every ancestor in the hierarchy is active
+    parent_active: bool,
+}
+
+impl EntityInfo {
+    fn is_active(&self) -> bool {
+        self.active && self.parent_active
+    }
+}
+
+/// A table of entities sharing the same set of component types
+pub struct Archetype {
+    /// Component types, sorted
+    types: Vec<TypeId>,
+    /// One column per type, in the same order
+    columns: Vec<Box<dyn Column>>,
+    /// Add/change ticks of every column, in the same order
+    ticks: Vec<Vec<ComponentTicks>>,
+    entities: Vec<EntityInfo>,
+    /// Column data pointers, refreshed before iterating mutably
+    ptrs: Vec<*mut u8>,
+    /// Tick pointers, refreshed along with `ptrs`
+    tick_ptrs: Vec<*mut ComponentTicks>,
+}
+
+impl Archetype {
+    fn new(types: Vec<TypeId>, columns: Vec<Box<dyn Column>>) -> Self {
+        Self {
+            ticks: vec![Vec::new(); columns.len()],
+            types,
+            columns,
+            entities: Vec::new(),
+            ptrs: Vec::new(),
+            tick_ptrs: Vec::new(),
+        }
+    }
+
+    /// Number of entities in the table
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Check if the table has no entities
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Check if entities in this table have a component type
+    pub fn has(&self, type_id: TypeId) -> bool {
+        self.column_index(type_id).is_some()
+    }
+
+    fn column_index(&self, type_id: TypeId) -> Option<usize> {
+        self.types.binary_search(&type_id).ok()
+    }
+
+    fn column<T: 'static>(&self) -> Option<&Vec<T>> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        self.columns[index].as_any().downcast_ref::<Vec<T>>()
+    }
+
+    fn column_mut<T: 'static>(&mut self, index: usize) -> Option<&mut Vec<T>> {
+        self.columns[index].as_any_mut().downcast_mut::<Vec<T>>()
+    }
+
+    fn refresh_ptrs(&mut self) {
+        self.ptrs.clear();
+        self.ptrs.extend(self.columns.iter_mut().map(|c| c.data_ptr()));
+        self.tick_ptrs.clear();
+        self.tick_ptrs.extend(self.ticks.iter_mut().map(|t| t.as_mut_ptr()));
+    }
+
+    /// Pointer to the component at `row` (call `refresh_ptrs` first)
+    fn row_ptr<T: 'static>(&self, row: usize) -> Option<*mut T> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        Some((self.ptrs[index] as *mut T).wrapping_add(row))
+    }
+
+    /// Pointer to the ticks of the component at `row` (call `refresh_ptrs` first)
+    fn ticks_ptr<T: 'static>(&self, row: usize) -> Option<*mut ComponentTicks> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        Some(self.tick_ptrs[index].wrapping_add(row))
+    }
+}
+
+/// Where an entity's row lives
+#[derive(Debug, Clone, Copy)]
+struct EntityLocation {
+    archetype: usize,
+    row: usize,
+}
+
+/// Read access to an entity
+#[derive(Clone, Copy)]
+pub struct EntityRef<'a> {
+    archetype: &'a Archetype,
+    row: usize,
+    sparse: &'a SparseStorage,
+}
+
+impl<'a> EntityRef<'a> {
+    fn info(&self) -> &'a EntityInfo {
+        &self.archetype.entities[self.row]
+    }
+
+    /// Get the entity ID
+    pub fn id(&self) -> EntityId {
+        self.info().id
+    }
+
+    /// Get the entity name
+    pub fn name(&self) -> &'a str {
+        &self.info().name
+    }
+
+    /// Check if the entity and all of its ancestors are active
+    pub fn is_active(&self) -> bool {
+        self.info().is_active()
+    }
+
+    /// Check the entity's own active flag, ignoring its ancestors
+    pub fn is_self_active(&self) -> bool {
+        self.info().active
+    }
+
+    /// Get a reference to a component
+    pub fn get_component<T: Component>(&self) -> Option<&'a T> {
+        match T::STORAGE {
+            StorageType::Table => self.archetype.column::<T>().map(|column| &column[self.row]),
+            StorageType::Sparse => self.sparse.get::<T>()?.get(self.id()),
+        }
+    }
+
+    /// Check if entity has a specific component
+    pub fn has_component<T: Component>(&self) -> bool {
+        has_component::<T>(self.archetype, self.sparse, self.row)
+    }
+}
+
+/// Check if the entity at `row` of a table has a component
+fn has_component<T: Component>(archetype: &Archetype, sparse: &SparseStorage, row: usize) -> bool {
+    match T::STORAGE {
+        StorageType::Table => archetype.has(TypeId::of::<T>()),
+        StorageType::Sparse => sparse
+            .get::<T>()
+            .is_some_and(|set| set.contains(archetype.entities[row].id)),
+    }
+}
+
+/// Exclusive access to an entity, including adding and removing components
+pub struct EntityMut<'a> {
+    scene: &'a mut Scene,
+    id: EntityId,
+}
+
+impl<'a> EntityMut<'a> {
+    fn location(&self) -> EntityLocation {
+        self.scene.locations[&self.id]
+    }
+
+    fn info(&self) -> &EntityInfo {
+        let location = self.location();
+        &self.scene.archetypes[location.archetype].entities[location.row]
+    }
+
+    /// Get the entity ID
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+
+    /// Get the entity name
+    pub fn name(&self) -> &str {
+        &self.info().name
+    }
+
+    /// Check if the entity and all of its ancestors are active
+    pub fn is_active(&self) -> bool {
+        self.info().is_active()
+    }
+
+    /// Check the entity's own active flag, ignoring its ancestors
+    pub fn is_self_active(&self) -> bool {
+        self.info().active
+    }
+
+    /// Set the entity's own active flag; descendants are inactive while it is off
+    pub fn set_active(&mut self, active: bool) {
+        let location = self.location();
+        self.scene.archetypes[location.archetype].entities[location.row].active = active;
+        self.scene.propagate_active(self.id);
+    }
+
+    /// Rename the entity
+    pub fn set_name(&mut self, name: String) {
+        self.scene.rename_entity(self.id, name);
+    }
+
+    /// Add a component to this entity, replacing one of the same type
+    pub fn add_component<T: Component>(&mut self, component: T) {
+        self.scene.insert_component(self.id, component);
+    }
+
+    /// Get a reference to a component
+    pub fn get_component<T: Component>(&self) -> Option<&T> {
+        self.scene.get_component::<T>(self.id)
+    }
+
+    /// Get a mutable reference to a component
+    pub fn get_component_mut<T: Component>(&mut self) -> Option<&mut T> {
+        self.scene.get_component_mut::<T>(self.id)
+    }
+
+    /// Borrow several components at once (see [`Scene::get_components_mut`])
+    pub fn get_components_mut<Q: QueryData>(&mut self) -> Option<Q::Item<'_>> {
+        self.scene.get_components_mut::<Q>(self.id)
+    }
+
+    /// Check if entity has a specific component
+    pub fn has_component<T: Component>(&self) -> bool {
+        let location = self.location();
+        has_component::<T>(&self.scene.archetypes[location.archetype], &self.scene.sparse, location.row)
+    }
+
+    /// Remove a component from this entity
+    pub fn remove_component<T: Component>(&mut self) -> bool {
+        self.scene.remove_component::<T>(self.id)
+    }
+}
+
+/// Mutable access to an entity's components while iterating over many entities
+///
+/// Adding or removing components moves the entity between tables, so it goes
+/// through [`Scene::get_entity_mut`] after the loop instead.
+pub struct EntityRowMut<'a> {
+    archetype: &'a Archetype,
+    row: usize,
+    sparse: &'a SparseStorage,
+    change_tick: u32,
+}
+
+impl<'a> EntityRowMut<'a> {
+    fn info(&self) -> &'a EntityInfo {
+        &self.archetype.entities[self.row]
+    }
+
+    /// Get the entity ID
+    pub fn id(&self) -> EntityId {
+        self.info().id
+    }
+
+    /// Get the entity name
+    pub fn name(&self) -> &'a str {
+        &self.info().name
+    }
+
+    /// Check if the entity and all of its ancestors are active
+    pub fn is_active(&self) -> bool {
+        self.info().is_active()
+    }
+
+    /// Check the entity's own active flag, ignoring its ancestors
+    pub fn is_self_active(&self) -> bool {
+        self.info().active
+    }
+
+    /// Pointers to a component and its ticks (refreshed by `entities_mut`)
+    fn component_ptrs<T: Component>(&self) -> Option<(*mut T, *mut ComponentTicks)> {
+        match T::STORAGE {
+            StorageType::Table => Some((
+                self.archetype.row_ptr::<T>(self.row)?,
+                self.archetype.ticks_ptr::<T>(self.row)?,
+            )),
+            StorageType::Sparse => {
+                let set = self.sparse.get::<T>()?;
+                let index = set.index(self.id())?;
+                let (values, ticks) = set.mut_ptrs();
+                Some((values.wrapping_add(index), ticks.wrapping_add(index)))
+            }
+        }
+    }
+
+    /// Get a reference to a component
+    pub fn get_component<T: Component>(&self) -> Option<&T> {
+        // SAFETY: the scene is mutably borrowed for 'a and each row is handed out once,
+        // so nothing else reaches this row; `&self` keeps it from being borrowed mutably
+        self.component_ptrs::<T>().map(|(ptr, _)| unsafe { &*ptr })
+    }
+
+    /// Get a mutable reference to a component, marking it changed
+    pub fn get_component_mut<T: Component>(&mut self) -> Option<&mut T> {
+        let (ptr, ticks) = self.component_ptrs::<T>()?;
+        // SAFETY: as above, and `&mut self` makes this the only borrow of the row
+        unsafe {
+            (*ticks).changed = self.change_tick;
+            Some(&mut *ptr)
+        }
+    }
+
+    /// Check if entity has a specific component
+    pub fn has_component<T: Component>(&self) -> bool {
+        has_component::<T>(self.archetype, self.sparse, self.row)
+    }
+}
+
+/// Components a query fetches for every matching entity
+///
+/// Implemented for `&T`, `&mut T`, `Option<&T>`, `Option<&mut T>`, `EntityId`, and
+/// tuples of those.
+///
+/// # Safety
+/// `access` must list every component `fetch` touches, and `fetch` may only read a
+/// component listed as immutable.
+pub unsafe trait QueryData {
+    type Item<'a>;
+    /// Column pointers resolved once per archetype
+    type State: Copy;
+
+    /// Append the component types accessed, and whether mutably
+    fn access(access: &mut Vec<(TypeId, &'static str, bool)>);
+
+    /// Resolve the columns of an archetype, or `None` if it doesn't match
+    fn state(archetype: &Archetype, sparse: &SparseStorage, ticks: ChangeTicks) -> Option<Self::State>;
+
+    /// Check that one row has every required component (only sparse components can
+    /// be missing from a matched table)
+    ///
+    /// # Safety
+    /// `state` must come from `archetype` and `row` must be in bounds.
+    unsafe fn matches(_archetype: &Archetype, _state: Self::State, _row: usize) -> bool {
+        true
+    }
+
+    /// Fetch the components of one row
+    ///
+    /// # Safety
+    /// `state` must come from `archetype`, `row` must be in bounds and pass `matches`,
+    /// and mutable components must not be borrowed elsewhere for `'a`.
+    unsafe fn fetch<'a>(archetype: &'a Archetype, state: Self::State, row: usize) -> Self::Item<'a>;
+}
+
+/// Queries that only read, and so can run on a shared scene
+///
+/// # Safety
+/// The query must not access any component mutably.
+pub unsafe trait ReadOnlyQueryData: QueryData {}
+
+/// Where a query finds one component type: a table column or a sparse set
+pub struct ComponentColumn<T> {
+    values: *mut T,
+    ticks: *mut ComponentTicks,
+    /// Entity-to-index map of a sparse set; null for table columns
+    rows: *const HashMap<EntityId, usize>,
+}
+
+impl<T> Clone for ComponentColumn<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ComponentColumn<T> {}
+
+impl<T: Component> ComponentColumn<T> {
+    /// Resolve the column for reading
+    fn read(archetype: &Archetype, sparse: &SparseStorage) -> Option<Self> {
+        match T::STORAGE {
+            StorageType::Table => {
+                let index = archetype.column_index(TypeId::of::<T>())?;
+                Some(Self {
+                    values: archetype.column::<T>()?.as_ptr() as *mut T,
+                    ticks: archetype.ticks[index].as_ptr() as *mut ComponentTicks,
+                    rows: std::ptr::null(),
+                })
+            }
+            StorageType::Sparse => {
+                let set = sparse.get::<T>()?;
+                Some(Self {
+                    values: set.values_ptr() as *mut T,
+                    ticks: set.ticks_ptr() as *mut ComponentTicks,
+                    rows: set.rows(),
+                })
+            }
+        }
+    }
+
+    /// Resolve the column for writing (pointers must have been refreshed)
+    fn write(archetype: &Archetype, sparse: &SparseStorage) -> Option<Self> {
+        match T::STORAGE {
+            StorageType::Table => Some(Self {
+                values: archetype.row_ptr::<T>(0)?,
+                ticks: archetype.ticks_ptr::<T>(0)?,
+                rows: std::ptr::null(),
+            }),
+            StorageType::Sparse => {
+                let set = sparse.get::<T>()?;
+                let (values, ticks) = set.mut_ptrs();
+                Some(Self {
+                    values,
+                    ticks,
+                    rows: set.rows(),
+                })
+            }
+        }
+    }
+
+    /// Index of the value of the entity at `row`, or `None` if it has none
+    unsafe fn index(&self, archetype: &Archetype, row: usize) -> Option<usize> {
+        if self.rows.is_null() {
+            Some(row)
+        } else {
+            (*self.rows).get(&archetype.entities[row].id).copied()
+        }
+    }
+}
+
+unsafe impl<T: Component> QueryData for &T {
+    type Item<'a> = &'a T;
+    type State = ComponentColumn<T>;
+
+    fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+        access.push((TypeId::of::<T>(), std::any::type_name::<T>(), false));
+    }
+
+    fn state(archetype: &Archetype, sparse: &SparseStorage, _ticks: ChangeTicks) -> Option<Self::State> {
+        ComponentColumn::read(archetype, sparse)
+    }
+
+    unsafe fn matches(archetype: &Archetype, state: Self::State, row: usize) -> bool {
+        state.index(archetype, row).is_some()
+    }
+
+    unsafe fn fetch<'a>(archetype: &'a Archetype, state: Self::State, row: usize) -> Self::Item<'a> {
+        let index = state.index(archetype, row).unwrap_or(row);
+        &*state.values.add(index)
+    }
+}
+
+unsafe impl<T: Component> ReadOnlyQueryData for &T {}
+
+unsafe impl<T: Component> QueryData for &mut T {
+    type Item<'a> = &'a mut T;
+    type State = (ComponentColumn<T>, u32);
+
+    fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+        access.push((TypeId::of::<T>(), std::any::type_name::<T>(), true));
+    }
+
+    fn state(archetype: &Archetype, sparse: &SparseStorage, ticks: ChangeTicks) -> Option<Self::State> {
+        Some((ComponentColumn::write(archetype, sparse)?, ticks.change_tick))
+    }
+
+    unsafe fn matches(archetype: &Archetype, state: Self::State, row: usize) -> bool {
+        state.0.index(archetype, row).is_some()
+    }
+
+    unsafe fn fetch<'a>(archetype: &'a Archetype, state: Self::State, row: usize) -> Self::Item<'a> {
+        let (column, change_tick) = state;
+        let index = column.index(archetype, row).unwrap_or(row);
+        (*column.ticks.add(index)).changed = change_tick;
+        &mut *column.values.add(index)
+    }
+}
+
+unsafe impl<T: Component> QueryData for Option<&T> {
+    type Item<'a> = Option<&'a T>;
+    type State = Option<ComponentColumn<T>>;
+
+    fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+        <&T>::access(access);
+    }
+
+    fn state(archetype: &Archetype, sparse: &SparseStorage, ticks: ChangeTicks) -> Option<Self::State> {
+        Some(<&T>::state(archetype, sparse, ticks))
+    }
+
+    unsafe fn fetch<'a>(archetype: &'a Archetype, state: Self::State, row: usize) -> Self::Item<'a> {
+        state
+            .filter(|&state| <&T>::matches(archetype, state, row))
+            .map(|state| <&T>::fetch(archetype, state, row))
+    }
+}
+
+unsafe impl<T: Component> ReadOnlyQueryData for Option<&T> {}
+
+unsafe impl<T: Component> QueryData for Option<&mut T> {
+    type Item<'a> = Option<&'a mut T>;
+    type State = Option<<&'static mut T as QueryData>::State>;
+
+    fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+        <&mut T>::access(access);
+    }
+
+    fn state(archetype: &Archetype, sparse: &SparseStorage, ticks: ChangeTicks) -> Option<Self::State> {
+        Some(<&mut T>::state(archetype, sparse, ticks))
+    }
+
+    unsafe fn fetch<'a>(archetype: &'a Archetype, state: Self::State, row: usize) -> Self::Item<'a> {
+        state
+            .filter(|&state| <&mut T>::matches(archetype, state, row))
+            .map(|state| <&mut T>::fetch(archetype, state, row))
+    }
+}
+
+unsafe impl QueryData for EntityId {
+    type Item<'a> = EntityId;
+    type State = ();
+
+    fn access(_access: &mut Vec<(TypeId, &'static str, bool)>) {}
+
+    fn state(_archetype: &Archetype, _sparse: &SparseStorage, _ticks: ChangeTicks) -> Option<Self::State> {
+        Some(())
+    }
+
+    unsafe fn fetch<'a>(archetype: &'a Archetype, _state: Self::State, row: usize) -> Self::Item<'a> {
+        archetype.entities[row].id
+    }
+}
+
+unsafe impl ReadOnlyQueryData for EntityId {}
+
+macro_rules! impl_query_tuple {
+    ($($name:ident),+) => {
+        unsafe impl<$($name: QueryData),+> QueryData for ($($name,)+) {
+            type Item<'a> = ($($name::Item<'a>,)+);
+            type State = ($($name::State,)+);
+
+            fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+                $($name::access(access);)+
+            }
+
+            fn state(archetype: &Archetype, sparse: &SparseStorage, ticks: ChangeTicks) -> Option<Self::State> {
+                Some(($($name::state(archetype, sparse, ticks)?,)+))
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn matches(archetype: &Archetype, state: Self::State, row: usize) -> bool {
+                let ($($name,)+) = state;
+                true $(&& $name::matches(archetype, $name, row))+
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn fetch<'a>(archetype: &'a Archetype, state: Self::State, row: usize) -> Self::Item<'a> {
+                let ($($name,)+) = state;
+                ($($name::fetch(archetype, $name, row),)+)
+            }
+        }
+
+        unsafe impl<$($name: ReadOnlyQueryData),+> ReadOnlyQueryData for ($($name,)+) {}
+    };
+}
+
+impl_query_tuple!(A);
+impl_query_tuple!(A, B);
+impl_query_tuple!(A, B, C);
+impl_query_tuple!(A, B, C, D);
+impl_query_tuple!(A, B, C, D, E);
+impl_query_tuple!(A, B, C, D, E, F);
+impl_query_tuple!(A, B, C, D, E, F, G);
+impl_query_tuple!(A, B, C, D, E, F, G, H);
+
+/// Queries [`QueryIter::par_for_each`] accepts: one of [`QueryItem`] or a tuple of them
+/// where no component accessed mutably appears twice
+///
+/// `M` is inferred. If a query accesses a component mutably and names it again, two impls
+/// apply and the call fails to compile ("type annotations needed", pointing at
+/// `Pair<Aliased>`):
+///
+/// ```ignore
+/// scene.query_mut::<(&mut Transform, &Velocity)>().par_for_each(|_| {}); // compiles
+/// scene.query_mut::<(&mut Transform, &Transform)>().par_for_each(|_| {}); // doesn't
+/// ```
+pub trait ParQueryData<M>: QueryData {}
+
+/// A single element of a parallel query: `&T`, `&mut T`, `Option<&T>`,
+/// `Option<&mut T>`, or [`EntityId`]
+pub trait QueryItem: QueryData {
+    /// Whether the component is accessed mutably
+    type Mutable;
+    /// The component type accessed
+    type Target: 'static;
+}
+
+impl<T: Component> QueryItem for &T {
+    type Mutable = par_access::No;
+    type Target = T;
+}
+
+impl<T: Component> QueryItem for &mut T {
+    type Mutable = par_access::Yes;
+    type Target = T;
+}
+
+impl<T: Component> QueryItem for Option<&T> {
+    type Mutable = par_access::No;
+    type Target = T;
+}
+
+impl<T: Component> QueryItem for Option<&mut T> {
+    type Mutable = par_access::Yes;
+    type Target = T;
+}
+
+impl QueryItem for EntityId {
+    type Mutable = par_access::No;
+    type Target = EntityId;
+}
+
+impl<Q: QueryItem> ParQueryData<par_access::Single> for Q {}
+
+/// Type-level aliasing checks for parallel queries
+///
+/// The markers are public in a private module, so `M` of [`ParQueryData`] can't be
+/// named to skip the check.
+mod par_access {
+    use super::QueryItem;
+    use std::marker::PhantomData;
+
+    pub struct Yes;
+    pub struct No;
+    pub struct Single;
+    pub struct ReadRead;
+    pub struct Distinct;
+    pub struct Aliased;
+
+    pub trait AnyMutable {}
+    impl AnyMutable for (Yes, No) {}
+    impl AnyMutable for (No, Yes) {}
+    impl AnyMutable for (Yes, Yes) {}
+
+    /// Implemented once for `(mutable, mutable, target, target)` of two query items
+    /// that may share a row, and twice (so `M` is ambiguous) for ones that alias
+    pub trait Pair<M> {}
+    impl<A, B> Pair<ReadRead> for (No, No, A, B) {}
+    impl<MA, MB, A, B> Pair<Distinct> for (MA, MB, A, B) where (MA, MB): AnyMutable {}
+    impl<MA, MB, T> Pair<Aliased> for (MA, MB, T, T) where (MA, MB): AnyMutable {}
+
+    /// Type-level list of the query items after one
+    pub struct Nil;
+    pub struct Cons<H, T>(PhantomData<(H, T)>);
+
+    /// A query item that doesn't alias any item in the list `L`
+    pub trait DisjointFrom<L, M> {}
+    impl<E> DisjointFrom<Nil, ()> for E {}
+    impl<E: QueryItem, H: QueryItem, T, M1, M2> DisjointFrom<Cons<H, T>, (M1, M2)> for E
+    where
+        (E::Mutable, H::Mutable, E::Target, H::Target): Pair<M1>,
+        E: DisjointFrom<T, M2>,
+    {
+    }
+}
+
+macro_rules! item_list {
+    () => { par_access::Nil };
+    ($head:ident $(, $tail:ident)*) => { par_access::Cons<$head, item_list!($($tail),*)> };
+}
+
+macro_rules! impl_par_query_tuple {
+    ($($name:ident $marker:ident),+) => {
+        impl_par_query_tuple!(@bounds [$($name $marker),+] [] $($name $marker),+);
+    };
+    // Check each item against the items after it
+    (@bounds $all:tt [$($bound:tt)*] $head:ident $head_marker:ident $(, $tail:ident $tail_marker:ident)*) => {
+        impl_par_query_tuple!(
+            @bounds $all
+            [$($bound)* $head: par_access::DisjointFrom<item_list!($($tail),*), $head_marker>,]
+            $($tail $tail_marker),*
+        );
+    };
+    (@bounds [$($name:ident $marker:ident),+] [$($bound:tt)*]) => {
+        impl<$($name: QueryItem, $marker),+> ParQueryData<($($marker,)+)> for ($($name,)+) where $($bound)* {}
+    };
+}
+
+impl_par_query_tuple!(A MA);
+impl_par_query_tuple!(A MA, B MB);
+impl_par_query_tuple!(A MA, B MB, C MC);
+impl_par_query_tuple!(A MA, B MB, C MC, D MD);
+impl_par_query_tuple!(A MA, B MB, C MC, D MD, E ME);
+impl_par_query_tuple!(A MA, B MB, C MC, D MD, E ME, F MF);
+impl_par_query_tuple!(A MA, B MB, C MC, D MD, E ME, F MF, G MG);
+impl_par_query_tuple!(A MA, B MB, C MC, D MD, E ME, F MF, G MG, H MH);
+
+/// Conditions on entities a query yields, on top of the components it fetches
+///
+/// Implemented for [`With`], [`Without`], [`Changed`], [`Added`], `()` (no filter), and
+/// tuples (all must match).
+pub trait QueryFilter {
+    /// Data resolved once per archetype
+    type State: Copy;
+
+    /// Resolve an archetype, or `None` if none of its entities can match
+    fn state(archetype: &Archetype, sparse: &SparseStorage, ticks: ChangeTicks) -> Option<Self::State>;
+
+    /// Check one row
+    ///
+    /// # Safety
+    /// `state` must come from `archetype` and `row` must be in bounds.
+    unsafe fn matches(archetype: &Archetype, state: Self::State, row: usize) -> bool;
+
+    /// List the components whose data the filter reads, as in [`QueryData::access`]
+    fn access(_access: &mut Vec<(TypeId, &'static str, bool)>) {}
+}
+
+/// Filter for entities that have a `T`, without fetching it
+///
+/// Meant for zero-sized tag components such as `struct Dead;`, which take no storage.
+pub struct With<T>(PhantomData<T>);
+
+/// Filter for entities that don't have a `T`
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    type State = ComponentColumn<T>;
+
+    fn state(archetype: &Archetype, sparse: &SparseStorage, _ticks: ChangeTicks) -> Option<Self::State> {
+        ComponentColumn::read(archetype, sparse)
+    }
+
+    unsafe fn matches(archetype: &Archetype, state: Self::State, row: usize) -> bool {
+        state.index(archetype, row).is_some()
+    }
+}
+
+impl<T: Component> QueryFilter for Without<T> {
+    type State = Option<ComponentColumn<T>>;
+
+    fn state(archetype: &Archetype, sparse: &SparseStorage, _ticks: ChangeTicks) -> Option<Self::State> {
+        match T::STORAGE {
+            StorageType::Table => (!archetype.has(TypeId::of::<T>())).then_some(None),
+            StorageType::Sparse => Some(ComponentColumn::read(archetype, sparse)),
+        }
+    }
+
+    unsafe fn matches(archetype: &Archetype, state: Self::State, row: usize) -> bool {
+        state.is_none_or(|state| state.index(archetype, row).is_none())
+    }
+}
+
+/// Filter for entities whose `T` was added or mutably accessed since the last
+/// [`Scene::clear_trackers`]
+pub struct Changed<T>(PhantomData<T>);
+
+/// Filter for entities whose `T` was added since the last [`Scene::clear_trackers`]
+pub struct Added<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Changed<T> {
+    type State = (ComponentColumn<T>, u32);
+
+    fn state(archetype: &Archetype, sparse: &SparseStorage, ticks: ChangeTicks) -> Option<Self::State> {
+        Some((ComponentColumn::read(archetype, sparse)?, ticks.last_change_tick))
+    }
+
+    unsafe fn matches(archetype: &Archetype, state: Self::State, row: usize) -> bool {
+        let (column, last_change_tick) = state;
+        column
+            .index(archetype, row)
+            .is_some_and(|index| (*column.ticks.add(index)).changed > last_change_tick)
+    }
+
+    fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+        access.push((TypeId::of::<T>(), std::any::type_name::<T>(), false));
+    }
+}
+
+impl<T: Component> QueryFilter for Added<T> {
+    type State = (ComponentColumn<T>, u32);
+
+    fn state(archetype: &Archetype, sparse: &SparseStorage, ticks: ChangeTicks) -> Option<Self::State> {
+        Some((ComponentColumn::read(archetype, sparse)?, ticks.last_change_tick))
+    }
+
+    unsafe fn matches(archetype: &Archetype, state: Self::State, row: usize) -> bool {
+        let (column, last_change_tick) = state;
+        column
+            .index(archetype, row)
+            .is_some_and(|index| (*column.ticks.add(index)).added > last_change_tick)
+    }
+
+    fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+        access.push((TypeId::of::<T>(), std::any::type_name::<T>(), false));
+    }
+}
+
+impl QueryFilter for () {
+    type State = ();
+
+    fn state(_archetype: &Archetype, _sparse: &SparseStorage, _ticks: ChangeTicks) -> Option<Self::State> {
+        Some(())
+    }
+
+    unsafe fn matches(_archetype: &Archetype, _state: Self::State, _row: usize) -> bool {
+        true
+    }
+}
+
+macro_rules! impl_filter_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: QueryFilter),+> QueryFilter for ($($name,)+) {
+            type State = ($($name::State,)+);
 
-use std::any::{Any, TypeId};
-use std::collections::HashMap;
+            fn state(archetype: &Archetype, sparse: &SparseStorage, ticks: ChangeTicks) -> Option<Self::State> {
+                Some(($($name::state(archetype, sparse, ticks)?,)+))
+            }
 
-/// Unique identifier for entities
-pub type EntityId = u64;
+            #[allow(non_snake_case)]
+            unsafe fn matches(archetype: &Archetype, state: Self::State, row: usize) -> bool {
+                let ($($name,)+) = state;
+                true $(&& $name::matches(archetype, $name, row))+
+            }
 
-/// Trait that all components must implement
-pub trait Component: Any + 'static {
-    /// Returns a unique identifier for this component type
-    fn type_id(&self) -> TypeId {
-        TypeId::of::<Self>()
-    }
+            fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+                $($name::access(access);)+
+            }
+        }
+    };
 }
 
-// Implement Component for common types
-impl Component for crate::math::Transform {}
-impl Component for crate::math::Transform2D {}
+impl_filter_tuple!(A);
+impl_filter_tuple!(A, B);
+impl_filter_tuple!(A, B, C);
+impl_filter_tuple!(A, B, C, D);
 
-/// An entity in the game world
-#[derive(Debug)]
-pub struct Entity {
-    id: EntityId,
-    name: String,
-    active: bool,
-    components: HashMap<TypeId, Box<dyn Any>>,
+/// Indices of the tables a query visits
+enum Tables<'a> {
+    All(std::ops::Range<usize>),
+    Cached(std::slice::Iter<'a, usize>),
+}
+
+impl Iterator for Tables<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        match self {
+            Tables::All(range) => range.next(),
+            Tables::Cached(indices) => indices.next().copied(),
+        }
+    }
+}
+
+/// Iterator over the active entities matching a query and filter
+pub struct QueryIter<'a, Q: QueryData, F: QueryFilter = ()> {
+    archetypes: &'a [Archetype],
+    sparse: &'a SparseStorage,
+    tables: Tables<'a>,
+    ticks: ChangeTicks,
+    current: Option<(&'a Archetype, Q::State, F::State)>,
+    row: usize,
+    _marker: PhantomData<(Q, F)>,
 }
 
-impl Entity {
-    /// Create a new entity
-    pub fn new(id: EntityId, name: String) -> Self {
+impl<'a, Q: QueryData, F: QueryFilter> QueryIter<'a, Q, F> {
+    fn new(scene: &'a Scene, tables: Tables<'a>) -> Self {
         Self {
-            id,
-            name,
-            active: true,
-            components: HashMap::new(),
+            archetypes: &scene.archetypes,
+            sparse: &scene.sparse,
+            tables,
+            ticks: scene.ticks(),
+            current: None,
+            row: 0,
+            _marker: PhantomData,
         }
     }
 
-    /// Get the entity ID
-    pub fn id(&self) -> EntityId {
-        self.id
+    fn resolve(
+        archetype: &'a Archetype,
+        sparse: &SparseStorage,
+        ticks: ChangeTicks,
+    ) -> Option<(&'a Archetype, Q::State, F::State)> {
+        Some((archetype, Q::state(archetype, sparse, ticks)?, F::state(archetype, sparse, ticks)?))
     }
+}
 
-    /// Get the entity name
-    pub fn name(&self) -> &str {
-        &self.name
+/// Lets table pointers cross threads during parallel iteration
+struct Shared<T>(T);
+
+// SAFETY: only used by `par_for_each`, where every row is fetched by one thread and
+// the `Send` bound on query items rules out components that can't cross threads
+unsafe impl<T> Send for Shared<T> {}
+unsafe impl<T> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    // A method so closures capture the whole wrapper rather than its field
+    fn get(&self) -> &T {
+        &self.0
     }
+}
 
-    /// Check if the entity is active
-    pub fn is_active(&self) -> bool {
-        self.active
+impl<'a, Q: QueryData, F: QueryFilter> QueryIter<'a, Q, F> {
+    /// Run `f` for every remaining entity, spreading rows across the rayon thread pool
+    ///
+    /// Items must be `Send`, so `&T` needs `T: Sync` and `&mut T` needs `T: Send`;
+    /// components that aren't thread-safe fail to compile here, and so do queries that
+    /// access a component mutably and name it again, e.g. `(&mut T, &T)` (see
+    /// [`ParQueryData`]).
+    pub fn par_for_each<G, M>(self, f: G)
+    where
+        Q: ParQueryData<M>,
+        G: Fn(Q::Item<'a>) + Send + Sync,
+        Q::Item<'a>: Send,
+    {
+        let (archetypes, sparse, ticks) = (self.archetypes, self.sparse, self.ticks);
+        let current = self.current.map(|table| (table, self.row));
+        let rest = self
+            .tables
+            .filter_map(|index| Self::resolve(&archetypes[index], sparse, ticks).map(|table| (table, 0)));
+        for (table, start) in current.into_iter().chain(rest) {
+            let archetype = table.0;
+            let table = Shared(table);
+            (start..archetype.len()).into_par_iter().for_each(|row| {
+                let &(archetype, state, filter) = table.get();
+                // SAFETY: as in `next`; rows are split between threads, never shared
+                unsafe {
+                    if archetype.entities[row].is_active()
+                        && Q::matches(archetype, state, row)
+                        && F::matches(archetype, filter, row)
+                    {
+                        f(Q::fetch(archetype, state, row));
+                    }
+                }
+            });
+        }
     }
+}
 
-    /// Set entity active state
-    pub fn set_active(&mut self, active: bool) {
-        self.active = active;
+impl<'a, Q: QueryData, F: QueryFilter> Iterator for QueryIter<'a, Q, F> {
+    type Item = Q::Item<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((archetype, state, filter)) = self.current {
+                while self.row < archetype.len() {
+                    let row = self.row;
+                    self.row += 1;
+                    // SAFETY: the states belong to this archetype, the row is in
+                    // bounds, and each row is yielded once per query
+                    unsafe {
+                        if archetype.entities[row].is_active()
+                            && Q::matches(archetype, state, row)
+                            && F::matches(archetype, filter, row)
+                        {
+                            return Some(Q::fetch(archetype, state, row));
+                        }
+                    }
+                }
+            }
+            let index = self.tables.next()?;
+            self.current = Self::resolve(&self.archetypes[index], self.sparse, self.ticks);
+            self.row = 0;
+        }
     }
+}
 
-    /// Add a component to this entity
-    pub fn add_component<T: Component>(&mut self, component: T) {
-        let type_id = TypeId::of::<T>();
-        self.components.insert(type_id, Box::new(component));
+/// Panic if a query borrows the same component mutably more than once
+///
+/// Runs whenever a query is created; [`QueryIter::par_for_each`] also rejects such
+/// queries at compile time.
+fn check_access<Q: QueryData>() {
+    let mut access = Vec::new();
+    Q::access(&mut access);
+    for (i, (type_id, name, mutable)) in access.iter().enumerate() {
+        let conflict = access[i + 1..].iter().any(|(other, _, other_mutable)| other == type_id && (*mutable || *other_mutable));
+        assert!(!conflict, "Query accesses {} mutably more than once", name);
     }
+}
 
-    /// Get a reference to a component
-    pub fn get_component<T: Component>(&self) -> Option<&T> {
-        let type_id = TypeId::of::<T>();
-        self.components.get(&type_id)?.downcast_ref::<T>()
+/// A query that remembers which tables match, for loops that run every frame
+///
+/// Plain queries check every table of the scene; a cached query only checks tables
+/// created since its last use. Matching is redone if the scene is cleared, a sparse
+/// component type is stored for the first time, or the query is used with another scene.
+///
+/// ```ignore
+/// let mut movers = CachedQuery::<(&mut Transform, &Velocity)>::new();
+/// // every frame:
+/// for (transform, velocity) in movers.iter_mut(scene) { /* ... */ }
+/// ```
+pub struct CachedQuery<Q: QueryData, F: QueryFilter = ()> {
+    layout_id: u64,
+    checked: usize,
+    matched: Vec<usize>,
+    _marker: PhantomData<(Q, F)>,
+}
+
+impl<Q: QueryData, F: QueryFilter> Default for CachedQuery<Q, F> {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    /// Get a mutable reference to a component
-    pub fn get_component_mut<T: Component>(&mut self) -> Option<&mut T> {
-        let type_id = TypeId::of::<T>();
-        self.components.get_mut(&type_id)?.downcast_mut::<T>()
+impl<Q: QueryData, F: QueryFilter> CachedQuery<Q, F> {
+    /// Create a query; panics if it borrows a component mutably twice
+    pub fn new() -> Self {
+        check_access::<Q>();
+        Self {
+            layout_id: 0,
+            checked: 0,
+            matched: Vec::new(),
+            _marker: PhantomData,
+        }
     }
 
-    /// Check if entity has a specific component
-    pub fn has_component<T: Component>(&self) -> bool {
-        let type_id = TypeId::of::<T>();
-        self.components.contains_key(&type_id)
+    /// Number of tables matched so far
+    pub fn matched_tables(&self) -> usize {
+        self.matched.len()
     }
 
-    /// Remove a component from this entity
-    pub fn remove_component<T: Component>(&mut self) -> bool {
-        let type_id = TypeId::of::<T>();
-        self.components.remove(&type_id).is_some()
+    /// Check tables created since the last call
+    fn update(&mut self, scene: &Scene) {
+        if self.layout_id != scene.layout_id {
+            self.layout_id = scene.layout_id;
+            self.checked = 0;
+            self.matched.clear();
+        }
+        let ticks = scene.ticks();
+        for (index, archetype) in scene.archetypes.iter().enumerate().skip(self.checked) {
+            if Q::state(archetype, &scene.sparse, ticks).is_some() && F::state(archetype, &scene.sparse, ticks).is_some() {
+                self.matched.push(index);
+            }
+        }
+        self.checked = scene.archetypes.len();
+    }
+
+    /// Iterate the matching active entities
+    pub fn iter<'a>(&'a mut self, scene: &'a Scene) -> QueryIter<'a, Q, F>
+    where
+        Q: ReadOnlyQueryData,
+    {
+        self.update(scene);
+        QueryIter::new(scene, Tables::Cached(self.matched.iter()))
+    }
+
+    /// Iterate and modify the matching active entities
+    pub fn iter_mut<'a>(&'a mut self, scene: &'a mut Scene) -> QueryIter<'a, Q, F> {
+        // Tables checked for the first time need fresh pointers too
+        let start = if self.layout_id == scene.layout_id { self.checked } else { 0 };
+        for archetype in &mut scene.archetypes[start..] {
+            archetype.refresh_ptrs();
+        }
+        self.update(scene);
+        for &index in &self.matched {
+            scene.archetypes[index].refresh_ptrs();
+        }
+        scene.sparse.refresh_ptrs();
+        QueryIter::new(scene, Tables::Cached(self.matched.iter()))
     }
 }
 
+/// Source of `Scene::layout_id` values
+static NEXT_LAYOUT_ID: AtomicU64 = AtomicU64::new(1);
+
 /// A scene manages a collection of entities
 pub struct Scene {
-    entities: HashMap<EntityId, Entity>,
-    next_entity_id: EntityId,
+    archetypes: Vec<Archetype>,
+    /// Identifies the current set of tables and sparse sets; changes when tables are
+    /// reset or a sparse set is created
+    pub(crate) layout_id: u64,
+    /// Components stored with `StorageType::Sparse`
+    sparse: SparseStorage,
+    archetype_index: HashMap<Vec<TypeId>, usize>,
+    locations: HashMap<EntityId, EntityLocation>,
+    /// Entity IDs by name (names needn't be unique)
+    names: BTreeMap<String, BTreeSet<EntityId>>,
+    /// Component types `clone_entity` can copy
+    pub(crate) cloners: HashMap<TypeId, CloneFn>,
+    pub(crate) next_entity_id: EntityId,
+    /// Tick recorded by component additions and mutable access
+    pub(crate) change_tick: u32,
+    /// Tick of the last `clear_trackers`
+    last_change_tick: u32,
+    /// Tick of the last transform propagation, which tracks changes on its own
+    pub(crate) propagated_tick: u32,
+    /// Called with each entity right before it is removed
+    removal_hooks: Vec<RemovalHook>,
+    /// Entities removed since the last `clear_trackers`
+    removed: Vec<EntityId>,
+    /// Latest snapshot, whose unchanged components the next one shares
+    pub(crate) last_snapshot: RefCell<Option<SceneSnapshot>>,
     name: String,
 }
 
+type RemovalHook = Box<dyn FnMut(&EntityRef)>;
+
 impl Scene {
     /// Create a new scene
     pub fn new(name: String) -> Self {
         log::info!("Created scene: {}", name);
-        Self {
-            entities: HashMap::new(),
+        let mut scene = Self {
+            archetypes: Vec::new(),
+            layout_id: 0,
+            sparse: SparseStorage::default(),
+            archetype_index: HashMap::new(),
+            locations: HashMap::new(),
+            names: BTreeMap::new(),
+            cloners: HashMap::new(),
             next_entity_id: 0,
+            change_tick: 1,
+            last_change_tick: 0,
+            propagated_tick: 0,
+            removal_hooks: Vec::new(),
+            removed: Vec::new(),
+            last_snapshot: RefCell::new(None),
             name,
-        }
+        };
+        scene.reset_archetypes();
+        duplicate::register_builtin_clones(&mut scene);
+        scene
+    }
+
+    /// Start over with only the table of entities without components
+    fn reset_archetypes(&mut self) {
+        self.archetypes = vec![Archetype::new(Vec::new(), Vec::new())];
+        self.sparse = SparseStorage::default();
+        self.layout_id = NEXT_LAYOUT_ID.fetch_add(1, Ordering::Relaxed);
+        self.archetype_index = HashMap::from([(Vec::new(), 0)]);
     }
 
     /// Get scene name
@@ -117,70 +1250,586 @@ impl Scene {
     /// Create a new entity in this scene
     pub fn create_entity(&mut self, name: String) -> EntityId {
         let id = self.next_entity_id;
-        self.next_entity_id += 1;
+        self.insert_entity(id, name);
+        id
+    }
+
+    /// Create an entity with a specific ID (e.g. when loading a saved scene)
+    ///
+    /// Fails if the ID is already taken. Later `create_entity` calls never reuse it.
+    pub(crate) fn create_entity_with_id(&mut self, id: EntityId, name: String) -> bool {
+        if self.locations.contains_key(&id) {
+            return false;
+        }
+        self.insert_entity(id, name);
+        true
+    }
 
-        let entity = Entity::new(id, name);
-        self.entities.insert(id, entity);
+    fn insert_entity(&mut self, id: EntityId, name: String) {
+        self.next_entity_id = self.next_entity_id.max(id + 1);
+        self.names.entry(name.clone()).or_default().insert(id);
+
+        let empty = &mut self.archetypes[0];
+        empty.entities.push(EntityInfo {
+            id,
+            name,
+            active: true,
+            parent_active: true,
+        });
+        self.locations.insert(
+            id,
+            EntityLocation {
+                archetype: 0,
+                row: empty.len() - 1,
+            },
+        );
 
         log::debug!("Created entity with ID: {}", id);
-        id
+    }
+
+    /// Rename an entity
+    pub fn rename_entity(&mut self, id: EntityId, name: String) -> bool {
+        let Some(location) = self.locations.get(&id) else {
+            return false;
+        };
+        let info = &mut self.archetypes[location.archetype].entities[location.row];
+        let old = std::mem::replace(&mut info.name, name.clone());
+        self.unindex_name(&old, id);
+        self.names.entry(name).or_default().insert(id);
+        true
+    }
+
+    fn unindex_name(&mut self, name: &str, id: EntityId) {
+        if let Some(ids) = self.names.get_mut(name) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.names.remove(name);
+            }
+        }
+    }
+
+    /// Find an entity by exact name (the oldest one if several share it)
+    pub fn find_by_name(&self, name: &str) -> Option<EntityId> {
+        self.names.get(name)?.first().copied()
+    }
+
+    /// Find all entities whose name starts with `prefix`, ordered by name then ID
+    pub fn find_all_by_prefix(&self, prefix: &str) -> Vec<EntityId> {
+        self.names
+            .range::<str, _>((std::ops::Bound::Included(prefix), std::ops::Bound::Unbounded))
+            .take_while(|(name, _)| name.starts_with(prefix))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect()
     }
 
     /// Get a reference to an entity
-    pub fn get_entity(&self, id: EntityId) -> Option<&Entity> {
-        self.entities.get(&id)
+    pub fn get_entity(&self, id: EntityId) -> Option<EntityRef<'_>> {
+        let location = self.locations.get(&id)?;
+        Some(EntityRef {
+            archetype: &self.archetypes[location.archetype],
+            row: location.row,
+            sparse: &self.sparse,
+        })
     }
 
     /// Get a mutable reference to an entity
-    pub fn get_entity_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
-        self.entities.get_mut(&id)
+    pub fn get_entity_mut(&mut self, id: EntityId) -> Option<EntityMut<'_>> {
+        self.locations.contains_key(&id).then_some(EntityMut { scene: self, id })
+    }
+
+    /// Get a component of an entity
+    pub fn get_component<T: Component>(&self, id: EntityId) -> Option<&T> {
+        self.get_entity(id)?.get_component::<T>()
+    }
+
+    /// Get a mutable component of an entity, marking it changed
+    pub fn get_component_mut<T: Component>(&mut self, id: EntityId) -> Option<&mut T> {
+        let location = *self.locations.get(&id)?;
+        if T::STORAGE == StorageType::Sparse {
+            return self.sparse.get_mut::<T>()?.get_mut(id, self.change_tick);
+        }
+        let archetype = &mut self.archetypes[location.archetype];
+        let index = archetype.column_index(TypeId::of::<T>())?;
+        archetype.ticks[index][location.row].changed = self.change_tick;
+        archetype.column_mut::<T>(index).map(|column| &mut column[location.row])
+    }
+
+    /// Borrow several components of one entity at once, e.g. `(&mut Transform, &mut Velocity)`
+    ///
+    /// Accepts the same tuples as [`Scene::query_mut`] and marks `&mut` components
+    /// changed. Returns `None` if the entity doesn't exist or lacks a required
+    /// component; panics if a component type is borrowed mutably twice.
+    pub fn get_components_mut<Q: QueryData>(&mut self, id: EntityId) -> Option<Q::Item<'_>> {
+        check_access::<Q>();
+        let location = *self.locations.get(&id)?;
+        let ticks = self.ticks();
+        self.archetypes[location.archetype].refresh_ptrs();
+        self.sparse.refresh_ptrs();
+        let archetype = &self.archetypes[location.archetype];
+        let state = Q::state(archetype, &self.sparse, ticks)?;
+        // SAFETY: the state belongs to this archetype, the row is in bounds, and the
+        // access check rules out two borrows of one component; `&mut self` keeps
+        // anything else from reaching the scene while the items live
+        unsafe {
+            if !Q::matches(archetype, state, location.row) {
+                return None;
+            }
+            Some(Q::fetch(archetype, state, location.row))
+        }
+    }
+
+    fn component_ticks<T: Component>(&self, id: EntityId) -> Option<ComponentTicks> {
+        self.component_ticks_by_type(id, TypeId::of::<T>())
+    }
+
+    /// Ticks of an entity's component of a type, or `None` if it has none
+    pub(crate) fn component_ticks_by_type(&self, id: EntityId, type_id: TypeId) -> Option<ComponentTicks> {
+        let location = self.locations.get(&id)?;
+        if let Some(ticks) = self.sparse.ticks(type_id, id) {
+            return Some(ticks);
+        }
+        let archetype = &self.archetypes[location.archetype];
+        Some(archetype.ticks[archetype.column_index(type_id)?][location.row])
+    }
+
+    /// Check if a component was added to an entity since the last [`Scene::clear_trackers`]
+    pub fn is_added<T: Component>(&self, id: EntityId) -> bool {
+        self.component_ticks::<T>(id)
+            .is_some_and(|ticks| ticks.added > self.last_change_tick)
+    }
+
+    /// Check if a component was added or mutably accessed since the last [`Scene::clear_trackers`]
+    pub fn is_changed<T: Component>(&self, id: EntityId) -> bool {
+        self.component_ticks::<T>(id)
+            .is_some_and(|ticks| ticks.changed > self.last_change_tick)
+    }
+
+    /// Mark a component as changed without touching it
+    pub fn mark_changed<T: Component>(&mut self, id: EntityId) {
+        if T::STORAGE == StorageType::Sparse {
+            if let Some(ticks) = self.sparse.get_mut::<T>().and_then(|set| set.ticks_mut(id)) {
+                ticks.changed = self.change_tick;
+            }
+        } else if let Some(location) = self.locations.get(&id) {
+            let archetype = &mut self.archetypes[location.archetype];
+            if let Some(index) = archetype.column_index(TypeId::of::<T>()) {
+                archetype.ticks[index][location.row].changed = self.change_tick;
+            }
+        }
+    }
+
+    /// Start a new change-detection period: components count as added or changed again
+    /// only once they're added or mutably accessed after this call
+    ///
+    /// The engine calls this at the end of every frame, after rendering.
+    pub fn clear_trackers(&mut self) {
+        self.last_change_tick = self.change_tick;
+        self.change_tick += 1;
+        self.removed.clear();
+    }
+
+    /// Run `f` with change detection going back to tick `since` instead of the last
+    /// [`Scene::clear_trackers`], returning its result and the tick to go back to next time
+    pub(crate) fn with_changes_since<R>(&mut self, since: u32, f: impl FnOnce(&mut Self) -> R) -> (R, u32) {
+        let last_change_tick = std::mem::replace(&mut self.last_change_tick, since);
+        let result = f(self);
+        self.last_change_tick = last_change_tick;
+        let tick = self.change_tick;
+        // Changes made after `f` must be newer than the returned tick
+        self.change_tick += 1;
+        (result, tick)
+    }
+
+    /// Entities removed since the last [`Scene::clear_trackers`], in removal order
+    ///
+    /// The engine clears them at the end of the frame, so every stage sees them.
+    pub fn removed_entities(&self) -> &[EntityId] {
+        &self.removed
+    }
+
+    /// Run `hook` with every entity right before it is removed, while its components
+    /// can still be read (not when the whole scene is cleared)
+    pub fn on_entity_removed<F: FnMut(&EntityRef) + 'static>(&mut self, hook: F) {
+        self.removal_hooks.push(Box::new(hook));
+    }
+
+    /// Run the removal hooks for an entity that is about to be removed
+    pub(crate) fn run_removal_hooks(&mut self, id: EntityId) {
+        if self.removal_hooks.is_empty() {
+            return;
+        }
+        let mut hooks = std::mem::take(&mut self.removal_hooks);
+        if let Some(entity) = self.get_entity(id) {
+            for hook in &mut hooks {
+                hook(&entity);
+            }
+        }
+        self.removal_hooks = hooks;
     }
 
-    /// Remove an entity from the scene
+    fn ticks(&self) -> ChangeTicks {
+        ChangeTicks {
+            last_change_tick: self.last_change_tick,
+            change_tick: self.change_tick,
+        }
+    }
+
+    /// Remove an entity from the scene (its children become roots)
     pub fn remove_entity(&mut self, id: EntityId) -> bool {
-        self.entities.remove(&id).is_some()
+        if !self.locations.contains_key(&id) {
+            return false;
+        }
+        self.run_removal_hooks(id);
+        self.detach_from_hierarchy(id);
+        self.remove_row(id)
+    }
+
+    /// Drop an entity's row without touching its hierarchy links or running hooks
+    pub(crate) fn remove_row(&mut self, id: EntityId) -> bool {
+        let Some(location) = self.locations.remove(&id) else {
+            return false;
+        };
+        let archetype = &mut self.archetypes[location.archetype];
+        for column in &mut archetype.columns {
+            column.swap_remove(location.row);
+        }
+        for ticks in &mut archetype.ticks {
+            ticks.swap_remove(location.row);
+        }
+        let info = archetype.entities.swap_remove(location.row);
+        if let Some(moved) = archetype.entities.get(location.row) {
+            self.locations.insert(moved.id, location);
+        }
+        self.unindex_name(&info.name, id);
+        self.sparse.remove_entity(id);
+        self.removed.push(id);
+        true
+    }
+
+    /// Move an entity with all of its components into another scene, returning its
+    /// new ID there
+    ///
+    /// The entity leaves its hierarchy first, so its children stay behind as roots.
+    /// In the target scene its components count as just added.
+    pub fn transfer_entity(&mut self, id: EntityId, target: &mut Scene) -> Option<EntityId> {
+        if !self.locations.contains_key(&id) {
+            return None;
+        }
+        self.detach_from_hierarchy(id);
+        let location = self.locations.remove(&id)?;
+        let source = &mut self.archetypes[location.archetype];
+
+        let index = match target.archetype_index.get(&source.types) {
+            Some(&index) => index,
+            None => {
+                let columns = source.columns.iter().map(|c| c.empty()).collect();
+                target.archetypes.push(Archetype::new(source.types.clone(), columns));
+                target.archetype_index.insert(source.types.clone(), target.archetypes.len() - 1);
+                target.archetypes.len() - 1
+            }
+        };
+        // Both tables have the same sorted types, so their columns line up
+        let destination = &mut target.archetypes[index];
+        for (i, column) in source.columns.iter_mut().enumerate() {
+            column.move_row(location.row, destination.columns[i].as_mut());
+            source.ticks[i].swap_remove(location.row);
+            destination.ticks[i].push(ComponentTicks::new(target.change_tick));
+        }
+        let info = source.entities.swap_remove(location.row);
+        if let Some(moved) = source.entities.get(location.row) {
+            self.locations.insert(moved.id, location);
+        }
+        self.unindex_name(&info.name, id);
+
+        let new_id = target.next_entity_id;
+        target.next_entity_id += 1;
+        let sparse_sets = target.sparse.set_count();
+        self.sparse.transfer_entity(id, &mut target.sparse, new_id, target.change_tick);
+        if target.sparse.set_count() != sparse_sets {
+            target.layout_id = NEXT_LAYOUT_ID.fetch_add(1, Ordering::Relaxed);
+        }
+        target.names.entry(info.name.clone()).or_default().insert(new_id);
+        // The entity arrives as a root
+        destination.entities.push(EntityInfo {
+            id: new_id,
+            parent_active: true,
+            ..info
+        });
+        target.locations.insert(
+            new_id,
+            EntityLocation {
+                archetype: index,
+                row: destination.len() - 1,
+            },
+        );
+        Some(new_id)
     }
 
     /// Get all entities
-    pub fn entities(&self) -> impl Iterator<Item = &Entity> {
-        self.entities.values()
+    pub fn entities(&self) -> impl Iterator<Item = EntityRef<'_>> {
+        let sparse = &self.sparse;
+        self.archetypes.iter().flat_map(move |archetype| {
+            (0..archetype.len()).map(move |row| EntityRef {
+                archetype,
+                row,
+                sparse,
+            })
+        })
     }
 
     /// Get all entities (mutable)
-    pub fn entities_mut(&mut self) -> impl Iterator<Item = &mut Entity> {
-        self.entities.values_mut()
+    pub fn entities_mut(&mut self) -> impl Iterator<Item = EntityRowMut<'_>> {
+        for archetype in &mut self.archetypes {
+            archetype.refresh_ptrs();
+        }
+        self.sparse.refresh_ptrs();
+        let change_tick = self.change_tick;
+        let sparse = &self.sparse;
+        self.archetypes.iter().flat_map(move |archetype| {
+            (0..archetype.len()).map(move |row| EntityRowMut {
+                archetype,
+                row,
+                sparse,
+                change_tick,
+            })
+        })
+    }
+
+    /// Update the inherited active state of an entity's descendants
+    pub(crate) fn propagate_active(&mut self, id: EntityId) {
+        let mut stack = vec![id];
+        while let Some(parent) = stack.pop() {
+            let active = self.get_entity(parent).is_none_or(|entity| entity.is_active());
+            for child in self.children(parent).to_vec() {
+                self.set_parent_active(child, active);
+                stack.push(child);
+            }
+        }
+    }
+
+    /// Set whether all ancestors of an entity are active, without updating its children
+    pub(crate) fn set_parent_active(&mut self, id: EntityId, active: bool) {
+        if let Some(location) = self.locations.get(&id) {
+            self.archetypes[location.archetype].entities[location.row].parent_active = active;
+        }
     }
 
-    /// Get all active entities
-    pub fn active_entities(&self) -> impl Iterator<Item = &Entity> {
-        self.entities.values().filter(|e| e.is_active())
+    /// Get all active entities (including their ancestors)
+    pub fn active_entities(&self) -> impl Iterator<Item = EntityRef<'_>> {
+        self.entities().filter(|e| e.is_active())
     }
 
     /// Get all active entities (mutable)
-    pub fn active_entities_mut(&mut self) -> impl Iterator<Item = &mut Entity> {
-        self.entities.values_mut().filter(|e| e.is_active())
+    pub fn active_entities_mut(&mut self) -> impl Iterator<Item = EntityRowMut<'_>> {
+        self.entities_mut().filter(|e| e.is_active())
+    }
+
+    /// Iterate the components of every active entity that has them
+    ///
+    /// ```ignore
+    /// for (id, transform) in scene.query::<(EntityId, &Transform)>() { /* ... */ }
+    /// ```
+    pub fn query<Q: ReadOnlyQueryData>(&self) -> QueryIter<'_, Q> {
+        self.query_filtered::<Q, ()>()
+    }
+
+    /// Iterate and modify the components of every active entity that has them
+    ///
+    /// Mutably fetched components are marked changed. Panics if the query borrows a
+    /// component mutably twice, e.g. `(&mut T, &T)`.
+    pub fn query_mut<Q: QueryData>(&mut self) -> QueryIter<'_, Q> {
+        self.query_filtered_mut::<Q, ()>()
+    }
+
+    /// Like [`Scene::query`], only yielding entities that pass a filter
+    ///
+    /// ```ignore
+    /// for transform in scene.query_filtered::<&Transform, Changed<Transform>>() { /* ... */ }
+    /// ```
+    pub fn query_filtered<Q: ReadOnlyQueryData, F: QueryFilter>(&self) -> QueryIter<'_, Q, F> {
+        QueryIter::new(self, Tables::All(0..self.archetypes.len()))
+    }
+
+    /// Like [`Scene::query_mut`], only yielding entities that pass a filter
+    pub fn query_filtered_mut<Q: QueryData, F: QueryFilter>(&mut self) -> QueryIter<'_, Q, F> {
+        check_access::<Q>();
+        self.refresh_write_ptrs();
+        QueryIter::new(self, Tables::All(0..self.archetypes.len()))
+    }
+
+    /// Refresh the column pointers mutable queries write through
+    pub(crate) fn refresh_write_ptrs(&mut self) {
+        for archetype in &mut self.archetypes {
+            archetype.refresh_ptrs();
+        }
+        self.sparse.refresh_ptrs();
+    }
+
+    /// Like [`Scene::query_filtered_mut`] through a shared reference
+    ///
+    /// # Safety
+    /// [`Scene::refresh_write_ptrs`] must have been called since the scene was last
+    /// modified, and no other live query may access a component this one writes.
+    pub(crate) unsafe fn query_shared<Q: QueryData, F: QueryFilter>(&self) -> QueryIter<'_, Q, F> {
+        check_access::<Q>();
+        QueryIter::new(self, Tables::All(0..self.archetypes.len()))
     }
 
     /// Find entities with a specific component
+    ///
+    /// Allocates; in per-frame code prefer `query_filtered::<EntityId, With<T>>()`.
     pub fn find_entities_with<T: Component>(&self) -> Vec<EntityId> {
-        self.entities
-            .values()
-            .filter(|e| e.has_component::<T>())
-            .map(|e| e.id())
+        if T::STORAGE == StorageType::Sparse {
+            return self.sparse.get::<T>().map_or_else(Vec::new, |set| set.entities().to_vec());
+        }
+        self.archetypes
+            .iter()
+            .filter(|a| a.has(TypeId::of::<T>()))
+            .flat_map(|a| a.entities.iter().map(|e| e.id))
             .collect()
     }
 
     /// Get count of entities
     pub fn entity_count(&self) -> usize {
-        self.entities.len()
+        self.locations.len()
+    }
+
+    /// Get count of archetype tables (distinct component sets seen so far)
+    pub fn archetype_count(&self) -> usize {
+        self.archetypes.len()
     }
 
     /// Clear all entities from the scene
     pub fn clear(&mut self) {
-        self.entities.clear();
+        self.reset_archetypes();
+        self.last_snapshot.take();
+        self.locations.clear();
+        self.names.clear();
         self.next_entity_id = 0;
         log::info!("Cleared scene: {}", self.name);
     }
+
+    fn insert_component<T: Component>(&mut self, id: EntityId, component: T) {
+        let Some(location) = self.locations.get(&id).copied() else {
+            return;
+        };
+        if T::STORAGE == StorageType::Sparse {
+            if self.sparse.get::<T>().is_none() {
+                // Cached queries may have skipped tables for lack of this set
+                self.layout_id = NEXT_LAYOUT_ID.fetch_add(1, Ordering::Relaxed);
+            }
+            self.sparse.get_or_create::<T>().insert(id, component, self.change_tick);
+            return;
+        }
+        let archetype = &mut self.archetypes[location.archetype];
+        if let Some(index) = archetype.column_index(TypeId::of::<T>()) {
+            archetype.ticks[index][location.row].changed = self.change_tick;
+            if let Some(column) = archetype.column_mut::<T>(index) {
+                column[location.row] = component;
+            }
+            return;
+        }
+
+        let source = &self.archetypes[location.archetype];
+        let mut types = source.types.clone();
+        let index = types.binary_search(&TypeId::of::<T>()).unwrap_err();
+        types.insert(index, TypeId::of::<T>());
+        let target = self.find_or_create_archetype(types, location.archetype, Some(Box::new(Vec::<T>::new())));
+
+        self.move_entity(id, location, target);
+        let archetype = &mut self.archetypes[target];
+        let Some(index) = archetype.column_index(TypeId::of::<T>()) else {
+            return;
+        };
+        archetype.ticks[index].push(ComponentTicks::new(self.change_tick));
+        if let Some(column) = archetype.column_mut::<T>(index) {
+            column.push(component);
+        }
+    }
+
+    fn remove_component<T: Component>(&mut self, id: EntityId) -> bool {
+        self.remove_component_by_type(id, TypeId::of::<T>())
+    }
+
+    /// Remove an entity's component of a type
+    pub(crate) fn remove_component_by_type(&mut self, id: EntityId, type_id: TypeId) -> bool {
+        let Some(location) = self.locations.get(&id).copied() else {
+            return false;
+        };
+        if self.sparse.remove(type_id, id) {
+            return true;
+        }
+        let source = &self.archetypes[location.archetype];
+        if !source.has(type_id) {
+            return false;
+        }
+
+        let types = source.types.iter().copied().filter(|t| *t != type_id).collect();
+        let target = self.find_or_create_archetype(types, location.archetype, None);
+        self.move_entity(id, location, target);
+        true
+    }
+
+    /// Get the table for a set of types, creating its columns from an existing table
+    fn find_or_create_archetype(
+        &mut self,
+        types: Vec<TypeId>,
+        template: usize,
+        added: Option<Box<dyn Column>>,
+    ) -> usize {
+        if let Some(&index) = self.archetype_index.get(&types) {
+            return index;
+        }
+        let template = &self.archetypes[template];
+        let mut added = added;
+        let columns = types
+            .iter()
+            .map(|type_id| match template.column_index(*type_id) {
+                Some(index) => template.columns[index].empty(),
+                None => added.take().expect("new archetype adds one column"),
+            })
+            .collect();
+
+        let index = self.archetypes.len();
+        self.archetypes.push(Archetype::new(types.clone(), columns));
+        self.archetype_index.insert(types, index);
+        index
+    }
+
+    /// Move an entity's row to another table, dropping components the target lacks
+    fn move_entity(&mut self, id: EntityId, location: EntityLocation, target: usize) {
+        let (source, destination) = if location.archetype < target {
+            let (left, right) = self.archetypes.split_at_mut(target);
+            (&mut left[location.archetype], &mut right[0])
+        } else {
+            let (left, right) = self.archetypes.split_at_mut(location.archetype);
+            (&mut right[0], &mut left[target])
+        };
+
+        for (i, type_id) in source.types.iter().enumerate() {
+            let ticks = source.ticks[i].swap_remove(location.row);
+            match destination.column_index(*type_id) {
+                Some(index) => {
+                    source.columns[i].move_row(location.row, destination.columns[index].as_mut());
+                    destination.ticks[index].push(ticks);
+                }
+                None => source.columns[i].swap_remove(location.row),
+            }
+        }
+        let info = source.entities.swap_remove(location.row);
+        if let Some(moved) = source.entities.get(location.row) {
+            self.locations.insert(moved.id, location);
+        }
+
+        destination.entities.push(info);
+        self.locations.insert(
+            id,
+            EntityLocation {
+                archetype: target,
+                row: destination.len() - 1,
+            },
+        );
+    }
 }
 
 impl Default for Scene {
@@ -209,9 +1858,15 @@ mod tests {
     }
     impl Component for TestComponent {}
 
+    #[derive(Debug, PartialEq)]
+    struct Velocity(f32);
+    impl Component for Velocity {}
+
     #[test]
     fn test_entity_components() {
-        let mut entity = Entity::new(0, "Test".to_string());
+        let mut scene = Scene::new("Test Scene".to_string());
+        let id = scene.create_entity("Test".to_string());
+        let mut entity = scene.get_entity_mut(id).unwrap();
         entity.add_component(TestComponent { value: 42 });
 
         assert!(entity.has_component::<TestComponent>());
@@ -226,4 +1881,244 @@ mod tests {
         assert_eq!(scene.entity_count(), 1);
         assert!(scene.get_entity(id).is_some());
     }
+
+    #[test]
+    fn test_rows_move_between_archetypes() {
+        let mut scene = Scene::new("Test".to_string());
+        let ids: Vec<EntityId> = (0..3).map(|i| scene.create_entity(format!("E{}", i))).collect();
+        for (i, id) in ids.iter().enumerate() {
+            let mut entity = scene.get_entity_mut(*id).unwrap();
+            entity.add_component(TestComponent { value: i as i32 });
+            entity.add_component(Velocity(i as f32));
+        }
+
+        // Moving the first row out swaps the last one into its place
+        assert!(scene.get_entity_mut(ids[0]).unwrap().remove_component::<Velocity>());
+        assert!(!scene.get_entity_mut(ids[0]).unwrap().remove_component::<Velocity>());
+        for (i, id) in ids.iter().enumerate() {
+            let entity = scene.get_entity(*id).unwrap();
+            assert_eq!(entity.get_component::<TestComponent>().unwrap().value, i as i32);
+            assert_eq!(entity.has_component::<Velocity>(), i != 0);
+        }
+
+        assert!(scene.remove_entity(ids[1]));
+        assert_eq!(scene.get_component::<Velocity>(ids[2]), Some(&Velocity(2.0)));
+        assert_eq!(scene.entity_count(), 2);
+        assert_eq!(scene.find_entities_with::<Velocity>(), [ids[2]]);
+    }
+
+    #[test]
+    fn test_queries() {
+        let mut scene = Scene::new("Test".to_string());
+        for i in 0..4 {
+            let id = scene.create_entity(format!("E{}", i));
+            let mut entity = scene.get_entity_mut(id).unwrap();
+            entity.add_component(TestComponent { value: i });
+            if i % 2 == 0 {
+                entity.add_component(Velocity(1.0));
+            }
+            entity.set_active(i != 2);
+        }
+
+        for (component, velocity) in scene.query_mut::<(&mut TestComponent, &Velocity)>() {
+            component.value += velocity.0 as i32 * 10;
+        }
+        let mut values: Vec<i32> = scene.query::<&TestComponent>().map(|c| c.value).collect();
+        values.sort();
+        assert_eq!(values, [1, 3, 10]);
+
+        for mut entity in scene.active_entities_mut() {
+            if let Some(velocity) = entity.get_component_mut::<Velocity>() {
+                velocity.0 = 2.0;
+            }
+        }
+        let with_velocity = scene.query::<(EntityId, Option<&Velocity>)>().filter(|(_, v)| v.is_some()).count();
+        assert_eq!(with_velocity, 1);
+    }
+
+    #[test]
+    fn test_par_for_each() {
+        let mut scene = Scene::new("Test".to_string());
+        for i in 0..1000 {
+            let id = scene.create_entity(format!("E{}", i));
+            let mut entity = scene.get_entity_mut(id).unwrap();
+            entity.add_component(Velocity(i as f32));
+            if i % 3 == 0 {
+                entity.add_component(TestComponent { value: 0 });
+            }
+        }
+
+        scene
+            .query_mut::<(&Velocity, &mut TestComponent)>()
+            .par_for_each(|(velocity, component)| component.value = velocity.0 as i32);
+        let matches = scene.query::<(&Velocity, &TestComponent)>().filter(|(v, c)| v.0 as i32 == c.value).count();
+        assert_eq!(matches, 334);
+
+        // Several mutable components, optional ones, and IDs are fine as long as none repeats
+        scene
+            .query_mut::<(EntityId, &mut Velocity, Option<&mut TestComponent>)>()
+            .par_for_each(|(_, velocity, component)| {
+                if let Some(component) = component {
+                    velocity.0 = -component.value as f32;
+                }
+            });
+        assert_eq!(scene.query::<&Velocity>().filter(|v| v.0 < 0.0).count(), 333);
+    }
+
+    #[test]
+    fn test_change_detection() {
+        let mut scene = Scene::new("Test".to_string());
+        let ids: Vec<EntityId> = (0..3).map(|i| scene.create_entity(format!("E{}", i))).collect();
+        for id in &ids {
+            scene.get_entity_mut(*id).unwrap().add_component(Velocity(0.0));
+        }
+        assert_eq!(scene.query_filtered::<EntityId, Added<Velocity>>().count(), 3);
+
+        scene.clear_trackers();
+        assert_eq!(scene.query_filtered::<EntityId, Changed<Velocity>>().count(), 0);
+        scene.get_component_mut::<Velocity>(ids[0]).unwrap().0 = 1.0;
+        for velocity in scene.query_filtered_mut::<&mut Velocity, Changed<Velocity>>() {
+            velocity.0 += 1.0;
+        }
+        scene.get_entity_mut(ids[2]).unwrap().add_component(TestComponent { value: 0 });
+        for mut entity in scene.active_entities_mut().filter(|e| e.id() == ids[1]) {
+            entity.get_component_mut::<Velocity>();
+        }
+
+        // Moving to another table keeps the ticks
+        let changed: Vec<EntityId> = scene.query_filtered::<EntityId, Changed<Velocity>>().collect();
+        assert_eq!(changed, [ids[0], ids[1]]);
+        assert_eq!(scene.get_component::<Velocity>(ids[0]), Some(&Velocity(2.0)));
+        let added: Vec<EntityId> = scene
+            .query_filtered::<EntityId, (Added<TestComponent>, Changed<TestComponent>)>()
+            .collect();
+        assert_eq!(added, [ids[2]]);
+        assert!(!scene.is_added::<Velocity>(ids[2]));
+    }
+
+    #[test]
+    fn test_tag_filters() {
+        #[derive(Debug)]
+        struct Dead;
+        impl Component for Dead {}
+
+        let mut scene = Scene::new("Test".to_string());
+        for i in 0..6 {
+            let id = scene.create_entity(format!("E{}", i));
+            let mut entity = scene.get_entity_mut(id).unwrap();
+            entity.add_component(Velocity(i as f32));
+            if i % 2 == 0 {
+                entity.add_component(Dead);
+            }
+        }
+
+        for velocity in scene.query_filtered_mut::<&mut Velocity, With<Dead>>() {
+            velocity.0 = -1.0;
+        }
+        let alive: Vec<f32> = scene.query_filtered::<&Velocity, Without<Dead>>().map(|v| v.0).collect();
+        assert_eq!(alive, [1.0, 3.0, 5.0]);
+        let dead = scene.query_filtered::<EntityId, (With<Dead>, With<Velocity>)>().count();
+        assert_eq!(dead, 3);
+        assert_eq!(scene.query::<&Dead>().count(), 3);
+
+        let first = scene.find_entities_with::<Dead>()[0];
+        assert!(scene.get_entity_mut(first).unwrap().remove_component::<Dead>());
+        assert_eq!(scene.query_filtered::<EntityId, With<Dead>>().count(), 2);
+    }
+
+    #[test]
+    fn test_name_index() {
+        let mut scene = Scene::new("Test".to_string());
+        let player = scene.create_entity("Player".to_string());
+        let enemies: Vec<EntityId> = (0..3).map(|i| scene.create_entity(format!("Enemy_{}", i))).collect();
+        let boss = scene.create_entity("Enemy".to_string());
+
+        assert_eq!(scene.find_by_name("Player"), Some(player));
+        assert_eq!(scene.find_by_name("Nobody"), None);
+        assert_eq!(scene.find_all_by_prefix("Enemy_"), enemies);
+        assert_eq!(scene.find_all_by_prefix("Enemy").len(), 4);
+
+        scene.remove_entity(enemies[1]);
+        assert!(scene.rename_entity(boss, "Enemy_Boss".to_string()));
+        scene.get_entity_mut(player).unwrap().set_name("Hero".to_string());
+        assert_eq!(scene.find_by_name("Player"), None);
+        assert_eq!(scene.find_by_name("Hero"), Some(player));
+        assert_eq!(scene.get_entity(boss).unwrap().name(), "Enemy_Boss");
+        assert_eq!(scene.find_all_by_prefix("Enemy_"), [enemies[0], enemies[2], boss]);
+
+        // Duplicate names resolve to the oldest entity
+        let twin = scene.create_entity("Hero".to_string());
+        assert_eq!(scene.find_by_name("Hero"), Some(player));
+        scene.remove_entity(player);
+        assert_eq!(scene.find_by_name("Hero"), Some(twin));
+    }
+
+    #[test]
+    fn test_get_components_mut() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.spawn((TestComponent { value: 1 }, Velocity(2.0)));
+
+        let mut entity = scene.get_entity_mut(id).unwrap();
+        let (component, velocity) = entity.get_components_mut::<(&mut TestComponent, &mut Velocity)>().unwrap();
+        component.value += 1;
+        velocity.0 *= 2.0;
+        assert!(entity.get_components_mut::<(&TestComponent, &mut crate::math::Transform)>().is_none());
+
+        let (component, velocity) = scene.get_components_mut::<(&TestComponent, Option<&Velocity>)>(id).unwrap();
+        assert_eq!((component.value, velocity.map(|v| v.0)), (2, Some(4.0)));
+        assert!(scene.is_changed::<Velocity>(id));
+        assert!(scene.get_components_mut::<&Velocity>(99).is_none());
+    }
+
+    #[test]
+    fn test_cached_query() {
+        struct Marker;
+        impl Component for Marker {}
+
+        let mut scene = Scene::new("Test".to_string());
+        let mut query = CachedQuery::<(&mut TestComponent, &Velocity)>::new();
+        let spawn = |scene: &mut Scene, velocity: bool| {
+            let id = scene.create_entity("E".to_string());
+            let mut entity = scene.get_entity_mut(id).unwrap();
+            entity.add_component(TestComponent { value: 0 });
+            if velocity {
+                entity.add_component(Velocity(1.0));
+            }
+        };
+        spawn(&mut scene, true);
+        spawn(&mut scene, false);
+
+        for (component, velocity) in query.iter_mut(&mut scene) {
+            component.value += velocity.0 as i32;
+        }
+        assert_eq!(query.matched_tables(), 1);
+
+        // Entities in new tables are picked up
+        spawn(&mut scene, true);
+        let id = scene.create_entity("Tagged".to_string());
+        let mut entity = scene.get_entity_mut(id).unwrap();
+        entity.add_component(Velocity(5.0));
+        entity.add_component(TestComponent { value: 0 });
+        entity.add_component(Marker);
+        for (component, velocity) in query.iter_mut(&mut scene) {
+            component.value += velocity.0 as i32;
+        }
+        assert_eq!(query.matched_tables(), 2);
+        let mut values: Vec<i32> = scene.query::<&TestComponent>().map(|c| c.value).collect();
+        values.sort();
+        assert_eq!(values, [0, 1, 2, 5]);
+
+        let mut readonly = CachedQuery::<&Velocity, With<Marker>>::new();
+        assert_eq!(readonly.iter(&scene).count(), 1);
+        scene.clear();
+        assert_eq!(readonly.iter(&scene).count(), 0);
+        assert_eq!(query.iter_mut(&mut scene).count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_query_aliasing_rejected() {
+        let mut scene = Scene::new("Test".to_string());
+        let _ = scene.query_mut::<(&mut Velocity, &Velocity)>();
+    }
 }
@@ -0,0 +1,658 @@
+//! Editor primitives: selection, transform gizmos, and undo
+//!
+//! Building blocks for an in-game or external level editor. [`Selection`]
+//! picks entities with a ray, [`Gizmo`] moves, rotates, and scales them with
+//! the mouse and draws itself through [`DebugDraw`], and [`EditHistory`]
+//! makes every edit undoable. [`Editor`] bundles them with default bindings;
+//! the engine runs it every frame when set with `Engine::set_editor`.
+
+use crate::debug_draw::DebugDraw;
+use crate::ecs::{EntityId, Scene};
+use crate::input::{InputManager, Key, MouseButton};
+use crate::math::Transform;
+use crate::renderer::{Camera, Color};
+use glam::{Quat, Vec3};
+
+/// Find the closest entity hit by a ray, using bounding spheres
+/// (radius = half the largest scale axis of the entity's `Transform`)
+pub fn pick_entity(scene: &Scene, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<(EntityId, f32)> {
+    let direction = direction.try_normalize()?;
+    let mut closest: Option<(EntityId, f32)> = None;
+
+    for entity in scene.active_entities() {
+        let Some(transform) = entity.get_component::<Transform>() else {
+            continue;
+        };
+        let radius = transform.scale.max_element() * 0.5;
+        let to_center = transform.position - origin;
+        let along = to_center.dot(direction);
+        let distance_sq = to_center.length_squared() - along * along;
+        if distance_sq > radius * radius {
+            continue;
+        }
+        let offset = (radius * radius - distance_sq).sqrt();
+        let t = if along - offset >= 0.0 { along - offset } else { along + offset };
+        if t < 0.0 || t > max_distance {
+            continue;
+        }
+        if closest.is_none_or(|(_, best)| t < best) {
+            closest = Some((entity.id(), t));
+        }
+    }
+
+    closest
+}
+
+/// Set of selected entities; the last one selected is the primary
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    entities: Vec<EntityId>,
+}
+
+impl Selection {
+    /// Create an empty selection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the selection with a single entity
+    pub fn select(&mut self, id: EntityId) {
+        self.entities.clear();
+        self.entities.push(id);
+    }
+
+    /// Add an entity to the selection
+    pub fn add(&mut self, id: EntityId) {
+        self.entities.retain(|e| *e != id);
+        self.entities.push(id);
+    }
+
+    /// Add the entity if it isn't selected, otherwise remove it
+    pub fn toggle(&mut self, id: EntityId) {
+        if self.contains(id) {
+            self.remove(id);
+        } else {
+            self.add(id);
+        }
+    }
+
+    /// Remove an entity from the selection
+    pub fn remove(&mut self, id: EntityId) {
+        self.entities.retain(|e| *e != id);
+    }
+
+    /// Deselect everything
+    pub fn clear(&mut self) {
+        self.entities.clear();
+    }
+
+    /// Check if an entity is selected
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.entities.contains(&id)
+    }
+
+    /// Check if nothing is selected
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Get the selected entities in selection order
+    pub fn entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    /// Get the most recently selected entity
+    pub fn primary(&self) -> Option<EntityId> {
+        self.entities.last().copied()
+    }
+
+    /// Drop entities that no longer exist
+    pub fn retain_existing(&mut self, scene: &Scene) {
+        self.entities.retain(|id| scene.get_entity(*id).is_some());
+    }
+
+    /// Get the center of the selected entities' transforms
+    pub fn pivot(&self, scene: &Scene) -> Option<Vec3> {
+        let positions: Vec<Vec3> = self
+            .entities
+            .iter()
+            .filter_map(|id| scene.get_entity(*id)?.get_component::<Transform>())
+            .map(|t| t.position)
+            .collect();
+        if positions.is_empty() {
+            return None;
+        }
+        Some(positions.iter().sum::<Vec3>() / positions.len() as f32)
+    }
+
+    /// Select the entity under a ray; `additive` toggles it instead of replacing the selection
+    ///
+    /// Clicking empty space clears a non-additive selection.
+    pub fn pick(&mut self, scene: &Scene, origin: Vec3, direction: Vec3, additive: bool) -> Option<EntityId> {
+        let hit = pick_entity(scene, origin, direction, f32::MAX).map(|(id, _)| id);
+        match (hit, additive) {
+            (Some(id), true) => self.toggle(id),
+            (Some(id), false) => self.select(id),
+            (None, false) => self.clear(),
+            (None, true) => {}
+        }
+        hit
+    }
+}
+
+/// A group of transform changes undone and redone together
+#[derive(Debug, Clone)]
+pub struct TransformEdit {
+    /// (entity, transform before, transform after)
+    pub changes: Vec<(EntityId, Transform, Transform)>,
+}
+
+impl TransformEdit {
+    fn apply(&self, scene: &mut Scene, undo: bool) {
+        for (id, before, after) in &self.changes {
+            if let Some(transform) = scene.get_component_mut::<Transform>(*id) {
+                *transform = if undo { *before } else { *after };
+            }
+        }
+    }
+}
+
+/// Undo/redo stack of transform edits
+#[derive(Debug, Clone)]
+pub struct EditHistory {
+    undo: Vec<TransformEdit>,
+    redo: Vec<TransformEdit>,
+    limit: usize,
+}
+
+impl EditHistory {
+    /// Create a history keeping at most `limit` undo steps
+    pub fn new(limit: usize) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            limit: limit.max(1),
+        }
+    }
+
+    /// Record an edit that has already been applied
+    pub fn push(&mut self, edit: TransformEdit) {
+        if edit.changes.iter().all(|(_, before, after)| before == after) {
+            return;
+        }
+        self.redo.clear();
+        self.undo.push(edit);
+        if self.undo.len() > self.limit {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Set an entity's transform as an undoable edit
+    pub fn set_transform(&mut self, scene: &mut Scene, id: EntityId, transform: Transform) -> bool {
+        let Some(current) = scene.get_component_mut::<Transform>(id) else {
+            return false;
+        };
+        let before = *current;
+        *current = transform;
+        self.push(TransformEdit {
+            changes: vec![(id, before, transform)],
+        });
+        true
+    }
+
+    /// Revert the most recent edit
+    pub fn undo(&mut self, scene: &mut Scene) -> bool {
+        let Some(edit) = self.undo.pop() else {
+            return false;
+        };
+        edit.apply(scene, true);
+        self.redo.push(edit);
+        true
+    }
+
+    /// Reapply the most recently undone edit
+    pub fn redo(&mut self, scene: &mut Scene) -> bool {
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        edit.apply(scene, false);
+        self.undo.push(edit);
+        true
+    }
+
+    /// Check if there is an edit to undo
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Check if there is an edit to redo
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forget all edits
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+/// What a gizmo manipulates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    /// Scales along the entity's local axes
+    Scale,
+}
+
+/// Gizmo handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    pub const ALL: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+    /// Get the world-space direction of the axis
+    pub fn direction(self) -> Vec3 {
+        match self {
+            GizmoAxis::X => Vec3::X,
+            GizmoAxis::Y => Vec3::Y,
+            GizmoAxis::Z => Vec3::Z,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            GizmoAxis::X => Color::RED,
+            GizmoAxis::Y => Color::GREEN,
+            GizmoAxis::Z => Color::BLUE,
+        }
+    }
+}
+
+/// In-progress gizmo drag
+#[derive(Debug, Clone)]
+struct Drag {
+    axis: GizmoAxis,
+    pivot: Vec3,
+    length: f32,
+    /// Axis parameter (translate/scale) or unit vector in the rotation plane (rotate)
+    start: Vec3,
+    transforms: Vec<(EntityId, Transform)>,
+}
+
+/// Translate/rotate/scale handles for the selection
+#[derive(Debug, Clone)]
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    /// Handle length as a fraction of the camera distance, keeping a constant on-screen size
+    pub screen_size: f32,
+    hovered: Option<GizmoAxis>,
+    drag: Option<Drag>,
+}
+
+impl Gizmo {
+    /// Create a translate gizmo
+    pub fn new() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+            screen_size: 0.15,
+            hovered: None,
+            drag: None,
+        }
+    }
+
+    /// Get the axis under the mouse (or being dragged)
+    pub fn hovered(&self) -> Option<GizmoAxis> {
+        self.drag.as_ref().map(|d| d.axis).or(self.hovered)
+    }
+
+    /// Check if a handle is being dragged
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    fn handle_length(&self, camera_position: Vec3, pivot: Vec3) -> f32 {
+        ((camera_position - pivot).length() * self.screen_size).max(0.001)
+    }
+
+    /// Closest points between a ray and an axis line, as (ray param, axis param, distance)
+    fn ray_axis(origin: Vec3, direction: Vec3, pivot: Vec3, axis: Vec3) -> Option<(f32, f32, f32)> {
+        let w = origin - pivot;
+        let b = direction.dot(axis);
+        let d = direction.dot(w);
+        let e = axis.dot(w);
+        let denom = 1.0 - b * b;
+        if denom.abs() < 1e-6 {
+            return None;
+        }
+        let s = (b * e - d) / denom;
+        let t = (e - b * d) / denom;
+        let distance = ((origin + direction * s) - (pivot + axis * t)).length();
+        Some((s, t, distance))
+    }
+
+    /// Point where a ray crosses the plane through `pivot` with normal `axis`
+    fn ray_plane(origin: Vec3, direction: Vec3, pivot: Vec3, axis: Vec3) -> Option<(f32, Vec3)> {
+        let facing = direction.dot(axis);
+        if facing.abs() < 1e-6 {
+            return None;
+        }
+        let s = (pivot - origin).dot(axis) / facing;
+        (s >= 0.0).then(|| (s, origin + direction * s))
+    }
+
+    /// Find the handle hit by a ray
+    fn hit(&self, pivot: Vec3, length: f32, origin: Vec3, direction: Vec3) -> Option<GizmoAxis> {
+        let tolerance = length * 0.1;
+        let mut best: Option<(GizmoAxis, f32)> = None;
+        for axis in GizmoAxis::ALL {
+            let hit = match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => Self::ray_axis(origin, direction, pivot, axis.direction())
+                    .filter(|(s, t, distance)| *s > 0.0 && *t >= 0.0 && *t <= length && *distance <= tolerance)
+                    .map(|(s, _, _)| s),
+                GizmoMode::Rotate => Self::ray_plane(origin, direction, pivot, axis.direction())
+                    .filter(|(_, point)| ((*point - pivot).length() - length).abs() <= tolerance)
+                    .map(|(s, _)| s),
+            };
+            if let Some(s) = hit {
+                if best.is_none_or(|(_, b)| s < b) {
+                    best = Some((axis, s));
+                }
+            }
+        }
+        best.map(|(axis, _)| axis)
+    }
+
+    /// Drag start value for an axis: axis parameter or plane direction
+    fn drag_value(&self, axis: GizmoAxis, pivot: Vec3, origin: Vec3, direction: Vec3) -> Option<Vec3> {
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                Self::ray_axis(origin, direction, pivot, axis.direction()).map(|(_, t, _)| Vec3::splat(t))
+            }
+            GizmoMode::Rotate => Self::ray_plane(origin, direction, pivot, axis.direction())
+                .and_then(|(_, point)| (point - pivot).try_normalize()),
+        }
+    }
+
+    /// Update which handle is under a ray
+    pub fn hover(&mut self, scene: &Scene, selection: &Selection, camera_position: Vec3, origin: Vec3, direction: Vec3) {
+        if self.drag.is_some() {
+            return;
+        }
+        self.hovered = selection.pivot(scene).and_then(|pivot| {
+            let length = self.handle_length(camera_position, pivot);
+            self.hit(pivot, length, origin, direction)
+        });
+    }
+
+    /// Start dragging the handle under a ray, returning false if none was hit
+    pub fn begin_drag(
+        &mut self,
+        scene: &Scene,
+        selection: &Selection,
+        camera_position: Vec3,
+        origin: Vec3,
+        direction: Vec3,
+    ) -> bool {
+        let Some(pivot) = selection.pivot(scene) else {
+            return false;
+        };
+        let length = self.handle_length(camera_position, pivot);
+        let Some(axis) = self.hit(pivot, length, origin, direction) else {
+            return false;
+        };
+        let Some(start) = self.drag_value(axis, pivot, origin, direction) else {
+            return false;
+        };
+        let transforms = selection
+            .entities()
+            .iter()
+            .filter_map(|id| Some((*id, *scene.get_entity(*id)?.get_component::<Transform>()?)))
+            .collect();
+        self.drag = Some(Drag {
+            axis,
+            pivot,
+            length,
+            start,
+            transforms,
+        });
+        true
+    }
+
+    /// Move the dragged handle to follow a ray
+    pub fn drag(&mut self, scene: &mut Scene, origin: Vec3, direction: Vec3) {
+        let Some(drag) = &self.drag else {
+            return;
+        };
+        let Some(current) = self.drag_value(drag.axis, drag.pivot, origin, direction) else {
+            return;
+        };
+        let axis = drag.axis.direction();
+
+        for (id, start) in &drag.transforms {
+            let mut transform = *start;
+            match self.mode {
+                GizmoMode::Translate => {
+                    transform.position += axis * (current.x - drag.start.x);
+                }
+                GizmoMode::Rotate => {
+                    let angle = axis.dot(drag.start.cross(current)).atan2(drag.start.dot(current));
+                    let rotation = Quat::from_axis_angle(axis, angle);
+                    transform.position = drag.pivot + rotation * (start.position - drag.pivot);
+                    transform.rotation = (rotation * start.rotation).normalize();
+                }
+                GizmoMode::Scale => {
+                    let factor = (1.0 + (current.x - drag.start.x) / drag.length).max(0.01);
+                    let mut scale = Vec3::ONE;
+                    scale[drag.axis as usize] = factor;
+                    transform.scale = start.scale * scale;
+                }
+            }
+            if let Some(target) = scene.get_component_mut::<Transform>(*id) {
+                *target = transform;
+            }
+        }
+    }
+
+    /// Finish dragging, returning the edit to record in the history
+    pub fn end_drag(&mut self, scene: &Scene) -> Option<TransformEdit> {
+        let drag = self.drag.take()?;
+        let changes = drag
+            .transforms
+            .into_iter()
+            .filter_map(|(id, before)| {
+                let after = *scene.get_entity(id)?.get_component::<Transform>()?;
+                Some((id, before, after))
+            })
+            .collect();
+        Some(TransformEdit { changes })
+    }
+
+    /// Cancel the drag, restoring the original transforms
+    pub fn cancel_drag(&mut self, scene: &mut Scene) {
+        if let Some(edit) = self.end_drag(scene) {
+            edit.apply(scene, true);
+        }
+    }
+
+    /// Draw the handles at the selection pivot
+    pub fn draw(&self, scene: &Scene, selection: &Selection, camera_position: Vec3, debug: &mut DebugDraw) {
+        let Some(pivot) = selection.pivot(scene) else {
+            return;
+        };
+        let length = self.handle_length(camera_position, pivot);
+        let highlighted = self.hovered();
+
+        for axis in GizmoAxis::ALL {
+            let color = if highlighted == Some(axis) { Color::YELLOW } else { axis.color() };
+            let direction = axis.direction();
+            let end = pivot + direction * length;
+            match self.mode {
+                GizmoMode::Translate => {
+                    debug.draw_line(pivot, end, color);
+                    // Arrow head
+                    let side = direction.any_orthonormal_vector() * length * 0.06;
+                    let back = end - direction * length * 0.15;
+                    debug.draw_line(end, back + side, color);
+                    debug.draw_line(end, back - side, color);
+                }
+                GizmoMode::Rotate => debug.draw_circle(pivot, direction, length, color),
+                GizmoMode::Scale => {
+                    debug.draw_line(pivot, end, color);
+                    let half = Vec3::splat(length * 0.05);
+                    debug.draw_aabb(end - half, end + half, color);
+                }
+            }
+        }
+    }
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Selection, gizmo, and undo history with default mouse and keyboard bindings
+///
+/// - Left click: select (Shift adds/removes), drag a handle to edit
+/// - W / E / R: translate / rotate / scale
+/// - Ctrl+Z / Ctrl+Y (or Ctrl+Shift+Z): undo / redo
+/// - Escape: cancel the current drag or clear the selection
+#[derive(Debug, Clone, Default)]
+pub struct Editor {
+    pub selection: Selection,
+    pub gizmo: Gizmo,
+    pub history: EditHistory,
+}
+
+impl Editor {
+    /// Create a new editor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process input for this frame; returns true if the editor used the mouse
+    pub fn update(&mut self, scene: &mut Scene, input: &InputManager, camera: &Camera, viewport: (u32, u32)) -> bool {
+        self.selection.retain_existing(scene);
+        let (origin, direction) = camera.screen_to_ray(input.mouse_position(), viewport);
+
+        let ctrl = input.key_pressed(Key::ControlLeft) || input.key_pressed(Key::ControlRight);
+        let shift = input.key_pressed(Key::ShiftLeft) || input.key_pressed(Key::ShiftRight);
+
+        if input.key_just_pressed(Key::Escape) {
+            if self.gizmo.is_dragging() {
+                self.gizmo.cancel_drag(scene);
+            } else {
+                self.selection.clear();
+            }
+        }
+
+        if self.gizmo.is_dragging() {
+            if input.mouse_button_pressed(MouseButton::Left) {
+                self.gizmo.drag(scene, origin, direction);
+            } else if let Some(edit) = self.gizmo.end_drag(scene) {
+                self.history.push(edit);
+            }
+            return true;
+        }
+
+        if ctrl {
+            if input.key_just_pressed(Key::KeyZ) {
+                if shift {
+                    self.history.redo(scene);
+                } else {
+                    self.history.undo(scene);
+                }
+            } else if input.key_just_pressed(Key::KeyY) {
+                self.history.redo(scene);
+            }
+        } else if input.key_just_pressed(Key::KeyW) {
+            self.gizmo.mode = GizmoMode::Translate;
+        } else if input.key_just_pressed(Key::KeyE) {
+            self.gizmo.mode = GizmoMode::Rotate;
+        } else if input.key_just_pressed(Key::KeyR) {
+            self.gizmo.mode = GizmoMode::Scale;
+        }
+
+        self.gizmo
+            .hover(scene, &self.selection, camera.position, origin, direction);
+
+        if input.mouse_button_just_pressed(MouseButton::Left) {
+            if self
+                .gizmo
+                .begin_drag(scene, &self.selection, camera.position, origin, direction)
+            {
+                return true;
+            }
+            self.selection.pick(scene, origin, direction, shift);
+            return true;
+        }
+
+        self.gizmo.hovered().is_some()
+    }
+
+    /// Draw selection bounds and the gizmo
+    pub fn draw(&self, scene: &Scene, camera: &Camera, debug: &mut DebugDraw) {
+        for id in self.selection.entities() {
+            let Some(transform) = scene.get_entity(*id).and_then(|e| e.get_component::<Transform>()) else {
+                continue;
+            };
+            let half = transform.scale * 0.5;
+            debug.draw_aabb(transform.position - half, transform.position + half, Color::YELLOW);
+        }
+        self.gizmo.draw(scene, &self.selection, camera.position, debug);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_drag_and_undo() {
+        let mut scene = Scene::new("Editor".to_string());
+        let id = scene.create_entity("Crate".to_string());
+        scene
+            .get_entity_mut(id)
+            .unwrap()
+            .add_component(Transform::from_position(Vec3::new(0.0, 0.0, -10.0)));
+
+        let camera = Vec3::ZERO;
+        let mut selection = Selection::new();
+        assert_eq!(selection.pick(&scene, camera, Vec3::NEG_Z, false), Some(id));
+        assert!(selection.pick(&scene, camera, Vec3::Y, false).is_none());
+        assert!(selection.is_empty());
+        selection.select(id);
+
+        // Handle length is 1.5 at distance 10; grab the X arrow halfway along
+        let mut gizmo = Gizmo::new();
+        let grab = Vec3::new(0.75, 0.0, -10.0);
+        assert!(gizmo.begin_drag(&scene, &selection, camera, camera, grab.normalize()));
+        let release = Vec3::new(2.75, 0.0, -10.0);
+        gizmo.drag(&mut scene, camera, release.normalize());
+        let edit = gizmo.end_drag(&scene).unwrap();
+
+        let position = |scene: &Scene| scene.get_entity(id).unwrap().get_component::<Transform>().unwrap().position;
+        assert!((position(&scene) - Vec3::new(2.0, 0.0, -10.0)).length() < 1e-3);
+
+        let mut history = EditHistory::default();
+        history.push(edit);
+        assert!(history.undo(&mut scene));
+        assert_eq!(position(&scene), Vec3::new(0.0, 0.0, -10.0));
+        assert!(history.redo(&mut scene));
+        assert!((position(&scene).x - 2.0).abs() < 1e-3);
+    }
+}
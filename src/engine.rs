@@ -4,18 +4,31 @@
 
 use winit::{
     event::*,
-    event_loop::EventLoop,
+    event_loop::{DeviceEvents, EventLoop, EventLoopWindowTarget},
 };
 use crate::{
     audio::AudioManager,
+    camera_controller::{self, FlyCameraController},
+    capture::CaptureConfig,
+    commands::Commands,
     config::EngineConfig,
+    crash::{self, CrashConfig, FrameStats},
     ecs::Scene,
-    input::InputManager,
-    renderer::Renderer,
+    editor::Editor,
+    hierarchy,
+    input::{InputManager, Key},
+    particles,
+    renderer::{Color, RenderError, Renderer},
     resource::ResourceManager,
+    scene_manager::{SceneManager, SceneSwitcher},
+    schedule::{Stage, SystemCtx, Systems},
+    sky::DayNightCycle,
     time::TimeManager,
-    window::Window,
+    trail,
+    window::{CursorGrab, Window},
 };
+#[cfg(feature = "egui")]
+use crate::debug_ui::{DebugStats, DebugUi};
 
 /// Main engine struct that orchestrates all systems
 pub struct Engine {
@@ -25,30 +38,56 @@ pub struct Engine {
     audio: AudioManager,
     input: InputManager,
     time: TimeManager,
-    scene: Scene,
+    scenes: SceneManager,
+    systems: Systems,
     resource_manager: ResourceManager,
     event_loop: Option<EventLoop<()>>,
     show_debug: bool,
+    day_night: Option<DayNightCycle>,
+    camera_controller: Option<FlyCameraController>,
+    capture_hotkey: Option<(Key, CaptureConfig)>,
+    editor: Option<Editor>,
+    #[cfg(feature = "egui")]
+    debug_ui: Option<DebugUi>,
 }
 
 impl Engine {
     /// Create a new engine with the given configuration
     pub fn new(config: EngineConfig) -> Self {
-        // Initialize logging
-        env_logger::Builder::from_default_env()
-            .filter_level(log::LevelFilter::Info)
-            .init();
-
-        log::info!("Initializing My Engine...");
-
         // Create event loop
         let event_loop = EventLoop::new().expect("Failed to create event loop");
 
+        Self::with_event_loop(config, event_loop)
+    }
+
+    /// Create a new engine for an Android app (call from `android_main`)
+    #[cfg(all(target_os = "android", feature = "android"))]
+    pub fn with_android_app(config: EngineConfig, app: crate::platform::AndroidApp) -> Self {
+        use winit::platform::android::EventLoopBuilderExtAndroid;
+
+        crate::platform::set_android_app(app.clone());
+        let event_loop = winit::event_loop::EventLoopBuilder::new()
+            .with_android_app(app)
+            .build()
+            .expect("Failed to create event loop");
+
+        Self::with_event_loop(config, event_loop)
+    }
+
+    fn with_event_loop(config: EngineConfig, event_loop: EventLoop<()>) -> Self {
+        // Initialize logging (recent lines are kept for crash reports)
+        let mut logger = env_logger::Builder::from_default_env();
+        logger.filter_level(log::LevelFilter::Info);
+        crash::init_logger(logger);
+
+        log::info!("Initializing My Engine...");
+
         // Create audio manager
-        let audio = AudioManager::new().unwrap_or_else(|e| {
+        let mut audio = AudioManager::new().unwrap_or_else(|e| {
             log::warn!("Failed to initialize audio: {}", e);
             AudioManager::new().unwrap()
         });
+        audio.set_background_audio(config.audio.background);
 
         Self {
             config,
@@ -57,21 +96,63 @@ impl Engine {
             audio,
             input: InputManager::new(),
             time: TimeManager::new(),
-            scene: Scene::default(),
+            scenes: SceneManager::new(Scene::default()),
+            systems: Systems::new(),
             resource_manager: ResourceManager::new(),
             event_loop: Some(event_loop),
             show_debug: true,
+            day_night: None,
+            camera_controller: None,
+            capture_hotkey: None,
+            editor: None,
+            #[cfg(feature = "egui")]
+            debug_ui: None,
         }
     }
 
-    /// Get reference to the scene
+    /// Get reference to the active scene
     pub fn scene(&self) -> &Scene {
-        &self.scene
+        self.scenes.active()
     }
 
-    /// Get mutable reference to the scene
+    /// Get mutable reference to the active scene
     pub fn scene_mut(&mut self) -> &mut Scene {
-        &mut self.scene
+        self.scenes.active_mut()
+    }
+
+    /// Get reference to all loaded scenes
+    pub fn scenes(&self) -> &SceneManager {
+        &self.scenes
+    }
+
+    /// Get mutable reference to all loaded scenes (for adding scenes and hooks)
+    pub fn scenes_mut(&mut self) -> &mut SceneManager {
+        &mut self.scenes
+    }
+
+    /// Load a scene without activating it
+    pub fn add_scene(&mut self, scene: Scene) -> bool {
+        self.scenes.add(scene)
+    }
+
+    /// Make a loaded scene active, moving `Persistent` entities into it
+    pub fn set_active_scene(&mut self, name: &str) -> Result<(), String> {
+        self.scenes.set_active(name)
+    }
+
+    /// Get a handle the game loop can use to switch scenes
+    pub fn scene_switcher(&self) -> SceneSwitcher {
+        self.scenes.switcher()
+    }
+
+    /// Get reference to the system registry
+    pub fn systems(&self) -> &Systems {
+        &self.systems
+    }
+
+    /// Get mutable reference to the system registry
+    pub fn systems_mut(&mut self) -> &mut Systems {
+        &mut self.systems
     }
 
     /// Get reference to the resource manager
@@ -109,16 +190,77 @@ impl Engine {
         &self.input
     }
 
+    /// Get mutable reference to the input manager, e.g. to configure double presses
+    pub fn input_mut(&mut self) -> &mut InputManager {
+        &mut self.input
+    }
+
     /// Get reference to the time manager
     pub fn time(&self) -> &TimeManager {
         &self.time
     }
 
-    /// Toggle debug overlay
+    /// Toggle debug overlay (FPS in the title, and the egui debug UI when the
+    /// `egui` feature is enabled)
     pub fn set_show_debug(&mut self, show: bool) {
         self.show_debug = show;
     }
 
+    /// Enable a day/night cycle that drives the sky and lighting every frame
+    pub fn set_day_night(&mut self, cycle: Option<DayNightCycle>) {
+        if cycle.is_none() {
+            if let Some(renderer) = &mut self.renderer {
+                renderer.set_sky(None);
+            }
+        }
+        self.day_night = cycle;
+    }
+
+    /// Get reference to the day/night cycle (if enabled)
+    pub fn day_night(&self) -> Option<&DayNightCycle> {
+        self.day_night.as_ref()
+    }
+
+    /// Get mutable reference to the day/night cycle (if enabled)
+    pub fn day_night_mut(&mut self) -> Option<&mut DayNightCycle> {
+        self.day_night.as_mut()
+    }
+
+    /// Drive the main camera from input every frame
+    pub fn set_camera_controller(&mut self, controller: Option<FlyCameraController>) {
+        self.camera_controller = controller;
+    }
+
+    /// Get mutable reference to the camera controller (if enabled)
+    pub fn camera_controller_mut(&mut self) -> Option<&mut FlyCameraController> {
+        self.camera_controller.as_mut()
+    }
+
+    /// Toggle recording a clip of the rendered frames when `key` is pressed
+    pub fn set_capture_hotkey(&mut self, key: Option<Key>, config: CaptureConfig) {
+        self.capture_hotkey = key.map(|key| (key, config));
+    }
+
+    /// Write a crash report and notify the player when the game panics
+    pub fn install_crash_handler(&mut self, config: CrashConfig) {
+        crash::install(config);
+    }
+
+    /// Enable the built-in editor (picking, gizmos, undo) on top of the game
+    pub fn set_editor(&mut self, editor: Option<Editor>) {
+        self.editor = editor;
+    }
+
+    /// Get reference to the editor (if enabled)
+    pub fn editor(&self) -> Option<&Editor> {
+        self.editor.as_ref()
+    }
+
+    /// Get mutable reference to the editor (if enabled)
+    pub fn editor_mut(&mut self) -> Option<&mut Editor> {
+        self.editor.as_mut()
+    }
+
     /// Run the engine with a game loop callback
     ///
     /// The callback receives:
@@ -132,30 +274,49 @@ impl Engine {
         F: FnMut(&mut Scene, &InputManager, f32) -> bool + 'static,
     {
         let event_loop = self.event_loop.take().expect("Event loop already consumed");
-
-        // Create window
-        let window = Window::new(&self.config.window, &event_loop);
-        
-        // Create renderer
-        let renderer = pollster::block_on(Renderer::new(
-            window.inner(),
-            &self.config.renderer,
-        ))
-        .expect("Failed to create renderer");
-
-        self.window = Some(window);
-        self.renderer = Some(renderer);
+        // Raw mouse motion drives `mouse_delta`; other apps' motion doesn't count
+        event_loop.listen_device_events(DeviceEvents::WhenFocused);
 
         log::info!("Engine started!");
 
         let mut engine_state = self;
 
+        // The window and renderer are created on the first Resumed event, which
+        // every platform sends; mobile platforms also suspend and resume later
         event_loop.run(move |event, control_flow| {
             match event {
+                Event::Resumed => {
+                    engine_state.resume(control_flow);
+                }
+                Event::Suspended => {
+                    log::info!("App suspended");
+                    if let Some(renderer) = &mut engine_state.renderer {
+                        renderer.suspend();
+                    }
+                    engine_state.audio.suspend();
+                    engine_state.input.clear();
+                }
                 Event::WindowEvent {
                     ref event,
                     window_id,
-                } if window_id == engine_state.window.as_ref().unwrap().id() => {
+                } if engine_state.window.as_ref().is_some_and(|w| w.id() == window_id) => {
+                    #[cfg(feature = "egui")]
+                    if let (Some(debug_ui), Some(window)) =
+                        (&mut engine_state.debug_ui, &engine_state.window)
+                    {
+                        let consumed = debug_ui.handle_event(window.inner(), event);
+                        let is_input = matches!(
+                            event,
+                            WindowEvent::KeyboardInput { .. }
+                                | WindowEvent::Ime(_)
+                                | WindowEvent::MouseInput { .. }
+                                | WindowEvent::MouseWheel { .. }
+                        );
+                        if consumed && is_input {
+                            return;
+                        }
+                    }
+
                     match event {
                         WindowEvent::CloseRequested => {
                             log::info!("Window close requested");
@@ -166,9 +327,22 @@ impl Engine {
                                 renderer.resize((physical_size.width, physical_size.height));
                             }
                         }
+                        WindowEvent::Focused(focused) => {
+                            engine_state.audio.set_window_focused(*focused);
+                            // Some platforms release the grab while another window has focus
+                            if *focused {
+                                engine_state.input.refresh_cursor();
+                            }
+                        }
+                        WindowEvent::Occluded(occluded) => {
+                            engine_state.audio.set_window_visible(!*occluded);
+                        }
                         WindowEvent::KeyboardInput { event, .. } => {
                             engine_state.input.handle_keyboard_input(event);
                         }
+                        WindowEvent::Ime(ime) => {
+                            engine_state.input.handle_ime(ime);
+                        }
                         WindowEvent::MouseInput { state, button, .. } => {
                             engine_state.input.handle_mouse_button(*button, *state);
                         }
@@ -185,28 +359,153 @@ impl Engine {
                             };
                             engine_state.input.handle_scroll(scroll);
                         }
+                        WindowEvent::Touch(touch) => {
+                            engine_state.input.handle_touch(touch);
+                        }
                         WindowEvent::RedrawRequested => {
+                            // Switch scenes before anything runs this frame
+                            engine_state.scenes.apply_requested_switch();
+
                             // Update time
                             engine_state.time.update();
                             let delta = engine_state.time.delta_time();
+                            engine_state.audio.update();
 
-                            // Run game logic
-                            let should_continue = game_loop(
-                                &mut engine_state.scene,
-                                &engine_state.input,
+                            let commands = Commands::new();
+                            let ctx = SystemCtx {
+                                input: &engine_state.input,
                                 delta,
-                            );
+                                elapsed: engine_state.time.elapsed_secs(),
+                                frame: engine_state.time.frame_count(),
+                                commands: &commands,
+                            };
+
+                            // Run game logic, adding the scene to the crash report if it panics
+                            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                                let scene = engine_state.scenes.active_mut();
+                                let systems = &mut engine_state.systems;
+                                systems.run_stage(Stage::PreUpdate, scene, &ctx)?;
+                                let should_continue = game_loop(scene, ctx.input, delta);
+                                systems.run_stage(Stage::Update, scene, &ctx)?;
+                                trail::update_trails(scene, delta);
+                                particles::update_particle_emitters(scene, delta);
+                                systems.run_stage(Stage::PostUpdate, scene, &ctx)?;
+                                hierarchy::propagate_transforms(scene);
+                                Ok::<bool, String>(should_continue)
+                            }));
+                            let should_continue = match result {
+                                Ok(Ok(should_continue)) => should_continue,
+                                Ok(Err(e)) => {
+                                    log::error!("Invalid system schedule: {}", e);
+                                    false
+                                }
+                                Err(payload) => {
+                                    if crash::last_report().is_some() {
+                                        let summary = crash::scene_summary(engine_state.scenes.active());
+                                        let _ = crash::append_to_report("scene", &summary);
+                                    }
+                                    std::panic::resume_unwind(payload);
+                                }
+                            };
+
+                            crash::record_frame(FrameStats {
+                                frame: engine_state.time.frame_count(),
+                                fps: engine_state.time.fps(),
+                                delta,
+                                entity_count: engine_state.scenes.active().entity_count(),
+                            });
 
                             if !should_continue {
                                 control_flow.exit();
                                 return;
                             }
 
-                            // Update camera
-                            if let Some(renderer) = &mut engine_state.renderer {
+                            // Update camera and draw the frame
+                            if let Some(renderer) = engine_state.renderer.as_mut().filter(|r| !r.is_suspended()) {
+                                if let Some(cycle) = &mut engine_state.day_night {
+                                    cycle.update(delta);
+                                    cycle.apply(renderer);
+                                }
+                                camera_controller::update_scene_camera(engine_state.scenes.active(), renderer.camera_mut(), delta);
+                                if let Some(controller) = &mut engine_state.camera_controller {
+                                    controller.update(renderer.camera_mut(), &engine_state.input, delta);
+                                }
                                 renderer.update_camera();
+                                engine_state.audio.set_listener_from_camera(renderer.camera());
+                                engine_state.audio.update_scene_audio(engine_state.scenes.active(), &engine_state.resource_manager);
+
+                                let camera_position = renderer.camera().position;
+                                renderer.ribbons_mut().queue_scene(engine_state.scenes.active(), camera_position);
+
+                                if let Some(editor) = &mut engine_state.editor {
+                                    let camera = renderer.camera().clone();
+                                    editor.update(
+                                        engine_state.scenes.active_mut(),
+                                        &engine_state.input,
+                                        &camera,
+                                        renderer.size(),
+                                    );
+                                    editor.draw(engine_state.scenes.active(), &camera, renderer.debug_draw_mut());
+                                    renderer.set_outlined_entities(editor.selection.entities(), Color::YELLOW);
+                                }
+
+                                if let Some((key, config)) = &engine_state.capture_hotkey {
+                                    if engine_state.input.key_just_pressed(*key) {
+                                        let result = if renderer.is_capturing() {
+                                            renderer.stop_capture()
+                                        } else {
+                                            renderer.start_capture(config.clone())
+                                        };
+                                        if let Err(e) = result {
+                                            log::warn!("Failed to toggle capture: {}", e);
+                                        }
+                                    }
+                                }
+
+                                if let Err(e) = engine_state.systems.run_stage(Stage::Render, engine_state.scenes.active_mut(), &ctx) {
+                                    log::error!("Invalid system schedule: {}", e);
+                                    control_flow.exit();
+                                    return;
+                                }
+
+                                engine_state.resource_manager.upload_meshes(renderer.device());
+
+                                #[cfg(feature = "egui")]
+                                let result = match &mut engine_state.debug_ui {
+                                    Some(debug_ui) => {
+                                        debug_ui.set_visible(engine_state.show_debug);
+                                        debug_ui.prepare(
+                                            engine_state.window.as_ref().unwrap().inner(),
+                                            engine_state.scenes.active_mut(),
+                                            DebugStats {
+                                                fps: engine_state.time.fps(),
+                                                delta,
+                                                frame_count: engine_state.time.frame_count(),
+                                                render: renderer.render_stats(),
+                                            },
+                                        );
+                                        renderer.render_scene(engine_state.scenes.active(), &engine_state.resource_manager, |device, queue, encoder, view| {
+                                            debug_ui.paint(device, queue, encoder, view);
+                                        })
+                                    }
+                                    None => renderer.render_scene(engine_state.scenes.active(), &engine_state.resource_manager, |_, _, _, _| {}),
+                                };
+                                #[cfg(not(feature = "egui"))]
+                                let result = renderer.render_scene(engine_state.scenes.active(), &engine_state.resource_manager, |_, _, _, _| {});
+
+                                match result {
+                                    Ok(()) => {}
+                                    Err(RenderError::OutOfMemory) => {
+                                        log::error!("GPU out of memory, exiting");
+                                        control_flow.exit();
+                                    }
+                                    Err(e) => log::warn!("Failed to render frame: {}", e),
+                                }
                             }
 
+                            // Every stage, Render included, has seen this frame's changes
+                            engine_state.scenes.active_mut().clear_trackers();
+
                             // Update window title with FPS if debug is enabled
                             if engine_state.show_debug {
                                 let fps = engine_state.time.fps();
@@ -215,15 +514,24 @@ impl Engine {
                                 engine_state.window.as_ref().unwrap().set_title(&title);
                             }
 
+                            engine_state.apply_window_requests();
+
                             // Update input for next frame
                             engine_state.input.update();
                         }
                         _ => {}
                     }
                 }
+                Event::DeviceEvent {
+                    event: DeviceEvent::MouseMotion { delta },
+                    ..
+                } => {
+                    engine_state.input.handle_mouse_motion(delta);
+                }
                 Event::AboutToWait => {
-                    // Request redraw
-                    if let Some(window) = &engine_state.window {
+                    // Request redraw (nothing is drawn while suspended)
+                    let suspended = engine_state.renderer.as_ref().is_none_or(|r| r.is_suspended());
+                    if let (Some(window), false) = (&engine_state.window, suspended) {
                         window.request_redraw();
                     }
                 }
@@ -231,4 +539,75 @@ impl Engine {
             }
         }).expect("Event loop error");
     }
+
+    /// Apply the cursor grab, visibility, and centering, and the IME state the game
+    /// asked for
+    fn apply_window_requests(&mut self) {
+        let Some(window) = &mut self.window else {
+            return;
+        };
+        let (text_input, ime_area) = self.input.take_text_input_changes();
+        if let Some(enabled) = text_input {
+            window.set_ime_allowed(enabled);
+        }
+        if let Some((position, size)) = ime_area {
+            window.set_ime_cursor_area(position, size);
+        }
+        if let Some((grab, visible)) = self.input.take_cursor_changes() {
+            window.set_cursor_grab(grab);
+            window.set_cursor_visible(visible);
+        }
+        // A confined cursor would stop at the window edge in relative mode
+        let recenter = self.input.relative_mouse_mode()
+            && window.cursor_grab() == CursorGrab::Confined
+            && window.inner().has_focus();
+        if self.input.take_center_cursor() || recenter {
+            if let Err(e) = window.center_cursor() {
+                log::warn!("{}", e);
+            }
+        }
+    }
+
+    /// Create the window and renderer on first resume, or recreate the surface after a suspend
+    fn resume(&mut self, target: &EventLoopWindowTarget<()>) {
+        if let (Some(window), Some(renderer)) = (&self.window, &mut self.renderer) {
+            log::info!("App resumed");
+            if let Err(e) = renderer.resume(window.inner()) {
+                log::error!("Failed to resume renderer: {}", e);
+                target.exit();
+                return;
+            }
+            self.audio.resume();
+            self.time.skip_gap();
+            return;
+        }
+
+        // Create window
+        let window = Window::new(&self.config.window, target);
+
+        // Create renderer
+        let renderer = pollster::block_on(Renderer::new(
+            window.inner(),
+            &self.config.renderer,
+        ))
+        .expect("Failed to create renderer");
+
+        let adapter = renderer.adapter_info();
+        crash::set_info("gpu", &format!("{} ({:?}, {:?})", adapter.name, adapter.backend, adapter.device_type));
+        crash::set_info("driver", &format!("{} {}", adapter.driver, adapter.driver_info));
+        crash::set_info("surface", &format!("{:?} {}x{}", renderer.surface_format(), renderer.size().0, renderer.size().1));
+
+        #[cfg(feature = "egui")]
+        {
+            self.debug_ui = Some(DebugUi::new(
+                window.inner(),
+                renderer.device(),
+                renderer.surface_format(),
+            ));
+        }
+
+        self.window = Some(window);
+        self.renderer = Some(renderer);
+        self.time.skip_gap();
+    }
 }
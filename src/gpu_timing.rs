@@ -0,0 +1,188 @@
+//! GPU pass timing with timestamp queries
+//!
+//! The renderer writes a timestamp between the stages of a frame (sky, each camera's
+//! scene pass, post-processing, tone mapping, overlays) with empty compute passes, so no
+//! pass has to know about the timer. Results are read back without stalling and show up
+//! in [`RenderStats`](crate::renderer::RenderStats) a frame or two later.
+
+use std::sync::mpsc;
+
+/// Timestamps written per frame at most; later stages are folded into the last one
+const MAX_TIMESTAMPS: u32 = 64;
+
+/// GPU time spent in one stage of a frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassTiming {
+    pub label: &'static str,
+    pub milliseconds: f32,
+}
+
+/// Durations in milliseconds between consecutive timestamps, labeled by the stage each
+/// one ends
+pub(crate) fn stage_timings(labels: &[&'static str], timestamps: &[u64], period_ns: f32) -> Vec<PassTiming> {
+    labels
+        .iter()
+        .zip(timestamps.windows(2))
+        .map(|(label, pair)| PassTiming {
+            label,
+            milliseconds: pair[1].saturating_sub(pair[0]) as f32 * period_ns / 1_000_000.0,
+        })
+        .collect()
+}
+
+/// Timestamp query set and readback of the last measured frame
+pub(crate) struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+    /// Whether this frame writes timestamps (not while the last ones are read back)
+    recording: bool,
+    /// Timestamps written this frame
+    count: u32,
+    /// Stages ended by each timestamp after the first
+    labels: Vec<&'static str>,
+    pending: Vec<&'static str>,
+    readback: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+    timings: Vec<PassTiming>,
+}
+
+impl GpuTimer {
+    pub(crate) fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Timestamp Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: MAX_TIMESTAMPS,
+        });
+        let size = MAX_TIMESTAMPS as u64 * std::mem::size_of::<u64>() as u64;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            recording: false,
+            count: 0,
+            labels: Vec::new(),
+            pending: Vec::new(),
+            readback: None,
+            timings: Vec::new(),
+        }
+    }
+
+    /// Timings of the last frame read back
+    pub(crate) fn timings(&self) -> &[PassTiming] {
+        &self.timings
+    }
+
+    /// Apply a finished readback and write the frame's first timestamp
+    pub(crate) fn begin(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        self.collect_results(device);
+        self.count = 0;
+        self.labels.clear();
+        self.recording = self.readback.is_none();
+        self.write(encoder);
+    }
+
+    /// Write a timestamp ending the stage `label`
+    pub(crate) fn stage(&mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str) {
+        if self.recording && self.count < MAX_TIMESTAMPS {
+            self.labels.push(label);
+            self.write(encoder);
+        }
+    }
+
+    fn write(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.recording || self.count >= MAX_TIMESTAMPS {
+            return;
+        }
+        // An empty compute pass only writes its beginning timestamp
+        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Timestamp"),
+            timestamp_writes: Some(wgpu::ComputePassTimestampWrites {
+                query_set: &self.query_set,
+                beginning_of_pass_write_index: Some(self.count),
+                end_of_pass_write_index: None,
+            }),
+        });
+        self.count += 1;
+    }
+
+    /// Copy the frame's timestamps to the readback buffer
+    pub(crate) fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.count < 2 {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..self.count, &self.resolve_buffer, 0);
+        let size = self.count as u64 * std::mem::size_of::<u64>() as u64;
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, size);
+    }
+
+    /// Start reading back the timestamps once the frame is submitted
+    pub(crate) fn map_results(&mut self) {
+        if !self.recording || self.count < 2 {
+            return;
+        }
+        self.recording = false;
+        self.pending = std::mem::take(&mut self.labels);
+        let size = self.count as u64 * std::mem::size_of::<u64>() as u64;
+        let (sender, receiver) = mpsc::channel();
+        self.readback_buffer
+            .slice(..size)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        self.readback = Some(receiver);
+    }
+
+    fn collect_results(&mut self, device: &wgpu::Device) {
+        let Some(receiver) = &self.readback else {
+            return;
+        };
+        device.poll(wgpu::Maintain::Poll);
+        match receiver.try_recv() {
+            Err(mpsc::TryRecvError::Empty) => return,
+            Ok(Ok(())) => {
+                let size = (self.pending.len() + 1) as u64 * std::mem::size_of::<u64>() as u64;
+                {
+                    let data = self.readback_buffer.slice(..size).get_mapped_range();
+                    self.timings = stage_timings(&self.pending, bytemuck::cast_slice(&data), self.period);
+                }
+                self.readback_buffer.unmap();
+            }
+            Ok(Err(e)) => log::warn!("Failed to read GPU timestamps: {}", e),
+            Err(mpsc::TryRecvError::Disconnected) => log::warn!("GPU timestamp readback was dropped"),
+        }
+        self.readback = None;
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_timings() {
+        let timings = stage_timings(&["Sky", "Scene Pass"], &[1_000, 501_000, 2_501_000], 2.0);
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings[0].label, "Sky");
+        assert_eq!(timings[0].milliseconds, 1.0);
+        assert_eq!(timings[1].milliseconds, 4.0);
+
+        // Wrapped or reordered timestamps don't go negative
+        assert_eq!(stage_timings(&["Tone Map"], &[10, 5], 1.0)[0].milliseconds, 0.0);
+    }
+}
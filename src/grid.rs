@@ -0,0 +1,215 @@
+//! Editor-style infinite ground grid
+//!
+//! The grid lies on the y = 0 plane and is drawn by a fullscreen shader, so it reaches
+//! the horizon without any geometry. It is drawn at the start of the main camera's
+//! scene pass, over the sky or clear color and underneath every mesh, and fades out
+//! with distance. Colored
+//! X (red) and Z (blue) axis lines cross the grid, and a small axes gizmo marks the
+//! origin with the Y axis in green.
+
+use crate::renderer::{Color, DEPTH_FORMAT};
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use wgpu::util::DeviceExt;
+
+/// Appearance of the ground grid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridSettings {
+    /// Distance between minor lines, in world units
+    pub cell_size: f32,
+    /// Minor cells between major lines
+    pub major_every: u32,
+    /// Distance from the camera where the grid has faded out completely
+    pub fade_distance: f32,
+    /// Line color; alpha is the grid's opacity
+    pub color: Color,
+    /// Length of the origin axes gizmo, in world units (0.0 hides it)
+    pub axis_length: f32,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            cell_size: 1.0,
+            major_every: 10,
+            fade_distance: 100.0,
+            color: Color::new(0.6, 0.6, 0.6, 0.6),
+            axis_length: 1.0,
+        }
+    }
+}
+
+/// Grid uniform buffer data
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+struct GridUniform {
+    inv_view_proj: [[f32; 4]; 4],
+    view_proj: [[f32; 4]; 4],
+    /// Camera position (xyz) and fade distance (w)
+    camera: [f32; 4],
+    color: [f32; 4],
+    /// Cell size (x), cells between major lines (y), and axis gizmo length (z)
+    params: [f32; 4],
+    /// Viewport size in pixels (xy)
+    viewport: [f32; 4],
+}
+
+impl GridUniform {
+    fn new(settings: &GridSettings, view_proj: Mat4, camera_position: Vec3, viewport: (u32, u32)) -> Self {
+        Self {
+            inv_view_proj: view_proj.inverse().to_cols_array_2d(),
+            view_proj: view_proj.to_cols_array_2d(),
+            camera: camera_position.extend(settings.fade_distance.max(f32::EPSILON)).to_array(),
+            color: settings.color.to_array(),
+            params: [
+                settings.cell_size.max(f32::EPSILON),
+                settings.major_every.max(1) as f32,
+                settings.axis_length.max(0.0),
+                0.0,
+            ],
+            viewport: [viewport.0.max(1) as f32, viewport.1.max(1) as f32, 0.0, 0.0],
+        }
+    }
+}
+
+/// GPU pipeline drawing the grid as a fullscreen triangle
+pub(crate) struct GridPipeline {
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl GridPipeline {
+    pub(crate) fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Grid Buffer"),
+            contents: bytemuck::cast_slice(&[GridUniform::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("grid_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("grid_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grid Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/grid.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Grid Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // Drawn in the scene pass ahead of the meshes, leaving depth untouched
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            buffer,
+            bind_group,
+        }
+    }
+
+    /// Upload the grid settings and the camera for this frame, drawing into a viewport
+    /// of `viewport` pixels
+    pub(crate) fn update(
+        &self,
+        queue: &wgpu::Queue,
+        settings: &GridSettings,
+        view_proj: Mat4,
+        camera_position: Vec3,
+        viewport: (u32, u32),
+    ) {
+        let uniform = GridUniform::new(settings, view_proj, camera_position, viewport);
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Record the grid draw into the main camera's scene pass
+    pub(crate) fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_uniform() {
+        let view_proj = Mat4::perspective_rh(1.0, 1.5, 0.1, 100.0) * Mat4::from_translation(Vec3::NEG_Y);
+        let uniform = GridUniform::new(&GridSettings::default(), view_proj, Vec3::Y, (1280, 720));
+        let inverse = Mat4::from_cols_array_2d(&uniform.inv_view_proj);
+        assert!((inverse * view_proj).abs_diff_eq(Mat4::IDENTITY, 1e-4));
+        assert_eq!(uniform.camera, [0.0, 1.0, 0.0, 100.0]);
+        assert_eq!(uniform.params, [1.0, 10.0, 1.0, 0.0]);
+        assert_eq!(uniform.viewport, [1280.0, 720.0, 0.0, 0.0]);
+
+        // Degenerate settings can't divide by zero in the shader
+        let settings = GridSettings {
+            cell_size: 0.0,
+            major_every: 0,
+            fade_distance: 0.0,
+            axis_length: -1.0,
+            ..Default::default()
+        };
+        let uniform = GridUniform::new(&settings, view_proj, Vec3::ZERO, (0, 0));
+        assert!(uniform.params[0] > 0.0 && uniform.camera[3] > 0.0);
+        assert_eq!(&uniform.params[1..], &[1.0, 0.0, 0.0]);
+        assert_eq!(uniform.viewport, [1.0, 1.0, 0.0, 0.0]);
+    }
+}
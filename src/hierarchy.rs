@@ -0,0 +1,367 @@
+//! Parent/child entity hierarchy
+//!
+//! `scene.set_parent(child, parent)` attaches an entity to another; its `Transform`
+//! is then relative to the parent. Every frame the engine walks each hierarchy from
+//! its roots and stores the resulting world matrix in a [`GlobalTransform`], which the
+//! renderer and lights use in place of the local transform. Only subtrees whose
+//! `Transform` or parent changed since the last propagation are recomputed. Entities outside
+//! any hierarchy have no `GlobalTransform` and are placed by their `Transform` alone.
+//!
+//! Deactivating an entity also deactivates its descendants: `is_active()` is true
+//! only if the entity and all of its ancestors are active, while `is_self_active()`
+//! reports the entity's own flag. Queries and `active_entities` skip inactive ones.
+
+use crate::ecs::{Component, EntityId, EntityRef, Scene};
+use crate::math::Transform;
+use glam::{Mat4, Quat, Vec3};
+
+/// The entity this one is attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(EntityId);
+
+impl Component for Parent {}
+
+impl Parent {
+    /// Get the parent entity
+    pub fn get(&self) -> EntityId {
+        self.0
+    }
+}
+
+/// Entities attached to this one, in attachment order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(Vec<EntityId>);
+
+impl Component for Children {}
+
+impl Children {
+    /// Get the child entities
+    pub fn get(&self) -> &[EntityId] {
+        &self.0
+    }
+}
+
+/// World-space transform of an entity in a hierarchy, computed from its parent chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform(pub Mat4);
+
+impl Component for GlobalTransform {}
+
+impl GlobalTransform {
+    /// World-space position
+    pub fn translation(&self) -> Vec3 {
+        self.0.w_axis.truncate()
+    }
+
+    /// World-space scale, rotation, and position
+    pub fn to_scale_rotation_translation(&self) -> (Vec3, Quat, Vec3) {
+        self.0.to_scale_rotation_translation()
+    }
+
+    /// World-space forward direction
+    pub fn forward(&self) -> Vec3 {
+        self.0.transform_vector3(Vec3::NEG_Z).normalize_or_zero()
+    }
+}
+
+/// World matrix of an entity: its `GlobalTransform` in a hierarchy, or else its `Transform`
+pub fn world_matrix(entity: &EntityRef) -> Option<Mat4> {
+    entity
+        .get_component::<GlobalTransform>()
+        .map(|global| global.0)
+        .or_else(|| entity.get_component::<Transform>().map(Transform::matrix))
+}
+
+impl Scene {
+    /// Attach `child` to `parent`, detaching it from any previous parent
+    ///
+    /// Fails if either entity doesn't exist or `parent` is `child` or one of its descendants.
+    pub fn set_parent(&mut self, child: EntityId, parent: EntityId) -> bool {
+        if self.get_entity(child).is_none() || self.get_entity(parent).is_none() {
+            return false;
+        }
+        let mut ancestor = Some(parent);
+        while let Some(id) = ancestor {
+            if id == child {
+                return false;
+            }
+            ancestor = self.parent(id);
+        }
+
+        self.remove_parent(child);
+        if let Some(mut entity) = self.get_entity_mut(child) {
+            entity.add_component(Parent(parent));
+        }
+        match self.get_component_mut::<Children>(parent) {
+            Some(children) => children.0.push(child),
+            None => {
+                if let Some(mut entity) = self.get_entity_mut(parent) {
+                    entity.add_component(Children(vec![child]));
+                }
+            }
+        }
+        let parent_active = self.get_entity(parent).is_some_and(|entity| entity.is_active());
+        self.set_parent_active(child, parent_active);
+        self.propagate_active(child);
+        true
+    }
+
+    /// Detach `child` from its parent, making it a root
+    pub fn remove_parent(&mut self, child: EntityId) -> bool {
+        let Some(parent) = self.parent(child) else {
+            return false;
+        };
+        if let Some(mut entity) = self.get_entity_mut(child) {
+            // The old world transform included the parent's; it's recomputed if the
+            // child still has children of its own
+            entity.remove_component::<Parent>();
+            entity.remove_component::<GlobalTransform>();
+        }
+        if let Some(children) = self.get_component_mut::<Children>(parent) {
+            children.0.retain(|id| *id != child);
+            if children.0.is_empty() {
+                if let Some(mut entity) = self.get_entity_mut(parent) {
+                    entity.remove_component::<Children>();
+                }
+            }
+        }
+        self.leave_hierarchy_if_alone(parent);
+        self.set_parent_active(child, true);
+        self.propagate_active(child);
+        true
+    }
+
+    /// Get the parent of an entity
+    pub fn parent(&self, id: EntityId) -> Option<EntityId> {
+        self.get_component::<Parent>(id).map(Parent::get)
+    }
+
+    /// Get the children of an entity
+    pub fn children(&self, id: EntityId) -> &[EntityId] {
+        self.get_component::<Children>(id).map(Children::get).unwrap_or_default()
+    }
+
+    /// Remove an entity and all of its descendants at once
+    ///
+    /// Removal hooks run for the whole subtree, parents before children, while every
+    /// entity of it still exists. The subtree is then unlinked from its parent and
+    /// removed without reparenting the descendants one by one.
+    pub fn despawn_recursive(&mut self, id: EntityId) -> bool {
+        if self.get_entity(id).is_none() {
+            return false;
+        }
+        let mut subtree = vec![id];
+        let mut next = 0;
+        while next < subtree.len() {
+            subtree.extend_from_slice(self.children(subtree[next]));
+            next += 1;
+        }
+
+        for &entity in &subtree {
+            self.run_removal_hooks(entity);
+        }
+        self.remove_parent(id);
+        for &entity in &subtree {
+            self.remove_row(entity);
+        }
+        true
+    }
+
+    /// Unlink an entity that is about to be removed; its children become roots
+    pub(crate) fn detach_from_hierarchy(&mut self, id: EntityId) {
+        self.remove_parent(id);
+        for child in self.children(id).to_vec() {
+            self.remove_parent(child);
+        }
+    }
+
+    /// Drop the `GlobalTransform` of an entity with neither parent nor children
+    fn leave_hierarchy_if_alone(&mut self, id: EntityId) {
+        let Some(mut entity) = self.get_entity_mut(id) else {
+            return;
+        };
+        if !entity.has_component::<Parent>() && !entity.has_component::<Children>() {
+            entity.remove_component::<GlobalTransform>();
+        }
+    }
+}
+
+/// Compute the `GlobalTransform` of every entity in a hierarchy (the engine calls
+/// this each frame before rendering)
+///
+/// Entities without a `Transform` pass their parent's transform through unchanged.
+/// An entity is only updated if its `Transform` changed or it was attached since the
+/// last propagation, or an ancestor was updated. Propagation keeps its own change tick,
+/// so changes made after it ran in one frame are picked up in the next.
+pub fn propagate_transforms(scene: &mut Scene) {
+    let since = scene.propagated_tick;
+    let ((), tick) = scene.with_changes_since(since, propagate_changed);
+    scene.propagated_tick = tick;
+}
+
+fn propagate_changed(scene: &mut Scene) {
+    let mut stack: Vec<(EntityId, Mat4, bool)> = scene
+        .query::<(EntityId, &Children, Option<&Parent>)>()
+        .filter(|(_, _, parent)| parent.is_none())
+        .map(|(id, _, _)| (id, Mat4::IDENTITY, false))
+        .collect();
+
+    while let Some((id, parent_matrix, parent_dirty)) = stack.pop() {
+        let current = scene.get_component::<GlobalTransform>(id).copied();
+        let dirty = parent_dirty
+            || current.is_none()
+            || scene.is_changed::<Transform>(id)
+            || scene.is_added::<Parent>(id);
+        let global = match current {
+            Some(global) if !dirty => global.0,
+            _ => {
+                let local = scene.get_component::<Transform>(id).map_or(Mat4::IDENTITY, Transform::matrix);
+                let global = parent_matrix * local;
+                if let Some(mut entity) = scene.get_entity_mut(id) {
+                    entity.add_component(GlobalTransform(global));
+                }
+                global
+            }
+        };
+        stack.extend(scene.children(id).iter().map(|child| (*child, global, dirty)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn(scene: &mut Scene, name: &str, position: Vec3) -> EntityId {
+        let id = scene.create_entity(name.to_string());
+        scene.get_entity_mut(id).unwrap().add_component(Transform::from_position(position));
+        id
+    }
+
+    fn world_position(scene: &Scene, id: EntityId) -> Vec3 {
+        world_matrix(&scene.get_entity(id).unwrap()).unwrap().w_axis.truncate()
+    }
+
+    #[test]
+    fn test_transforms_propagate_down_the_chain() {
+        let mut scene = Scene::new("Test".to_string());
+        let platform = spawn(&mut scene, "Platform", Vec3::new(10.0, 0.0, 0.0));
+        let turret = spawn(&mut scene, "Turret", Vec3::new(0.0, 2.0, 0.0));
+        let barrel = spawn(&mut scene, "Barrel", Vec3::new(0.0, 0.0, -1.0));
+        assert!(scene.set_parent(turret, platform));
+        assert!(scene.set_parent(barrel, turret));
+
+        scene.get_component_mut::<Transform>(platform).unwrap().rotation =
+            Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        propagate_transforms(&mut scene);
+
+        assert!(world_position(&scene, turret).abs_diff_eq(Vec3::new(10.0, 2.0, 0.0), 1e-5));
+        assert!(world_position(&scene, barrel).abs_diff_eq(Vec3::new(9.0, 2.0, 0.0), 1e-5));
+        assert_eq!(scene.children(platform), [turret]);
+
+        // Unchanged subtrees are skipped, changed ones pick up their ancestors
+        scene.clear_trackers();
+        propagate_transforms(&mut scene);
+        assert!(!scene.is_changed::<GlobalTransform>(barrel));
+        scene.get_component_mut::<Transform>(turret).unwrap().position.y = 3.0;
+        propagate_transforms(&mut scene);
+        assert!(!scene.is_changed::<GlobalTransform>(platform));
+        assert!(world_position(&scene, barrel).abs_diff_eq(Vec3::new(9.0, 3.0, 0.0), 1e-5));
+
+        // Changes made after propagation (e.g. by Render systems) outlive the frame's clear
+        scene.get_component_mut::<Transform>(platform).unwrap().position.x = 20.0;
+        scene.clear_trackers();
+        propagate_transforms(&mut scene);
+        assert!(world_position(&scene, turret).abs_diff_eq(Vec3::new(20.0, 3.0, 0.0), 1e-5));
+    }
+
+    #[test]
+    fn test_cycles_rejected_and_links_cleaned_up() {
+        let mut scene = Scene::new("Test".to_string());
+        let a = spawn(&mut scene, "A", Vec3::ZERO);
+        let b = spawn(&mut scene, "B", Vec3::X);
+        let c = spawn(&mut scene, "C", Vec3::Y);
+        assert!(scene.set_parent(b, a));
+        assert!(scene.set_parent(c, b));
+        assert!(!scene.set_parent(a, c));
+        assert!(!scene.set_parent(a, a));
+
+        // Removing the middle entity orphans its child
+        scene.remove_entity(b);
+        assert!(scene.children(a).is_empty());
+        assert_eq!(scene.parent(c), None);
+        propagate_transforms(&mut scene);
+        assert!(!scene.get_entity(c).unwrap().has_component::<GlobalTransform>());
+        assert_eq!(world_position(&scene, c), Vec3::Y);
+
+        assert!(scene.set_parent(c, a));
+        assert!(scene.despawn_recursive(a));
+        assert_eq!(scene.entity_count(), 0);
+    }
+
+    #[test]
+    fn test_despawn_recursive_runs_hooks() {
+        let mut scene = Scene::new("Test".to_string());
+        let base = spawn(&mut scene, "Base", Vec3::ZERO);
+        let tank = spawn(&mut scene, "Tank", Vec3::X);
+        let turret = spawn(&mut scene, "Turret", Vec3::Y);
+        let barrel = spawn(&mut scene, "Barrel", Vec3::Z);
+        scene.set_parent(tank, base);
+        scene.set_parent(turret, tank);
+        scene.set_parent(barrel, turret);
+
+        let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let hook_log = log.clone();
+        scene.on_entity_removed(move |entity| {
+            let parent = entity.get_component::<Parent>().map(Parent::get);
+            hook_log.borrow_mut().push((entity.name().to_string(), parent));
+        });
+
+        scene.clear_trackers();
+        assert!(scene.despawn_recursive(tank));
+        assert_eq!(
+            *log.borrow(),
+            [
+                ("Tank".to_string(), Some(base)),
+                ("Turret".to_string(), Some(tank)),
+                ("Barrel".to_string(), Some(turret)),
+            ]
+        );
+        assert_eq!(scene.removed_entities(), [tank, turret, barrel]);
+        assert!(scene.children(base).is_empty());
+        assert_eq!(scene.entity_count(), 1);
+        assert!(!scene.despawn_recursive(tank));
+    }
+
+    #[test]
+    fn test_active_state_propagates() {
+        let mut scene = Scene::new("Test".to_string());
+        let root = spawn(&mut scene, "Root", Vec3::ZERO);
+        let arm = spawn(&mut scene, "Arm", Vec3::X);
+        let hand = spawn(&mut scene, "Hand", Vec3::Y);
+        scene.set_parent(arm, root);
+        scene.set_parent(hand, arm);
+
+        scene.get_entity_mut(hand).unwrap().set_active(false);
+        scene.get_entity_mut(root).unwrap().set_active(false);
+        let arm_ref = scene.get_entity(arm).unwrap();
+        assert!(arm_ref.is_self_active() && !arm_ref.is_active());
+        assert_eq!(scene.active_entities().count(), 0);
+        assert_eq!(scene.query::<&Transform>().count(), 0);
+
+        // Reactivating the root leaves the hand's own flag alone
+        scene.get_entity_mut(root).unwrap().set_active(true);
+        assert_eq!(scene.active_entities().count(), 2);
+        assert!(!scene.get_entity(hand).unwrap().is_active());
+
+        // Attaching to an inactive parent and detaching again
+        scene.get_entity_mut(hand).unwrap().set_active(true);
+        scene.get_entity_mut(arm).unwrap().set_active(false);
+        assert!(!scene.get_entity(hand).unwrap().is_active());
+        scene.remove_parent(hand);
+        assert!(scene.get_entity(hand).unwrap().is_active());
+        scene.set_parent(hand, arm);
+        assert!(!scene.get_entity(hand).unwrap().is_active());
+        scene.remove_entity(arm);
+        assert!(scene.get_entity(hand).unwrap().is_active());
+    }
+}
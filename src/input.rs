@@ -1,16 +1,165 @@
-//! Input handling for keyboard and mouse
+//! Input handling for keyboard, mouse, touch, and text
 //!
 //! Tracks input state and provides query methods for game logic.
+//!
+//! Text typed this frame, including text committed by an input method editor (IME) for
+//! languages like Chinese or Japanese, is collected separately from key presses, so a
+//! text field only needs [`InputManager::edit_text`]:
+//!
+//! ```ignore
+//! input.set_text_input(true);
+//! if input.edit_text(&mut chat_line) {
+//!     send_chat(std::mem::take(&mut chat_line));
+//! }
+//! ```
 
-use std::collections::HashSet;
-use winit::event::{ElementState, KeyEvent, MouseButton as WinitMouseButton};
-use winit::keyboard::{KeyCode, PhysicalKey};
+use crate::window::CursorGrab;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use winit::event::{ElementState, Ime, KeyEvent, MouseButton as WinitMouseButton, Touch, TouchPhase};
+use winit::keyboard::{Key as LogicalKey, KeyCode, NamedKey, PhysicalKey};
 use glam::Vec2;
 
 pub use winit::keyboard::KeyCode as Key;
 pub use winit::event::MouseButton;
 
-/// Manages input state for keyboard and mouse
+/// A finger touching the screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchPoint {
+    pub id: u64,
+    pub position: Vec2,
+    pub start_position: Vec2,
+    /// Normalized pressure (0 to 1), if the device reports it
+    pub force: Option<f32>,
+}
+
+/// Default time between two presses that counts as a double press
+const DEFAULT_DOUBLE_PRESS_TIME: Duration = Duration::from_millis(300);
+
+/// An input axis read with [`InputManager::axis`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    /// Left/right: A/D, the arrow keys, or an analog value
+    Horizontal,
+    /// Down/up: S/W, the arrow keys, or an analog value
+    Vertical,
+    /// An analog input set by the game, e.g. a gamepad trigger
+    Named(&'static str),
+}
+
+/// How raw axis values map to the values the game reads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResponseCurve {
+    /// Output follows the stick
+    Linear,
+    /// Output is raised to this power, for finer control near the center
+    Exponential(f32),
+}
+
+/// Dead zone, saturation, and response curve of an axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisSettings {
+    /// Raw values up to this far from the center read as 0
+    pub dead_zone: f32,
+    /// Raw values this far from the center and beyond read as fully pushed
+    pub saturation: f32,
+    pub curve: ResponseCurve,
+}
+
+impl Default for AxisSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.1,
+            saturation: 1.0,
+            curve: ResponseCurve::Linear,
+        }
+    }
+}
+
+impl AxisSettings {
+    /// Set the dead zone (0 to 1)
+    pub fn with_dead_zone(mut self, dead_zone: f32) -> Self {
+        self.dead_zone = dead_zone.clamp(0.0, 0.99);
+        self
+    }
+
+    /// Set the saturation (0 to 1)
+    pub fn with_saturation(mut self, saturation: f32) -> Self {
+        self.saturation = saturation.clamp(0.01, 1.0);
+        self
+    }
+
+    /// Set the response curve
+    pub fn with_curve(mut self, curve: ResponseCurve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    /// Map a raw value (-1 to 1): the range between the dead zone and saturation is
+    /// stretched to 0 to 1, then shaped by the curve
+    pub fn apply(&self, value: f32) -> f32 {
+        let magnitude = value.abs().min(1.0);
+        if magnitude <= self.dead_zone {
+            return 0.0;
+        }
+        let range = (self.saturation - self.dead_zone).max(f32::EPSILON);
+        let scaled = ((magnitude - self.dead_zone) / range).min(1.0);
+        let shaped = match self.curve {
+            ResponseCurve::Linear => scaled,
+            ResponseCurve::Exponential(exponent) => scaled.powf(exponent.max(0.0)),
+        };
+        shaped.copysign(value)
+    }
+}
+
+/// Text being composed in an input method editor, not yet committed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImeComposition {
+    pub text: String,
+    /// Byte range of the cursor or selection within `text`, if shown
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// A change to a text field, in the order typed
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TextEdit {
+    Insert(String),
+    Backspace,
+}
+
+/// Cursor settings the game asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CursorState {
+    grab: CursorGrab,
+    visible: bool,
+}
+
+impl Default for CursorState {
+    fn default() -> Self {
+        Self {
+            grab: CursorGrab::None,
+            visible: true,
+        }
+    }
+}
+
+/// Cursor and IME requests, applied to the window by the engine
+#[derive(Debug, Default)]
+struct WindowRequests {
+    text_input: bool,
+    text_input_changed: bool,
+    ime_cursor_area: Option<(Vec2, Vec2)>,
+    cursor: CursorState,
+    cursor_changed: bool,
+    center_cursor: bool,
+}
+
+/// Manages input state for keyboard, mouse, and touch
+///
+/// Methods that take `&self` to set analog axes or make cursor and IME requests lock
+/// internally, so systems running in parallel can share the manager.
 #[derive(Debug)]
 pub struct InputManager {
     // Keyboard state
@@ -25,6 +174,32 @@ pub struct InputManager {
     mouse_position: Vec2,
     mouse_delta: Vec2,
     scroll_delta: f32,
+
+    // Axes
+    analog_axes: Mutex<HashMap<Axis, f32>>,
+    axis_settings: HashMap<Axis, AxisSettings>,
+
+    // Double presses
+    double_press_time: Duration,
+    last_key_presses: HashMap<KeyCode, Instant>,
+    keys_double_tapped: HashSet<KeyCode>,
+    last_mouse_presses: HashMap<WinitMouseButton, Instant>,
+    mouse_buttons_double_clicked: HashSet<WinitMouseButton>,
+
+    // Touch state
+    touches: HashMap<u64, TouchPoint>,
+    touches_just_started: Vec<u64>,
+    touches_just_ended: Vec<TouchPoint>,
+    touch_emulates_mouse: bool,
+    mouse_touch: Option<u64>,
+
+    // Text state
+    typed_text: String,
+    text_edits: Vec<TextEdit>,
+    text_submitted: bool,
+    ime_composition: Option<ImeComposition>,
+
+    requests: Mutex<WindowRequests>,
 }
 
 impl InputManager {
@@ -40,6 +215,23 @@ impl InputManager {
             mouse_position: Vec2::ZERO,
             mouse_delta: Vec2::ZERO,
             scroll_delta: 0.0,
+            analog_axes: Mutex::new(HashMap::new()),
+            axis_settings: HashMap::new(),
+            double_press_time: DEFAULT_DOUBLE_PRESS_TIME,
+            last_key_presses: HashMap::new(),
+            keys_double_tapped: HashSet::new(),
+            last_mouse_presses: HashMap::new(),
+            mouse_buttons_double_clicked: HashSet::new(),
+            touches: HashMap::new(),
+            touches_just_started: Vec::new(),
+            touches_just_ended: Vec::new(),
+            touch_emulates_mouse: cfg!(any(target_os = "android", target_os = "ios")),
+            mouse_touch: None,
+            typed_text: String::new(),
+            text_edits: Vec::new(),
+            text_submitted: false,
+            ime_composition: None,
+            requests: Mutex::new(WindowRequests::default()),
         }
     }
 
@@ -49,8 +241,15 @@ impl InputManager {
         self.keys_just_released.clear();
         self.mouse_buttons_just_pressed.clear();
         self.mouse_buttons_just_released.clear();
+        self.keys_double_tapped.clear();
+        self.mouse_buttons_double_clicked.clear();
         self.mouse_delta = Vec2::ZERO;
         self.scroll_delta = 0.0;
+        self.touches_just_started.clear();
+        self.touches_just_ended.clear();
+        self.typed_text.clear();
+        self.text_edits.clear();
+        self.text_submitted = false;
     }
 
     /// Handle keyboard input event
@@ -60,6 +259,9 @@ impl InputManager {
                 ElementState::Pressed => {
                     if !self.keys_pressed.contains(&key_code) {
                         self.keys_just_pressed.insert(key_code);
+                        if double_press(&mut self.last_key_presses, key_code, self.double_press_time) {
+                            self.keys_double_tapped.insert(key_code);
+                        }
                     }
                     self.keys_pressed.insert(key_code);
                 }
@@ -69,6 +271,45 @@ impl InputManager {
                 }
             }
         }
+
+        // Text, backspace, and enter follow key repeat, unlike key presses
+        if event.state == ElementState::Pressed {
+            match &event.logical_key {
+                LogicalKey::Named(NamedKey::Backspace) => self.text_edits.push(TextEdit::Backspace),
+                LogicalKey::Named(NamedKey::Enter) => self.text_submitted = true,
+                _ => {
+                    if let Some(text) = &event.text {
+                        self.insert_text(text);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handle an input method editor event
+    pub fn handle_ime(&mut self, ime: &Ime) {
+        match ime {
+            Ime::Preedit(text, cursor) if !text.is_empty() => {
+                self.ime_composition = Some(ImeComposition {
+                    text: text.clone(),
+                    cursor: *cursor,
+                });
+            }
+            Ime::Commit(text) => {
+                self.ime_composition = None;
+                self.insert_text(text);
+            }
+            Ime::Preedit(..) | Ime::Disabled => self.ime_composition = None,
+            Ime::Enabled => {}
+        }
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        if !text.is_empty() {
+            self.typed_text.push_str(&text);
+            self.text_edits.push(TextEdit::Insert(text));
+        }
     }
 
     /// Handle mouse button input
@@ -77,6 +318,9 @@ impl InputManager {
             ElementState::Pressed => {
                 if !self.mouse_buttons_pressed.contains(&button) {
                     self.mouse_buttons_just_pressed.insert(button);
+                    if double_press(&mut self.last_mouse_presses, button, self.double_press_time) {
+                        self.mouse_buttons_double_clicked.insert(button);
+                    }
                 }
                 self.mouse_buttons_pressed.insert(button);
             }
@@ -87,9 +331,9 @@ impl InputManager {
         }
     }
 
-    /// Handle mouse motion
+    /// Handle raw mouse motion, accumulated until the next `update`
     pub fn handle_mouse_motion(&mut self, delta: (f64, f64)) {
-        self.mouse_delta = Vec2::new(delta.0 as f32, delta.1 as f32);
+        self.mouse_delta += Vec2::new(delta.0 as f32, delta.1 as f32);
     }
 
     /// Set mouse position
@@ -102,6 +346,102 @@ impl InputManager {
         self.scroll_delta = delta;
     }
 
+    /// Handle a touch event
+    pub fn handle_touch(&mut self, touch: &Touch) {
+        let position = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+        let force = touch.force.map(|f| f.normalized() as f32);
+
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.insert(
+                    touch.id,
+                    TouchPoint {
+                        id: touch.id,
+                        position,
+                        start_position: position,
+                        force,
+                    },
+                );
+                self.touches_just_started.push(touch.id);
+
+                if self.touch_emulates_mouse && self.mouse_touch.is_none() {
+                    self.mouse_touch = Some(touch.id);
+                    self.mouse_position = position;
+                    self.handle_mouse_button(WinitMouseButton::Left, ElementState::Pressed);
+                }
+            }
+            TouchPhase::Moved => {
+                if let Some(point) = self.touches.get_mut(&touch.id) {
+                    point.position = position;
+                    point.force = force;
+                }
+                if self.mouse_touch == Some(touch.id) {
+                    self.mouse_delta += position - self.mouse_position;
+                    self.mouse_position = position;
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if let Some(mut point) = self.touches.remove(&touch.id) {
+                    point.position = position;
+                    self.touches_just_ended.push(point);
+                }
+                if self.mouse_touch == Some(touch.id) {
+                    self.mouse_touch = None;
+                    self.mouse_position = position;
+                    self.handle_mouse_button(WinitMouseButton::Left, ElementState::Released);
+                }
+            }
+        }
+    }
+
+    /// Make the first finger act as the left mouse button (on by default on mobile)
+    pub fn set_touch_emulates_mouse(&mut self, enabled: bool) {
+        self.touch_emulates_mouse = enabled;
+    }
+
+    /// Get all fingers currently touching the screen
+    pub fn touches(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.touches.values()
+    }
+
+    /// Get a touch by id
+    pub fn touch(&self, id: u64) -> Option<&TouchPoint> {
+        self.touches.get(&id)
+    }
+
+    /// Get the number of fingers touching the screen
+    pub fn touch_count(&self) -> usize {
+        self.touches.len()
+    }
+
+    /// Get touches that started this frame
+    pub fn touches_just_started(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.touches_just_started.iter().filter_map(|id| self.touches.get(id))
+    }
+
+    /// Get touches that ended this frame (with their final position)
+    pub fn touches_just_ended(&self) -> &[TouchPoint] {
+        &self.touches_just_ended
+    }
+
+    /// Release every key, button, and touch (e.g. when the app loses focus)
+    pub fn clear(&mut self) {
+        for key in self.keys_pressed.drain() {
+            self.keys_just_released.insert(key);
+        }
+        for button in self.mouse_buttons_pressed.drain() {
+            self.mouse_buttons_just_released.insert(button);
+        }
+        self.touches_just_ended.extend(self.touches.drain().map(|(_, point)| point));
+        self.mouse_touch = None;
+        self.ime_composition = None;
+        self.last_key_presses.clear();
+        self.last_mouse_presses.clear();
+        self.analog_axes.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+        self.mouse_delta = Vec2::ZERO;
+        self.scroll_delta = 0.0;
+    }
+
     /// Check if a key is currently pressed
     pub fn key_pressed(&self, key: KeyCode) -> bool {
         self.keys_pressed.contains(&key)
@@ -132,44 +472,262 @@ impl InputManager {
         self.mouse_buttons_just_released.contains(&button)
     }
 
+    /// Check if a key was pressed twice in quick succession, on the second press
+    ///
+    /// A third quick press starts a new pair rather than counting again.
+    pub fn key_double_tapped(&self, key: KeyCode) -> bool {
+        self.keys_double_tapped.contains(&key)
+    }
+
+    /// Check if a mouse button was double-clicked, on the second click
+    pub fn mouse_double_clicked(&self, button: WinitMouseButton) -> bool {
+        self.mouse_buttons_double_clicked.contains(&button)
+    }
+
+    /// Set the longest time between two presses that counts as a double press
+    pub fn set_double_press_time(&mut self, time: Duration) {
+        self.double_press_time = time;
+    }
+
+    /// Get the longest time between two presses that counts as a double press
+    pub fn double_press_time(&self) -> Duration {
+        self.double_press_time
+    }
+
     /// Get current mouse position
     pub fn mouse_position(&self) -> Vec2 {
         self.mouse_position
     }
 
-    /// Get mouse movement delta
+    /// Get the raw mouse movement since the last frame
+    ///
+    /// This comes straight from the device, so it keeps counting when the cursor is
+    /// stuck at the edge of the screen or grabbed, as mouse look needs.
     pub fn mouse_delta(&self) -> Vec2 {
         self.mouse_delta
     }
 
+    /// Get the text typed this frame, with key repeat and committed IME text
+    pub fn typed_text(&self) -> &str {
+        &self.typed_text
+    }
+
+    /// Get how many times backspace was pressed this frame, with key repeat
+    pub fn backspaces(&self) -> usize {
+        self.text_edits.iter().filter(|edit| **edit == TextEdit::Backspace).count()
+    }
+
+    /// Check if enter was pressed this frame
+    pub fn text_submitted(&self) -> bool {
+        self.text_submitted
+    }
+
+    /// Apply this frame's typing and backspaces to `text`, returning whether enter was
+    /// pressed
+    pub fn edit_text(&self, text: &mut String) -> bool {
+        for edit in &self.text_edits {
+            match edit {
+                TextEdit::Insert(typed) => text.push_str(typed),
+                TextEdit::Backspace => {
+                    text.pop();
+                }
+            }
+        }
+        self.text_submitted
+    }
+
+    /// Get the text the IME is composing, to draw in the text field until it's committed
+    pub fn ime_composition(&self) -> Option<&ImeComposition> {
+        self.ime_composition.as_ref()
+    }
+
+    /// Let an input method editor compose text, e.g. while a text field has focus
+    ///
+    /// Typed text is reported either way; without an IME, languages that need one
+    /// can't be entered.
+    pub fn set_text_input(&self, enabled: bool) {
+        let mut requests = self.requests();
+        if enabled != requests.text_input {
+            requests.text_input = enabled;
+            requests.text_input_changed = true;
+        }
+    }
+
+    /// Check if text input with an IME is enabled
+    pub fn text_input(&self) -> bool {
+        self.requests().text_input
+    }
+
+    /// Place the IME's candidate window next to the text field at `position` (in pixels)
+    pub fn set_ime_cursor_area(&self, position: Vec2, size: Vec2) {
+        self.requests().ime_cursor_area = Some((position, size));
+    }
+
+    /// Take the IME state to apply, if it changed
+    pub(crate) fn take_text_input_changes(&self) -> (Option<bool>, Option<(Vec2, Vec2)>) {
+        let mut requests = self.requests();
+        let enabled = std::mem::take(&mut requests.text_input_changed).then_some(requests.text_input);
+        (enabled, requests.ime_cursor_area.take())
+    }
+
+    fn requests(&self) -> MutexGuard<'_, WindowRequests> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Confine the cursor to the window or lock it in place
+    ///
+    /// Cursor methods take `&self` so the game loop can call them; the engine applies
+    /// the changes to the window at the end of the frame, falling back to the other
+    /// grab mode where the platform lacks one.
+    pub fn set_cursor_grab(&self, grab: CursorGrab) {
+        let cursor = self.requests().cursor;
+        self.request_cursor(CursorState { grab, ..cursor });
+    }
+
+    /// Get the requested cursor grab
+    pub fn cursor_grab(&self) -> CursorGrab {
+        self.requests().cursor.grab
+    }
+
+    /// Show or hide the cursor while it's over the window
+    pub fn set_cursor_visible(&self, visible: bool) {
+        let cursor = self.requests().cursor;
+        self.request_cursor(CursorState { visible, ..cursor });
+    }
+
+    /// Check if the cursor is shown
+    pub fn cursor_visible(&self) -> bool {
+        self.requests().cursor.visible
+    }
+
+    /// Move the cursor to the center of the window at the end of the frame
+    pub fn center_cursor(&self) {
+        self.requests().center_cursor = true;
+    }
+
+    /// Hide and lock the cursor for mouse look, reading movement from `mouse_delta`,
+    /// or release it again
+    ///
+    /// Where the cursor can only be confined, the engine keeps it centered instead.
+    pub fn set_relative_mouse_mode(&self, enabled: bool) {
+        self.request_cursor(if enabled {
+            CursorState {
+                grab: CursorGrab::Locked,
+                visible: false,
+            }
+        } else {
+            CursorState::default()
+        });
+    }
+
+    /// Check if relative mouse mode is on
+    pub fn relative_mouse_mode(&self) -> bool {
+        self.requests().cursor
+            == CursorState {
+                grab: CursorGrab::Locked,
+                visible: false,
+            }
+    }
+
+    fn request_cursor(&self, cursor: CursorState) {
+        let mut requests = self.requests();
+        if cursor != requests.cursor {
+            requests.cursor = cursor;
+            requests.cursor_changed = true;
+        }
+    }
+
+    /// Apply the cursor settings again, e.g. after the window regains focus
+    pub(crate) fn refresh_cursor(&self) {
+        self.requests().cursor_changed = true;
+    }
+
+    /// Take the cursor grab and visibility to apply, if they changed
+    pub(crate) fn take_cursor_changes(&self) -> Option<(CursorGrab, bool)> {
+        let mut requests = self.requests();
+        let cursor = requests.cursor;
+        std::mem::take(&mut requests.cursor_changed).then_some((cursor.grab, cursor.visible))
+    }
+
+    /// Take a pending `center_cursor` request
+    pub(crate) fn take_center_cursor(&self) -> bool {
+        std::mem::take(&mut self.requests().center_cursor)
+    }
+
     /// Get scroll wheel delta
     pub fn scroll_delta(&self) -> f32 {
         self.scroll_delta
     }
 
+    /// Get the value of an axis (-1 to 1), shaped by its [`AxisSettings`]
+    ///
+    /// Horizontal and vertical combine the arrow and WASD keys with any analog value
+    /// set for them, whichever is pushed further.
+    pub fn axis(&self, axis: Axis) -> f32 {
+        let digital = match axis {
+            Axis::Horizontal => self.key_axis([KeyCode::ArrowLeft, KeyCode::KeyA], [KeyCode::ArrowRight, KeyCode::KeyD]),
+            Axis::Vertical => self.key_axis([KeyCode::ArrowDown, KeyCode::KeyS], [KeyCode::ArrowUp, KeyCode::KeyW]),
+            Axis::Named(_) => 0.0,
+        };
+        let analog = self.analog_axes().get(&axis).copied().unwrap_or(0.0);
+        let raw = if analog.abs() > digital.abs() { analog } else { digital };
+        self.axis_settings(axis).apply(raw)
+    }
+
     /// Get horizontal axis input (-1 to 1)
     pub fn axis_horizontal(&self) -> f32 {
-        let mut value = 0.0;
-        if self.key_pressed(KeyCode::ArrowLeft) || self.key_pressed(KeyCode::KeyA) {
-            value -= 1.0;
-        }
-        if self.key_pressed(KeyCode::ArrowRight) || self.key_pressed(KeyCode::KeyD) {
-            value += 1.0;
-        }
-        value
+        self.axis(Axis::Horizontal)
     }
 
     /// Get vertical axis input (-1 to 1)
     pub fn axis_vertical(&self) -> f32 {
+        self.axis(Axis::Vertical)
+    }
+
+    fn key_axis(&self, negative: [KeyCode; 2], positive: [KeyCode; 2]) -> f32 {
         let mut value = 0.0;
-        if self.key_pressed(KeyCode::ArrowDown) || self.key_pressed(KeyCode::KeyS) {
+        if negative.iter().any(|&key| self.key_pressed(key)) {
             value -= 1.0;
         }
-        if self.key_pressed(KeyCode::ArrowUp) || self.key_pressed(KeyCode::KeyW) {
+        if positive.iter().any(|&key| self.key_pressed(key)) {
             value += 1.0;
         }
         value
     }
+
+    /// Set the raw value of an analog input (a gamepad stick or trigger) feeding an axis
+    ///
+    /// The value holds until it's set again, like the stick it comes from. Takes `&self`
+    /// so the game loop and systems can feed it every frame.
+    pub fn set_analog_axis(&self, axis: Axis, value: f32) {
+        self.analog_axes().insert(axis, value.clamp(-1.0, 1.0));
+    }
+
+    fn analog_axes(&self) -> MutexGuard<'_, HashMap<Axis, f32>> {
+        self.analog_axes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Set the dead zone, saturation, and response curve of an axis
+    pub fn set_axis_settings(&mut self, axis: Axis, settings: AxisSettings) {
+        self.axis_settings.insert(axis, settings);
+    }
+
+    /// Get the settings of an axis
+    pub fn axis_settings(&self, axis: Axis) -> AxisSettings {
+        self.axis_settings.get(&axis).copied().unwrap_or_default()
+    }
+}
+
+/// Record a press of `key`, returning whether it completes a double press
+fn double_press<T: Eq + Hash>(last_presses: &mut HashMap<T, Instant>, key: T, window: Duration) -> bool {
+    let now = Instant::now();
+    match last_presses.remove(&key) {
+        Some(last) if now.duration_since(last) <= window => true,
+        _ => {
+            last_presses.insert(key, now);
+            false
+        }
+    }
 }
 
 impl Default for InputManager {
@@ -177,3 +735,115 @@ impl Default for InputManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mouse_motion_accumulates_per_frame() {
+        let mut input = InputManager::new();
+        input.handle_mouse_motion((3.0, -1.0));
+        input.handle_mouse_motion((2.0, 4.0));
+        assert_eq!(input.mouse_delta(), Vec2::new(5.0, 3.0));
+        input.update();
+        assert_eq!(input.mouse_delta(), Vec2::ZERO);
+
+        // Losing focus mid-frame drops motion that hasn't been read yet
+        input.handle_mouse_motion((1.0, 1.0));
+        input.handle_scroll(2.0);
+        input.clear();
+        assert_eq!(input.mouse_delta(), Vec2::ZERO);
+        assert_eq!(input.scroll_delta(), 0.0);
+    }
+
+    #[test]
+    fn test_cursor_requests_applied_once() {
+        let input = InputManager::new();
+        assert_eq!(input.take_cursor_changes(), None);
+
+        input.set_relative_mouse_mode(true);
+        assert!(input.relative_mouse_mode());
+        assert_eq!(input.take_cursor_changes(), Some((CursorGrab::Locked, false)));
+        assert_eq!(input.take_cursor_changes(), None);
+
+        // Asking for what's already set changes nothing
+        input.set_cursor_grab(CursorGrab::Locked);
+        assert_eq!(input.take_cursor_changes(), None);
+
+        input.set_cursor_visible(true);
+        assert!(!input.relative_mouse_mode());
+        assert_eq!(input.take_cursor_changes(), Some((CursorGrab::Locked, true)));
+    }
+
+    #[test]
+    fn test_axis_settings() {
+        let mut input = InputManager::new();
+        input.set_analog_axis(Axis::Horizontal, 0.05);
+        assert_eq!(input.axis_horizontal(), 0.0);
+        input.set_analog_axis(Axis::Horizontal, -0.55);
+        assert!((input.axis_horizontal() + 0.5).abs() < 1e-6);
+
+        let trigger = Axis::Named("throttle");
+        input.set_axis_settings(
+            trigger,
+            AxisSettings::default()
+                .with_dead_zone(0.2)
+                .with_saturation(0.8)
+                .with_curve(ResponseCurve::Exponential(2.0)),
+        );
+        input.set_analog_axis(trigger, 0.5);
+        assert!((input.axis(trigger) - 0.25).abs() < 1e-6);
+        input.set_analog_axis(trigger, 0.9);
+        assert_eq!(input.axis(trigger), 1.0);
+        // Fully pushed digital input passes through any settings
+        assert_eq!(input.axis_settings(trigger).apply(-1.0), -1.0);
+    }
+
+    #[test]
+    fn test_double_click() {
+        let mut input = InputManager::new();
+        input.set_double_press_time(Duration::from_secs(60));
+        let click = |input: &mut InputManager| {
+            input.update();
+            input.handle_mouse_button(MouseButton::Left, ElementState::Pressed);
+            input.handle_mouse_button(MouseButton::Left, ElementState::Released);
+        };
+
+        click(&mut input);
+        assert!(!input.mouse_double_clicked(MouseButton::Left));
+        click(&mut input);
+        assert!(input.mouse_double_clicked(MouseButton::Left));
+        // The third click starts a new pair
+        click(&mut input);
+        assert!(!input.mouse_double_clicked(MouseButton::Left));
+
+        input.set_double_press_time(Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(1));
+        click(&mut input);
+        assert!(!input.mouse_double_clicked(MouseButton::Left));
+    }
+
+    #[test]
+    fn test_text_edits_apply_in_order() {
+        let mut input = InputManager::new();
+        input.handle_ime(&Ime::Preedit("にほ".to_string(), Some((6, 6))));
+        assert_eq!(input.ime_composition().unwrap().text, "にほ");
+
+        input.handle_ime(&Ime::Commit("日本".to_string()));
+        assert_eq!(input.ime_composition(), None);
+        input.text_edits.push(TextEdit::Backspace);
+        input.insert_text("語\r");
+        assert_eq!(input.typed_text(), "日本語");
+        assert_eq!(input.backspaces(), 1);
+
+        let mut field = "> ".to_string();
+        assert!(!input.edit_text(&mut field));
+        assert_eq!(field, "> 日語");
+
+        input.update();
+        assert_eq!(input.typed_text(), "");
+        assert!(!input.edit_text(&mut field));
+        assert_eq!(field, "> 日語");
+    }
+}
@@ -0,0 +1,337 @@
+//! Vignette, film grain, and chromatic aberration post effects
+//!
+//! These lightweight effects imitate a camera lens and film. Enable them by adding
+//! `PostEffect::Vignette`, `PostEffect::FilmGrain`, or `PostEffect::ChromaticAberration`
+//! to the renderer's post-processing stack; whichever of them are in the stack run
+//! together in a single pass, at the position of the first one. Each has an intensity
+//! meant to be animated by games, e.g. a stronger vignette while the player is hurt.
+
+use crate::postprocess::HDR_FORMAT;
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+/// Darkening towards the screen corners
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VignetteSettings {
+    /// How dark the corners get (0.0..=1.0)
+    pub intensity: f32,
+    /// Distance from the center where darkening starts (1.0 is the corners)
+    pub radius: f32,
+    /// Width of the transition from the radius outwards
+    pub smoothness: f32,
+}
+
+impl Default for VignetteSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 0.35,
+            radius: 0.5,
+            smoothness: 0.5,
+        }
+    }
+}
+
+/// Animated noise over the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilmGrainSettings {
+    /// Strength of the noise, as a fraction of the pixel brightness
+    pub intensity: f32,
+    /// Size of the grain in pixels
+    pub size: f32,
+}
+
+impl Default for FilmGrainSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 0.05,
+            size: 1.5,
+        }
+    }
+}
+
+/// Color fringes from red and blue separating towards the screen edges
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChromaticAberrationSettings {
+    /// Separation of the red and blue channels at the screen corners, in pixels
+    pub intensity: f32,
+}
+
+impl Default for ChromaticAberrationSettings {
+    fn default() -> Self {
+        Self { intensity: 2.0 }
+    }
+}
+
+/// Lens uniform buffer data
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+struct LensUniform {
+    vignette: [f32; 4],
+    grain: [f32; 4],
+    aberration: [f32; 4],
+}
+
+impl LensUniform {
+    /// Uniform for the enabled effects; disabled ones get zero intensity
+    fn new(
+        vignette: Option<&VignetteSettings>,
+        grain: Option<&FilmGrainSettings>,
+        aberration: Option<&ChromaticAberrationSettings>,
+        frame: u32,
+    ) -> Self {
+        let vignette = vignette.map_or([0.0; 4], |v| {
+            [v.intensity.clamp(0.0, 1.0), v.radius, v.smoothness.max(1e-3), 0.0]
+        });
+        let (grain_intensity, grain_size) = grain.map_or((0.0, 1.0), |g| (g.intensity.max(0.0), g.size.max(1.0)));
+        Self {
+            vignette,
+            // Cycle the seed so it stays exactly representable as a float
+            grain: [grain_intensity, grain_size, (frame % 4096) as f32, 0.0],
+            aberration: [aberration.map_or(0.0, |a| a.intensity), 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// GPU pipelines and scratch target for the lens effects pass
+pub(crate) struct LensPipeline {
+    effects: wgpu::RenderPipeline,
+    copy: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    buffer: wgpu::Buffer,
+    /// Samples the HDR target while writing the scratch target
+    source_bind_group: wgpu::BindGroup,
+    scratch: wgpu::TextureView,
+    /// Samples the scratch target while copying it back
+    scratch_bind_group: wgpu::BindGroup,
+    /// Frames drawn, seeding the grain
+    frame: u32,
+}
+
+impl LensPipeline {
+    pub(crate) fn new(device: &wgpu::Device, source: &wgpu::TextureView, width: u32, height: u32) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Lens Buffer"),
+            contents: bytemuck::cast_slice(&[LensUniform::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("lens_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Lens Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Lens Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/lens.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Lens Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label: &str, entry_point: &str| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: HDR_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        let effects = create_pipeline("Lens Pipeline", "fs_main");
+        let copy = create_pipeline("Lens Copy Pipeline", "fs_copy");
+        let scratch = Self::create_scratch(device, width, height);
+        let source_bind_group = Self::create_bind_group(device, &layout, &sampler, &buffer, source);
+        let scratch_bind_group = Self::create_bind_group(device, &layout, &sampler, &buffer, &scratch);
+        Self {
+            effects,
+            copy,
+            layout,
+            sampler,
+            buffer,
+            source_bind_group,
+            scratch,
+            scratch_bind_group,
+            frame: 0,
+        }
+    }
+
+    fn create_scratch(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Lens Scratch Texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        buffer: &wgpu::Buffer,
+        source: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("lens_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// Recreate the scratch target for a new HDR target
+    pub(crate) fn resize(&mut self, device: &wgpu::Device, source: &wgpu::TextureView, width: u32, height: u32) {
+        self.scratch = Self::create_scratch(device, width, height);
+        self.source_bind_group = Self::create_bind_group(device, &self.layout, &self.sampler, &self.buffer, source);
+        self.scratch_bind_group =
+            Self::create_bind_group(device, &self.layout, &self.sampler, &self.buffer, &self.scratch);
+    }
+
+    /// Upload the settings of the effects in the stack (`None` for the others) for this
+    /// frame, advancing the grain
+    pub(crate) fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        vignette: Option<&VignetteSettings>,
+        grain: Option<&FilmGrainSettings>,
+        aberration: Option<&ChromaticAberrationSettings>,
+    ) {
+        self.frame = self.frame.wrapping_add(1);
+        let uniform = LensUniform::new(vignette, grain, aberration, self.frame);
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Record the effects pass and the copy back onto `target` (the HDR scene color)
+    pub(crate) fn draw(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let passes = [
+            ("Lens Pass", &self.scratch, &self.effects, &self.source_bind_group),
+            ("Lens Copy Pass", target, &self.copy, &self.scratch_bind_group),
+        ];
+        for (label, view, pipeline, source) in passes {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, source, &[]);
+            pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lens_uniform_disables_missing_effects() {
+        let uniform = LensUniform::new(None, None, None, 7);
+        assert_eq!(uniform.vignette[0], 0.0);
+        assert_eq!(uniform.grain[0], 0.0);
+        assert_eq!(uniform.aberration[0], 0.0);
+
+        let vignette = VignetteSettings {
+            intensity: 2.0,
+            smoothness: 0.0,
+            ..Default::default()
+        };
+        let uniform = LensUniform::new(
+            Some(&vignette),
+            Some(&FilmGrainSettings::default()),
+            Some(&ChromaticAberrationSettings::default()),
+            4097,
+        );
+        assert_eq!(uniform.vignette, [1.0, 0.5, 1e-3, 0.0]);
+        assert_eq!(uniform.grain, [0.05, 1.5, 1.0, 0.0]);
+        assert_eq!(uniform.aberration[0], 2.0);
+    }
+}
@@ -5,13 +5,83 @@
 //! ## Features
 //! - Modern GPU rendering via wgpu (Vulkan, DirectX 12, Metal)
 //! - Window management and input handling via winit
-//! - Audio playback via rodio
+//! - Double-tap and double-click detection with a configurable time window
+//! - Input axes with dead zones, saturation, and response curves for analog sticks
+//! - Cursor grab, hiding, and relative mouse mode for first-person controls
+//! - Text input with key repeat and IME composition for chat boxes and name entry
+//! - Android and iOS support: suspend/resume lifecycle, touch input, and bundled asset loading
+//! - Audio playback via rodio, with positional sound effects and per-sound pitch and pan
+//! - Runtime-adjustable filter, reverb, and echo effects on the music and sound effect buses
+//! - Output device selection with seamless recovery when the device changes
+//! - Optional pausing or ducking of audio while the window is in the background
+//! - Music position tracking with beat and marker callbacks for rhythm mechanics
+//! - `AudioEmitter` and `AudioListener` components for sounds that follow entities
 //! - Math utilities via glam
-//! - Simple ECS (Entity Component System)
+//! - ECS with archetype and sparse-set component storage and tuple queries, optionally run in parallel
+//! - Cached queries that only re-match tables after the scene structure changes
+//! - Change detection with `Changed`/`Added` query filters
+//! - Zero-sized tag components with `With`/`Without` query filters
+//! - One-call spawning from component tuples, plus `#[derive(Component)]` and `#[derive(Bundle)]`
+//! - Dynamic string-keyed components for scripts and data-driven content
+//! - Entity cloning, optionally with the whole child hierarchy
+//! - Multiple loaded scenes with switching hooks and persistent entities
+//! - Deferred command buffers for spawning and despawning while iterating
+//! - Parent/child entity hierarchies with world transform and active state propagation
+//! - Prefab entity templates built in code or loaded from JSON
+//! - Scene files keeping entity IDs, hierarchy, and registered components
+//! - Copy-on-write scene snapshots for rewind, undo, and rollback netcode
+//! - System scheduler with PreUpdate/Update/PostUpdate/Render stages and ordering constraints
+//! - Declared system data access with conflict detection, batches, and an execution graph dump
+//! - Parallel systems that run alongside the rest of their batch
 //! - Resource management for textures, shaders, and meshes
+//! - Mesh vertex formats from full 3D vertices down to position-color and position-UV
+//! - GPU mipmap generation and anisotropic texture filtering
+//! - KTX2/DDS block-compressed textures (BC1/BC3/BC5/BC7) with CPU decompression fallback
+//! - Virtual file system with prioritized mounts for asset overrides
+//! - Mod discovery with load ordering, dependencies, and capability manifests
 //! - 2D and 3D rendering capabilities
+//! - Multiple cameras with viewports for split screen and minimaps
+//! - Fly camera controller with WASD movement, mouse look, and scroll speed
+//! - Follow and orbit camera components that aim the camera at an entity
+//! - Frustum culling and distance-based mesh LOD with dithered cross-fades
+//! - Occlusion culling with hardware occlusion queries
+//! - Optional depth pre-pass so overlapping opaque meshes are shaded once per pixel
+//! - Stencil-based selection outlines for editor and gameplay highlighting
+//! - Headless rendering into an offscreen texture for tests and tools
+//! - Static batching of meshes sharing a texture and material
+//! - Per-object uniforms packed into one dynamically offset buffer per frame
+//! - Render graph for composing shadow, post-processing, and UI passes
+//! - Point and spot lights as ECS components
+//! - Physically-based metallic/roughness materials
+//! - Planar reflections and cubemap reflection probes lighting the PBR specular term
+//! - Hot reload of the built-in and material WGSL shaders during development
+//! - GPU skinning of meshes posed by joint hierarchies
+//! - HDR rendering with ACES or Reinhard tone mapping
+//! - Post-processing stack with bloom, vignette, film grain, and chromatic aberration
+//! - Color grading through 3D LUTs, blended for day/night moods
+//! - FXAA and temporal anti-aliasing on top of MSAA
+//! - Heightmap terrain with quadtree LOD chunks and texture splatting
+//! - Day/night cycle with procedural sky and sun/ambient lighting
 //! - Configuration loading from JSON
+//! - Grid A* pathfinding with path smoothing and per-frame search budgets
+//! - Navmesh baking with path-following agents and local avoidance
+//! - Composable steering behaviors and flocking
+//! - Versioned save slots for persistent components and world resources
+//! - UDP client-server networking with component replication
+//! - Rollback netcode with input prediction for peer-to-peer games
+//! - Replay recording with snapshot seeking and spectator camera playback
+//! - Localization with JSON/FTL string tables, plurals, and runtime language switching
 //! - Built-in logging and debug overlay
+//! - Per-frame render statistics with GPU pass timings from timestamp queries
+//! - Crash handler writing diagnostic reports on panic
+//! - Immediate-mode debug line drawing
+//! - Infinite editor ground grid with origin axes
+//! - Camera-facing line and trail renderers for projectiles, swings, and skid marks
+//! - GPU-simulated particle emitters drawn as instanced billboards
+//! - Editor primitives: picking, transform gizmos, and undoable edits
+//! - Gameplay clip capture to PNG sequences, GIF, or MP4 (via ffmpeg)
+//! - Optional egui debug tooling with an entity inspector (`egui` feature)
+//! - Optional sandboxed WebAssembly gameplay scripts (`wasm` feature)
 //!
 //! ## Example Usage
 //! ```no_run
@@ -28,30 +98,116 @@
 //! }
 //! ```
 
+// Lets the derive macros refer to `::rgame` from inside this crate too
+extern crate self as rgame;
+
+pub mod antialias;
 pub mod audio;
+pub mod audio_effects;
+pub mod audio_emitter;
+pub mod audio_output;
+pub mod audio_sync;
+pub mod batching;
+pub mod bloom;
+pub mod bundle;
+pub mod camera_controller;
+pub mod capture;
+pub mod color_grading;
+pub mod commands;
+pub mod compressed_texture;
 pub mod config;
+pub mod crash;
+pub mod debug_draw;
+#[cfg(feature = "egui")]
+pub mod debug_ui;
+pub mod depth_prepass;
+pub mod duplicate;
+pub mod dynamic;
 pub mod ecs;
+pub mod editor;
 pub mod engine;
+pub mod gpu_timing;
+pub mod grid;
+pub mod hierarchy;
 pub mod input;
+pub mod lens;
+pub mod light;
+pub mod localization;
+pub mod lod;
 pub mod math;
+pub mod mipmap;
+pub mod mods;
+pub mod navmesh;
+pub mod net;
+pub mod occlusion;
+pub mod outline;
+pub mod particles;
+pub mod pathfinding;
+pub mod pbr;
+pub mod platform;
+pub mod postprocess;
+pub mod prefab;
+pub mod reflection;
+pub mod registry;
+pub mod render_graph;
 pub mod renderer;
+pub mod replay;
 pub mod resource;
+pub mod rollback;
+pub mod save;
+pub mod schedule;
+pub mod scene_file;
+pub mod scene_manager;
+#[cfg(feature = "wasm")]
+pub mod scripting;
+pub mod shader_reload;
+pub mod skinning;
+pub mod snapshot;
+pub mod sky;
+pub mod sparse;
+pub mod steering;
+pub mod terrain;
 pub mod time;
+pub mod trail;
+pub mod uniform_ring;
 pub mod utils;
+pub mod vfs;
 pub mod window;
 
 /// Commonly used types and traits
 pub mod prelude {
-    pub use crate::audio::{AudioManager, AudioSource};
+    pub use crate::audio::{AudioManager, AudioSource, BackgroundAudio, PlaybackParams, Rolloff, SoundHandle, SpatialSettings};
+    pub use crate::audio_effects::{Effect, EffectChain};
+    pub use crate::audio_emitter::{AudioEmitter, AudioListener};
+    pub use crate::audio_sync::MusicCue;
+    pub use crate::bundle::Bundle;
+    pub use crate::camera_controller::{FlyCameraController, FollowCamera, OrbitCamera};
+    pub use crate::commands::Commands;
     pub use crate::config::EngineConfig;
-    pub use crate::ecs::{Component, Entity, EntityId, Scene};
+    pub use crate::dynamic::{DynamicComponent, DynamicComponents, DynamicSchema, DynamicValue};
+    pub use crate::ecs::{
+        Added, CachedQuery, Changed, Component, EntityId, EntityMut, EntityRef, EntityRowMut, Scene, StorageType, With,
+        Without,
+    };
     pub use crate::engine::Engine;
-    pub use crate::input::{InputManager, Key, MouseButton};
+    pub use crate::grid::GridSettings;
+    pub use crate::hierarchy::{Children, GlobalTransform, Parent};
+    pub use crate::input::{Axis, AxisSettings, ImeComposition, InputManager, Key, MouseButton, ResponseCurve};
+    pub use crate::light::Light;
     pub use crate::math::*;
-    pub use crate::renderer::{Camera, Color, Renderer, Vertex};
-    pub use crate::resource::{ResourceManager, Texture, Mesh, MeshBuilder};
+    pub use crate::prefab::Prefab;
+    pub use crate::reflection::{PlanarReflection, ReflectionProbe};
+    pub use crate::registry::{ComponentRegistry, NamedComponent};
+    pub use crate::renderer::{
+        Camera, CameraClear, Color, MeshRenderer, PosColorVertex, PosUvVertex, RenderError, RenderLayers, Renderer,
+        Vertex, VertexLayout, Viewport,
+    };
+    pub use crate::resource::{AudioHandle, ResourceManager, Texture, Mesh, MeshBuilder};
+    pub use crate::scene_manager::{Persistent, SceneManager, SceneSwitcher};
+    pub use crate::schedule::{ParallelCtx, Stage, SystemConflict, SystemCtx, SystemDesc, SystemView, Systems};
+    pub use crate::snapshot::SceneSnapshot;
     pub use crate::time::TimeManager;
     pub use crate::utils::{Random, Timer};
-    pub use crate::window::Window;
+    pub use crate::window::{CursorGrab, Window};
     pub use glam::{Vec2, Vec3, Vec4, Mat4, Quat};
 }
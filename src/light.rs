@@ -0,0 +1,156 @@
+//! Point and spot lights
+//!
+//! Entities with a `Transform` and a [`Light`] are gathered every frame and uploaded
+//! to a storage buffer read by the default shader. Spot lights shine along the
+//! transform's forward axis. When there are more lights than the renderer's
+//! `max_lights`, the ones closest to the camera are kept.
+
+use crate::ecs::{Component, Scene};
+use crate::hierarchy::world_matrix;
+use crate::renderer::Color;
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+/// Shape of a light's emission
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// Emits in all directions
+    Point,
+    /// Emits in a cone; angles are half-angles in radians, fading from inner to outer
+    Spot { inner_angle: f32, outer_angle: f32 },
+}
+
+/// A point or spot light following its entity's transform
+#[derive(Debug, Clone, Copy)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Color,
+    pub intensity: f32,
+    /// Distance at which the light's contribution reaches zero
+    pub range: f32,
+    pub enabled: bool,
+}
+
+impl Component for Light {}
+
+impl Light {
+    /// Create a point light
+    pub fn point(color: Color, intensity: f32, range: f32) -> Self {
+        Self {
+            kind: LightKind::Point,
+            color,
+            intensity,
+            range,
+            enabled: true,
+        }
+    }
+
+    /// Create a spot light with inner and outer cone half-angles in radians
+    pub fn spot(color: Color, intensity: f32, range: f32, inner_angle: f32, outer_angle: f32) -> Self {
+        Self {
+            kind: LightKind::Spot {
+                inner_angle: inner_angle.min(outer_angle),
+                outer_angle,
+            },
+            ..Self::point(color, intensity, range)
+        }
+    }
+
+    /// Light intensity at a distance, using windowed inverse-square attenuation
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        if distance >= self.range {
+            return 0.0;
+        }
+        let window = (1.0 - (distance / self.range).powi(4)).clamp(0.0, 1.0);
+        window * window / (distance * distance + 1.0)
+    }
+
+    pub(crate) fn to_uniform(self, position: Vec3, direction: Vec3) -> PointLightUniform {
+        // Point lights use a cone wider than any direction so the spot factor is always 1
+        let (cos_inner, cos_outer) = match self.kind {
+            LightKind::Point => (-1.0, -2.0),
+            LightKind::Spot { inner_angle, outer_angle } => {
+                let cos_outer = outer_angle.cos();
+                // Keep the smoothstep edges apart for hard-edged cones
+                (inner_angle.cos().max(cos_outer + 1e-4), cos_outer)
+            }
+        };
+        let direction = direction.normalize_or_zero();
+        PointLightUniform {
+            position_range: [position.x, position.y, position.z, self.range],
+            color: [
+                self.color.r * self.intensity,
+                self.color.g * self.intensity,
+                self.color.b * self.intensity,
+                1.0,
+            ],
+            direction: [direction.x, direction.y, direction.z, 0.0],
+            cone: [cos_inner, cos_outer, 0.0, 0.0],
+        }
+    }
+}
+
+/// Point/spot light data as laid out in the light storage buffer
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(crate) struct PointLightUniform {
+    position_range: [f32; 4],
+    color: [f32; 4],
+    direction: [f32; 4],
+    cone: [f32; 4],
+}
+
+impl PointLightUniform {
+    fn position(&self) -> Vec3 {
+        Vec3::new(self.position_range[0], self.position_range[1], self.position_range[2])
+    }
+}
+
+/// Gather every enabled light in the scene
+pub(crate) fn scene_lights(scene: &Scene) -> Vec<PointLightUniform> {
+    scene
+        .active_entities()
+        .filter_map(|entity| {
+            let light = entity.get_component::<Light>().filter(|l| l.enabled)?;
+            let world = world_matrix(&entity)?;
+            let forward = world.transform_vector3(Vec3::NEG_Z).normalize_or_zero();
+            Some(light.to_uniform(world.w_axis.truncate(), forward))
+        })
+        .collect()
+}
+
+/// Keep the `max` lights closest to the viewer
+pub(crate) fn closest_lights(lights: &mut Vec<PointLightUniform>, viewer: Vec3, max: usize) {
+    if lights.len() > max {
+        lights.sort_by(|a, b| {
+            a.position()
+                .distance_squared(viewer)
+                .total_cmp(&b.position().distance_squared(viewer))
+        });
+        lights.truncate(max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_attenuation_and_culling() {
+        let light = Light::point(Color::WHITE, 1.0, 10.0);
+        assert_eq!(light.attenuation(0.0), 1.0);
+        assert!(light.attenuation(2.0) > light.attenuation(5.0));
+        assert_eq!(light.attenuation(10.0), 0.0);
+
+        let spot = Light::spot(Color::WHITE, 2.0, 5.0, 0.5, 0.3);
+        assert_eq!(spot.kind, LightKind::Spot { inner_angle: 0.3, outer_angle: 0.3 });
+
+        let mut lights: Vec<PointLightUniform> = [8.0, 1.0, 4.0]
+            .iter()
+            .map(|&x| light.to_uniform(Vec3::new(x, 0.0, 0.0), Vec3::NEG_Z))
+            .collect();
+        closest_lights(&mut lights, Vec3::ZERO, 2);
+        let xs: Vec<f32> = lights.iter().map(|l| l.position().x).collect();
+        assert_eq!(xs, [1.0, 4.0]);
+    }
+}
@@ -0,0 +1,768 @@
+//! Localization and string tables
+//!
+//! Loads per-language string tables from JSON or (a subset of) Fluent FTL files,
+//! resolves messages with argument interpolation and plural rules, and supports
+//! switching the active language at runtime.
+//!
+//! ```no_run
+//! use rgame::localization::Localization;
+//! use rgame::tr;
+//!
+//! let mut loc = Localization::new("en");
+//! loc.load_file("en", "assets/lang/en.ftl").unwrap();
+//! Localization::install_global(loc);
+//!
+//! let title = tr!("menu.start");
+//! let items = tr!("inventory.items", count = 3);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+/// Argument value passed to a message
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocArg {
+    Number(f64),
+    Text(String),
+}
+
+impl fmt::Display for LocArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocArg::Number(n) if n.fract() == 0.0 => write!(f, "{}", *n as i64),
+            LocArg::Number(n) => write!(f, "{}", n),
+            LocArg::Text(s) => f.write_str(s),
+        }
+    }
+}
+
+macro_rules! impl_loc_arg_number {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for LocArg {
+                fn from(value: $t) -> Self {
+                    LocArg::Number(value as f64)
+                }
+            }
+        )*
+    };
+}
+
+impl_loc_arg_number!(i8, i16, i32, i64, u8, u16, u32, u64, usize, isize, f32, f64);
+
+impl From<&str> for LocArg {
+    fn from(value: &str) -> Self {
+        LocArg::Text(value.to_string())
+    }
+}
+
+impl From<String> for LocArg {
+    fn from(value: String) -> Self {
+        LocArg::Text(value)
+    }
+}
+
+impl From<&String> for LocArg {
+    fn from(value: &String) -> Self {
+        LocArg::Text(value.clone())
+    }
+}
+
+/// CLDR plural category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    /// Name used as variant key in string tables
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluralCategory::Zero => "zero",
+            PluralCategory::One => "one",
+            PluralCategory::Two => "two",
+            PluralCategory::Few => "few",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other",
+        }
+    }
+
+    /// Select the plural category of `n` for a language code (e.g. "en", "pt-BR")
+    pub fn for_number(language: &str, n: f64) -> Self {
+        let lang = language
+            .split(['-', '_'])
+            .next()
+            .unwrap_or(language)
+            .to_lowercase();
+        let integer = n.fract() == 0.0;
+        let i = n.abs() as u64;
+
+        match lang.as_str() {
+            // No plural forms
+            "ja" | "zh" | "ko" | "th" | "vi" | "id" | "ms" | "tr" => PluralCategory::Other,
+            // 0 and 1 are singular
+            "fr" | "pt" => {
+                if integer && i <= 1 {
+                    PluralCategory::One
+                } else {
+                    PluralCategory::Other
+                }
+            }
+            // East Slavic
+            "ru" | "uk" | "be" => {
+                if !integer {
+                    PluralCategory::Other
+                } else if i % 10 == 1 && i % 100 != 11 {
+                    PluralCategory::One
+                } else if (2..=4).contains(&(i % 10)) && !(12..=14).contains(&(i % 100)) {
+                    PluralCategory::Few
+                } else {
+                    PluralCategory::Many
+                }
+            }
+            "pl" => {
+                if !integer {
+                    PluralCategory::Other
+                } else if i == 1 {
+                    PluralCategory::One
+                } else if (2..=4).contains(&(i % 10)) && !(12..=14).contains(&(i % 100)) {
+                    PluralCategory::Few
+                } else {
+                    PluralCategory::Many
+                }
+            }
+            "cs" | "sk" => {
+                if !integer {
+                    PluralCategory::Many
+                } else if i == 1 {
+                    PluralCategory::One
+                } else if (2..=4).contains(&i) {
+                    PluralCategory::Few
+                } else {
+                    PluralCategory::Other
+                }
+            }
+            "ar" => {
+                if !integer {
+                    PluralCategory::Other
+                } else if i == 0 {
+                    PluralCategory::Zero
+                } else if i == 1 {
+                    PluralCategory::One
+                } else if i == 2 {
+                    PluralCategory::Two
+                } else if (3..=10).contains(&(i % 100)) {
+                    PluralCategory::Few
+                } else if (11..=99).contains(&(i % 100)) {
+                    PluralCategory::Many
+                } else {
+                    PluralCategory::Other
+                }
+            }
+            // English, German, Spanish, Italian, Dutch, Scandinavian, ...
+            _ => {
+                if integer && i == 1 {
+                    PluralCategory::One
+                } else {
+                    PluralCategory::Other
+                }
+            }
+        }
+    }
+}
+
+/// A single translatable message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// Plain text with optional `{name}` placeholders
+    Text(String),
+    /// Variant selected by the value of an argument (plural category or exact value)
+    Select {
+        variable: String,
+        variants: Vec<(String, String)>,
+        default: usize,
+    },
+}
+
+impl Message {
+    /// Pick the pattern to use for the given arguments
+    fn pattern<'a>(&'a self, language: &str, args: &[(&str, LocArg)]) -> &'a str {
+        match self {
+            Message::Text(text) => text,
+            Message::Select {
+                variable,
+                variants,
+                default,
+            } => {
+                let value = args.iter().find(|(name, _)| name == variable).map(|(_, v)| v);
+                let chosen = value.and_then(|value| {
+                    let exact = value.to_string();
+                    variants
+                        .iter()
+                        .find(|(key, _)| key.trim_start_matches('=') == exact)
+                        .or_else(|| match value {
+                            LocArg::Number(n) => {
+                                let category = PluralCategory::for_number(language, *n);
+                                variants.iter().find(|(key, _)| key == category.as_str())
+                            }
+                            LocArg::Text(_) => None,
+                        })
+                });
+                chosen
+                    .or_else(|| variants.get(*default))
+                    .map(|(_, pattern)| pattern.as_str())
+                    .unwrap_or("")
+            }
+        }
+    }
+}
+
+/// Replace `{name}` placeholders in a pattern with argument values
+fn interpolate(pattern: &str, args: &[(&str, LocArg)]) -> String {
+    let mut result = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) => {
+                let name = after[..end].trim().trim_start_matches('$');
+                match args.iter().find(|(arg, _)| *arg == name) {
+                    Some((_, value)) => result.push_str(&value.to_string()),
+                    None => {
+                        result.push('{');
+                        result.push_str(&after[..end]);
+                        result.push('}');
+                    }
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Messages for a single language
+#[derive(Debug, Clone, Default)]
+pub struct StringTable {
+    messages: HashMap<String, Message>,
+}
+
+impl StringTable {
+    /// Create an empty string table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a plain text message
+    pub fn insert(&mut self, key: impl Into<String>, text: impl Into<String>) {
+        self.messages.insert(key.into(), Message::Text(text.into()));
+    }
+
+    /// Insert a message of any kind
+    pub fn insert_message(&mut self, key: impl Into<String>, message: Message) {
+        self.messages.insert(key.into(), message);
+    }
+
+    /// Get a message by key
+    pub fn get(&self, key: &str) -> Option<&Message> {
+        self.messages.get(key)
+    }
+
+    /// Number of messages
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Check if the table is empty
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Merge another table into this one (entries in `other` win)
+    pub fn extend(&mut self, other: StringTable) {
+        self.messages.extend(other.messages);
+    }
+
+    /// Parse a JSON string table
+    ///
+    /// Nested objects are flattened into dotted keys. An object whose keys are
+    /// all plural categories (`zero`, `one`, `two`, `few`, `many`, `other`) or
+    /// exact values (`=0`) becomes a plural message selected by `count`.
+    pub fn from_json(content: &str) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(content)
+            .map_err(|e| format!("Failed to parse string table JSON: {}", e))?;
+        let mut table = StringTable::new();
+        flatten_json(&mut table, String::new(), &value)?;
+        Ok(table)
+    }
+
+    /// Parse a Fluent (FTL) string table
+    ///
+    /// Supports the commonly used subset: `key = value` messages, indented
+    /// continuation lines, `{ $var }` placeables, comments, and select
+    /// expressions (`{ $count -> [one] ... *[other] ... }`).
+    pub fn from_ftl(content: &str) -> Result<Self, String> {
+        let mut table = StringTable::new();
+        let mut current: Option<(String, String)> = None;
+
+        for (line_number, line) in content.lines().enumerate() {
+            let is_continuation = line.starts_with(' ') || line.starts_with('\t');
+
+            if is_continuation && current.is_some() {
+                if let Some((_, value)) = current.as_mut() {
+                    if !value.is_empty() {
+                        value.push('\n');
+                    }
+                    value.push_str(line.trim());
+                }
+                continue;
+            }
+
+            if let Some((key, value)) = current.take() {
+                table.insert_message(key, parse_ftl_value(&value)?);
+            }
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = trimmed.split_once('=').ok_or_else(|| {
+                format!("Invalid FTL entry on line {}: {}", line_number + 1, trimmed)
+            })?;
+            current = Some((key.trim().to_string(), value.trim().to_string()));
+        }
+
+        if let Some((key, value)) = current.take() {
+            table.insert_message(key, parse_ftl_value(&value)?);
+        }
+
+        Ok(table)
+    }
+}
+
+fn is_variant_key(key: &str) -> bool {
+    matches!(key, "zero" | "one" | "two" | "few" | "many" | "other") || key.starts_with('=')
+}
+
+fn flatten_json(table: &mut StringTable, prefix: String, value: &serde_json::Value) -> Result<(), String> {
+    let join = |key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        }
+    };
+
+    match value {
+        serde_json::Value::Object(map) => {
+            let is_plural = !map.is_empty()
+                && map.iter().all(|(k, v)| is_variant_key(k) && v.is_string());
+            if is_plural && !prefix.is_empty() {
+                let variants: Vec<(String, String)> = map
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.as_str().unwrap_or_default().to_string()))
+                    .collect();
+                let default = variants
+                    .iter()
+                    .position(|(k, _)| k == "other")
+                    .unwrap_or(variants.len() - 1);
+                table.insert_message(
+                    prefix,
+                    Message::Select {
+                        variable: "count".to_string(),
+                        variants,
+                        default,
+                    },
+                );
+            } else {
+                for (key, child) in map {
+                    flatten_json(table, join(key), child)?;
+                }
+            }
+            Ok(())
+        }
+        serde_json::Value::String(text) => {
+            table.insert(prefix, text.clone());
+            Ok(())
+        }
+        other => Err(format!("Unsupported string table value for '{}': {}", prefix, other)),
+    }
+}
+
+/// Convert `{ $var }` placeables into the `{var}` form used by interpolation
+fn normalize_placeables(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) => {
+                let inner = after[..end].trim();
+                if let Some(name) = inner.strip_prefix('$') {
+                    result.push('{');
+                    result.push_str(name.trim());
+                    result.push('}');
+                } else if let Some(literal) = inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                    result.push_str(literal);
+                } else {
+                    result.push('{');
+                    result.push_str(inner);
+                    result.push('}');
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn parse_ftl_value(value: &str) -> Result<Message, String> {
+    let trimmed = value.trim();
+
+    // Select expression: { $var -> [key] text *[other] text }
+    if let Some(inner) = trimmed.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+        if let Some((selector, body)) = inner.split_once("->") {
+            let variable = selector.trim().trim_start_matches('$').to_string();
+            let mut variants = Vec::new();
+            let mut default = None;
+
+            let mut rest = body.trim();
+            while !rest.is_empty() {
+                let is_default = rest.starts_with('*');
+                let open = rest
+                    .find('[')
+                    .ok_or_else(|| format!("Invalid select variant: {}", rest))?;
+                let close = rest[open..]
+                    .find(']')
+                    .map(|i| i + open)
+                    .ok_or_else(|| format!("Unterminated select variant key: {}", rest))?;
+                let key = rest[open + 1..close].trim().to_string();
+                let after = &rest[close + 1..];
+                let next = find_next_variant(after);
+                let text = after[..next].trim();
+
+                if is_default {
+                    default = Some(variants.len());
+                }
+                variants.push((key, normalize_placeables(text)));
+                rest = after[next..].trim();
+            }
+
+            if variants.is_empty() {
+                return Err(format!("Select expression has no variants: {}", value));
+            }
+            let default = default.unwrap_or(variants.len() - 1);
+            return Ok(Message::Select {
+                variable,
+                variants,
+                default,
+            });
+        }
+    }
+
+    Ok(Message::Text(normalize_placeables(trimmed)))
+}
+
+/// Find where the next `[key]` or `*[key]` variant starts (outside placeables)
+fn find_next_variant(text: &str) -> usize {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '[' | '*' if depth == 0 => {
+                let at_line_start = text[..i].ends_with('\n') || text[..i].trim().is_empty();
+                let starts_variant = c == '[' || text[i + 1..].starts_with('[');
+                if starts_variant && (at_line_start || text[..i].ends_with(char::is_whitespace)) {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Callback invoked when the active language changes
+pub type LanguageChangedHook = Box<dyn FnMut(&str) + Send + Sync>;
+
+/// Manages string tables and the active language
+pub struct Localization {
+    tables: HashMap<String, StringTable>,
+    language: String,
+    fallback_language: String,
+    font_fallbacks: HashMap<String, Vec<String>>,
+    language_changed_hooks: Vec<LanguageChangedHook>,
+}
+
+impl Localization {
+    /// Create a localization manager with a default (fallback) language
+    pub fn new(default_language: impl Into<String>) -> Self {
+        let language = default_language.into();
+        Self {
+            tables: HashMap::new(),
+            fallback_language: language.clone(),
+            language,
+            font_fallbacks: HashMap::new(),
+            language_changed_hooks: Vec::new(),
+        }
+    }
+
+    /// Get the active language
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Get the fallback language used for missing keys
+    pub fn fallback_language(&self) -> &str {
+        &self.fallback_language
+    }
+
+    /// Set the fallback language used for missing keys
+    pub fn set_fallback_language(&mut self, language: impl Into<String>) {
+        self.fallback_language = language.into();
+    }
+
+    /// List languages with loaded string tables
+    pub fn languages(&self) -> Vec<&str> {
+        let mut languages: Vec<&str> = self.tables.keys().map(|s| s.as_str()).collect();
+        languages.sort_unstable();
+        languages
+    }
+
+    /// Add (or merge) a string table for a language
+    pub fn add_table(&mut self, language: impl Into<String>, table: StringTable) {
+        self.tables.entry(language.into()).or_default().extend(table);
+    }
+
+    /// Load a string table from a `.json` or `.ftl` file
+    pub fn load_file<P: AsRef<Path>>(&mut self, language: &str, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read string table: {}", e))?;
+
+        let table = match crate::utils::path_utils::get_extension(path).as_deref() {
+            Some("json") => StringTable::from_json(&content)?,
+            Some("ftl") => StringTable::from_ftl(&content)?,
+            other => return Err(format!("Unsupported string table format: {:?}", other)),
+        };
+
+        log::info!("Loaded {} strings for '{}' from {:?}", table.len(), language, path);
+        self.add_table(language, table);
+        Ok(())
+    }
+
+    /// Load every `<language>.json` / `<language>.ftl` file in a directory
+    pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<usize, String> {
+        let entries = std::fs::read_dir(dir.as_ref())
+            .map_err(|e| format!("Failed to read localization directory: {}", e))?;
+
+        let mut count = 0;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let supported = matches!(
+                crate::utils::path_utils::get_extension(&path).as_deref(),
+                Some("json") | Some("ftl")
+            );
+            if let (true, Some(language)) = (supported, path.file_stem().and_then(|s| s.to_str())) {
+                self.load_file(language, &path)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Switch the active language at runtime
+    pub fn set_language(&mut self, language: impl Into<String>) {
+        let language = language.into();
+        if !self.tables.contains_key(&language) {
+            log::warn!("No string table loaded for language '{}'", language);
+        }
+        if language == self.language {
+            return;
+        }
+
+        log::info!("Switching language to '{}'", language);
+        self.language = language;
+        for hook in &mut self.language_changed_hooks {
+            hook(&self.language);
+        }
+    }
+
+    /// Register a callback that runs whenever the active language changes
+    pub fn on_language_changed<F>(&mut self, hook: F)
+    where
+        F: FnMut(&str) + Send + Sync + 'static,
+    {
+        self.language_changed_hooks.push(Box::new(hook));
+    }
+
+    /// Set the ordered list of font names a text renderer should try for a language
+    pub fn set_font_fallbacks(&mut self, language: impl Into<String>, fonts: Vec<String>) {
+        self.font_fallbacks.insert(language.into(), fonts);
+    }
+
+    /// Get the font fallback chain for the active language
+    ///
+    /// Falls back to the chain of the fallback language, then an empty list.
+    pub fn font_fallbacks(&self) -> &[String] {
+        self.font_fallbacks
+            .get(&self.language)
+            .or_else(|| self.font_fallbacks.get(&self.fallback_language))
+            .map(|fonts| fonts.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Check if a key exists in the active or fallback language
+    pub fn has(&self, key: &str) -> bool {
+        self.lookup(key).is_some()
+    }
+
+    fn lookup(&self, key: &str) -> Option<(&str, &Message)> {
+        self.tables
+            .get(&self.language)
+            .and_then(|t| t.get(key))
+            .map(|m| (self.language.as_str(), m))
+            .or_else(|| {
+                self.tables
+                    .get(&self.fallback_language)
+                    .and_then(|t| t.get(key))
+                    .map(|m| (self.fallback_language.as_str(), m))
+            })
+    }
+
+    /// Translate a key without arguments
+    pub fn tr(&self, key: &str) -> String {
+        self.tr_args(key, &[])
+    }
+
+    /// Translate a key, interpolating arguments and applying plural rules
+    ///
+    /// Missing keys return the key itself so untranslated strings stay visible.
+    pub fn tr_args(&self, key: &str, args: &[(&str, LocArg)]) -> String {
+        match self.lookup(key) {
+            Some((language, message)) => interpolate(message.pattern(language, args), args),
+            None => {
+                log::debug!("Missing translation for '{}' in '{}'", key, self.language);
+                key.to_string()
+            }
+        }
+    }
+
+    /// Install a localization manager as the global instance used by [`tr!`](crate::tr)
+    pub fn install_global(localization: Localization) {
+        let lock = global_lock();
+        match lock.write() {
+            Ok(mut global) => *global = Some(localization),
+            Err(poisoned) => *poisoned.into_inner() = Some(localization),
+        }
+    }
+
+    /// Run a closure with mutable access to the global instance (e.g. to switch language)
+    pub fn with_global<R>(f: impl FnOnce(&mut Localization) -> R) -> Option<R> {
+        let mut global = global_lock().write().ok()?;
+        global.as_mut().map(f)
+    }
+}
+
+fn global_lock() -> &'static RwLock<Option<Localization>> {
+    static GLOBAL: OnceLock<RwLock<Option<Localization>>> = OnceLock::new();
+    GLOBAL.get_or_init(|| RwLock::new(None))
+}
+
+/// Translate using the global instance (used by [`tr!`](crate::tr))
+pub fn translate(key: &str, args: &[(&str, LocArg)]) -> String {
+    match global_lock().read() {
+        Ok(global) => match global.as_ref() {
+            Some(localization) => localization.tr_args(key, args),
+            None => key.to_string(),
+        },
+        Err(_) => key.to_string(),
+    }
+}
+
+/// Look up a translated string in the global [`Localization`]
+///
+/// ```no_run
+/// # use rgame::tr;
+/// let start = tr!("menu.start");
+/// let greeting = tr!("menu.greeting", name = "Ada");
+/// let items = tr!("inventory.items", count = 5);
+/// ```
+#[macro_export]
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::localization::translate($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::localization::translate(
+            $key,
+            &[$((stringify!($name), $crate::localization::LocArg::from($value))),+],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_plurals_and_interpolation() {
+        let table = StringTable::from_json(
+            r#"{
+                "menu": { "start": "Start", "greeting": "Hello, {name}!" },
+                "items": { "=0": "No items", "one": "{count} item", "other": "{count} items" }
+            }"#,
+        )
+        .unwrap();
+
+        let mut loc = Localization::new("en");
+        loc.add_table("en", table);
+
+        assert_eq!(loc.tr("menu.start"), "Start");
+        assert_eq!(loc.tr_args("menu.greeting", &[("name", "Ada".into())]), "Hello, Ada!");
+        assert_eq!(loc.tr_args("items", &[("count", 0.into())]), "No items");
+        assert_eq!(loc.tr_args("items", &[("count", 1.into())]), "1 item");
+        assert_eq!(loc.tr_args("items", &[("count", 7.into())]), "7 items");
+        assert_eq!(loc.tr("missing.key"), "missing.key");
+    }
+
+    #[test]
+    fn test_ftl_select_and_language_switch() {
+        let ftl = "# Russian\n\
+                   menu-start = Начать\n\
+                   apples = { $count ->\n    [one] {$count} яблоко\n    [few] {$count} яблока\n   *[many] {$count} яблок\n  }\n";
+        let mut loc = Localization::new("en");
+        loc.add_table("ru", StringTable::from_ftl(ftl).unwrap());
+        loc.add_table("en", StringTable::from_ftl("menu-start = Start").unwrap());
+
+        assert_eq!(loc.tr("menu-start"), "Start");
+        loc.set_language("ru");
+        assert_eq!(loc.tr("menu-start"), "Начать");
+        assert_eq!(loc.tr_args("apples", &[("count", 21.into())]), "21 яблоко");
+        assert_eq!(loc.tr_args("apples", &[("count", 3.into())]), "3 яблока");
+        assert_eq!(loc.tr_args("apples", &[("count", 11.into())]), "11 яблок");
+    }
+}
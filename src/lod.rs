@@ -0,0 +1,108 @@
+//! Level-of-detail mesh selection
+//!
+//! An [`LodGroup`] next to a [`MeshRenderer`](crate::renderer::MeshRenderer) swaps the
+//! drawn mesh by distance to the main camera. The renderer's texture and material are
+//! kept. Past the last level the entity isn't drawn. With a fade distance, the two
+//! levels around a threshold are dithered into each other instead of popping.
+
+use crate::ecs::Component;
+use crate::resource::MeshHandle;
+
+/// A mesh used up to a distance from the camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodLevel {
+    pub mesh: MeshHandle,
+    pub max_distance: f32,
+}
+
+/// Meshes of decreasing detail with distance thresholds
+#[derive(Debug, Clone, Default)]
+pub struct LodGroup {
+    /// Levels sorted by `max_distance`, most detailed first
+    pub levels: Vec<LodLevel>,
+    /// Width of the band before each threshold where levels cross-fade (0 = pop)
+    pub fade_distance: f32,
+}
+
+impl Component for LodGroup {}
+
+/// The level(s) to draw at a distance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodSelection {
+    pub mesh: MeshHandle,
+    /// Coarser level faded in while crossing a threshold (`None` past the last level)
+    pub next: Option<MeshHandle>,
+    /// Share of the pixels given to `next` (0 when not fading)
+    pub blend: f32,
+}
+
+impl LodGroup {
+    /// Create an empty group
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a level used up to `max_distance`
+    pub fn with_level(mut self, mesh: MeshHandle, max_distance: f32) -> Self {
+        self.levels.push(LodLevel { mesh, max_distance });
+        self.levels.sort_by(|a, b| a.max_distance.total_cmp(&b.max_distance));
+        self
+    }
+
+    /// Cross-fade over `distance` before each threshold
+    pub fn with_fade(mut self, distance: f32) -> Self {
+        self.fade_distance = distance.max(0.0);
+        self
+    }
+
+    /// Pick the level(s) for a camera distance, or `None` past the last level
+    pub fn select(&self, distance: f32) -> Option<LodSelection> {
+        let index = self.levels.iter().position(|level| distance < level.max_distance)?;
+        let level = self.levels[index];
+        let fade_start = level.max_distance - self.fade_distance;
+        let blend = if self.fade_distance > 0.0 && distance > fade_start {
+            ((distance - fade_start) / self.fade_distance).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        Some(LodSelection {
+            mesh: level.mesh,
+            next: self.levels.get(index + 1).map(|next| next.mesh).filter(|_| blend > 0.0),
+            blend,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lod_selection() {
+        let group = LodGroup::new().with_level(2, 50.0).with_level(0, 10.0).with_level(1, 25.0);
+        assert_eq!(group.select(5.0).map(|s| s.mesh), Some(0));
+        assert_eq!(group.select(10.0).map(|s| s.mesh), Some(1));
+        assert_eq!(group.select(49.0).map(|s| s.mesh), Some(2));
+        assert_eq!(group.select(50.0), None);
+        assert_eq!(group.select(12.0).map(|s| s.blend), Some(0.0));
+
+        // Crossing into the next level, and fading out past the last one
+        let faded = group.clone().with_fade(4.0);
+        assert_eq!(
+            faded.select(23.0),
+            Some(LodSelection {
+                mesh: 1,
+                next: Some(2),
+                blend: 0.5
+            })
+        );
+        assert_eq!(
+            faded.select(49.0),
+            Some(LodSelection {
+                mesh: 2,
+                next: None,
+                blend: 0.75
+            })
+        );
+    }
+}
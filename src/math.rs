@@ -3,6 +3,7 @@
 //! Re-exports glam types and provides additional helper functions.
 
 pub use glam::*;
+use serde::{Deserialize, Serialize};
 
 /// Common math constants and helper functions
 pub mod helpers {
@@ -42,7 +43,7 @@ pub mod helpers {
 }
 
 /// Transform component for 3D objects
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Transform {
     pub position: Vec3,
     pub rotation: Quat,
@@ -121,7 +122,7 @@ impl Default for Transform {
 }
 
 /// 2D Transform for 2D games
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Transform2D {
     pub position: Vec2,
     pub rotation: f32,
@@ -163,7 +164,7 @@ impl Default for Transform2D {
 }
 
 /// Rectangle for 2D collision and rendering
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Rect {
     pub x: f32,
     pub y: f32,
@@ -199,6 +200,93 @@ impl Rect {
     }
 }
 
+/// Axis-aligned bounding box
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Create a bounding box from its corners
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Get the smallest box containing every point (a zero box at the origin if empty)
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        let mut points = points.into_iter();
+        let Some(first) = points.next() else {
+            return Self::default();
+        };
+        points.fold(Self::new(first, first), |aabb, p| Self::new(aabb.min.min(p), aabb.max.max(p)))
+    }
+
+    /// Get center point
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Get half the size along each axis
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// Get the box containing this one after a transform
+    pub fn transformed(&self, matrix: Mat4) -> Self {
+        let center = matrix.transform_point3(self.center());
+        let half = self.half_extents();
+        // Each world axis extent is the sum of the absolute rotated local extents
+        let abs = Mat3::from_cols(
+            matrix.x_axis.truncate().abs(),
+            matrix.y_axis.truncate().abs(),
+            matrix.z_axis.truncate().abs(),
+        );
+        let extent = abs * half;
+        Self::new(center - extent, center + extent)
+    }
+}
+
+/// The six planes bounding a camera's view, for culling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// Plane normals (xyz) point inwards, with the plane offset in w
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extract the planes of a view-projection matrix (depth range 0 to 1)
+    pub fn from_view_proj(view_proj: Mat4) -> Self {
+        let [r0, r1, r2, r3] = [view_proj.row(0), view_proj.row(1), view_proj.row(2), view_proj.row(3)];
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(|plane| {
+            let length = plane.truncate().length();
+            if length > 0.0 {
+                plane / length
+            } else {
+                plane
+            }
+        });
+        Self { planes }
+    }
+
+    /// Check if a bounding box is at least partly inside
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let center = aabb.center();
+        let half = aabb.half_extents();
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            normal.dot(center) + plane.w + normal.abs().dot(half) >= 0.0
+        })
+    }
+
+    /// Check if a sphere is at least partly inside
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -210,6 +298,30 @@ mod tests {
         assert_eq!(transform.position, Vec3::new(1.0, 2.0, 3.0));
     }
 
+    #[test]
+    fn test_frustum_culls_aabb() {
+        let view = Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        let proj = Mat4::perspective_rh(90f32.to_radians(), 1.0, 0.1, 100.0);
+        let frustum = Frustum::from_view_proj(proj * view);
+
+        let unit = Aabb::from_points([Vec3::splat(-0.5), Vec3::splat(0.5)]);
+        let ahead = unit.transformed(Mat4::from_translation(Vec3::new(0.0, 0.0, -10.0)));
+        let behind = unit.transformed(Mat4::from_translation(Vec3::new(0.0, 0.0, 10.0)));
+        let beyond_far = unit.transformed(Mat4::from_translation(Vec3::new(0.0, 0.0, -200.0)));
+        // Just past the right edge of the 90 degree view, but overlapping it
+        let straddling = unit.transformed(Mat4::from_translation(Vec3::new(10.4, 0.0, -10.0)));
+        assert!(frustum.intersects_aabb(&ahead));
+        assert!(!frustum.intersects_aabb(&behind));
+        assert!(!frustum.intersects_aabb(&beyond_far));
+        assert!(frustum.intersects_aabb(&straddling));
+        assert!(frustum.intersects_sphere(Vec3::new(0.0, 0.0, 0.5), 1.0));
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, 5.0), 1.0));
+
+        // A rotated box grows to contain its corners
+        let rotated = unit.transformed(Mat4::from_rotation_y(45f32.to_radians()));
+        assert!((rotated.max.x - 0.5 * 2f32.sqrt()).abs() < 1e-5);
+    }
+
     #[test]
     fn test_rect_intersection() {
         let rect1 = Rect::new(0.0, 0.0, 10.0, 10.0);
@@ -0,0 +1,373 @@
+//! Mod discovery and loading
+//!
+//! A mod is a folder containing a `mod.json` manifest, an optional `assets`
+//! directory mounted into the [`Vfs`], and optional WebAssembly scripts. Mods
+//! declare the capabilities they need; the game decides which ones it grants.
+//!
+//! ```json
+//! {
+//!     "id": "better_grass",
+//!     "name": "Better Grass",
+//!     "version": "1.0.0",
+//!     "priority": 10,
+//!     "dependencies": ["core_textures"],
+//!     "scripts": ["scripts/wind.wasm"],
+//!     "capabilities": ["assets", "scripts", "modify_entities"]
+//! }
+//! ```
+
+use crate::vfs::{normalize_path, Vfs};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// File name of a mod's manifest
+pub const MANIFEST_FILE: &str = "mod.json";
+
+/// Directory inside a mod that is mounted into the VFS
+pub const ASSETS_DIR: &str = "assets";
+
+/// Something a mod is allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModCapability {
+    /// Mount assets, overriding lower priority files
+    Assets,
+    /// Run WebAssembly scripts
+    Scripts,
+    /// Scripts may spawn and despawn entities
+    SpawnEntities,
+    /// Scripts may change entity positions and components
+    ModifyEntities,
+    /// Scripts may play sounds
+    Audio,
+}
+
+/// Contents of a mod's `mod.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModManifest {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    /// Higher priority mods load later and override lower ones
+    #[serde(default)]
+    pub priority: i32,
+    /// Ids of mods that must be loaded first
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// Script paths relative to the mod folder
+    #[serde(default)]
+    pub scripts: Vec<String>,
+    #[serde(default)]
+    pub capabilities: Vec<ModCapability>,
+}
+
+impl ModManifest {
+    /// Load a manifest from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("Failed to read mod manifest: {}", e))?;
+        let manifest: Self = serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse mod manifest: {}", e))?;
+        if manifest.id.is_empty() || normalize_path(&manifest.id).as_deref() != Some(manifest.id.as_str()) {
+            return Err(format!("Invalid mod id: '{}'", manifest.id));
+        }
+        Ok(manifest)
+    }
+}
+
+/// A discovered mod
+#[derive(Debug, Clone)]
+pub struct ModInfo {
+    pub manifest: ModManifest,
+    /// Mod folder on disk
+    pub root: PathBuf,
+    pub enabled: bool,
+    pub loaded: bool,
+}
+
+impl ModInfo {
+    /// Check if the mod both requested and was granted a capability
+    pub fn has_capability(&self, capability: ModCapability, allowed: &HashSet<ModCapability>) -> bool {
+        self.manifest.capabilities.contains(&capability) && allowed.contains(&capability)
+    }
+}
+
+/// Discovers, orders, and loads mods
+pub struct ModManager {
+    mods: Vec<ModInfo>,
+    allowed: HashSet<ModCapability>,
+    #[cfg(feature = "wasm")]
+    scripts: Vec<(String, crate::scripting::ScriptId)>,
+}
+
+impl ModManager {
+    /// Create a new mod manager that grants asset overrides only
+    pub fn new() -> Self {
+        Self {
+            mods: Vec::new(),
+            allowed: [ModCapability::Assets].into_iter().collect(),
+            #[cfg(feature = "wasm")]
+            scripts: Vec::new(),
+        }
+    }
+
+    /// Set which capabilities mods may be granted
+    pub fn set_allowed_capabilities(&mut self, capabilities: &[ModCapability]) {
+        self.allowed = capabilities.iter().copied().collect();
+    }
+
+    /// Get the capabilities mods may be granted
+    pub fn allowed_capabilities(&self) -> &HashSet<ModCapability> {
+        &self.allowed
+    }
+
+    /// Scan a directory for mod folders, returning how many new mods were found
+    ///
+    /// Folders without a valid manifest are skipped with a warning.
+    pub fn discover<P: AsRef<Path>>(&mut self, dir: P) -> Result<usize, String> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read mods directory {:?}: {}", dir, e))?;
+
+        let mut found = 0;
+        for entry in entries.flatten() {
+            let root = entry.path();
+            let manifest_path = root.join(MANIFEST_FILE);
+            if !manifest_path.is_file() {
+                continue;
+            }
+            let manifest = match ModManifest::load(&manifest_path) {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    log::warn!("Skipping mod {:?}: {}", root, e);
+                    continue;
+                }
+            };
+            if self.get(&manifest.id).is_some() {
+                log::warn!("Skipping mod {:?}: duplicate id '{}'", root, manifest.id);
+                continue;
+            }
+            log::info!("Discovered mod '{}' ({})", manifest.id, manifest.version);
+            self.mods.push(ModInfo {
+                manifest,
+                root,
+                enabled: true,
+                loaded: false,
+            });
+            found += 1;
+        }
+        Ok(found)
+    }
+
+    /// Get a mod by id
+    pub fn get(&self, id: &str) -> Option<&ModInfo> {
+        self.mods.iter().find(|m| m.manifest.id == id)
+    }
+
+    /// Get all discovered mods
+    pub fn mods(&self) -> &[ModInfo] {
+        &self.mods
+    }
+
+    /// Enable or disable a mod before loading
+    pub fn set_enabled(&mut self, id: &str, enabled: bool) -> bool {
+        match self.mods.iter_mut().find(|m| m.manifest.id == id) {
+            Some(info) => {
+                info.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the ids of enabled mods in load order (priority, then id)
+    ///
+    /// Mods whose dependencies are missing or disabled are left out.
+    pub fn load_order(&self) -> Vec<String> {
+        let mut enabled: Vec<&ModInfo> = self.mods.iter().filter(|m| m.enabled).collect();
+        enabled.sort_by(|a, b| {
+            a.manifest
+                .priority
+                .cmp(&b.manifest.priority)
+                .then_with(|| a.manifest.id.cmp(&b.manifest.id))
+        });
+
+        // Drop mods with unmet dependencies until the set is stable
+        loop {
+            let ids: HashSet<&str> = enabled.iter().map(|m| m.manifest.id.as_str()).collect();
+            let before = enabled.len();
+            enabled.retain(|m| {
+                let missing: Vec<&String> = m
+                    .manifest
+                    .dependencies
+                    .iter()
+                    .filter(|d| !ids.contains(d.as_str()))
+                    .collect();
+                if !missing.is_empty() {
+                    log::warn!("Mod '{}' is missing dependencies: {:?}", m.manifest.id, missing);
+                }
+                missing.is_empty()
+            });
+            if enabled.len() == before {
+                break;
+            }
+        }
+
+        // Dependencies load before their dependents regardless of priority
+        let mut order: Vec<String> = Vec::new();
+        while order.len() < enabled.len() {
+            let next = enabled.iter().find(|m| {
+                !order.contains(&m.manifest.id)
+                    && m.manifest.dependencies.iter().all(|d| order.contains(d))
+            });
+            match next {
+                Some(info) => order.push(info.manifest.id.clone()),
+                None => {
+                    log::warn!("Circular mod dependencies; skipping remaining mods");
+                    break;
+                }
+            }
+        }
+        order
+    }
+
+    /// Mount the assets of every enabled mod into the VFS, returning the loaded ids
+    pub fn mount_assets(&mut self, vfs: &mut Vfs) -> Vec<String> {
+        let order = self.load_order();
+        for id in &order {
+            let Some(info) = self.mods.iter_mut().find(|m| &m.manifest.id == id) else {
+                continue;
+            };
+            let assets = info.root.join(ASSETS_DIR);
+            if assets.is_dir() {
+                if info.has_capability(ModCapability::Assets, &self.allowed) {
+                    vfs.mount(id, &assets, info.manifest.priority);
+                } else {
+                    log::warn!("Mod '{}' is not permitted to override assets", id);
+                }
+            }
+            info.loaded = true;
+        }
+        order
+    }
+
+    /// Unmount every loaded mod from the VFS
+    pub fn unmount_assets(&mut self, vfs: &mut Vfs) {
+        for info in self.mods.iter_mut().filter(|m| m.loaded) {
+            vfs.unmount(&info.manifest.id);
+            info.loaded = false;
+        }
+    }
+
+    /// Load the scripts of every loaded mod with its granted capabilities
+    #[cfg(feature = "wasm")]
+    pub fn load_scripts(
+        &mut self,
+        host: &mut crate::scripting::ScriptHost,
+        scene: &mut crate::ecs::Scene,
+    ) -> Result<usize, String> {
+        use crate::scripting::ScriptCapabilities;
+
+        let mut count = 0;
+        for id in self.load_order() {
+            let Some(info) = self.get(&id) else {
+                continue;
+            };
+            if !info.loaded || info.manifest.scripts.is_empty() {
+                continue;
+            }
+            if !info.has_capability(ModCapability::Scripts, &self.allowed) {
+                log::warn!("Mod '{}' is not permitted to run scripts", id);
+                continue;
+            }
+
+            let capabilities = ScriptCapabilities {
+                spawn_entities: info.has_capability(ModCapability::SpawnEntities, &self.allowed),
+                modify_entities: info.has_capability(ModCapability::ModifyEntities, &self.allowed),
+                play_sounds: info.has_capability(ModCapability::Audio, &self.allowed),
+            };
+
+            let mut loaded = Vec::new();
+            for script in &info.manifest.scripts {
+                let relative = normalize_path(script)
+                    .ok_or_else(|| format!("Invalid script path in mod '{}': {}", id, script))?;
+                let bytes = std::fs::read(info.root.join(&relative))
+                    .map_err(|e| format!("Failed to read script '{}' of mod '{}': {}", script, id, e))?;
+                let name = format!("{}/{}", id, relative);
+                loaded.push(host.load_with_capabilities(&name, &bytes, scene, capabilities)?);
+            }
+            count += loaded.len();
+            self.scripts.extend(loaded.into_iter().map(|script| (id.clone(), script)));
+        }
+        Ok(count)
+    }
+
+    /// Unload every script loaded from mods
+    #[cfg(feature = "wasm")]
+    pub fn unload_scripts(&mut self, host: &mut crate::scripting::ScriptHost) {
+        for (_, script) in self.scripts.drain(..) {
+            host.unload(script);
+        }
+    }
+}
+
+impl Default for ModManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_mod(dir: &Path, json: &str, texture: &[u8]) {
+        std::fs::create_dir_all(dir.join("assets/textures")).unwrap();
+        std::fs::write(dir.join(MANIFEST_FILE), json).unwrap();
+        std::fs::write(dir.join("assets/textures/grass.png"), texture).unwrap();
+    }
+
+    #[test]
+    fn test_load_order_and_asset_overrides() {
+        let dir = std::env::temp_dir().join(format!("rgame_mods_test_{}", std::process::id()));
+        write_mod(
+            &dir.join("base"),
+            r#"{"id": "base", "name": "Base", "priority": 5, "capabilities": ["assets"]}"#,
+            b"base",
+        );
+        write_mod(
+            &dir.join("addon"),
+            r#"{"id": "addon", "name": "Addon", "priority": 1, "dependencies": ["base"], "capabilities": ["assets"]}"#,
+            b"addon",
+        );
+        write_mod(
+            &dir.join("orphan"),
+            r#"{"id": "orphan", "name": "Orphan", "dependencies": ["missing"], "capabilities": ["assets"]}"#,
+            b"orphan",
+        );
+
+        let mut mods = ModManager::new();
+        assert_eq!(mods.discover(&dir).unwrap(), 3);
+        assert_eq!(mods.load_order(), vec!["base", "addon"]);
+
+        let mut vfs = Vfs::new();
+        mods.mount_assets(&mut vfs);
+        assert_eq!(vfs.read("textures/grass.png").unwrap(), b"base");
+        assert!(mods.get("addon").unwrap().loaded);
+        assert!(!mods.get("orphan").unwrap().loaded);
+
+        mods.set_enabled("base", false);
+        assert!(mods.load_order().is_empty());
+
+        mods.unmount_assets(&mut vfs);
+        assert!(vfs.mounts().is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
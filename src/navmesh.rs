@@ -0,0 +1,597 @@
+//! Navigation meshes and path-following agents
+//!
+//! A [`NavMesh`] is baked from level triangles: walkable triangles (by slope)
+//! are welded into a connected polygon graph. Paths are found with A* over
+//! that graph and straightened with the funnel algorithm. Entities with a
+//! [`NavAgent`] and a [`Transform`] are moved along their paths by
+//! [`update_agents`], steering around each other.
+
+use crate::ecs::{Component, EntityId, Scene};
+use crate::math::Transform;
+use crate::resource::Mesh;
+use glam::{Mat4, Vec3};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Settings used when baking a navmesh
+#[derive(Debug, Clone, Copy)]
+pub struct NavMeshConfig {
+    /// Steepest walkable slope, in degrees
+    pub max_slope: f32,
+    /// Vertices closer than this are merged so neighbouring triangles connect
+    pub weld_distance: f32,
+}
+
+impl Default for NavMeshConfig {
+    fn default() -> Self {
+        Self {
+            max_slope: 45.0,
+            weld_distance: 0.01,
+        }
+    }
+}
+
+/// Collects level geometry to bake into a [`NavMesh`]
+#[derive(Debug, Clone, Default)]
+pub struct NavMeshBuilder {
+    vertices: Vec<Vec3>,
+    indices: Vec<u32>,
+    config: NavMeshConfig,
+}
+
+impl NavMeshBuilder {
+    /// Create a new builder with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the bake settings
+    pub fn with_config(mut self, config: NavMeshConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Add triangles transformed by `matrix`
+    pub fn add_triangles(mut self, vertices: &[Vec3], indices: &[u32], matrix: Mat4) -> Self {
+        let base = self.vertices.len() as u32;
+        self.vertices
+            .extend(vertices.iter().map(|v| matrix.transform_point3(*v)));
+        self.indices.extend(indices.iter().map(|i| base + i));
+        self
+    }
+
+    /// Add a mesh placed in the level with `transform`
+    pub fn add_mesh(self, mesh: &Mesh, transform: &Transform) -> Self {
+        let vertices: Vec<Vec3> = mesh
+            .vertices
+            .iter()
+            .map(|v| Vec3::from_array(v.position))
+            .collect();
+        self.add_triangles(&vertices, &mesh.indices, transform.matrix())
+    }
+
+    /// Bake the collected geometry
+    pub fn build(self) -> Result<NavMesh, String> {
+        NavMesh::bake(&self.vertices, &self.indices, &self.config)
+    }
+}
+
+/// A walkable triangle and its neighbours
+#[derive(Debug, Clone)]
+struct NavPoly {
+    vertices: [usize; 3],
+    /// Neighbour polygon across edge i (vertices i and i+1)
+    neighbors: [Option<usize>; 3],
+    center: Vec3,
+}
+
+/// A baked navigation mesh
+#[derive(Debug, Clone)]
+pub struct NavMesh {
+    vertices: Vec<Vec3>,
+    polys: Vec<NavPoly>,
+}
+
+impl NavMesh {
+    /// Bake a navmesh from triangle soup
+    pub fn bake(vertices: &[Vec3], indices: &[u32], config: &NavMeshConfig) -> Result<Self, String> {
+        if !indices.len().is_multiple_of(3) {
+            return Err(format!("Failed to bake navmesh: index count {} is not a multiple of 3", indices.len()));
+        }
+        if let Some(index) = indices.iter().find(|i| **i as usize >= vertices.len()) {
+            return Err(format!("Failed to bake navmesh: vertex index {} out of range", index));
+        }
+
+        // Weld nearby vertices on a grid so shared edges match exactly
+        let cell = config.weld_distance.max(f32::EPSILON);
+        let mut welded: Vec<Vec3> = Vec::new();
+        let mut lookup: HashMap<(i64, i64, i64), usize> = HashMap::new();
+        let remap: Vec<usize> = vertices
+            .iter()
+            .map(|v| {
+                let key = ((v.x / cell).round() as i64, (v.y / cell).round() as i64, (v.z / cell).round() as i64);
+                *lookup.entry(key).or_insert_with(|| {
+                    welded.push(*v);
+                    welded.len() - 1
+                })
+            })
+            .collect();
+
+        let min_normal_y = config.max_slope.to_radians().cos();
+        let mut polys = Vec::new();
+        for triangle in indices.chunks_exact(3) {
+            let mut ids = [0; 3];
+            for (id, index) in ids.iter_mut().zip(triangle) {
+                *id = remap[*index as usize];
+            }
+            if ids[0] == ids[1] || ids[1] == ids[2] || ids[0] == ids[2] {
+                continue;
+            }
+
+            let [a, b, c] = ids.map(|i| welded[i]);
+            let normal = (b - a).cross(c - a);
+            if normal.length_squared() <= f32::EPSILON {
+                continue;
+            }
+            // Accept either winding; only the slope matters
+            if normal.normalize().y.abs() < min_normal_y {
+                continue;
+            }
+
+            polys.push(NavPoly {
+                vertices: ids,
+                neighbors: [None; 3],
+                center: (a + b + c) / 3.0,
+            });
+        }
+
+        // Connect polygons sharing an edge
+        let mut edges: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+        for p in 0..polys.len() {
+            for e in 0..3 {
+                let v0 = polys[p].vertices[e];
+                let v1 = polys[p].vertices[(e + 1) % 3];
+                let key = (v0.min(v1), v0.max(v1));
+                if let Some((other, other_edge)) = edges.remove(&key) {
+                    polys[p].neighbors[e] = Some(other);
+                    polys[other].neighbors[other_edge] = Some(p);
+                } else {
+                    edges.insert(key, (p, e));
+                }
+            }
+        }
+
+        log::info!("Baked navmesh: {} polygons", polys.len());
+        Ok(Self { vertices: welded, polys })
+    }
+
+    /// Get the number of walkable polygons
+    pub fn poly_count(&self) -> usize {
+        self.polys.len()
+    }
+
+    fn corners(&self, poly: usize) -> [Vec3; 3] {
+        self.polys[poly].vertices.map(|v| self.vertices[v])
+    }
+
+    /// Find the polygon under (or nearest to) a point and the closest point on it
+    pub fn find_nearest(&self, point: Vec3) -> Option<(usize, Vec3)> {
+        let mut best: Option<(usize, Vec3, f32)> = None;
+        for poly in 0..self.polys.len() {
+            let [a, b, c] = self.corners(poly);
+            let closest = closest_point_on_triangle(point, a, b, c);
+            let distance = closest.distance_squared(point);
+            if best.is_none_or(|(_, _, d)| distance < d) {
+                best = Some((poly, closest, distance));
+            }
+        }
+        best.map(|(poly, closest, _)| (poly, closest))
+    }
+
+    /// Get the closest point on the navmesh
+    pub fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        self.find_nearest(point).map(|(_, p)| p)
+    }
+
+    /// Find a path between two points (snapped onto the navmesh)
+    pub fn find_path(&self, start: Vec3, end: Vec3) -> Option<Vec<Vec3>> {
+        let (start_poly, start) = self.find_nearest(start)?;
+        let (end_poly, end) = self.find_nearest(end)?;
+        let corridor = self.find_corridor(start_poly, end_poly, end)?;
+        Some(self.string_pull(&corridor, start, end))
+    }
+
+    /// A* over the polygon graph
+    fn find_corridor(&self, start: usize, end: usize, goal: Vec3) -> Option<Vec<usize>> {
+        #[derive(PartialEq)]
+        struct Open(f32, usize);
+        impl Eq for Open {}
+        impl PartialOrd for Open {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Open {
+            fn cmp(&self, other: &Self) -> Ordering {
+                other.0.total_cmp(&self.0)
+            }
+        }
+
+        let mut g = vec![f32::INFINITY; self.polys.len()];
+        let mut came_from = vec![usize::MAX; self.polys.len()];
+        let mut open = BinaryHeap::new();
+        g[start] = 0.0;
+        open.push(Open(self.polys[start].center.distance(goal), start));
+
+        while let Some(Open(_, poly)) = open.pop() {
+            if poly == end {
+                let mut corridor = vec![end];
+                let mut current = end;
+                while current != start {
+                    current = came_from[current];
+                    corridor.push(current);
+                }
+                corridor.reverse();
+                return Some(corridor);
+            }
+            for next in self.polys[poly].neighbors.iter().flatten() {
+                let cost = g[poly] + self.polys[poly].center.distance(self.polys[*next].center);
+                if cost < g[*next] {
+                    g[*next] = cost;
+                    came_from[*next] = poly;
+                    open.push(Open(cost + self.polys[*next].center.distance(goal), *next));
+                }
+            }
+        }
+        None
+    }
+
+    /// Shared edge between two neighbouring polygons as (left, right) seen from `from`
+    fn portal(&self, from: usize, to: usize) -> (Vec3, Vec3) {
+        let poly = &self.polys[from];
+        let edge = poly.neighbors.iter().position(|n| *n == Some(to)).unwrap_or(0);
+        let a = self.vertices[poly.vertices[edge]];
+        let b = self.vertices[poly.vertices[(edge + 1) % 3]];
+        if tri_area_2d(poly.center, a, b) < 0.0 {
+            (b, a)
+        } else {
+            (a, b)
+        }
+    }
+
+    /// Straighten a polygon corridor with the "simple stupid funnel" algorithm
+    fn string_pull(&self, corridor: &[usize], start: Vec3, end: Vec3) -> Vec<Vec3> {
+        let mut portals = vec![(start, start)];
+        for pair in corridor.windows(2) {
+            portals.push(self.portal(pair[0], pair[1]));
+        }
+        portals.push((end, end));
+
+        let mut path = vec![start];
+        let (mut apex, mut left, mut right) = (start, start, start);
+        let (mut left_index, mut right_index) = (0, 0);
+        let mut i = 1;
+
+        while i < portals.len() {
+            let (new_left, new_right) = portals[i];
+
+            // Tighten the right side
+            if tri_area_2d(apex, right, new_right) <= 0.0 {
+                if apex == right || tri_area_2d(apex, left, new_right) > 0.0 {
+                    right = new_right;
+                    right_index = i;
+                } else {
+                    // Right crossed over left: left becomes a corner
+                    path.push(left);
+                    apex = left;
+                    (left, right) = (apex, apex);
+                    right_index = left_index;
+                    i = left_index + 1;
+                    continue;
+                }
+            }
+
+            // Tighten the left side
+            if tri_area_2d(apex, left, new_left) >= 0.0 {
+                if apex == left || tri_area_2d(apex, right, new_left) < 0.0 {
+                    left = new_left;
+                    left_index = i;
+                } else {
+                    path.push(right);
+                    apex = right;
+                    (left, right) = (apex, apex);
+                    left_index = right_index;
+                    i = right_index + 1;
+                    continue;
+                }
+            }
+
+            i += 1;
+        }
+
+        if path.last() != Some(&end) {
+            path.push(end);
+        }
+        path
+    }
+}
+
+/// Twice the signed area of triangle (a, b, c) projected onto the XZ plane
+fn tri_area_2d(a: Vec3, b: Vec3, c: Vec3) -> f32 {
+    let ab = b - a;
+    let ac = c - a;
+    ac.x * ab.z - ab.x * ac.z
+}
+
+/// Closest point to `p` on triangle (a, b, c)
+fn closest_point_on_triangle(p: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+/// Moves an entity along navmesh paths
+#[derive(Debug, Clone)]
+pub struct NavAgent {
+    /// Maximum speed in units per second
+    pub speed: f32,
+    /// Maximum change of velocity per second
+    pub acceleration: f32,
+    /// Radius used for avoidance between agents
+    pub radius: f32,
+    /// Distance at which the destination counts as reached
+    pub stopping_distance: f32,
+    /// Strength of separation from nearby agents
+    pub avoidance_weight: f32,
+    destination: Option<Vec3>,
+    path: Vec<Vec3>,
+    waypoint: usize,
+    velocity: Vec3,
+    needs_path: bool,
+}
+
+impl Component for NavAgent {}
+
+impl NavAgent {
+    /// Create a new agent with the given speed
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            acceleration: speed * 8.0,
+            radius: 0.5,
+            stopping_distance: 0.1,
+            avoidance_weight: 1.0,
+            destination: None,
+            path: Vec::new(),
+            waypoint: 0,
+            velocity: Vec3::ZERO,
+            needs_path: false,
+        }
+    }
+
+    /// Set the point to walk to (the path is computed on the next update)
+    pub fn set_destination(&mut self, destination: Vec3) {
+        self.destination = Some(destination);
+        self.needs_path = true;
+    }
+
+    /// Stop and clear the destination
+    pub fn stop(&mut self) {
+        self.destination = None;
+        self.path.clear();
+        self.needs_path = false;
+    }
+
+    /// Get the current destination
+    pub fn destination(&self) -> Option<Vec3> {
+        self.destination
+    }
+
+    /// Get the current path
+    pub fn path(&self) -> &[Vec3] {
+        &self.path
+    }
+
+    /// Get the current velocity
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    /// Check if the agent has no destination left to walk to
+    pub fn is_idle(&self) -> bool {
+        self.destination.is_none()
+    }
+}
+
+/// Move all active agents along their paths, avoiding each other
+pub fn update_agents(scene: &mut Scene, navmesh: &NavMesh, delta: f32) {
+    let agents: Vec<(EntityId, Vec3, Vec3, f32)> = scene
+        .active_entities()
+        .filter_map(|e| {
+            let agent = e.get_component::<NavAgent>()?;
+            let transform = e.get_component::<Transform>()?;
+            Some((e.id(), transform.position, agent.velocity, agent.radius))
+        })
+        .collect();
+
+    for (id, position, _, radius) in &agents {
+        let Some(mut entity) = scene.get_entity_mut(*id) else {
+            continue;
+        };
+        let Some(agent) = entity.get_component_mut::<NavAgent>() else {
+            continue;
+        };
+
+        if agent.needs_path {
+            agent.needs_path = false;
+            agent.waypoint = 0;
+            agent.path = agent
+                .destination
+                .and_then(|destination| navmesh.find_path(*position, destination))
+                .unwrap_or_default();
+            if agent.path.is_empty() {
+                log::debug!("No navmesh path for entity {}", id);
+                agent.destination = None;
+            }
+        }
+
+        // Advance past reached waypoints
+        while agent.waypoint < agent.path.len() {
+            let target = agent.path[agent.waypoint];
+            let last = agent.waypoint + 1 == agent.path.len();
+            let reach = if last { agent.stopping_distance } else { agent.radius * 0.5 };
+            if horizontal(target - *position).length() > reach {
+                break;
+            }
+            agent.waypoint += 1;
+        }
+        if agent.waypoint >= agent.path.len() && agent.destination.is_some() {
+            agent.destination = None;
+            agent.path.clear();
+        }
+
+        let mut desired = Vec3::ZERO;
+        if let Some(target) = agent.path.get(agent.waypoint) {
+            let to_target = horizontal(*target - *position);
+            let distance = to_target.length();
+            // Slow down on the final approach
+            let last = agent.waypoint + 1 == agent.path.len();
+            let speed = if last {
+                agent.speed.min(distance / delta.max(f32::EPSILON))
+            } else {
+                agent.speed
+            };
+            desired = to_target.normalize_or_zero() * speed;
+        }
+
+        // Separation from neighbours that are too close
+        let mut separation = Vec3::ZERO;
+        for (other, other_position, _, other_radius) in &agents {
+            if other == id {
+                continue;
+            }
+            let offset = horizontal(*position - *other_position);
+            let distance = offset.length();
+            let min_distance = radius + other_radius;
+            if distance < min_distance && distance > f32::EPSILON {
+                separation += offset / distance * (min_distance - distance) / min_distance;
+            }
+        }
+        if separation != Vec3::ZERO {
+            desired += separation * agent.speed * agent.avoidance_weight;
+            desired = desired.clamp_length_max(agent.speed);
+        }
+
+        let change = (desired - agent.velocity).clamp_length_max(agent.acceleration * delta);
+        agent.velocity += change;
+        let velocity = agent.velocity;
+
+        if let Some(transform) = entity.get_component_mut::<Transform>() {
+            let moved = transform.position + velocity * delta;
+            transform.position = navmesh.closest_point(moved).unwrap_or(moved);
+        }
+    }
+}
+
+fn horizontal(v: Vec3) -> Vec3 {
+    Vec3::new(v.x, 0.0, v.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An L-shaped floor: the direct line from one arm to the other leaves the mesh
+    fn l_shape() -> NavMesh {
+        let quad = |x0: f32, z0: f32, x1: f32, z1: f32| {
+            [
+                Vec3::new(x0, 0.0, z0),
+                Vec3::new(x1, 0.0, z0),
+                Vec3::new(x1, 0.0, z1),
+                Vec3::new(x0, 0.0, z1),
+            ]
+        };
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for q in [quad(0.0, 0.0, 2.0, 2.0), quad(2.0, 0.0, 10.0, 2.0), quad(0.0, 2.0, 2.0, 10.0)] {
+            let base = vertices.len() as u32;
+            vertices.extend(q);
+            indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+        // A steep wall that must not become walkable
+        vertices.extend([Vec3::new(5.0, 0.0, 5.0), Vec3::new(6.0, 0.0, 5.0), Vec3::new(5.0, 3.0, 5.0)]);
+        indices.extend([12, 13, 14]);
+
+        NavMesh::bake(&vertices, &indices, &NavMeshConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_navmesh_path_turns_corner() {
+        let navmesh = l_shape();
+        assert_eq!(navmesh.poly_count(), 6);
+
+        let path = navmesh
+            .find_path(Vec3::new(9.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 9.0))
+            .unwrap();
+        assert_eq!(path.len(), 3);
+        assert!(path[1].distance(Vec3::new(2.0, 0.0, 2.0)) < 1e-4);
+    }
+
+    #[test]
+    fn test_agent_reaches_destination() {
+        let navmesh = l_shape();
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Agent".to_string());
+        let mut entity = scene.get_entity_mut(id).unwrap();
+        entity.add_component(Transform::from_position(Vec3::new(9.0, 0.0, 1.0)));
+        let mut agent = NavAgent::new(4.0);
+        agent.set_destination(Vec3::new(1.0, 0.0, 9.0));
+        entity.add_component(agent);
+
+        for _ in 0..300 {
+            update_agents(&mut scene, &navmesh, 1.0 / 60.0);
+        }
+
+        let entity = scene.get_entity(id).unwrap();
+        assert!(entity.get_component::<NavAgent>().unwrap().is_idle());
+        let position = entity.get_component::<Transform>().unwrap().position;
+        assert!(position.distance(Vec3::new(1.0, 0.0, 9.0)) < 0.2);
+    }
+}
@@ -0,0 +1,799 @@
+//! Client-server networking with entity replication
+//!
+//! A small UDP transport for multiplayer prototypes:
+//! - Connection handshake, heartbeats, and timeouts
+//! - A fixed server tick with snapshots sent to every client
+//! - Opt-in component replication via [`Replicated<T>`] markers
+//! - Unreliable game messages in both directions
+//!
+//! The server owns the authoritative [`Scene`]; clients mirror replicated
+//! entities into their own scene when snapshots arrive.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use crate::ecs::{Component, EntityId, EntityMut, EntityRef, Scene};
+
+/// Identifier assigned to a client by the server
+pub type ClientId = u64;
+
+/// Identifier of a replicated entity (the entity ID on the server)
+pub type NetworkId = u64;
+
+/// Maximum size of a single datagram we send
+const MAX_PACKET_SIZE: usize = 1200;
+
+/// Marks component `T` of an entity for replication to clients
+pub struct Replicated<T> {
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Replicated<T> {
+    /// Create a replication marker
+    pub fn new() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for Replicated<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static> Component for Replicated<T> {}
+
+/// Added to client-side entities that mirror a server entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetEntity {
+    pub id: NetworkId,
+}
+
+impl Component for NetEntity {}
+
+/// Replicated state of a single entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityState {
+    pub id: NetworkId,
+    pub name: String,
+    pub components: Vec<(String, Vec<u8>)>,
+}
+
+/// State of all replicated entities at a server tick
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub entities: Vec<EntityState>,
+    pub despawned: Vec<NetworkId>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Packet {
+    Connect { protocol_id: u64 },
+    Accept { client_id: ClientId, tick_rate: u32, server_tick: u64 },
+    Deny { reason: String },
+    Disconnect,
+    Ping { time: f64 },
+    Pong { time: f64, server_tick: u64 },
+    Snapshot(Snapshot),
+    Message { data: Vec<u8> },
+}
+
+impl Packet {
+    fn encode(&self) -> Result<Vec<u8>, String> {
+        bincode::serialize(self).map_err(|e| format!("Failed to encode packet: {}", e))
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        bincode::deserialize(bytes).ok()
+    }
+}
+
+type EncodeFn = fn(&EntityRef) -> Option<Result<Vec<u8>, String>>;
+type ApplyFn = fn(&mut EntityMut, &[u8]) -> Result<(), String>;
+
+struct ReplicatedComponent {
+    name: String,
+    encode: EncodeFn,
+    apply: ApplyFn,
+}
+
+fn encode_component<T: Component + Serialize>(entity: &EntityRef) -> Option<Result<Vec<u8>, String>> {
+    if !entity.has_component::<Replicated<T>>() {
+        return None;
+    }
+    entity.get_component::<T>().map(|component| {
+        bincode::serialize(component).map_err(|e| format!("Failed to encode component: {}", e))
+    })
+}
+
+fn apply_component<T: Component + DeserializeOwned>(entity: &mut EntityMut, bytes: &[u8]) -> Result<(), String> {
+    let component: T = bincode::deserialize(bytes)
+        .map_err(|e| format!("Failed to decode component: {}", e))?;
+    entity.add_component(component);
+    Ok(())
+}
+
+/// Component types that can be replicated, shared by server and client
+#[derive(Default)]
+pub struct ReplicationRegistry {
+    components: Vec<ReplicatedComponent>,
+}
+
+impl ReplicationRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a replicable component type under a name shared by server and client
+    pub fn register<T>(&mut self, name: &str)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.components.retain(|c| c.name != name);
+        self.components.push(ReplicatedComponent {
+            name: name.to_string(),
+            encode: encode_component::<T>,
+            apply: apply_component::<T>,
+        });
+    }
+
+    /// Capture the replicated components of an entity (None if it has none)
+    pub fn capture(&self, entity: &EntityRef) -> Result<Option<EntityState>, String> {
+        let mut components = Vec::new();
+        for replicated in &self.components {
+            if let Some(bytes) = (replicated.encode)(entity) {
+                components.push((replicated.name.clone(), bytes?));
+            }
+        }
+        if components.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(EntityState {
+            id: entity.id(),
+            name: entity.name().to_string(),
+            components,
+        }))
+    }
+
+    /// Apply replicated component data to an entity
+    pub fn apply(&self, entity: &mut EntityMut, state: &EntityState) -> Result<(), String> {
+        for (name, bytes) in &state.components {
+            match self.components.iter().find(|c| &c.name == name) {
+                Some(replicated) => (replicated.apply)(entity, bytes)?,
+                None => log::warn!("Received unregistered component '{}'", name),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Timing configuration shared by server and client
+#[derive(Debug, Clone, Copy)]
+pub struct NetConfig {
+    /// Protocol identifier; peers with a different id are rejected
+    pub protocol_id: u64,
+    /// Server ticks (snapshots) per second
+    pub tick_rate: u32,
+    /// Seconds without packets before a peer is considered disconnected
+    pub timeout: f32,
+    /// Seconds between heartbeat pings
+    pub heartbeat_interval: f32,
+    /// Maximum number of connected clients
+    pub max_clients: usize,
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        Self {
+            protocol_id: 0x7267_616d_6500_0001,
+            tick_rate: 20,
+            timeout: 5.0,
+            heartbeat_interval: 0.5,
+            max_clients: 16,
+        }
+    }
+}
+
+fn bind_nonblocking<A: ToSocketAddrs>(addr: A) -> Result<UdpSocket, String> {
+    let socket = UdpSocket::bind(addr).map_err(|e| format!("Failed to bind UDP socket: {}", e))?;
+    socket
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to set socket non-blocking: {}", e))?;
+    Ok(socket)
+}
+
+/// Drain all pending datagrams from a socket
+fn receive_all(socket: &UdpSocket) -> Vec<(SocketAddr, Packet)> {
+    let mut buffer = [0u8; 65536];
+    let mut packets = Vec::new();
+    loop {
+        match socket.recv_from(&mut buffer) {
+            Ok((len, addr)) => {
+                if let Some(packet) = Packet::decode(&buffer[..len]) {
+                    packets.push((addr, packet));
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+            // Windows reports ICMP "port unreachable" as a receive error; skip it
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => continue,
+            Err(e) => {
+                log::warn!("UDP receive error: {}", e);
+                break;
+            }
+        }
+    }
+    packets
+}
+
+fn send_packet(socket: &UdpSocket, addr: SocketAddr, packet: &Packet) {
+    match packet.encode() {
+        Ok(bytes) => {
+            if let Err(e) = socket.send_to(&bytes, addr) {
+                log::debug!("Failed to send packet to {}: {}", addr, e);
+            }
+        }
+        Err(e) => log::warn!("{}", e),
+    }
+}
+
+/// Split a snapshot into datagram-sized pieces
+fn split_snapshot(snapshot: Snapshot) -> Vec<Snapshot> {
+    let mut parts = Vec::new();
+    let mut current = Snapshot {
+        tick: snapshot.tick,
+        entities: Vec::new(),
+        despawned: snapshot.despawned,
+    };
+    let mut size = 64 + current.despawned.len() * 8;
+
+    for entity in snapshot.entities {
+        let entity_size = 32
+            + entity.name.len()
+            + entity
+                .components
+                .iter()
+                .map(|(name, bytes)| name.len() + bytes.len() + 16)
+                .sum::<usize>();
+        if size + entity_size > MAX_PACKET_SIZE && !current.entities.is_empty() {
+            parts.push(std::mem::replace(
+                &mut current,
+                Snapshot {
+                    tick: snapshot.tick,
+                    entities: Vec::new(),
+                    despawned: Vec::new(),
+                },
+            ));
+            size = 64;
+        }
+        size += entity_size;
+        current.entities.push(entity);
+    }
+
+    parts.push(current);
+    parts
+}
+
+/// Events produced by [`NetServer::update`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerEvent {
+    ClientConnected(ClientId),
+    ClientDisconnected(ClientId),
+    Message(ClientId, Vec<u8>),
+}
+
+struct ClientConnection {
+    id: ClientId,
+    since_last_packet: f32,
+}
+
+/// Authoritative game server
+pub struct NetServer {
+    socket: UdpSocket,
+    config: NetConfig,
+    registry: ReplicationRegistry,
+    clients: HashMap<SocketAddr, ClientConnection>,
+    next_client_id: ClientId,
+    tick: u64,
+    tick_accumulator: f32,
+    replicated: HashSet<NetworkId>,
+    recent_despawns: Vec<(NetworkId, u64)>,
+}
+
+impl NetServer {
+    /// Bind a server to a local address (e.g. "0.0.0.0:7777")
+    pub fn bind<A: ToSocketAddrs>(addr: A, config: NetConfig, registry: ReplicationRegistry) -> Result<Self, String> {
+        let socket = bind_nonblocking(addr)?;
+        log::info!("Server listening on {:?}", socket.local_addr().ok());
+        Ok(Self {
+            socket,
+            config,
+            registry,
+            clients: HashMap::new(),
+            next_client_id: 1,
+            tick: 0,
+            tick_accumulator: 0.0,
+            replicated: HashSet::new(),
+            recent_despawns: Vec::new(),
+        })
+    }
+
+    /// Get the bound local address
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.socket.local_addr().ok()
+    }
+
+    /// Get the current server tick
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Get the connected client IDs
+    pub fn clients(&self) -> Vec<ClientId> {
+        self.clients.values().map(|c| c.id).collect()
+    }
+
+    /// Process incoming packets, time out clients, and send snapshots on tick boundaries
+    pub fn update(&mut self, delta: f32, scene: &Scene) -> Vec<ServerEvent> {
+        let mut events = Vec::new();
+
+        for (addr, packet) in receive_all(&self.socket) {
+            if let Some(client) = self.clients.get_mut(&addr) {
+                client.since_last_packet = 0.0;
+            }
+            match packet {
+                Packet::Connect { protocol_id } => self.handle_connect(addr, protocol_id, &mut events),
+                Packet::Disconnect => {
+                    if let Some(client) = self.clients.remove(&addr) {
+                        log::info!("Client {} disconnected", client.id);
+                        events.push(ServerEvent::ClientDisconnected(client.id));
+                    }
+                }
+                Packet::Ping { time } if self.clients.contains_key(&addr) => {
+                    send_packet(&self.socket, addr, &Packet::Pong { time, server_tick: self.tick });
+                }
+                Packet::Message { data } => {
+                    if let Some(client) = self.clients.get(&addr) {
+                        events.push(ServerEvent::Message(client.id, data));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let timeout = self.config.timeout;
+        let mut timed_out = Vec::new();
+        for (addr, client) in &mut self.clients {
+            client.since_last_packet += delta;
+            if client.since_last_packet > timeout {
+                timed_out.push(*addr);
+            }
+        }
+        for addr in timed_out {
+            if let Some(client) = self.clients.remove(&addr) {
+                log::info!("Client {} timed out", client.id);
+                events.push(ServerEvent::ClientDisconnected(client.id));
+            }
+        }
+
+        let tick_duration = 1.0 / self.config.tick_rate.max(1) as f32;
+        self.tick_accumulator += delta;
+        if self.tick_accumulator >= tick_duration {
+            // Don't try to catch up on missed ticks; one snapshot carries the latest state
+            self.tick_accumulator %= tick_duration;
+            self.tick += 1;
+            if let Err(e) = self.send_snapshot(scene) {
+                log::warn!("Failed to send snapshot: {}", e);
+            }
+        }
+
+        events
+    }
+
+    fn handle_connect(&mut self, addr: SocketAddr, protocol_id: u64, events: &mut Vec<ServerEvent>) {
+        if protocol_id != self.config.protocol_id {
+            send_packet(&self.socket, addr, &Packet::Deny { reason: "Protocol mismatch".to_string() });
+            return;
+        }
+
+        let client_id = match self.clients.get(&addr) {
+            // Repeated connect (our accept was lost); resend it
+            Some(client) => client.id,
+            None => {
+                if self.clients.len() >= self.config.max_clients {
+                    send_packet(&self.socket, addr, &Packet::Deny { reason: "Server full".to_string() });
+                    return;
+                }
+                let id = self.next_client_id;
+                self.next_client_id += 1;
+                self.clients.insert(addr, ClientConnection { id, since_last_packet: 0.0 });
+                log::info!("Client {} connected from {}", id, addr);
+                events.push(ServerEvent::ClientConnected(id));
+                id
+            }
+        };
+
+        send_packet(
+            &self.socket,
+            addr,
+            &Packet::Accept {
+                client_id,
+                tick_rate: self.config.tick_rate,
+                server_tick: self.tick,
+            },
+        );
+    }
+
+    /// Build a snapshot of all replicated entities at the current tick
+    pub fn build_snapshot(&mut self, scene: &Scene) -> Result<Snapshot, String> {
+        let mut entities = Vec::new();
+        let mut present = HashSet::new();
+        for entity in scene.active_entities() {
+            if let Some(state) = self.registry.capture(&entity)? {
+                present.insert(state.id);
+                entities.push(state);
+            }
+        }
+
+        for id in self.replicated.difference(&present) {
+            self.recent_despawns.push((*id, self.tick));
+        }
+        self.replicated = present;
+
+        // Repeat despawns for a second's worth of ticks to survive packet loss
+        let keep_ticks = self.config.tick_rate as u64;
+        let tick = self.tick;
+        self.recent_despawns
+            .retain(|(_, despawn_tick)| tick.saturating_sub(*despawn_tick) <= keep_ticks);
+
+        Ok(Snapshot {
+            tick,
+            entities,
+            despawned: self.recent_despawns.iter().map(|(id, _)| *id).collect(),
+        })
+    }
+
+    fn send_snapshot(&mut self, scene: &Scene) -> Result<(), String> {
+        if self.clients.is_empty() {
+            return Ok(());
+        }
+        let snapshot = self.build_snapshot(scene)?;
+        let parts: Vec<Vec<u8>> = split_snapshot(snapshot)
+            .into_iter()
+            .map(|part| Packet::Snapshot(part).encode())
+            .collect::<Result<_, _>>()?;
+
+        for addr in self.clients.keys() {
+            for bytes in &parts {
+                if let Err(e) = self.socket.send_to(bytes, addr) {
+                    log::debug!("Failed to send snapshot to {}: {}", addr, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Send an unreliable message to one client
+    pub fn send_message(&self, client: ClientId, data: &[u8]) {
+        if let Some((addr, _)) = self.clients.iter().find(|(_, c)| c.id == client) {
+            send_packet(&self.socket, *addr, &Packet::Message { data: data.to_vec() });
+        }
+    }
+
+    /// Send an unreliable message to every client
+    pub fn broadcast(&self, data: &[u8]) {
+        let packet = Packet::Message { data: data.to_vec() };
+        for addr in self.clients.keys() {
+            send_packet(&self.socket, *addr, &packet);
+        }
+    }
+
+    /// Disconnect a client
+    pub fn kick(&mut self, client: ClientId) {
+        let addr = self.clients.iter().find(|(_, c)| c.id == client).map(|(a, _)| *a);
+        if let Some(addr) = addr {
+            send_packet(&self.socket, addr, &Packet::Disconnect);
+            self.clients.remove(&addr);
+        }
+    }
+}
+
+impl Drop for NetServer {
+    fn drop(&mut self) {
+        for addr in self.clients.keys() {
+            send_packet(&self.socket, *addr, &Packet::Disconnect);
+        }
+    }
+}
+
+/// Connection state of a [`NetClient`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+/// Events produced by [`NetClient::update`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    Connected(ClientId),
+    Disconnected(String),
+    /// A snapshot for this server tick was applied to the scene
+    SnapshotApplied(u64),
+    Message(Vec<u8>),
+}
+
+/// Game client mirroring replicated entities from a server
+pub struct NetClient {
+    socket: UdpSocket,
+    server: SocketAddr,
+    config: NetConfig,
+    registry: ReplicationRegistry,
+    state: ConnectionState,
+    client_id: Option<ClientId>,
+    time: f64,
+    since_last_packet: f32,
+    since_last_send: f32,
+    rtt: f32,
+    server_tick: f64,
+    last_snapshot_tick: u64,
+    entity_map: HashMap<NetworkId, EntityId>,
+}
+
+impl NetClient {
+    /// Start connecting to a server
+    pub fn connect<A: ToSocketAddrs>(server: A, config: NetConfig, registry: ReplicationRegistry) -> Result<Self, String> {
+        let server = server
+            .to_socket_addrs()
+            .map_err(|e| format!("Invalid server address: {}", e))?
+            .next()
+            .ok_or("Server address did not resolve")?;
+        let local = if server.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = bind_nonblocking(local)?;
+
+        let client = Self {
+            socket,
+            server,
+            config,
+            registry,
+            state: ConnectionState::Connecting,
+            client_id: None,
+            time: 0.0,
+            since_last_packet: 0.0,
+            since_last_send: 0.0,
+            rtt: 0.0,
+            server_tick: 0.0,
+            last_snapshot_tick: 0,
+            entity_map: HashMap::new(),
+        };
+        client.send(&Packet::Connect { protocol_id: config.protocol_id });
+        log::info!("Connecting to {}", server);
+        Ok(client)
+    }
+
+    fn send(&self, packet: &Packet) {
+        send_packet(&self.socket, self.server, packet);
+    }
+
+    /// Get the connection state
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// Get the ID assigned by the server
+    pub fn client_id(&self) -> Option<ClientId> {
+        self.client_id
+    }
+
+    /// Get the measured round-trip time in seconds
+    pub fn rtt(&self) -> f32 {
+        self.rtt
+    }
+
+    /// Get the estimated current server tick (fractional, advanced locally between snapshots)
+    pub fn server_tick(&self) -> f64 {
+        self.server_tick
+    }
+
+    /// Get the tick of the newest applied snapshot
+    pub fn last_snapshot_tick(&self) -> u64 {
+        self.last_snapshot_tick
+    }
+
+    /// Get the local entity mirroring a server entity
+    pub fn local_entity(&self, id: NetworkId) -> Option<EntityId> {
+        self.entity_map.get(&id).copied()
+    }
+
+    /// Process incoming packets, send heartbeats, and apply snapshots to `scene`
+    pub fn update(&mut self, delta: f32, scene: &mut Scene) -> Vec<ClientEvent> {
+        let mut events = Vec::new();
+        if self.state == ConnectionState::Disconnected {
+            return events;
+        }
+
+        self.time += delta as f64;
+        self.since_last_packet += delta;
+        self.since_last_send += delta;
+        if self.state == ConnectionState::Connected {
+            self.server_tick += delta as f64 * self.config.tick_rate as f64;
+        }
+
+        for (addr, packet) in receive_all(&self.socket) {
+            if addr != self.server {
+                continue;
+            }
+            self.since_last_packet = 0.0;
+
+            match packet {
+                Packet::Accept { client_id, tick_rate, server_tick }
+                    if self.state == ConnectionState::Connecting =>
+                {
+                    self.state = ConnectionState::Connected;
+                    self.client_id = Some(client_id);
+                    self.config.tick_rate = tick_rate;
+                    self.server_tick = server_tick as f64;
+                    log::info!("Connected as client {}", client_id);
+                    events.push(ClientEvent::Connected(client_id));
+                }
+                Packet::Deny { reason } => {
+                    self.state = ConnectionState::Disconnected;
+                    events.push(ClientEvent::Disconnected(reason));
+                    return events;
+                }
+                Packet::Disconnect => {
+                    self.state = ConnectionState::Disconnected;
+                    events.push(ClientEvent::Disconnected("Server closed the connection".to_string()));
+                    return events;
+                }
+                Packet::Pong { time, server_tick } => {
+                    let rtt = (self.time - time).max(0.0) as f32;
+                    self.rtt = if self.rtt == 0.0 { rtt } else { self.rtt * 0.9 + rtt * 0.1 };
+                    // Server tick when the pong was sent plus half the round trip
+                    let estimate = server_tick as f64
+                        + (self.rtt as f64 * 0.5) * self.config.tick_rate as f64;
+                    self.server_tick = self.server_tick.max(estimate);
+                }
+                Packet::Snapshot(snapshot)
+                    if self.state == ConnectionState::Connected
+                        && snapshot.tick >= self.last_snapshot_tick =>
+                {
+                    let tick = snapshot.tick;
+                    match self.apply_snapshot(&snapshot, scene) {
+                        Ok(()) => events.push(ClientEvent::SnapshotApplied(tick)),
+                        Err(e) => log::warn!("Failed to apply snapshot {}: {}", tick, e),
+                    }
+                }
+                Packet::Message { data } => events.push(ClientEvent::Message(data)),
+                _ => {}
+            }
+        }
+
+        if self.since_last_packet > self.config.timeout {
+            self.state = ConnectionState::Disconnected;
+            events.push(ClientEvent::Disconnected("Connection timed out".to_string()));
+            return events;
+        }
+
+        if self.since_last_send >= self.config.heartbeat_interval {
+            self.since_last_send = 0.0;
+            match self.state {
+                ConnectionState::Connecting => {
+                    self.send(&Packet::Connect { protocol_id: self.config.protocol_id })
+                }
+                ConnectionState::Connected => self.send(&Packet::Ping { time: self.time }),
+                ConnectionState::Disconnected => {}
+            }
+        }
+
+        events
+    }
+
+    /// Apply a snapshot to the scene, spawning, updating, and despawning mirrored entities
+    pub fn apply_snapshot(&mut self, snapshot: &Snapshot, scene: &mut Scene) -> Result<(), String> {
+        self.last_snapshot_tick = self.last_snapshot_tick.max(snapshot.tick);
+        self.server_tick = self.server_tick.max(snapshot.tick as f64);
+
+        for state in &snapshot.entities {
+            let local = match self.entity_map.get(&state.id) {
+                Some(local) if scene.get_entity(*local).is_some() => *local,
+                _ => {
+                    let local = scene.create_entity(state.name.clone());
+                    if let Some(mut entity) = scene.get_entity_mut(local) {
+                        entity.add_component(NetEntity { id: state.id });
+                    }
+                    self.entity_map.insert(state.id, local);
+                    local
+                }
+            };
+            if let Some(mut entity) = scene.get_entity_mut(local) {
+                self.registry.apply(&mut entity, state)?;
+            }
+        }
+
+        for id in &snapshot.despawned {
+            if let Some(local) = self.entity_map.remove(id) {
+                scene.remove_entity(local);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send an unreliable message to the server
+    pub fn send_message(&self, data: &[u8]) {
+        if self.state == ConnectionState::Connected {
+            self.send(&Packet::Message { data: data.to_vec() });
+        }
+    }
+
+    /// Disconnect from the server
+    pub fn disconnect(&mut self) {
+        if self.state != ConnectionState::Disconnected {
+            self.send(&Packet::Disconnect);
+            self.state = ConnectionState::Disconnected;
+        }
+    }
+}
+
+impl Drop for NetClient {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Transform;
+    use glam::Vec3;
+    use std::time::Duration;
+
+    fn registry() -> ReplicationRegistry {
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Transform>("transform");
+        registry
+    }
+
+    #[test]
+    fn test_loopback_replication() {
+        let config = NetConfig {
+            tick_rate: 100,
+            ..Default::default()
+        };
+        let mut server = NetServer::bind("127.0.0.1:0", config, registry()).unwrap();
+        let mut client = NetClient::connect(server.local_addr().unwrap(), config, registry()).unwrap();
+
+        let mut server_scene = Scene::new("Server".to_string());
+        let id = server_scene.create_entity("Player".to_string());
+        let mut entity = server_scene.get_entity_mut(id).unwrap();
+        entity.add_component(Transform::from_position(Vec3::new(4.0, 5.0, 6.0)));
+        entity.add_component(Replicated::<Transform>::new());
+        server_scene.create_entity("ServerOnly".to_string());
+
+        let mut client_scene = Scene::new("Client".to_string());
+        for _ in 0..100 {
+            server.update(0.02, &server_scene);
+            client.update(0.02, &mut client_scene);
+            if client.last_snapshot_tick() > 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        assert_eq!(client.state(), ConnectionState::Connected);
+        assert_eq!(client_scene.entity_count(), 1);
+        let local = client.local_entity(id).unwrap();
+        let mirrored = client_scene.get_entity(local).unwrap();
+        assert_eq!(
+            mirrored.get_component::<Transform>().unwrap().position,
+            Vec3::new(4.0, 5.0, 6.0)
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! Occlusion culling with hardware occlusion queries
+//!
+//! After the opaque meshes of the main camera are drawn, each entity's bounding box is
+//! rasterized against the depth buffer inside an occlusion query, without writing color
+//! or depth. The results are read back without stalling, so they lag a frame or more;
+//! entities whose box passed no samples for a few queries in a row are skipped until a
+//! query sees them again.
+
+use crate::ecs::EntityId;
+use crate::math::{Aabb, Frustum};
+use crate::renderer::DEPTH_FORMAT;
+use glam::Vec3;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc;
+
+/// Consecutive hidden results before an entity is skipped, so one bad frame doesn't pop
+const HIDDEN_RESULTS_TO_CULL: u32 = 2;
+
+/// Bounding box of an entity, as read by the proxy shader
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ProxyBox {
+    min: [f32; 4],
+    max: [f32; 4],
+}
+
+/// Union the bounds of each entity's draws, keeping the entities worth querying: those
+/// in the frustum that don't contain the camera (whose box would be clipped by the near
+/// plane, so they always count as visible)
+pub(crate) fn occlusion_proxies(
+    bounds: impl IntoIterator<Item = (EntityId, Aabb)>,
+    frustum: &Frustum,
+    eye: Vec3,
+    near: f32,
+) -> Vec<(EntityId, Aabb)> {
+    let mut proxies: Vec<(EntityId, Aabb)> = Vec::new();
+    let mut index: HashMap<EntityId, usize> = HashMap::new();
+    for (id, aabb) in bounds {
+        match index.get(&id) {
+            Some(&i) => {
+                let merged = &mut proxies[i].1;
+                *merged = Aabb::new(merged.min.min(aabb.min), merged.max.max(aabb.max));
+            }
+            None => {
+                index.insert(id, proxies.len());
+                proxies.push((id, aabb));
+            }
+        }
+    }
+    proxies.retain(|(_, aabb)| {
+        let inside = (eye - aabb.center()).abs().cmple(aabb.half_extents() + Vec3::splat(near)).all();
+        !inside && frustum.intersects_aabb(aabb)
+    });
+    proxies
+}
+
+/// Proxy pipeline, query set, and per-entity visibility history
+pub(crate) struct OcclusionCuller {
+    pipeline: wgpu::RenderPipeline,
+    query_set: wgpu::QuerySet,
+    capacity: u32,
+    box_buffer: wgpu::Buffer,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Entities queried this frame, in query order
+    queried: Vec<EntityId>,
+    /// Entities of the queries being read back
+    pending: Vec<EntityId>,
+    readback: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+    /// Consecutive hidden results per queried entity
+    hidden: HashMap<EntityId, u32>,
+}
+
+impl OcclusionCuller {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        camera_layout: &wgpu::BindGroupLayout,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Occlusion Proxy Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/occlusion.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Occlusion Proxy Pipeline Layout"),
+            bind_group_layouts: &[camera_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Occlusion Proxy Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<ProxyBox>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::empty(),
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let capacity = 256;
+        let (query_set, box_buffer, resolve_buffer, readback_buffer) = Self::create_queries(device, capacity);
+        Self {
+            pipeline,
+            query_set,
+            capacity,
+            box_buffer,
+            resolve_buffer,
+            readback_buffer,
+            queried: Vec::new(),
+            pending: Vec::new(),
+            readback: None,
+            hidden: HashMap::new(),
+        }
+    }
+
+    fn create_queries(
+        device: &wgpu::Device,
+        capacity: u32,
+    ) -> (wgpu::QuerySet, wgpu::Buffer, wgpu::Buffer, wgpu::Buffer) {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Occlusion Query Set"),
+            ty: wgpu::QueryType::Occlusion,
+            count: capacity,
+        });
+        let box_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Occlusion Proxy Buffer"),
+            size: capacity as u64 * std::mem::size_of::<ProxyBox>() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let results_size = capacity as u64 * std::mem::size_of::<u64>() as u64;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Occlusion Resolve Buffer"),
+            size: results_size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Occlusion Readback Buffer"),
+            size: results_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        (query_set, box_buffer, resolve_buffer, readback_buffer)
+    }
+
+    /// Whether recent queries found the entity hidden
+    pub(crate) fn is_occluded(&self, entity: EntityId) -> bool {
+        self.hidden.get(&entity).is_some_and(|count| *count >= HIDDEN_RESULTS_TO_CULL)
+    }
+
+    /// Apply finished query results, then queue this frame's proxies from
+    /// [`occlusion_proxies`] (unless the previous results are still being read back)
+    pub(crate) fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, proxies: &[(EntityId, Aabb)]) {
+        self.collect_results(device);
+        self.queried.clear();
+
+        // Entities that weren't queried (off screen or around the camera) start over as visible
+        let ids: HashSet<EntityId> = proxies.iter().map(|(id, _)| *id).collect();
+        self.hidden.retain(|id, _| ids.contains(id));
+        if self.readback.is_some() || proxies.is_empty() {
+            return;
+        }
+
+        if proxies.len() as u32 > self.capacity {
+            self.capacity = (proxies.len() as u32).next_power_of_two();
+            (self.query_set, self.box_buffer, self.resolve_buffer, self.readback_buffer) =
+                Self::create_queries(device, self.capacity);
+        }
+        let boxes: Vec<ProxyBox> = proxies
+            .iter()
+            .map(|(_, aabb)| ProxyBox {
+                min: aabb.min.extend(0.0).to_array(),
+                max: aabb.max.extend(0.0).to_array(),
+            })
+            .collect();
+        queue.write_buffer(&self.box_buffer, 0, bytemuck::cast_slice(&boxes));
+        self.queried = proxies.iter().map(|(id, _)| *id).collect();
+    }
+
+    /// Query set for the scene pass, if proxies are queued this frame
+    pub(crate) fn query_set(&self) -> Option<&wgpu::QuerySet> {
+        (!self.queried.is_empty()).then_some(&self.query_set)
+    }
+
+    /// Draw the queued proxies against the depth of the opaque meshes
+    pub(crate) fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        if self.queried.is_empty() {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_vertex_buffer(0, self.box_buffer.slice(..));
+        for i in 0..self.queried.len() as u32 {
+            pass.begin_occlusion_query(i);
+            pass.draw(0..14, i..i + 1);
+            pass.end_occlusion_query();
+        }
+    }
+
+    /// Copy this frame's results to the readback buffer
+    pub(crate) fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.queried.is_empty() {
+            return;
+        }
+        let count = self.queried.len() as u32;
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        let size = count as u64 * std::mem::size_of::<u64>() as u64;
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, size);
+    }
+
+    /// Start reading back the results once the frame is submitted
+    pub(crate) fn map_results(&mut self) {
+        if self.queried.is_empty() {
+            return;
+        }
+        self.pending = std::mem::take(&mut self.queried);
+        let size = self.pending.len() as u64 * std::mem::size_of::<u64>() as u64;
+        let (sender, receiver) = mpsc::channel();
+        self.readback_buffer
+            .slice(..size)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        self.readback = Some(receiver);
+    }
+
+    /// Update the visibility history from a finished readback, without waiting for it
+    fn collect_results(&mut self, device: &wgpu::Device) {
+        let Some(receiver) = &self.readback else {
+            return;
+        };
+        device.poll(wgpu::Maintain::Poll);
+        match receiver.try_recv() {
+            Err(mpsc::TryRecvError::Empty) => return,
+            Ok(Ok(())) => {
+                let size = self.pending.len() as u64 * std::mem::size_of::<u64>() as u64;
+                {
+                    let data = self.readback_buffer.slice(..size).get_mapped_range();
+                    let samples: &[u64] = bytemuck::cast_slice(&data);
+                    for (id, &passed) in self.pending.iter().zip(samples) {
+                        let hidden = self.hidden.entry(*id).or_insert(0);
+                        *hidden = if passed == 0 { hidden.saturating_add(1) } else { 0 };
+                    }
+                }
+                self.readback_buffer.unmap();
+            }
+            Ok(Err(e)) => log::warn!("Failed to read occlusion queries: {}", e),
+            Err(mpsc::TryRecvError::Disconnected) => log::warn!("Occlusion query readback was dropped"),
+        }
+        self.readback = None;
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Mat4;
+
+    #[test]
+    fn test_occlusion_proxies() {
+        let view_proj = Mat4::perspective_rh(1.0, 1.0, 0.1, 100.0)
+            * Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        let frustum = Frustum::from_view_proj(view_proj);
+        let unit = |center: Vec3| Aabb::new(center - Vec3::ONE, center + Vec3::ONE);
+
+        let proxies = occlusion_proxies(
+            [
+                // Two LOD draws of one entity merge into one box
+                (1, unit(Vec3::new(0.0, 0.0, -10.0))),
+                (1, unit(Vec3::new(0.0, 2.0, -10.0))),
+                // Behind the camera
+                (2, unit(Vec3::new(0.0, 0.0, 10.0))),
+                // Around the camera
+                (3, unit(Vec3::ZERO)),
+                (4, unit(Vec3::new(0.0, 0.0, -20.0))),
+            ],
+            &frustum,
+            Vec3::ZERO,
+            0.1,
+        );
+        let ids: Vec<EntityId> = proxies.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [1, 4]);
+        assert_eq!(proxies[0].1.max.y, 3.0);
+    }
+}
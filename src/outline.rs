@@ -0,0 +1,269 @@
+//! Stencil-based selection outlines
+//!
+//! Entities passed to `Renderer::set_outlined_entities` are drawn twice after the main
+//! camera's scene pass: first into a stencil buffer without writing color, then pushed
+//! out along their normals by the outline width and colored wherever the stencil is still
+//! clear, leaving a ring around each silhouette. Outlines ignore the scene depth so
+//! selected entities stay visible behind other geometry.
+
+use crate::postprocess::HDR_FORMAT;
+use crate::renderer::Vertex;
+use crate::resource::Mesh;
+use crate::skinning::SkinVertex;
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+/// Outline width used until one is set, in pixels
+pub(crate) const DEFAULT_OUTLINE_WIDTH: f32 = 3.0;
+
+const STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Stencil8;
+
+/// Outline uniform buffer data
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+struct OutlineUniform {
+    color: [f32; 4],
+    /// Width in pixels (x) and viewport size in pixels (zw)
+    params: [f32; 4],
+}
+
+impl OutlineUniform {
+    fn new(color: [f32; 4], width: f32, viewport: (u32, u32)) -> Self {
+        Self {
+            color,
+            params: [width.max(0.0), 0.0, viewport.0.max(1) as f32, viewport.1.max(1) as f32],
+        }
+    }
+}
+
+/// A mesh draw to outline, using the object slot of its scene draw
+pub(crate) struct OutlineDraw<'a> {
+    pub(crate) mesh: &'a Mesh,
+    pub(crate) object_offset: u32,
+    pub(crate) skinned: bool,
+}
+
+/// Stencil-writing and outline pipelines, each with a skinned variant if supported
+struct OutlinePipelines {
+    mesh: wgpu::RenderPipeline,
+    skinned: Option<wgpu::RenderPipeline>,
+}
+
+impl OutlinePipelines {
+    fn get(&self, skinned: bool) -> Option<&wgpu::RenderPipeline> {
+        if skinned {
+            self.skinned.as_ref()
+        } else {
+            Some(&self.mesh)
+        }
+    }
+}
+
+/// Outline pipelines, stencil target, and color uniform
+pub(crate) struct OutlinePipeline {
+    mask: OutlinePipelines,
+    outline: OutlinePipelines,
+    sample_count: u32,
+    stencil_view: wgpu::TextureView,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl OutlinePipeline {
+    /// Create the pipelines with `layout`, the material pipeline layout, binding the
+    /// outline uniform at group 3 like material parameters
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        params_layout: &wgpu::BindGroupLayout,
+        common_shader: &str,
+        sample_count: u32,
+        skinning: bool,
+        size: (u32, u32),
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Outline Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                format!("{}\n{}", common_shader, include_str!("shaders/outline.wgsl")).into(),
+            ),
+        });
+        let create = |label: &str, vs_entry: &str, skinned: bool, write_mask, stencil| {
+            let mesh_buffers = [Vertex::desc()];
+            let skinned_buffers = [Vertex::desc(), SkinVertex::desc()];
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vs_entry,
+                    buffers: if skinned { &skinned_buffers } else { &mesh_buffers },
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: HDR_FORMAT,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: STENCIL_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState {
+                        front: stencil,
+                        back: stencil,
+                        read_mask: 0xff,
+                        write_mask: 0xff,
+                    },
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+        // Silhouettes set the stencil to the reference; the outline only fills around them
+        let write = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Always,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Replace,
+        };
+        let test = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::NotEqual,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Keep,
+        };
+        let (no_color, color) = (wgpu::ColorWrites::empty(), wgpu::ColorWrites::ALL);
+        let mask = OutlinePipelines {
+            mesh: create("Outline Mask Pipeline", "vs_mask", false, no_color, write),
+            skinned: skinning
+                .then(|| create("Outline Mask Pipeline (Skinned)", "vs_mask_skinned", true, no_color, write)),
+        };
+        let outline = OutlinePipelines {
+            mesh: create("Outline Pipeline", "vs_outline", false, color, test),
+            skinned: skinning.then(|| create("Outline Pipeline (Skinned)", "vs_outline_skinned", true, color, test)),
+        };
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Outline Buffer"),
+            contents: bytemuck::bytes_of(&OutlineUniform::new([0.0; 4], DEFAULT_OUTLINE_WIDTH, size)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Outline Bind Group"),
+            layout: params_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        Self {
+            mask,
+            outline,
+            sample_count,
+            stencil_view: Self::create_stencil_view(device, size, sample_count),
+            buffer,
+            bind_group,
+        }
+    }
+
+    fn create_stencil_view(device: &wgpu::Device, size: (u32, u32), sample_count: u32) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Outline Stencil Texture"),
+            size: wgpu::Extent3d {
+                width: size.0.max(1),
+                height: size.1.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: STENCIL_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    /// Recreate the stencil target after the surface size changed
+    pub(crate) fn resize(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        self.stencil_view = Self::create_stencil_view(device, size, self.sample_count);
+    }
+
+    /// Set the outline color and width for a viewport of `viewport` pixels
+    pub(crate) fn update(&self, queue: &wgpu::Queue, color: [f32; 4], width: f32, viewport: (u32, u32)) {
+        let uniform = OutlineUniform::new(color, width, viewport);
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    /// Stencil attachment for the outline pass, cleared every frame
+    pub(crate) fn stencil_attachment(&self) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+        wgpu::RenderPassDepthStencilAttachment {
+            view: &self.stencil_view,
+            depth_ops: None,
+            stencil_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(0),
+                store: wgpu::StoreOp::Discard,
+            }),
+        }
+    }
+
+    /// Draw the outlines into a pass with the camera (group 0) and a texture (group 2)
+    /// bound; skinned meshes are skipped without skinning support
+    pub(crate) fn draw<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        object_bind_group: &'a wgpu::BindGroup,
+        draws: &[OutlineDraw<'a>],
+    ) {
+        pass.set_bind_group(3, &self.bind_group, &[]);
+        pass.set_stencil_reference(1);
+        for pipelines in [&self.mask, &self.outline] {
+            for draw in draws {
+                let (Some(pipeline), Some(vertex_buffer), Some(index_buffer)) = (
+                    pipelines.get(draw.skinned),
+                    &draw.mesh.vertex_buffer,
+                    &draw.mesh.index_buffer,
+                ) else {
+                    continue;
+                };
+                pass.set_pipeline(pipeline);
+                pass.set_bind_group(1, object_bind_group, &[draw.object_offset]);
+                pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                if let Some(skin_buffer) = draw.mesh.skin_buffer.as_ref().filter(|_| draw.skinned) {
+                    pass.set_vertex_buffer(1, skin_buffer.slice(..));
+                }
+                pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                pass.draw_indexed(0..draw.mesh.indices.len() as u32, 0, 0..1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outline_uniform() {
+        let uniform = OutlineUniform::new([1.0, 0.5, 0.0, 1.0], 2.5, (1280, 720));
+        assert_eq!(uniform.params, [2.5, 0.0, 1280.0, 720.0]);
+
+        // Negative widths and empty viewports don't flip or divide by zero in the shader
+        let uniform = OutlineUniform::new([1.0; 4], -1.0, (0, 0));
+        assert_eq!(uniform.params, [0.0, 0.0, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,532 @@
+//! GPU particle emitters
+//!
+//! A [`ParticleEmitter`] spawns particles from its entity's [`Transform`]. The
+//! simulation runs entirely on the GPU: particles live in a storage buffer, a compute
+//! pass spawns and integrates them each frame, and the scene pass draws them as
+//! instanced camera-facing billboards. Particles are recycled as a ring, so
+//! `capacity` should cover `rate * max lifetime` to keep them from being cut short.
+
+use crate::ecs::{Component, EntityId, Scene};
+use crate::math::Transform;
+use crate::renderer::{Color, RenderLayers, DEPTH_FORMAT};
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+
+/// Largest particle count of a single emitter (bounded by the compute dispatch size)
+pub const MAX_PARTICLES: u32 = 1 << 22;
+
+/// Invocations per compute workgroup (matches `cs_update` in particles.wgsl)
+const WORKGROUP_SIZE: u32 = 64;
+
+/// How particles are blended onto the scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParticleBlend {
+    /// Colors add up, for fire, sparks, and magic
+    #[default]
+    Additive,
+    /// Regular transparency (unsorted), for smoke and dust
+    Alpha,
+}
+
+/// Particle spawner simulated on the GPU
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    /// Number of particles alive at once
+    pub capacity: u32,
+    /// Particles spawned per second
+    pub rate: f32,
+    /// Minimum and maximum particle lifetime in seconds
+    pub lifetime: (f32, f32),
+    /// Minimum and maximum launch speed
+    pub speed: (f32, f32),
+    /// Launch direction, relative to the entity's rotation
+    pub direction: Vec3,
+    /// Half-angle of the launch cone in radians
+    pub spread: f32,
+    /// Constant acceleration applied to every particle
+    pub gravity: Vec3,
+    pub start_size: f32,
+    pub end_size: f32,
+    pub start_color: Color,
+    pub end_color: Color,
+    pub blend: ParticleBlend,
+    /// Spawn new particles (existing ones finish their lifetime either way)
+    pub emitting: bool,
+    accumulator: f32,
+    pending: u32,
+    cursor: u32,
+    step: EmitterStep,
+}
+
+impl Component for ParticleEmitter {}
+
+/// Spawning and timing for the next simulation step
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct EmitterStep {
+    spawn_start: u32,
+    spawn_count: u32,
+    delta: f32,
+    seed: u32,
+}
+
+impl ParticleEmitter {
+    /// Create an emitter spawning `rate` white particles per second, launched upwards
+    pub fn new(capacity: u32, rate: f32) -> Self {
+        Self {
+            capacity: capacity.clamp(1, MAX_PARTICLES),
+            rate,
+            lifetime: (1.0, 2.0),
+            speed: (1.0, 2.0),
+            direction: Vec3::Y,
+            spread: 0.3,
+            gravity: Vec3::ZERO,
+            start_size: 0.1,
+            end_size: 0.0,
+            start_color: Color::WHITE,
+            end_color: Color::new(1.0, 1.0, 1.0, 0.0),
+            blend: ParticleBlend::Additive,
+            emitting: true,
+            accumulator: 0.0,
+            pending: 0,
+            cursor: 0,
+            step: EmitterStep::default(),
+        }
+    }
+
+    /// Set the minimum and maximum lifetime
+    pub fn with_lifetime(mut self, min: f32, max: f32) -> Self {
+        self.lifetime = (min, max.max(min));
+        self
+    }
+
+    /// Set the launch direction, cone half-angle, and speed range
+    pub fn with_velocity(mut self, direction: Vec3, spread: f32, min_speed: f32, max_speed: f32) -> Self {
+        self.direction = direction;
+        self.spread = spread;
+        self.speed = (min_speed, max_speed.max(min_speed));
+        self
+    }
+
+    /// Set the constant acceleration
+    pub fn with_gravity(mut self, gravity: Vec3) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    /// Set the size at birth and death
+    pub fn with_size(mut self, start: f32, end: f32) -> Self {
+        self.start_size = start;
+        self.end_size = end;
+        self
+    }
+
+    /// Set the color at birth and death
+    pub fn with_colors(mut self, start: Color, end: Color) -> Self {
+        self.start_color = start;
+        self.end_color = end;
+        self
+    }
+
+    /// Set the blend mode
+    pub fn with_blend(mut self, blend: ParticleBlend) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Spawn `count` extra particles on the next update, even if not emitting
+    pub fn burst(&mut self, count: u32) {
+        self.pending = self.pending.saturating_add(count);
+    }
+
+    /// Advance spawning by `delta` seconds
+    pub fn update(&mut self, delta: f32) {
+        if self.emitting {
+            self.accumulator += self.rate.max(0.0) * delta;
+        }
+        let spawned = self.accumulator.floor();
+        self.accumulator -= spawned;
+
+        let capacity = self.particle_count();
+        let count = (spawned as u32).saturating_add(self.pending).min(capacity);
+        self.pending = 0;
+        self.cursor %= capacity;
+        self.step = EmitterStep {
+            spawn_start: self.cursor,
+            spawn_count: count,
+            delta,
+            seed: self.step.seed.wrapping_add(1),
+        };
+        self.cursor = (self.cursor + count) % capacity;
+    }
+
+    /// Get the capacity limited to `1..=MAX_PARTICLES`
+    fn particle_count(&self) -> u32 {
+        self.capacity.clamp(1, MAX_PARTICLES)
+    }
+
+    fn to_uniform(&self, transform: &Transform) -> EmitterUniform {
+        let direction = (transform.rotation * self.direction).normalize_or_zero();
+        EmitterUniform {
+            origin: transform.position.extend(self.spread).to_array(),
+            direction: direction.extend(0.0).to_array(),
+            gravity: self.gravity.extend(self.step.delta).to_array(),
+            start_color: self.start_color.to_array(),
+            end_color: self.end_color.to_array(),
+            shape: [self.start_size, self.end_size, self.speed.0, self.speed.1],
+            lifetime: [self.lifetime.0, self.lifetime.1, 0.0, 0.0],
+            spawn: [self.step.spawn_start, self.step.spawn_count, self.particle_count(), self.step.seed],
+        }
+    }
+}
+
+/// Advance every emitter's spawning (call once per frame)
+pub fn update_particle_emitters(scene: &mut Scene, delta: f32) {
+    for emitter in scene.query_mut::<&mut ParticleEmitter>() {
+        emitter.update(delta);
+    }
+}
+
+/// A single particle as stored on the GPU (`Particle` in particles.wgsl)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct GpuParticle {
+    position: [f32; 3],
+    age: f32,
+    velocity: [f32; 3],
+    lifetime: f32,
+}
+
+/// Emitter parameters for one simulation step (`EmitterParams` in particles.wgsl)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct EmitterUniform {
+    /// World position (xyz) and cone half-angle (w)
+    origin: [f32; 4],
+    direction: [f32; 4],
+    /// Acceleration (xyz) and time step (w)
+    gravity: [f32; 4],
+    start_color: [f32; 4],
+    end_color: [f32; 4],
+    /// Start size, end size, min speed, max speed
+    shape: [f32; 4],
+    /// Min and max lifetime
+    lifetime: [f32; 4],
+    /// Spawn start, spawn count, capacity, random seed
+    spawn: [u32; 4],
+}
+
+/// Camera data for the billboards (`ParticleCamera` in particles.wgsl)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct ParticleCameraUniform {
+    view_proj: [[f32; 4]; 4],
+    right: [f32; 4],
+    up: [f32; 4],
+}
+
+/// Buffers and bind groups of one emitter
+struct GpuEmitter {
+    capacity: u32,
+    blend: ParticleBlend,
+    layers: RenderLayers,
+    params: wgpu::Buffer,
+    compute_bind_group: wgpu::BindGroup,
+    render_bind_group: wgpu::BindGroup,
+}
+
+/// GPU pipelines simulating and drawing every particle emitter in the scene
+pub(crate) struct ParticlePipeline {
+    update_pipeline: wgpu::ComputePipeline,
+    additive_pipeline: wgpu::RenderPipeline,
+    alpha_pipeline: wgpu::RenderPipeline,
+    compute_layout: wgpu::BindGroupLayout,
+    render_layout: wgpu::BindGroupLayout,
+    camera_buffer: wgpu::Buffer,
+    emitters: HashMap<EntityId, GpuEmitter>,
+}
+
+impl ParticlePipeline {
+    pub(crate) fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Camera Buffer"),
+            contents: bytemuck::cast_slice(&[ParticleCameraUniform::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let buffer_entry = |binding, visibility, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        // Vertex shaders can't write storage buffers, so compute and render bind the
+        // particles separately
+        let compute_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particle_compute_bind_group_layout"),
+            entries: &[
+                buffer_entry(0, wgpu::ShaderStages::COMPUTE, wgpu::BufferBindingType::Uniform),
+                buffer_entry(
+                    1,
+                    wgpu::ShaderStages::COMPUTE,
+                    wgpu::BufferBindingType::Storage { read_only: false },
+                ),
+            ],
+        });
+        let render_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particle_render_bind_group_layout"),
+            entries: &[
+                buffer_entry(0, wgpu::ShaderStages::VERTEX, wgpu::BufferBindingType::Uniform),
+                buffer_entry(
+                    2,
+                    wgpu::ShaderStages::VERTEX,
+                    wgpu::BufferBindingType::Storage { read_only: true },
+                ),
+                buffer_entry(3, wgpu::ShaderStages::VERTEX, wgpu::BufferBindingType::Uniform),
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Particle Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/particles.wgsl").into()),
+        });
+
+        let compute_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle Update Pipeline Layout"),
+            bind_group_layouts: &[&compute_layout],
+            push_constant_ranges: &[],
+        });
+        let update_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Particle Update Pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &shader,
+            entry_point: "cs_update",
+            compilation_options: Default::default(),
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle Render Pipeline Layout"),
+            bind_group_layouts: &[&render_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label: &str, blend: wgpu::BlendState| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&render_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
+        };
+
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::OVER,
+        };
+        let additive_pipeline = create_pipeline("Particle Additive Pipeline", additive);
+        let alpha_pipeline = create_pipeline("Particle Alpha Pipeline", wgpu::BlendState::ALPHA_BLENDING);
+
+        Self {
+            update_pipeline,
+            additive_pipeline,
+            alpha_pipeline,
+            compute_layout,
+            render_layout,
+            camera_buffer,
+            emitters: HashMap::new(),
+        }
+    }
+
+    fn create_emitter(&self, device: &wgpu::Device, capacity: u32, blend: ParticleBlend) -> GpuEmitter {
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Emitter Buffer"),
+            contents: bytemuck::cast_slice(&[EmitterUniform::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        // Zeroed particles have no lifetime left, so they start dead
+        let particles = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Buffer"),
+            size: (capacity as usize * std::mem::size_of::<GpuParticle>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particle_compute_bind_group"),
+            layout: &self.compute_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: particles.as_entire_binding(),
+                },
+            ],
+        });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particle_render_bind_group"),
+            layout: &self.render_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: particles.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.camera_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        GpuEmitter {
+            capacity,
+            blend,
+            layers: RenderLayers::DEFAULT,
+            params,
+            compute_bind_group,
+            render_bind_group,
+        }
+    }
+
+    /// Upload this frame's emitter parameters and camera, creating buffers for new emitters
+    pub(crate) fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, scene: &Scene, view: Mat4, view_proj: Mat4) {
+        let mut live = Vec::new();
+        for entity in scene.active_entities() {
+            let (Some(emitter), Some(transform)) =
+                (entity.get_component::<ParticleEmitter>(), entity.get_component::<Transform>())
+            else {
+                continue;
+            };
+            let stale = self
+                .emitters
+                .get(&entity.id())
+                .is_none_or(|gpu| gpu.capacity != emitter.particle_count());
+            if stale {
+                let gpu = self.create_emitter(device, emitter.particle_count(), emitter.blend);
+                self.emitters.insert(entity.id(), gpu);
+            }
+            let gpu = self.emitters.get_mut(&entity.id()).expect("emitter was just inserted");
+            gpu.blend = emitter.blend;
+            gpu.layers = entity.get_component::<RenderLayers>().copied().unwrap_or_default();
+            queue.write_buffer(&gpu.params, 0, bytemuck::cast_slice(&[emitter.to_uniform(transform)]));
+            live.push(entity.id());
+        }
+        self.emitters.retain(|id, _| live.contains(id));
+
+        // The view matrix's rows are the camera axes in world space
+        let camera = ParticleCameraUniform {
+            view_proj: view_proj.to_cols_array_2d(),
+            right: view.row(0).truncate().extend(0.0).to_array(),
+            up: view.row(1).truncate().extend(0.0).to_array(),
+        };
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[camera]));
+    }
+
+    /// Whether there are emitters to simulate and draw
+    pub(crate) fn is_empty(&self) -> bool {
+        self.emitters.is_empty()
+    }
+
+    /// Record the compute pass spawning and integrating every emitter's particles
+    pub(crate) fn simulate(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.emitters.is_empty() {
+            return;
+        }
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Particle Update Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.update_pipeline);
+        for emitter in self.emitters.values() {
+            pass.set_bind_group(0, &emitter.compute_bind_group, &[]);
+            pass.dispatch_workgroups(emitter.capacity.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+    }
+
+    /// Record the billboards of the emitters on `layers` into the scene pass
+    pub(crate) fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, layers: RenderLayers) {
+        for emitter in self.emitters.values().filter(|emitter| layers.intersects(emitter.layers)) {
+            let pipeline = match emitter.blend {
+                ParticleBlend::Additive => &self.additive_pipeline,
+                ParticleBlend::Alpha => &self.alpha_pipeline,
+            };
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &emitter.render_bind_group, &[]);
+            pass.draw(0..6, 0..emitter.capacity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emitter_spawns_into_ring() {
+        let mut emitter = ParticleEmitter::new(8, 10.0);
+        emitter.update(0.55);
+        assert_eq!((emitter.step.spawn_start, emitter.step.spawn_count), (0, 5));
+
+        // The leftover half particle carries over to the next step
+        emitter.update(0.55);
+        assert_eq!((emitter.step.spawn_start, emitter.step.spawn_count), (5, 6));
+        assert_eq!(emitter.cursor, 3);
+
+        // Bursts spawn even while stopped, capped at the capacity
+        emitter.emitting = false;
+        emitter.burst(20);
+        emitter.update(0.1);
+        assert_eq!((emitter.step.spawn_start, emitter.step.spawn_count), (3, 8));
+        emitter.update(0.1);
+        assert_eq!(emitter.step.spawn_count, 0);
+
+        assert_eq!(std::mem::size_of::<GpuParticle>(), 32);
+        assert_eq!(std::mem::size_of::<EmitterUniform>(), 128);
+    }
+}
@@ -0,0 +1,540 @@
+//! Grid-based A* pathfinding
+//!
+//! Provides a weighted grid map, A* search with configurable heuristics,
+//! line-of-sight path smoothing, and a [`Pathfinder`] that spreads searches
+//! across frames with a per-frame node budget.
+
+use glam::{IVec2, Vec2};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+/// Cost value marking a cell as not walkable
+pub const BLOCKED: f32 = f32::INFINITY;
+
+/// A grid of movement costs (1.0 = normal ground, [`BLOCKED`] = wall)
+#[derive(Debug, Clone)]
+pub struct GridMap {
+    width: i32,
+    height: i32,
+    costs: Vec<f32>,
+    /// World position of the corner of cell (0, 0)
+    pub origin: Vec2,
+    /// World size of a cell
+    pub cell_size: f32,
+}
+
+impl GridMap {
+    /// Create a new grid with every cell walkable at cost 1.0
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width: width as i32,
+            height: height as i32,
+            costs: vec![1.0; (width * height) as usize],
+            origin: Vec2::ZERO,
+            cell_size: 1.0,
+        }
+    }
+
+    /// Create a grid from rows of characters (`#` is a wall, digits 1-9 are costs)
+    pub fn from_ascii(rows: &[&str]) -> Self {
+        let height = rows.len() as u32;
+        let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0) as u32;
+        let mut grid = Self::new(width, height);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let cost = match c {
+                    '#' => BLOCKED,
+                    d if d.is_ascii_digit() && d != '0' => d.to_digit(10).unwrap() as f32,
+                    _ => 1.0,
+                };
+                grid.set_cost(IVec2::new(x as i32, y as i32), cost);
+            }
+        }
+        grid
+    }
+
+    /// Get the grid width in cells
+    pub fn width(&self) -> u32 {
+        self.width as u32
+    }
+
+    /// Get the grid height in cells
+    pub fn height(&self) -> u32 {
+        self.height as u32
+    }
+
+    /// Check if a cell is inside the grid
+    pub fn in_bounds(&self, cell: IVec2) -> bool {
+        cell.x >= 0 && cell.y >= 0 && cell.x < self.width && cell.y < self.height
+    }
+
+    fn index(&self, cell: IVec2) -> usize {
+        (cell.y * self.width + cell.x) as usize
+    }
+
+    /// Get the movement cost of a cell (out of bounds cells are blocked)
+    pub fn cost(&self, cell: IVec2) -> f32 {
+        if self.in_bounds(cell) {
+            self.costs[self.index(cell)]
+        } else {
+            BLOCKED
+        }
+    }
+
+    /// Set the movement cost of a cell
+    pub fn set_cost(&mut self, cell: IVec2, cost: f32) {
+        if self.in_bounds(cell) {
+            let index = self.index(cell);
+            self.costs[index] = cost;
+        }
+    }
+
+    /// Check if a cell can be walked on
+    pub fn is_walkable(&self, cell: IVec2) -> bool {
+        self.cost(cell).is_finite()
+    }
+
+    /// Mark a cell as walkable (cost 1.0) or blocked
+    pub fn set_walkable(&mut self, cell: IVec2, walkable: bool) {
+        self.set_cost(cell, if walkable { 1.0 } else { BLOCKED });
+    }
+
+    /// Convert a world position to the cell containing it
+    pub fn world_to_cell(&self, position: Vec2) -> IVec2 {
+        ((position - self.origin) / self.cell_size).floor().as_ivec2()
+    }
+
+    /// Get the world position of a cell's center
+    pub fn cell_to_world(&self, cell: IVec2) -> Vec2 {
+        self.origin + (cell.as_vec2() + Vec2::splat(0.5)) * self.cell_size
+    }
+
+    /// Check if a straight line between two cells only crosses walkable cells
+    pub fn line_of_sight(&self, from: IVec2, to: IVec2) -> bool {
+        // Supercover traversal: visits every cell the segment between centers touches
+        let delta = to - from;
+        let steps = delta.abs();
+        let sign = delta.signum();
+        let mut cell = from;
+        let (mut ix, mut iy) = (0, 0);
+
+        if !self.is_walkable(cell) {
+            return false;
+        }
+        while ix < steps.x || iy < steps.y {
+            let decision = (1 + 2 * ix) * steps.y - (1 + 2 * iy) * steps.x;
+            if decision == 0 {
+                // Passing exactly through a corner touches both neighbours
+                if !self.is_walkable(cell + IVec2::new(sign.x, 0))
+                    || !self.is_walkable(cell + IVec2::new(0, sign.y))
+                {
+                    return false;
+                }
+                cell += sign;
+                ix += 1;
+                iy += 1;
+            } else if decision < 0 {
+                cell.x += sign.x;
+                ix += 1;
+            } else {
+                cell.y += sign.y;
+                iy += 1;
+            }
+            if !self.is_walkable(cell) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Distance estimate used to guide A*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    /// |dx| + |dy|, for 4-directional movement
+    Manhattan,
+    /// Straight-line distance
+    Euclidean,
+    /// Exact distance for 8-directional movement with diagonal cost √2
+    Octile,
+    /// max(|dx|, |dy|), for 8-directional movement with diagonal cost 1
+    Chebyshev,
+    /// No estimate (Dijkstra's algorithm)
+    Zero,
+}
+
+impl Heuristic {
+    /// Estimate the cost between two cells
+    pub fn estimate(&self, from: IVec2, to: IVec2) -> f32 {
+        let d = (to - from).abs().as_vec2();
+        match self {
+            Heuristic::Manhattan => d.x + d.y,
+            Heuristic::Euclidean => d.length(),
+            Heuristic::Octile => d.max_element() + (std::f32::consts::SQRT_2 - 1.0) * d.min_element(),
+            Heuristic::Chebyshev => d.max_element(),
+            Heuristic::Zero => 0.0,
+        }
+    }
+}
+
+/// Search settings
+#[derive(Debug, Clone, Copy)]
+pub struct PathConfig {
+    pub heuristic: Heuristic,
+    /// Allow diagonal moves
+    pub allow_diagonal: bool,
+    /// Allow diagonal moves past the corner of a blocked cell
+    pub cut_corners: bool,
+    /// Heuristic multiplier; values above 1.0 trade optimality for speed
+    pub heuristic_weight: f32,
+    /// Give up after expanding this many nodes
+    pub max_nodes: usize,
+}
+
+impl Default for PathConfig {
+    fn default() -> Self {
+        Self {
+            heuristic: Heuristic::Octile,
+            allow_diagonal: true,
+            cut_corners: false,
+            heuristic_weight: 1.0,
+            max_nodes: 100_000,
+        }
+    }
+}
+
+const NEIGHBORS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+/// Open list entry ordered by lowest f-score
+#[derive(Debug, Clone, Copy)]
+struct OpenNode {
+    cell: IVec2,
+    f: f32,
+    g: f32,
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed for a min-heap; prefer deeper nodes on ties
+        other
+            .f
+            .total_cmp(&self.f)
+            .then_with(|| self.g.total_cmp(&other.g))
+    }
+}
+
+/// State of a search in progress
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchStatus {
+    /// Still searching
+    Pending,
+    /// A path was found (cells from start to goal, inclusive)
+    Found(Vec<IVec2>),
+    /// No path exists (or the node limit was reached)
+    NotFound,
+}
+
+/// An A* search that can be advanced a few nodes at a time
+#[derive(Debug, Clone)]
+pub struct AStarSearch {
+    start: IVec2,
+    goal: IVec2,
+    config: PathConfig,
+    open: BinaryHeap<OpenNode>,
+    came_from: HashMap<IVec2, IVec2>,
+    g_score: HashMap<IVec2, f32>,
+    expanded: usize,
+    status: SearchStatus,
+}
+
+impl AStarSearch {
+    /// Start a new search
+    pub fn new(grid: &GridMap, start: IVec2, goal: IVec2, config: PathConfig) -> Self {
+        let mut search = Self {
+            start,
+            goal,
+            config,
+            open: BinaryHeap::new(),
+            came_from: HashMap::new(),
+            g_score: HashMap::new(),
+            expanded: 0,
+            status: SearchStatus::Pending,
+        };
+
+        if !grid.is_walkable(start) || !grid.is_walkable(goal) {
+            search.status = SearchStatus::NotFound;
+        } else {
+            search.g_score.insert(start, 0.0);
+            search.open.push(OpenNode {
+                cell: start,
+                f: search.h(start),
+                g: 0.0,
+            });
+        }
+        search
+    }
+
+    fn h(&self, cell: IVec2) -> f32 {
+        self.config.heuristic.estimate(cell, self.goal) * self.config.heuristic_weight
+    }
+
+    /// Get the current status
+    pub fn status(&self) -> &SearchStatus {
+        &self.status
+    }
+
+    /// Get the number of nodes expanded so far
+    pub fn nodes_expanded(&self) -> usize {
+        self.expanded
+    }
+
+    /// Expand up to `budget` nodes; returns the number actually expanded
+    pub fn step(&mut self, grid: &GridMap, budget: usize) -> usize {
+        let mut used = 0;
+        while used < budget && self.status == SearchStatus::Pending {
+            let Some(node) = self.open.pop() else {
+                self.status = SearchStatus::NotFound;
+                break;
+            };
+            // Skip stale heap entries
+            if node.g > self.g_score.get(&node.cell).copied().unwrap_or(f32::INFINITY) {
+                continue;
+            }
+
+            used += 1;
+            self.expanded += 1;
+
+            if node.cell == self.goal {
+                self.status = SearchStatus::Found(self.reconstruct());
+                break;
+            }
+            if self.expanded >= self.config.max_nodes {
+                self.status = SearchStatus::NotFound;
+                break;
+            }
+
+            let count = if self.config.allow_diagonal { 8 } else { 4 };
+            for offset in &NEIGHBORS[..count] {
+                let next = node.cell + *offset;
+                let cell_cost = grid.cost(next);
+                if !cell_cost.is_finite() {
+                    continue;
+                }
+
+                let diagonal = offset.x != 0 && offset.y != 0;
+                if diagonal && !self.config.cut_corners {
+                    let side_a = grid.is_walkable(node.cell + IVec2::new(offset.x, 0));
+                    let side_b = grid.is_walkable(node.cell + IVec2::new(0, offset.y));
+                    if !side_a || !side_b {
+                        continue;
+                    }
+                }
+
+                let step = if diagonal { std::f32::consts::SQRT_2 } else { 1.0 };
+                let g = node.g + step * cell_cost;
+                if g < self.g_score.get(&next).copied().unwrap_or(f32::INFINITY) {
+                    self.g_score.insert(next, g);
+                    self.came_from.insert(next, node.cell);
+                    self.open.push(OpenNode {
+                        cell: next,
+                        f: g + self.h(next),
+                        g,
+                    });
+                }
+            }
+        }
+        used
+    }
+
+    fn reconstruct(&self) -> Vec<IVec2> {
+        let mut path = vec![self.goal];
+        let mut cell = self.goal;
+        while cell != self.start {
+            cell = self.came_from[&cell];
+            path.push(cell);
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Find a path between two cells (blocking until the search finishes)
+pub fn find_path(grid: &GridMap, start: IVec2, goal: IVec2, config: &PathConfig) -> Option<Vec<IVec2>> {
+    let mut search = AStarSearch::new(grid, start, goal, *config);
+    search.step(grid, usize::MAX);
+    match search.status {
+        SearchStatus::Found(path) => Some(path),
+        _ => None,
+    }
+}
+
+/// Remove waypoints that can be skipped with a straight walkable line
+pub fn smooth_path(grid: &GridMap, path: &[IVec2]) -> Vec<IVec2> {
+    if path.len() <= 2 {
+        return path.to_vec();
+    }
+
+    let mut smoothed = vec![path[0]];
+    let mut anchor = 0;
+    while anchor < path.len() - 1 {
+        // Furthest waypoint directly visible from the anchor
+        let mut next = anchor + 1;
+        for candidate in (anchor + 2..path.len()).rev() {
+            if grid.line_of_sight(path[anchor], path[candidate]) {
+                next = candidate;
+                break;
+            }
+        }
+        smoothed.push(path[next]);
+        anchor = next;
+    }
+    smoothed
+}
+
+/// Identifier of a queued path request
+pub type PathRequestId = u64;
+
+/// Runs queued path requests incrementally within a per-frame node budget
+pub struct Pathfinder {
+    config: PathConfig,
+    smooth: bool,
+    queue: VecDeque<(PathRequestId, AStarSearch)>,
+    results: HashMap<PathRequestId, SearchStatus>,
+    next_id: PathRequestId,
+}
+
+impl Pathfinder {
+    /// Create a new pathfinder
+    pub fn new(config: PathConfig) -> Self {
+        Self {
+            config,
+            smooth: false,
+            queue: VecDeque::new(),
+            results: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Smooth finished paths with [`smooth_path`]
+    pub fn set_smoothing(&mut self, smooth: bool) {
+        self.smooth = smooth;
+    }
+
+    /// Queue a path request
+    pub fn request(&mut self, grid: &GridMap, start: IVec2, goal: IVec2) -> PathRequestId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.queue.push_back((id, AStarSearch::new(grid, start, goal, self.config)));
+        id
+    }
+
+    /// Cancel a request and drop its result
+    pub fn cancel(&mut self, id: PathRequestId) {
+        self.queue.retain(|(request, _)| *request != id);
+        self.results.remove(&id);
+    }
+
+    /// Get the number of requests still being searched
+    pub fn pending_count(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Advance queued searches, expanding at most `node_budget` nodes in total
+    pub fn update(&mut self, grid: &GridMap, node_budget: usize) {
+        let mut budget = node_budget;
+        while budget > 0 {
+            let Some((id, search)) = self.queue.front_mut() else {
+                break;
+            };
+            budget -= search.step(grid, budget).min(budget);
+
+            if *search.status() != SearchStatus::Pending {
+                let id = *id;
+                let (_, search) = self.queue.pop_front().unwrap();
+                let status = match search.status {
+                    SearchStatus::Found(path) if self.smooth => SearchStatus::Found(smooth_path(grid, &path)),
+                    status => status,
+                };
+                self.results.insert(id, status);
+            }
+        }
+    }
+
+    /// Get the status of a request; finished results are removed when taken
+    pub fn poll(&mut self, id: PathRequestId) -> SearchStatus {
+        if self.queue.iter().any(|(request, _)| *request == id) {
+            return SearchStatus::Pending;
+        }
+        self.results.remove(&id).unwrap_or(SearchStatus::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_path_around_wall() {
+        let grid = GridMap::from_ascii(&[
+            ".....",
+            ".###.",
+            ".#...",
+            ".#.#.",
+            "...#.",
+        ]);
+        let start = IVec2::new(2, 2);
+        let goal = IVec2::new(4, 4);
+        let path = find_path(&grid, start, goal, &PathConfig::default()).unwrap();
+
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.iter().all(|cell| grid.is_walkable(*cell)));
+        assert!(find_path(&grid, start, IVec2::new(1, 1), &PathConfig::default()).is_none());
+    }
+
+    #[test]
+    fn test_incremental_search_and_smoothing() {
+        let grid = GridMap::new(32, 32);
+        let mut pathfinder = Pathfinder::new(PathConfig {
+            allow_diagonal: false,
+            heuristic: Heuristic::Manhattan,
+            ..Default::default()
+        });
+        pathfinder.set_smoothing(true);
+        let id = pathfinder.request(&grid, IVec2::ZERO, IVec2::new(20, 10));
+
+        pathfinder.update(&grid, 5);
+        assert_eq!(pathfinder.poll(id), SearchStatus::Pending);
+
+        for _ in 0..100 {
+            pathfinder.update(&grid, 5);
+        }
+        assert_eq!(
+            pathfinder.poll(id),
+            SearchStatus::Found(vec![IVec2::ZERO, IVec2::new(20, 10)])
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Physically-based materials (metallic/roughness workflow)
+//!
+//! [`PbrMaterial`] follows the glTF 2.0 metallic-roughness model: factors are
+//! multiplied by the optional textures, with roughness read from the green channel
+//! and metallic from the blue channel of the metallic-roughness texture. Normal maps
+//! are tangent-space and green-up; load them and metallic-roughness maps with
+//! `ResourceManager::load_texture_linear`. Create the GPU material with
+//! `Renderer::create_pbr_material` and assign the returned handle with
+//! `MeshRenderer::with_material`.
+
+use crate::renderer::Color;
+use crate::resource::TextureHandle;
+use bytemuck::{Pod, Zeroable};
+
+/// Base color, metallic, roughness, and emissive inputs for PBR shading
+#[derive(Debug, Clone, Copy)]
+pub struct PbrMaterial {
+    pub base_color: Color,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Emitted light (rgb), unaffected by lighting
+    pub emissive: Color,
+    pub base_color_texture: Option<TextureHandle>,
+    /// Roughness in the green channel, metallic in the blue channel
+    pub metallic_roughness_texture: Option<TextureHandle>,
+    pub emissive_texture: Option<TextureHandle>,
+    pub normal_texture: Option<TextureHandle>,
+    /// Strength of the normal map's bumps (1.0 = as authored)
+    pub normal_scale: f32,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: Color::WHITE,
+            metallic: 0.0,
+            roughness: 0.5,
+            emissive: Color::BLACK,
+            base_color_texture: None,
+            metallic_roughness_texture: None,
+            emissive_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+        }
+    }
+}
+
+impl PbrMaterial {
+    /// Create an untextured material
+    pub fn new(base_color: Color, metallic: f32, roughness: f32) -> Self {
+        Self {
+            base_color,
+            metallic,
+            roughness,
+            ..Default::default()
+        }
+    }
+
+    /// Set the emissive color
+    pub fn with_emissive(mut self, emissive: Color) -> Self {
+        self.emissive = emissive;
+        self
+    }
+
+    /// Set the base color texture
+    pub fn with_base_color_texture(mut self, texture: TextureHandle) -> Self {
+        self.base_color_texture = Some(texture);
+        self
+    }
+
+    /// Set the metallic-roughness texture
+    pub fn with_metallic_roughness_texture(mut self, texture: TextureHandle) -> Self {
+        self.metallic_roughness_texture = Some(texture);
+        self
+    }
+
+    /// Set the emissive texture
+    pub fn with_emissive_texture(mut self, texture: TextureHandle) -> Self {
+        self.emissive_texture = Some(texture);
+        self
+    }
+
+    /// Set the tangent-space normal map
+    pub fn with_normal_texture(mut self, texture: TextureHandle, scale: f32) -> Self {
+        self.normal_texture = Some(texture);
+        self.normal_scale = scale;
+        self
+    }
+
+    /// Get the textures in binding order (base color, metallic-roughness, emissive, normal)
+    pub(crate) fn textures(&self) -> [Option<TextureHandle>; 4] {
+        [
+            self.base_color_texture,
+            self.metallic_roughness_texture,
+            self.emissive_texture,
+            self.normal_texture,
+        ]
+    }
+
+    pub(crate) fn to_uniform(self) -> PbrUniform {
+        PbrUniform {
+            base_color: self.base_color.to_array(),
+            emissive: [self.emissive.r, self.emissive.g, self.emissive.b, 0.0],
+            // Very low roughness makes the GGX highlight vanish
+            factors: [
+                self.metallic.clamp(0.0, 1.0),
+                self.roughness.clamp(0.04, 1.0),
+                self.normal_scale,
+                0.0,
+            ],
+        }
+    }
+}
+
+/// PBR factors as laid out in the material uniform (`PbrParams` in pbr.wgsl)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(crate) struct PbrUniform {
+    base_color: [f32; 4],
+    emissive: [f32; 4],
+    /// Metallic (x), roughness (y), and normal scale (z)
+    factors: [f32; 4],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pbr_uniform_clamps_factors() {
+        let uniform = PbrMaterial::new(Color::RED, 1.5, 0.0)
+            .with_emissive(Color::rgb(0.0, 2.0, 0.0))
+            .to_uniform();
+        assert_eq!(uniform.base_color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(uniform.emissive, [0.0, 2.0, 0.0, 0.0]);
+        assert_eq!(uniform.factors, [1.0, 0.04, 1.0, 0.0]);
+        assert_eq!(std::mem::size_of::<PbrUniform>(), 48);
+    }
+}
@@ -0,0 +1,93 @@
+//! Platform integration for Android and iOS
+//!
+//! Desktop builds are unaffected: assets are plain files relative to the
+//! working directory. With the `android` feature, relative asset paths are
+//! read from the APK's `assets/` folder (call [`set_android_app`] from
+//! `android_main`, or use `Engine::with_android_app`). On iOS, relative paths
+//! resolve against the app bundle's resource directory.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(all(target_os = "android", feature = "android"))]
+pub use winit::platform::android::activity::AndroidApp;
+
+#[cfg(all(target_os = "android", feature = "android"))]
+static ANDROID_APP: std::sync::OnceLock<AndroidApp> = std::sync::OnceLock::new();
+
+/// Check if the engine was built for a mobile platform
+pub fn is_mobile() -> bool {
+    cfg!(any(target_os = "android", target_os = "ios"))
+}
+
+/// Register the Android app handle used for asset loading
+#[cfg(all(target_os = "android", feature = "android"))]
+pub fn set_android_app(app: AndroidApp) {
+    let _ = ANDROID_APP.set(app);
+}
+
+/// Get the Android app handle, if registered
+#[cfg(all(target_os = "android", feature = "android"))]
+pub fn android_app() -> Option<&'static AndroidApp> {
+    ANDROID_APP.get()
+}
+
+/// Get the directory relative asset paths are resolved against on the filesystem
+///
+/// This is the bundle's resource directory on iOS and the working directory elsewhere.
+pub fn asset_dir() -> PathBuf {
+    if cfg!(target_os = "ios") {
+        std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+            .unwrap_or_else(|| PathBuf::from("."))
+    } else {
+        PathBuf::from(".")
+    }
+}
+
+/// Read an asset file, looking inside the APK on Android and the app bundle on iOS
+pub fn read_asset<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, String> {
+    let path = path.as_ref();
+
+    #[cfg(all(target_os = "android", feature = "android"))]
+    if path.is_relative() {
+        if let Some(bytes) = read_apk_asset(path) {
+            return Ok(bytes);
+        }
+    }
+
+    let resolved = if path.is_relative() && !path.exists() {
+        asset_dir().join(path)
+    } else {
+        path.to_path_buf()
+    };
+    std::fs::read(&resolved).map_err(|e| format!("Failed to read asset {:?}: {}", path, e))
+}
+
+#[cfg(all(target_os = "android", feature = "android"))]
+fn read_apk_asset(path: &Path) -> Option<Vec<u8>> {
+    use std::io::Read;
+
+    let app = ANDROID_APP.get()?;
+    let name = std::ffi::CString::new(path.to_str()?.replace('\\', "/")).ok()?;
+    let mut asset = app.asset_manager().open(&name)?;
+    let mut bytes = Vec::new();
+    asset.read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_asset_from_filesystem() {
+        let path = std::env::temp_dir().join(format!("rgame_platform_test_{}.txt", std::process::id()));
+        std::fs::write(&path, b"asset").unwrap();
+        assert_eq!(read_asset(&path).unwrap(), b"asset");
+        std::fs::remove_file(&path).ok();
+
+        assert!(read_asset("definitely/missing/asset.png").is_err());
+        assert!(!is_mobile());
+    }
+}
@@ -0,0 +1,277 @@
+//! Post-processing of the HDR scene color
+//!
+//! The scene is rendered into an `Rgba16Float` target so lighting can exceed 1.0.
+//! Effects in the renderer's post-processing stack run on it in order, then a
+//! tone-mapping pass scales it by the camera's exposure, maps it to the
+//! displayable range, and color grades it (see `color_grading`) onto the surface.
+
+use crate::color_grading::ColorGrading;
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+/// Color format of the HDR scene target
+pub(crate) const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// An effect in the renderer's post-processing stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostEffect {
+    /// Glow around bright areas (see `BloomSettings`)
+    Bloom,
+    /// Darkened screen corners (see `VignetteSettings`)
+    Vignette,
+    /// Animated noise (see `FilmGrainSettings`)
+    FilmGrain,
+    /// Red and blue fringes towards the screen edges (see `ChromaticAberrationSettings`)
+    ChromaticAberration,
+}
+
+impl PostEffect {
+    /// Check if this is one of the effects drawn together by the lens pass
+    pub(crate) fn is_lens(self) -> bool {
+        matches!(
+            self,
+            PostEffect::Vignette | PostEffect::FilmGrain | PostEffect::ChromaticAberration
+        )
+    }
+}
+
+/// Curve mapping HDR scene color to the displayable range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMapping {
+    /// Filmic curve with a soft shoulder and slightly boosted contrast
+    #[default]
+    Aces,
+    /// `c / (1 + c)`, preserving hues but looking flatter
+    Reinhard,
+    /// Clamp to 1.0 (bright areas clip to white)
+    None,
+}
+
+impl ToneMapping {
+    fn shader_mode(self) -> u32 {
+        match self {
+            ToneMapping::Aces => 0,
+            ToneMapping::Reinhard => 1,
+            ToneMapping::None => 2,
+        }
+    }
+}
+
+/// Tone mapping uniform buffer data
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct ToneMapUniform {
+    exposure: f32,
+    mode: u32,
+    /// Blend from the first color grading LUT to the second
+    lut_blend: f32,
+    /// Strength of the color grading (0.0 turns it off)
+    lut_intensity: f32,
+    /// Encode the output as sRGB in the shader, for surfaces without an sRGB format
+    encode_srgb: u32,
+    _padding: [u32; 3],
+}
+
+/// Create the single-sampled HDR target the scene resolves into
+pub(crate) fn create_hdr_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("HDR Color Texture"),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: HDR_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// GPU pipeline resolving the HDR target to the surface as a fullscreen triangle
+pub(crate) struct ToneMapPipeline {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    encode_srgb: bool,
+}
+
+impl ToneMapPipeline {
+    /// Create the pipeline, with the color grading LUTs bound at group 1
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        source: &wgpu::TextureView,
+        grading_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tone Map Buffer"),
+            contents: bytemuck::cast_slice(&[ToneMapUniform::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("tone_map_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Tone Map Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Tone Map Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/tonemap.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Tone Map Pipeline Layout"),
+            bind_group_layouts: &[&layout, grading_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Tone Map Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let bind_group = Self::create_bind_group(device, &layout, &sampler, &buffer, source);
+        Self {
+            pipeline,
+            layout,
+            sampler,
+            buffer,
+            bind_group,
+            // Scene colors are linear; sRGB targets encode them on write, others need it done
+            encode_srgb: !format.is_srgb(),
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        buffer: &wgpu::Buffer,
+        source: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("tone_map_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// Point the pass at a new HDR target (after a resize)
+    pub(crate) fn set_source(&mut self, device: &wgpu::Device, source: &wgpu::TextureView) {
+        self.bind_group = Self::create_bind_group(device, &self.layout, &self.sampler, &self.buffer, source);
+    }
+
+    /// Upload the curve, exposure, and color grading blend for this frame
+    pub(crate) fn update(
+        &self,
+        queue: &wgpu::Queue,
+        tone_mapping: ToneMapping,
+        exposure: f32,
+        grading: Option<&ColorGrading>,
+    ) {
+        let uniform = ToneMapUniform {
+            exposure,
+            mode: tone_mapping.shader_mode(),
+            lut_blend: grading.map_or(0.0, |g| g.blend.clamp(0.0, 1.0)),
+            lut_intensity: grading.map_or(0.0, |g| g.intensity.clamp(0.0, 1.0)),
+            encode_srgb: self.encode_srgb as u32,
+            _padding: [0; 3],
+        };
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Record the fullscreen draw into a pass targeting the surface
+    pub(crate) fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, grading: &'a wgpu::BindGroup) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_bind_group(1, grading, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tone_map_uniform_layout() {
+        // Matches ToneMapUniform in tonemap.wgsl
+        assert_eq!(std::mem::size_of::<ToneMapUniform>(), 32);
+        assert_eq!(ToneMapping::default(), ToneMapping::Aces);
+        let modes = [ToneMapping::Aces, ToneMapping::Reinhard, ToneMapping::None].map(ToneMapping::shader_mode);
+        assert_eq!(modes, [0, 1, 2]);
+    }
+}
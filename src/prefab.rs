@@ -0,0 +1,198 @@
+//! Prefabs: reusable entity templates
+//!
+//! A [`Prefab`] lists the components of an entity and the child prefabs attached to
+//! it. Build one in code with [`Prefab::with`] / [`Prefab::with_child`], or load it
+//! from a JSON file whose components are named through a [`ComponentRegistry`]:
+//!
+//! ```json
+//! {
+//!     "name": "Enemy",
+//!     "components": { "transform": { "position": [0, 0, 0], ... }, "health": 3 },
+//!     "children": [{ "name": "Gun", "components": { ... } }]
+//! }
+//! ```
+//!
+//! `scene.spawn_prefab(&prefab)` creates the entities and links them with
+//! [`Scene::set_parent`], returning the root.
+
+use crate::ecs::{Component, EntityId, Scene};
+use crate::registry::{ComponentRegistry, ComponentValue};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// An entity template with components and child templates
+pub struct Prefab {
+    /// Name given to spawned entities
+    pub name: String,
+    components: Vec<Box<dyn ComponentValue>>,
+    /// Templates spawned as children of this one
+    pub children: Vec<Prefab>,
+}
+
+impl Clone for Prefab {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            components: self.components.iter().map(|c| c.clone_value()).collect(),
+            children: self.children.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Prefab {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Prefab")
+            .field("name", &self.name)
+            .field("components", &self.components.len())
+            .field("children", &self.children)
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct PrefabFile {
+    name: String,
+    #[serde(default)]
+    components: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    children: Vec<PrefabFile>,
+}
+
+impl Prefab {
+    /// Create a template without components
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            components: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Add a component (replacing one of the same type)
+    pub fn with<T: Component + Clone>(mut self, component: T) -> Self {
+        self.insert(Box::new(component));
+        self
+    }
+
+    /// Add a child template
+    pub fn with_child(mut self, child: Prefab) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Number of components on the root entity
+    pub fn component_count(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Parse a prefab from JSON
+    pub fn from_json(json: &str, registry: &ComponentRegistry) -> Result<Self, String> {
+        let file: PrefabFile =
+            serde_json::from_str(json).map_err(|e| format!("Failed to parse prefab: {}", e))?;
+        Self::from_file(file, registry)
+    }
+
+    /// Load a prefab from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P, registry: &ComponentRegistry) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read prefab {:?}: {}", path, e))?;
+        Self::from_json(&json, registry).map_err(|e| format!("{} ({:?})", e, path))
+    }
+
+    fn from_file(file: PrefabFile, registry: &ComponentRegistry) -> Result<Self, String> {
+        let mut prefab = Prefab::new(&file.name);
+        for (name, value) in file.components {
+            prefab.insert(registry.deserialize(&name, value)?);
+        }
+        for child in file.children {
+            prefab.children.push(Self::from_file(child, registry)?);
+        }
+        Ok(prefab)
+    }
+
+    fn insert(&mut self, component: Box<dyn ComponentValue>) {
+        self.components
+            .retain(|c| c.component_type() != component.component_type());
+        self.components.push(component);
+    }
+}
+
+impl Scene {
+    /// Spawn a prefab and its children, returning the root entity
+    pub fn spawn_prefab(&mut self, prefab: &Prefab) -> EntityId {
+        let id = self.create_entity(prefab.name.clone());
+        if let Some(mut entity) = self.get_entity_mut(id) {
+            for component in &prefab.components {
+                component.insert_into(&mut entity);
+            }
+        }
+        for child in &prefab.children {
+            let child_id = self.spawn_prefab(child);
+            self.set_parent(child_id, id);
+        }
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Transform;
+    use glam::Vec3;
+    use serde::Serialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Health(i32);
+    impl Component for Health {}
+
+    #[test]
+    fn test_spawn_prefab_from_code() {
+        let enemy = Prefab::new("Enemy")
+            .with(Transform::from_position(Vec3::new(1.0, 0.0, 0.0)))
+            .with(Health(3))
+            .with(Health(5))
+            .with_child(Prefab::new("Gun").with(Transform::new()));
+        assert_eq!(enemy.component_count(), 2);
+
+        let mut scene = Scene::new("Test".to_string());
+        let first = scene.spawn_prefab(&enemy);
+        let second = scene.spawn_prefab(&enemy);
+        assert_ne!(first, second);
+        assert_eq!(scene.entity_count(), 4);
+
+        let root = scene.get_entity(first).unwrap();
+        assert_eq!(root.name(), "Enemy");
+        assert_eq!(root.get_component::<Health>(), Some(&Health(5)));
+        let gun = scene.children(first)[0];
+        assert_eq!(scene.get_entity(gun).unwrap().name(), "Gun");
+        assert_eq!(scene.parent(gun), Some(first));
+    }
+
+    #[test]
+    fn test_load_prefab_json() {
+        let mut registry = ComponentRegistry::new();
+        registry.register::<Transform>("transform");
+        registry.register::<Health>("health");
+
+        let json = r#"{
+            "name": "Pickup",
+            "components": {
+                "health": 10,
+                "transform": { "position": [0.0, 2.0, 0.0], "rotation": [0.0, 0.0, 0.0, 1.0], "scale": [1.0, 1.0, 1.0] }
+            },
+            "children": [{ "name": "Glow" }]
+        }"#;
+        let prefab = Prefab::from_json(json, &registry).unwrap();
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.spawn_prefab(&prefab);
+
+        assert_eq!(scene.get_component::<Health>(id), Some(&Health(10)));
+        assert_eq!(scene.get_component::<Transform>(id).unwrap().position, Vec3::new(0.0, 2.0, 0.0));
+        assert_eq!(scene.children(id).len(), 1);
+
+        let unknown = r#"{ "name": "Bad", "components": { "mana": 1 } }"#;
+        assert!(Prefab::from_json(unknown, &registry).unwrap_err().contains("mana"));
+    }
+}
@@ -0,0 +1,626 @@
+//! Planar reflections and cubemap reflection probes
+//!
+//! A [`PlanarReflection`] renders the scene every frame from the main camera mirrored
+//! about a plane. Surfaces lying on that plane (floors, water) reflect it sharply: the
+//! PBR shader projects their position into the mirrored view to look up the reflected
+//! color. Geometry behind the plane is clipped by tilting the mirrored camera's near
+//! plane onto the mirror.
+//!
+//! A [`ReflectionProbe`] entity captures its surroundings into a cubemap, one 90 degree
+//! camera per face, and the probe closest to the main camera feeds the PBR specular
+//! term of every other surface. The cubemap's mip levels are progressively blurred
+//! copies, sampled sharp by smooth surfaces and blurry by rough ones. A probe is baked
+//! the first time it is used, again after `Renderer::rebake_reflection_probes`, and
+//! every frame if it is `realtime`.
+//!
+//! Captures draw the sky and meshes only, and don't show reflections themselves.
+
+use crate::ecs::{Component, EntityId};
+use crate::mipmap::{mip_level_count, MipmapGenerator};
+use crate::postprocess::HDR_FORMAT;
+use crate::renderer::{Camera, DEPTH_FORMAT};
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3, Vec4};
+use std::collections::HashMap;
+
+/// A mirror plane reflecting the scene into the surfaces lying on it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlanarReflection {
+    /// Any point on the plane
+    pub point: Vec3,
+    /// Normal of the plane, pointing to the side the reflection is seen from
+    pub normal: Vec3,
+    /// Size of the reflection texture relative to the surface
+    pub resolution_scale: f32,
+}
+
+impl PlanarReflection {
+    /// A mirror through `point` facing `normal`, at half the surface resolution
+    pub fn new(point: Vec3, normal: Vec3) -> Self {
+        Self {
+            point,
+            normal: normal.normalize_or_zero(),
+            resolution_scale: 0.5,
+        }
+    }
+
+    /// A horizontal mirror at `height`, seen from above (floors and water)
+    pub fn horizontal(height: f32) -> Self {
+        Self::new(Vec3::new(0.0, height, 0.0), Vec3::Y)
+    }
+
+    /// The plane's unit normal (xyz) and distance from the origin along it (w)
+    pub fn plane(&self) -> Vec4 {
+        let normal = self.normal.normalize_or_zero();
+        normal.extend(normal.dot(self.point))
+    }
+
+    /// Signed distance of `point` from the plane, positive on the side the normal faces
+    pub fn distance(&self, point: Vec3) -> f32 {
+        let plane = self.plane();
+        plane.truncate().dot(point) - plane.w
+    }
+
+    /// Mirror image of `point`
+    pub fn reflect_point(&self, point: Vec3) -> Vec3 {
+        point - 2.0 * self.distance(point) * self.normal.normalize_or_zero()
+    }
+
+    /// Camera seeing the scene as it appears in the mirror from `camera`
+    pub fn mirror_camera(&self, camera: &Camera) -> Camera {
+        let normal = self.normal.normalize_or_zero();
+        Camera {
+            position: self.reflect_point(camera.position),
+            target: self.reflect_point(camera.target),
+            up: camera.up - 2.0 * camera.up.dot(normal) * normal,
+            ..camera.clone()
+        }
+    }
+
+    /// View-projection matrix of the mirror camera for `camera`, whose near plane lies
+    /// on the mirror so nothing behind it shows up in the reflection
+    pub(crate) fn clipped_view_proj(&self, camera: &Camera) -> Mat4 {
+        let mirror = self.mirror_camera(camera);
+        let view = mirror.view_matrix();
+        let plane = self.plane();
+        let clip_plane = view.inverse().transpose() * plane.truncate().extend(-plane.w);
+        oblique_projection(mirror.projection_matrix(), clip_plane) * view
+    }
+}
+
+/// Replace the near plane of a [0, 1] depth `projection` with `clip_plane`, given in
+/// view space with the visible side positive (Lengyel's oblique near-plane clipping)
+pub(crate) fn oblique_projection(projection: Mat4, clip_plane: Vec4) -> Mat4 {
+    // The far corner of the frustum opposite the plane stays on the far plane
+    let corner = projection.inverse() * Vec4::new(clip_plane.x.signum(), clip_plane.y.signum(), 1.0, 1.0);
+    let row = clip_plane / clip_plane.dot(corner);
+    let mut projection = projection;
+    projection.x_axis.z = row.x;
+    projection.y_axis.z = row.y;
+    projection.z_axis.z = row.z;
+    projection.w_axis.z = row.w;
+    projection
+}
+
+/// Captures the surroundings of its entity's `Transform` into a cubemap that lights
+/// the PBR specular term
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReflectionProbe {
+    /// Width and height of each cubemap face, in pixels
+    pub resolution: u32,
+    /// Capture again every frame, for scenes with moving objects
+    pub realtime: bool,
+}
+
+impl Default for ReflectionProbe {
+    fn default() -> Self {
+        Self {
+            resolution: 128,
+            realtime: false,
+        }
+    }
+}
+
+impl Component for ReflectionProbe {}
+
+/// Forward and up directions of the cameras capturing the six cubemap faces, in the
+/// +X, -X, +Y, -Y, +Z, -Z layer order
+///
+/// A right-handed camera sees each face mirrored compared to the cubemap's own
+/// orientation, so the faces hold the world mirrored along x instead: the shader
+/// samples them with the x of its lookup direction negated.
+const CUBE_FACES: [(Vec3, Vec3); 6] = [
+    (Vec3::NEG_X, Vec3::Y),
+    (Vec3::X, Vec3::Y),
+    (Vec3::Y, Vec3::NEG_Z),
+    (Vec3::NEG_Y, Vec3::Z),
+    (Vec3::Z, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::Y),
+];
+
+/// Camera capturing cubemap layer `face` around `position`
+pub(crate) fn cube_face_camera(position: Vec3, face: usize, near: f32, far: f32) -> Camera {
+    let (forward, up) = CUBE_FACES[face];
+    Camera {
+        up,
+        fov: 90.0,
+        near,
+        far,
+        ..Camera::new(position, position + forward, 1.0)
+    }
+}
+
+/// Reflection uniform buffer data, bound with each camera
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub(crate) struct ReflectionUniform {
+    mirror_view_proj: [[f32; 4]; 4],
+    /// Mirror plane normal (xyz) and distance from the origin (w)
+    plane: [f32; 4],
+    /// Planar reflection enabled (x), probe enabled (y), and the probe's last mip level (z)
+    params: [f32; 4],
+}
+
+impl ReflectionUniform {
+    /// Reflect the planar mirror seen through `mirror_view_proj`, and the probe cubemap
+    /// with `probe_mips` levels
+    pub(crate) fn new(planar: Option<(&PlanarReflection, Mat4)>, probe_mips: Option<u32>) -> Self {
+        let mut uniform = Self::zeroed();
+        if let Some((planar, view_proj)) = planar {
+            uniform.mirror_view_proj = view_proj.to_cols_array_2d();
+            uniform.plane = planar.plane().to_array();
+            uniform.params[0] = 1.0;
+        }
+        if let Some(mips) = probe_mips {
+            uniform.params[1] = 1.0;
+            uniform.params[2] = mips.saturating_sub(1) as f32;
+        }
+        uniform
+    }
+}
+
+/// Which reflections a camera bind group sees
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReflectionBinding {
+    /// The planar reflection and the active probe
+    Main,
+    /// The active probe; the planar reflection is only rendered for the main camera
+    View,
+    /// None, as captures can't sample the textures they render into
+    Capture,
+}
+
+/// A capture to render: the planar reflection or one face of the active probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Capture {
+    Planar,
+    ProbeFace(usize),
+}
+
+/// Multisampled color and depth attachments a capture is drawn with
+pub(crate) struct CaptureTarget {
+    size: (u32, u32),
+    msaa_view: Option<wgpu::TextureView>,
+    depth_view: wgpu::TextureView,
+}
+
+impl CaptureTarget {
+    fn new(device: &wgpu::Device, size: (u32, u32), sample_count: u32) -> Self {
+        let create = |label, format| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size.0,
+                        height: size.1,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        Self {
+            size,
+            msaa_view: (sample_count > 1).then(|| create("Reflection MSAA Texture", HDR_FORMAT)),
+            depth_view: create("Reflection Depth Texture", DEPTH_FORMAT),
+        }
+    }
+
+    pub(crate) fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub(crate) fn depth_view(&self) -> &wgpu::TextureView {
+        &self.depth_view
+    }
+
+    /// Color attachment drawing into `view`, resolving into it when multisampled
+    pub(crate) fn color_attachment<'a>(
+        &'a self,
+        view: &'a wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachment<'a> {
+        let ops = wgpu::Operations {
+            load,
+            store: wgpu::StoreOp::Store,
+        };
+        match &self.msaa_view {
+            Some(msaa_view) => wgpu::RenderPassColorAttachment {
+                view: msaa_view,
+                resolve_target: Some(view),
+                ops,
+            },
+            None => wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops,
+            },
+        }
+    }
+}
+
+/// Sampled color texture of the planar reflection
+struct PlanarTarget {
+    target: CaptureTarget,
+    view: wgpu::TextureView,
+}
+
+/// Cubemap of a reflection probe
+struct ProbeCapture {
+    texture: wgpu::Texture,
+    cube_view: wgpu::TextureView,
+    face_views: Vec<wgpu::TextureView>,
+    target: CaptureTarget,
+    baked: bool,
+}
+
+impl ProbeCapture {
+    fn new(device: &wgpu::Device, resolution: u32, sample_count: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Reflection Probe Texture"),
+            size: wgpu::Extent3d {
+                width: resolution,
+                height: resolution,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: mip_level_count(resolution, resolution),
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let face_views = (0..6)
+            .map(|face| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Reflection Probe Face View"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_mip_level: 0,
+                    mip_level_count: Some(1),
+                    base_array_layer: face,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let cube_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Reflection Probe View"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        Self {
+            texture,
+            cube_view,
+            face_views,
+            target: CaptureTarget::new(device, (resolution, resolution), sample_count),
+            baked: false,
+        }
+    }
+
+    fn resolution(&self) -> u32 {
+        self.texture.width()
+    }
+}
+
+/// Reflection textures, their fallbacks, and the uniforms bound with each camera
+pub(crate) struct Reflections {
+    sampler: wgpu::Sampler,
+    fallback_cube: wgpu::TextureView,
+    fallback_planar: wgpu::TextureView,
+    /// Uniforms by `ReflectionBinding`
+    buffers: [wgpu::Buffer; 3],
+    planar: Option<PlanarTarget>,
+    probes: HashMap<EntityId, ProbeCapture>,
+    active_probe: Option<EntityId>,
+    mipmaps: MipmapGenerator,
+}
+
+impl Reflections {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let fallback = |label, layers, dimension| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: 1,
+                        height: 1,
+                        depth_or_array_layers: layers,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: HDR_FORMAT,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor {
+                    dimension: Some(dimension),
+                    ..Default::default()
+                })
+        };
+        let buffer = |label| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: std::mem::size_of::<ReflectionUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        Self {
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Reflection Sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }),
+            fallback_cube: fallback("Fallback Reflection Cube", 6, wgpu::TextureViewDimension::Cube),
+            fallback_planar: fallback("Fallback Planar Reflection", 1, wgpu::TextureViewDimension::D2),
+            buffers: [
+                buffer("Reflection Buffer"),
+                buffer("View Reflection Buffer"),
+                buffer("Capture Reflection Buffer"),
+            ],
+            planar: None,
+            probes: HashMap::new(),
+            active_probe: None,
+            mipmaps: MipmapGenerator::new(device),
+        }
+    }
+
+    /// Layout entries of the camera bind group for the reflection uniform (3), probe
+    /// cubemap (4), planar reflection (5), and their sampler (6)
+    pub(crate) fn layout_entries() -> [wgpu::BindGroupLayoutEntry; 4] {
+        let texture = |binding, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension,
+                multisampled: false,
+            },
+            count: None,
+        };
+        [
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            texture(4, wgpu::TextureViewDimension::Cube),
+            texture(5, wgpu::TextureViewDimension::D2),
+            wgpu::BindGroupLayoutEntry {
+                binding: 6,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ]
+    }
+
+    /// Camera bind group entries matching `layout_entries`
+    pub(crate) fn entries(&self, binding: ReflectionBinding) -> [wgpu::BindGroupEntry<'_>; 4] {
+        let (cube, planar) = match binding {
+            ReflectionBinding::Main => (self.probe_view(), self.planar.as_ref().map(|p| &p.view)),
+            ReflectionBinding::View => (self.probe_view(), None),
+            ReflectionBinding::Capture => (None, None),
+        };
+        [
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: self.buffers[binding as usize].as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(cube.unwrap_or(&self.fallback_cube)),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::TextureView(planar.unwrap_or(&self.fallback_planar)),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::Sampler(&self.sampler),
+            },
+        ]
+    }
+
+    fn probe_view(&self) -> Option<&wgpu::TextureView> {
+        self.active_probe.and_then(|id| self.probes.get(&id)).map(|probe| &probe.cube_view)
+    }
+
+    /// Write the uniforms of the main camera and the added cameras
+    pub(crate) fn write(&self, queue: &wgpu::Queue, main: ReflectionUniform, view: ReflectionUniform) {
+        queue.write_buffer(&self.buffers[ReflectionBinding::Main as usize], 0, bytemuck::bytes_of(&main));
+        queue.write_buffer(&self.buffers[ReflectionBinding::View as usize], 0, bytemuck::bytes_of(&view));
+    }
+
+    /// Create, resize, or drop (with `None`) the planar reflection texture; returns
+    /// whether camera bind groups must be rebuilt
+    pub(crate) fn prepare_planar(&mut self, device: &wgpu::Device, size: Option<(u32, u32)>, sample_count: u32) -> bool {
+        if self.planar.as_ref().map(|p| p.target.size()) == size {
+            return false;
+        }
+        self.planar = size.map(|size| {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Planar Reflection Texture"),
+                size: wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: HDR_FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            });
+            PlanarTarget {
+                target: CaptureTarget::new(device, size, sample_count),
+                view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            }
+        });
+        true
+    }
+
+    /// Make `active` (a probe entity and its resolution) the probe lighting the scene,
+    /// creating its cubemap if needed, and drop the cubemaps of probes not in `probes`;
+    /// returns whether camera bind groups must be rebuilt
+    pub(crate) fn prepare_probe(
+        &mut self,
+        device: &wgpu::Device,
+        active: Option<(EntityId, u32)>,
+        probes: &[EntityId],
+        sample_count: u32,
+    ) -> bool {
+        self.probes.retain(|id, _| probes.contains(id));
+        let mut changed = self.active_probe != active.map(|(id, _)| id);
+        self.active_probe = active.map(|(id, _)| id);
+        if let Some((id, resolution)) = active {
+            let resolution = resolution.clamp(1, device.limits().max_texture_dimension_2d);
+            if self.probes.get(&id).map(ProbeCapture::resolution) != Some(resolution) {
+                self.probes.insert(id, ProbeCapture::new(device, resolution, sample_count));
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Mip levels of the active probe, once it has been baked
+    pub(crate) fn probe_mips(&self) -> Option<u32> {
+        let probe = self.probes.get(&self.active_probe?)?;
+        probe.baked.then(|| probe.texture.mip_level_count())
+    }
+
+    /// Whether the active probe still has to be captured
+    pub(crate) fn probe_needs_bake(&self) -> bool {
+        self.active_probe
+            .and_then(|id| self.probes.get(&id))
+            .is_some_and(|probe| !probe.baked)
+    }
+
+    /// Mark the active probe's faces as captured, blurring them into its mip levels
+    pub(crate) fn finish_probe(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(probe) = self.active_probe.and_then(|id| self.probes.get_mut(&id)) {
+            self.mipmaps.generate(device, queue, &probe.texture);
+            probe.baked = true;
+        }
+    }
+
+    /// Capture every probe again the next time it is used
+    pub(crate) fn rebake(&mut self) {
+        for probe in self.probes.values_mut() {
+            probe.baked = false;
+        }
+    }
+
+    /// Attachments and color view a capture is drawn into, if it exists
+    pub(crate) fn capture_target(&self, capture: Capture) -> Option<(&CaptureTarget, &wgpu::TextureView)> {
+        match capture {
+            Capture::Planar => self.planar.as_ref().map(|p| (&p.target, &p.view)),
+            Capture::ProbeFace(face) => {
+                let probe = self.probes.get(&self.active_probe?)?;
+                Some((&probe.target, &probe.face_views[face]))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cubemap layer and texture coordinates a direction samples, following the
+    /// cubemap conventions of the graphics APIs
+    fn cube_lookup(dir: Vec3) -> (usize, f32, f32) {
+        let abs = dir.abs();
+        let (face, sc, tc, ma) = if abs.x >= abs.y && abs.x >= abs.z {
+            if dir.x > 0.0 {
+                (0, -dir.z, -dir.y, abs.x)
+            } else {
+                (1, dir.z, -dir.y, abs.x)
+            }
+        } else if abs.y >= abs.z {
+            if dir.y > 0.0 {
+                (2, dir.x, dir.z, abs.y)
+            } else {
+                (3, dir.x, -dir.z, abs.y)
+            }
+        } else if dir.z > 0.0 {
+            (4, dir.x, -dir.y, abs.z)
+        } else {
+            (5, -dir.x, -dir.y, abs.z)
+        };
+        (face, (sc / ma + 1.0) * 0.5, (tc / ma + 1.0) * 0.5)
+    }
+
+    #[test]
+    fn test_cube_faces_match_cubemap_layout() {
+        for face in 0..6 {
+            let camera = cube_face_camera(Vec3::new(1.0, 2.0, 3.0), face, 0.1, 100.0);
+            let inverse = camera.view_proj_matrix().inverse();
+            for (u, v) in [(0.5, 0.5), (0.2, 0.3), (0.9, 0.6)] {
+                // The pixel at (u, v) of the face, sampled the way the shader does
+                let near = inverse.project_point3(Vec3::new(u * 2.0 - 1.0, 1.0 - v * 2.0, 0.0));
+                let far = inverse.project_point3(Vec3::new(u * 2.0 - 1.0, 1.0 - v * 2.0, 1.0));
+                let dir = far - near;
+                let (lookup_face, lookup_u, lookup_v) = cube_lookup(Vec3::new(-dir.x, dir.y, dir.z));
+                assert_eq!(lookup_face, face);
+                assert!((lookup_u - u).abs() < 1e-4 && (lookup_v - v).abs() < 1e-4, "face {}", face);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mirror_camera_clips_behind_the_plane() {
+        let mirror = PlanarReflection::horizontal(1.0);
+        let camera = Camera::new(Vec3::new(0.0, 4.0, 6.0), Vec3::new(0.0, 1.0, 0.0), 1.5);
+        let reflected = mirror.mirror_camera(&camera);
+        assert!(reflected.position.abs_diff_eq(Vec3::new(0.0, -2.0, 6.0), 1e-5));
+        assert!(reflected.target.abs_diff_eq(camera.target, 1e-5));
+
+        // Points on the mirror land where the main camera sees them, mirrored left to right
+        let point = Vec3::new(0.5, 1.0, 2.0);
+        let seen = camera.view_proj_matrix().project_point3(point);
+        let mirrored = reflected.view_proj_matrix().project_point3(point);
+        assert!((seen.x + mirrored.x).abs() < 1e-4 && (seen.y - mirrored.y).abs() < 1e-4);
+
+        // Above the mirror is kept, below (where the mirror camera looks up from) is cut
+        let view_proj = mirror.clipped_view_proj(&camera);
+        let above = view_proj * Vec4::new(0.0, 1.5, 0.0, 1.0);
+        let below = view_proj * Vec4::new(0.0, 0.5, 0.0, 1.0);
+        assert!(above.z >= 0.0 && above.z <= above.w);
+        assert!(below.z < 0.0);
+    }
+}
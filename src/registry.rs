@@ -0,0 +1,137 @@
+//! Component type registry
+//!
+//! Data files refer to components by a stable name (`"transform"`, `"health"`, ...).
+//! A [`ComponentRegistry`] maps those names to Rust types so prefab and scene files
+//! can be turned into components and back.
+
+use crate::ecs::{Component, EntityMut, EntityRef};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::TypeId;
+
+/// A type-erased component value that can be inserted into any number of entities
+pub trait ComponentValue {
+    /// Insert a copy of the value into an entity
+    fn insert_into(&self, entity: &mut EntityMut);
+
+    /// Clone the value behind the box
+    fn clone_value(&self) -> Box<dyn ComponentValue>;
+
+    /// Type of the wrapped component
+    fn component_type(&self) -> TypeId;
+}
+
+impl<T: Component + Clone> ComponentValue for T {
+    fn insert_into(&self, entity: &mut EntityMut) {
+        entity.add_component(self.clone());
+    }
+
+    fn clone_value(&self) -> Box<dyn ComponentValue> {
+        Box::new(self.clone())
+    }
+
+    fn component_type(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+}
+
+type SerializeFn = fn(&EntityRef) -> Option<Result<serde_json::Value, String>>;
+type DeserializeFn = fn(serde_json::Value) -> Result<Box<dyn ComponentValue>, String>;
+
+struct RegisteredComponent {
+    name: String,
+    type_id: TypeId,
+    serialize: SerializeFn,
+    deserialize: DeserializeFn,
+}
+
+fn serialize_value<T: Component + Serialize>(entity: &EntityRef) -> Option<Result<serde_json::Value, String>> {
+    entity
+        .get_component::<T>()
+        .map(|component| serde_json::to_value(component).map_err(|e| e.to_string()))
+}
+
+fn deserialize_value<T>(value: serde_json::Value) -> Result<Box<dyn ComponentValue>, String>
+where
+    T: Component + Clone + DeserializeOwned,
+{
+    let component: T = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    Ok(Box::new(component))
+}
+
+/// A component with a stable name, usually from `#[component(name = "...")]`
+pub trait NamedComponent: Component {
+    /// Name the component is registered under
+    const NAME: &'static str;
+}
+
+/// Maps stable component names to serializable component types
+#[derive(Default)]
+pub struct ComponentRegistry {
+    components: Vec<RegisteredComponent>,
+}
+
+impl ComponentRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a component type under a stable name, replacing any previous registration
+    pub fn register<T>(&mut self, name: &str)
+    where
+        T: Component + Clone + Serialize + DeserializeOwned,
+    {
+        self.components
+            .retain(|c| c.name != name && c.type_id != TypeId::of::<T>());
+        self.components.push(RegisteredComponent {
+            name: name.to_string(),
+            type_id: TypeId::of::<T>(),
+            serialize: serialize_value::<T>,
+            deserialize: deserialize_value::<T>,
+        });
+    }
+
+    /// Register a component type under its [`NamedComponent::NAME`]
+    pub fn register_named<T>(&mut self)
+    where
+        T: NamedComponent + Clone + Serialize + DeserializeOwned,
+    {
+        self.register::<T>(T::NAME);
+    }
+
+    /// Check if a name is registered
+    pub fn contains(&self, name: &str) -> bool {
+        self.components.iter().any(|c| c.name == name)
+    }
+
+    /// Get the name a component type is registered under
+    pub fn name_of<T: Component>(&self) -> Option<&str> {
+        self.components
+            .iter()
+            .find(|c| c.type_id == TypeId::of::<T>())
+            .map(|c| c.name.as_str())
+    }
+
+    /// Build a component from its name and JSON value
+    pub fn deserialize(&self, name: &str, value: serde_json::Value) -> Result<Box<dyn ComponentValue>, String> {
+        let component = self
+            .components
+            .iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| format!("Unknown component '{}'", name))?;
+        (component.deserialize)(value).map_err(|e| format!("Invalid component '{}': {}", name, e))
+    }
+
+    /// Serialize every registered component of an entity, keyed by name
+    pub fn serialize_entity(&self, entity: &EntityRef) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+        let mut components = serde_json::Map::new();
+        for component in &self.components {
+            if let Some(value) = (component.serialize)(entity) {
+                let value = value.map_err(|e| format!("Failed to serialize component '{}': {}", component.name, e))?;
+                components.insert(component.name.clone(), value);
+            }
+        }
+        Ok(components)
+    }
+}
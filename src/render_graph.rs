@@ -0,0 +1,471 @@
+//! Render graph for composing extra render passes
+//!
+//! Passes declare the color and depth attachments they write and the textures they
+//! sample. The graph orders passes so writers run before readers, allocates its
+//! textures at the surface size, and records everything into the frame's encoder.
+//! Passes that don't touch the [`SURFACE`] run before the built-in scene pass (e.g.
+//! shadow maps); passes that do run after it (post-processing, UI).
+
+use crate::renderer::Color;
+use std::collections::HashMap;
+
+/// Name of the swapchain image in pass descriptions
+pub const SURFACE: &str = "surface";
+
+/// A texture owned by the graph, sized relative to the surface
+#[derive(Debug, Clone, Copy)]
+pub struct TextureDesc {
+    pub format: wgpu::TextureFormat,
+    /// Size as a fraction of the surface size (1.0 = full resolution)
+    pub scale: f32,
+    /// Use a fixed size instead of scaling with the surface
+    pub fixed_size: Option<(u32, u32)>,
+}
+
+impl TextureDesc {
+    /// Create a full-resolution texture description
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            format,
+            scale: 1.0,
+            fixed_size: None,
+        }
+    }
+
+    /// Scale the texture relative to the surface
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Use a fixed size (e.g. for shadow maps)
+    pub fn with_fixed_size(mut self, width: u32, height: u32) -> Self {
+        self.fixed_size = Some((width, height));
+        self
+    }
+
+    fn size(&self, surface: (u32, u32)) -> (u32, u32) {
+        self.fixed_size.unwrap_or((
+            ((surface.0 as f32 * self.scale) as u32).max(1),
+            ((surface.1 as f32 * self.scale) as u32).max(1),
+        ))
+    }
+}
+
+/// Attachments and inputs of a render pass
+#[derive(Debug, Clone, Default)]
+pub struct PassDesc {
+    pub name: String,
+    /// Color targets, cleared to the color if given
+    pub colors: Vec<(String, Option<Color>)>,
+    /// Depth target, cleared to the value if given
+    pub depth: Option<(String, Option<f32>)>,
+    /// Textures sampled by the pass
+    pub reads: Vec<String>,
+}
+
+impl PassDesc {
+    /// Create a pass description with no attachments
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a color attachment
+    pub fn color(mut self, target: &str, clear: Option<Color>) -> Self {
+        self.colors.push((target.to_string(), clear));
+        self
+    }
+
+    /// Set the depth attachment
+    pub fn depth(mut self, target: &str, clear: Option<f32>) -> Self {
+        self.depth = Some((target.to_string(), clear));
+        self
+    }
+
+    /// Declare a texture sampled by the pass
+    pub fn read(mut self, texture: &str) -> Self {
+        self.reads.push(texture.to_string());
+        self
+    }
+
+    fn writes(&self) -> impl Iterator<Item = &str> {
+        self.colors
+            .iter()
+            .map(|(target, _)| target.as_str())
+            .chain(self.depth.iter().map(|(target, _)| target.as_str()))
+    }
+
+    fn touches_surface(&self) -> bool {
+        self.writes().chain(self.reads.iter().map(String::as_str)).any(|t| t == SURFACE)
+    }
+}
+
+/// Resources available to a node while recording
+pub struct NodeContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    /// Surface size in pixels
+    pub size: (u32, u32),
+    pub surface_format: wgpu::TextureFormat,
+    textures: &'a HashMap<String, GraphTexture>,
+}
+
+impl<'a> NodeContext<'a> {
+    /// Get a graph texture's view (e.g. to bind an input)
+    pub fn texture(&self, name: &str) -> Option<&'a wgpu::TextureView> {
+        self.textures.get(name).map(|t| &t.view)
+    }
+
+    /// Check if graph textures were recreated since the last frame (rebuild bind groups)
+    pub fn textures_changed(&self) -> bool {
+        self.textures.values().any(|t| t.fresh)
+    }
+}
+
+/// The work done by a render pass
+pub trait RenderNode {
+    /// Update buffers and bind groups before any pass of the frame is recorded
+    fn prepare(&mut self, _ctx: &NodeContext) {}
+
+    /// Record draw commands into the pass
+    fn run<'a>(&'a self, ctx: &NodeContext<'a>, pass: &mut wgpu::RenderPass<'a>);
+}
+
+struct GraphTexture {
+    view: wgpu::TextureView,
+    size: (u32, u32),
+    fresh: bool,
+}
+
+struct GraphPass {
+    desc: PassDesc,
+    node: Box<dyn RenderNode>,
+    enabled: bool,
+}
+
+/// When a pass runs relative to the built-in scene rendering
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphPhase {
+    /// Before the scene (offscreen passes such as shadow maps)
+    BeforeScene,
+    /// After the scene is tone mapped onto the surface, before the overlay (post-processing, UI)
+    AfterScene,
+}
+
+/// A set of render passes ordered by their resource dependencies
+#[derive(Default)]
+pub struct RenderGraph {
+    texture_descs: HashMap<String, TextureDesc>,
+    textures: HashMap<String, GraphTexture>,
+    passes: Vec<GraphPass>,
+    order: Option<Vec<(usize, GraphPhase)>>,
+}
+
+impl RenderGraph {
+    /// Create an empty render graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a texture passes can render to and sample from
+    pub fn add_texture(&mut self, name: &str, desc: TextureDesc) {
+        self.texture_descs.insert(name.to_string(), desc);
+        self.textures.remove(name);
+        self.order = None;
+    }
+
+    /// Add a pass (passes writing the same target run in the order they were added)
+    pub fn add_pass<N: RenderNode + 'static>(&mut self, desc: PassDesc, node: N) {
+        self.passes.push(GraphPass {
+            desc,
+            node: Box::new(node),
+            enabled: true,
+        });
+        self.order = None;
+    }
+
+    /// Remove a pass by name
+    pub fn remove_pass(&mut self, name: &str) -> bool {
+        let count = self.passes.len();
+        self.passes.retain(|p| p.desc.name != name);
+        self.order = None;
+        self.passes.len() != count
+    }
+
+    /// Enable or disable a pass without removing it
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        for pass in self.passes.iter_mut().filter(|p| p.desc.name == name) {
+            pass.enabled = enabled;
+        }
+    }
+
+    /// Check if the graph has no passes
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// Get the pass names in execution order with their phase
+    pub fn execution_order(&mut self) -> Result<Vec<(String, GraphPhase)>, String> {
+        let order = self.compile()?.to_vec();
+        Ok(order
+            .iter()
+            .map(|&(i, phase)| (self.passes[i].desc.name.clone(), phase))
+            .collect())
+    }
+
+    /// Order the passes so every writer of a resource runs before its readers
+    fn compile(&mut self) -> Result<&[(usize, GraphPhase)], String> {
+        if self.order.is_none() {
+            self.order = Some(self.sort_passes()?);
+        }
+        Ok(self.order.as_deref().unwrap_or_default())
+    }
+
+    fn sort_passes(&self) -> Result<Vec<(usize, GraphPhase)>, String> {
+        let count = self.passes.len();
+        let mut dependencies: Vec<Vec<usize>> = vec![Vec::new(); count];
+
+        for (i, pass) in self.passes.iter().enumerate() {
+            for resource in pass.desc.writes().chain(pass.desc.reads.iter().map(String::as_str)) {
+                if resource != SURFACE && !self.texture_descs.contains_key(resource) {
+                    return Err(format!("Pass {} uses unknown texture {}", pass.desc.name, resource));
+                }
+            }
+            if let Some(read) = pass.desc.reads.iter().find(|r| pass.desc.writes().any(|w| w == r.as_str())) {
+                return Err(format!("Pass {} reads and writes {}", pass.desc.name, read));
+            }
+
+            for (j, other) in self.passes.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                // Earlier writers of a target we write, and all writers of what we read
+                let writes_before = j < i && other.desc.writes().any(|w| pass.desc.writes().any(|o| o == w));
+                let feeds = other.desc.writes().any(|w| pass.desc.reads.iter().any(|r| r == w));
+                if writes_before || feeds {
+                    dependencies[i].push(j);
+                }
+            }
+        }
+
+        // Kahn's algorithm, preferring declaration order among ready passes
+        let mut remaining: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+        let mut done = vec![false; count];
+        let mut order = Vec::with_capacity(count);
+        while order.len() < count {
+            let next = (0..count)
+                .find(|&i| !done[i] && remaining[i] == 0)
+                .ok_or_else(|| "Render graph has a dependency cycle".to_string())?;
+            done[next] = true;
+            order.push(next);
+            for (i, deps) in dependencies.iter().enumerate() {
+                remaining[i] -= deps.iter().filter(|&&d| d == next).count();
+            }
+        }
+
+        // A pass runs after the scene if it uses the surface or depends on such a pass
+        let mut after = vec![false; count];
+        for &i in &order {
+            after[i] = self.passes[i].desc.touches_surface() || dependencies[i].iter().any(|&d| after[d]);
+        }
+        let phase = |i: usize| if after[i] { GraphPhase::AfterScene } else { GraphPhase::BeforeScene };
+
+        let mut phased: Vec<(usize, GraphPhase)> = order.iter().map(|&i| (i, phase(i))).collect();
+        // Stable: keeps dependency order within each phase
+        phased.sort_by_key(|&(_, phase)| phase == GraphPhase::AfterScene);
+        Ok(phased)
+    }
+
+    /// Create or resize graph textures for the current surface size
+    fn allocate(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        for texture in self.textures.values_mut() {
+            texture.fresh = false;
+        }
+        for (name, desc) in &self.texture_descs {
+            let texture_size = desc.size(size);
+            if self.textures.get(name).is_some_and(|t| t.size == texture_size) {
+                continue;
+            }
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(name),
+                size: wgpu::Extent3d {
+                    width: texture_size.0,
+                    height: texture_size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: desc.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            self.textures.insert(
+                name.clone(),
+                GraphTexture {
+                    view,
+                    size: texture_size,
+                    fresh: true,
+                },
+            );
+        }
+    }
+
+    /// Allocate textures and let every node prepare for the frame
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface_format: wgpu::TextureFormat,
+        size: (u32, u32),
+    ) -> Result<(), String> {
+        self.compile()?;
+        self.allocate(device, size);
+        let ctx = NodeContext {
+            device,
+            queue,
+            size,
+            surface_format,
+            textures: &self.textures,
+        };
+        for pass in self.passes.iter_mut().filter(|p| p.enabled) {
+            pass.node.prepare(&ctx);
+        }
+        Ok(())
+    }
+
+    /// Record the passes of one phase into the encoder (call after `prepare`)
+    pub(crate) fn record(
+        &self,
+        phase: GraphPhase,
+        ctx: &NodeContext,
+        encoder: &mut wgpu::CommandEncoder,
+        surface: &wgpu::TextureView,
+    ) {
+        let Some(order) = &self.order else {
+            return;
+        };
+        for &(index, pass_phase) in order {
+            let pass = &self.passes[index];
+            if pass_phase != phase || !pass.enabled {
+                continue;
+            }
+            let view = |target: &str| {
+                if target == SURFACE {
+                    Some(surface)
+                } else {
+                    self.textures.get(target).map(|t| &t.view)
+                }
+            };
+
+            let colors: Vec<Option<wgpu::RenderPassColorAttachment>> = pass
+                .desc
+                .colors
+                .iter()
+                .map(|(target, clear)| {
+                    view(target).map(|view| wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: clear.map_or(wgpu::LoadOp::Load, |c| wgpu::LoadOp::Clear(c.to_wgpu())),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })
+                })
+                .collect();
+            let depth = pass.desc.depth.as_ref().and_then(|(target, clear)| {
+                view(target).map(|view| wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: clear.map_or(wgpu::LoadOp::Load, wgpu::LoadOp::Clear),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                })
+            });
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(&pass.desc.name),
+                color_attachments: &colors,
+                depth_stencil_attachment: depth,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            pass.node.run(ctx, &mut render_pass);
+        }
+    }
+
+    /// Build the context handed to nodes while recording
+    pub(crate) fn context<'a>(
+        &'a self,
+        device: &'a wgpu::Device,
+        queue: &'a wgpu::Queue,
+        surface_format: wgpu::TextureFormat,
+        size: (u32, u32),
+    ) -> NodeContext<'a> {
+        NodeContext {
+            device,
+            queue,
+            size,
+            surface_format,
+            textures: &self.textures,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Noop;
+
+    impl RenderNode for Noop {
+        fn run<'a>(&'a self, _ctx: &NodeContext<'a>, _pass: &mut wgpu::RenderPass<'a>) {}
+    }
+
+    #[test]
+    fn test_passes_ordered_by_dependencies() {
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let mut graph = RenderGraph::new();
+        graph.add_texture("shadow", TextureDesc::new(wgpu::TextureFormat::Depth32Float).with_fixed_size(1024, 1024));
+        graph.add_texture("bright", TextureDesc::new(format).with_scale(0.5));
+
+        // Declared out of order: the composite reads what the later passes write
+        graph.add_pass(PassDesc::new("composite").color(SURFACE, None).read("bright"), Noop);
+        graph.add_pass(PassDesc::new("bright").color("bright", Some(Color::BLACK)).read("shadow"), Noop);
+        graph.add_pass(PassDesc::new("shadows").depth("shadow", Some(1.0)), Noop);
+        graph.add_pass(PassDesc::new("ui").color(SURFACE, None), Noop);
+
+        let order = graph.execution_order().unwrap();
+        let names: Vec<&str> = order.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["shadows", "bright", "composite", "ui"]);
+        assert_eq!(order[0].1, GraphPhase::BeforeScene);
+        assert_eq!(order[2].1, GraphPhase::AfterScene);
+
+        assert_eq!(TextureDesc::new(format).with_scale(0.5).size((1280, 720)), (640, 360));
+    }
+
+    #[test]
+    fn test_invalid_graphs_rejected() {
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let mut graph = RenderGraph::new();
+        graph.add_pass(PassDesc::new("missing").color("nowhere", None), Noop);
+        assert!(graph.execution_order().is_err());
+
+        let mut graph = RenderGraph::new();
+        graph.add_texture("a", TextureDesc::new(format));
+        graph.add_texture("b", TextureDesc::new(format));
+        graph.add_pass(PassDesc::new("ab").color("b", None).read("a"), Noop);
+        graph.add_pass(PassDesc::new("ba").color("a", None).read("b"), Noop);
+        assert!(graph.execution_order().unwrap_err().contains("cycle"));
+
+        let mut graph = RenderGraph::new();
+        graph.add_texture("a", TextureDesc::new(format));
+        graph.add_pass(PassDesc::new("feedback").color("a", None).read("a"), Noop);
+        assert!(graph.execution_order().is_err());
+    }
+}
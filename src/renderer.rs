@@ -4,12 +4,54 @@
 
 use wgpu::util::DeviceExt;
 use winit::window::Window;
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec2, Vec3};
 use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+use crate::capture::{CaptureConfig, CaptureReadback, FrameCapture};
+use image::RgbaImage;
 use crate::config::RendererConfig;
+use crate::debug_draw::{DebugDraw, DebugDrawPipeline};
+use crate::depth_prepass::{DepthPrepass, PrepassDraw};
+use crate::gpu_timing::{GpuTimer, PassTiming};
+use crate::hierarchy::world_matrix;
+use crate::grid::{GridPipeline, GridSettings};
+use crate::lens::{ChromaticAberrationSettings, FilmGrainSettings, LensPipeline, VignetteSettings};
+use crate::ecs::{Component, EntityId, Scene};
+use crate::light::{closest_lights, scene_lights, Light, PointLightUniform};
+use crate::lod::LodGroup;
+use crate::math::{Frustum, Transform};
+use crate::occlusion::{occlusion_proxies, OcclusionCuller};
+use crate::outline::{OutlineDraw, OutlinePipeline, DEFAULT_OUTLINE_WIDTH};
+use crate::particles::{ParticleEmitter, ParticlePipeline};
+use crate::pbr::{PbrMaterial, PbrUniform};
+use crate::antialias::{jitter_matrix, AntiAliasPipeline, AntiAliasing};
+use crate::bloom::{BloomPipeline, BloomSettings};
+use crate::color_grading::{ColorGrading, ColorLut, ColorLutHandle, ColorLuts};
+use crate::postprocess::{create_hdr_view, PostEffect, ToneMapPipeline, ToneMapping, HDR_FORMAT};
+use crate::reflection::{
+    cube_face_camera, Capture, CaptureTarget, PlanarReflection, ReflectionBinding, ReflectionProbe, ReflectionUniform,
+    Reflections,
+};
+use crate::render_graph::{GraphPhase, RenderGraph};
+use crate::resource::{Mesh, MeshHandle, ResourceManager, Texture, TextureHandle};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::shader_reload::{ShaderSources, ShaderWatcher};
+use crate::skinning::{Skeleton, SkinVertex};
+use crate::sky::{Sky, SkyPipeline};
+use crate::terrain::{Terrain, TerrainPipeline};
+use crate::trail::{RibbonPipeline, Ribbons};
+use crate::uniform_ring::UniformRing;
 
-/// RGBA color
-#[derive(Debug, Clone, Copy)]
+/// Linear RGBA color
+///
+/// Components are linear light, the space shaders blend and light in, and go to the GPU
+/// unchanged: in uniforms, clear colors, and vertex colors alike. Colors picked in an
+/// image editor or written as hex codes are sRGB-encoded; create those with
+/// [`Color::from_hex`], [`Color::from_srgb_u8`], or [`Color::from_srgb`], which decode
+/// them. Alpha is always linear.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -17,18 +59,97 @@ pub struct Color {
     pub a: f32,
 }
 
+/// Decode an sRGB-encoded component (0.0..=1.0) to linear
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode a linear component (0.0..=1.0) as sRGB
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
 impl Color {
-    /// Create a new color
+    /// Create a new color from linear components
     pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
         Self { r, g, b, a }
     }
 
-    /// Create RGB color (alpha = 1.0)
+    /// Create a color from linear components (alpha = 1.0)
     pub fn rgb(r: f32, g: f32, b: f32) -> Self {
         Self::new(r, g, b, 1.0)
     }
 
-    /// Convert to wgpu color
+    /// Create a color from sRGB-encoded components (0.0..=1.0, alpha = 1.0)
+    pub fn from_srgb(r: f32, g: f32, b: f32) -> Self {
+        Self::rgb(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b))
+    }
+
+    /// Create a color from 8-bit sRGB components, as shown by image editors (alpha = 1.0)
+    pub fn from_srgb_u8(r: u8, g: u8, b: u8) -> Self {
+        Self::from_srgba_u8(r, g, b, 255)
+    }
+
+    /// Create a color from 8-bit sRGB components and alpha
+    pub fn from_srgba_u8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        let [r, g, b, a] = [r, g, b, a].map(|c| c as f32 / 255.0);
+        Self::from_srgb(r, g, b).with_alpha(a)
+    }
+
+    /// Parse an sRGB hex code: `#rrggbb` or `#rrggbbaa` (the `#` is optional)
+    pub fn from_hex(hex: &str) -> Result<Self, String> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if !matches!(digits.len(), 6 | 8) || !digits.is_ascii() {
+            return Err(format!("Invalid hex color: {:?}", hex));
+        }
+        let component = |i: usize| {
+            u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| format!("Invalid hex color {:?}: {}", hex, e))
+        };
+        let alpha = if digits.len() == 8 { component(6)? } else { 255 };
+        Ok(Self::from_srgba_u8(component(0)?, component(2)?, component(4)?, alpha))
+    }
+
+    /// Treat the components as sRGB-encoded and decode them to linear
+    pub fn to_linear(&self) -> Color {
+        Self::new(srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b), self.a)
+    }
+
+    /// Encode the (linear) components as sRGB, e.g. for UI or image output
+    pub fn to_srgb(&self) -> Color {
+        Self::new(linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b), self.a)
+    }
+
+    /// Encode as 8-bit sRGB components and alpha, clamping to the displayable range
+    pub fn to_srgba_u8(&self) -> [u8; 4] {
+        let srgb = self.to_srgb();
+        [srgb.r, srgb.g, srgb.b, srgb.a].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+
+    /// Encode as an sRGB hex code (`#rrggbb`, or `#rrggbbaa` if not opaque)
+    pub fn to_hex(&self) -> String {
+        let [r, g, b, a] = self.to_srgba_u8();
+        if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+
+    /// Get the same color with another alpha
+    pub fn with_alpha(mut self, a: f32) -> Self {
+        self.a = a;
+        self
+    }
+
+    /// Convert to a wgpu color (linear, as render pass clear colors expect)
     pub fn to_wgpu(&self) -> wgpu::Color {
         wgpu::Color {
             r: self.r as f64,
@@ -38,7 +159,7 @@ impl Color {
         }
     }
 
-    /// Convert to array
+    /// Convert to a linear array, for uniforms and vertex colors
     pub fn to_array(&self) -> [f32; 4] {
         [self.r, self.g, self.b, self.a]
     }
@@ -56,12 +177,14 @@ impl Color {
 
 /// Vertex structure for rendering
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
 pub struct Vertex {
     pub position: [f32; 3],
     pub tex_coords: [f32; 2],
     pub normal: [f32; 3],
     pub color: [f32; 4],
+    /// Tangent (xyz) and bitangent sign (w) for normal mapping; see `Mesh::generate_tangents`
+    pub tangent: [f32; 4],
 }
 
 impl Vertex {
@@ -95,19 +218,528 @@ impl Vertex {
                     shader_location: 3,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                // Tangent
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
 }
 
+/// A vertex type that can be stored in a `Mesh` and bound to a pipeline
+///
+/// Scene meshes use the full 3D [`Vertex`]; lines, sprites, and custom passes can use a
+/// leaner format such as [`PosColorVertex`] or [`PosUvVertex`] to save bandwidth.
+pub trait VertexLayout: Pod {
+    /// Vertex buffer layout, with attributes at shader locations from 0
+    fn layout() -> wgpu::VertexBufferLayout<'static>;
+
+    /// Position used for the mesh bounds
+    fn position(&self) -> [f32; 3];
+
+    /// Derive missing attributes once a `MeshBuilder` is done (e.g. tangents)
+    fn finish(_vertices: &mut [Self], _indices: &[u32]) {}
+}
+
+impl VertexLayout for Vertex {
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        Self::desc()
+    }
+
+    fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    fn finish(vertices: &mut [Self], indices: &[u32]) {
+        if vertices.iter().all(|v| v.tangent == [0.0; 4]) {
+            crate::resource::generate_tangents(vertices, indices);
+        }
+    }
+}
+
+/// Position and color vertex (28 bytes), for lines and flat-shaded geometry
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PosColorVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl VertexLayout for PosColorVertex {
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &ATTRIBUTES,
+        }
+    }
+
+    fn position(&self) -> [f32; 3] {
+        self.position
+    }
+}
+
+/// Position and texture coordinate vertex (20 bytes), for sprites and screen-space quads
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PosUvVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+}
+
+impl VertexLayout for PosUvVertex {
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &ATTRIBUTES,
+        }
+    }
+
+    fn position(&self) -> [f32; 3] {
+        self.position
+    }
+}
+
+/// How the scene's meshes are shaded, for diagnosing broken meshes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebugView {
+    /// Normal shading with materials and lighting
+    #[default]
+    Shaded,
+    /// Triangle edges only (needs `POLYGON_MODE_LINE` support)
+    Wireframe,
+    /// World-space normals as RGB
+    Normals,
+    /// Texture coordinates as red/green over a checker
+    Uvs,
+    /// Additive heatmap of how many fragments each pixel receives
+    Overdraw,
+}
+
+impl DebugView {
+    fn fs_entry(self) -> &'static str {
+        match self {
+            DebugView::Shaded => "fs_main",
+            DebugView::Wireframe => "fs_wireframe",
+            DebugView::Normals => "fs_normals",
+            DebugView::Uvs => "fs_uv",
+            DebugView::Overdraw => "fs_overdraw",
+        }
+    }
+}
+
+/// Pipelines replacing every mesh pipeline while a debug view is active
+struct DebugViewPipelines {
+    object: wgpu::RenderPipeline,
+    instanced: wgpu::RenderPipeline,
+}
+
+/// Draws an entity's mesh at its `Transform`
+#[derive(Debug, Clone, Copy)]
+pub struct MeshRenderer {
+    pub mesh: MeshHandle,
+    pub texture: Option<TextureHandle>,
+    /// Custom material (the default shader if None)
+    pub material: Option<MaterialHandle>,
+    pub visible: bool,
+    /// Blend over the scene after opaque meshes, sorted back to front, without writing depth
+    pub transparent: bool,
+}
+
+impl Component for MeshRenderer {}
+
+impl MeshRenderer {
+    /// Create a mesh renderer for a mesh handle
+    pub fn new(mesh: MeshHandle) -> Self {
+        Self {
+            mesh,
+            texture: None,
+            material: None,
+            visible: true,
+            transparent: false,
+        }
+    }
+
+    /// Set the texture drawn on the mesh
+    pub fn with_texture(mut self, texture: TextureHandle) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Draw the mesh with a custom material
+    pub fn with_material(mut self, material: MaterialHandle) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    /// Draw the mesh in the transparent pass
+    pub fn with_transparency(mut self) -> Self {
+        self.transparent = true;
+        self
+    }
+}
+
+/// The layers (0..32) an entity's mesh and particles are drawn on; only cameras whose
+/// `layers` mask shares one of them draw it. Entities without the component are on layer 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderLayers(pub u32);
+
+impl Component for RenderLayers {}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl RenderLayers {
+    /// Only layer 0
+    pub const DEFAULT: Self = Self(1);
+    /// Every layer
+    pub const ALL: Self = Self(u32::MAX);
+    /// No layer (never drawn, or a camera drawing no entities)
+    pub const NONE: Self = Self(0);
+
+    /// Only the given layer (`layer` must be below 32)
+    pub const fn layer(layer: u32) -> Self {
+        Self(1 << layer)
+    }
+
+    /// Add a layer
+    pub const fn with(self, layer: u32) -> Self {
+        Self(self.0 | 1 << layer)
+    }
+
+    /// Remove a layer
+    pub const fn without(self, layer: u32) -> Self {
+        Self(self.0 & !(1 << layer))
+    }
+
+    /// Check if a layer is included
+    pub fn contains(self, layer: u32) -> bool {
+        layer < 32 && self.0 & (1 << layer) != 0
+    }
+
+    /// Check if the two masks share a layer
+    pub fn intersects(self, other: RenderLayers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+/// Per-instance data for [`Renderer::draw_instanced`] (second vertex buffer)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct InstanceData {
+    pub model: [[f32; 4]; 4],
+    pub color: [f32; 4],
+}
+
+impl InstanceData {
+    /// Create instance data from a model matrix and a color (multiplied with vertex colors)
+    pub fn new(model: Mat4, color: Color) -> Self {
+        Self {
+            model: model.to_cols_array_2d(),
+            color: color.to_array(),
+        }
+    }
+
+    /// Get instance buffer layout (locations 5-9)
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+            5 => Float32x4,
+            6 => Float32x4,
+            7 => Float32x4,
+            8 => Float32x4,
+            9 => Float32x4,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceData>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+impl From<Mat4> for InstanceData {
+    fn from(model: Mat4) -> Self {
+        Self::new(model, Color::WHITE)
+    }
+}
+
+/// Instances queued with [`Renderer::draw_instanced`]
+#[derive(Debug, Clone)]
+struct InstanceBatch {
+    mesh: MeshHandle,
+    texture: Option<TextureHandle>,
+    instances: std::ops::Range<u32>,
+}
+
+/// Handle to a material created with [`Renderer::create_material`]
+pub type MaterialHandle = usize;
+
+/// Description of a custom material
+///
+/// The shader is appended to the shared bindings in `shaders/common.wgsl` (camera and
+/// light at group 0, object at group 1, texture at group 2) and must define `fs_main`.
+/// If it has no `vs_main`, one calling `mesh_vertex` is added. Parameters are bound as a
+/// uniform at `@group(3) @binding(0)`.
+#[derive(Debug, Clone)]
+pub struct MaterialDescriptor {
+    pub name: String,
+    pub shader: String,
+    /// Initial contents of the uniform parameters
+    pub params: Vec<u8>,
+    pub blend: Option<wgpu::BlendState>,
+    pub cull_mode: Option<wgpu::Face>,
+    /// File the shader was read from, recompiled when it changes with hot reload on
+    pub path: Option<PathBuf>,
+}
+
+impl MaterialDescriptor {
+    /// Create an alpha-blended, back-face culled material from WGSL source
+    pub fn new(name: &str, shader: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            shader: shader.to_string(),
+            params: Vec::new(),
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            cull_mode: Some(wgpu::Face::Back),
+            path: None,
+        }
+    }
+
+    /// Create a material from a WGSL file, which shader hot reload watches
+    pub fn from_file(name: &str, path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let shader = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read shader {}: {}", path.display(), e))?;
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            ..Self::new(name, &shader)
+        })
+    }
+
+    /// Set the initial uniform parameters
+    pub fn with_params<T: Pod>(mut self, params: &T) -> Self {
+        self.params = bytemuck::bytes_of(params).to_vec();
+        self
+    }
+
+    /// Build the full WGSL source compiled for this material on top of `common.wgsl`
+    fn source(&self, common: &str) -> String {
+        let mut source = format!("{}\n{}\n", common, self.shader);
+        if !self.shader.contains("fn vs_main") {
+            source.push_str("@vertex\nfn vs_main(input: VertexInput) -> VertexOutput {\n    return mesh_vertex(input);\n}\n");
+        }
+        if !self.shader.contains("fn vs_skinned") {
+            source.push_str(
+                "@vertex\nfn vs_skinned(input: VertexInput, skin: SkinInput) -> VertexOutput {\n    return mesh_vertex(skin_vertex(input, skin));\n}\n",
+            );
+        }
+        source
+    }
+}
+
+/// A custom shader pipeline with its uniform parameters
+pub struct Material {
+    name: String,
+    /// Shared by every PBR material
+    pipelines: MaterialPipelines,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    params_size: usize,
+    /// Source of custom materials, recompiled by shader hot reload (`None` for PBR)
+    descriptor: Option<MaterialDescriptor>,
+}
+
+impl Material {
+    /// Get the material name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the size of the parameter buffer in bytes
+    pub fn params_size(&self) -> usize {
+        self.params_size
+    }
+}
+
+/// A mesh drawn in the scene pass
+struct SceneDraw<'a> {
+    mesh: &'a Mesh,
+    model: Mat4,
+    texture: Option<TextureHandle>,
+    material: Option<MaterialHandle>,
+    /// Dithered coverage and pattern inversion while cross-fading LODs
+    fade: (f32, bool),
+    transparent: bool,
+    skeleton: Option<&'a Skeleton>,
+    /// Entity drawing the mesh (`None` for queued meshes), for occlusion culling
+    entity: Option<EntityId>,
+    /// Layers of the entity, checked against each camera's mask
+    layers: RenderLayers,
+    /// Dynamic offset of the draw's object uniform, set when the objects are uploaded
+    object_offset: u32,
+}
+
+impl SceneDraw<'_> {
+    /// Whether the mesh is posed by a skeleton
+    fn skinned(&self) -> bool {
+        self.skeleton.is_some() && self.mesh.skin_buffer.is_some()
+    }
+}
+
+/// A material's pipelines for the opaque and transparent passes
+#[derive(Clone)]
+struct MaterialPipelines {
+    opaque: Arc<wgpu::RenderPipeline>,
+    /// Blends and tests depth without writing it
+    transparent: Arc<wgpu::RenderPipeline>,
+    /// Opaque and transparent variants for skinned meshes (`None` without skinning support)
+    skinned: Option<[Arc<wgpu::RenderPipeline>; 2]>,
+}
+
+impl MaterialPipelines {
+    /// Pick the pipeline for a draw; without skinning support, skinned meshes keep their
+    /// bind pose
+    fn get(&self, transparent: bool, skinned: bool) -> &wgpu::RenderPipeline {
+        match (&self.skinned, skinned, transparent) {
+            (Some([_, pipeline]), true, true) | (Some([pipeline, _]), true, false) => pipeline,
+            (_, _, true) => &self.transparent,
+            (_, _, false) => &self.opaque,
+        }
+    }
+}
+
+/// A mesh queued with [`Renderer::draw_mesh`]
+#[derive(Debug, Clone, Copy)]
+struct MeshDraw {
+    mesh: MeshHandle,
+    model: Mat4,
+    texture: Option<TextureHandle>,
+}
+
+/// Depth buffer format used by the scene pass
+pub(crate) const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
 /// Camera uniform buffer data
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 struct CameraUniform {
     view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> Self {
+        Self {
+            view_proj: camera.view_proj_matrix().to_cols_array_2d(),
+            position: camera.position.extend(1.0).to_array(),
+        }
+    }
+
+    /// Shift the projection by a TAA jitter matrix
+    fn with_jitter(mut self, jitter: Mat4) -> Self {
+        self.view_proj = (jitter * Mat4::from_cols_array_2d(&self.view_proj)).to_cols_array_2d();
+        self
+    }
+}
+
+/// Per-object uniform buffer data (bound with a dynamic offset)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct ObjectUniform {
+    model: [[f32; 4]; 4],
+    normal: [[f32; 4]; 4],
+    /// Dither threshold (x) and inverted pattern flag (y) for LOD cross-fades
+    fade: [f32; 4],
+    /// First joint matrix (x) of skinned meshes
+    skin: [u32; 4],
+}
+
+impl ObjectUniform {
+    fn new(model: Mat4) -> Self {
+        // Inverse transpose keeps normals perpendicular under non-uniform scale
+        let normal = if model.determinant().abs() > f32::EPSILON {
+            model.inverse().transpose()
+        } else {
+            model
+        };
+        Self {
+            model: model.to_cols_array_2d(),
+            normal: normal.to_cols_array_2d(),
+            fade: [1.0, 0.0, 0.0, 0.0],
+            skin: [0; 4],
+        }
+    }
+
+    /// Keep only `coverage` of the pixels; the inverted pattern keeps the others
+    fn with_fade(mut self, coverage: f32, inverted: bool) -> Self {
+        self.fade = [coverage, if inverted { 1.0 } else { 0.0 }, 0.0, 0.0];
+        self
+    }
+
+    /// Pose the mesh with the joint matrices starting at `first_joint`
+    fn with_skin(mut self, first_joint: u32) -> Self {
+        self.skin = [first_joint, 0, 0, 0];
+        self
+    }
+}
+
+/// Directional light uniform buffer data
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct LightUniform {
+    direction: [f32; 4],
+    color: [f32; 4],
+    ambient: [f32; 4],
+}
+
+/// Directional light (sun or moon) plus ambient term used by the default shader
+#[derive(Debug, Clone, Copy)]
+pub struct DirectionalLight {
+    /// Direction pointing towards the light
+    pub direction: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+    pub ambient: Color,
+}
+
+impl DirectionalLight {
+    fn to_uniform(self) -> LightUniform {
+        let direction = self.direction.normalize_or_zero();
+        LightUniform {
+            direction: [direction.x, direction.y, direction.z, 0.0],
+            color: [
+                self.color.r * self.intensity,
+                self.color.g * self.intensity,
+                self.color.b * self.intensity,
+                1.0,
+            ],
+            ambient: self.ambient.to_array(),
+        }
+    }
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::ONE.normalize(),
+            color: Color::WHITE,
+            intensity: 0.7,
+            ambient: Color::rgb(0.3, 0.3, 0.3),
+        }
+    }
 }
 
 /// Camera for 3D rendering
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub position: Vec3,
     pub target: Vec3,
@@ -116,6 +748,10 @@ pub struct Camera {
     pub aspect_ratio: f32,
     pub near: f32,
     pub far: f32,
+    /// Multiplier applied to the HDR scene color before tone mapping
+    pub exposure: f32,
+    /// Culling mask: entities are drawn only if their `RenderLayers` share a layer with it
+    pub layers: RenderLayers,
 }
 
 impl Camera {
@@ -129,6 +765,8 @@ impl Camera {
             aspect_ratio,
             near: 0.1,
             far: 1000.0,
+            exposure: 1.0,
+            layers: RenderLayers::ALL,
         }
     }
 
@@ -152,64 +790,415 @@ impl Camera {
         self.projection_matrix() * self.view_matrix()
     }
 
+    /// Get a world-space ray (origin, unit direction) through a pixel of the viewport
+    pub fn screen_to_ray(&self, screen: Vec2, viewport: (u32, u32)) -> (Vec3, Vec3) {
+        let ndc_x = screen.x / viewport.0.max(1) as f32 * 2.0 - 1.0;
+        let ndc_y = 1.0 - screen.y / viewport.1.max(1) as f32 * 2.0;
+        let inverse = self.view_proj_matrix().inverse();
+        let near = inverse.project_point3(Vec3::new(ndc_x, ndc_y, 0.0));
+        let far = inverse.project_point3(Vec3::new(ndc_x, ndc_y, 1.0));
+        (near, (far - near).normalize_or_zero())
+    }
+
+    /// Project a world-space point to viewport pixels, or `None` if it is behind the camera
+    pub fn world_to_screen(&self, point: Vec3, viewport: (u32, u32)) -> Option<Vec2> {
+        let clip = self.view_proj_matrix() * point.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        Some(Vec2::new(
+            (ndc.x + 1.0) * 0.5 * viewport.0 as f32,
+            (1.0 - ndc.y) * 0.5 * viewport.1 as f32,
+        ))
+    }
+
     /// Update aspect ratio (call when window resizes)
     pub fn update_aspect_ratio(&mut self, width: u32, height: u32) {
         self.aspect_ratio = width as f32 / height as f32;
     }
 }
 
-/// Main renderer
-pub struct Renderer {
-    surface: wgpu::Surface<'static>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
-    size: (u32, u32),
-    render_pipeline: wgpu::RenderPipeline,
-    camera: Camera,
-    camera_buffer: wgpu::Buffer,
-    camera_bind_group: wgpu::BindGroup,
-    clear_color: Color,
+/// Region of the surface a camera draws into, in fractions of the surface size
+/// (origin at the top left)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
 }
 
-impl Renderer {
-    /// Create a new renderer
-    pub async fn new(window: &Window, renderer_config: &RendererConfig) -> Result<Self, String> {
-        let size = window.inner_size();
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
 
-        // Create wgpu instance
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
+impl Viewport {
+    /// The whole surface
+    pub const FULL: Viewport = Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
 
-        // Create surface
-        // SAFETY: The window reference is valid for the lifetime of the surface
-        let surface: wgpu::Surface<'static> = unsafe {
-            let raw_surface = instance
-                .create_surface(window)
-                .map_err(|e| format!("Failed to create surface: {}", e))?;
-            std::mem::transmute(raw_surface)
-        };
+    /// Create a viewport from fractions of the surface size
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
 
-        // Request adapter
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .ok_or("Failed to find suitable GPU adapter")?;
+    /// Get cell `index` of an evenly split grid, filled row by row (split screen)
+    pub fn grid(columns: u32, rows: u32, index: u32) -> Self {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        let (width, height) = (1.0 / columns as f32, 1.0 / rows as f32);
+        Self {
+            x: (index % columns) as f32 * width,
+            y: (index / columns % rows) as f32 * height,
+            width,
+            height,
+        }
+    }
 
-        log::info!("Using GPU: {}", adapter.get_info().name);
+    /// Get the pixel rectangle (x, y, width, height) on a surface of `size`,
+    /// clamped to the surface and at least one pixel wide
+    pub fn to_pixels(&self, size: (u32, u32)) -> (u32, u32, u32, u32) {
+        let to_pixel = |fraction: f32, extent: u32| ((fraction.clamp(0.0, 1.0) * extent as f32).round() as u32).min(extent);
+        let x = to_pixel(self.x, size.0).min(size.0.saturating_sub(1));
+        let y = to_pixel(self.y, size.1).min(size.1.saturating_sub(1));
+        let right = to_pixel(self.x + self.width, size.0).max(x + 1);
+        let bottom = to_pixel(self.y + self.height, size.1).max(y + 1);
+        (x, y, right - x, bottom - y)
+    }
 
-        // Request device and queue
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
+    /// Check if the viewport covers the whole surface
+    pub fn is_full(&self) -> bool {
+        *self == Self::FULL
+    }
+}
+
+/// Handle to a camera added with [`Renderer::add_camera`]
+pub type CameraHandle = usize;
+
+/// How a camera's pass starts out, before it draws its meshes
+///
+/// The frame is always cleared to the renderer's clear color (or the sky) first; a
+/// camera only clears its own viewport on top of that.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CameraClear {
+    /// Keep the color drawn so far and clear depth, so meshes of earlier cameras
+    /// don't hide this camera's
+    #[default]
+    Depth,
+    /// Fill the viewport with a color and clear depth
+    Color(Color),
+    /// Keep both color and depth, drawing into the scene of the previous camera
+    None,
+}
+
+/// Why a frame could not be rendered
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderError {
+    /// The GPU ran out of memory; the renderer can't recover
+    OutOfMemory,
+    /// Any other failure, such as rendering while suspended
+    Other(String),
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::OutOfMemory => write!(f, "GPU out of memory"),
+            RenderError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<String> for RenderError {
+    fn from(message: String) -> Self {
+        RenderError::Other(message)
+    }
+}
+
+impl From<&str> for RenderError {
+    fn from(message: &str) -> Self {
+        RenderError::Other(message.to_string())
+    }
+}
+
+/// Graphics API the renderer runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GpuBackend {
+    /// The best one available on the platform
+    #[default]
+    Auto,
+    Vulkan,
+    Dx12,
+    Metal,
+    /// OpenGL (or OpenGL ES), for old drivers and software rasterizers
+    Gl,
+}
+
+impl GpuBackend {
+    pub fn to_wgpu(self) -> wgpu::Backends {
+        match self {
+            GpuBackend::Auto => wgpu::Backends::all(),
+            GpuBackend::Vulkan => wgpu::Backends::VULKAN,
+            GpuBackend::Dx12 => wgpu::Backends::DX12,
+            GpuBackend::Metal => wgpu::Backends::METAL,
+            GpuBackend::Gl => wgpu::Backends::GL,
+        }
+    }
+}
+
+/// Which GPU to prefer on systems with several, like hybrid laptops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PowerPreference {
+    /// The discrete GPU
+    #[default]
+    HighPerformance,
+    /// The integrated GPU, saving battery
+    LowPower,
+}
+
+impl PowerPreference {
+    pub fn to_wgpu(self) -> wgpu::PowerPreference {
+        match self {
+            PowerPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+            PowerPreference::LowPower => wgpu::PowerPreference::LowPower,
+        }
+    }
+}
+
+/// Draw counts of the last rendered frame, summed over every camera
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderStats {
+    /// Meshes and instanced batches submitted to the GPU (one draw call each)
+    pub drawn: u32,
+    /// Triangles in the drawn meshes and batches
+    pub triangles: u64,
+    /// Meshes skipped because they were outside the camera's view
+    pub culled: u32,
+    /// Meshes skipped because occlusion queries found them hidden
+    pub occluded: u32,
+    /// GPU time per stage of a recent frame, with GPU timing enabled
+    pub pass_timings: Vec<PassTiming>,
+}
+
+/// A camera added with [`Renderer::add_camera`], with its own uniform buffer
+struct ViewCamera {
+    camera: Camera,
+    viewport: Viewport,
+    clear: CameraClear,
+    order: i32,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Main renderer
+pub struct Renderer {
+    instance: wgpu::Instance,
+    /// `None` while the app is suspended (mobile platforms drop the window surface)
+    surface: Option<wgpu::Surface<'static>>,
+    /// Render target of a headless renderer, instead of a surface
+    offscreen: Option<Arc<wgpu::Texture>>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    size: (u32, u32),
+    /// The default shader's pipelines
+    mesh_pipelines: MaterialPipelines,
+    instanced_pipeline: wgpu::RenderPipeline,
+    instance_buffer: wgpu::Buffer,
+    instance_capacity: usize,
+    instances: Vec<InstanceData>,
+    instance_batches: Vec<InstanceBatch>,
+    camera: Camera,
+    viewport: Viewport,
+    /// Clear behavior of the main camera
+    camera_clear: CameraClear,
+    view_cameras: Vec<Option<ViewCamera>>,
+    /// Fills a viewport with a camera's clear color
+    clear_pipeline: wgpu::RenderPipeline,
+    frustum_culling: bool,
+    occlusion: Option<OcclusionCuller>,
+    outline: Option<OutlinePipeline>,
+    /// Writes opaque depth before each camera's scene pass (`None` when disabled)
+    depth_prepass: Option<DepthPrepass>,
+    outlined: HashSet<EntityId>,
+    outline_color: Color,
+    outline_width: f32,
+    gpu_timer: Option<GpuTimer>,
+    render_stats: RenderStats,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
+    object_bind_group_layout: wgpu::BindGroupLayout,
+    /// Per-draw object uniforms of the current frame
+    objects: UniformRing,
+    object_bind_group: wgpu::BindGroup,
+    /// Joint matrices of this frame's skinned meshes (`None` without skinning support)
+    joint_buffer: Option<wgpu::Buffer>,
+    joint_capacity: usize,
+    depth_view: wgpu::TextureView,
+    sample_count: u32,
+    msaa_view: Option<wgpu::TextureView>,
+    hdr_view: wgpu::TextureView,
+    tone_map_pipeline: ToneMapPipeline,
+    tone_mapping: ToneMapping,
+    color_luts: ColorLuts,
+    color_grading: Option<ColorGrading>,
+    post_effects: Vec<PostEffect>,
+    debug_view: DebugView,
+    debug_view_pipelines: Option<DebugViewPipelines>,
+    bloom: BloomSettings,
+    bloom_pipeline: Option<BloomPipeline>,
+    vignette: VignetteSettings,
+    film_grain: FilmGrainSettings,
+    chromatic_aberration: ChromaticAberrationSettings,
+    lens_pipeline: Option<LensPipeline>,
+    anti_aliasing: AntiAliasing,
+    anti_alias_pipeline: Option<AntiAliasPipeline>,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    material_bind_group_layout: wgpu::BindGroupLayout,
+    render_pipeline_layout: wgpu::PipelineLayout,
+    material_pipeline_layout: wgpu::PipelineLayout,
+    materials: Vec<Material>,
+    pbr_bind_group_layout: wgpu::BindGroupLayout,
+    pbr_pipeline: Option<MaterialPipelines>,
+    texture_sampler: wgpu::Sampler,
+    white_texture_view: wgpu::TextureView,
+    flat_normal_view: wgpu::TextureView,
+    white_texture_bind_group: wgpu::BindGroup,
+    texture_bind_groups: HashMap<TextureHandle, wgpu::BindGroup>,
+    mesh_queue: Vec<MeshDraw>,
+    light: DirectionalLight,
+    light_buffer: wgpu::Buffer,
+    point_light_buffer: wgpu::Buffer,
+    max_lights: usize,
+    light_queue: Vec<PointLightUniform>,
+    sky: Option<Sky>,
+    sky_pipeline: Option<SkyPipeline>,
+    grid: Option<GridSettings>,
+    grid_pipeline: Option<GridPipeline>,
+    terrain: Option<Terrain>,
+    terrain_pipeline: Option<TerrainPipeline>,
+    reflections: Reflections,
+    planar_reflection: Option<PlanarReflection>,
+    /// Camera of the reflection captures, which see no reflections themselves
+    reflection_camera: ViewCamera,
+    capture: Option<FrameCapture>,
+    capture_readback: Option<CaptureReadback>,
+    debug_draw: DebugDraw,
+    debug_draw_pipeline: Option<DebugDrawPipeline>,
+    ribbons: Ribbons,
+    ribbon_pipeline: Option<RibbonPipeline>,
+    particle_pipeline: Option<ParticlePipeline>,
+    render_graph: RenderGraph,
+    adapter_info: wgpu::AdapterInfo,
+    clear_color: Color,
+    shader_sources: ShaderSources,
+    shader_watcher: Option<ShaderWatcher>,
+}
+
+impl Renderer {
+    /// Create a new renderer
+    pub async fn new(window: &Window, renderer_config: &RendererConfig) -> Result<Self, String> {
+        let size = window.inner_size();
+
+        // Create wgpu instance
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: renderer_config.backend.to_wgpu(),
+            ..Default::default()
+        });
+
+        // Create surface
+        // SAFETY: The window reference is valid for the lifetime of the surface
+        let surface: wgpu::Surface<'static> = unsafe {
+            let raw_surface = instance
+                .create_surface(window)
+                .map_err(|e| format!("Failed to create surface: {}", e))?;
+            std::mem::transmute(raw_surface)
+        };
+
+        Self::with_target(instance, Some(surface), (size.width, size.height), renderer_config).await
+    }
+
+    /// Create a renderer without a window, drawing frames into an offscreen texture of
+    /// `size` that [`Renderer::read_frame`] copies back; for golden-image tests, servers,
+    /// and thumbnail tools
+    pub async fn new_headless(size: (u32, u32), renderer_config: &RendererConfig) -> Result<Self, String> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: renderer_config.backend.to_wgpu(),
+            ..Default::default()
+        });
+        Self::with_target(instance, None, (size.0.max(1), size.1.max(1)), renderer_config).await
+    }
+
+    /// Pick the first GPU whose name contains the configured `adapter_name`, or else the
+    /// one wgpu prefers for the configured power preference; every GPU found is logged
+    async fn select_adapter(
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface<'static>>,
+        renderer_config: &RendererConfig,
+    ) -> Result<wgpu::Adapter, String> {
+        let backends = renderer_config.backend.to_wgpu();
+        let mut adapters: Vec<wgpu::Adapter> = instance
+            .enumerate_adapters(backends)
+            .into_iter()
+            .filter(|adapter| surface.is_none_or(|surface| adapter.is_surface_supported(surface)))
+            .collect();
+        let names: Vec<String> = adapters.iter().map(|adapter| adapter.get_info().name).collect();
+        for adapter in &adapters {
+            let info = adapter.get_info();
+            log::info!("Found GPU: {} ({:?}, {:?})", info.name, info.backend, info.device_type);
+        }
+
+        if let Some(filter) = &renderer_config.adapter_name {
+            match pick_adapter(&names, filter) {
+                Some(index) => return Ok(adapters.swap_remove(index)),
+                None => log::warn!("No GPU matches \"{}\", using the default one", filter),
+            }
+        }
+        instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: renderer_config.power_preference.to_wgpu(),
+                compatible_surface: surface,
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or_else(|| format!("Failed to find suitable GPU adapter (backends: {:?})", backends))
+    }
+
+    /// Set up the device and pipelines for a window surface, or an offscreen target if
+    /// `surface` is `None`
+    async fn with_target(
+        instance: wgpu::Instance,
+        surface: Option<wgpu::Surface<'static>>,
+        size: (u32, u32),
+        renderer_config: &RendererConfig,
+    ) -> Result<Self, String> {
+        let adapter = Self::select_adapter(&instance, surface.as_ref(), renderer_config).await?;
+        let info = adapter.get_info();
+        log::info!("Using GPU: {} ({:?})", info.name, info.backend);
+
+        // Request device and queue
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    // Lets MSAA use every sample count the adapter supports, not just 4,
+                    // enables the wireframe debug view where line rasterization is available,
+                    // keeps BCn textures compressed on the GPU where it can sample them,
+                    // and allows GPU pass timing where timestamps are supported
+                    required_features: adapter.features()
+                        & (wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                            | wgpu::Features::POLYGON_MODE_LINE
+                            | wgpu::Features::TEXTURE_COMPRESSION_BC
+                            | wgpu::Features::TIMESTAMP_QUERY),
+                    required_limits: wgpu::Limits::default(),
                     label: None,
                 },
                 None,
@@ -217,43 +1206,77 @@ impl Renderer {
             .await
             .map_err(|e| format!("Failed to create device: {}", e))?;
 
-        // Configure surface
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+        // Configure surface; headless frames are RGBA8, which readback supports
+        let (surface_format, surface_usages, alpha_mode) = match &surface {
+            Some(surface) => {
+                let surface_caps = surface.get_capabilities(&adapter);
+                let format = surface_caps
+                    .formats
+                    .iter()
+                    .copied()
+                    .find(|f| f.is_srgb())
+                    .unwrap_or(surface_caps.formats[0]);
+                (format, surface_caps.usages, surface_caps.alpha_modes[0])
+            }
+            None => (
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                wgpu::TextureUsages::COPY_SRC,
+                wgpu::CompositeAlphaMode::Opaque,
+            ),
+        };
+
+        // Validate the requested MSAA sample count for both the color and depth targets
+        let adapter_specific = device
+            .features()
+            .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+        let sample_count = pick_sample_count(renderer_config.msaa_samples, |count| {
+            (count == 4 || adapter_specific)
+                && adapter.get_texture_format_features(HDR_FORMAT).flags.sample_count_supported(count)
+                && adapter.get_texture_format_features(DEPTH_FORMAT).flags.sample_count_supported(count)
+        });
+        if sample_count != renderer_config.msaa_samples.max(1) {
+            log::warn!(
+                "MSAA x{} is not supported, using x{}",
+                renderer_config.msaa_samples,
+                sample_count
+            );
+        }
+
+        // Copying out of the swapchain is needed for frame capture
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT | (surface_usages & wgpu::TextureUsages::COPY_SRC);
 
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: surface_format,
-            width: size.width,
-            height: size.height,
+            width: size.0,
+            height: size.1,
             present_mode: if renderer_config.target_fps == 0 {
                 wgpu::PresentMode::AutoNoVsync
             } else {
                 wgpu::PresentMode::AutoVsync
             },
-            alpha_mode: surface_caps.alpha_modes[0],
+            alpha_mode,
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
 
-        surface.configure(&device, &config);
+        let offscreen = match &surface {
+            Some(surface) => {
+                surface.configure(&device, &config);
+                None
+            }
+            None => Some(create_offscreen_texture(&device, &config)),
+        };
 
         // Create camera
         let camera = Camera::new(
             Vec3::new(0.0, 2.0, 5.0),
             Vec3::ZERO,
-            size.width as f32 / size.height as f32,
+            size.0 as f32 / size.1 as f32,
         );
 
         // Create camera buffer
-        let camera_uniform = CameraUniform {
-            view_proj: camera.view_proj_matrix().to_cols_array_2d(),
-        };
+        let camera_uniform = CameraUniform::new(&camera);
 
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Camera Buffer"),
@@ -261,12 +1284,259 @@ impl Renderer {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        // Camera bind group layout
+        // Light buffer
+        let light = DirectionalLight::default();
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::cast_slice(&[light.to_uniform()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Point and spot lights: a count header followed by the light array
+        let max_lights = renderer_config.max_lights as usize;
+        let point_light_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Point Light Buffer"),
+            size: (16 + std::mem::size_of::<PointLightUniform>() * max_lights.max(1)) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Camera bind group layout (camera + lights + reflections)
+        let camera_layout_entries = [
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ];
         let camera_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[camera_layout_entries.as_slice(), &Reflections::layout_entries()].concat(),
+                label: Some("camera_bind_group_layout"),
+            });
+
+        let reflections = Reflections::new(&device);
+        let camera_bind_group = create_camera_bind_group(
+            &device,
+            &camera_bind_group_layout,
+            [&camera_buffer, &light_buffer, &point_light_buffer],
+            &reflections,
+            ReflectionBinding::Main,
+            "camera_bind_group",
+        );
+        // Reflection captures share one camera, moved between their submissions
+        let reflection_camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Reflection Camera Buffer"),
+            contents: bytemuck::cast_slice(&[camera_uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let reflection_camera = ViewCamera {
+            camera: camera.clone(),
+            viewport: Viewport::FULL,
+            clear: CameraClear::Depth,
+            order: 0,
+            bind_group: create_camera_bind_group(
+                &device,
+                &camera_bind_group_layout,
+                [&reflection_camera_buffer, &light_buffer, &point_light_buffer],
+                &reflections,
+                ReflectionBinding::Capture,
+                "reflection_camera_bind_group",
+            ),
+            buffer: reflection_camera_buffer,
+        };
+
+        // Skinning reads joint matrices from a storage buffer in the vertex shader
+        let skinning = adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::VERTEX_STORAGE);
+        if !skinning {
+            log::warn!("Vertex storage buffers unsupported; skinned meshes will keep their bind pose");
+        }
+
+        // Object bind group layout (model matrices, one slot per draw, and joint matrices)
+        let mut object_entries = vec![wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<ObjectUniform>() as u64),
+            },
+            count: None,
+        }];
+        if skinning {
+            object_entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Mat4>() as u64),
+                },
+                count: None,
+            });
+        }
+        let object_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &object_entries,
+                label: Some("object_bind_group_layout"),
+            });
+
+        let objects = UniformRing::new(&device, "Object Buffer");
+        let joint_capacity = 64;
+        let joint_buffer = skinning.then(|| Self::create_joint_buffer(&device, joint_capacity));
+        let object_bind_group = Self::create_object_bind_group(
+            &device,
+            &object_bind_group_layout,
+            objects.buffer(),
+            joint_buffer.as_ref(),
+        );
+
+        let depth_view = Self::create_depth_view(&device, size.0, size.1, sample_count);
+        let clear_pipeline = Self::create_clear_pipeline(&device, sample_count);
+        let msaa_view = Self::create_msaa_view(&device, &config, sample_count);
+        let hdr_view = create_hdr_view(&device, config.width, config.height);
+        let anti_aliasing = renderer_config.aa_mode;
+        let anti_alias_pipeline = (anti_aliasing != AntiAliasing::None)
+            .then(|| AntiAliasPipeline::new(&device, &hdr_view, config.width, config.height));
+        let color_luts = ColorLuts::new(&device, &queue);
+        let tone_map_pipeline = ToneMapPipeline::new(
+            &device,
+            config.format,
+            anti_alias_pipeline.as_ref().map_or(&hdr_view, |aa| aa.output()),
+            color_luts.layout(),
+        );
+
+        // Texture bind group layout (diffuse texture + sampler)
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("texture_bind_group_layout"),
+            });
+
+        let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // Untextured meshes sample a 1x1 white texture
+        let white_texture = device.create_texture_with_data(
+            &queue,
+            &wgpu::TextureDescriptor {
+                label: Some("White Texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &[255, 255, 255, 255],
+        );
+        let white_texture_view = white_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Materials without a normal map sample a flat tangent-space normal
+        let flat_normal_texture = device.create_texture_with_data(
+            &queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Flat Normal Texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &[128, 128, 255, 255],
+        );
+        let flat_normal_view = flat_normal_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let white_texture_bind_group = Self::create_texture_bind_group(
+            &device,
+            &texture_bind_group_layout,
+            &texture_sampler,
+            &white_texture_view,
+        );
+
+        // Pipeline layout
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &camera_bind_group_layout,
+                    &object_bind_group_layout,
+                    &texture_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        // Material pipeline layout (adds the material parameters at group 3)
+        let material_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -274,147 +1544,2476 @@ impl Renderer {
                     },
                     count: None,
                 }],
-                label: Some("camera_bind_group_layout"),
+                label: Some("material_bind_group_layout"),
+            });
+        let material_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Material Pipeline Layout"),
+                bind_group_layouts: &[
+                    &camera_bind_group_layout,
+                    &object_bind_group_layout,
+                    &texture_bind_group_layout,
+                    &material_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
             });
 
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-            label: Some("camera_bind_group"),
-        });
+        // PBR material bind group layout (factors, base/metallic-roughness/emissive textures,
+        // sampler, normal map)
+        let pbr_texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let pbr_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    pbr_texture_entry(1),
+                    pbr_texture_entry(2),
+                    pbr_texture_entry(3),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    pbr_texture_entry(5),
+                ],
+                label: Some("pbr_bind_group_layout"),
+            });
+
+        let shader_sources = ShaderSources::default();
+        let (mesh_pipelines, instanced_pipeline) = Self::create_default_pipelines(
+            &device,
+            &render_pipeline_layout,
+            &shader_sources,
+            sample_count,
+            skinning,
+        );
+        let instance_capacity = 1024;
+        let instance_buffer = Self::create_instance_buffer(&device, instance_capacity);
+
+        log::info!("Renderer initialized: {}x{}", size.0, size.1);
+
+        let mut renderer = Self {
+            instance,
+            surface,
+            offscreen,
+            device,
+            queue,
+            config,
+            size,
+            mesh_pipelines,
+            instanced_pipeline,
+            instance_buffer,
+            instance_capacity,
+            instances: Vec::new(),
+            instance_batches: Vec::new(),
+            camera,
+            viewport: Viewport::FULL,
+            camera_clear: CameraClear::Depth,
+            view_cameras: Vec::new(),
+            clear_pipeline,
+            frustum_culling: true,
+            occlusion: None,
+            outline: None,
+            depth_prepass: None,
+            outlined: HashSet::new(),
+            outline_color: Color::rgb(1.0, 0.6, 0.1),
+            outline_width: DEFAULT_OUTLINE_WIDTH,
+            gpu_timer: None,
+            render_stats: RenderStats::default(),
+            camera_buffer,
+            camera_bind_group,
+            camera_bind_group_layout,
+            object_bind_group_layout,
+            objects,
+            object_bind_group,
+            joint_buffer,
+            joint_capacity,
+            depth_view,
+            sample_count,
+            msaa_view,
+            hdr_view,
+            tone_map_pipeline,
+            tone_mapping: ToneMapping::default(),
+            color_luts,
+            color_grading: None,
+            post_effects: Vec::new(),
+            debug_view: DebugView::Shaded,
+            debug_view_pipelines: None,
+            bloom: BloomSettings::default(),
+            bloom_pipeline: None,
+            vignette: VignetteSettings::default(),
+            film_grain: FilmGrainSettings::default(),
+            chromatic_aberration: ChromaticAberrationSettings::default(),
+            lens_pipeline: None,
+            anti_aliasing,
+            anti_alias_pipeline,
+            texture_bind_group_layout,
+            material_bind_group_layout,
+            render_pipeline_layout,
+            material_pipeline_layout,
+            materials: Vec::new(),
+            pbr_bind_group_layout,
+            pbr_pipeline: None,
+            texture_sampler,
+            white_texture_view,
+            flat_normal_view,
+            white_texture_bind_group,
+            texture_bind_groups: HashMap::new(),
+            mesh_queue: Vec::new(),
+            light,
+            light_buffer,
+            point_light_buffer,
+            max_lights,
+            light_queue: Vec::new(),
+            sky: None,
+            sky_pipeline: None,
+            grid: None,
+            grid_pipeline: None,
+            terrain: None,
+            terrain_pipeline: None,
+            reflections,
+            planar_reflection: None,
+            reflection_camera,
+            capture: None,
+            capture_readback: None,
+            debug_draw: DebugDraw::new(),
+            debug_draw_pipeline: None,
+            ribbons: Ribbons::new(),
+            ribbon_pipeline: None,
+            particle_pipeline: None,
+            render_graph: RenderGraph::new(),
+            adapter_info: adapter.get_info(),
+            clear_color: renderer_config.clear_color,
+            shader_sources,
+            shader_watcher: None,
+        };
+        if let Some(dir) = &renderer_config.shader_dir {
+            renderer.enable_shader_hot_reload(dir);
+        }
+        renderer.set_depth_prepass(renderer_config.depth_prepass);
+        if renderer_config.grid {
+            renderer.set_grid(Some(GridSettings::default()));
+        }
+        Ok(renderer)
+    }
 
-        // Shader
+    /// Build the default shader's pipelines and the instanced pipeline
+    fn create_default_pipelines(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        sources: &ShaderSources,
+        sample_count: u32,
+        skinning: bool,
+    ) -> (MaterialPipelines, wgpu::RenderPipeline) {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/default.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(format!("{}\n{}", sources.common, sources.default).into()),
         });
+        let desc = MeshPipelineDesc {
+            label: "Render Pipeline",
+            shader: &shader,
+            vs_entry: "vs_main",
+            fs_entry: "fs_main",
+            buffers: &[Vertex::desc()],
+            format: HDR_FORMAT,
+            sample_count,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            depth_test: true,
+            depth_write: true,
+        };
+        // Per-object and instanced draws share the layout
+        let mesh_pipelines = create_material_pipelines(device, layout, desc, skinning);
+        let instanced_pipeline = create_mesh_pipeline(
+            device,
+            layout,
+            MeshPipelineDesc {
+                label: "Instanced Render Pipeline",
+                vs_entry: "vs_instanced",
+                fs_entry: "fs_instanced",
+                buffers: &[Vertex::desc(), InstanceData::desc()],
+                ..desc
+            },
+        );
+        (mesh_pipelines, instanced_pipeline)
+    }
 
-        // Pipeline layout
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&camera_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        // Render pipeline
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
+    fn create_clear_pipeline(device: &wgpu::Device, sample_count: u32) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Clear Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/clear.wgsl").into()),
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Clear Pipeline"),
+            layout: None,
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
+                buffers: &[],
                 compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    format: HDR_FORMAT,
+                    // Output (1.0) times the blend constant replaces the color
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::Constant,
+                            dst_factor: wgpu::BlendFactor::Zero,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::Constant,
+                            dst_factor: wgpu::BlendFactor::Zero,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                    }),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
                 compilation_options: Default::default(),
             }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
             multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
+                count: sample_count,
+                ..Default::default()
             },
             multiview: None,
-        });
-
-        log::info!("Renderer initialized: {}x{}", size.width, size.height);
-
-        Ok(Self {
-            surface,
-            device,
-            queue,
-            config,
-            size: (size.width, size.height),
-            render_pipeline,
-            camera,
-            camera_buffer,
-            camera_bind_group,
-            clear_color: Color::new(0.1, 0.2, 0.3, 1.0),
         })
     }
 
-    /// Get reference to the device
-    pub fn device(&self) -> &wgpu::Device {
-        &self.device
-    }
-
-    /// Get reference to the queue
-    pub fn queue(&self) -> &wgpu::Queue {
-        &self.queue
+    fn create_depth_view(device: &wgpu::Device, width: u32, height: u32, sample_count: u32) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
     }
 
-    /// Get mutable reference to the camera
-    pub fn camera_mut(&mut self) -> &mut Camera {
-        &mut self.camera
+    /// Create the multisampled color target (None without MSAA)
+    fn create_msaa_view(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> Option<wgpu::TextureView> {
+        if sample_count <= 1 {
+            return None;
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Color Texture"),
+            size: wgpu::Extent3d {
+                width: config.width.max(1),
+                height: config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
     }
 
-    /// Get reference to the camera
-    pub fn camera(&self) -> &Camera {
-        &self.camera
+    /// Recreate the depth, MSAA, and HDR targets after the surface size changed
+    fn recreate_targets(&mut self) {
+        self.depth_view = Self::create_depth_view(&self.device, self.config.width, self.config.height, self.sample_count);
+        self.msaa_view = Self::create_msaa_view(&self.device, &self.config, self.sample_count);
+        self.hdr_view = create_hdr_view(&self.device, self.config.width, self.config.height);
+        if let Some(bloom) = &mut self.bloom_pipeline {
+            bloom.resize(&self.device, &self.hdr_view, self.config.width, self.config.height);
+        }
+        if let Some(lens) = &mut self.lens_pipeline {
+            lens.resize(&self.device, &self.hdr_view, self.config.width, self.config.height);
+        }
+        if let Some(aa) = &mut self.anti_alias_pipeline {
+            aa.resize(&self.device, &self.hdr_view, self.config.width, self.config.height);
+        }
+        if let Some(outline) = &mut self.outline {
+            outline.resize(&self.device, (self.config.width, self.config.height));
+        }
+        self.update_tone_map_source();
     }
 
-    /// Set clear color
-    pub fn set_clear_color(&mut self, color: Color) {
-        self.clear_color = color;
+    /// Tone map the anti-aliased color when anti-aliasing is on, else the HDR target
+    fn update_tone_map_source(&mut self) {
+        let source = match &self.anti_alias_pipeline {
+            Some(aa) if self.anti_aliasing != AntiAliasing::None => aa.output(),
+            _ => &self.hdr_view,
+        };
+        self.tone_map_pipeline.set_source(&self.device, source);
     }
 
-    /// Resize the renderer
-    pub fn resize(&mut self, new_size: (u32, u32)) {
-        if new_size.0 > 0 && new_size.1 > 0 {
-            self.size = new_size;
-            self.config.width = new_size.0;
-            self.config.height = new_size.1;
-            self.surface.configure(&self.device, &self.config);
-            self.camera.update_aspect_ratio(new_size.0, new_size.1);
-            log::debug!("Resized to: {}x{}", new_size.0, new_size.1);
+    /// Color attachment drawing into the MSAA target (resolved into `view` if `resolve` is
+    /// set), or straight into `view` without MSAA
+    fn color_attachment<'a>(
+        &'a self,
+        view: &'a wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+        resolve: bool,
+    ) -> wgpu::RenderPassColorAttachment<'a> {
+        let ops = wgpu::Operations {
+            load,
+            store: wgpu::StoreOp::Store,
+        };
+        match &self.msaa_view {
+            Some(msaa_view) => wgpu::RenderPassColorAttachment {
+                view: msaa_view,
+                resolve_target: resolve.then_some(view),
+                ops,
+            },
+            None => wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops,
+            },
         }
     }
 
-    /// Update camera uniform buffer
-    pub fn update_camera(&mut self) {
-        let camera_uniform = CameraUniform {
-            view_proj: self.camera.view_proj_matrix().to_cols_array_2d(),
+    fn create_joint_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Joint Buffer"),
+            size: (capacity * std::mem::size_of::<Mat4>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_object_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        object_buffer: &wgpu::Buffer,
+        joint_buffer: Option<&wgpu::Buffer>,
+    ) -> wgpu::BindGroup {
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: object_buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(std::mem::size_of::<ObjectUniform>() as u64),
+            }),
+        }];
+        if let Some(joint_buffer) = joint_buffer {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 1,
+                resource: joint_buffer.as_entire_binding(),
+            });
+        }
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &entries,
+            label: Some("object_bind_group"),
+        })
+    }
+
+    fn create_texture_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("texture_bind_group"),
+        })
+    }
+
+    /// Create bind groups for textures drawn for the first time
+    fn prepare_textures(&mut self, handles: impl Iterator<Item = TextureHandle>, resources: &ResourceManager) {
+        for handle in handles {
+            if self.texture_bind_groups.contains_key(&handle) {
+                continue;
+            }
+            if let Some(texture) = resources.get_texture(handle) {
+                let bind_group = Self::create_texture_bind_group(
+                    &self.device,
+                    &self.texture_bind_group_layout,
+                    &texture.sampler,
+                    &texture.view,
+                );
+                self.texture_bind_groups.insert(handle, bind_group);
+            }
+        }
+    }
+
+    fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (capacity * std::mem::size_of::<InstanceData>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Upload queued instances, growing the instance buffer if needed
+    fn write_instances(&mut self) {
+        if self.instances.len() > self.instance_capacity {
+            self.instance_capacity = self.instances.len().next_power_of_two();
+            self.instance_buffer = Self::create_instance_buffer(&self.device, self.instance_capacity);
+        }
+        if !self.instances.is_empty() {
+            self.queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&self.instances));
+        }
+    }
+
+    /// Upload the object uniforms pushed this frame, rebinding the object buffer if it grew
+    fn flush_objects(&mut self) {
+        if self.objects.flush(&self.device, &self.queue) {
+            self.object_bind_group = Self::create_object_bind_group(
+                &self.device,
+                &self.object_bind_group_layout,
+                self.objects.buffer(),
+                self.joint_buffer.as_ref(),
+            );
+        }
+    }
+
+    /// Upload the joint matrices of skinned meshes, growing the joint buffer if needed
+    fn write_joints(&mut self, joints: &[[[f32; 4]; 4]]) {
+        if self.joint_buffer.is_none() || joints.is_empty() {
+            return;
+        }
+        if joints.len() > self.joint_capacity {
+            self.joint_capacity = joints.len().next_power_of_two();
+            self.joint_buffer = Some(Self::create_joint_buffer(&self.device, self.joint_capacity));
+            self.object_bind_group = Self::create_object_bind_group(
+                &self.device,
+                &self.object_bind_group_layout,
+                self.objects.buffer(),
+                self.joint_buffer.as_ref(),
+            );
+        }
+        if let Some(buffer) = &self.joint_buffer {
+            self.queue.write_buffer(buffer, 0, bytemuck::cast_slice(joints));
+        }
+    }
+
+    /// Get reference to the device
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    /// Get reference to the queue
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Get information about the GPU in use
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
+    /// Get mutable reference to the camera
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    /// Get reference to the camera
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Get the part of the surface the main camera draws into
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// Set the part of the surface the main camera draws into
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+        self.update_aspect_ratios();
+    }
+
+    /// Add a camera drawing the scene into `viewport` (split screen, minimaps, etc.)
+    ///
+    /// Cameras draw in ascending `order`; the main camera has order 0, so use a
+    /// higher order for cameras drawn over it. Sky, lines, trails, and particles are
+    /// only drawn by the main camera.
+    pub fn add_camera(&mut self, camera: Camera, viewport: Viewport, order: i32) -> CameraHandle {
+        let buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("View Camera Buffer"),
+            contents: bytemuck::cast_slice(&[CameraUniform::new(&camera)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = create_camera_bind_group(
+            &self.device,
+            &self.camera_bind_group_layout,
+            [&buffer, &self.light_buffer, &self.point_light_buffer],
+            &self.reflections,
+            ReflectionBinding::View,
+            "view_camera_bind_group",
+        );
+
+        let view = ViewCamera {
+            camera,
+            viewport,
+            clear: CameraClear::Depth,
+            order,
+            buffer,
+            bind_group,
+        };
+        let handle = match self.view_cameras.iter().position(Option::is_none) {
+            Some(free) => {
+                self.view_cameras[free] = Some(view);
+                free
+            }
+            None => {
+                self.view_cameras.push(Some(view));
+                self.view_cameras.len() - 1
+            }
+        };
+        self.update_aspect_ratios();
+        handle
+    }
+
+    /// Remove a camera added with [`Renderer::add_camera`]
+    pub fn remove_camera(&mut self, handle: CameraHandle) -> bool {
+        self.view_cameras.get_mut(handle).and_then(Option::take).is_some()
+    }
+
+    /// Get a camera added with [`Renderer::add_camera`]
+    pub fn view_camera(&self, handle: CameraHandle) -> Option<&Camera> {
+        self.view_cameras.get(handle)?.as_ref().map(|view| &view.camera)
+    }
+
+    /// Get a mutable camera added with [`Renderer::add_camera`]
+    pub fn view_camera_mut(&mut self, handle: CameraHandle) -> Option<&mut Camera> {
+        self.view_cameras.get_mut(handle)?.as_mut().map(|view| &mut view.camera)
+    }
+
+    /// Move a camera added with [`Renderer::add_camera`] to another viewport
+    pub fn set_camera_viewport(&mut self, handle: CameraHandle, viewport: Viewport) {
+        if let Some(view) = self.view_cameras.get_mut(handle).and_then(Option::as_mut) {
+            view.viewport = viewport;
+            self.update_aspect_ratios();
+        }
+    }
+
+    /// Get how the main camera's pass starts out
+    pub fn camera_clear(&self) -> CameraClear {
+        self.camera_clear
+    }
+
+    /// Set how the main camera's pass starts out (clearing depth by default)
+    pub fn set_camera_clear(&mut self, clear: CameraClear) {
+        self.camera_clear = clear;
+    }
+
+    /// Set how the pass of a camera added with [`Renderer::add_camera`] starts out
+    pub fn set_view_camera_clear(&mut self, handle: CameraHandle, clear: CameraClear) {
+        if let Some(view) = self.view_cameras.get_mut(handle).and_then(Option::as_mut) {
+            view.clear = clear;
+        }
+    }
+
+    /// Change the draw order of a camera added with [`Renderer::add_camera`]
+    pub fn set_camera_order(&mut self, handle: CameraHandle, order: i32) {
+        if let Some(view) = self.view_cameras.get_mut(handle).and_then(Option::as_mut) {
+            view.order = order;
+        }
+    }
+
+    /// Check if meshes outside a camera's view are skipped
+    pub fn frustum_culling(&self) -> bool {
+        self.frustum_culling
+    }
+
+    /// Enable or disable skipping meshes outside a camera's view (on by default)
+    pub fn set_frustum_culling(&mut self, enabled: bool) {
+        self.frustum_culling = enabled;
+    }
+
+    /// Check if meshes hidden behind others are skipped
+    pub fn occlusion_culling(&self) -> bool {
+        self.occlusion.is_some()
+    }
+
+    /// Enable or disable skipping entities that occlusion queries found hidden behind
+    /// other meshes in recent frames (main camera only, off by default)
+    pub fn set_occlusion_culling(&mut self, enabled: bool) {
+        if !enabled {
+            self.occlusion = None;
+        } else if self.occlusion.is_none() {
+            self.occlusion = Some(OcclusionCuller::new(
+                &self.device,
+                &self.camera_bind_group_layout,
+                HDR_FORMAT,
+                self.sample_count,
+            ));
+        }
+    }
+
+    /// Outline `entities` in `color`, for editor selection or gameplay highlighting;
+    /// an empty slice removes all outlines
+    pub fn set_outlined_entities(&mut self, entities: &[EntityId], color: Color) {
+        self.outlined = entities.iter().copied().collect();
+        self.outline_color = color;
+        if !self.outlined.is_empty() && self.outline.is_none() {
+            self.outline = Some(self.create_outline_pipeline(&self.shader_sources));
+        }
+    }
+
+    /// Get the outlined entities
+    pub fn outlined_entities(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.outlined.iter().copied()
+    }
+
+    /// Get the outline width in pixels
+    pub fn outline_width(&self) -> f32 {
+        self.outline_width
+    }
+
+    /// Set the outline width in pixels
+    pub fn set_outline_width(&mut self, width: f32) {
+        self.outline_width = width.max(0.0);
+    }
+
+    /// Check if opaque meshes are drawn into a depth pre-pass first
+    pub fn depth_prepass(&self) -> bool {
+        self.depth_prepass.is_some()
+    }
+
+    /// Enable or disable drawing opaque meshes' depth before shading them, so each pixel
+    /// is shaded once even where meshes overlap (off by default)
+    pub fn set_depth_prepass(&mut self, enabled: bool) {
+        if !enabled {
+            self.depth_prepass = None;
+        } else if self.depth_prepass.is_none() {
+            self.depth_prepass = Some(self.create_depth_prepass(&self.shader_sources));
+        }
+    }
+
+    fn create_depth_prepass(&self, sources: &ShaderSources) -> DepthPrepass {
+        DepthPrepass::new(
+            &self.device,
+            &self.camera_bind_group_layout,
+            &self.object_bind_group_layout,
+            &sources.common,
+            self.sample_count,
+            self.joint_buffer.is_some(),
+        )
+    }
+
+    fn create_outline_pipeline(&self, sources: &ShaderSources) -> OutlinePipeline {
+        OutlinePipeline::new(
+            &self.device,
+            &self.material_pipeline_layout,
+            &self.material_bind_group_layout,
+            &sources.common,
+            self.sample_count,
+            self.joint_buffer.is_some(),
+            self.size,
+        )
+    }
+
+    /// Check if GPU pass timings are measured
+    pub fn gpu_timing(&self) -> bool {
+        self.gpu_timer.is_some()
+    }
+
+    /// Enable or disable measuring GPU time per pass with timestamp queries (off by
+    /// default), reported in [`RenderStats::pass_timings`]
+    pub fn set_gpu_timing(&mut self, enabled: bool) {
+        if !enabled {
+            self.gpu_timer = None;
+        } else if self.gpu_timer.is_none() {
+            if !self.device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+                log::warn!("GPU timing is not supported on this device");
+                return;
+            }
+            self.gpu_timer = Some(GpuTimer::new(&self.device, &self.queue));
+        }
+    }
+
+    /// Watch the built-in mesh shaders in `shader_dir` (the engine's `src/shaders`) and
+    /// material shaders created with [`MaterialDescriptor::from_file`], rebuilding their
+    /// pipelines when the files change; for development
+    pub fn enable_shader_hot_reload(&mut self, shader_dir: impl AsRef<Path>) {
+        let mut watcher = ShaderWatcher::new(shader_dir.as_ref());
+        for path in self.materials.iter().filter_map(|m| m.descriptor.as_ref()?.path.as_ref()) {
+            watcher.watch(path);
+        }
+        log::info!("Watching shaders in {}", watcher.dir().display());
+        self.shader_watcher = Some(watcher);
+        // The files may already differ from the compiled-in shaders
+        self.reload_builtin_shaders();
+    }
+
+    /// Stop watching shader files
+    pub fn disable_shader_hot_reload(&mut self) {
+        self.shader_watcher = None;
+    }
+
+    /// Rebuild the pipelines of shader files changed since the last check
+    fn reload_changed_shaders(&mut self) {
+        let Some(watcher) = &mut self.shader_watcher else {
+            return;
+        };
+        let changed = watcher.poll();
+        if changed.is_empty() {
+            return;
+        }
+        if changed.iter().any(|path| watcher.is_builtin(path)) {
+            self.reload_builtin_shaders();
+        }
+        for handle in 0..self.materials.len() {
+            let path = self.materials[handle].descriptor.as_ref().and_then(|d| d.path.as_ref());
+            if path.is_some_and(|path| changed.contains(path)) {
+                self.reload_material(handle);
+            }
+        }
+    }
+
+    /// Recompile the built-in shaders from the watched directory, keeping the current
+    /// pipelines if they fail
+    fn reload_builtin_shaders(&mut self) {
+        let Some(watcher) = &self.shader_watcher else {
+            return;
+        };
+        let sources = match ShaderSources::load(watcher.dir()) {
+            Ok(sources) => sources,
+            Err(e) => {
+                log::error!("{}", e);
+                return;
+            }
+        };
+        if sources == self.shader_sources {
+            return;
+        }
+
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let (mesh_pipelines, instanced_pipeline) = Self::create_default_pipelines(
+            &self.device,
+            &self.render_pipeline_layout,
+            &sources,
+            self.sample_count,
+            self.joint_buffer.is_some(),
+        );
+        let pbr_pipeline = self.pbr_pipeline.as_ref().map(|_| self.create_pbr_pipelines(&sources));
+        let debug_view_pipelines = self
+            .debug_view_pipelines
+            .as_ref()
+            .map(|_| self.create_debug_view_pipelines(self.debug_view, &sources));
+        let outline = self.outline.as_ref().map(|_| self.create_outline_pipeline(&sources));
+        let depth_prepass = self.depth_prepass.as_ref().map(|_| self.create_depth_prepass(&sources));
+        if let Some(error) = pollster::block_on(self.device.pop_error_scope()) {
+            log::error!("Failed to reload shaders: {}", error);
+            return;
+        }
+
+        self.mesh_pipelines = mesh_pipelines;
+        self.instanced_pipeline = instanced_pipeline;
+        self.debug_view_pipelines = debug_view_pipelines;
+        self.outline = outline;
+        self.depth_prepass = depth_prepass;
+        if let Some(pbr) = pbr_pipeline {
+            for material in self.materials.iter_mut().filter(|m| m.descriptor.is_none()) {
+                material.pipelines = pbr.clone();
+            }
+            self.pbr_pipeline = Some(pbr);
+        }
+        let common_changed = sources.common != self.shader_sources.common;
+        self.shader_sources = sources;
+        log::info!("Reloaded built-in shaders");
+
+        // Every custom material includes the common bindings
+        if common_changed {
+            for handle in 0..self.materials.len() {
+                if self.materials[handle].descriptor.is_some() {
+                    self.reload_material(handle);
+                }
+            }
+        }
+    }
+
+    /// Recompile a custom material, re-reading its shader file if it has one
+    fn reload_material(&mut self, handle: MaterialHandle) {
+        let Some(mut descriptor) = self.materials[handle].descriptor.clone() else {
+            return;
+        };
+        if let Some(path) = &descriptor.path {
+            match std::fs::read_to_string(path) {
+                Ok(shader) => descriptor.shader = shader,
+                Err(e) => {
+                    log::error!("Failed to read shader {}: {}", path.display(), e);
+                    return;
+                }
+            }
+        }
+        match self.compile_material(&descriptor) {
+            Ok(pipelines) => {
+                let material = &mut self.materials[handle];
+                material.pipelines = pipelines;
+                material.descriptor = Some(descriptor);
+                log::info!("Reloaded material: {}", material.name);
+            }
+            Err(e) => log::error!("{}", e),
+        }
+    }
+
+    /// Get the draw counts and pass timings of the last rendered frame
+    pub fn render_stats(&self) -> RenderStats {
+        self.render_stats.clone()
+    }
+
+    /// Match every camera's aspect ratio to its viewport
+    fn update_aspect_ratios(&mut self) {
+        let (_, _, width, height) = self.viewport.to_pixels(self.size);
+        self.camera.update_aspect_ratio(width, height);
+        for view in self.view_cameras.iter_mut().flatten() {
+            let (_, _, width, height) = view.viewport.to_pixels(self.size);
+            view.camera.update_aspect_ratio(width, height);
+        }
+    }
+
+    /// Get the surface texture format
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    /// Get the curve used to map HDR scene color to the surface
+    pub fn tone_mapping(&self) -> ToneMapping {
+        self.tone_mapping
+    }
+
+    /// Set the tone mapping curve (exposure is set on the [`Camera`])
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    /// Upload a color grading LUT to use in [`ColorGrading`]
+    pub fn add_color_lut(&mut self, lut: &ColorLut) -> ColorLutHandle {
+        self.color_luts.add(&self.device, &self.queue, lut)
+    }
+
+    /// Get the color grading (if enabled)
+    pub fn color_grading(&self) -> Option<&ColorGrading> {
+        self.color_grading.as_ref()
+    }
+
+    /// Grade the tone-mapped image through LUTs from [`Renderer::add_color_lut`]; set it
+    /// again with other LUTs or blend values to swap or cross-fade them
+    pub fn set_color_grading(&mut self, grading: Option<ColorGrading>) {
+        if let Some(grading) = &grading {
+            let mut handles = std::iter::once(grading.lut).chain(grading.blend_lut);
+            if handles.any(|handle| !self.color_luts.contains(handle)) {
+                log::warn!("Color grading uses an invalid LUT handle: {:?}", grading);
+            }
+        }
+        self.color_luts.bind(&self.device, grading.as_ref());
+        self.color_grading = grading;
+    }
+
+    /// Get the active debug view
+    pub fn debug_view(&self) -> DebugView {
+        self.debug_view
+    }
+
+    /// Switch how meshes are shaded; falls back to [`DebugView::Shaded`] if the view
+    /// isn't supported by the device
+    pub fn set_debug_view(&mut self, view: DebugView) {
+        if view == self.debug_view {
+            return;
+        }
+        if view == DebugView::Wireframe && !self.device.features().contains(wgpu::Features::POLYGON_MODE_LINE) {
+            log::warn!("Wireframe debug view is not supported on this device");
+            return;
+        }
+        self.debug_view = view;
+        self.debug_view_pipelines =
+            (view != DebugView::Shaded).then(|| self.create_debug_view_pipelines(view, &self.shader_sources));
+    }
+
+    fn create_debug_view_pipelines(&self, view: DebugView, sources: &ShaderSources) -> DebugViewPipelines {
+        let shader = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Debug View Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                format!("{}\n{}\n{}", sources.common, sources.default, sources.debug_view).into(),
+            ),
+        });
+        let overdraw = view == DebugView::Overdraw;
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::OVER,
+        };
+        let desc = |label, vs_entry, buffers| MeshPipelineDesc {
+            label,
+            shader: &shader,
+            vs_entry,
+            fs_entry: view.fs_entry(),
+            buffers,
+            format: HDR_FORMAT,
+            sample_count: self.sample_count,
+            blend: overdraw.then_some(additive),
+            // Show back faces too, since missing or flipped triangles are what's being debugged
+            cull_mode: None,
+            polygon_mode: if view == DebugView::Wireframe {
+                wgpu::PolygonMode::Line
+            } else {
+                wgpu::PolygonMode::Fill
+            },
+            depth_test: !overdraw,
+            depth_write: true,
+        };
+        DebugViewPipelines {
+            object: create_mesh_pipeline(
+                &self.device,
+                &self.render_pipeline_layout,
+                desc("Debug View Pipeline", "vs_main", &[Vertex::desc()]),
+            ),
+            instanced: create_mesh_pipeline(
+                &self.device,
+                &self.render_pipeline_layout,
+                desc(
+                    "Instanced Debug View Pipeline",
+                    "vs_instanced",
+                    &[Vertex::desc(), InstanceData::desc()],
+                ),
+            ),
+        }
+    }
+
+    /// Get the post-processing effects applied before tone mapping, in order
+    pub fn post_effects(&self) -> &[PostEffect] {
+        &self.post_effects
+    }
+
+    /// Set the post-processing stack (an empty slice disables post-processing)
+    pub fn set_post_effects(&mut self, effects: &[PostEffect]) {
+        self.post_effects = effects.to_vec();
+        if self.post_effects.contains(&PostEffect::Bloom) && self.bloom_pipeline.is_none() {
+            self.bloom_pipeline = Some(BloomPipeline::new(
+                &self.device,
+                &self.hdr_view,
+                self.config.width,
+                self.config.height,
+            ));
+        }
+        if self.post_effects.iter().any(|effect| effect.is_lens()) && self.lens_pipeline.is_none() {
+            self.lens_pipeline = Some(LensPipeline::new(
+                &self.device,
+                &self.hdr_view,
+                self.config.width,
+                self.config.height,
+            ));
+        }
+    }
+
+    /// Get the bloom settings
+    pub fn bloom(&self) -> &BloomSettings {
+        &self.bloom
+    }
+
+    /// Set the HDR brightness above which pixels bloom
+    pub fn set_bloom_threshold(&mut self, threshold: f32) {
+        self.bloom.threshold = threshold.max(0.0);
+    }
+
+    /// Set the strength of the bloom glow
+    pub fn set_bloom_intensity(&mut self, intensity: f32) {
+        self.bloom.intensity = intensity.max(0.0);
+    }
+
+    /// Set all bloom settings
+    pub fn set_bloom(&mut self, bloom: BloomSettings) {
+        self.bloom = bloom;
+    }
+
+    /// Get the vignette settings
+    pub fn vignette(&self) -> &VignetteSettings {
+        &self.vignette
+    }
+
+    /// Set how dark the vignette makes the screen corners (0.0..=1.0)
+    pub fn set_vignette_intensity(&mut self, intensity: f32) {
+        self.vignette.intensity = intensity.clamp(0.0, 1.0);
+    }
+
+    /// Set all vignette settings
+    pub fn set_vignette(&mut self, vignette: VignetteSettings) {
+        self.vignette = vignette;
+    }
+
+    /// Get the film grain settings
+    pub fn film_grain(&self) -> &FilmGrainSettings {
+        &self.film_grain
+    }
+
+    /// Set the strength of the film grain
+    pub fn set_film_grain_intensity(&mut self, intensity: f32) {
+        self.film_grain.intensity = intensity.max(0.0);
+    }
+
+    /// Set all film grain settings
+    pub fn set_film_grain(&mut self, film_grain: FilmGrainSettings) {
+        self.film_grain = film_grain;
+    }
+
+    /// Get the chromatic aberration settings
+    pub fn chromatic_aberration(&self) -> &ChromaticAberrationSettings {
+        &self.chromatic_aberration
+    }
+
+    /// Set the channel separation at the screen corners, in pixels
+    pub fn set_chromatic_aberration_intensity(&mut self, intensity: f32) {
+        self.chromatic_aberration.intensity = intensity.max(0.0);
+    }
+
+    /// Set all chromatic aberration settings
+    pub fn set_chromatic_aberration(&mut self, chromatic_aberration: ChromaticAberrationSettings) {
+        self.chromatic_aberration = chromatic_aberration;
+    }
+
+    /// Set clear color
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
+
+    /// Get the current directional light
+    pub fn light(&self) -> &DirectionalLight {
+        &self.light
+    }
+
+    /// Set the directional light and ambient term
+    pub fn set_light(&mut self, light: DirectionalLight) {
+        self.light = light;
+        self.queue.write_buffer(
+            &self.light_buffer,
+            0,
+            bytemuck::cast_slice(&[light.to_uniform()]),
+        );
+    }
+
+    /// Queue a point or spot light for the next frame, in addition to scene [`Light`]s
+    pub fn draw_light(&mut self, position: Vec3, direction: Vec3, light: &Light) {
+        if light.enabled {
+            self.light_queue.push(light.to_uniform(position, direction));
+        }
+    }
+
+    /// Get the maximum number of point and spot lights per frame
+    pub fn max_lights(&self) -> usize {
+        self.max_lights
+    }
+
+    /// Get the current sky (if enabled)
+    pub fn sky(&self) -> Option<&Sky> {
+        self.sky.as_ref()
+    }
+
+    /// Draw a procedural sky behind the scene instead of the clear color
+    pub fn set_sky(&mut self, sky: Option<Sky>) {
+        if sky.is_some() && self.sky_pipeline.is_none() {
+            self.sky_pipeline = Some(SkyPipeline::new(&self.device, HDR_FORMAT, self.sample_count));
+        }
+        self.sky = sky;
+    }
+
+    /// Get the ground grid settings (if enabled)
+    pub fn grid(&self) -> Option<&GridSettings> {
+        self.grid.as_ref()
+    }
+
+    /// Draw an infinite ground grid with origin axes under the main camera's scene
+    pub fn set_grid(&mut self, grid: Option<GridSettings>) {
+        if grid.is_some() && self.grid_pipeline.is_none() {
+            self.grid_pipeline = Some(GridPipeline::new(&self.device, HDR_FORMAT, self.sample_count));
+        }
+        self.grid = grid;
+    }
+
+    /// Get the terrain (if any)
+    pub fn terrain(&self) -> Option<&Terrain> {
+        self.terrain.as_ref()
+    }
+
+    /// Get mutable access to the terrain, e.g. to edit its splat map
+    pub fn terrain_mut(&mut self) -> Option<&mut Terrain> {
+        self.terrain.as_mut()
+    }
+
+    /// Draw a heightmap terrain in the scene, updating its LOD chunks from the main
+    /// camera every frame
+    ///
+    /// Until [`Renderer::set_terrain_layers`] is called all four layers are white.
+    pub fn set_terrain(&mut self, terrain: Option<Terrain>) {
+        if terrain.is_some() && self.terrain_pipeline.is_none() {
+            self.terrain_pipeline = Some(TerrainPipeline::new(
+                &self.device,
+                &self.camera_bind_group_layout,
+                HDR_FORMAT,
+                self.sample_count,
+                &self.white_texture_view,
+            ));
+        }
+        self.terrain = terrain;
+    }
+
+    /// Set the four textures the terrain's splat weights blend, each repeated `tiling`
+    /// times across the terrain
+    pub fn set_terrain_layers(
+        &mut self,
+        resources: &ResourceManager,
+        layers: [TextureHandle; 4],
+        tiling: [f32; 4],
+    ) -> Result<(), String> {
+        let Some(pipeline) = &mut self.terrain_pipeline else {
+            return Err("Set a terrain before its layers".to_string());
+        };
+        let mut views = Vec::with_capacity(4);
+        for handle in layers {
+            let texture = resources
+                .get_texture(handle)
+                .ok_or_else(|| format!("Terrain layer texture {} not found", handle))?;
+            views.push(&texture.view);
+        }
+        pipeline.set_layers(&self.device, [views[0], views[1], views[2], views[3]], tiling);
+        Ok(())
+    }
+
+    /// Get the planar reflection (if enabled)
+    pub fn planar_reflection(&self) -> Option<&PlanarReflection> {
+        self.planar_reflection.as_ref()
+    }
+
+    /// Mirror the scene into the PBR surfaces lying on a plane (floors, water), drawing
+    /// it again from the mirrored main camera every frame
+    pub fn set_planar_reflection(&mut self, reflection: Option<PlanarReflection>) {
+        self.planar_reflection = reflection;
+    }
+
+    /// Capture every [`ReflectionProbe`] again the next time it is used, after the
+    /// scene around it changed
+    pub fn rebake_reflection_probes(&mut self) {
+        self.reflections.rebake();
+    }
+
+    /// Get the debug lines drawn over the next frame
+    pub fn debug_draw_mut(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
+    }
+
+    /// Queue a mesh to be drawn with a model matrix by the next [`Renderer::render_scene`]
+    ///
+    /// The same mesh can be queued many times; only the per-draw model matrix differs.
+    pub fn draw_mesh(&mut self, mesh: MeshHandle, transform: Mat4, texture: Option<TextureHandle>) {
+        self.mesh_queue.push(MeshDraw {
+            mesh,
+            model: transform,
+            texture,
+        });
+    }
+
+    /// Queue many copies of a mesh to be drawn in one call by the next [`Renderer::render_scene`]
+    pub fn draw_instanced(&mut self, mesh: MeshHandle, instances: &[InstanceData], texture: Option<TextureHandle>) {
+        if instances.is_empty() {
+            return;
+        }
+        let start = self.instances.len() as u32;
+        self.instances.extend_from_slice(instances);
+        self.instance_batches.push(InstanceBatch {
+            mesh,
+            texture,
+            instances: start..self.instances.len() as u32,
+        });
+    }
+
+    /// Compile a custom material, reporting WGSL and pipeline errors instead of panicking
+    pub fn create_material(&mut self, descriptor: &MaterialDescriptor) -> Result<MaterialHandle, String> {
+        let pipelines = self.compile_material(descriptor)?;
+        if let (Some(watcher), Some(path)) = (&mut self.shader_watcher, &descriptor.path) {
+            watcher.watch(path);
+        }
+
+        // Uniform buffers must be at least 16 bytes and a multiple of 16
+        let params_size = descriptor.params.len().max(16).next_multiple_of(16);
+        let mut params = descriptor.params.clone();
+        params.resize(params_size, 0);
+        let params_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material Params Buffer"),
+            contents: &params,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let params_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.material_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+            label: Some("material_bind_group"),
+        });
+
+        log::info!("Created material: {}", descriptor.name);
+        self.materials.push(Material {
+            name: descriptor.name.clone(),
+            pipelines,
+            params_buffer,
+            params_bind_group,
+            params_size,
+            descriptor: Some(descriptor.clone()),
+        });
+        Ok(self.materials.len() - 1)
+    }
+
+    /// Compile a material's shader and pipelines, catching validation errors
+    fn compile_material(&self, descriptor: &MaterialDescriptor) -> Result<MaterialPipelines, String> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let shader = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&descriptor.name),
+            source: wgpu::ShaderSource::Wgsl(descriptor.source(&self.shader_sources.common).into()),
+        });
+        let pipelines = create_material_pipelines(
+            &self.device,
+            &self.material_pipeline_layout,
+            MeshPipelineDesc {
+                label: &descriptor.name,
+                shader: &shader,
+                vs_entry: "vs_main",
+                fs_entry: "fs_main",
+                buffers: &[Vertex::desc()],
+                format: HDR_FORMAT,
+                sample_count: self.sample_count,
+                blend: descriptor.blend,
+                cull_mode: descriptor.cull_mode,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                depth_test: true,
+                depth_write: true,
+            },
+            self.joint_buffer.is_some(),
+        );
+
+        match pollster::block_on(self.device.pop_error_scope()) {
+            Some(error) => Err(format!("Failed to create material {}: {}", descriptor.name, error)),
+            None => Ok(pipelines),
+        }
+    }
+
+    /// Build the pipelines shared by every PBR material
+    fn create_pbr_pipelines(&self, sources: &ShaderSources) -> MaterialPipelines {
+        let shader = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("PBR Shader"),
+            source: wgpu::ShaderSource::Wgsl(format!("{}\n{}", sources.common, sources.pbr).into()),
+        });
+        let layout = self.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("PBR Pipeline Layout"),
+            bind_group_layouts: &[
+                &self.camera_bind_group_layout,
+                &self.object_bind_group_layout,
+                &self.texture_bind_group_layout,
+                &self.pbr_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        create_material_pipelines(
+            &self.device,
+            &layout,
+            MeshPipelineDesc {
+                label: "PBR Pipeline",
+                shader: &shader,
+                vs_entry: "vs_main",
+                fs_entry: "fs_main",
+                buffers: &[Vertex::desc()],
+                format: HDR_FORMAT,
+                sample_count: self.sample_count,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                depth_test: true,
+                depth_write: true,
+            },
+            self.joint_buffer.is_some(),
+        )
+    }
+
+    /// Create a physically-based material, reusing one shared PBR pipeline
+    pub fn create_pbr_material(
+        &mut self,
+        name: &str,
+        material: &PbrMaterial,
+        resources: &ResourceManager,
+    ) -> Result<MaterialHandle, String> {
+        let mut views = Vec::with_capacity(4);
+        // The maps share one sampler: the first texture's, else the renderer's default
+        let mut sampler = None;
+        for (i, handle) in material.textures().into_iter().enumerate() {
+            views.push(match handle {
+                Some(handle) => {
+                    let texture = resources
+                        .get_texture(handle)
+                        .ok_or_else(|| format!("Invalid texture handle for material {}: {}", name, handle))?;
+                    sampler.get_or_insert(&texture.sampler);
+                    &texture.view
+                }
+                // The normal map defaults to a flat one, the others to white
+                None if i == 3 => &self.flat_normal_view,
+                None => &self.white_texture_view,
+            });
+        }
+
+        if self.pbr_pipeline.is_none() {
+            self.pbr_pipeline = Some(self.create_pbr_pipelines(&self.shader_sources));
+        }
+        let pipelines = self.pbr_pipeline.clone().ok_or("PBR pipeline unavailable")?;
+
+        let params_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("PBR Params Buffer"),
+            contents: bytemuck::bytes_of(&material.to_uniform()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let params_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.pbr_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(views[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(views[1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(views[2]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(sampler.unwrap_or(&self.texture_sampler)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(views[3]),
+                },
+            ],
+            label: Some("pbr_bind_group"),
+        });
+
+        log::info!("Created PBR material: {}", name);
+        self.materials.push(Material {
+            name: name.to_string(),
+            pipelines,
+            params_buffer,
+            params_bind_group,
+            params_size: std::mem::size_of::<PbrUniform>(),
+            descriptor: None,
+        });
+        Ok(self.materials.len() - 1)
+    }
+
+    /// Update a PBR material's color and metallic/roughness factors (textures are fixed at creation)
+    pub fn set_pbr_factors(&mut self, handle: MaterialHandle, material: &PbrMaterial) -> Result<(), String> {
+        self.set_material_params(handle, &material.to_uniform())
+    }
+
+    /// Get a material by handle
+    pub fn material(&self, handle: MaterialHandle) -> Option<&Material> {
+        self.materials.get(handle)
+    }
+
+    /// Update a material's uniform parameters
+    pub fn set_material_params<T: Pod>(&mut self, handle: MaterialHandle, params: &T) -> Result<(), String> {
+        let material = self
+            .materials
+            .get(handle)
+            .ok_or_else(|| format!("Invalid material handle: {}", handle))?;
+        let bytes = bytemuck::bytes_of(params);
+        if bytes.len() > material.params_size {
+            return Err(format!(
+                "Material params for {} are {} bytes, expected at most {}",
+                material.name,
+                bytes.len(),
+                material.params_size
+            ));
+        }
+        self.queue.write_buffer(&material.params_buffer, 0, bytes);
+        Ok(())
+    }
+
+    /// Get the line and trail ribbons drawn in the next frame
+    pub fn ribbons_mut(&mut self) -> &mut Ribbons {
+        &mut self.ribbons
+    }
+
+    /// Get the render graph of extra passes composed around the scene
+    pub fn render_graph(&self) -> &RenderGraph {
+        &self.render_graph
+    }
+
+    /// Get the render graph mutably to add or remove passes
+    pub fn render_graph_mut(&mut self) -> &mut RenderGraph {
+        &mut self.render_graph
+    }
+
+    /// Start recording rendered frames to a clip, returning its path
+    pub fn start_capture(&mut self, config: CaptureConfig) -> Result<std::path::PathBuf, String> {
+        if !self.config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+            return Err("Frame capture is not supported by this surface".to_string());
+        }
+        if !CaptureReadback::supports(self.config.format) {
+            return Err(format!("Frame capture does not support {:?} surfaces", self.config.format));
+        }
+        if self.capture.as_ref().is_some_and(|c| c.is_recording()) {
+            return Err("Capture is already recording".to_string());
+        }
+
+        let mut capture = FrameCapture::new(config);
+        let path = capture.start()?;
+        self.capture = Some(capture);
+        Ok(path)
+    }
+
+    /// Stop recording and finish the clip, returning its path
+    pub fn stop_capture(&mut self) -> Result<std::path::PathBuf, String> {
+        self.capture_readback = None;
+        self.capture
+            .take()
+            .ok_or_else(|| "Capture is not recording".to_string())?
+            .stop()
+    }
+
+    /// Check if rendered frames are being recorded
+    pub fn is_capturing(&self) -> bool {
+        self.capture.as_ref().is_some_and(|c| c.is_recording())
+    }
+
+    /// Release the window surface (the app was suspended)
+    pub fn suspend(&mut self) {
+        if self.surface.take().is_some() {
+            log::info!("Renderer suspended");
+        }
+    }
+
+    /// Recreate the window surface after the app was resumed
+    pub fn resume(&mut self, window: &Window) -> Result<(), String> {
+        if self.surface.is_some() || self.offscreen.is_some() {
+            return Ok(());
+        }
+
+        // SAFETY: The window reference is valid for the lifetime of the surface
+        let surface: wgpu::Surface<'static> = unsafe {
+            let raw_surface = self
+                .instance
+                .create_surface(window)
+                .map_err(|e| format!("Failed to create surface: {}", e))?;
+            std::mem::transmute(raw_surface)
         };
+
+        let size = window.inner_size();
+        if size.width > 0 && size.height > 0 {
+            self.size = (size.width, size.height);
+            self.config.width = size.width;
+            self.config.height = size.height;
+            self.update_aspect_ratios();
+            self.recreate_targets();
+        }
+        surface.configure(&self.device, &self.config);
+        self.surface = Some(surface);
+        log::info!("Renderer resumed");
+        Ok(())
+    }
+
+    /// Get the post-process anti-aliasing mode
+    pub fn anti_aliasing(&self) -> AntiAliasing {
+        self.anti_aliasing
+    }
+
+    /// Set the post-process anti-aliasing mode (applied on top of MSAA)
+    pub fn set_anti_aliasing(&mut self, mode: AntiAliasing) {
+        if mode == self.anti_aliasing {
+            return;
+        }
+        self.anti_aliasing = mode;
+        if mode != AntiAliasing::None {
+            let aa = self.anti_alias_pipeline.get_or_insert_with(|| {
+                AntiAliasPipeline::new(&self.device, &self.hdr_view, self.config.width, self.config.height)
+            });
+            aa.reset_history();
+        }
+        self.update_tone_map_source();
+    }
+
+    /// Projection jitter for the next TAA frame in a viewport, or identity without TAA
+    fn jitter(&self, viewport: Viewport) -> Mat4 {
+        match &self.anti_alias_pipeline {
+            Some(aa) if self.anti_aliasing == AntiAliasing::Taa => {
+                let (_, _, width, height) = viewport.to_pixels(self.size);
+                jitter_matrix(aa.jitter(), width, height)
+            }
+            _ => Mat4::IDENTITY,
+        }
+    }
+
+    /// Check if the renderer has a surface to draw to (headless renderers never suspend)
+    pub fn is_suspended(&self) -> bool {
+        self.surface.is_none() && self.offscreen.is_none()
+    }
+
+    /// Check if the renderer draws offscreen instead of to a window
+    pub fn is_headless(&self) -> bool {
+        self.offscreen.is_some()
+    }
+
+    /// Resize the renderer
+    pub fn resize(&mut self, new_size: (u32, u32)) {
+        if new_size.0 > 0 && new_size.1 > 0 {
+            self.size = new_size;
+            self.config.width = new_size.0;
+            self.config.height = new_size.1;
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+            if self.offscreen.is_some() {
+                self.offscreen = Some(create_offscreen_texture(&self.device, &self.config));
+            }
+            self.update_aspect_ratios();
+            self.recreate_targets();
+            log::debug!("Resized to: {}x{}", new_size.0, new_size.1);
+        }
+    }
+
+    /// Update the camera uniform buffers (the main camera and any added cameras)
+    pub fn update_camera(&mut self) {
+        let camera_uniform = CameraUniform::new(&self.camera).with_jitter(self.jitter(self.viewport));
         self.queue.write_buffer(
             &self.camera_buffer,
             0,
             bytemuck::cast_slice(&[camera_uniform]),
         );
+        for view in self.view_cameras.iter().flatten() {
+            let uniform = CameraUniform::new(&view.camera).with_jitter(self.jitter(view.viewport));
+            self.queue.write_buffer(&view.buffer, 0, bytemuck::cast_slice(&[uniform]));
+        }
+    }
+
+    /// Begin rendering a frame, or `None` if this frame should be skipped
+    ///
+    /// A lost or outdated surface (after minimizing, or moving the window to another
+    /// monitor) is reconfigured and acquired again; if that still fails, or acquiring
+    /// times out, the frame is skipped rather than failing.
+    pub fn begin_frame(&self) -> Result<Option<(wgpu::SurfaceTexture, wgpu::TextureView)>, RenderError> {
+        if self.offscreen.is_some() {
+            return Err("Headless renderers have no surface".into());
+        }
+        let surface = self.surface.as_ref().ok_or("Cannot render while suspended")?;
+        let output = match surface.get_current_texture() {
+            Ok(output) => output,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                log::debug!("Surface lost or outdated, reconfiguring");
+                surface.configure(&self.device, &self.config);
+                match surface.get_current_texture() {
+                    Ok(output) => output,
+                    Err(wgpu::SurfaceError::OutOfMemory) => return Err(RenderError::OutOfMemory),
+                    Err(e) => {
+                        log::warn!("Skipping frame: {}", e);
+                        return Ok(None);
+                    }
+                }
+            }
+            Err(wgpu::SurfaceError::Timeout) => {
+                log::debug!("Timed out acquiring the surface texture, skipping frame");
+                return Ok(None);
+            }
+            Err(wgpu::SurfaceError::OutOfMemory) => return Err(RenderError::OutOfMemory),
+        };
+
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        Ok(Some((output, view)))
+    }
+
+    /// Get the texture to draw the next frame into: the surface's or the offscreen one,
+    /// or `None` to skip the frame
+    fn acquire_target(&self) -> Result<Option<(FrameTarget, wgpu::TextureView)>, RenderError> {
+        match &self.offscreen {
+            Some(texture) => {
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                Ok(Some((FrameTarget::Offscreen(texture.clone()), view)))
+            }
+            None => Ok(self
+                .begin_frame()?
+                .map(|(output, view)| (FrameTarget::Surface(output), view))),
+        }
+    }
+
+    /// Drop what was queued for a skipped frame, so it isn't drawn twice by the next one
+    fn discard_queued_draws(&mut self) {
+        self.mesh_queue.clear();
+        self.instances.clear();
+        self.instance_batches.clear();
+        self.light_queue.clear();
+        self.debug_draw.clear();
+        self.ribbons.clear();
+    }
+
+    /// Read back the last frame of a headless renderer, waiting for the GPU to finish it
+    pub fn read_frame(&self) -> Result<RgbaImage, String> {
+        let texture = self
+            .offscreen
+            .as_ref()
+            .ok_or("Only headless renderers can read frames back")?;
+        let readback = CaptureReadback::new(&self.device, texture.width(), texture.height());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Readback Encoder"),
+            });
+        readback.copy(&mut encoder, texture);
+        self.queue.submit(std::iter::once(encoder.finish()));
+        let frame = readback.read(&self.device, self.config.format)?;
+        RgbaImage::from_raw(frame.width, frame.height, frame.rgba)
+            .ok_or_else(|| "Read back a truncated frame".to_string())
+    }
+
+    /// Render a frame that clears the screen (or draws the sky), then lets `overlay` record
+    /// additional passes (debug UI, etc.) into the same encoder before presenting
+    pub fn render_frame<F>(&mut self, overlay: F) -> Result<(), RenderError>
+    where
+        F: FnOnce(&wgpu::Device, &wgpu::Queue, &mut wgpu::CommandEncoder, &wgpu::TextureView),
+    {
+        self.draw_frame(None, overlay)
+    }
+
+    /// Render a frame like [`Renderer::render_frame`], also drawing every active entity
+    /// with a `Transform` and a visible [`MeshRenderer`]
+    pub fn render_scene<F>(
+        &mut self,
+        scene: &Scene,
+        resources: &ResourceManager,
+        overlay: F,
+    ) -> Result<(), RenderError>
+    where
+        F: FnOnce(&wgpu::Device, &wgpu::Queue, &mut wgpu::CommandEncoder, &wgpu::TextureView),
+    {
+        self.draw_frame(Some((scene, resources)), overlay)
+    }
+
+    /// Upload the lights closest to the camera, up to `max_lights`
+    fn write_lights(&mut self, mut lights: Vec<PointLightUniform>) {
+        closest_lights(&mut lights, self.camera.position, self.max_lights);
+        self.queue
+            .write_buffer(&self.point_light_buffer, 0, bytemuck::cast_slice(&[lights.len() as u32, 0, 0, 0]));
+        if !lights.is_empty() {
+            self.queue
+                .write_buffer(&self.point_light_buffer, 16, bytemuck::cast_slice(&lights));
+        }
+    }
+
+    /// Run the post-processing stack on the HDR scene color
+    fn post_process(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.debug_view != DebugView::Shaded {
+            return;
+        }
+        let enabled = |effect| self.post_effects.contains(&effect);
+        let vignette = enabled(PostEffect::Vignette).then_some(&self.vignette);
+        let film_grain = enabled(PostEffect::FilmGrain).then_some(&self.film_grain);
+        let aberration = enabled(PostEffect::ChromaticAberration).then_some(&self.chromatic_aberration);
+        let mut lens_drawn = false;
+        for effect in &self.post_effects {
+            match effect {
+                PostEffect::Bloom => {
+                    if let Some(bloom) = &self.bloom_pipeline {
+                        bloom.update(&self.queue, &self.bloom);
+                        bloom.draw(encoder, &self.hdr_view);
+                    }
+                }
+                // The lens effects share one pass, drawn where the first of them is
+                PostEffect::Vignette | PostEffect::FilmGrain | PostEffect::ChromaticAberration => {
+                    if let Some(lens) = self.lens_pipeline.as_mut().filter(|_| !lens_drawn) {
+                        lens.update(&self.queue, vignette, film_grain, aberration);
+                        lens.draw(encoder, &self.hdr_view);
+                        lens_drawn = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Write a GPU timestamp ending a stage of the frame, if GPU timing is on
+    fn time_stage(&mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str) {
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.stage(encoder, label);
+        }
+    }
+
+    /// Run the anti-aliasing pass, if any, on the post-processed scene color
+    fn anti_alias(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.anti_aliasing == AntiAliasing::None {
+            return;
+        }
+        if let Some(aa) = &mut self.anti_alias_pipeline {
+            aa.draw(&self.queue, encoder, self.anti_aliasing);
+        }
+    }
+
+    /// Resolve the HDR scene color onto the surface
+    fn tone_map(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        // Debug views show their colors unaltered
+        let (tone_mapping, exposure, grading) = match self.debug_view {
+            DebugView::Shaded => (self.tone_mapping, self.camera.exposure, self.color_grading.as_ref()),
+            _ => (ToneMapping::None, 1.0, None),
+        };
+        self.tone_map_pipeline.update(&self.queue, tone_mapping, exposure, grading);
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Tone Map Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        self.tone_map_pipeline.draw(&mut pass, self.color_luts.bind_group());
+    }
+
+    /// Render the planar reflection and bake the active reflection probe, then write the
+    /// reflection uniforms of the cameras
+    fn capture_reflections(&mut self, scene: Option<&Scene>, draws: &[SceneDraw], batches: &[(&Mesh, InstanceBatch)]) {
+        let mut rebind = false;
+        if let Some(scene) = scene {
+            let probes: Vec<(EntityId, Vec3, ReflectionProbe)> = scene
+                .active_entities()
+                .filter_map(|entity| {
+                    let probe = entity.get_component::<ReflectionProbe>()?;
+                    Some((entity.id(), entity.get_component::<Transform>()?.position, *probe))
+                })
+                .collect();
+            // The probe closest to the camera lights the whole scene
+            let camera_position = self.camera.position;
+            let active = probes.iter().min_by(|a, b| {
+                a.1.distance_squared(camera_position)
+                    .total_cmp(&b.1.distance_squared(camera_position))
+            });
+            let ids: Vec<EntityId> = probes.iter().map(|(id, _, _)| *id).collect();
+            rebind |= self.reflections.prepare_probe(
+                &self.device,
+                active.map(|(id, _, probe)| (*id, probe.resolution)),
+                &ids,
+                self.sample_count,
+            );
+
+            if let Some(&(_, position, probe)) = active {
+                if probe.realtime || self.reflections.probe_needs_bake() {
+                    for face in 0..6 {
+                        let camera = cube_face_camera(position, face, self.camera.near, self.camera.far);
+                        let view_proj = camera.view_proj_matrix();
+                        self.render_capture(Capture::ProbeFace(face), camera, view_proj, draws, batches);
+                    }
+                    self.reflections.finish_probe(&self.device, &self.queue);
+                }
+            }
+        }
+
+        // A mirror seen from behind reflects nothing
+        let planar = self
+            .planar_reflection
+            .filter(|planar| scene.is_some() && planar.distance(self.camera.position) > 0.0);
+        let planar_size = planar.map(|planar| {
+            let scale = planar.resolution_scale.clamp(0.05, 1.0);
+            (
+                ((self.size.0 as f32 * scale) as u32).max(1),
+                ((self.size.1 as f32 * scale) as u32).max(1),
+            )
+        });
+        rebind |= self
+            .reflections
+            .prepare_planar(&self.device, planar_size, self.sample_count);
+        if let Some(planar) = &planar {
+            let view_proj = planar.clipped_view_proj(&self.camera);
+            self.render_capture(Capture::Planar, planar.mirror_camera(&self.camera), view_proj, draws, batches);
+        }
+
+        if rebind {
+            self.rebuild_camera_bind_groups();
+        }
+        let probe_mips = self.reflections.probe_mips();
+        let main = ReflectionUniform::new(
+            planar.as_ref().map(|planar| (planar, planar.mirror_camera(&self.camera).view_proj_matrix())),
+            probe_mips,
+        );
+        self.reflections
+            .write(&self.queue, main, ReflectionUniform::new(None, probe_mips));
+    }
+
+    /// Draw the sky and meshes seen by `camera` (through `view_proj`) into a reflection
+    /// capture, in a submission of its own so the next capture can move the camera
+    fn render_capture(
+        &mut self,
+        capture: Capture,
+        camera: Camera,
+        view_proj: Mat4,
+        draws: &[SceneDraw],
+        batches: &[(&Mesh, InstanceBatch)],
+    ) {
+        let uniform = CameraUniform {
+            view_proj: view_proj.to_cols_array_2d(),
+            position: camera.position.extend(1.0).to_array(),
+        };
+        self.queue
+            .write_buffer(&self.reflection_camera.buffer, 0, bytemuck::cast_slice(&[uniform]));
+        if let (Some(sky), Some(pipeline)) = (&self.sky, &self.sky_pipeline) {
+            pipeline.update(&self.queue, sky, camera.view_proj_matrix());
+        }
+        self.reflection_camera.camera = camera;
+
+        let Some((target, view)) = self.reflections.capture_target(capture) else {
+            return;
+        };
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Reflection Encoder"),
+            });
+        {
+            let mut clear_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Reflection Clear Pass"),
+                color_attachments: &[Some(
+                    target.color_attachment(view, wgpu::LoadOp::Clear(self.clear_color.to_wgpu())),
+                )],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            if let (Some(_), Some(pipeline)) = (&self.sky, &self.sky_pipeline) {
+                pipeline.draw(&mut clear_pass);
+            }
+        }
+        self.record_scene_pass(&mut encoder, Some(&self.reflection_camera), Some((target, view)), draws, batches);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Bind the current reflection textures to the main and added cameras
+    fn rebuild_camera_bind_groups(&mut self) {
+        let lights = [&self.light_buffer, &self.point_light_buffer];
+        self.camera_bind_group = create_camera_bind_group(
+            &self.device,
+            &self.camera_bind_group_layout,
+            [&self.camera_buffer, lights[0], lights[1]],
+            &self.reflections,
+            ReflectionBinding::Main,
+            "camera_bind_group",
+        );
+        for view in self.view_cameras.iter_mut().flatten() {
+            view.bind_group = create_camera_bind_group(
+                &self.device,
+                &self.camera_bind_group_layout,
+                [&view.buffer, lights[0], lights[1]],
+                &self.reflections,
+                ReflectionBinding::View,
+                "view_camera_bind_group",
+            );
+        }
     }
 
-    /// Begin rendering a frame
-    pub fn begin_frame(&self) -> Result<(wgpu::SurfaceTexture, wgpu::TextureView), String> {
-        let output = self
-            .surface
-            .get_current_texture()
-            .map_err(|e| format!("Failed to get surface texture: {}", e))?;
+    /// Record a pass drawing the scene's meshes from an added camera, or the main camera
+    /// if `view` is `None` (which also draws ribbons and particles), into the frame or a
+    /// reflection `capture` target
+    fn record_scene_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: Option<&ViewCamera>,
+        capture: Option<(&CaptureTarget, &wgpu::TextureView)>,
+        draws: &[SceneDraw],
+        batches: &[(&Mesh, InstanceBatch)],
+    ) -> RenderStats {
+        let (camera, camera_bind_group, viewport, clear) = match view {
+            Some(view) => (&view.camera, &view.bind_group, view.viewport, view.clear),
+            None => (&self.camera, &self.camera_bind_group, self.viewport, self.camera_clear),
+        };
+        let main_camera = view.is_none();
+        let frustum = Frustum::from_view_proj(camera.view_proj_matrix());
+        let occlusion = self.occlusion.as_ref().filter(|_| main_camera);
+        let mut stats = RenderStats::default();
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        // Opaque draws keep their material order; transparent ones blend back to front
+        let mut opaque = Vec::with_capacity(draws.len());
+        let mut transparent = Vec::new();
+        for (i, draw) in draws.iter().enumerate() {
+            if !camera.layers.intersects(draw.layers) {
+                continue;
+            }
+            let bounds = draw.mesh.bounds.transformed(draw.model);
+            if self.frustum_culling && !frustum.intersects_aabb(&bounds) {
+                stats.culled += 1;
+                continue;
+            }
+            if draw.entity.zip(occlusion).is_some_and(|(id, o)| o.is_occluded(id)) {
+                stats.occluded += 1;
+                continue;
+            }
+            if draw.transparent {
+                transparent.push((i, bounds.center().distance_squared(camera.position)));
+            } else {
+                opaque.push(i);
+            }
+        }
+        sort_back_to_front(&mut transparent);
+        let transparent: Vec<usize> = transparent.into_iter().map(|(i, _)| i).collect();
+
+        let mut depth_load = match clear {
+            CameraClear::None => wgpu::LoadOp::Load,
+            CameraClear::Depth | CameraClear::Color(_) => wgpu::LoadOp::Clear(1.0),
+        };
+        // Debug views show every mesh as drawn, so they skip the pre-pass
+        let prepass = self.depth_prepass.as_ref().filter(|_| capture.is_none());
+        if let Some(prepass) = prepass.filter(|_| self.debug_view == DebugView::Shaded) {
+            self.record_depth_prepass(encoder, prepass, camera_bind_group, viewport, depth_load, draws, &opaque);
+            depth_load = wgpu::LoadOp::Load;
+        }
+
+        let (label, color_attachment, depth_view, size) = match capture {
+            Some((target, view)) => (
+                "Reflection Pass",
+                target.color_attachment(view, wgpu::LoadOp::Load),
+                target.depth_view(),
+                target.size(),
+            ),
+            None => (
+                if main_camera { "Scene Pass" } else { "Camera Pass" },
+                self.color_attachment(&self.hdr_view, wgpu::LoadOp::Load, false),
+                &self.depth_view,
+                self.size,
+            ),
+        };
+        let mut scene_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(color_attachment)],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: depth_load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: occlusion.and_then(|o| o.query_set()),
+            timestamp_writes: None,
+        });
+
+        if !viewport.is_full() {
+            let (x, y, width, height) = viewport.to_pixels(size);
+            scene_pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+            scene_pass.set_scissor_rect(x, y, width, height);
+        }
+        // A load op would clear the whole target, so the viewport is filled with a triangle
+        if let CameraClear::Color(color) = clear {
+            scene_pass.set_pipeline(&self.clear_pipeline);
+            scene_pass.set_blend_constant(color.to_wgpu());
+            scene_pass.draw(0..3, 0..1);
+        }
+        if let (Some(_), Some(pipeline)) = (&self.grid, &self.grid_pipeline) {
+            if main_camera && capture.is_none() {
+                pipeline.draw(&mut scene_pass);
+            }
+        }
+
+        let debug_pipelines = self.debug_view_pipelines.as_ref();
+        scene_pass.set_bind_group(0, camera_bind_group, &[]);
+        self.draw_meshes(&mut scene_pass, draws, &opaque, false, &mut stats);
+        if !batches.is_empty() {
+            scene_pass.set_pipeline(debug_pipelines.map_or(&self.instanced_pipeline, |d| &d.instanced));
+            scene_pass.set_bind_group(1, &self.object_bind_group, &[0]);
+            scene_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            for (mesh, batch) in batches {
+                let (Some(vertex_buffer), Some(index_buffer)) = (&mesh.vertex_buffer, &mesh.index_buffer) else {
+                    continue;
+                };
+                let texture_bind_group = batch
+                    .texture
+                    .and_then(|handle| self.texture_bind_groups.get(&handle))
+                    .unwrap_or(&self.white_texture_bind_group);
+                scene_pass.set_bind_group(2, texture_bind_group, &[]);
+                scene_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                scene_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                scene_pass.draw_indexed(0..mesh.indices.len() as u32, 0, batch.instances.clone());
+                stats.drawn += 1;
+                stats.triangles += (mesh.indices.len() / 3) as u64 * batch.instances.len() as u64;
+            }
+        }
+        if let (Some(terrain), Some(pipeline)) = (&self.terrain, &self.terrain_pipeline) {
+            if capture.is_none() && camera.layers.intersects(RenderLayers::DEFAULT) {
+                let frustum = self.frustum_culling.then_some(&frustum);
+                pipeline.draw(&mut scene_pass, terrain, frustum, &mut stats);
+            }
+        }
+        // Occlusion proxies test against the opaque depth before transparent meshes blend
+        if let Some(occlusion) = occlusion {
+            occlusion.draw(&mut scene_pass);
+        }
+        self.draw_meshes(&mut scene_pass, draws, &transparent, true, &mut stats);
+        if main_camera {
+            if let Some(pipeline) = &self.ribbon_pipeline {
+                pipeline.draw(&mut scene_pass);
+            }
+            if let Some(pipeline) = self.particle_pipeline.as_ref().filter(|p| !p.is_empty()) {
+                pipeline.draw(&mut scene_pass, camera.layers);
+            }
+        }
+        stats
+    }
+
+    /// Record a depth-only pass of the opaque draws `order` for one camera
+    #[allow(clippy::too_many_arguments)]
+    fn record_depth_prepass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        prepass: &DepthPrepass,
+        camera_bind_group: &wgpu::BindGroup,
+        viewport: Viewport,
+        depth_load: wgpu::LoadOp<f32>,
+        draws: &[SceneDraw],
+        order: &[usize],
+    ) {
+        // Dithered LOD fades discard pixels, which the pre-pass can't
+        let prepass_draws: Vec<PrepassDraw> = order
+            .iter()
+            .map(|&i| &draws[i])
+            .filter(|draw| draw.fade.0 >= 1.0)
+            .map(|draw| PrepassDraw {
+                mesh: draw.mesh,
+                object_offset: draw.object_offset,
+                skinned: draw.skinned(),
+            })
+            .collect();
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Depth Pre-Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: depth_load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        if !viewport.is_full() {
+            let (x, y, width, height) = viewport.to_pixels(self.size);
+            pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+            pass.set_scissor_rect(x, y, width, height);
+        }
+        pass.set_bind_group(0, camera_bind_group, &[]);
+        prepass.draw(&mut pass, &self.object_bind_group, &prepass_draws);
+    }
+
+    /// Record a pass outlining the draws of the outlined entities, seen from the main camera
+    fn record_outline_pass(&self, encoder: &mut wgpu::CommandEncoder, draws: &[SceneDraw]) {
+        let Some(outline) = &self.outline else {
+            return;
+        };
+        let outlined: Vec<OutlineDraw> = draws
+            .iter()
+            .filter(|draw| draw.entity.is_some_and(|id| self.outlined.contains(&id)))
+            .map(|draw| OutlineDraw {
+                mesh: draw.mesh,
+                object_offset: draw.object_offset,
+                skinned: draw.skinned(),
+            })
+            .collect();
+        if outlined.is_empty() {
+            return;
+        }
+
+        let (x, y, width, height) = self.viewport.to_pixels(self.size);
+        outline.update(&self.queue, self.outline_color.to_array(), self.outline_width, (width, height));
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Outline Pass"),
+            color_attachments: &[Some(self.color_attachment(&self.hdr_view, wgpu::LoadOp::Load, false))],
+            depth_stencil_attachment: Some(outline.stencil_attachment()),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        if !self.viewport.is_full() {
+            pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+            pass.set_scissor_rect(x, y, width, height);
+        }
+        pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        pass.set_bind_group(2, &self.white_texture_bind_group, &[]);
+        outline.draw(&mut pass, &self.object_bind_group, &outlined);
+    }
+
+    /// Draw `draws` in `order` with their materials' opaque or transparent pipelines,
+    /// counting them in `stats`
+    fn draw_meshes<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        draws: &[SceneDraw<'a>],
+        order: &[usize],
+        transparent: bool,
+        stats: &mut RenderStats,
+    ) {
+        let debug_pipelines = self.debug_view_pipelines.as_ref();
+        let mut current_material = None;
+        for &i in order {
+            let draw = &draws[i];
+            let mesh = draw.mesh;
+            let (Some(vertex_buffer), Some(index_buffer)) = (&mesh.vertex_buffer, &mesh.index_buffer) else {
+                continue;
+            };
+            // Debug views replace every material
+            let material = draw
+                .material
+                .filter(|_| debug_pipelines.is_none())
+                .and_then(|handle| self.materials.get(handle));
+            let skinned = draw.skinned();
+            if current_material != Some((draw.material, skinned)) {
+                current_material = Some((draw.material, skinned));
+                let pipeline = match (debug_pipelines, material) {
+                    (Some(debug), _) => &debug.object,
+                    (None, Some(material)) => material.pipelines.get(transparent, skinned),
+                    (None, None) => self.mesh_pipelines.get(transparent, skinned),
+                };
+                pass.set_pipeline(pipeline);
+            }
+            if let Some(material) = material {
+                pass.set_bind_group(3, &material.params_bind_group, &[]);
+            }
+            pass.set_bind_group(1, &self.object_bind_group, &[draw.object_offset]);
+            let texture_bind_group = draw
+                .texture
+                .and_then(|handle| self.texture_bind_groups.get(&handle))
+                .unwrap_or(&self.white_texture_bind_group);
+            pass.set_bind_group(2, texture_bind_group, &[]);
+            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            if let Some(skin_buffer) = mesh.skin_buffer.as_ref().filter(|_| skinned) {
+                pass.set_vertex_buffer(1, skin_buffer.slice(..));
+            }
+            pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..1);
+            stats.drawn += 1;
+            stats.triangles += (mesh.indices.len() / 3) as u64;
+        }
+    }
+
+    fn record_graph(&self, phase: GraphPhase, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let ctx = self
+            .render_graph
+            .context(&self.device, &self.queue, self.config.format, self.size);
+        self.render_graph.record(phase, &ctx, encoder, view);
+    }
+
+    fn draw_frame<F>(&mut self, scene: Option<(&Scene, &ResourceManager)>, overlay: F) -> Result<(), RenderError>
+    where
+        F: FnOnce(&wgpu::Device, &wgpu::Queue, &mut wgpu::CommandEncoder, &wgpu::TextureView),
+    {
+        self.reload_changed_shaders();
+        let Some((output, view)) = self.acquire_target()? else {
+            self.discard_queued_draws();
+            return Ok(());
+        };
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Frame Encoder"),
+            });
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.begin(&self.device, &mut encoder);
+        }
+
+        self.render_graph
+            .prepare(&self.device, &self.queue, self.config.format, self.size)?;
+        self.record_graph(GraphPhase::BeforeScene, &mut encoder, &view);
+
+        if !self.ribbons.is_empty() || self.ribbon_pipeline.is_some() {
+            let pipeline = self
+                .ribbon_pipeline
+                .get_or_insert_with(|| RibbonPipeline::new(&self.device, HDR_FORMAT, self.sample_count));
+            pipeline.update(&self.device, &self.queue, &self.ribbons, self.camera.view_proj_matrix());
+            self.ribbons.clear();
+        }
+
+        if let Some((scene, _)) = scene {
+            let has_emitters = scene
+                .active_entities()
+                .any(|entity| entity.has_component::<ParticleEmitter>());
+            if has_emitters || self.particle_pipeline.is_some() {
+                let pipeline = self
+                    .particle_pipeline
+                    .get_or_insert_with(|| ParticlePipeline::new(&self.device, HDR_FORMAT, self.sample_count));
+                pipeline.update(
+                    &self.device,
+                    &self.queue,
+                    scene,
+                    self.camera.view_matrix(),
+                    self.camera.view_proj_matrix(),
+                );
+                pipeline.simulate(&mut encoder);
+            }
+        }
+
+        if !self.debug_draw.is_empty() || self.debug_draw_pipeline.is_some() {
+            let pipeline = self
+                .debug_draw_pipeline
+                .get_or_insert_with(|| DebugDrawPipeline::new(&self.device, HDR_FORMAT, self.sample_count));
+            pipeline.update(&self.device, &self.queue, &self.debug_draw, self.camera.view_proj_matrix());
+            self.debug_draw.clear();
+        }
+
+        let mut lights = scene.map(|(scene, _)| scene_lights(scene)).unwrap_or_default();
+        lights.append(&mut self.light_queue);
+        self.write_lights(lights);
+
+        let camera_position = self.camera.position;
+        let mut draws = scene
+            .map(|(scene, resources)| mesh_draws(scene, resources, camera_position))
+            .unwrap_or_default();
+        if let Some((_, resources)) = scene {
+            draws.extend(self.mesh_queue.iter().filter_map(|draw| {
+                Some(SceneDraw {
+                    mesh: resources.get_mesh(draw.mesh).filter(|m| is_uploaded(m))?,
+                    model: draw.model,
+                    texture: draw.texture,
+                    material: None,
+                    fade: (1.0, false),
+                    transparent: false,
+                    skeleton: None,
+                    entity: None,
+                    layers: RenderLayers::DEFAULT,
+                    object_offset: 0,
+                })
+            }));
+        }
+        self.mesh_queue.clear();
+        // Group draws by material to limit pipeline switches
+        draws.sort_by_key(|draw| (draw.material, draw.skinned()));
 
-        Ok((output, view))
+        let batches: Vec<(&Mesh, InstanceBatch)> = match scene {
+            Some((_, resources)) => self
+                .instance_batches
+                .drain(..)
+                .filter_map(|batch| Some((resources.get_mesh(batch.mesh).filter(|m| is_uploaded(m))?, batch)))
+                .collect(),
+            None => Vec::new(),
+        };
+        self.instance_batches.clear();
+        self.write_instances();
+        self.instances.clear();
+
+        let mut joints = Vec::new();
+        self.objects.reset();
+        for draw in &mut draws {
+            let mut object = ObjectUniform::new(draw.model).with_fade(draw.fade.0, draw.fade.1);
+            if let Some(skeleton) = draw.skeleton.filter(|_| draw.skinned()) {
+                object = object.with_skin(joints.len() as u32);
+                joints.extend(skeleton.joint_matrices().iter().map(Mat4::to_cols_array_2d));
+            }
+            draw.object_offset = self.objects.push(&object);
+        }
+        self.flush_objects();
+        self.write_joints(&joints);
+        if let Some(occlusion) = &mut self.occlusion {
+            let proxies = occlusion_proxies(
+                draws
+                    .iter()
+                    .filter_map(|draw| Some((draw.entity?, draw.mesh.bounds.transformed(draw.model)))),
+                &Frustum::from_view_proj(self.camera.view_proj_matrix()),
+                self.camera.position,
+                self.camera.near,
+            );
+            occlusion.prepare(&self.device, &self.queue, &proxies);
+        }
+        if let Some((_, resources)) = scene {
+            let textures = draws
+                .iter()
+                .filter_map(|draw| draw.texture)
+                .chain(batches.iter().filter_map(|(_, batch)| batch.texture));
+            self.prepare_textures(textures, resources);
+        }
+        if let Some(terrain) = &mut self.terrain {
+            terrain.update(self.camera.position);
+            terrain.upload_chunks(&self.device);
+        }
+        self.capture_reflections(scene.map(|(scene, _)| scene), &draws, &batches);
+        if let (Some(sky), Some(pipeline)) = (&self.sky, &self.sky_pipeline) {
+            pipeline.update(&self.queue, sky, self.camera.view_proj_matrix());
+        }
+        if let (Some(grid), Some(pipeline)) = (&self.grid, &self.grid_pipeline) {
+            let (_, _, width, height) = self.viewport.to_pixels(self.size);
+            pipeline.update(
+                &self.queue,
+                grid,
+                self.camera.view_proj_matrix(),
+                self.camera.position,
+                (width, height),
+            );
+        }
+
+        self.time_stage(&mut encoder, "Before Scene");
+        {
+            let mut clear_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Clear Pass"),
+                color_attachments: &[Some(self.color_attachment(
+                    &self.hdr_view,
+                    wgpu::LoadOp::Clear(self.clear_color.to_wgpu()),
+                    false,
+                ))],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            if let (Some(_), Some(pipeline)) = (&self.sky, &self.sky_pipeline) {
+                pipeline.draw(&mut clear_pass);
+            }
+        }
+        self.time_stage(&mut encoder, "Sky");
+
+        // The main camera has order 0; the sort is stable so it goes first among equals
+        let mut views: Vec<(i32, Option<&ViewCamera>)> = vec![(0, None)];
+        views.extend(self.view_cameras.iter().flatten().map(|view| (view.order, Some(view))));
+        views.sort_by_key(|(order, _)| *order);
+        let mut stats = RenderStats::default();
+        for (_, view) in views {
+            let view_stats = self.record_scene_pass(&mut encoder, view, None, &draws, &batches);
+            stats.drawn += view_stats.drawn;
+            stats.triangles += view_stats.triangles;
+            stats.culled += view_stats.culled;
+            stats.occluded += view_stats.occluded;
+            if let Some(timer) = &mut self.gpu_timer {
+                timer.stage(&mut encoder, if view.is_none() { "Scene Pass" } else { "Camera Pass" });
+            }
+        }
+        if let Some(timer) = &self.gpu_timer {
+            stats.pass_timings = timer.timings().to_vec();
+        }
+        self.render_stats = stats;
+        if let Some(occlusion) = &self.occlusion {
+            occlusion.resolve(&mut encoder);
+        }
+        if !self.outlined.is_empty() {
+            self.record_outline_pass(&mut encoder, &draws);
+            self.time_stage(&mut encoder, "Outline");
+        }
+
+        // Debug lines go on top of the scene; this last pass also resolves MSAA
+        if self.debug_draw_pipeline.is_some() || self.msaa_view.is_some() {
+            let mut debug_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Debug Draw Pass"),
+                color_attachments: &[Some(self.color_attachment(&self.hdr_view, wgpu::LoadOp::Load, true))],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            if let Some(pipeline) = &self.debug_draw_pipeline {
+                pipeline.draw(&mut debug_pass);
+            }
+        }
+        self.time_stage(&mut encoder, "Debug Draw");
+
+        self.post_process(&mut encoder);
+        self.time_stage(&mut encoder, "Post Process");
+        self.anti_alias(&mut encoder);
+        self.time_stage(&mut encoder, "Anti-Aliasing");
+        self.tone_map(&mut encoder, &view);
+        self.time_stage(&mut encoder, "Tone Map");
+        self.record_graph(GraphPhase::AfterScene, &mut encoder, &view);
+
+        overlay(&self.device, &self.queue, &mut encoder, &view);
+        self.time_stage(&mut encoder, "Overlay");
+        if let Some(timer) = &self.gpu_timer {
+            timer.resolve(&mut encoder);
+        }
+
+        let capture_frame = self.capture.as_ref().is_some_and(|c| c.wants_frame());
+        if capture_frame {
+            let size = (output.texture().width(), output.texture().height());
+            if self.capture_readback.as_ref().map(|r| r.size()) != Some(size) {
+                self.capture_readback = Some(CaptureReadback::new(&self.device, size.0, size.1));
+            }
+            if let Some(readback) = &self.capture_readback {
+                readback.copy(&mut encoder, output.texture());
+            }
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        if let Some(occlusion) = &mut self.occlusion {
+            occlusion.map_results();
+        }
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.map_results();
+        }
+
+        if capture_frame {
+            if let (Some(capture), Some(readback)) = (&mut self.capture, &self.capture_readback) {
+                let result = readback
+                    .read(&self.device, self.config.format)
+                    .and_then(|frame| capture.push_frame(frame));
+                if let Err(e) = result {
+                    log::error!("Frame capture failed: {}", e);
+                    self.capture = None;
+                    self.capture_readback = None;
+                }
+            }
+        }
+
+        output.present();
+
+        Ok(())
     }
 
     /// Render a frame with the provided mesh data
@@ -423,8 +4022,37 @@ impl Renderer {
         vertex_buffer: &wgpu::Buffer,
         index_buffer: &wgpu::Buffer,
         num_indices: u32,
-    ) -> Result<(), String> {
-        let (output, view) = self.begin_frame()?;
+    ) -> Result<(), RenderError> {
+        self.draw_single(vertex_buffer, index_buffer, num_indices, None)
+    }
+
+    /// Render a frame with a mesh (with GPU buffers) drawn using a loaded texture
+    pub fn draw_textured(&mut self, mesh: &Mesh, texture: &Texture) -> Result<(), RenderError> {
+        let (Some(vertex_buffer), Some(index_buffer)) = (&mesh.vertex_buffer, &mesh.index_buffer) else {
+            return Err("Mesh has no GPU buffers".into());
+        };
+        let bind_group = Self::create_texture_bind_group(
+            &self.device,
+            &self.texture_bind_group_layout,
+            &texture.sampler,
+            &texture.view,
+        );
+        self.draw_single(vertex_buffer, index_buffer, mesh.indices.len() as u32, Some(&bind_group))
+    }
+
+    fn draw_single(
+        &mut self,
+        vertex_buffer: &wgpu::Buffer,
+        index_buffer: &wgpu::Buffer,
+        num_indices: u32,
+        texture_bind_group: Option<&wgpu::BindGroup>,
+    ) -> Result<(), RenderError> {
+        let Some((output, view)) = self.acquire_target()? else {
+            return Ok(());
+        };
+        self.objects.reset();
+        let object_offset = self.objects.push(&ObjectUniform::new(Mat4::IDENTITY));
+        self.flush_objects();
 
         let mut encoder = self
             .device
@@ -435,25 +4063,34 @@ impl Renderer {
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(self.clear_color.to_wgpu()),
+                color_attachments: &[Some(self.color_attachment(
+                    &self.hdr_view,
+                    wgpu::LoadOp::Clear(self.clear_color.to_wgpu()),
+                    true,
+                ))],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
                         store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
+                    }),
+                    stencil_ops: None,
+                }),
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_pipeline(&self.mesh_pipelines.opaque);
             render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.object_bind_group, &[object_offset]);
+            render_pass.set_bind_group(2, texture_bind_group.unwrap_or(&self.white_texture_bind_group), &[]);
             render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
             render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
             render_pass.draw_indexed(0..num_indices, 0, 0..1);
         }
+        self.post_process(&mut encoder);
+        self.anti_alias(&mut encoder);
+        self.tone_map(&mut encoder, &view);
 
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
@@ -465,4 +4102,694 @@ impl Renderer {
     pub fn size(&self) -> (u32, u32) {
         self.size
     }
+
+    /// Get the MSAA sample count in use (1 when disabled or unsupported)
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+}
+
+/// Index of the first adapter whose name contains `filter`, ignoring case
+fn pick_adapter(names: &[String], filter: &str) -> Option<usize> {
+    let filter = filter.to_lowercase();
+    names.iter().position(|name| name.to_lowercase().contains(&filter))
+}
+
+/// Pick the highest supported sample count not above `requested` (falls back to 1)
+fn pick_sample_count(requested: u32, supported: impl Fn(u32) -> bool) -> u32 {
+    [16, 8, 4, 2]
+        .into_iter()
+        .find(|&count| count <= requested && supported(count))
+        .unwrap_or(1)
+}
+
+/// Collect the uploaded meshes of active entities with a world transform and a visible `MeshRenderer`
+///
+/// Entities with an [`LodGroup`] draw the level picked by their distance to `camera_position`,
+/// or both levels (dithered) while cross-fading.
+fn mesh_draws<'a>(scene: &'a Scene, resources: &'a ResourceManager, camera_position: Vec3) -> Vec<SceneDraw<'a>> {
+    let mut draws = Vec::new();
+    for entity in scene.active_entities() {
+        let (Some(renderer), Some(model)) = (
+            entity.get_component::<MeshRenderer>().filter(|r| r.visible),
+            world_matrix(&entity),
+        ) else {
+            continue;
+        };
+        let layers = entity.get_component::<RenderLayers>().copied().unwrap_or_default();
+        let mut draw = |mesh: MeshHandle, fade: (f32, bool)| {
+            if let Some(mesh) = resources.get_mesh(mesh).filter(|m| is_uploaded(m)) {
+                draws.push(SceneDraw {
+                    mesh,
+                    model,
+                    texture: renderer.texture,
+                    material: renderer.material,
+                    fade,
+                    transparent: renderer.transparent,
+                    skeleton: entity.get_component::<Skeleton>(),
+                    entity: Some(entity.id()),
+                    layers,
+                    object_offset: 0,
+                });
+            }
+        };
+        let Some(lod) = entity.get_component::<LodGroup>() else {
+            draw(renderer.mesh, (1.0, false));
+            continue;
+        };
+        if let Some(selection) = lod.select(model.w_axis.truncate().distance(camera_position)) {
+            draw(selection.mesh, (1.0 - selection.blend, false));
+            if let Some(next) = selection.next {
+                draw(next, (1.0 - selection.blend, true));
+            }
+        }
+    }
+    draws
+}
+
+/// Sort transparent draws (index, squared camera distance) farthest first
+fn sort_back_to_front(draws: &mut [(usize, f32)]) {
+    draws.sort_by(|a, b| b.1.total_cmp(&a.1));
+}
+
+/// Texture a frame is drawn into
+enum FrameTarget {
+    Surface(wgpu::SurfaceTexture),
+    Offscreen(Arc<wgpu::Texture>),
+}
+
+impl FrameTarget {
+    fn texture(&self) -> &wgpu::Texture {
+        match self {
+            FrameTarget::Surface(output) => &output.texture,
+            FrameTarget::Offscreen(texture) => texture,
+        }
+    }
+
+    /// Show a surface frame; offscreen frames stay for readback
+    fn present(self) {
+        if let FrameTarget::Surface(output) = self {
+            output.present();
+        }
+    }
+}
+
+/// Bind a camera's uniform buffer with the light buffers (`buffers`: camera, light,
+/// point lights) and the reflections `binding` sees
+fn create_camera_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffers: [&wgpu::Buffer; 3],
+    reflections: &Reflections,
+    binding: ReflectionBinding,
+    label: &str,
+) -> wgpu::BindGroup {
+    let entries: Vec<wgpu::BindGroupEntry> = buffers
+        .iter()
+        .enumerate()
+        .map(|(i, buffer)| wgpu::BindGroupEntry {
+            binding: i as u32,
+            resource: buffer.as_entire_binding(),
+        })
+        .chain(reflections.entries(binding))
+        .collect();
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &entries,
+        label: Some(label),
+    })
+}
+
+/// Create the texture a headless renderer draws into, matching its surface configuration
+fn create_offscreen_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Arc<wgpu::Texture> {
+    Arc::new(device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Target"),
+        size: wgpu::Extent3d {
+            width: config.width.max(1),
+            height: config.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: config.usage,
+        view_formats: &[],
+    }))
+}
+
+/// Settings for a pipeline drawing meshes with the shared bind group layouts
+#[derive(Clone, Copy)]
+struct MeshPipelineDesc<'a> {
+    label: &'a str,
+    shader: &'a wgpu::ShaderModule,
+    vs_entry: &'a str,
+    fs_entry: &'a str,
+    buffers: &'a [wgpu::VertexBufferLayout<'a>],
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    blend: Option<wgpu::BlendState>,
+    cull_mode: Option<wgpu::Face>,
+    polygon_mode: wgpu::PolygonMode,
+    /// Test and write depth (off draws every fragment, e.g. for overdraw)
+    depth_test: bool,
+    /// Write depth when testing it (off for transparent meshes)
+    depth_write: bool,
+}
+
+fn create_mesh_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    desc: MeshPipelineDesc,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(desc.label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: desc.shader,
+            entry_point: desc.vs_entry,
+            buffers: desc.buffers,
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: desc.shader,
+            entry_point: desc.fs_entry,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: desc.format,
+                blend: desc.blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: desc.cull_mode,
+            polygon_mode: desc.polygon_mode,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: desc.depth_test && desc.depth_write,
+            // Less-equal passes the fragments whose depth the pre-pass already wrote
+            depth_compare: if desc.depth_test {
+                wgpu::CompareFunction::LessEqual
+            } else {
+                wgpu::CompareFunction::Always
+            },
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: desc.sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+/// Create a material's opaque pipeline and its transparent twin, plus variants drawing
+/// skinned meshes with `vs_skinned` if `skinning` is supported
+fn create_material_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    desc: MeshPipelineDesc,
+    skinning: bool,
+) -> MaterialPipelines {
+    let [opaque, transparent] = create_opaque_and_transparent(device, layout, desc);
+    let skinned = skinning.then(|| {
+        let label = format!("{} (Skinned)", desc.label);
+        let buffers = [Vertex::desc(), SkinVertex::desc()];
+        create_opaque_and_transparent(
+            device,
+            layout,
+            MeshPipelineDesc {
+                label: &label,
+                vs_entry: "vs_skinned",
+                buffers: &buffers,
+                ..desc
+            },
+        )
+    });
+    MaterialPipelines {
+        opaque,
+        transparent,
+        skinned,
+    }
+}
+
+fn create_opaque_and_transparent(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    desc: MeshPipelineDesc,
+) -> [Arc<wgpu::RenderPipeline>; 2] {
+    let label = format!("{} (Transparent)", desc.label);
+    let transparent = create_mesh_pipeline(
+        device,
+        layout,
+        MeshPipelineDesc {
+            label: &label,
+            blend: desc.blend.or(Some(wgpu::BlendState::ALPHA_BLENDING)),
+            depth_write: false,
+            ..desc
+        },
+    );
+    [Arc::new(create_mesh_pipeline(device, layout, desc)), Arc::new(transparent)]
+}
+
+fn is_uploaded(mesh: &Mesh) -> bool {
+    mesh.vertex_buffer.is_some() && mesh.index_buffer.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::MeshBuilder;
+    use glam::Quat;
+
+    #[test]
+    fn test_render_error() {
+        let error: RenderError = "Cannot render while suspended".into();
+        assert_eq!(error, RenderError::Other("Cannot render while suspended".to_string()));
+        assert_eq!(error.to_string(), "Cannot render while suspended");
+        assert_eq!(RenderError::OutOfMemory.to_string(), "GPU out of memory");
+    }
+
+    #[test]
+    fn test_headless_frame_readback() {
+        let config = crate::config::EngineConfig::default().renderer;
+        let Ok(mut renderer) = pollster::block_on(Renderer::new_headless((16, 8), &config)) else {
+            // No GPU adapter on this machine
+            return;
+        };
+        assert!(renderer.is_headless() && !renderer.is_suspended());
+        renderer.set_tone_mapping(ToneMapping::None);
+        renderer.set_clear_color(Color::rgb(1.0, 0.0, 0.0));
+        renderer.render_frame(|_, _, _, _| {}).unwrap();
+
+        let image = renderer.read_frame().unwrap();
+        assert_eq!(image.dimensions(), (16, 8));
+        let pixel = image.get_pixel(8, 4);
+        assert!(pixel[0] > 250 && pixel[1] < 5 && pixel[2] < 5, "{:?}", pixel);
+
+        renderer.resize((4, 4));
+        renderer.render_frame(|_, _, _, _| {}).unwrap();
+        assert_eq!(renderer.read_frame().unwrap().dimensions(), (4, 4));
+    }
+
+    #[test]
+    fn test_color_srgb_conversions() {
+        let color = Color::from_hex("#FF8000").unwrap();
+        assert_eq!((color.r, color.b, color.a), (1.0, 0.0, 1.0));
+        assert!((color.g - 0.2158).abs() < 1e-3);
+        assert_eq!(color, Color::from_srgb_u8(255, 128, 0));
+        assert_eq!(color.to_hex(), "#ff8000");
+        assert_eq!(Color::from_hex("11223344").unwrap().to_srgba_u8(), [0x11, 0x22, 0x33, 0x44]);
+        assert_eq!(Color::from_hex("11223344").unwrap().to_hex(), "#11223344");
+        for invalid in ["#12345", "#gg0000", "#ff00ff0", "#ééé"] {
+            assert!(Color::from_hex(invalid).is_err(), "{}", invalid);
+        }
+
+        // Encoding and decoding are inverses, with the linear segment near black
+        for c in [0.0, 0.002, 0.01, 0.5, 1.0] {
+            assert!((srgb_to_linear(linear_to_srgb(c)) - c).abs() < 1e-5);
+        }
+        let gray = Color::rgb(0.5, 0.5, 0.5).with_alpha(0.5);
+        assert!((gray.to_srgb().to_linear().r - 0.5).abs() < 1e-5);
+        assert_eq!(gray.to_srgb().a, 0.5);
+    }
+
+    #[test]
+    fn test_srgb_clear_color_round_trips() {
+        let config = crate::config::EngineConfig::default().renderer;
+        let Ok(mut renderer) = pollster::block_on(Renderer::new_headless((4, 4), &config)) else {
+            // No GPU adapter on this machine
+            return;
+        };
+        renderer.set_tone_mapping(ToneMapping::None);
+        renderer.set_clear_color(Color::from_hex("#40a0e0").unwrap());
+        renderer.render_frame(|_, _, _, _| {}).unwrap();
+        let pixel = renderer.read_frame().unwrap().get_pixel(2, 2).0;
+        let expected = [0x40, 0xa0, 0xe0, 0xff];
+        assert!(pixel.iter().zip(expected).all(|(a, b)| a.abs_diff(b) <= 1), "{:?}", pixel);
+    }
+
+    #[test]
+    fn test_depth_prepass_matches_scene_pass() {
+        let config = crate::config::EngineConfig::default().renderer;
+        let Ok(mut renderer) = pollster::block_on(Renderer::new_headless((32, 32), &config)) else {
+            // No GPU adapter on this machine
+            return;
+        };
+        let mut resources = ResourceManager::new();
+        let cube = resources.add_mesh("cube".to_string(), MeshBuilder::cube(2.0), renderer.device());
+        let mut scene = Scene::new("Test".to_string());
+        for (x, z) in [(0.0, 0.0), (0.5, -1.0), (-0.5, 1.0)] {
+            let id = scene.create_entity("Cube".to_string());
+            let mut entity = scene.get_entity_mut(id).unwrap();
+            entity.add_component(Transform::from_position(Vec3::new(x, 0.0, z)));
+            entity.add_component(MeshRenderer::new(cube));
+        }
+
+        renderer.render_scene(&scene, &resources, |_, _, _, _| {}).unwrap();
+        let without = renderer.read_frame().unwrap();
+        renderer.set_depth_prepass(true);
+        renderer.render_scene(&scene, &resources, |_, _, _, _| {}).unwrap();
+        let with = renderer.read_frame().unwrap();
+        assert!(renderer.depth_prepass());
+        assert!(without.pixels().zip(with.pixels()).all(|(a, b)| a == b));
+    }
+
+    #[test]
+    fn test_grid_draws_below_horizon() {
+        let config = crate::config::EngineConfig::default().renderer;
+        let Ok(mut renderer) = pollster::block_on(Renderer::new_headless((64, 64), &config)) else {
+            // No GPU adapter on this machine
+            return;
+        };
+        renderer.camera_mut().position = Vec3::new(0.5, 2.0, 5.0);
+        renderer.camera_mut().target = Vec3::new(0.5, 0.0, 0.0);
+        let scene = Scene::new("Test".to_string());
+        let resources = ResourceManager::new();
+
+        renderer.render_scene(&scene, &resources, |_, _, _, _| {}).unwrap();
+        let empty = renderer.read_frame().unwrap();
+        renderer.set_grid(Some(GridSettings::default()));
+        renderer.render_scene(&scene, &resources, |_, _, _, _| {}).unwrap();
+        let grid = renderer.read_frame().unwrap();
+
+        // The sky above the horizon keeps the clear color, the ground shows lines
+        assert!((0..64).all(|x| empty.get_pixel(x, 0) == grid.get_pixel(x, 0)));
+        assert!((32..64).any(|y| (0..64).any(|x| empty.get_pixel(x, y) != grid.get_pixel(x, y))));
+    }
+
+    #[test]
+    fn test_terrain_draws_chunks() {
+        let config = crate::config::EngineConfig::default().renderer;
+        let Ok(mut renderer) = pollster::block_on(Renderer::new_headless((64, 64), &config)) else {
+            // No GPU adapter on this machine
+            return;
+        };
+        renderer.camera_mut().position = Vec3::new(32.0, 20.0, 80.0);
+        renderer.camera_mut().target = Vec3::new(32.0, 0.0, 32.0);
+        let scene = Scene::new("Test".to_string());
+        let resources = ResourceManager::new();
+
+        renderer.render_scene(&scene, &resources, |_, _, _, _| {}).unwrap();
+        let empty = renderer.read_frame().unwrap();
+        let terrain_config = crate::terrain::TerrainConfig {
+            size: Vec2::new(64.0, 64.0),
+            height_scale: 4.0,
+            chunk_resolution: 8,
+            ..Default::default()
+        };
+        let heightmap = crate::terrain::Heightmap::flat(65, 65);
+        renderer.set_terrain(Some(Terrain::new(heightmap, terrain_config)));
+        renderer.render_scene(&scene, &resources, |_, _, _, _| {}).unwrap();
+        let terrain = renderer.read_frame().unwrap();
+
+        let chunks = renderer.terrain().unwrap().visible_chunks().len() as u32;
+        assert!(chunks > 0);
+        assert!(renderer.render_stats().drawn > 0);
+        assert_eq!(renderer.render_stats().drawn + renderer.render_stats().culled, chunks);
+        assert!((40..64).any(|y| (0..64).any(|x| empty.get_pixel(x, y) != terrain.get_pixel(x, y))));
+        assert!(renderer.set_terrain_layers(&resources, [0; 4], [1.0; 4]).is_err());
+    }
+
+    #[test]
+    fn test_color_grading_swaps_and_blends_luts() {
+        let config = crate::config::EngineConfig::default().renderer;
+        let Ok(mut renderer) = pollster::block_on(Renderer::new_headless((8, 8), &config)) else {
+            // No GPU adapter on this machine
+            return;
+        };
+        renderer.set_tone_mapping(ToneMapping::None);
+        renderer.set_clear_color(Color::rgb(0.6, 0.2, 0.05));
+        let scene = Scene::new("Test".to_string());
+        let resources = ResourceManager::new();
+        let render = |renderer: &mut Renderer| {
+            renderer.render_scene(&scene, &resources, |_, _, _, _| {}).unwrap();
+            renderer.read_frame().unwrap().get_pixel(4, 4).0
+        };
+        let ungraded = render(&mut renderer);
+
+        let strip = ColorLut::identity(16).to_strip();
+        let swapped = image::RgbaImage::from_fn(strip.width(), strip.height(), |x, y| {
+            let [r, g, b, a] = strip.get_pixel(x, y).0;
+            image::Rgba([b, g, r, a])
+        });
+        let identity = renderer.add_color_lut(&ColorLut::identity(16));
+        let swapped = renderer.add_color_lut(&ColorLut::from_strip(&swapped).unwrap());
+        let close = |a: u8, b: u8| a.abs_diff(b) <= 2;
+
+        renderer.set_color_grading(Some(ColorGrading::new(swapped)));
+        let graded = render(&mut renderer);
+        assert!(close(graded[0], ungraded[2]) && close(graded[1], ungraded[1]) && close(graded[2], ungraded[0]));
+
+        renderer.set_color_grading(Some(ColorGrading::new(identity).blended(swapped, 0.5)));
+        let half = render(&mut renderer);
+        assert!(close(half[0], half[2]) && half[0] < ungraded[0] && half[0] > ungraded[2]);
+
+        renderer.set_color_grading(None);
+        assert_eq!(render(&mut renderer), ungraded);
+    }
+
+    #[test]
+    fn test_lens_effects_darken_corners() {
+        let config = crate::config::EngineConfig::default().renderer;
+        let Ok(mut renderer) = pollster::block_on(Renderer::new_headless((32, 32), &config)) else {
+            // No GPU adapter on this machine
+            return;
+        };
+        renderer.set_tone_mapping(ToneMapping::None);
+        renderer.set_clear_color(Color::rgb(0.5, 0.5, 0.5));
+        renderer.set_post_effects(&[PostEffect::Vignette, PostEffect::ChromaticAberration]);
+        renderer.set_vignette_intensity(1.0);
+        let scene = Scene::new("Test".to_string());
+        let resources = ResourceManager::new();
+        renderer.render_scene(&scene, &resources, |_, _, _, _| {}).unwrap();
+        let frame = renderer.read_frame().unwrap();
+
+        // A flat color shows no fringes, so only the vignette changes it
+        let center = frame.get_pixel(16, 16).0;
+        let corner = frame.get_pixel(0, 0).0;
+        assert_eq!(center[0], center[2]);
+        assert!(corner[0] < center[0] / 2);
+
+        // Without the vignette in the stack, its settings have no effect
+        renderer.set_post_effects(&[PostEffect::ChromaticAberration]);
+        renderer.render_scene(&scene, &resources, |_, _, _, _| {}).unwrap();
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.get_pixel(0, 0).0, center);
+    }
+
+    #[test]
+    fn test_render_layers() {
+        let layers = RenderLayers::layer(3).with(5);
+        assert!(layers.contains(3) && layers.contains(5) && !layers.contains(0) && !layers.contains(40));
+        assert!(!layers.without(3).without(5).intersects(RenderLayers::ALL));
+        assert!(RenderLayers::default().intersects(RenderLayers::ALL.without(1)));
+        assert!(!RenderLayers::NONE.intersects(RenderLayers::ALL));
+    }
+
+    #[test]
+    fn test_camera_layer_mask_skips_entities() {
+        let config = crate::config::EngineConfig::default().renderer;
+        let Ok(mut renderer) = pollster::block_on(Renderer::new_headless((32, 32), &config)) else {
+            // No GPU adapter on this machine
+            return;
+        };
+        let mut resources = ResourceManager::new();
+        let cube = resources.add_mesh("cube".to_string(), MeshBuilder::cube(2.0), renderer.device());
+        let mut scene = Scene::new("Test".to_string());
+        let icon = scene.create_entity("Minimap Icon".to_string());
+        let mut entity = scene.get_entity_mut(icon).unwrap();
+        entity.add_component(Transform::default());
+        entity.add_component(MeshRenderer::new(cube));
+        entity.add_component(RenderLayers::layer(1));
+        renderer.camera_mut().position = Vec3::new(0.0, 0.0, 5.0);
+        renderer.camera_mut().target = Vec3::ZERO;
+
+        let center = |renderer: &mut Renderer| {
+            renderer.render_scene(&scene, &resources, |_, _, _, _| {}).unwrap();
+            renderer.read_frame().unwrap().get_pixel(16, 16).0
+        };
+        let drawn = center(&mut renderer);
+        renderer.camera_mut().layers = RenderLayers::ALL.without(1);
+        let skipped = center(&mut renderer);
+        assert_ne!(drawn, skipped);
+        renderer.camera_mut().layers = RenderLayers::layer(1);
+        assert_eq!(center(&mut renderer), drawn);
+    }
+
+    #[test]
+    fn test_reflections_light_pbr_surfaces() {
+        let config = crate::config::EngineConfig::default().renderer;
+        let Ok(mut renderer) = pollster::block_on(Renderer::new_headless((32, 32), &config)) else {
+            // No GPU adapter on this machine
+            return;
+        };
+        renderer.set_tone_mapping(ToneMapping::None);
+        let mut resources = ResourceManager::new();
+        let cube = resources.add_mesh("cube".to_string(), MeshBuilder::cube(2.0), renderer.device());
+        let mirror = renderer
+            .create_pbr_material("mirror", &PbrMaterial::new(Color::WHITE, 1.0, 0.0), &resources)
+            .unwrap();
+        let glow = PbrMaterial::new(Color::BLACK, 0.0, 1.0).with_emissive(Color::rgb(4.0, 0.0, 0.0));
+        let glow = renderer.create_pbr_material("glow", &glow, &resources).unwrap();
+
+        // A red cube standing on a mirror floor whose top face lies at y = 0
+        let mut scene = Scene::new("Test".to_string());
+        let floor = scene.create_entity("Floor".to_string());
+        let mut transform = Transform::from_position(Vec3::new(0.0, -1.0, 0.0));
+        transform.scale = Vec3::new(10.0, 1.0, 10.0);
+        let mut entity = scene.get_entity_mut(floor).unwrap();
+        entity.add_component(transform);
+        entity.add_component(MeshRenderer::new(cube).with_material(mirror));
+        let id = scene.create_entity("Cube".to_string());
+        let mut entity = scene.get_entity_mut(id).unwrap();
+        entity.add_component(Transform::from_position(Vec3::new(0.0, 1.5, -2.0)));
+        entity.add_component(MeshRenderer::new(cube).with_material(glow));
+
+        let render = |renderer: &mut Renderer, scene: &Scene| {
+            renderer.render_scene(scene, &resources, |_, _, _, _| {}).unwrap();
+            renderer.read_frame().unwrap()
+        };
+        let plain = render(&mut renderer, &scene);
+        // The floor turns red where it mirrors the cube
+        let redder = |image: &RgbaImage| {
+            image
+                .pixels()
+                .zip(plain.pixels())
+                .filter(|(a, b)| a[0] as i32 - b[0] as i32 > 30 && a[1] as i32 - b[1] as i32 <= 5)
+                .count()
+        };
+
+        renderer.set_planar_reflection(Some(PlanarReflection::horizontal(0.0)));
+        let planar = render(&mut renderer, &scene);
+        assert!(redder(&planar) > 0);
+
+        renderer.set_planar_reflection(None);
+        let probe = scene.create_entity("Probe".to_string());
+        let mut entity = scene.get_entity_mut(probe).unwrap();
+        entity.add_component(Transform::from_position(Vec3::new(0.0, 0.5, 1.0)));
+        entity.add_component(ReflectionProbe::default());
+        let probed = render(&mut renderer, &scene);
+        assert!(redder(&probed) > 0);
+    }
+
+    #[test]
+    fn test_camera_clear_fills_its_viewport() {
+        let mut config = crate::config::EngineConfig::default().renderer;
+        config.clear_color = Color::rgb(1.0, 0.0, 0.0);
+        let Ok(mut renderer) = pollster::block_on(Renderer::new_headless((16, 8), &config)) else {
+            // No GPU adapter on this machine
+            return;
+        };
+        renderer.set_tone_mapping(ToneMapping::None);
+        let minimap = renderer.add_camera(Camera::new(Vec3::Z, Vec3::ZERO, 1.0), Viewport::grid(2, 1, 1), 1);
+        renderer.set_view_camera_clear(minimap, CameraClear::Color(Color::rgb(0.0, 0.0, 1.0)));
+        let (scene, resources) = (Scene::new("Test".to_string()), ResourceManager::new());
+        renderer.render_scene(&scene, &resources, |_, _, _, _| {}).unwrap();
+
+        let image = renderer.read_frame().unwrap();
+        let (left, right) = (image.get_pixel(4, 4), image.get_pixel(12, 4));
+        assert!(left[0] > 250 && left[2] < 5, "{:?}", left);
+        assert!(right[2] > 250 && right[0] < 5, "{:?}", right);
+    }
+
+    #[test]
+    fn test_object_uniform_normal_matrix() {
+        // A plane tilted 45 degrees, squashed along Y: normals must stay perpendicular
+        let model = Mat4::from_scale_rotation_translation(
+            Vec3::new(1.0, 0.25, 1.0),
+            Quat::IDENTITY,
+            Vec3::new(3.0, 0.0, 0.0),
+        );
+        let uniform = ObjectUniform::new(model);
+        let normal_matrix = Mat4::from_cols_array_2d(&uniform.normal);
+
+        let tangent = model.transform_vector3(Vec3::new(1.0, 1.0, 0.0));
+        let normal = normal_matrix.transform_vector3(Vec3::new(1.0, -1.0, 0.0));
+        assert!(tangent.dot(normal).abs() < 1e-5);
+        assert_eq!(Mat4::from_cols_array_2d(&uniform.model), model);
+
+        // Degenerate scale falls back to the model matrix instead of NaNs
+        let flat = ObjectUniform::new(Mat4::from_scale(Vec3::new(1.0, 0.0, 1.0)));
+        assert!(flat.normal.iter().flatten().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn test_viewport_pixels() {
+        assert_eq!(Viewport::FULL.to_pixels((1280, 720)), (0, 0, 1280, 720));
+
+        // Two-player split screen, side by side
+        assert_eq!(Viewport::grid(2, 1, 0).to_pixels((1280, 720)), (0, 0, 640, 720));
+        assert_eq!(Viewport::grid(2, 1, 1).to_pixels((1280, 720)), (640, 0, 640, 720));
+        assert_eq!(Viewport::grid(2, 2, 3), Viewport::new(0.5, 0.5, 0.5, 0.5));
+
+        // Out-of-range viewports are clamped to at least one pixel on the surface
+        assert_eq!(Viewport::new(1.5, -1.0, 0.5, 0.0).to_pixels((100, 100)), (99, 0, 1, 1));
+    }
+
+    #[test]
+    fn test_material_source_adds_vertex_stage() {
+        let fragment_only = MaterialDescriptor::new(
+            "flat",
+            "@fragment\nfn fs_main(input: VertexOutput) -> @location(0) vec4<f32> { return input.color; }",
+        )
+        .with_params(&[1.0f32, 0.5, 0.25, 1.0]);
+        assert_eq!(fragment_only.params.len(), 16);
+        let common = ShaderSources::default().common;
+        let source = fragment_only.source(&common);
+        assert!(source.starts_with(&common));
+        assert!(source.contains("return mesh_vertex(input);"));
+        assert!(source.contains("fn vs_skinned"));
+
+        let custom_vertex = MaterialDescriptor::new("wobble", "@vertex\nfn vs_main(input: VertexInput) -> VertexOutput { return mesh_vertex(input); }");
+        assert!(!custom_vertex.source(&common).contains("@vertex\nfn vs_main(input: VertexInput) -> VertexOutput {\n"));
+    }
+
+    #[test]
+    fn test_debug_view_entry_points_exist() {
+        let source = format!("{}{}", include_str!("shaders/default.wgsl"), include_str!("shaders/debug_view.wgsl"));
+        for view in [
+            DebugView::Shaded,
+            DebugView::Wireframe,
+            DebugView::Normals,
+            DebugView::Uvs,
+            DebugView::Overdraw,
+        ] {
+            assert!(source.contains(&format!("fn {}(", view.fs_entry())), "{:?}", view);
+        }
+    }
+
+    #[test]
+    fn test_sort_back_to_front() {
+        let mut draws = vec![(0, 4.0), (1, 25.0), (2, 1.0), (3, 9.0)];
+        sort_back_to_front(&mut draws);
+        let order: Vec<usize> = draws.iter().map(|(i, _)| *i).collect();
+        assert_eq!(order, [1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn test_pick_sample_count() {
+        let only_four = |count| count == 4;
+        assert_eq!(pick_sample_count(4, only_four), 4);
+        assert_eq!(pick_sample_count(8, only_four), 4);
+        assert_eq!(pick_sample_count(2, only_four), 1);
+        assert_eq!(pick_sample_count(0, only_four), 1);
+        assert_eq!(pick_sample_count(16, |_| true), 16);
+    }
+
+    #[test]
+    fn test_pick_adapter() {
+        let names = ["Intel(R) UHD Graphics 620".to_string(), "NVIDIA GeForce RTX 3060 Laptop GPU".to_string()];
+        assert_eq!(pick_adapter(&names, "nvidia"), Some(1));
+        assert_eq!(pick_adapter(&names, "Graphics"), Some(0));
+        assert_eq!(pick_adapter(&names, "Radeon"), None);
+    }
 }
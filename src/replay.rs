@@ -0,0 +1,524 @@
+//! Gameplay replay recording and playback
+//!
+//! A replay stores every frame's inputs plus a snapshot of the world every
+//! few frames. Like rollback, this relies on a deterministic simulation: the
+//! same [`RollbackState`] and step function replay the recorded inputs into
+//! the same game, and snapshots make seeking cheap. Camera samples can be
+//! recorded alongside so spectators see what the player saw.
+//!
+//! Inputs are run-length encoded and the file is bincode, so idle stretches
+//! cost almost nothing.
+
+use crate::renderer::Camera;
+use crate::rollback::{Frame, RollbackState};
+use crate::save::write_atomic;
+use glam::Vec3;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Current replay file format version
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+/// Bytes at the start of every replay file
+const REPLAY_MAGIC: &[u8; 4] = b"RGRP";
+
+/// Information stored at the start of a replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    /// Replay format version
+    pub version: u32,
+    /// Game-defined version; replays only play back on the same simulation
+    pub game_version: u32,
+    /// Simulation frames per second
+    pub tick_rate: f32,
+    /// Frames between world snapshots
+    pub snapshot_interval: u64,
+    /// Number of recorded frames
+    pub frame_count: Frame,
+    /// Seconds since the UNIX epoch when recording finished
+    pub timestamp: u64,
+    /// Game-defined metadata (map, player names, ...)
+    pub metadata: HashMap<String, String>,
+}
+
+/// Camera position at a recorded frame, for spectating
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraSample {
+    pub frame: Frame,
+    pub position: Vec3,
+    pub target: Vec3,
+    pub fov: f32,
+}
+
+impl CameraSample {
+    /// Blend towards another sample
+    pub fn lerp(&self, other: &CameraSample, t: f32) -> CameraSample {
+        CameraSample {
+            frame: if t < 0.5 { self.frame } else { other.frame },
+            position: self.position.lerp(other.position, t),
+            target: self.target.lerp(other.target, t),
+            fov: self.fov + (other.fov - self.fov) * t,
+        }
+    }
+
+    /// Copy this sample into a renderer camera
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.position = self.position;
+        camera.target = self.target;
+        camera.fov = self.fov;
+    }
+}
+
+/// World snapshot taken before simulating `frame`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Snapshot<T> {
+    frame: Frame,
+    state: T,
+    checksum: Option<u64>,
+}
+
+/// On-disk layout with run-length encoded inputs
+///
+/// `S` is the snapshot list, borrowed when writing and owned when reading.
+#[derive(Serialize, Deserialize)]
+struct ReplayFile<I, S> {
+    header: ReplayHeader,
+    inputs: Vec<(u32, Vec<I>)>,
+    snapshots: S,
+    camera: Vec<CameraSample>,
+}
+
+/// A recorded replay
+#[derive(Debug, Clone)]
+pub struct Replay<I, T> {
+    header: ReplayHeader,
+    /// Inputs of all players, per frame
+    inputs: Vec<Vec<I>>,
+    snapshots: Vec<Snapshot<T>>,
+    camera: Vec<CameraSample>,
+}
+
+impl<I, T> Replay<I, T>
+where
+    I: Clone + PartialEq + Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned,
+{
+    /// Get the replay header
+    pub fn header(&self) -> &ReplayHeader {
+        &self.header
+    }
+
+    /// Get the number of recorded frames
+    pub fn frame_count(&self) -> Frame {
+        self.inputs.len() as Frame
+    }
+
+    /// Get the recorded length in seconds
+    pub fn duration(&self) -> f32 {
+        self.frame_count() as f32 / self.header.tick_rate
+    }
+
+    /// Get the inputs of all players for a frame
+    pub fn inputs(&self, frame: Frame) -> Option<&[I]> {
+        self.inputs.get(frame as usize).map(|i| i.as_slice())
+    }
+
+    /// Get the frames that have world snapshots
+    pub fn snapshot_frames(&self) -> Vec<Frame> {
+        self.snapshots.iter().map(|s| s.frame).collect()
+    }
+
+    /// Get the recorded camera samples
+    pub fn camera_samples(&self) -> &[CameraSample] {
+        &self.camera
+    }
+
+    /// Get the camera at a (fractional) frame, interpolating between samples
+    pub fn camera_at(&self, frame: f32) -> Option<CameraSample> {
+        let next = self.camera.partition_point(|s| (s.frame as f32) <= frame);
+        match (next.checked_sub(1).map(|i| &self.camera[i]), self.camera.get(next)) {
+            (Some(a), Some(b)) => {
+                let t = (frame - a.frame as f32) / (b.frame - a.frame) as f32;
+                Some(a.lerp(b, t))
+            }
+            (Some(a), None) => Some(*a),
+            (None, Some(b)) => Some(*b),
+            (None, None) => None,
+        }
+    }
+
+    /// Encode the replay into bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut inputs: Vec<(u32, Vec<I>)> = Vec::new();
+        for frame in &self.inputs {
+            match inputs.last_mut() {
+                Some((count, last)) if last == frame && *count < u32::MAX => *count += 1,
+                _ => inputs.push((1, frame.clone())),
+            }
+        }
+
+        let file = ReplayFile {
+            header: self.header.clone(),
+            inputs,
+            snapshots: &self.snapshots,
+            camera: self.camera.clone(),
+        };
+        let mut bytes = REPLAY_MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, &file).map_err(|e| format!("Failed to encode replay: {}", e))?;
+        Ok(bytes)
+    }
+
+    /// Decode a replay from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let body = bytes
+            .strip_prefix(REPLAY_MAGIC.as_slice())
+            .ok_or_else(|| "Not a replay file".to_string())?;
+        let file: ReplayFile<I, Vec<Snapshot<T>>> =
+            bincode::deserialize(body).map_err(|e| format!("Failed to decode replay: {}", e))?;
+
+        if file.header.version > REPLAY_FORMAT_VERSION {
+            return Err(format!(
+                "Replay uses format version {} (supported: {})",
+                file.header.version, REPLAY_FORMAT_VERSION
+            ));
+        }
+
+        let mut inputs = Vec::with_capacity(file.header.frame_count as usize);
+        for (count, frame) in file.inputs {
+            inputs.extend(std::iter::repeat_n(frame, count as usize));
+        }
+
+        Ok(Self {
+            header: file.header,
+            inputs,
+            snapshots: file.snapshots,
+            camera: file.camera,
+        })
+    }
+
+    /// Write the replay to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        write_atomic(path.as_ref(), &self.to_bytes()?)?;
+        log::info!("Saved replay ({} frames) to {:?}", self.frame_count(), path.as_ref());
+        Ok(())
+    }
+
+    /// Read a replay from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let bytes = std::fs::read(path.as_ref()).map_err(|e| format!("Failed to read replay file: {}", e))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Records inputs, snapshots, and camera samples while the game runs
+pub struct ReplayRecorder<I, S: RollbackState> {
+    header: ReplayHeader,
+    inputs: Vec<Vec<I>>,
+    snapshots: Vec<Snapshot<S::Snapshot>>,
+    camera: Vec<CameraSample>,
+}
+
+impl<I, S> ReplayRecorder<I, S>
+where
+    I: Clone + PartialEq + Serialize + DeserializeOwned,
+    S: RollbackState,
+    S::Snapshot: Serialize + DeserializeOwned,
+{
+    /// Start recording a simulation running at `tick_rate` frames per second
+    pub fn new(tick_rate: f32, snapshot_interval: u64) -> Self {
+        Self {
+            header: ReplayHeader {
+                version: REPLAY_FORMAT_VERSION,
+                game_version: 0,
+                tick_rate,
+                snapshot_interval: snapshot_interval.max(1),
+                frame_count: 0,
+                timestamp: 0,
+                metadata: HashMap::new(),
+            },
+            inputs: Vec::new(),
+            snapshots: Vec::new(),
+            camera: Vec::new(),
+        }
+    }
+
+    /// Set the game-defined version stored in the replay
+    pub fn set_game_version(&mut self, version: u32) {
+        self.header.game_version = version;
+    }
+
+    /// Store a metadata value in the replay
+    pub fn set_metadata(&mut self, key: &str, value: &str) {
+        self.header.metadata.insert(key.to_string(), value.to_string());
+    }
+
+    /// Get the next frame to be recorded
+    pub fn frame(&self) -> Frame {
+        self.inputs.len() as Frame
+    }
+
+    /// Record a frame's inputs; call with the state *before* stepping it
+    pub fn record_frame(&mut self, state: &S, inputs: &[I]) {
+        let frame = self.frame();
+        if frame.is_multiple_of(self.header.snapshot_interval) {
+            self.snapshots.push(Snapshot {
+                frame,
+                state: state.save_state(),
+                checksum: state.checksum(),
+            });
+        }
+        self.inputs.push(inputs.to_vec());
+    }
+
+    /// Record the camera for the frame about to be recorded
+    pub fn record_camera(&mut self, camera: &Camera) {
+        let frame = self.frame();
+        self.camera.retain(|s| s.frame != frame);
+        self.camera.push(CameraSample {
+            frame,
+            position: camera.position,
+            target: camera.target,
+            fov: camera.fov,
+        });
+    }
+
+    /// Stop recording and get the replay
+    pub fn finish(self) -> Replay<I, S::Snapshot> {
+        let mut header = self.header;
+        header.frame_count = self.inputs.len() as Frame;
+        header.timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Replay {
+            header,
+            inputs: self.inputs,
+            snapshots: self.snapshots,
+            camera: self.camera,
+        }
+    }
+}
+
+/// Plays a replay back by resimulating its inputs
+pub struct ReplayPlayer<I, S: RollbackState> {
+    replay: Replay<I, S::Snapshot>,
+    frame: Frame,
+    speed: f32,
+    paused: bool,
+    accumulator: f32,
+    desync_frame: Option<Frame>,
+}
+
+impl<I, S> ReplayPlayer<I, S>
+where
+    I: Clone + PartialEq + Serialize + DeserializeOwned,
+    S: RollbackState,
+    S::Snapshot: Serialize + DeserializeOwned,
+{
+    /// Create a player positioned before the first frame
+    ///
+    /// Call [`ReplayPlayer::seek`] with frame 0 to load the initial state.
+    pub fn new(replay: Replay<I, S::Snapshot>) -> Self {
+        Self {
+            replay,
+            frame: 0,
+            speed: 1.0,
+            paused: false,
+            accumulator: 0.0,
+            desync_frame: None,
+        }
+    }
+
+    /// Get the replay being played
+    pub fn replay(&self) -> &Replay<I, S::Snapshot> {
+        &self.replay
+    }
+
+    /// Get the next frame to be simulated
+    pub fn frame(&self) -> Frame {
+        self.frame
+    }
+
+    /// Check if every recorded frame has been played
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.replay.frame_count()
+    }
+
+    /// Set the playback speed multiplier
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    /// Pause or resume playback
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Check if playback is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// First snapshot frame whose checksum didn't match the resimulated state
+    pub fn desync_frame(&self) -> Option<Frame> {
+        self.desync_frame
+    }
+
+    /// Compare the state against the recorded snapshot checksum of the current frame
+    fn verify(&mut self, state: &S) {
+        let Some(snapshot) = self.replay.snapshots.iter().find(|s| s.frame == self.frame) else {
+            return;
+        };
+        if let (Some(recorded), Some(actual)) = (snapshot.checksum, state.checksum()) {
+            if recorded != actual && self.desync_frame.is_none() {
+                log::error!("Replay desync detected at frame {}", self.frame);
+                self.desync_frame = Some(self.frame);
+            }
+        }
+    }
+
+    /// Simulate one recorded frame, returning false at the end of the replay
+    pub fn step<F>(&mut self, state: &mut S, mut step: F) -> bool
+    where
+        F: FnMut(&mut S, &[I]),
+    {
+        let Some(inputs) = self.replay.inputs(self.frame) else {
+            return false;
+        };
+        let inputs = inputs.to_vec();
+        self.verify(state);
+        step(state, &inputs);
+        self.frame += 1;
+        true
+    }
+
+    /// Advance playback by real time, returning the number of frames simulated
+    pub fn update<F>(&mut self, state: &mut S, delta: f32, mut step: F) -> u32
+    where
+        F: FnMut(&mut S, &[I]),
+    {
+        if self.paused || self.is_finished() {
+            return 0;
+        }
+        self.accumulator += delta * self.speed;
+        let frame_time = 1.0 / self.replay.header.tick_rate;
+        let mut stepped = 0;
+        while self.accumulator >= frame_time {
+            self.accumulator -= frame_time;
+            if !self.step(state, &mut step) {
+                self.accumulator = 0.0;
+                break;
+            }
+            stepped += 1;
+        }
+        stepped
+    }
+
+    /// Jump to a frame by restoring the nearest earlier snapshot and resimulating
+    pub fn seek<F>(&mut self, state: &mut S, frame: Frame, mut step: F) -> Result<(), String>
+    where
+        F: FnMut(&mut S, &[I]),
+    {
+        let frame = frame.min(self.replay.frame_count());
+        // Keep stepping from the current frame when it's past the nearest snapshot
+        let snapshot = self
+            .replay
+            .snapshots
+            .iter()
+            .rev()
+            .find(|s| s.frame <= frame)
+            .filter(|s| !(s.frame < self.frame && self.frame <= frame));
+        if let Some(snapshot) = snapshot {
+            state.load_state(&snapshot.state);
+            self.frame = snapshot.frame;
+        } else if frame < self.frame {
+            return Err(format!("Cannot seek to frame {}: no snapshot before it", frame));
+        }
+
+        while self.frame < frame {
+            self.step(state, &mut step);
+        }
+        self.accumulator = 0.0;
+        Ok(())
+    }
+
+    /// Get the spectator camera at the current playback position
+    pub fn camera(&self) -> Option<CameraSample> {
+        let frame_time = 1.0 / self.replay.header.tick_rate;
+        self.replay
+            .camera_at(self.frame as f32 + self.accumulator / frame_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Game {
+        position: i64,
+        velocity: i64,
+    }
+
+    impl RollbackState for Game {
+        type Snapshot = Game;
+
+        fn save_state(&self) -> Game {
+            self.clone()
+        }
+
+        fn load_state(&mut self, snapshot: &Game) {
+            *self = snapshot.clone();
+        }
+
+        fn checksum(&self) -> Option<u64> {
+            Some((self.position * 31 + self.velocity) as u64)
+        }
+    }
+
+    fn step(game: &mut Game, inputs: &[i8]) {
+        game.velocity += inputs[0] as i64;
+        game.position += game.velocity;
+    }
+
+    #[test]
+    fn test_record_save_and_seek() {
+        let mut game = Game { position: 0, velocity: 0 };
+        let mut recorder: ReplayRecorder<i8, Game> = ReplayRecorder::new(60.0, 10);
+        recorder.set_metadata("map", "arena");
+        let mut history = Vec::new();
+        for frame in 0..95 {
+            let input = if frame % 20 < 5 { 1 } else { 0 };
+            if frame % 30 == 0 {
+                let camera = Camera::new(Vec3::new(frame as f32, 0.0, 0.0), Vec3::ZERO, 1.0);
+                recorder.record_camera(&camera);
+            }
+            recorder.record_frame(&game, &[input]);
+            step(&mut game, &[input]);
+            history.push(game.clone());
+        }
+
+        let path = std::env::temp_dir().join(format!("rgame_replay_test_{}.replay", std::process::id()));
+        recorder.finish().save(&path).unwrap();
+        let replay: Replay<i8, Game> = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(replay.frame_count(), 95);
+        assert_eq!(replay.header().metadata["map"], "arena");
+        assert_eq!(replay.camera_at(15.0).unwrap().position.x, 15.0);
+
+        let mut playback = Game { position: -1, velocity: -1 };
+        let mut player: ReplayPlayer<i8, Game> = ReplayPlayer::new(replay);
+        player.seek(&mut playback, 57, step).unwrap();
+        assert_eq!(playback, history[56]);
+
+        player.seek(&mut playback, 12, step).unwrap();
+        assert_eq!(playback, history[11]);
+
+        while player.step(&mut playback, step) {}
+        assert_eq!(playback, game);
+        assert_eq!(player.desync_frame(), None);
+    }
+}
@@ -1,12 +1,24 @@
 //! Resource management for textures, meshes, and other assets
 //!
 //! Provides loading and caching of game resources.
+//!
+//! Sounds live here too: [`ResourceManager::load_audio`] caches an [`AudioSource`] under a
+//! name and hands out an [`AudioHandle`]. Unloading frees the data but keeps the handle
+//! valid, so loading the same name again fills it back in, and
+//! [`ResourceManager::reload_modified_audio`] picks up edited files during development.
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use wgpu::{Device, Queue, TextureView};
 use image::GenericImageView;
-use crate::renderer::Vertex;
+use glam::{Vec2, Vec3};
+use crate::audio::AudioSource;
+use crate::compressed_texture::{is_compressed_container, level_size, CompressedImage};
+use crate::math::Aabb;
+use crate::mipmap::{mip_level_count, MipmapGenerator};
+use crate::renderer::{Vertex, VertexLayout};
+use crate::skinning::SkinVertex;
 
 /// Handle to a loaded texture
 pub type TextureHandle = usize;
@@ -14,35 +26,148 @@ pub type TextureHandle = usize;
 /// Handle to a loaded mesh
 pub type MeshHandle = usize;
 
+/// Handle to a loaded sound
+pub type AudioHandle = usize;
+
 /// A texture resource
 pub struct Texture {
     pub view: TextureView,
+    /// Sampler built from the texture's [`TextureSettings`]
+    pub sampler: wgpu::Sampler,
     pub size: (u32, u32),
 }
 
+/// How a texture is stored and sampled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureSettings {
+    /// Decode sRGB to linear when sampling (off for data such as normal maps)
+    pub srgb: bool,
+    /// Generate a full mip chain on the GPU after upload
+    pub mipmaps: bool,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    pub mipmap_filter: wgpu::FilterMode,
+    /// Maximum anisotropic samples (1 = off, up to 16; needs every filter `Linear`)
+    pub anisotropy: u16,
+    pub address_mode: wgpu::AddressMode,
+}
+
+impl Default for TextureSettings {
+    fn default() -> Self {
+        Self {
+            srgb: true,
+            mipmaps: true,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy: 1,
+            address_mode: wgpu::AddressMode::Repeat,
+        }
+    }
+}
+
+impl TextureSettings {
+    /// Crisp, unfiltered texels without mipmaps
+    pub fn pixel_art() -> Self {
+        Self {
+            mipmaps: false,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Self::default()
+        }
+    }
+
+    /// Store the texels as linear data (normal, metallic-roughness maps)
+    pub fn linear(mut self) -> Self {
+        self.srgb = false;
+        self
+    }
+
+    /// Sample up to `samples` texels along the view direction on oblique surfaces
+    pub fn with_anisotropy(mut self, samples: u16) -> Self {
+        self.anisotropy = samples;
+        self
+    }
+
+    /// Anisotropy passed to the sampler: wgpu only allows it with every filter linear
+    fn anisotropy_clamp(&self) -> u16 {
+        let linear = [self.mag_filter, self.min_filter, self.mipmap_filter]
+            .iter()
+            .all(|filter| *filter == wgpu::FilterMode::Linear);
+        if linear {
+            self.anisotropy.clamp(1, 16)
+        } else {
+            1
+        }
+    }
+
+    fn format(&self) -> wgpu::TextureFormat {
+        if self.srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        }
+    }
+
+    /// Create a sampler with these filters
+    pub fn create_sampler(&self, device: &Device) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            address_mode_u: self.address_mode,
+            address_mode_v: self.address_mode,
+            address_mode_w: self.address_mode,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            anisotropy_clamp: self.anisotropy_clamp(),
+            ..Default::default()
+        })
+    }
+}
+
 /// A mesh resource containing vertex and index data
-pub struct Mesh {
-    pub vertices: Vec<Vertex>,
+///
+/// Scene rendering draws meshes of the full 3D [`Vertex`]; meshes of leaner vertex types
+/// are drawn by pipelines built from their [`VertexLayout`], such as render graph passes.
+pub struct Mesh<V: VertexLayout = Vertex> {
+    pub vertices: Vec<V>,
     pub indices: Vec<u32>,
     pub vertex_buffer: Option<wgpu::Buffer>,
     pub index_buffer: Option<wgpu::Buffer>,
+    /// Joint influences per vertex for skinned meshes (empty otherwise)
+    pub skin: Vec<SkinVertex>,
+    pub skin_buffer: Option<wgpu::Buffer>,
+    /// Local-space bounds of the vertices, used for frustum culling
+    pub bounds: Aabb,
 }
 
-impl Mesh {
+impl<V: VertexLayout> Mesh<V> {
     /// Create a new mesh
-    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+    pub fn new(vertices: Vec<V>, indices: Vec<u32>) -> Self {
+        let bounds = Aabb::from_points(vertices.iter().map(|v| Vec3::from(v.position())));
         Self {
             vertices,
             indices,
             vertex_buffer: None,
             index_buffer: None,
+            skin: Vec::new(),
+            skin_buffer: None,
+            bounds,
         }
     }
 
+    /// Recompute the bounds after editing the vertices
+    pub fn compute_bounds(&mut self) {
+        self.bounds = Aabb::from_points(self.vertices.iter().map(|v| Vec3::from(v.position())));
+    }
+
     /// Create GPU buffers for this mesh
     pub fn create_buffers(&mut self, device: &Device) {
         use wgpu::util::DeviceExt;
 
+        self.compute_bounds();
+
         self.vertex_buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
             contents: bytemuck::cast_slice(&self.vertices),
@@ -54,16 +179,88 @@ impl Mesh {
             contents: bytemuck::cast_slice(&self.indices),
             usage: wgpu::BufferUsages::INDEX,
         }));
+
+        self.skin_buffer = (!self.skin.is_empty()).then(|| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Skin Buffer"),
+                contents: bytemuck::cast_slice(&self.skin),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+        });
+    }
+}
+
+impl Mesh {
+    /// Attach joint influences, one per vertex, so the mesh can be posed by a `Skeleton`
+    pub fn with_skin(mut self, skin: Vec<SkinVertex>) -> Self {
+        if skin.len() != self.vertices.len() {
+            log::warn!(
+                "Skin has {} entries for {} vertices; ignoring it",
+                skin.len(),
+                self.vertices.len()
+            );
+            return self;
+        }
+        self.skin = skin;
+        self
+    }
+
+    /// Compute per-vertex tangents from positions and texture coordinates
+    ///
+    /// Texture coordinates have a top-left origin, so the bitangent (`cross(normal,
+    /// tangent) * w`) points towards decreasing v, matching green-up normal maps.
+    pub fn generate_tangents(&mut self) {
+        generate_tangents(&mut self.vertices, &self.indices);
     }
 }
 
-/// Builder for creating meshes
-pub struct MeshBuilder {
-    vertices: Vec<Vertex>,
+/// Compute tangents for indexed vertices; see [`Mesh::generate_tangents`]
+pub(crate) fn generate_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut tangents = vec![Vec3::ZERO; vertices.len()];
+    let mut bitangents = vec![Vec3::ZERO; vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        let (Some(va), Some(vb), Some(vc)) = (vertices.get(a), vertices.get(b), vertices.get(c)) else {
+            continue;
+        };
+        let edge1 = Vec3::from(vb.position) - Vec3::from(va.position);
+        let edge2 = Vec3::from(vc.position) - Vec3::from(va.position);
+        let duv1 = Vec2::from(vb.tex_coords) - Vec2::from(va.tex_coords);
+        let duv2 = Vec2::from(vc.tex_coords) - Vec2::from(va.tex_coords);
+        let det = duv1.x * duv2.y - duv2.x * duv1.y;
+        if det.abs() < f32::EPSILON {
+            continue;
+        }
+        let tangent = (edge1 * duv2.y - edge2 * duv1.y) / det;
+        let bitangent = (edge2 * duv1.x - edge1 * duv2.x) / det;
+        for index in [a, b, c] {
+            tangents[index] += tangent;
+            bitangents[index] -= bitangent;
+        }
+    }
+
+    for (vertex, (tangent, bitangent)) in vertices.iter_mut().zip(tangents.into_iter().zip(bitangents)) {
+        let normal = Vec3::from(vertex.normal).normalize_or_zero();
+        // Gram-Schmidt, falling back to any vector perpendicular to the normal
+        let mut t = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+        if t == Vec3::ZERO {
+            t = normal.any_orthonormal_vector();
+        }
+        let w = if normal.cross(t).dot(bitangent) < 0.0 { -1.0 } else { 1.0 };
+        vertex.tangent = [t.x, t.y, t.z, w];
+    }
+}
+
+/// Builder for creating meshes of any vertex type
+///
+/// The shape constructors (`quad`, `cube`, ...) build meshes of the full 3D [`Vertex`].
+pub struct MeshBuilder<V: VertexLayout = Vertex> {
+    vertices: Vec<V>,
     indices: Vec<u32>,
 }
 
-impl MeshBuilder {
+impl<V: VertexLayout> MeshBuilder<V> {
     /// Create a new mesh builder
     pub fn new() -> Self {
         Self {
@@ -73,13 +270,13 @@ impl MeshBuilder {
     }
 
     /// Add a vertex
-    pub fn add_vertex(mut self, vertex: Vertex) -> Self {
+    pub fn add_vertex(mut self, vertex: V) -> Self {
         self.vertices.push(vertex);
         self
     }
 
     /// Add vertices
-    pub fn add_vertices(mut self, vertices: &[Vertex]) -> Self {
+    pub fn add_vertices(mut self, vertices: &[V]) -> Self {
         self.vertices.extend_from_slice(vertices);
         self
     }
@@ -96,10 +293,14 @@ impl MeshBuilder {
         self
     }
 
-    /// Build the mesh
-    pub fn build(self) -> Mesh {
+    /// Build the mesh; full 3D vertices get tangents unless any vertex already has one
+    pub fn build(mut self) -> Mesh<V> {
+        V::finish(&mut self.vertices, &self.indices);
         Mesh::new(self.vertices, self.indices)
     }
+}
+
+impl MeshBuilder {
 
     /// Create a quad mesh (rectangle)
     pub fn quad(width: f32, height: f32) -> Mesh {
@@ -107,15 +308,17 @@ impl MeshBuilder {
         let hh = height / 2.0;
 
         let vertices = vec![
-            Vertex { position: [-hw, -hh, 0.0], tex_coords: [0.0, 1.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [hw, -hh, 0.0], tex_coords: [1.0, 1.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [hw, hh, 0.0], tex_coords: [1.0, 0.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [-hw, hh, 0.0], tex_coords: [0.0, 0.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] },
+            Vertex { position: [-hw, -hh, 0.0], tex_coords: [0.0, 1.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [hw, -hh, 0.0], tex_coords: [1.0, 1.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [hw, hh, 0.0], tex_coords: [1.0, 0.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [-hw, hh, 0.0], tex_coords: [0.0, 0.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
         ];
 
         let indices = vec![0, 1, 2, 0, 2, 3];
 
-        Mesh::new(vertices, indices)
+        let mut mesh = Mesh::new(vertices, indices);
+        mesh.generate_tangents();
+        mesh
     }
 
     /// Create a cube mesh
@@ -123,35 +326,35 @@ impl MeshBuilder {
         let s = size / 2.0;
         let vertices = vec![
             // Front face
-            Vertex { position: [-s, -s, s], tex_coords: [0.0, 1.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [s, -s, s], tex_coords: [1.0, 1.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [s, s, s], tex_coords: [1.0, 0.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [-s, s, s], tex_coords: [0.0, 0.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] },
+            Vertex { position: [-s, -s, s], tex_coords: [0.0, 1.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [s, -s, s], tex_coords: [1.0, 1.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [s, s, s], tex_coords: [1.0, 0.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [-s, s, s], tex_coords: [0.0, 0.0], normal: [0.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
             // Back face
-            Vertex { position: [s, -s, -s], tex_coords: [0.0, 1.0], normal: [0.0, 0.0, -1.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [-s, -s, -s], tex_coords: [1.0, 1.0], normal: [0.0, 0.0, -1.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [-s, s, -s], tex_coords: [1.0, 0.0], normal: [0.0, 0.0, -1.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [s, s, -s], tex_coords: [0.0, 0.0], normal: [0.0, 0.0, -1.0], color: [1.0, 1.0, 1.0, 1.0] },
+            Vertex { position: [s, -s, -s], tex_coords: [0.0, 1.0], normal: [0.0, 0.0, -1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [-s, -s, -s], tex_coords: [1.0, 1.0], normal: [0.0, 0.0, -1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [-s, s, -s], tex_coords: [1.0, 0.0], normal: [0.0, 0.0, -1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [s, s, -s], tex_coords: [0.0, 0.0], normal: [0.0, 0.0, -1.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
             // Top face
-            Vertex { position: [-s, s, s], tex_coords: [0.0, 1.0], normal: [0.0, 1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [s, s, s], tex_coords: [1.0, 1.0], normal: [0.0, 1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [s, s, -s], tex_coords: [1.0, 0.0], normal: [0.0, 1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [-s, s, -s], tex_coords: [0.0, 0.0], normal: [0.0, 1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
+            Vertex { position: [-s, s, s], tex_coords: [0.0, 1.0], normal: [0.0, 1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [s, s, s], tex_coords: [1.0, 1.0], normal: [0.0, 1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [s, s, -s], tex_coords: [1.0, 0.0], normal: [0.0, 1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [-s, s, -s], tex_coords: [0.0, 0.0], normal: [0.0, 1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
             // Bottom face
-            Vertex { position: [-s, -s, -s], tex_coords: [0.0, 1.0], normal: [0.0, -1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [s, -s, -s], tex_coords: [1.0, 1.0], normal: [0.0, -1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [s, -s, s], tex_coords: [1.0, 0.0], normal: [0.0, -1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [-s, -s, s], tex_coords: [0.0, 0.0], normal: [0.0, -1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
+            Vertex { position: [-s, -s, -s], tex_coords: [0.0, 1.0], normal: [0.0, -1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [s, -s, -s], tex_coords: [1.0, 1.0], normal: [0.0, -1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [s, -s, s], tex_coords: [1.0, 0.0], normal: [0.0, -1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [-s, -s, s], tex_coords: [0.0, 0.0], normal: [0.0, -1.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
             // Right face
-            Vertex { position: [s, -s, s], tex_coords: [0.0, 1.0], normal: [1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [s, -s, -s], tex_coords: [1.0, 1.0], normal: [1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [s, s, -s], tex_coords: [1.0, 0.0], normal: [1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [s, s, s], tex_coords: [0.0, 0.0], normal: [1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
+            Vertex { position: [s, -s, s], tex_coords: [0.0, 1.0], normal: [1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [s, -s, -s], tex_coords: [1.0, 1.0], normal: [1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [s, s, -s], tex_coords: [1.0, 0.0], normal: [1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [s, s, s], tex_coords: [0.0, 0.0], normal: [1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
             // Left face
-            Vertex { position: [-s, -s, -s], tex_coords: [0.0, 1.0], normal: [-1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [-s, -s, s], tex_coords: [1.0, 1.0], normal: [-1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [-s, s, s], tex_coords: [1.0, 0.0], normal: [-1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
-            Vertex { position: [-s, s, -s], tex_coords: [0.0, 0.0], normal: [-1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0] },
+            Vertex { position: [-s, -s, -s], tex_coords: [0.0, 1.0], normal: [-1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [-s, -s, s], tex_coords: [1.0, 1.0], normal: [-1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [-s, s, s], tex_coords: [1.0, 0.0], normal: [-1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
+            Vertex { position: [-s, s, -s], tex_coords: [0.0, 0.0], normal: [-1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0, 1.0], tangent: [0.0; 4] },
         ];
 
         let indices = vec![
@@ -163,22 +366,118 @@ impl MeshBuilder {
             20, 21, 22, 20, 22, 23, // Left
         ];
 
-        Mesh::new(vertices, indices)
+        let mut mesh = Mesh::new(vertices, indices);
+        mesh.generate_tangents();
+        mesh
     }
 }
 
-impl Default for MeshBuilder {
+impl<V: VertexLayout> Default for MeshBuilder<V> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-/// Manages resources like textures and meshes
+/// Upload a block-compressed image, decompressing it if the device can't sample BCn
+fn create_compressed_texture(
+    name: &str,
+    image: &CompressedImage,
+    settings: &TextureSettings,
+    device: &Device,
+    queue: &Queue,
+) -> Texture {
+    let srgb = image.srgb.unwrap_or(settings.srgb);
+    let native = device.features().contains(wgpu::Features::TEXTURE_COMPRESSION_BC) && image.is_block_aligned();
+    let format = if native {
+        image.format.wgpu_format(srgb)
+    } else {
+        log::warn!("Decompressing texture {}: BC formats are unavailable", name);
+        TextureSettings { srgb, ..*settings }.format()
+    };
+    let mip_level_count = if settings.mipmaps { image.levels.len() as u32 } else { 1 };
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(name),
+        size: wgpu::Extent3d {
+            width: image.width,
+            height: image.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    for level in 0..mip_level_count {
+        let (width, height) = level_size(image.width, image.height, level);
+        let destination = wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        };
+        if native {
+            // Copies cover whole blocks, even on levels smaller than one
+            let (blocks_x, blocks_y) = (width.div_ceil(4), height.div_ceil(4));
+            queue.write_texture(
+                destination,
+                &image.levels[level as usize],
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(image.bytes_per_row(level)),
+                    rows_per_image: Some(blocks_y),
+                },
+                wgpu::Extent3d {
+                    width: blocks_x * 4,
+                    height: blocks_y * 4,
+                    depth_or_array_layers: 1,
+                },
+            );
+        } else {
+            queue.write_texture(
+                destination,
+                &image.decompress(level),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    Texture {
+        view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        sampler: settings.create_sampler(device),
+        size: (image.width, image.height),
+    }
+}
+
+/// A cached sound and the file it came from
+struct AudioAsset {
+    source: AudioSource,
+    /// File to reload from (`None` for sounds inserted from memory)
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+/// Manages resources like textures, meshes, and sounds
 pub struct ResourceManager {
     textures: HashMap<String, Texture>,
     meshes: HashMap<String, Mesh>,
+    sounds: HashMap<String, AudioAsset>,
     texture_handles: Vec<String>,
     mesh_handles: Vec<String>,
+    audio_handles: Vec<String>,
+    mipmaps: Option<MipmapGenerator>,
 }
 
 impl ResourceManager {
@@ -187,18 +486,48 @@ impl ResourceManager {
         Self {
             textures: HashMap::new(),
             meshes: HashMap::new(),
+            sounds: HashMap::new(),
             texture_handles: Vec::new(),
             mesh_handles: Vec::new(),
+            audio_handles: Vec::new(),
+            mipmaps: None,
         }
     }
 
-    /// Load a texture from a file
+    /// Load a color texture from a file (sRGB, mipmapped)
     pub fn load_texture<P: AsRef<Path>>(
         &mut self,
         name: String,
         path: P,
         device: &Device,
         queue: &Queue,
+    ) -> Result<TextureHandle, String> {
+        self.load_texture_with_settings(name, path, &TextureSettings::default(), device, queue)
+    }
+
+    /// Load a data texture from a file without sRGB decoding (normal, metallic-roughness maps)
+    pub fn load_texture_linear<P: AsRef<Path>>(
+        &mut self,
+        name: String,
+        path: P,
+        device: &Device,
+        queue: &Queue,
+    ) -> Result<TextureHandle, String> {
+        self.load_texture_with_settings(name, path, &TextureSettings::default().linear(), device, queue)
+    }
+
+    /// Load a texture from a file with explicit format, mipmap, and filtering settings
+    ///
+    /// KTX2 and DDS files with BC1/BC3/BC5/BC7 data keep their compression where the
+    /// device supports it and are decompressed otherwise. Their mip levels come from the
+    /// file, and their declared color space overrides `settings.srgb`.
+    pub fn load_texture_with_settings<P: AsRef<Path>>(
+        &mut self,
+        name: String,
+        path: P,
+        settings: &TextureSettings,
+        device: &Device,
+        queue: &Queue,
     ) -> Result<TextureHandle, String> {
         // Check if already loaded
         if let Some(index) = self.texture_handles.iter().position(|n| n == &name) {
@@ -206,7 +535,14 @@ impl ResourceManager {
         }
 
         // Load image
-        let img = image::open(path.as_ref())
+        let bytes = crate::platform::read_asset(path.as_ref())?;
+        if is_compressed_container(&bytes) {
+            let image = CompressedImage::parse(&bytes)?;
+            let texture = create_compressed_texture(&name, &image, settings, device, queue);
+            log::info!("Loaded compressed texture: {:?} ({:?})", path.as_ref(), image.format);
+            return Ok(self.insert_texture(name, texture));
+        }
+        let img = image::load_from_memory(&bytes)
             .map_err(|e| format!("Failed to load image: {}", e))?;
         let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
@@ -218,14 +554,24 @@ impl ResourceManager {
             depth_or_array_layers: 1,
         };
 
+        let mip_level_count = if settings.mipmaps {
+            mip_level_count(dimensions.0, dimensions.1)
+        } else {
+            1
+        };
+        let mut usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+        if mip_level_count > 1 {
+            // Lower levels are rendered from the ones above
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some(&name),
             size,
-            mip_level_count: 1,
+            mip_level_count,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            format: settings.format(),
+            usage,
             view_formats: &[],
         });
 
@@ -245,18 +591,26 @@ impl ResourceManager {
             size,
         );
 
+        self.mipmaps
+            .get_or_insert_with(|| MipmapGenerator::new(device))
+            .generate(device, queue, &texture);
+
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         let texture_resource = Texture {
             view,
+            sampler: settings.create_sampler(device),
             size: dimensions,
         };
 
-        self.textures.insert(name.clone(), texture_resource);
-        self.texture_handles.push(name);
-
         log::info!("Loaded texture: {:?}", path.as_ref());
-        Ok(self.texture_handles.len() - 1)
+        Ok(self.insert_texture(name, texture_resource))
+    }
+
+    fn insert_texture(&mut self, name: String, texture: Texture) -> TextureHandle {
+        self.textures.insert(name.clone(), texture);
+        self.texture_handles.push(name);
+        self.texture_handles.len() - 1
     }
 
     /// Get a texture by handle
@@ -267,21 +621,42 @@ impl ResourceManager {
 
     /// Add a mesh to the resource manager
     pub fn add_mesh(&mut self, name: String, mut mesh: Mesh, device: &Device) -> MeshHandle {
+        // Create GPU buffers
+        if !self.mesh_handles.contains(&name) {
+            mesh.create_buffers(device);
+        }
+        self.insert_mesh(name, mesh)
+    }
+
+    /// Add a mesh whose GPU buffers are created later by [`ResourceManager::upload_meshes`]
+    ///
+    /// Useful before the renderer exists; the engine uploads pending meshes every frame.
+    pub fn insert_mesh(&mut self, name: String, mesh: Mesh) -> MeshHandle {
         // Check if already exists
         if let Some(index) = self.mesh_handles.iter().position(|n| n == &name) {
             return index;
         }
 
-        // Create GPU buffers
-        mesh.create_buffers(device);
-
+        log::info!("Added mesh: {}", name);
         self.meshes.insert(name.clone(), mesh);
         self.mesh_handles.push(name);
-
-        log::info!("Added mesh");
         self.mesh_handles.len() - 1
     }
 
+    /// Create GPU buffers for meshes that don't have them yet
+    pub fn upload_meshes(&mut self, device: &Device) {
+        for mesh in self.meshes.values_mut() {
+            if mesh.vertex_buffer.is_none() || mesh.index_buffer.is_none() {
+                mesh.create_buffers(device);
+            }
+        }
+    }
+
+    /// Get the handle of a mesh by name
+    pub fn get_mesh_handle(&self, name: &str) -> Option<MeshHandle> {
+        self.mesh_handles.iter().position(|n| n == name)
+    }
+
     /// Get a mesh by handle
     pub fn get_mesh(&self, handle: MeshHandle) -> Option<&Mesh> {
         let name = self.mesh_handles.get(handle)?;
@@ -293,6 +668,135 @@ impl ResourceManager {
         let name = self.mesh_handles.get(handle)?;
         self.meshes.get_mut(name)
     }
+
+    /// Load a sound from a file (WAV, MP3, OGG, FLAC), or get the handle if it's loaded
+    ///
+    /// Sounds are decoded when played, so loading only reads the file.
+    pub fn load_audio<P: AsRef<Path>>(&mut self, name: String, path: P) -> Result<AudioHandle, String> {
+        if let Some(handle) = self.get_audio_handle(&name).filter(|_| self.sounds.contains_key(&name)) {
+            return Ok(handle);
+        }
+        let path = path.as_ref();
+        let source = AudioSource::load(path)?;
+        let asset = AudioAsset {
+            source,
+            path: Some(path.to_path_buf()),
+            modified: modified(path),
+        };
+        Ok(self.insert_audio_asset(name, asset))
+    }
+
+    /// Load several sounds up front, e.g. while a loading screen shows
+    pub fn preload_audio<N, P>(&mut self, sounds: impl IntoIterator<Item = (N, P)>) -> Result<Vec<AudioHandle>, String>
+    where
+        N: Into<String>,
+        P: AsRef<Path>,
+    {
+        sounds
+            .into_iter()
+            .map(|(name, path)| self.load_audio(name.into(), path))
+            .collect()
+    }
+
+    /// Add a sound that's already in memory (keeps the existing one if the name is taken)
+    pub fn insert_audio(&mut self, name: String, source: AudioSource) -> AudioHandle {
+        if let Some(handle) = self.get_audio_handle(&name).filter(|_| self.sounds.contains_key(&name)) {
+            return handle;
+        }
+        let asset = AudioAsset {
+            source,
+            path: None,
+            modified: None,
+        };
+        self.insert_audio_asset(name, asset)
+    }
+
+    fn insert_audio_asset(&mut self, name: String, asset: AudioAsset) -> AudioHandle {
+        self.sounds.insert(name.clone(), asset);
+        // An unloaded sound gets its old handle back
+        if let Some(handle) = self.get_audio_handle(&name) {
+            return handle;
+        }
+        self.audio_handles.push(name);
+        self.audio_handles.len() - 1
+    }
+
+    /// Get the handle of a sound by name
+    pub fn get_audio_handle(&self, name: &str) -> Option<AudioHandle> {
+        self.audio_handles.iter().position(|n| n == name)
+    }
+
+    /// Get a sound by handle (`None` once unloaded)
+    pub fn get_audio(&self, handle: AudioHandle) -> Option<&AudioSource> {
+        let name = self.audio_handles.get(handle)?;
+        self.sounds.get(name).map(|asset| &asset.source)
+    }
+
+    /// Get a sound by name
+    pub fn get_audio_by_name(&self, name: &str) -> Option<&AudioSource> {
+        self.sounds.get(name).map(|asset| &asset.source)
+    }
+
+    /// Free a sound's data; sounds already playing finish normally
+    ///
+    /// The handle stays reserved for the name, so loading it again reuses the handle.
+    pub fn unload_audio(&mut self, handle: AudioHandle) -> bool {
+        let Some(name) = self.audio_handles.get(handle) else {
+            return false;
+        };
+        self.sounds.remove(name).is_some()
+    }
+
+    /// Read a sound from its file again
+    pub fn reload_audio(&mut self, handle: AudioHandle) -> Result<(), String> {
+        let name = self
+            .audio_handles
+            .get(handle)
+            .ok_or_else(|| format!("Invalid audio handle {}", handle))?;
+        let asset = self
+            .sounds
+            .get_mut(name)
+            .ok_or_else(|| format!("Sound '{}' is not loaded", name))?;
+        let path = asset
+            .path
+            .clone()
+            .ok_or_else(|| format!("Sound '{}' wasn't loaded from a file", name))?;
+        asset.source = AudioSource::load(&path)?;
+        asset.modified = modified(&path);
+        Ok(())
+    }
+
+    /// Reload the sounds whose files changed since they were loaded, returning their handles
+    ///
+    /// Sounds that fail to reload are logged and keep their old data.
+    pub fn reload_modified_audio(&mut self) -> Vec<AudioHandle> {
+        let mut reloaded = Vec::new();
+        for (handle, name) in self.audio_handles.iter().enumerate() {
+            let Some(asset) = self.sounds.get_mut(name) else {
+                continue;
+            };
+            let Some(path) = &asset.path else {
+                continue;
+            };
+            let current = modified(path);
+            if current == asset.modified {
+                continue;
+            }
+            asset.modified = current;
+            match AudioSource::load(path) {
+                Ok(source) => {
+                    asset.source = source;
+                    reloaded.push(handle);
+                }
+                Err(e) => log::error!("Failed to reload sound '{}': {}", name, e),
+            }
+        }
+        reloaded
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
 }
 
 impl Default for ResourceManager {
@@ -300,3 +804,95 @@ impl Default for ResourceManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_mesh_reuses_handle() {
+        let mut resources = ResourceManager::new();
+        let cube = resources.insert_mesh("cube".to_string(), MeshBuilder::cube(1.0));
+        let quad = resources.insert_mesh("quad".to_string(), MeshBuilder::quad(1.0, 1.0));
+        assert_ne!(cube, quad);
+        assert_eq!(resources.insert_mesh("cube".to_string(), MeshBuilder::quad(2.0, 2.0)), cube);
+
+        let mesh = resources.get_mesh(cube).unwrap();
+        assert_eq!(mesh.indices.len(), 36);
+        assert!(mesh.vertex_buffer.is_none());
+    }
+
+    #[test]
+    fn test_audio_lifecycle() {
+        let path = std::env::temp_dir().join(format!("rgame_audio_test_{}.wav", std::process::id()));
+        std::fs::write(&path, [0u8; 4]).unwrap();
+
+        let mut resources = ResourceManager::new();
+        let beep = resources.insert_audio("beep".to_string(), AudioSource::from_bytes(vec![1, 2]));
+        let step = resources.load_audio("step".to_string(), &path).unwrap();
+        assert_ne!(beep, step);
+        assert_eq!(resources.load_audio("step".to_string(), "missing.wav"), Ok(step));
+        assert_eq!(resources.get_audio_handle("step"), Some(step));
+        assert!(resources.load_audio("other".to_string(), "missing.wav").is_err());
+
+        // Edited files are picked up
+        std::fs::write(&path, [0u8; 8]).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(10)).unwrap();
+        assert_eq!(resources.reload_modified_audio(), [step]);
+        assert_eq!(resources.get_audio(step).unwrap().len(), 8);
+        assert!(resources.reload_audio(beep).is_err());
+
+        assert!(resources.unload_audio(step));
+        assert!(resources.get_audio(step).is_none());
+        assert_eq!(resources.load_audio("step".to_string(), &path), Ok(step));
+        assert_eq!(resources.get_audio_by_name("step").unwrap().len(), 8);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_texture_settings_anisotropy() {
+        let settings = TextureSettings::default().with_anisotropy(32);
+        assert_eq!(settings.anisotropy_clamp(), 16);
+        assert_eq!(settings.linear().format(), wgpu::TextureFormat::Rgba8Unorm);
+
+        // Nearest filtering can't be combined with anisotropy
+        let pixel_art = TextureSettings::pixel_art().with_anisotropy(8);
+        assert_eq!(pixel_art.anisotropy_clamp(), 1);
+        assert!(!pixel_art.mipmaps);
+    }
+
+    #[test]
+    fn test_generated_tangents_follow_u() {
+        let quad = MeshBuilder::quad(2.0, 2.0);
+        for vertex in &quad.vertices {
+            assert_eq!(vertex.tangent, [1.0, 0.0, 0.0, 1.0]);
+        }
+
+        // Every cube face gets a unit tangent perpendicular to its normal
+        let cube = MeshBuilder::cube(1.0);
+        for vertex in &cube.vertices {
+            let tangent = Vec3::new(vertex.tangent[0], vertex.tangent[1], vertex.tangent[2]);
+            assert!((tangent.length() - 1.0).abs() < 1e-5);
+            assert!(tangent.dot(Vec3::from(vertex.normal)).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_mesh_builder_with_lean_vertices() {
+        use crate::renderer::{PosColorVertex, PosUvVertex};
+
+        let line = MeshBuilder::<PosColorVertex>::new()
+            .add_vertex(PosColorVertex { position: [-1.0, 0.0, 2.0], color: [1.0; 4] })
+            .add_vertex(PosColorVertex { position: [3.0, 1.0, 0.0], color: [1.0; 4] })
+            .add_indices(&[0, 1])
+            .build();
+        assert_eq!(line.bounds.min, Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(line.bounds.max, Vec3::new(3.0, 1.0, 2.0));
+
+        assert_eq!(std::mem::size_of::<PosColorVertex>(), 28);
+        assert_eq!(std::mem::size_of::<PosUvVertex>(), 20);
+        assert_eq!(PosUvVertex::layout().array_stride, 20);
+        assert_eq!(Vertex::layout().array_stride, std::mem::size_of::<Vertex>() as u64);
+    }
+}
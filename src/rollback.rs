@@ -0,0 +1,534 @@
+//! Rollback netcode for peer-to-peer games
+//!
+//! Every peer simulates every frame immediately, predicting remote inputs
+//! that have not arrived yet (by repeating the last known input). When a
+//! late remote input differs from the prediction, the session restores the
+//! saved state of that frame and resimulates up to the present with the
+//! corrected inputs.
+//!
+//! The simulation step must be deterministic: the same state and inputs must
+//! always produce the same next state on every peer.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// Simulation frame number
+pub type Frame = u64;
+
+/// Game state that can be saved and restored for rollback
+pub trait RollbackState {
+    /// Saved copy of the state
+    type Snapshot;
+
+    /// Save the current state
+    fn save_state(&self) -> Self::Snapshot;
+
+    /// Restore a previously saved state
+    fn load_state(&mut self, snapshot: &Self::Snapshot);
+
+    /// Optional checksum of the state used for desync detection
+    fn checksum(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Configuration for a [`RollbackSession`]
+#[derive(Debug, Clone, Copy)]
+pub struct RollbackConfig {
+    /// Number of players in the session
+    pub num_players: usize,
+    /// Index of the local player
+    pub local_player: usize,
+    /// Maximum number of frames simulated ahead of the last confirmed frame
+    pub max_prediction: u64,
+    /// Frames of delay applied to local input (trades latency for fewer rollbacks)
+    pub input_delay: u64,
+}
+
+impl Default for RollbackConfig {
+    fn default() -> Self {
+        Self {
+            num_players: 2,
+            local_player: 0,
+            max_prediction: 8,
+            input_delay: 2,
+        }
+    }
+}
+
+/// Result of [`RollbackSession::advance`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdvanceResult {
+    /// The frame was simulated; `rolled_back` frames were resimulated first
+    Advanced { frame: Frame, rolled_back: u64 },
+    /// Too far ahead of remote players; wait for their inputs before advancing
+    Stalled,
+}
+
+/// Per-player input history
+struct PlayerInputs<I> {
+    confirmed: HashMap<Frame, I>,
+    /// Inputs used for frames that were simulated with a prediction
+    predicted: HashMap<Frame, I>,
+    last_confirmed_frame: Option<Frame>,
+}
+
+impl<I: Clone + Default> PlayerInputs<I> {
+    fn new() -> Self {
+        Self {
+            confirmed: HashMap::new(),
+            predicted: HashMap::new(),
+            last_confirmed_frame: None,
+        }
+    }
+
+    /// Get the confirmed input for a frame, or predict it from the last one before it
+    fn input_for(&self, frame: Frame) -> (I, bool) {
+        if let Some(input) = self.confirmed.get(&frame) {
+            return (input.clone(), true);
+        }
+        let predicted = self
+            .last_confirmed_frame
+            .filter(|last| *last < frame)
+            .and_then(|last| self.confirmed.get(&last))
+            .cloned()
+            .unwrap_or_default();
+        (predicted, false)
+    }
+
+    /// Frame up to which all inputs are confirmed
+    fn contiguous_confirmed(&self, start: Frame) -> Option<Frame> {
+        let mut frame = start;
+        if !self.confirmed.contains_key(&frame) {
+            return None;
+        }
+        while self.confirmed.contains_key(&(frame + 1)) {
+            frame += 1;
+        }
+        Some(frame)
+    }
+}
+
+/// Drives a deterministic simulation with input prediction and rollback
+pub struct RollbackSession<I, S: RollbackState> {
+    config: RollbackConfig,
+    players: Vec<PlayerInputs<I>>,
+    current_frame: Frame,
+    confirmed_frame: Option<Frame>,
+    rollback_to: Option<Frame>,
+    states: VecDeque<(Frame, S::Snapshot)>,
+    checksums: HashMap<Frame, u64>,
+    remote_checksums: HashMap<Frame, u64>,
+    desync_frame: Option<Frame>,
+    total_rollbacks: u64,
+}
+
+impl<I, S> RollbackSession<I, S>
+where
+    I: Clone + Default + PartialEq,
+    S: RollbackState,
+{
+    /// Create a session starting at frame 0
+    pub fn new(config: RollbackConfig) -> Self {
+        assert!(config.local_player < config.num_players, "local_player out of range");
+        Self {
+            config,
+            players: (0..config.num_players).map(|_| PlayerInputs::new()).collect(),
+            current_frame: 0,
+            confirmed_frame: None,
+            rollback_to: None,
+            states: VecDeque::new(),
+            checksums: HashMap::new(),
+            remote_checksums: HashMap::new(),
+            desync_frame: None,
+            total_rollbacks: 0,
+        }
+    }
+
+    /// Get the session configuration
+    pub fn config(&self) -> &RollbackConfig {
+        &self.config
+    }
+
+    /// Get the next frame to be simulated
+    pub fn current_frame(&self) -> Frame {
+        self.current_frame
+    }
+
+    /// Get the newest frame for which all players' inputs are known
+    pub fn confirmed_frame(&self) -> Option<Frame> {
+        self.confirmed_frame
+    }
+
+    /// Total number of frames resimulated because of mispredictions
+    pub fn total_rollbacks(&self) -> u64 {
+        self.total_rollbacks
+    }
+
+    /// First frame at which a remote checksum differed from ours, if any
+    pub fn desync_frame(&self) -> Option<Frame> {
+        self.desync_frame
+    }
+
+    /// Add the local player's input for the next frame
+    ///
+    /// Returns the frame the input applies to (current frame plus input delay).
+    pub fn add_local_input(&mut self, input: I) -> Frame {
+        let frame = self.current_frame + self.config.input_delay;
+        let local = self.config.local_player;
+        // During the first `input_delay` frames nobody has input yet; fill with defaults
+        for early in 0..self.config.input_delay.min(frame) {
+            self.players[local].confirmed.entry(early).or_default();
+        }
+        self.add_input(local, frame, input);
+        frame
+    }
+
+    /// Add a confirmed input for a remote player received from the network
+    pub fn add_remote_input(&mut self, player: usize, frame: Frame, input: I) {
+        if player >= self.players.len() || player == self.config.local_player {
+            return;
+        }
+        self.add_input(player, frame, input);
+    }
+
+    fn add_input(&mut self, player: usize, frame: Frame, input: I) {
+        let inputs = &mut self.players[player];
+        if inputs.confirmed.contains_key(&frame) {
+            return;
+        }
+
+        // A frame we already simulated with a wrong guess must be resimulated
+        if let Some(predicted) = inputs.predicted.remove(&frame) {
+            if predicted != input {
+                self.rollback_to = Some(self.rollback_to.map_or(frame, |f| f.min(frame)));
+            }
+        }
+
+        inputs.last_confirmed_frame = Some(inputs.last_confirmed_frame.map_or(frame, |f| f.max(frame)));
+        inputs.confirmed.insert(frame, input);
+    }
+
+    /// Record a remote peer's checksum for a confirmed frame
+    pub fn add_remote_checksum(&mut self, frame: Frame, checksum: u64) {
+        self.remote_checksums.insert(frame, checksum);
+        self.check_desync(frame);
+    }
+
+    /// Get our checksum of the state after simulating a frame
+    pub fn checksum(&self, frame: Frame) -> Option<u64> {
+        self.checksums.get(&frame).copied()
+    }
+
+    fn check_desync(&mut self, frame: Frame) {
+        if let (Some(local), Some(remote)) = (self.checksums.get(&frame), self.remote_checksums.get(&frame)) {
+            if local != remote && self.desync_frame.is_none() {
+                log::error!("Desync detected at frame {}", frame);
+                self.desync_frame = Some(frame);
+            }
+        }
+    }
+
+    /// Collect the inputs of all players for a frame, recording predictions
+    fn frame_inputs(&mut self, frame: Frame) -> Vec<I> {
+        let mut inputs = Vec::with_capacity(self.players.len());
+        for player in &mut self.players {
+            let (input, confirmed) = player.input_for(frame);
+            if confirmed {
+                player.predicted.remove(&frame);
+            } else {
+                player.predicted.insert(frame, input.clone());
+            }
+            inputs.push(input);
+        }
+        inputs
+    }
+
+    fn save(&mut self, frame: Frame, state: &S) {
+        self.states.retain(|(f, _)| *f != frame);
+        self.states.push_back((frame, state.save_state()));
+        let keep = self.config.max_prediction as usize + 2;
+        while self.states.len() > keep {
+            self.states.pop_front();
+        }
+    }
+
+    fn record_checksum(&mut self, frame: Frame, state: &S) {
+        if let Some(checksum) = state.checksum() {
+            self.checksums.insert(frame, checksum);
+        }
+    }
+
+    fn update_confirmed(&mut self) {
+        let start = self.confirmed_frame.map_or(0, |f| f + 1);
+        let confirmed = self
+            .players
+            .iter()
+            .map(|p| p.contiguous_confirmed(start))
+            .collect::<Option<Vec<Frame>>>()
+            .and_then(|frames| frames.into_iter().min());
+
+        if let Some(frame) = confirmed {
+            // Only frames we've actually simulated count as confirmed
+            let frame = frame.min(self.current_frame.saturating_sub(1));
+            if self.current_frame > 0 && self.confirmed_frame.is_none_or(|f| frame > f) {
+                self.confirmed_frame = Some(frame);
+                self.check_desync(frame);
+
+                // Forget history no rollback can reach anymore
+                for player in &mut self.players {
+                    player.confirmed.retain(|f, _| *f + 1 >= frame);
+                    player.predicted.retain(|f, _| *f > frame);
+                }
+                self.checksums.retain(|f, _| *f + 64 >= frame);
+                self.remote_checksums.retain(|f, _| *f + 64 >= frame);
+            }
+        }
+    }
+
+    /// Simulate the next frame, rolling back and resimulating first if needed
+    ///
+    /// `step` advances the state by one frame given every player's input
+    /// (indexed by player).
+    pub fn advance<F>(&mut self, state: &mut S, mut step: F) -> AdvanceResult
+    where
+        F: FnMut(&mut S, &[I]),
+    {
+        let mut rolled_back = 0;
+
+        if let Some(target) = self.rollback_to.take() {
+            if target < self.current_frame {
+                match self.states.iter().find(|(f, _)| *f == target) {
+                    Some((_, snapshot)) => {
+                        state.load_state(snapshot);
+                        for frame in target..self.current_frame {
+                            self.save(frame, state);
+                            let inputs = self.frame_inputs(frame);
+                            step(state, &inputs);
+                            self.record_checksum(frame, state);
+                            rolled_back += 1;
+                        }
+                        self.total_rollbacks += rolled_back;
+                    }
+                    None => log::error!("Cannot roll back to frame {}: state not saved", target),
+                }
+            }
+        }
+
+        let confirmed = self.confirmed_frame.map_or(0, |f| f + 1);
+        if self.current_frame >= confirmed + self.config.max_prediction {
+            self.update_confirmed();
+            return AdvanceResult::Stalled;
+        }
+
+        let frame = self.current_frame;
+        self.save(frame, state);
+        let inputs = self.frame_inputs(frame);
+        step(state, &inputs);
+        self.record_checksum(frame, state);
+        self.current_frame += 1;
+        self.update_confirmed();
+
+        AdvanceResult::Advanced { frame, rolled_back }
+    }
+
+    /// Local inputs from `from` onwards, for sending to peers
+    pub fn local_inputs_since(&self, from: Frame) -> Vec<(Frame, I)> {
+        let local = &self.players[self.config.local_player];
+        let mut inputs: Vec<(Frame, I)> = local
+            .confirmed
+            .iter()
+            .filter(|(f, _)| **f >= from)
+            .map(|(f, i)| (*f, i.clone()))
+            .collect();
+        inputs.sort_by_key(|(f, _)| *f);
+        inputs
+    }
+}
+
+/// Input packet exchanged between peers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InputPacket<I> {
+    player: usize,
+    /// Oldest frame the sender still needs acknowledged from us
+    ack: Option<Frame>,
+    inputs: Vec<(Frame, I)>,
+    checksum: Option<(Frame, u64)>,
+}
+
+/// Minimal UDP transport exchanging inputs between two or more peers
+///
+/// Inputs are sent redundantly until acknowledged, so individual packet loss
+/// only delays confirmation instead of stalling the game.
+pub struct RollbackSocket {
+    socket: UdpSocket,
+    peers: Vec<(usize, SocketAddr)>,
+    acked: HashMap<usize, Frame>,
+    received: HashMap<usize, Frame>,
+}
+
+impl RollbackSocket {
+    /// Bind a local socket and register peers as (player index, address)
+    pub fn bind<A: ToSocketAddrs>(addr: A, peers: Vec<(usize, SocketAddr)>) -> Result<Self, String> {
+        let socket = UdpSocket::bind(addr).map_err(|e| format!("Failed to bind UDP socket: {}", e))?;
+        socket
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to set socket non-blocking: {}", e))?;
+        Ok(Self {
+            socket,
+            peers,
+            acked: HashMap::new(),
+            received: HashMap::new(),
+        })
+    }
+
+    /// Get the bound local address
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.socket.local_addr().ok()
+    }
+
+    /// Send unacknowledged local inputs (and the newest checksum) to all peers
+    pub fn send<I, S>(&self, session: &RollbackSession<I, S>)
+    where
+        I: Clone + Default + PartialEq + Serialize,
+        S: RollbackState,
+    {
+        let checksum = session
+            .confirmed_frame()
+            .and_then(|f| session.checksum(f).map(|c| (f, c)));
+
+        for (player, addr) in &self.peers {
+            let from = self.acked.get(player).map_or(0, |f| f + 1);
+            let packet = InputPacket {
+                player: session.config().local_player,
+                ack: self.received.get(player).copied(),
+                inputs: session.local_inputs_since(from),
+                checksum,
+            };
+            match bincode::serialize(&packet) {
+                Ok(bytes) => {
+                    if let Err(e) = self.socket.send_to(&bytes, addr) {
+                        log::debug!("Failed to send inputs to {}: {}", addr, e);
+                    }
+                }
+                Err(e) => log::warn!("Failed to encode input packet: {}", e),
+            }
+        }
+    }
+
+    /// Receive remote inputs and feed them into the session
+    pub fn receive<I, S>(&mut self, session: &mut RollbackSession<I, S>)
+    where
+        I: Clone + Default + PartialEq + DeserializeOwned,
+        S: RollbackState,
+    {
+        let mut buffer = [0u8; 65536];
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => continue,
+                Err(e) => {
+                    log::warn!("UDP receive error: {}", e);
+                    break;
+                }
+            };
+            if !self.peers.iter().any(|(_, peer)| *peer == addr) {
+                continue;
+            }
+            let Ok(packet) = bincode::deserialize::<InputPacket<I>>(&buffer[..len]) else {
+                continue;
+            };
+
+            if let Some(ack) = packet.ack {
+                let acked = self.acked.entry(packet.player).or_insert(ack);
+                *acked = (*acked).max(ack);
+            }
+            for (frame, input) in packet.inputs {
+                let received = self.received.entry(packet.player).or_insert(frame);
+                *received = (*received).max(frame);
+                session.add_remote_input(packet.player, frame, input);
+            }
+            if let Some((frame, checksum)) = packet.checksum {
+                session.add_remote_checksum(frame, checksum);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Game {
+        positions: [i64; 2],
+    }
+
+    impl RollbackState for Game {
+        type Snapshot = Game;
+
+        fn save_state(&self) -> Game {
+            self.clone()
+        }
+
+        fn load_state(&mut self, snapshot: &Game) {
+            *self = snapshot.clone();
+        }
+
+        fn checksum(&self) -> Option<u64> {
+            Some((self.positions[0] * 31 + self.positions[1]) as u64)
+        }
+    }
+
+    fn step(game: &mut Game, inputs: &[i8]) {
+        for (position, input) in game.positions.iter_mut().zip(inputs) {
+            *position += *input as i64;
+        }
+    }
+
+    #[test]
+    fn test_rollback_converges() {
+        let config = |local_player| RollbackConfig {
+            local_player,
+            input_delay: 0,
+            ..Default::default()
+        };
+        let mut a: RollbackSession<i8, Game> = RollbackSession::new(config(0));
+        let mut b: RollbackSession<i8, Game> = RollbackSession::new(config(1));
+        let mut game_a = Game { positions: [0, 0] };
+        let mut game_b = Game { positions: [0, 0] };
+
+        // Player 1 changes input on frame 3; A only learns about it two frames later
+        let input_b = |frame: Frame| if frame < 3 { 1 } else { -1 };
+        let mut delayed: VecDeque<(Frame, i8)> = VecDeque::new();
+
+        for frame in 0..10 {
+            let fa = a.add_local_input(1);
+            let fb = b.add_local_input(input_b(frame));
+            b.add_remote_input(0, fa, 1);
+            delayed.push_back((fb, input_b(frame)));
+            if delayed.len() > 2 {
+                let (f, i) = delayed.pop_front().unwrap();
+                a.add_remote_input(1, f, i);
+            }
+            a.advance(&mut game_a, step);
+            b.advance(&mut game_b, step);
+        }
+        while let Some((f, i)) = delayed.pop_front() {
+            a.add_remote_input(1, f, i);
+        }
+        a.add_local_input(0);
+        b.add_local_input(0);
+        a.add_remote_input(1, 10, 0);
+        b.add_remote_input(0, 10, 0);
+        a.advance(&mut game_a, step);
+        b.advance(&mut game_b, step);
+
+        assert!(a.total_rollbacks() > 0);
+        assert_eq!(game_a, game_b);
+        assert_eq!(game_a.positions, [10, 3 - 7]);
+    }
+}
@@ -0,0 +1,394 @@
+//! Save game system
+//!
+//! Serializes registered ("persistent") components and named world resources
+//! into versioned save slots, written atomically into a platform-appropriate
+//! save directory. Unlike a raw scene file, a save only contains the component
+//! types the game opted in to, and loading reconstructs those entities.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::ecs::{Component, EntityMut, EntityRef, Scene};
+
+/// Current save file format version
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+/// Metadata stored in the header of every save
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveMetadata {
+    /// Slot name the save was written to
+    pub slot: String,
+    /// Save format version
+    pub version: u32,
+    /// Game-defined version (e.g. the game's release number)
+    pub game_version: u32,
+    /// Seconds since the UNIX epoch when the save was written
+    pub timestamp: u64,
+    /// Name of the scene that was saved
+    pub scene_name: String,
+}
+
+/// A saved entity with its persistent components
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedEntity {
+    pub name: String,
+    pub active: bool,
+    pub components: HashMap<String, serde_json::Value>,
+}
+
+/// Full contents of a save slot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveData {
+    pub metadata: SaveMetadata,
+    pub entities: Vec<SavedEntity>,
+    pub resources: HashMap<String, serde_json::Value>,
+}
+
+type SerializeFn = fn(&EntityRef) -> Option<Result<serde_json::Value, String>>;
+type DeserializeFn = fn(&mut EntityMut, serde_json::Value) -> Result<(), String>;
+type MigrationFn = Box<dyn Fn(&mut serde_json::Value) -> Result<(), String>>;
+
+struct PersistentComponent {
+    name: String,
+    serialize: SerializeFn,
+    deserialize: DeserializeFn,
+}
+
+fn serialize_component<T: Component + Serialize>(entity: &EntityRef) -> Option<Result<serde_json::Value, String>> {
+    entity.get_component::<T>().map(|component| {
+        serde_json::to_value(component).map_err(|e| format!("Failed to serialize component: {}", e))
+    })
+}
+
+fn deserialize_component<T: Component + DeserializeOwned>(
+    entity: &mut EntityMut,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    let component: T = serde_json::from_value(value)
+        .map_err(|e| format!("Failed to deserialize component: {}", e))?;
+    entity.add_component(component);
+    Ok(())
+}
+
+/// Get the platform-appropriate directory for a game's save files
+///
+/// - Windows: `%APPDATA%\<game>\saves`
+/// - macOS: `~/Library/Application Support/<game>/saves`
+/// - Linux/other: `$XDG_DATA_HOME/<game>/saves` or `~/.local/share/<game>/saves`
+pub fn default_save_dir(game_name: &str) -> PathBuf {
+    let env_path = |key: &str| std::env::var_os(key).filter(|v| !v.is_empty()).map(PathBuf::from);
+
+    let base = if cfg!(target_os = "windows") {
+        env_path("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_path("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_path("XDG_DATA_HOME")
+            .or_else(|| env_path("HOME").map(|home| home.join(".local").join("share")))
+    };
+
+    base.unwrap_or_else(|| PathBuf::from("."))
+        .join(game_name)
+        .join("saves")
+}
+
+/// Write a file atomically by writing a temporary sibling and renaming it over the target
+pub fn write_atomic<P: AsRef<Path>>(path: P, contents: &[u8]) -> Result<(), String> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
+    }
+
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let write = || -> std::io::Result<()> {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    };
+
+    write().map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("Failed to write {:?}: {}", path, e)
+    })
+}
+
+/// Writes and reads save slots
+pub struct SaveManager {
+    save_dir: PathBuf,
+    game_version: u32,
+    components: Vec<PersistentComponent>,
+    resources: HashMap<String, serde_json::Value>,
+    migrations: HashMap<u32, MigrationFn>,
+}
+
+impl SaveManager {
+    /// Create a save manager using the platform save directory for `game_name`
+    pub fn new(game_name: &str) -> Self {
+        Self::with_dir(default_save_dir(game_name))
+    }
+
+    /// Create a save manager writing into a specific directory
+    pub fn with_dir<P: Into<PathBuf>>(save_dir: P) -> Self {
+        Self {
+            save_dir: save_dir.into(),
+            game_version: 0,
+            components: Vec::new(),
+            resources: HashMap::new(),
+            migrations: HashMap::new(),
+        }
+    }
+
+    /// Get the save directory
+    pub fn save_dir(&self) -> &Path {
+        &self.save_dir
+    }
+
+    /// Set the game-defined version written into new saves
+    pub fn set_game_version(&mut self, version: u32) {
+        self.game_version = version;
+    }
+
+    /// Mark a component type as persistent under a stable name
+    ///
+    /// The name is written into save files, so it must not change between releases.
+    pub fn register_component<T>(&mut self, name: &str)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.components.retain(|c| c.name != name);
+        self.components.push(PersistentComponent {
+            name: name.to_string(),
+            serialize: serialize_component::<T>,
+            deserialize: deserialize_component::<T>,
+        });
+    }
+
+    /// Register a migration that upgrades save JSON written with `from_game_version`
+    ///
+    /// Migrations run in ascending version order for every version at or above the
+    /// save's game version and below the current one.
+    pub fn register_migration<F>(&mut self, from_game_version: u32, migration: F)
+    where
+        F: Fn(&mut serde_json::Value) -> Result<(), String> + 'static,
+    {
+        self.migrations.insert(from_game_version, Box::new(migration));
+    }
+
+    /// Store a world resource (score, quest flags, RNG seed, ...) to include in saves
+    pub fn set_resource<T: Serialize>(&mut self, name: &str, value: &T) -> Result<(), String> {
+        let value = serde_json::to_value(value)
+            .map_err(|e| format!("Failed to serialize resource '{}': {}", name, e))?;
+        self.resources.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Get a world resource (set directly or restored by the last load)
+    pub fn resource<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.resources
+            .get(name)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+
+    /// Get the path of a save slot
+    pub fn slot_path(&self, slot: &str) -> PathBuf {
+        self.save_dir.join(format!("{}.save.json", slot))
+    }
+
+    /// Collect persistent data from a scene without writing it
+    pub fn capture(&self, slot: &str, scene: &Scene) -> Result<SaveData, String> {
+        let mut entities: Vec<EntityRef> = scene.entities().collect();
+        entities.sort_by_key(|e| e.id());
+
+        let mut saved = Vec::new();
+        for entity in entities {
+            let mut components = HashMap::new();
+            for persistent in &self.components {
+                if let Some(value) = (persistent.serialize)(&entity) {
+                    components.insert(persistent.name.clone(), value?);
+                }
+            }
+            if !components.is_empty() {
+                saved.push(SavedEntity {
+                    name: entity.name().to_string(),
+                    active: entity.is_self_active(),
+                    components,
+                });
+            }
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Ok(SaveData {
+            metadata: SaveMetadata {
+                slot: slot.to_string(),
+                version: SAVE_FORMAT_VERSION,
+                game_version: self.game_version,
+                timestamp,
+                scene_name: scene.name().to_string(),
+            },
+            entities: saved,
+            resources: self.resources.clone(),
+        })
+    }
+
+    /// Write the persistent state of a scene into a save slot
+    pub fn save(&self, slot: &str, scene: &Scene) -> Result<SaveMetadata, String> {
+        let data = self.capture(slot, scene)?;
+        let content = serde_json::to_vec_pretty(&data)
+            .map_err(|e| format!("Failed to serialize save: {}", e))?;
+        write_atomic(self.slot_path(slot), &content)?;
+
+        log::info!("Saved {} entities to slot '{}'", data.entities.len(), slot);
+        Ok(data.metadata)
+    }
+
+    /// Read a save slot, applying migrations, without touching any scene
+    pub fn read_save(&self, slot: &str) -> Result<SaveData, String> {
+        let path = self.slot_path(slot);
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read save slot '{}': {}", slot, e))?;
+        let mut value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse save slot '{}': {}", slot, e))?;
+
+        let format_version = value["metadata"]["version"].as_u64().unwrap_or(0) as u32;
+        if format_version > SAVE_FORMAT_VERSION {
+            return Err(format!(
+                "Save slot '{}' uses format version {} (supported: {})",
+                slot, format_version, SAVE_FORMAT_VERSION
+            ));
+        }
+
+        let saved_game_version = value["metadata"]["game_version"].as_u64().unwrap_or(0) as u32;
+        if saved_game_version < self.game_version {
+            let mut versions: Vec<u32> = self
+                .migrations
+                .keys()
+                .copied()
+                .filter(|v| *v >= saved_game_version && *v < self.game_version)
+                .collect();
+            versions.sort_unstable();
+            for version in versions {
+                (self.migrations[&version])(&mut value)?;
+            }
+            value["metadata"]["game_version"] = self.game_version.into();
+        }
+
+        serde_json::from_value(value).map_err(|e| format!("Invalid save slot '{}': {}", slot, e))
+    }
+
+    /// Load a save slot, replacing the scene's entities with the saved ones
+    pub fn load_save(&mut self, slot: &str, scene: &mut Scene) -> Result<SaveMetadata, String> {
+        let data = self.read_save(slot)?;
+
+        scene.clear();
+        for saved in data.entities {
+            let id = scene.create_entity(saved.name);
+            let mut entity = scene
+                .get_entity_mut(id)
+                .ok_or_else(|| format!("Failed to create entity {}", id))?;
+            entity.set_active(saved.active);
+
+            for (name, value) in saved.components {
+                match self.components.iter().find(|c| c.name == name) {
+                    Some(persistent) => (persistent.deserialize)(&mut entity, value)?,
+                    None => log::warn!("Skipping unregistered component '{}' in save", name),
+                }
+            }
+        }
+
+        self.resources = data.resources;
+        log::info!("Loaded save slot '{}'", slot);
+        Ok(data.metadata)
+    }
+
+    /// Check if a save slot exists
+    pub fn has_save(&self, slot: &str) -> bool {
+        self.slot_path(slot).is_file()
+    }
+
+    /// Delete a save slot
+    pub fn delete_save(&self, slot: &str) -> Result<(), String> {
+        fs::remove_file(self.slot_path(slot))
+            .map_err(|e| format!("Failed to delete save slot '{}': {}", slot, e))
+    }
+
+    /// List the metadata of all saves, newest first
+    pub fn list_saves(&self) -> Vec<SaveMetadata> {
+        let Ok(entries) = fs::read_dir(&self.save_dir) else {
+            return Vec::new();
+        };
+
+        #[derive(Deserialize)]
+        struct Header {
+            metadata: SaveMetadata,
+        }
+
+        let mut saves: Vec<SaveMetadata> = entries
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".save.json"))
+            .filter_map(|e| fs::read_to_string(e.path()).ok())
+            .filter_map(|content| serde_json::from_str::<Header>(&content).ok())
+            .map(|header| header.metadata)
+            .collect();
+        saves.sort_by_key(|save| std::cmp::Reverse(save.timestamp));
+        saves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Transform;
+    use glam::Vec3;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Health(i32);
+    impl Component for Health {}
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("rgame_save_test_{}", std::process::id()));
+        let mut saves = SaveManager::with_dir(&dir);
+        saves.register_component::<Transform>("transform");
+        saves.register_component::<Health>("health");
+        saves.set_resource("score", &1200u32).unwrap();
+
+        let mut scene = Scene::new("Level".to_string());
+        let player = scene.create_entity("Player".to_string());
+        let mut entity = scene.get_entity_mut(player).unwrap();
+        entity.add_component(Transform::from_position(Vec3::new(1.0, 2.0, 3.0)));
+        entity.add_component(Health(75));
+        scene.create_entity("Unsaved".to_string());
+
+        saves.save("slot1", &scene).unwrap();
+        assert!(saves.has_save("slot1"));
+
+        let mut loaded = Scene::new("Level".to_string());
+        saves.set_resource("score", &0u32).unwrap();
+        saves.load_save("slot1", &mut loaded).unwrap();
+
+        assert_eq!(loaded.entity_count(), 1);
+        let entity = loaded.entities().next().unwrap();
+        assert_eq!(entity.name(), "Player");
+        assert_eq!(entity.get_component::<Health>(), Some(&Health(75)));
+        assert_eq!(
+            entity.get_component::<Transform>().unwrap().position,
+            Vec3::new(1.0, 2.0, 3.0)
+        );
+        assert_eq!(saves.resource::<u32>("score"), Some(1200));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,201 @@
+//! Scene files
+//!
+//! `scene.save(path, &registry)` writes every entity of a scene as JSON: its ID, name,
+//! active flag, children, and all components registered in the [`ComponentRegistry`].
+//! `Scene::load(path, &registry)` rebuilds the scene with the same entity IDs and
+//! hierarchy, so levels can be authored once and loaded at runtime. Components that
+//! aren't registered are not saved; `GlobalTransform` is recomputed on load.
+
+use crate::ecs::{EntityId, EntityRef, Scene};
+use crate::hierarchy;
+use crate::registry::ComponentRegistry;
+use crate::save::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Current scene file format version
+pub const SCENE_FORMAT_VERSION: u32 = 1;
+
+/// A saved entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneEntity {
+    pub id: EntityId,
+    pub name: String,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    /// Child entity IDs in attachment order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<EntityId>,
+    /// Registered components keyed by name
+    #[serde(default)]
+    pub components: serde_json::Map<String, serde_json::Value>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// Full contents of a scene file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneData {
+    pub version: u32,
+    pub name: String,
+    pub entities: Vec<SceneEntity>,
+}
+
+impl Scene {
+    /// Collect the entities and registered components of the scene without writing them
+    pub fn to_scene_data(&self, registry: &ComponentRegistry) -> Result<SceneData, String> {
+        let mut entities: Vec<EntityRef> = self.entities().collect();
+        entities.sort_by_key(|e| e.id());
+
+        let entities = entities
+            .into_iter()
+            .map(|entity| {
+                Ok(SceneEntity {
+                    id: entity.id(),
+                    name: entity.name().to_string(),
+                    active: entity.is_self_active(),
+                    children: self.children(entity.id()).to_vec(),
+                    components: registry.serialize_entity(&entity)?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(SceneData {
+            version: SCENE_FORMAT_VERSION,
+            name: self.name().to_string(),
+            entities,
+        })
+    }
+
+    /// Build a scene from scene data, keeping entity IDs and hierarchy
+    pub fn from_scene_data(data: SceneData, registry: &ComponentRegistry) -> Result<Scene, String> {
+        if data.version > SCENE_FORMAT_VERSION {
+            return Err(format!(
+                "Scene '{}' uses format version {} (supported: {})",
+                data.name, data.version, SCENE_FORMAT_VERSION
+            ));
+        }
+
+        let mut scene = Scene::new(data.name);
+        for saved in &data.entities {
+            if !scene.create_entity_with_id(saved.id, saved.name.clone()) {
+                return Err(format!("Entity ID {} appears twice", saved.id));
+            }
+        }
+
+        for saved in data.entities {
+            for (name, value) in saved.components {
+                if !registry.contains(&name) {
+                    log::warn!("Skipping unregistered component '{}' on entity {}", name, saved.id);
+                    continue;
+                }
+                let component = registry.deserialize(&name, value)?;
+                if let Some(mut entity) = scene.get_entity_mut(saved.id) {
+                    component.insert_into(&mut entity);
+                }
+            }
+            for child in saved.children {
+                if !scene.set_parent(child, saved.id) {
+                    return Err(format!("Entity {} has invalid child {}", saved.id, child));
+                }
+            }
+            if let Some(mut entity) = scene.get_entity_mut(saved.id) {
+                entity.set_active(saved.active);
+            }
+        }
+
+        hierarchy::propagate_transforms(&mut scene);
+        Ok(scene)
+    }
+
+    /// Write the scene into a JSON file
+    pub fn save<P: AsRef<Path>>(&self, path: P, registry: &ComponentRegistry) -> Result<(), String> {
+        let data = self.to_scene_data(registry)?;
+        let content = serde_json::to_vec_pretty(&data)
+            .map_err(|e| format!("Failed to serialize scene: {}", e))?;
+        write_atomic(path.as_ref(), &content)?;
+
+        log::info!("Saved scene '{}' ({} entities) to {:?}", self.name(), data.entities.len(), path.as_ref());
+        Ok(())
+    }
+
+    /// Load a scene from a JSON file written by [`Scene::save`]
+    pub fn load<P: AsRef<Path>>(path: P, registry: &ComponentRegistry) -> Result<Scene, String> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read scene {:?}: {}", path, e))?;
+        let data: SceneData = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse scene {:?}: {}", path, e))?;
+        Self::from_scene_data(data, registry).map_err(|e| format!("{} ({:?})", e, path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::Component;
+    use crate::math::Transform;
+    use glam::Vec3;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Health(i32);
+    impl Component for Health {}
+
+    #[test]
+    fn test_save_and_load_scene() {
+        let mut registry = ComponentRegistry::new();
+        registry.register::<Transform>("transform");
+        registry.register::<Health>("health");
+
+        let mut scene = Scene::new("Level 1".to_string());
+        scene.create_entity("Deleted".to_string());
+        let ship = scene.create_entity("Ship".to_string());
+        let turret = scene.create_entity("Turret".to_string());
+        scene.remove_entity(0);
+        let mut entity = scene.get_entity_mut(ship).unwrap();
+        entity.add_component(Transform::from_position(Vec3::new(5.0, 0.0, 0.0)));
+        entity.add_component(Health(40));
+        let mut entity = scene.get_entity_mut(turret).unwrap();
+        entity.add_component(Transform::from_position(Vec3::Y));
+        entity.set_active(false);
+        scene.set_parent(turret, ship);
+
+        let path = std::env::temp_dir().join(format!("rgame_scene_test_{}.json", std::process::id()));
+        scene.save(&path, &registry).unwrap();
+        let mut loaded = Scene::load(&path, &registry).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(loaded.name(), "Level 1");
+        assert_eq!(loaded.entity_count(), 2);
+        assert_eq!(loaded.get_component::<Health>(ship), Some(&Health(40)));
+        assert_eq!(loaded.children(ship), [turret]);
+        assert!(!loaded.get_entity(turret).unwrap().is_active());
+        let world = loaded.get_component::<hierarchy::GlobalTransform>(turret).unwrap();
+        assert_eq!(world.translation(), Vec3::new(5.0, 1.0, 0.0));
+
+        // New entities don't collide with loaded IDs
+        let spawned = loaded.create_entity("Spawned".to_string());
+        assert!(spawned > turret);
+    }
+
+    #[test]
+    fn test_invalid_scene_data_rejected() {
+        let registry = ComponentRegistry::new();
+        let data = SceneData {
+            version: SCENE_FORMAT_VERSION,
+            name: "Bad".to_string(),
+            entities: vec![SceneEntity {
+                id: 3,
+                name: "Orphan".to_string(),
+                active: true,
+                children: vec![7],
+                components: serde_json::Map::new(),
+            }],
+        };
+        let error = Scene::from_scene_data(data, &registry).err().unwrap();
+        assert!(error.contains("invalid child 7"));
+    }
+}
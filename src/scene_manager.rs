@@ -0,0 +1,271 @@
+//! Multiple scenes
+//!
+//! The engine holds every loaded scene (menu, levels, ...) in a [`SceneManager`] and
+//! runs and renders the active one. Scenes added ahead of time stay loaded, so a level
+//! can be built while the menu is showing. Switching runs the scenes' unload and load
+//! hooks, and moves entities tagged [`Persistent`] (with their children) into the new
+//! scene, e.g. the player or a music controller.
+//!
+//! The game loop callback only sees the active scene; it requests switches through a
+//! [`SceneSwitcher`] handle, which the engine applies at the start of the next frame.
+
+use crate::ecs::{Component, EntityId, Scene, With};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Tag for entities that move along when the active scene changes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Persistent;
+
+impl Component for Persistent {}
+
+type SceneHook = Box<dyn FnMut(&mut Scene)>;
+
+#[derive(Default)]
+struct SceneHooks {
+    on_load: Option<SceneHook>,
+    on_unload: Option<SceneHook>,
+}
+
+/// Handle for requesting a scene switch from inside the game loop
+#[derive(Clone, Default)]
+pub struct SceneSwitcher {
+    request: Rc<RefCell<Option<String>>>,
+}
+
+impl SceneSwitcher {
+    /// Switch to a scene at the start of the next frame
+    pub fn switch_to(&self, name: &str) {
+        *self.request.borrow_mut() = Some(name.to_string());
+    }
+
+    /// Get the requested scene, if a switch is pending
+    pub fn pending(&self) -> Option<String> {
+        self.request.borrow().clone()
+    }
+}
+
+/// Loaded scenes, one of which is active
+pub struct SceneManager {
+    scenes: Vec<Scene>,
+    active: usize,
+    hooks: HashMap<String, SceneHooks>,
+    switcher: SceneSwitcher,
+}
+
+impl SceneManager {
+    /// Create a manager with one active scene
+    pub fn new(scene: Scene) -> Self {
+        Self {
+            scenes: vec![scene],
+            active: 0,
+            hooks: HashMap::new(),
+            switcher: SceneSwitcher::default(),
+        }
+    }
+
+    /// Add (preload) a scene without activating it
+    ///
+    /// Fails if a scene with the same name is already loaded.
+    pub fn add(&mut self, scene: Scene) -> bool {
+        if self.index_of(scene.name()).is_some() {
+            return false;
+        }
+        self.scenes.push(scene);
+        true
+    }
+
+    /// Unload a scene, returning it (the active scene can't be removed)
+    pub fn remove(&mut self, name: &str) -> Option<Scene> {
+        let index = self.index_of(name).filter(|&i| i != self.active)?;
+        if index < self.active {
+            self.active -= 1;
+        }
+        Some(self.scenes.remove(index))
+    }
+
+    /// Get a loaded scene
+    pub fn get(&self, name: &str) -> Option<&Scene> {
+        self.index_of(name).map(|i| &self.scenes[i])
+    }
+
+    /// Get a loaded scene (mutable)
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Scene> {
+        self.index_of(name).map(|i| &mut self.scenes[i])
+    }
+
+    /// Check if a scene is loaded
+    pub fn contains(&self, name: &str) -> bool {
+        self.index_of(name).is_some()
+    }
+
+    /// Names of the loaded scenes, in the order they were added
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.scenes.iter().map(|s| s.name())
+    }
+
+    /// Get the active scene
+    pub fn active(&self) -> &Scene {
+        &self.scenes[self.active]
+    }
+
+    /// Get the active scene (mutable)
+    pub fn active_mut(&mut self) -> &mut Scene {
+        &mut self.scenes[self.active]
+    }
+
+    /// Get the name of the active scene
+    pub fn active_name(&self) -> &str {
+        self.active().name()
+    }
+
+    /// Run `hook` every time a scene becomes active, after persistent entities arrived
+    pub fn on_load<F: FnMut(&mut Scene) + 'static>(&mut self, name: &str, hook: F) {
+        self.hooks.entry(name.to_string()).or_default().on_load = Some(Box::new(hook));
+    }
+
+    /// Run `hook` every time a scene stops being active, after persistent entities left
+    pub fn on_unload<F: FnMut(&mut Scene) + 'static>(&mut self, name: &str, hook: F) {
+        self.hooks.entry(name.to_string()).or_default().on_unload = Some(Box::new(hook));
+    }
+
+    /// Get a handle for requesting switches from the game loop
+    pub fn switcher(&self) -> SceneSwitcher {
+        self.switcher.clone()
+    }
+
+    /// Make another loaded scene active
+    pub fn set_active(&mut self, name: &str) -> Result<(), String> {
+        let index = self
+            .index_of(name)
+            .ok_or_else(|| format!("Scene '{}' is not loaded", name))?;
+        if index == self.active {
+            return Ok(());
+        }
+
+        let previous = self.active;
+        let (from, to) = if previous < index {
+            let (left, right) = self.scenes.split_at_mut(index);
+            (&mut left[previous], &mut right[0])
+        } else {
+            let (left, right) = self.scenes.split_at_mut(previous);
+            (&mut right[0], &mut left[index])
+        };
+        let moved = transfer_persistent(from, to);
+
+        let from_name = from.name().to_string();
+        if let Some(hook) = self.hooks.get_mut(&from_name).and_then(|h| h.on_unload.as_mut()) {
+            hook(&mut self.scenes[previous]);
+        }
+        self.active = index;
+        if let Some(hook) = self.hooks.get_mut(name).and_then(|h| h.on_load.as_mut()) {
+            hook(&mut self.scenes[index]);
+        }
+
+        log::info!("Switched scene '{}' -> '{}' ({} persistent entities)", from_name, name, moved);
+        Ok(())
+    }
+
+    /// Apply a switch requested through a [`SceneSwitcher`]
+    pub(crate) fn apply_requested_switch(&mut self) {
+        let request = self.switcher.request.borrow_mut().take();
+        if let Some(name) = request {
+            if let Err(e) = self.set_active(&name) {
+                log::error!("Failed to switch scene: {}", e);
+            }
+        }
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.scenes.iter().position(|s| s.name() == name)
+    }
+}
+
+/// Move every `Persistent` entity and its descendants, keeping their hierarchy
+fn transfer_persistent(from: &mut Scene, to: &mut Scene) -> usize {
+    let roots: Vec<EntityId> = from
+        .query_filtered::<EntityId, With<Persistent>>()
+        .collect();
+
+    // Parents first, so children can be attached to the moved copies
+    let mut order: Vec<(EntityId, Option<EntityId>)> = Vec::new();
+    let mut stack: Vec<(EntityId, Option<EntityId>)> = roots
+        .iter()
+        .filter(|&&id| !has_persistent_ancestor(from, id))
+        .map(|&id| (id, None))
+        .collect();
+    while let Some((id, parent)) = stack.pop() {
+        order.push((id, parent));
+        stack.extend(from.children(id).iter().map(|&child| (child, Some(id))));
+    }
+
+    let mut moved: HashMap<EntityId, EntityId> = HashMap::new();
+    for (id, parent) in order {
+        if let Some(new_id) = from.transfer_entity(id, to) {
+            if let Some(new_parent) = parent.and_then(|p| moved.get(&p)) {
+                to.set_parent(new_id, *new_parent);
+            }
+            moved.insert(id, new_id);
+        }
+    }
+    moved.len()
+}
+
+fn has_persistent_ancestor(scene: &Scene, id: EntityId) -> bool {
+    let mut ancestor = scene.parent(id);
+    while let Some(parent) = ancestor {
+        if scene.get_component::<Persistent>(parent).is_some() {
+            return true;
+        }
+        ancestor = scene.parent(parent);
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Transform;
+    use glam::Vec3;
+
+    #[test]
+    fn test_switch_scenes_with_persistent_entities() {
+        let mut menu = Scene::new("Menu".to_string());
+        let player = menu.create_entity("Player".to_string());
+        let sword = menu.create_entity("Sword".to_string());
+        menu.create_entity("Button".to_string());
+        let mut entity = menu.get_entity_mut(player).unwrap();
+        entity.add_component(Persistent);
+        entity.add_component(Transform::from_position(Vec3::X));
+        menu.set_parent(sword, player);
+
+        let mut scenes = SceneManager::new(menu);
+        assert!(scenes.add(Scene::new("Level 1".to_string())));
+        assert!(!scenes.add(Scene::new("Menu".to_string())));
+        scenes.on_load("Level 1", |scene| {
+            scene.create_entity("Spawner".to_string());
+        });
+        scenes.on_unload("Menu", |scene| scene.clear());
+
+        let switcher = scenes.switcher();
+        switcher.switch_to("Level 1");
+        assert_eq!(switcher.pending().as_deref(), Some("Level 1"));
+        scenes.apply_requested_switch();
+        assert_eq!(switcher.pending(), None);
+
+        assert_eq!(scenes.active_name(), "Level 1");
+        assert_eq!(scenes.get("Menu").unwrap().entity_count(), 0);
+        let level = scenes.active();
+        assert_eq!(level.entity_count(), 3);
+        let player = level.find_by_name("Player").unwrap();
+        assert_eq!(level.get_component::<Transform>(player).unwrap().position, Vec3::X);
+        let sword = level.find_by_name("Sword").unwrap();
+        assert_eq!(level.parent(sword), Some(player));
+
+        assert!(scenes.set_active("Missing").is_err());
+        assert!(scenes.remove("Level 1").is_none());
+        assert!(scenes.remove("Menu").is_some());
+        assert_eq!(scenes.names().collect::<Vec<_>>(), ["Level 1"]);
+    }
+}
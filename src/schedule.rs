@@ -0,0 +1,917 @@
+//! System scheduling
+//!
+//! Game logic can be split into systems: plain functions registered into a stage with
+//! optional ordering constraints against other systems of the same stage. The engine
+//! runs every stage once per frame: `PreUpdate`, then the `Engine::run` callback,
+//! `Update`, the built-in trail and particle updates, `PostUpdate`, transform
+//! propagation, and finally `Render` just before the frame is drawn. Change trackers
+//! are cleared once the frame is drawn, so every stage sees the frame's changes.
+//!
+//! Systems can also declare which component types they read and write. The scheduler
+//! then groups each stage into batches of systems that neither depend on nor conflict
+//! with each other, and reports conflicting systems that have no ordering between
+//! them, since their relative order only follows registration order. Systems without
+//! declarations may access anything and get a batch of their own.
+//!
+//! Systems added with [`Systems::add_parallel_system`] get a [`SystemView`] instead of
+//! `&mut Scene`, which only queries the components the system declared. The parallel
+//! systems of a batch run at the same time on the rayon thread pool, after the batch's
+//! other systems.
+
+use crate::commands::Commands;
+use crate::ecs::{Component, QueryData, QueryFilter, QueryIter, ReadOnlyQueryData, Scene};
+use crate::input::InputManager;
+use std::any::TypeId;
+use std::fmt::Write;
+
+/// A point in the frame where systems run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Before the game loop callback (e.g. reading input into components)
+    PreUpdate,
+    /// After the game loop callback (gameplay)
+    Update,
+    /// After the built-in trail and particle updates (e.g. constraints, cleanup)
+    PostUpdate,
+    /// Right before rendering, after transforms propagated and the camera updated
+    Render,
+}
+
+impl Stage {
+    /// All stages in execution order
+    pub const ALL: [Stage; 4] = [Stage::PreUpdate, Stage::Update, Stage::PostUpdate, Stage::Render];
+}
+
+/// Frame state handed to every system
+pub struct SystemCtx<'a> {
+    pub input: &'a InputManager,
+    /// Delta time in seconds
+    pub delta: f32,
+    /// Seconds since the engine started
+    pub elapsed: f32,
+    /// Number of frames since the engine started
+    pub frame: u64,
+    /// Deferred spawns and despawns, applied as soon as the system returns
+    pub commands: &'a Commands,
+}
+
+/// A system function
+pub type System = fn(&mut Scene, &SystemCtx);
+
+/// Frame state handed to parallel systems
+///
+/// There is no command buffer; spawn and despawn from systems added with
+/// [`Systems::add_system`].
+pub struct ParallelCtx<'a> {
+    pub input: &'a InputManager,
+    /// Delta time in seconds
+    pub delta: f32,
+    /// Seconds since the engine started
+    pub elapsed: f32,
+    /// Number of frames since the engine started
+    pub frame: u64,
+}
+
+/// A system function that may run alongside other systems of its batch
+pub type ParallelSystem = fn(&mut SystemView, &ParallelCtx);
+
+/// Queries of a parallel system, limited to the components it declared
+///
+/// Querying a component the system didn't declare, or writing one it only declared
+/// as read, panics. Query items must be `Send`, as with [`QueryIter::par_for_each`].
+pub struct SystemView<'a> {
+    scene: &'a Scene,
+    desc: &'a SystemDesc,
+}
+
+impl<'a> SystemView<'a> {
+    /// Like [`Scene::query`]
+    pub fn query<'s, Q: ReadOnlyQueryData>(&'s self) -> QueryIter<'s, Q>
+    where
+        Q::Item<'s>: Send,
+    {
+        self.query_filtered::<Q, ()>()
+    }
+
+    /// Like [`Scene::query_mut`]
+    pub fn query_mut<'s, Q: QueryData>(&'s mut self) -> QueryIter<'s, Q>
+    where
+        Q::Item<'s>: Send,
+    {
+        self.query_filtered_mut::<Q, ()>()
+    }
+
+    /// Like [`Scene::query_filtered`]
+    pub fn query_filtered<'s, Q: ReadOnlyQueryData, F: QueryFilter>(&'s self) -> QueryIter<'s, Q, F>
+    where
+        Q::Item<'s>: Send,
+    {
+        self.check_declared::<Q, F>();
+        self.scene.query_filtered::<Q, F>()
+    }
+
+    /// Like [`Scene::query_filtered_mut`]
+    pub fn query_filtered_mut<'s, Q: QueryData, F: QueryFilter>(&'s mut self) -> QueryIter<'s, Q, F>
+    where
+        Q::Item<'s>: Send,
+    {
+        self.check_declared::<Q, F>();
+        // SAFETY: `run_stage` refreshes the pointers before running parallel systems,
+        // the systems of a batch don't write what another one accesses, and `&mut self`
+        // keeps this system's own queries from overlapping
+        unsafe { self.scene.query_shared::<Q, F>() }
+    }
+
+    /// Panic if the query accesses a component the system didn't declare
+    fn check_declared<Q: QueryData, F: QueryFilter>(&self) {
+        let mut access = Vec::new();
+        Q::access(&mut access);
+        F::access(&mut access);
+        let declared = |list: &[ComponentAccess], type_id| list.iter().any(|a| a.type_id == type_id);
+        for (type_id, name, mutable) in access {
+            let allowed = declared(&self.desc.writes, type_id) || (!mutable && declared(&self.desc.reads, type_id));
+            assert!(
+                allowed,
+                "System {} {} {} without declaring it",
+                self.desc.name,
+                if mutable { "writes" } else { "reads" },
+                name
+            );
+        }
+    }
+}
+
+/// A component type a system reads or writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentAccess {
+    pub type_id: TypeId,
+    pub name: &'static str,
+}
+
+impl ComponentAccess {
+    /// Describe a component type
+    pub fn of<T: Component>() -> Self {
+        let name = std::any::type_name::<T>();
+        Self {
+            type_id: TypeId::of::<T>(),
+            name: name.rsplit("::").next().unwrap_or(name),
+        }
+    }
+}
+
+/// Name, stage, ordering constraints, and data access of a system
+#[derive(Debug, Clone)]
+pub struct SystemDesc {
+    pub name: String,
+    pub stage: Stage,
+    /// Systems of the same stage this one must run before
+    pub before: Vec<String>,
+    /// Systems of the same stage this one must run after
+    pub after: Vec<String>,
+    /// Component types read
+    pub reads: Vec<ComponentAccess>,
+    /// Component types written
+    pub writes: Vec<ComponentAccess>,
+}
+
+impl SystemDesc {
+    /// Create a system description without ordering constraints
+    pub fn new(name: &str, stage: Stage) -> Self {
+        Self {
+            name: name.to_string(),
+            stage,
+            before: Vec::new(),
+            after: Vec::new(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Run before another system of the same stage
+    pub fn before(mut self, system: &str) -> Self {
+        self.before.push(system.to_string());
+        self
+    }
+
+    /// Run after another system of the same stage
+    pub fn after(mut self, system: &str) -> Self {
+        self.after.push(system.to_string());
+        self
+    }
+
+    /// Declare that the system reads a component type
+    pub fn reads<T: Component>(mut self) -> Self {
+        self.reads.push(ComponentAccess::of::<T>());
+        self
+    }
+
+    /// Declare that the system writes a component type
+    pub fn writes<T: Component>(mut self) -> Self {
+        self.writes.push(ComponentAccess::of::<T>());
+        self
+    }
+
+    /// Check if the system declared its data access
+    pub fn has_access(&self) -> bool {
+        !self.reads.is_empty() || !self.writes.is_empty()
+    }
+
+    /// Component types one system writes while the other reads or writes them
+    fn conflicts_with(&self, other: &SystemDesc) -> Vec<&'static str> {
+        let mut shared: Vec<&'static str> = Vec::new();
+        let mut check = |writes: &[ComponentAccess], accessed: &[ComponentAccess]| {
+            for access in writes {
+                if accessed.iter().any(|a| a.type_id == access.type_id) && !shared.contains(&access.name) {
+                    shared.push(access.name);
+                }
+            }
+        };
+        check(&self.writes, &other.reads);
+        check(&self.writes, &other.writes);
+        check(&other.writes, &self.reads);
+        shared
+    }
+}
+
+/// Two systems accessing the same components with no ordering between them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemConflict {
+    pub first: String,
+    pub second: String,
+    /// Names of the component types involved
+    pub components: Vec<&'static str>,
+}
+
+/// Execution plan of one stage
+struct StagePlan {
+    /// System indices in execution order
+    order: Vec<usize>,
+    /// Batch number of each entry of `order`
+    batches: Vec<usize>,
+    /// Systems each system waits for directly
+    dependencies: Vec<Vec<usize>>,
+    conflicts: Vec<SystemConflict>,
+}
+
+enum SystemFn {
+    Exclusive(System),
+    Parallel(ParallelSystem),
+}
+
+struct RegisteredSystem {
+    desc: SystemDesc,
+    run: SystemFn,
+    enabled: bool,
+}
+
+/// Lets parallel systems share the scene
+struct SharedScene<'a>(&'a Scene);
+
+// SAFETY: parallel systems only reach the scene through `SystemView`, whose queries
+// stick to the declared components and require `Send` items; the systems of a batch
+// never write a component another one accesses
+unsafe impl Sync for SharedScene<'_> {}
+
+impl<'a> SharedScene<'a> {
+    // A method so closures capture the whole wrapper rather than its field
+    fn get(&self) -> &'a Scene {
+        self.0
+    }
+}
+
+/// Registry of systems grouped into stages
+#[derive(Default)]
+pub struct Systems {
+    systems: Vec<RegisteredSystem>,
+    plans: Option<Vec<(Stage, StagePlan)>>,
+}
+
+impl Systems {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a system (unconstrained systems run in the order they were added)
+    pub fn add_system(&mut self, desc: SystemDesc, system: System) {
+        self.push(desc, SystemFn::Exclusive(system));
+    }
+
+    /// Register a system that runs alongside the other parallel systems of its batch
+    ///
+    /// It can only query the components declared with [`SystemDesc::reads`] and
+    /// [`SystemDesc::writes`]; without declarations it runs alone.
+    pub fn add_parallel_system(&mut self, desc: SystemDesc, system: ParallelSystem) {
+        self.push(desc, SystemFn::Parallel(system));
+    }
+
+    fn push(&mut self, desc: SystemDesc, run: SystemFn) {
+        self.systems.push(RegisteredSystem {
+            desc,
+            run,
+            enabled: true,
+        });
+        self.plans = None;
+    }
+
+    /// Remove a system by name
+    pub fn remove_system(&mut self, name: &str) -> bool {
+        let count = self.systems.len();
+        self.systems.retain(|s| s.desc.name != name);
+        self.plans = None;
+        self.systems.len() != count
+    }
+
+    /// Enable or disable a system without removing it
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        for system in self.systems.iter_mut().filter(|s| s.desc.name == name) {
+            system.enabled = enabled;
+        }
+    }
+
+    /// Check if a system is registered
+    pub fn contains(&self, name: &str) -> bool {
+        self.systems.iter().any(|s| s.desc.name == name)
+    }
+
+    /// Check if no systems are registered
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// Get the system names of a stage in execution order
+    pub fn execution_order(&mut self, stage: Stage) -> Result<Vec<String>, String> {
+        self.compile()?;
+        Ok(self
+            .plan(stage)
+            .order
+            .iter()
+            .map(|&i| self.systems[i].desc.name.clone())
+            .collect())
+    }
+
+    /// Get the system names of a stage grouped into batches of independent systems
+    pub fn batches(&mut self, stage: Stage) -> Result<Vec<Vec<String>>, String> {
+        self.compile()?;
+        let plan = self.plan(stage);
+        let mut batches: Vec<Vec<String>> = Vec::new();
+        for (&index, &batch) in plan.order.iter().zip(&plan.batches) {
+            if batches.len() <= batch {
+                batches.resize(batch + 1, Vec::new());
+            }
+            batches[batch].push(self.systems[index].desc.name.clone());
+        }
+        Ok(batches)
+    }
+
+    /// Get the systems of a stage with conflicting access and no ordering between them
+    pub fn conflicts(&mut self, stage: Stage) -> Result<Vec<SystemConflict>, String> {
+        self.compile()?;
+        Ok(self.plan(stage).conflicts.clone())
+    }
+
+    /// Describe every stage's batches, data access, ordering, and conflicts, for debugging
+    pub fn execution_graph(&mut self) -> Result<String, String> {
+        self.compile()?;
+        let names = |accesses: &[ComponentAccess]| -> String {
+            accesses.iter().map(|a| a.name).collect::<Vec<_>>().join(", ")
+        };
+
+        let mut graph = String::new();
+        for stage in Stage::ALL {
+            let plan = self.plan(stage);
+            if plan.order.is_empty() {
+                continue;
+            }
+            let _ = writeln!(graph, "{:?}", stage);
+            for (&index, &batch) in plan.order.iter().zip(&plan.batches) {
+                let desc = &self.systems[index].desc;
+                let mut details: Vec<String> = Vec::new();
+                if !desc.reads.is_empty() {
+                    details.push(format!("reads {}", names(&desc.reads)));
+                }
+                if !desc.writes.is_empty() {
+                    details.push(format!("writes {}", names(&desc.writes)));
+                }
+                if !desc.has_access() {
+                    details.push("exclusive".to_string());
+                }
+                if matches!(self.systems[index].run, SystemFn::Parallel(_)) {
+                    details.push("parallel".to_string());
+                }
+                if !plan.dependencies[index].is_empty() {
+                    let after: Vec<&str> = plan.dependencies[index]
+                        .iter()
+                        .map(|&d| self.systems[d].desc.name.as_str())
+                        .collect();
+                    details.push(format!("after {}", after.join(", ")));
+                }
+                if !self.systems[index].enabled {
+                    details.push("disabled".to_string());
+                }
+                let _ = writeln!(graph, "  [{}] {} ({})", batch, desc.name, details.join("; "));
+            }
+            for conflict in &plan.conflicts {
+                let _ = writeln!(
+                    graph,
+                    "  conflict: {} / {} on {}",
+                    conflict.first,
+                    conflict.second,
+                    conflict.components.join(", ")
+                );
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Run the enabled systems of a stage, one batch after another
+    ///
+    /// Within a batch, systems added with [`Systems::add_system`] run first, one at a
+    /// time, then the parallel systems run together.
+    pub fn run_stage(&mut self, stage: Stage, scene: &mut Scene, ctx: &SystemCtx) -> Result<(), String> {
+        self.compile()?;
+        let plan = self.plan(stage);
+        let batch_count = plan.batches.iter().max().map_or(0, |&last| last + 1);
+        for batch in 0..batch_count {
+            let mut parallel: Vec<(&SystemDesc, ParallelSystem)> = Vec::new();
+            for (&index, _) in plan.order.iter().zip(&plan.batches).filter(|&(_, &b)| b == batch) {
+                let system = &self.systems[index];
+                if !system.enabled {
+                    continue;
+                }
+                match system.run {
+                    SystemFn::Exclusive(run) => {
+                        run(scene, ctx);
+                        ctx.commands.apply(scene);
+                    }
+                    SystemFn::Parallel(run) => parallel.push((&system.desc, run)),
+                }
+            }
+            Self::run_parallel(&parallel, scene, ctx);
+        }
+        Ok(())
+    }
+
+    fn run_parallel(systems: &[(&SystemDesc, ParallelSystem)], scene: &mut Scene, ctx: &SystemCtx) {
+        if systems.is_empty() {
+            return;
+        }
+        scene.refresh_write_ptrs();
+        let ctx = ParallelCtx {
+            input: ctx.input,
+            delta: ctx.delta,
+            elapsed: ctx.elapsed,
+            frame: ctx.frame,
+        };
+        if let [(desc, run)] = systems {
+            run(&mut SystemView { scene, desc }, &ctx);
+            return;
+        }
+        let scene = SharedScene(scene);
+        rayon::scope(|s| {
+            for &(desc, run) in systems {
+                let (scene, ctx) = (&scene, &ctx);
+                s.spawn(move |_| run(&mut SystemView { scene: scene.get(), desc }, ctx));
+            }
+        });
+    }
+
+    /// Order the systems of every stage so their constraints hold
+    fn compile(&mut self) -> Result<(), String> {
+        if self.plans.is_none() {
+            let plans = Stage::ALL
+                .iter()
+                .map(|&stage| Ok((stage, self.plan_stage(stage)?)))
+                .collect::<Result<Vec<_>, String>>()?;
+            for (_, plan) in &plans {
+                for conflict in &plan.conflicts {
+                    log::warn!(
+                        "Systems {} and {} both access {} without an ordering between them",
+                        conflict.first,
+                        conflict.second,
+                        conflict.components.join(", ")
+                    );
+                }
+            }
+            self.plans = Some(plans);
+        }
+        Ok(())
+    }
+
+    fn plan(&self, stage: Stage) -> &StagePlan {
+        static EMPTY: StagePlan = StagePlan {
+            order: Vec::new(),
+            batches: Vec::new(),
+            dependencies: Vec::new(),
+            conflicts: Vec::new(),
+        };
+        self.plans
+            .as_deref()
+            .and_then(|plans| plans.iter().find(|(s, _)| *s == stage))
+            .map_or(&EMPTY, |(_, plan)| plan)
+    }
+
+    fn plan_stage(&self, stage: Stage) -> Result<StagePlan, String> {
+        let members: Vec<usize> = (0..self.systems.len())
+            .filter(|&i| self.systems[i].desc.stage == stage)
+            .collect();
+        // Other stages may have a system of the same name, so look in this one first
+        let find = |from: &SystemDesc, name: &str| -> Result<usize, String> {
+            if let Some(&target) = members.iter().find(|&&i| self.systems[i].desc.name == name) {
+                return Ok(target);
+            }
+            if self.contains(name) {
+                return Err(format!(
+                    "System {} is ordered against {} in another stage",
+                    from.name, name
+                ));
+            }
+            Err(format!("System {} is ordered against unknown system {}", from.name, name))
+        };
+
+        // Edges point from a system to the systems that must run first
+        let mut dependencies: Vec<Vec<usize>> = vec![Vec::new(); self.systems.len()];
+        for &i in &members {
+            let desc = &self.systems[i].desc;
+            if members.iter().any(|&j| j < i && self.systems[j].desc.name == desc.name) {
+                return Err(format!("System {} is registered twice", desc.name));
+            }
+            for name in &desc.after {
+                dependencies[i].push(find(desc, name)?);
+            }
+            for name in &desc.before {
+                let target = find(desc, name)?;
+                dependencies[target].push(i);
+            }
+        }
+
+        // Kahn's algorithm, preferring registration order among ready systems
+        let mut remaining: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+        let mut done = vec![false; self.systems.len()];
+        let mut order = Vec::with_capacity(members.len());
+        while order.len() < members.len() {
+            let next = members
+                .iter()
+                .copied()
+                .find(|&i| !done[i] && remaining[i] == 0)
+                .ok_or_else(|| format!("Systems in {:?} have an ordering cycle", stage))?;
+            done[next] = true;
+            order.push(next);
+            for &i in &members {
+                remaining[i] -= dependencies[i].iter().filter(|&&d| d == next).count();
+            }
+        }
+
+        // Systems that must run before each system, directly or not
+        let mut ancestors: Vec<Vec<bool>> = vec![vec![false; self.systems.len()]; self.systems.len()];
+        for &i in &order {
+            for &d in &dependencies[i] {
+                let inherited = ancestors[d].clone();
+                ancestors[i][d] = true;
+                for (slot, &inherited) in ancestors[i].iter_mut().zip(&inherited) {
+                    *slot |= inherited;
+                }
+            }
+        }
+
+        // A system joins the batch after its dependencies and the earlier systems it
+        // conflicts with, so running batches in order keeps the sequential semantics
+        let mut batches: Vec<usize> = Vec::with_capacity(order.len());
+        let mut conflicts = Vec::new();
+        for (position, &i) in order.iter().enumerate() {
+            let desc = &self.systems[i].desc;
+            let mut batch = 0;
+            for (&j, &earlier_batch) in order[..position].iter().zip(&batches) {
+                let other = &self.systems[j].desc;
+                let shared = if desc.has_access() && other.has_access() {
+                    desc.conflicts_with(other)
+                } else {
+                    Vec::new()
+                };
+                let exclusive = !desc.has_access() || !other.has_access();
+                if ancestors[i][j] || exclusive || !shared.is_empty() {
+                    batch = batch.max(earlier_batch + 1);
+                }
+                if !shared.is_empty() && !ancestors[i][j] {
+                    conflicts.push(SystemConflict {
+                        first: other.name.clone(),
+                        second: desc.name.clone(),
+                        components: shared,
+                    });
+                }
+            }
+            batches.push(batch);
+        }
+
+        Ok(StagePlan {
+            order,
+            batches,
+            dependencies,
+            conflicts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{Changed, EntityId};
+    use crate::math::Transform;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn noop(_scene: &mut Scene, _ctx: &SystemCtx) {}
+
+    fn spawn(scene: &mut Scene, _ctx: &SystemCtx) {
+        let id = scene.create_entity("Spawned".to_string());
+        if let Some(mut entity) = scene.get_entity_mut(id) {
+            entity.add_component(Transform::new());
+        }
+    }
+
+    fn advance(scene: &mut Scene, ctx: &SystemCtx) {
+        for transform in scene.query_mut::<&mut Transform>() {
+            transform.position.x += ctx.delta;
+        }
+    }
+
+    fn despawn_moved(scene: &mut Scene, ctx: &SystemCtx) {
+        for (id, transform) in scene.query::<(EntityId, &Transform)>() {
+            if transform.position.x >= 1.0 {
+                ctx.commands.despawn(id);
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct Moved;
+    impl crate::ecs::Component for Moved {}
+
+    fn mark_moved(scene: &mut Scene, _ctx: &SystemCtx) {
+        let moved: Vec<EntityId> = scene.query_filtered::<EntityId, Changed<Transform>>().collect();
+        for id in moved {
+            if let Some(mut entity) = scene.get_entity_mut(id) {
+                entity.add_component(Moved);
+            }
+        }
+    }
+
+    thread_local! {
+        static SEEN_REMOVED: std::cell::RefCell<Vec<EntityId>> = const { std::cell::RefCell::new(Vec::new()) };
+    }
+
+    fn record_removed(scene: &mut Scene, _ctx: &SystemCtx) {
+        SEEN_REMOVED.with(|seen| seen.borrow_mut().extend_from_slice(scene.removed_entities()));
+    }
+
+    #[test]
+    fn test_systems_ordered_by_constraints() {
+        let mut systems = Systems::new();
+        systems.add_system(SystemDesc::new("physics", Stage::Update).after("input"), noop);
+        systems.add_system(SystemDesc::new("audio", Stage::Update), noop);
+        systems.add_system(SystemDesc::new("input", Stage::Update), noop);
+        systems.add_system(SystemDesc::new("ai", Stage::Update).before("physics"), noop);
+        systems.add_system(SystemDesc::new("cleanup", Stage::PostUpdate), noop);
+
+        assert_eq!(systems.execution_order(Stage::Update).unwrap(), ["audio", "input", "ai", "physics"]);
+        assert_eq!(systems.execution_order(Stage::PostUpdate).unwrap(), ["cleanup"]);
+        assert!(systems.execution_order(Stage::Render).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_schedules_rejected() {
+        let mut systems = Systems::new();
+        systems.add_system(SystemDesc::new("a", Stage::Update).after("missing"), noop);
+        assert!(systems.execution_order(Stage::Update).is_err());
+
+        let mut systems = Systems::new();
+        systems.add_system(SystemDesc::new("a", Stage::Update).after("b"), noop);
+        systems.add_system(SystemDesc::new("b", Stage::Update).after("a"), noop);
+        assert!(systems.execution_order(Stage::Update).unwrap_err().contains("cycle"));
+
+        let mut systems = Systems::new();
+        systems.add_system(SystemDesc::new("a", Stage::Update), noop);
+        systems.add_system(SystemDesc::new("b", Stage::PreUpdate).before("a"), noop);
+        assert!(systems.execution_order(Stage::Update).is_err());
+    }
+
+    #[test]
+    fn test_same_name_in_two_stages() {
+        let mut systems = Systems::new();
+        systems.add_system(SystemDesc::new("cleanup", Stage::PreUpdate), noop);
+        systems.add_system(SystemDesc::new("reap", Stage::PostUpdate).after("cleanup"), noop);
+        systems.add_system(SystemDesc::new("cleanup", Stage::PostUpdate), noop);
+
+        assert_eq!(systems.execution_order(Stage::PreUpdate).unwrap(), ["cleanup"]);
+        assert_eq!(systems.execution_order(Stage::PostUpdate).unwrap(), ["cleanup", "reap"]);
+    }
+
+    #[derive(Debug)]
+    struct Velocity;
+    impl crate::ecs::Component for Velocity {}
+
+    #[derive(Debug)]
+    struct Follow;
+    impl crate::ecs::Component for Follow {}
+
+    #[test]
+    fn test_batches_and_conflicts() {
+        let mut systems = Systems::new();
+        systems.add_system(SystemDesc::new("input", Stage::Update).writes::<Velocity>(), noop);
+        systems.add_system(SystemDesc::new("ai", Stage::Update).writes::<Velocity>(), noop);
+        systems.add_system(
+            SystemDesc::new("movement", Stage::Update)
+                .after("input")
+                .reads::<Velocity>()
+                .writes::<Transform>(),
+            noop,
+        );
+        systems.add_system(SystemDesc::new("camera", Stage::Update).reads::<Follow>(), noop);
+        systems.add_system(SystemDesc::new("debug", Stage::Update), noop);
+
+        assert_eq!(
+            systems.batches(Stage::Update).unwrap(),
+            vec![vec!["input", "camera"], vec!["ai"], vec!["movement"], vec!["debug"]]
+        );
+        // "ai" and "movement" are ordered only by registration
+        let conflicts = systems.conflicts(Stage::Update).unwrap();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!((conflicts[0].first.as_str(), conflicts[0].second.as_str()), ("input", "ai"));
+        assert_eq!((conflicts[1].first.as_str(), conflicts[1].second.as_str()), ("ai", "movement"));
+        assert_eq!(conflicts[1].components, ["Velocity"]);
+
+        let graph = systems.execution_graph().unwrap();
+        assert!(graph.contains("[2] movement (reads Velocity; writes Transform; after input)"));
+        assert!(graph.contains("[3] debug (exclusive)"));
+    }
+
+    #[test]
+    fn test_run_stage() {
+        let mut scene = Scene::new("Test".to_string());
+        let input = InputManager::new();
+        let commands = Commands::new();
+        let ctx = SystemCtx {
+            input: &input,
+            delta: 0.5,
+            elapsed: 0.5,
+            frame: 1,
+            commands: &commands,
+        };
+
+        let mut systems = Systems::new();
+        systems.add_system(SystemDesc::new("advance", Stage::Update), advance);
+        systems.add_system(SystemDesc::new("spawn", Stage::Update).before("advance"), spawn);
+        systems.run_stage(Stage::Update, &mut scene, &ctx).unwrap();
+
+        let entity = scene.entities().next().unwrap();
+        assert_eq!(entity.get_component::<Transform>().unwrap().position.x, 0.5);
+
+        systems.set_enabled("spawn", false);
+        systems.run_stage(Stage::Update, &mut scene, &ctx).unwrap();
+        systems.run_stage(Stage::PreUpdate, &mut scene, &ctx).unwrap();
+        assert_eq!(scene.entity_count(), 1);
+
+        // Commands are applied before the next system runs
+        systems.add_system(SystemDesc::new("despawn", Stage::PostUpdate), despawn_moved);
+        systems.run_stage(Stage::PostUpdate, &mut scene, &ctx).unwrap();
+        assert_eq!(scene.entity_count(), 0);
+    }
+
+    #[test]
+    fn test_render_stage_sees_update_changes() {
+        let mut scene = Scene::new("Test".to_string());
+        let input = InputManager::new();
+        let commands = Commands::new();
+        let ctx = SystemCtx {
+            input: &input,
+            delta: 0.5,
+            elapsed: 0.5,
+            frame: 1,
+            commands: &commands,
+        };
+        let mut systems = Systems::new();
+        systems.add_system(SystemDesc::new("advance", Stage::Update), advance);
+        systems.add_system(SystemDesc::new("mark", Stage::Render), mark_moved);
+        let id = scene.create_entity("Mover".to_string());
+        scene.get_entity_mut(id).unwrap().add_component(Transform::new());
+        scene.clear_trackers();
+
+        // One frame in the engine's order, clearing the trackers after rendering
+        for stage in Stage::ALL {
+            systems.run_stage(stage, &mut scene, &ctx).unwrap();
+            if stage == Stage::PostUpdate {
+                crate::hierarchy::propagate_transforms(&mut scene);
+            }
+        }
+        assert!(scene.get_entity(id).unwrap().has_component::<Moved>());
+        scene.clear_trackers();
+        assert!(!scene.is_changed::<Transform>(id));
+    }
+
+    #[test]
+    fn test_later_stages_see_removed_entities() {
+        let mut scene = Scene::new("Test".to_string());
+        let input = InputManager::new();
+        let commands = Commands::new();
+        let ctx = SystemCtx {
+            input: &input,
+            delta: 1.0,
+            elapsed: 1.0,
+            frame: 1,
+            commands: &commands,
+        };
+        let mut systems = Systems::new();
+        systems.add_system(SystemDesc::new("advance", Stage::Update), advance);
+        systems.add_system(SystemDesc::new("despawn", Stage::Update).after("advance"), despawn_moved);
+        systems.add_system(SystemDesc::new("record", Stage::Render), record_removed);
+        let id = scene.create_entity("Mover".to_string());
+        scene.get_entity_mut(id).unwrap().add_component(Transform::new());
+
+        for stage in Stage::ALL {
+            systems.run_stage(stage, &mut scene, &ctx).unwrap();
+        }
+        assert_eq!(SEEN_REMOVED.with(|seen| seen.take()), [id]);
+        scene.clear_trackers();
+        assert!(scene.removed_entities().is_empty());
+    }
+
+    static STARTED: AtomicUsize = AtomicUsize::new(0);
+
+    /// Wait until both parallel test systems are running, returning whether they met
+    fn meet() -> bool {
+        STARTED.fetch_add(1, Ordering::SeqCst);
+        let start = std::time::Instant::now();
+        while STARTED.load(Ordering::SeqCst) < 2 {
+            if start.elapsed() > std::time::Duration::from_secs(1) {
+                return false;
+            }
+            std::hint::spin_loop();
+        }
+        true
+    }
+
+    fn parallel_advance(view: &mut SystemView, ctx: &ParallelCtx) {
+        assert!(meet(), "parallel systems didn't overlap");
+        for transform in view.query_mut::<&mut Transform>() {
+            transform.position.x += ctx.delta;
+        }
+    }
+
+    fn parallel_count_moved(view: &mut SystemView, _ctx: &ParallelCtx) {
+        assert!(meet(), "parallel systems didn't overlap");
+        assert_eq!(view.query::<&Moved>().count(), 1);
+    }
+
+    #[test]
+    fn test_parallel_systems_overlap() {
+        // Two threads even on a single-core machine; the stage is set up on the pool so
+        // the scene never crosses threads
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        pool.install(|| {
+            let mut scene = Scene::new("Test".to_string());
+            let input = InputManager::new();
+            let commands = Commands::new();
+            let ctx = SystemCtx {
+                input: &input,
+                delta: 1.0,
+                elapsed: 1.0,
+                frame: 1,
+                commands: &commands,
+            };
+            let mut systems = Systems::new();
+            systems.add_parallel_system(SystemDesc::new("advance", Stage::Update).writes::<Transform>(), parallel_advance);
+            systems.add_parallel_system(SystemDesc::new("count", Stage::Update).reads::<Moved>(), parallel_count_moved);
+            assert_eq!(systems.batches(Stage::Update).unwrap(), vec![vec!["advance", "count"]]);
+            let id = scene.create_entity("Mover".to_string());
+            let mut entity = scene.get_entity_mut(id).unwrap();
+            entity.add_component(Transform::new());
+            entity.add_component(Moved);
+
+            systems.run_stage(Stage::Update, &mut scene, &ctx).unwrap();
+            assert_eq!(scene.get_entity(id).unwrap().get_component::<Transform>().unwrap().position.x, 1.0);
+        });
+    }
+
+    fn parallel_undeclared(view: &mut SystemView, _ctx: &ParallelCtx) {
+        for transform in view.query_mut::<&mut Transform>() {
+            transform.position.x += 1.0;
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "System sneaky writes")]
+    fn test_parallel_system_needs_declared_access() {
+        let mut scene = Scene::new("Test".to_string());
+        let input = InputManager::new();
+        let commands = Commands::new();
+        let ctx = SystemCtx {
+            input: &input,
+            delta: 1.0,
+            elapsed: 1.0,
+            frame: 1,
+            commands: &commands,
+        };
+        let mut systems = Systems::new();
+        systems.add_parallel_system(SystemDesc::new("sneaky", Stage::Update).reads::<Transform>(), parallel_undeclared);
+        systems.run_stage(Stage::Update, &mut scene, &ctx).unwrap();
+    }
+}
@@ -0,0 +1,688 @@
+//! Sandboxed WebAssembly gameplay scripts
+//!
+//! Only available with the `wasm` feature. Scripts are WebAssembly modules
+//! (compiled from any language) that import a small, stable host API from the
+//! `rgame` module and export `update(delta: f32)` (and optionally `init()`).
+//! Scripts get no filesystem, network, or clock access; CPU time per call is
+//! bounded with fuel and linear memory is capped.
+//!
+//! ## Host API (version 1)
+//! Strings are passed as `(ptr, len)` pairs of UTF-8 in the script's exported
+//! `memory`. Entity ids are `i64`; `-1` means "none".
+//!
+//! | Function | Signature |
+//! |----------|-----------|
+//! | `log` | `(msg_ptr, msg_len)` |
+//! | `spawn_entity` | `(name_ptr, name_len) -> i64` |
+//! | `despawn_entity` | `(id) -> i32` |
+//! | `find_entity` | `(name_ptr, name_len) -> i64` |
+//! | `get_position` | `(id, out_ptr) -> i32` (writes 3 × f32) |
+//! | `set_position` | `(id, x, y, z) -> i32` |
+//! | `has_component` | `(id, name_ptr, name_len) -> i32` |
+//! | `get_component` | `(id, name_ptr, name_len, out_ptr, out_cap) -> i32` (JSON length, written if it fits) |
+//! | `set_component` | `(id, name_ptr, name_len, json_ptr, json_len) -> i32` |
+//! | `query` | `(name_ptr, name_len, out_ptr, out_cap) -> i32` (match count, writes up to `out_cap` × i64) |
+//! | `raycast` | `(ox, oy, oz, dx, dy, dz, max_distance, out_ptr) -> i64` (writes hit distance as f32) |
+//! | `play_sound` | `(name_ptr, name_len, volume) -> i32` |
+//!
+//! Functions returning `i32` status use `0` for success and `-1` for failure.
+//! Calls not permitted by the script's [`ScriptCapabilities`] also fail with `-1`.
+
+use crate::audio::{AudioManager, AudioSource};
+use crate::ecs::{Component, EntityId, EntityMut, EntityRef, Scene};
+use crate::math::Transform;
+use glam::Vec3;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use wasmtime::{Caller, Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+/// Version of the host API exposed to scripts
+///
+/// Scripts may export `rgame_api_version() -> i32`; modules built against a
+/// newer API than the host supports are rejected at load time.
+pub const HOST_API_VERSION: i32 = 1;
+
+/// Identifier of a loaded script
+pub type ScriptId = usize;
+
+/// Custom raycast used by the `raycast` host function
+pub type RaycastFn = dyn Fn(&Scene, Vec3, Vec3, f32) -> Option<(EntityId, f32)>;
+
+type GetComponentFn = fn(&EntityRef) -> Option<Result<serde_json::Value, String>>;
+type SetComponentFn = fn(&mut EntityMut, serde_json::Value) -> Result<(), String>;
+
+/// Component type exposed to scripts under a stable name
+#[derive(Clone, Copy)]
+struct ScriptComponent {
+    get: GetComponentFn,
+    set: SetComponentFn,
+}
+
+fn get_component<T: Component + Serialize>(entity: &EntityRef) -> Option<Result<serde_json::Value, String>> {
+    entity
+        .get_component::<T>()
+        .map(|c| serde_json::to_value(c).map_err(|e| e.to_string()))
+}
+
+fn set_component<T: Component + DeserializeOwned>(entity: &mut EntityMut, value: serde_json::Value) -> Result<(), String> {
+    let component: T = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    entity.add_component(component);
+    Ok(())
+}
+
+/// A sound requested by a script
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundRequest {
+    pub name: String,
+    pub volume: f32,
+}
+
+/// Host functions a script is allowed to call beyond read-only queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptCapabilities {
+    /// `spawn_entity` and `despawn_entity`
+    pub spawn_entities: bool,
+    /// `set_position` and `set_component`
+    pub modify_entities: bool,
+    /// `play_sound`
+    pub play_sounds: bool,
+}
+
+impl ScriptCapabilities {
+    /// Every capability
+    pub fn all() -> Self {
+        Self {
+            spawn_entities: true,
+            modify_entities: true,
+            play_sounds: true,
+        }
+    }
+
+    /// Only read-only queries, logging, and raycasts
+    pub fn read_only() -> Self {
+        Self {
+            spawn_entities: false,
+            modify_entities: false,
+            play_sounds: false,
+        }
+    }
+}
+
+impl Default for ScriptCapabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// Per-script state reachable from host functions
+struct HostState {
+    script: String,
+    capabilities: ScriptCapabilities,
+    /// The game's scene, swapped in for the duration of a call
+    scene: Scene,
+    components: Arc<HashMap<String, ScriptComponent>>,
+    raycast: Option<Arc<RaycastFn>>,
+    sounds: Vec<SoundRequest>,
+    limits: StoreLimits,
+}
+
+/// A loaded script instance
+struct Script {
+    name: String,
+    store: Store<HostState>,
+    update: Option<TypedFunc<f32, ()>>,
+    enabled: bool,
+}
+
+/// Loads WebAssembly scripts and runs them against the scene
+pub struct ScriptHost {
+    engine: Engine,
+    linker: Linker<HostState>,
+    scripts: Vec<Option<Script>>,
+    components: Arc<HashMap<String, ScriptComponent>>,
+    raycast: Option<Arc<RaycastFn>>,
+    sound_sources: HashMap<String, AudioSource>,
+    sound_queue: Vec<SoundRequest>,
+    fuel_per_call: u64,
+    memory_limit: usize,
+}
+
+impl ScriptHost {
+    /// Create a new script host
+    pub fn new() -> Result<Self, String> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| format!("Failed to create WASM engine: {}", e))?;
+
+        let mut linker = Linker::new(&engine);
+        define_host_api(&mut linker).map_err(|e| format!("Failed to define script host API: {}", e))?;
+
+        log::info!("Script host initialized (API version {})", HOST_API_VERSION);
+
+        Ok(Self {
+            engine,
+            linker,
+            scripts: Vec::new(),
+            components: Arc::new(HashMap::new()),
+            raycast: None,
+            sound_sources: HashMap::new(),
+            sound_queue: Vec::new(),
+            fuel_per_call: 10_000_000,
+            memory_limit: 16 * 1024 * 1024,
+        })
+    }
+
+    /// Set the instruction budget ("fuel") for each call into a script
+    pub fn set_fuel_per_call(&mut self, fuel: u64) {
+        self.fuel_per_call = fuel;
+    }
+
+    /// Set the maximum linear memory size of each script, in bytes
+    ///
+    /// Applies to scripts loaded afterwards.
+    pub fn set_memory_limit(&mut self, bytes: usize) {
+        self.memory_limit = bytes;
+    }
+
+    /// Expose a component type to scripts under a stable name (as JSON)
+    pub fn register_component<T>(&mut self, name: &str)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        Arc::make_mut(&mut self.components).insert(
+            name.to_string(),
+            ScriptComponent {
+                get: get_component::<T>,
+                set: set_component::<T>,
+            },
+        );
+    }
+
+    /// Make a sound available to scripts' `play_sound` by name
+    pub fn register_sound(&mut self, name: &str, source: AudioSource) {
+        self.sound_sources.insert(name.to_string(), source);
+    }
+
+    /// Replace the default raycast (bounding spheres from `Transform` scale)
+    pub fn set_raycast_handler<F>(&mut self, handler: F)
+    where
+        F: Fn(&Scene, Vec3, Vec3, f32) -> Option<(EntityId, f32)> + 'static,
+    {
+        self.raycast = Some(Arc::new(handler));
+    }
+
+    /// Load a script from a `.wasm` (or `.wat`) file
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P, scene: &mut Scene) -> Result<ScriptId, String> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| format!("Failed to read script file: {}", e))?;
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("script");
+        self.load_bytes(name, &bytes, scene)
+    }
+
+    /// Load a script from WebAssembly binary or text and run its `init` export
+    pub fn load_bytes(&mut self, name: &str, bytes: &[u8], scene: &mut Scene) -> Result<ScriptId, String> {
+        self.load_with_capabilities(name, bytes, scene, ScriptCapabilities::all())
+    }
+
+    /// Load a script that may only use the given capabilities
+    pub fn load_with_capabilities(
+        &mut self,
+        name: &str,
+        bytes: &[u8],
+        scene: &mut Scene,
+        capabilities: ScriptCapabilities,
+    ) -> Result<ScriptId, String> {
+        let module = Module::new(&self.engine, bytes)
+            .map_err(|e| format!("Failed to compile script '{}': {}", name, e))?;
+
+        let state = HostState {
+            script: name.to_string(),
+            capabilities,
+            scene: Scene::new(String::new()),
+            components: self.components.clone(),
+            raycast: self.raycast.clone(),
+            sounds: Vec::new(),
+            limits: StoreLimitsBuilder::new().memory_size(self.memory_limit).build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store
+            .set_fuel(self.fuel_per_call)
+            .map_err(|e| format!("Failed to set script fuel: {}", e))?;
+
+        let instance = self
+            .linker
+            .instantiate(&mut store, &module)
+            .map_err(|e| format!("Failed to instantiate script '{}': {}", name, e))?;
+
+        if let Ok(version) = instance.get_typed_func::<(), i32>(&mut store, "rgame_api_version") {
+            let version = version
+                .call(&mut store, ())
+                .map_err(|e| format!("Failed to query API version of script '{}': {}", name, e))?;
+            if version > HOST_API_VERSION {
+                return Err(format!(
+                    "Script '{}' requires host API version {} (host provides {})",
+                    name, version, HOST_API_VERSION
+                ));
+            }
+        }
+
+        let update = instance.get_typed_func::<f32, ()>(&mut store, "update").ok();
+        let init = instance.get_typed_func::<(), ()>(&mut store, "init").ok();
+
+        let mut script = Script {
+            name: name.to_string(),
+            store,
+            update,
+            enabled: true,
+        };
+
+        if let Some(init) = init {
+            self.call(&mut script, scene, |store| init.call(store, ()))
+                .map_err(|e| format!("Script '{}' failed in init: {}", name, e))?;
+        }
+
+        log::info!("Loaded script: {}", name);
+        self.scripts.push(Some(script));
+        Ok(self.scripts.len() - 1)
+    }
+
+    /// Unload a script
+    pub fn unload(&mut self, id: ScriptId) -> bool {
+        self.scripts.get_mut(id).and_then(|s| s.take()).is_some()
+    }
+
+    /// Get the number of loaded scripts
+    pub fn script_count(&self) -> usize {
+        self.scripts.iter().flatten().count()
+    }
+
+    /// Check if a script is still running (scripts that trap are disabled)
+    pub fn is_enabled(&self, id: ScriptId) -> bool {
+        matches!(self.scripts.get(id), Some(Some(script)) if script.enabled)
+    }
+
+    /// Call `update(delta)` on every enabled script
+    pub fn update(&mut self, scene: &mut Scene, delta: f32) {
+        let mut scripts = std::mem::take(&mut self.scripts);
+        for script in scripts.iter_mut().flatten() {
+            if !script.enabled {
+                continue;
+            }
+            let Some(update) = script.update.clone() else {
+                continue;
+            };
+            if let Err(e) = self.call(script, scene, |store| update.call(store, delta)) {
+                log::error!("Script '{}' disabled: {}", script.name, e);
+                script.enabled = false;
+            }
+        }
+        self.scripts = scripts;
+    }
+
+    /// Run `f` with the scene swapped into the script's store
+    fn call<F>(&mut self, script: &mut Script, scene: &mut Scene, f: F) -> wasmtime::Result<()>
+    where
+        F: FnOnce(&mut Store<HostState>) -> wasmtime::Result<()>,
+    {
+        {
+            let state = script.store.data_mut();
+            state.components = self.components.clone();
+            state.raycast = self.raycast.clone();
+            std::mem::swap(&mut state.scene, scene);
+        }
+        let result = script.store.set_fuel(self.fuel_per_call).and_then(|_| f(&mut script.store));
+
+        let state = script.store.data_mut();
+        std::mem::swap(&mut state.scene, scene);
+        self.sound_queue.append(&mut state.sounds);
+        result
+    }
+
+    /// Take the sounds scripts requested since the last call
+    pub fn drain_sounds(&mut self) -> Vec<SoundRequest> {
+        std::mem::take(&mut self.sound_queue)
+    }
+
+    /// Play the sounds scripts requested since the last call
+    pub fn play_sounds(&mut self, audio: &mut AudioManager) {
+        for request in std::mem::take(&mut self.sound_queue) {
+            match self.sound_sources.get(&request.name) {
+                Some(source) => {
+                    if let Err(e) = audio.play_sfx(source) {
+                        log::warn!("Failed to play script sound '{}': {}", request.name, e);
+                    }
+                }
+                None => log::warn!("Script requested unknown sound: {}", request.name),
+            }
+        }
+    }
+}
+
+fn memory(caller: &mut Caller<'_, HostState>) -> Option<Memory> {
+    caller.get_export("memory").and_then(|export| export.into_memory())
+}
+
+fn read_bytes(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Option<Vec<u8>> {
+    let memory = memory(caller)?;
+    let mut bytes = vec![0u8; usize::try_from(len).ok()?];
+    memory.read(caller, usize::try_from(ptr).ok()?, &mut bytes).ok()?;
+    Some(bytes)
+}
+
+fn read_string(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Option<String> {
+    read_bytes(caller, ptr, len).and_then(|bytes| String::from_utf8(bytes).ok())
+}
+
+fn write_bytes(caller: &mut Caller<'_, HostState>, ptr: i32, bytes: &[u8]) -> bool {
+    let Some(memory) = memory(caller) else {
+        return false;
+    };
+    let Ok(ptr) = usize::try_from(ptr) else {
+        return false;
+    };
+    memory.write(caller, ptr, bytes).is_ok()
+}
+
+fn entity_id(id: i64) -> Option<EntityId> {
+    u64::try_from(id).ok()
+}
+
+fn status(ok: bool) -> i32 {
+    if ok {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Check a capability, logging when a script is denied
+fn permitted(caller: &Caller<'_, HostState>, allowed: fn(&ScriptCapabilities) -> bool, function: &str) -> bool {
+    let state = caller.data();
+    let ok = allowed(&state.capabilities);
+    if !ok {
+        log::warn!("Script '{}' is not permitted to call {}", state.script, function);
+    }
+    ok
+}
+
+fn define_host_api(linker: &mut Linker<HostState>) -> wasmtime::Result<()> {
+    linker.func_wrap("rgame", "log", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        if let Some(message) = read_string(&mut caller, ptr, len) {
+            log::info!("[{}] {}", caller.data().script, message);
+        }
+    })?;
+
+    linker.func_wrap("rgame", "spawn_entity", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> i64 {
+        if !permitted(&caller, |c| c.spawn_entities, "spawn_entity") {
+            return -1;
+        }
+        let Some(name) = read_string(&mut caller, ptr, len) else {
+            return -1;
+        };
+        caller.data_mut().scene.create_entity(name) as i64
+    })?;
+
+    linker.func_wrap("rgame", "despawn_entity", |mut caller: Caller<'_, HostState>, id: i64| -> i32 {
+        if !permitted(&caller, |c| c.spawn_entities, "despawn_entity") {
+            return -1;
+        }
+        status(entity_id(id).is_some_and(|id| caller.data_mut().scene.remove_entity(id)))
+    })?;
+
+    linker.func_wrap("rgame", "find_entity", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> i64 {
+        let Some(name) = read_string(&mut caller, ptr, len) else {
+            return -1;
+        };
+        caller.data().scene.find_by_name(&name).map_or(-1, |id| id as i64)
+    })?;
+
+    linker.func_wrap("rgame", "get_position", |mut caller: Caller<'_, HostState>, id: i64, out: i32| -> i32 {
+        let position = entity_id(id)
+            .and_then(|id| caller.data().scene.get_entity(id))
+            .and_then(|e| e.get_component::<Transform>())
+            .map(|t| t.position);
+        let Some(position) = position else {
+            return -1;
+        };
+        let bytes: Vec<u8> = position.to_array().iter().flat_map(|v| v.to_le_bytes()).collect();
+        status(write_bytes(&mut caller, out, &bytes))
+    })?;
+
+    linker.func_wrap(
+        "rgame",
+        "set_position",
+        |mut caller: Caller<'_, HostState>, id: i64, x: f32, y: f32, z: f32| -> i32 {
+            if !permitted(&caller, |c| c.modify_entities, "set_position") {
+                return -1;
+            }
+            let Some(mut entity) = entity_id(id).and_then(|id| caller.data_mut().scene.get_entity_mut(id)) else {
+                return -1;
+            };
+            match entity.get_component_mut::<Transform>() {
+                Some(transform) => transform.position = Vec3::new(x, y, z),
+                None => entity.add_component(Transform::from_position(Vec3::new(x, y, z))),
+            }
+            0
+        },
+    )?;
+
+    linker.func_wrap(
+        "rgame",
+        "has_component",
+        |mut caller: Caller<'_, HostState>, id: i64, ptr: i32, len: i32| -> i32 {
+            let Some(name) = read_string(&mut caller, ptr, len) else {
+                return -1;
+            };
+            let state = caller.data();
+            let found = state.components.get(&name).zip(entity_id(id).and_then(|id| state.scene.get_entity(id)));
+            match found {
+                Some((component, entity)) => (component.get)(&entity).is_some() as i32,
+                None => -1,
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "rgame",
+        "get_component",
+        |mut caller: Caller<'_, HostState>, id: i64, ptr: i32, len: i32, out: i32, cap: i32| -> i32 {
+            let Some(name) = read_string(&mut caller, ptr, len) else {
+                return -1;
+            };
+            let state = caller.data();
+            let value = state
+                .components
+                .get(&name)
+                .zip(entity_id(id).and_then(|id| state.scene.get_entity(id)))
+                .and_then(|(component, entity)| (component.get)(&entity));
+            let json = match value {
+                Some(Ok(value)) => value.to_string(),
+                Some(Err(e)) => {
+                    log::warn!("Failed to serialize component '{}' for script: {}", name, e);
+                    return -1;
+                }
+                None => return -1,
+            };
+            if json.len() <= cap.max(0) as usize && !write_bytes(&mut caller, out, json.as_bytes()) {
+                return -1;
+            }
+            json.len() as i32
+        },
+    )?;
+
+    linker.func_wrap(
+        "rgame",
+        "set_component",
+        |mut caller: Caller<'_, HostState>, id: i64, ptr: i32, len: i32, json_ptr: i32, json_len: i32| -> i32 {
+            if !permitted(&caller, |c| c.modify_entities, "set_component") {
+                return -1;
+            }
+            let (Some(name), Some(json)) = (
+                read_string(&mut caller, ptr, len),
+                read_string(&mut caller, json_ptr, json_len),
+            ) else {
+                return -1;
+            };
+            let Ok(value) = serde_json::from_str::<serde_json::Value>(&json) else {
+                return -1;
+            };
+            let state = caller.data_mut();
+            let Some(component) = state.components.get(&name).copied() else {
+                return -1;
+            };
+            let Some(mut entity) = entity_id(id).and_then(|id| state.scene.get_entity_mut(id)) else {
+                return -1;
+            };
+            match (component.set)(&mut entity, value) {
+                Ok(()) => 0,
+                Err(e) => {
+                    log::warn!("Script '{}' set invalid component '{}': {}", state.script, name, e);
+                    -1
+                }
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "rgame",
+        "query",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32, out: i32, cap: i32| -> i32 {
+            let Some(name) = read_string(&mut caller, ptr, len) else {
+                return -1;
+            };
+            let state = caller.data();
+            let Some(component) = state.components.get(&name) else {
+                return -1;
+            };
+            let mut ids: Vec<EntityId> = state
+                .scene
+                .active_entities()
+                .filter(|e| (component.get)(e).is_some())
+                .map(|e| e.id())
+                .collect();
+            ids.sort_unstable();
+
+            let bytes: Vec<u8> = ids
+                .iter()
+                .take(cap.max(0) as usize)
+                .flat_map(|id| id.to_le_bytes())
+                .collect();
+            if !bytes.is_empty() && !write_bytes(&mut caller, out, &bytes) {
+                return -1;
+            }
+            ids.len() as i32
+        },
+    )?;
+
+    linker.func_wrap(
+        "rgame",
+        "raycast",
+        |mut caller: Caller<'_, HostState>, ox: f32, oy: f32, oz: f32, dx: f32, dy: f32, dz: f32, max: f32, out: i32| -> i64 {
+            let state = caller.data();
+            let origin = Vec3::new(ox, oy, oz);
+            let direction = Vec3::new(dx, dy, dz);
+            let hit = match &state.raycast {
+                Some(raycast) => raycast(&state.scene, origin, direction, max),
+                None => crate::editor::pick_entity(&state.scene, origin, direction, max),
+            };
+            match hit {
+                Some((id, distance)) => {
+                    write_bytes(&mut caller, out, &distance.to_le_bytes());
+                    id as i64
+                }
+                None => -1,
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "rgame",
+        "play_sound",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32, volume: f32| -> i32 {
+            if !permitted(&caller, |c| c.play_sounds, "play_sound") {
+                return -1;
+            }
+            let Some(name) = read_string(&mut caller, ptr, len) else {
+                return -1;
+            };
+            caller.data_mut().sounds.push(SoundRequest {
+                name,
+                volume: volume.clamp(0.0, 1.0),
+            });
+            0
+        },
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOVER: &str = r#"
+        (module
+          (import "rgame" "spawn_entity" (func $spawn (param i32 i32) (result i64)))
+          (import "rgame" "find_entity" (func $find (param i32 i32) (result i64)))
+          (import "rgame" "get_position" (func $get_pos (param i64 i32) (result i32)))
+          (import "rgame" "set_position" (func $set_pos (param i64 f32 f32 f32) (result i32)))
+          (import "rgame" "play_sound" (func $play (param i32 i32 f32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "Mover")
+          (data (i32.const 8) "step")
+          (func (export "init")
+            (drop (call $set_pos (call $spawn (i32.const 0) (i32.const 5)) (f32.const 0) (f32.const 0) (f32.const 0))))
+          (func (export "update") (param $dt f32)
+            (local $id i64)
+            (local.set $id (call $find (i32.const 0) (i32.const 5)))
+            (drop (call $get_pos (local.get $id) (i32.const 16)))
+            (drop (call $set_pos (local.get $id)
+              (f32.add (f32.load (i32.const 16)) (local.get $dt))
+              (f32.const 0) (f32.const 0)))
+            (drop (call $play (i32.const 8) (i32.const 4) (f32.const 0.5)))))
+    "#;
+
+    #[test]
+    fn test_script_moves_entity() {
+        let mut host = ScriptHost::new().unwrap();
+        let mut scene = Scene::new("Test".to_string());
+        host.load_bytes("mover", MOVER.as_bytes(), &mut scene).unwrap();
+        assert_eq!(scene.entity_count(), 1);
+
+        host.update(&mut scene, 0.5);
+        host.update(&mut scene, 0.25);
+
+        let entity = scene.entities().next().unwrap();
+        assert_eq!(entity.get_component::<Transform>().unwrap().position.x, 0.75);
+        assert_eq!(host.drain_sounds().len(), 2);
+
+        let mut host = ScriptHost::new().unwrap();
+        let mut sandboxed = Scene::new("Sandboxed".to_string());
+        host.load_with_capabilities("mover", MOVER.as_bytes(), &mut sandboxed, ScriptCapabilities::read_only())
+            .unwrap();
+        host.update(&mut sandboxed, 0.5);
+        assert_eq!(sandboxed.entity_count(), 0);
+        assert!(host.drain_sounds().is_empty());
+    }
+
+    #[test]
+    fn test_runaway_script_is_disabled() {
+        let mut host = ScriptHost::new().unwrap();
+        host.set_fuel_per_call(10_000);
+        let mut scene = Scene::new("Test".to_string());
+        let id = host
+            .load_bytes(
+                "spin",
+                br#"(module (func (export "update") (param f32) (loop (br 0))))"#,
+                &mut scene,
+            )
+            .unwrap();
+
+        host.update(&mut scene, 0.016);
+        assert!(!host.is_enabled(id));
+    }
+}
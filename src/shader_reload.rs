@@ -0,0 +1,151 @@
+//! Shader hot reload for development
+//!
+//! The built-in mesh shaders are compiled into the engine. With hot reload enabled, the
+//! renderer polls the modification times of the same files in a shader directory (and of
+//! custom material shaders loaded from files) and rebuilds the affected pipelines when
+//! they change. A shader that fails to compile is logged and the old pipeline is kept.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Built-in shader files that can be reloaded, relative to the shader directory
+pub(crate) const BUILTIN_SHADERS: [&str; 4] = ["common.wgsl", "default.wgsl", "pbr.wgsl", "debug_view.wgsl"];
+
+/// Time between checks of the watched files
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// WGSL sources of the built-in mesh shaders
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ShaderSources {
+    /// Bindings and helpers prepended to every mesh and material shader
+    pub(crate) common: String,
+    pub(crate) default: String,
+    pub(crate) pbr: String,
+    pub(crate) debug_view: String,
+}
+
+impl Default for ShaderSources {
+    fn default() -> Self {
+        Self {
+            common: include_str!("shaders/common.wgsl").to_string(),
+            default: include_str!("shaders/default.wgsl").to_string(),
+            pbr: include_str!("shaders/pbr.wgsl").to_string(),
+            debug_view: include_str!("shaders/debug_view.wgsl").to_string(),
+        }
+    }
+}
+
+impl ShaderSources {
+    /// Read the built-in shaders from a directory
+    pub(crate) fn load(dir: &Path) -> Result<Self, String> {
+        let read = |name: &str| {
+            let path = dir.join(name);
+            std::fs::read_to_string(&path).map_err(|e| format!("Failed to read shader {}: {}", path.display(), e))
+        };
+        Ok(Self {
+            common: read(BUILTIN_SHADERS[0])?,
+            default: read(BUILTIN_SHADERS[1])?,
+            pbr: read(BUILTIN_SHADERS[2])?,
+            debug_view: read(BUILTIN_SHADERS[3])?,
+        })
+    }
+}
+
+/// Polls shader files for changes
+pub(crate) struct ShaderWatcher {
+    /// Directory holding the built-in shaders
+    dir: PathBuf,
+    files: HashMap<PathBuf, Option<SystemTime>>,
+    interval: Duration,
+    last_poll: Instant,
+}
+
+impl ShaderWatcher {
+    /// Watch the built-in shaders in `dir`
+    pub(crate) fn new(dir: &Path) -> Self {
+        let mut watcher = Self {
+            dir: dir.to_path_buf(),
+            files: HashMap::new(),
+            interval: POLL_INTERVAL,
+            last_poll: Instant::now(),
+        };
+        for name in BUILTIN_SHADERS {
+            watcher.watch(&dir.join(name));
+        }
+        watcher
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Also watch another file, such as a material shader
+    pub(crate) fn watch(&mut self, path: &Path) {
+        self.files.insert(path.to_path_buf(), modified(path));
+    }
+
+    /// Whether a changed file is one of the built-in shaders
+    pub(crate) fn is_builtin(&self, path: &Path) -> bool {
+        BUILTIN_SHADERS.iter().any(|name| path == self.dir.join(name))
+    }
+
+    /// Files modified since the last poll (checked at most every 250 ms)
+    pub(crate) fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < self.interval {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+        let mut changed = Vec::new();
+        for (path, time) in &mut self.files {
+            let current = modified(path);
+            if current != *time {
+                *time = current;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watcher_reports_modified_files() {
+        let dir = std::env::temp_dir().join(format!("shader_reload_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let material = dir.join("water.wgsl");
+        std::fs::write(&material, "// v1").unwrap();
+
+        let mut watcher = ShaderWatcher::new(&dir);
+        watcher.interval = Duration::ZERO;
+        watcher.watch(&material);
+        assert!(watcher.poll().is_empty());
+
+        let file = std::fs::File::options().write(true).open(&material).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+        assert_eq!(watcher.poll(), std::slice::from_ref(&material));
+        assert!(watcher.poll().is_empty());
+        assert!(!watcher.is_builtin(&material));
+        assert!(watcher.is_builtin(&dir.join("default.wgsl")));
+
+        // A built-in shader appearing counts as a change
+        std::fs::write(dir.join("common.wgsl"), "").unwrap();
+        assert_eq!(watcher.poll(), [dir.join("common.wgsl")]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_matches_builtin_sources() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/shaders");
+        assert_eq!(ShaderSources::load(&dir).unwrap(), ShaderSources::default());
+        assert!(ShaderSources::load(Path::new("missing_dir")).is_err());
+    }
+}
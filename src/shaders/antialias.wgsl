@@ -0,0 +1,111 @@
+// Post-process anti-aliasing on the HDR scene color, drawn as a fullscreen triangle:
+// FXAA blurs along detected edges, TAA blends the jittered frame into a history
+
+struct AntiAliasUniform {
+    texel_size: vec2<f32>,
+    // Share of the current frame in the TAA blend (1 = no history)
+    current_weight: f32,
+    _padding: f32,
+};
+
+@group(0) @binding(0)
+var t_current: texture_2d<f32>;
+
+@group(0) @binding(1)
+var s_linear: sampler;
+
+@group(0) @binding(2)
+var t_history: texture_2d<f32>;
+
+@group(0) @binding(3)
+var<uniform> params: AntiAliasUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: VertexOutput;
+    output.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return output;
+}
+
+// Luma of the Reinhard-compressed color, so bright HDR pixels don't swamp edge detection
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(color / (1.0 + color), vec3<f32>(0.299, 0.587, 0.114));
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+fn sample_current(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(t_current, s_linear, uv, 0.0).rgb;
+}
+
+const EDGE_THRESHOLD: f32 = 0.125;
+const EDGE_THRESHOLD_MIN: f32 = 0.0312;
+const REDUCE_MUL: f32 = 0.125;
+const REDUCE_MIN: f32 = 0.0078125;
+const SPAN_MAX: f32 = 8.0;
+
+@fragment
+fn fs_fxaa(input: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = input.uv;
+    let texel = params.texel_size;
+    let center = textureSampleLevel(t_current, s_linear, uv, 0.0);
+    let l_nw = luma(sample_current(uv + vec2<f32>(-1.0, -1.0) * texel));
+    let l_ne = luma(sample_current(uv + vec2<f32>(1.0, -1.0) * texel));
+    let l_sw = luma(sample_current(uv + vec2<f32>(-1.0, 1.0) * texel));
+    let l_se = luma(sample_current(uv + vec2<f32>(1.0, 1.0) * texel));
+    let l_m = luma(center.rgb);
+    let l_min = min(l_m, min(min(l_nw, l_ne), min(l_sw, l_se)));
+    let l_max = max(l_m, max(max(l_nw, l_ne), max(l_sw, l_se)));
+    if l_max - l_min < max(EDGE_THRESHOLD_MIN, l_max * EDGE_THRESHOLD) {
+        return center;
+    }
+
+    // Blur along the edge, perpendicular to the luma gradient
+    var direction = vec2<f32>(-((l_nw + l_ne) - (l_sw + l_se)), (l_nw + l_sw) - (l_ne + l_se));
+    let reduce = max((l_nw + l_ne + l_sw + l_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX)) * texel;
+
+    let inner = 0.5 * (sample_current(uv + direction * (1.0 / 3.0 - 0.5)) + sample_current(uv + direction * (2.0 / 3.0 - 0.5)));
+    let outer = inner * 0.5 + 0.25 * (sample_current(uv - direction * 0.5) + sample_current(uv + direction * 0.5));
+    // The wider blur crossed another edge; fall back to the narrow one
+    let l_outer = luma(outer);
+    if l_outer < l_min || l_outer > l_max {
+        return vec4<f32>(inner, center.a);
+    }
+    return vec4<f32>(outer, center.a);
+}
+
+@fragment
+fn fs_taa(input: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_current));
+    let pixel = vec2<i32>(input.clip_position.xy);
+    let current = textureLoad(t_current, pixel, 0);
+
+    // Clamp the history to the current neighborhood so stale colors don't ghost
+    var low = current.rgb;
+    var high = current.rgb;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbor = textureLoad(t_current, clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), size - 1), 0).rgb;
+            low = min(low, neighbor);
+            high = max(high, neighbor);
+        }
+    }
+    let history = clamp(textureSampleLevel(t_history, s_linear, input.uv, 0.0).rgb, low, high);
+
+    // Weight by inverse luminance so single bright samples don't flicker
+    let current_weight = params.current_weight / (1.0 + luminance(current.rgb));
+    let history_weight = (1.0 - params.current_weight) / (1.0 + luminance(history));
+    let color = (current.rgb * current_weight + history * history_weight) / max(current_weight + history_weight, 1e-5);
+    return vec4<f32>(color, current.a);
+}
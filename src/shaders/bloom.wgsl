@@ -0,0 +1,90 @@
+// Bloom: bright-pass prefilter, downsample chain, tent-filtered upsample, and composite,
+// each drawn as a fullscreen triangle
+
+struct BloomUniform {
+    threshold: f32,
+    // Width of the soft transition below the threshold
+    knee: f32,
+    intensity: f32,
+    _padding: f32,
+};
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var s_source: sampler;
+
+@group(0) @binding(2)
+var<uniform> bloom: BloomUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: VertexOutput;
+    output.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return output;
+}
+
+fn texel_size() -> vec2<f32> {
+    return 1.0 / vec2<f32>(textureDimensions(t_source));
+}
+
+// Center plus four bilinear taps half a texel out (dual Kawase)
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let t = texel_size() * 0.5;
+    var color = textureSample(t_source, s_source, uv).rgb * 4.0;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(-t.x, -t.y)).rgb;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(t.x, -t.y)).rgb;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(-t.x, t.y)).rgb;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(t.x, t.y)).rgb;
+    return color / 8.0;
+}
+
+// 3x3 tent filter
+fn upsample(uv: vec2<f32>) -> vec3<f32> {
+    let t = texel_size();
+    var color = textureSample(t_source, s_source, uv).rgb * 4.0;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(-t.x, 0.0)).rgb * 2.0;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(t.x, 0.0)).rgb * 2.0;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(0.0, -t.y)).rgb * 2.0;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(0.0, t.y)).rgb * 2.0;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(-t.x, -t.y)).rgb;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(t.x, -t.y)).rgb;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(-t.x, t.y)).rgb;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(t.x, t.y)).rgb;
+    return color / 16.0;
+}
+
+@fragment
+fn fs_prefilter(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Clamp so single overbright pixels don't flicker as huge blobs
+    let color = min(downsample(input.uv), vec3<f32>(1000.0));
+    let brightness = max(color.r, max(color.g, color.b));
+    let knee = max(bloom.knee, 0.0001);
+    var soft = clamp(brightness - bloom.threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee);
+    let contribution = max(soft, brightness - bloom.threshold) / max(brightness, 0.0001);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+@fragment
+fn fs_downsample(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(input.uv), 1.0);
+}
+
+@fragment
+fn fs_upsample(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(input.uv), 1.0);
+}
+
+@fragment
+fn fs_composite(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(input.uv) * bloom.intensity, 1.0);
+}
@@ -0,0 +1,212 @@
+// Bindings and vertex types shared by the default shader and custom materials.
+// Materials may add their own uniform parameters at @group(3) @binding(0).
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    // World-space camera position (xyz)
+    position: vec4<f32>,
+};
+
+struct LightUniform {
+    // Direction pointing towards the light (xyz)
+    direction: vec4<f32>,
+    // Light color premultiplied by intensity (rgb)
+    color: vec4<f32>,
+    // Ambient light color (rgb)
+    ambient: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(0) @binding(1)
+var<uniform> light: LightUniform;
+
+struct PointLight {
+    // World position (xyz) and range (w)
+    position_range: vec4<f32>,
+    // Light color premultiplied by intensity (rgb)
+    color: vec4<f32>,
+    // Spot direction (xyz)
+    direction: vec4<f32>,
+    // Cosines of the spot cone's inner (x) and outer (y) half-angles
+    cone: vec4<f32>,
+};
+
+struct PointLights {
+    count: u32,
+    lights: array<PointLight>,
+};
+
+@group(0) @binding(2)
+var<storage, read> point_lights: PointLights;
+
+struct ReflectionUniform {
+    // View-projection of the camera mirrored about the reflection plane
+    mirror_view_proj: mat4x4<f32>,
+    // Mirror plane normal (xyz) and distance from the origin (w)
+    plane: vec4<f32>,
+    // Planar reflection enabled (x), probe enabled (y), and the probe's last mip level (z)
+    params: vec4<f32>,
+};
+
+@group(0) @binding(3)
+var<uniform> reflection: ReflectionUniform;
+
+@group(0) @binding(4)
+var t_reflection_probe: texture_cube<f32>;
+
+@group(0) @binding(5)
+var t_planar_reflection: texture_2d<f32>;
+
+@group(0) @binding(6)
+var s_reflection: sampler;
+
+struct ObjectUniform {
+    model: mat4x4<f32>,
+    // Inverse transpose of the model matrix, for normals
+    normal: mat4x4<f32>,
+    // LOD cross-fade: share of pixels kept (x), and whether the pattern is inverted (y)
+    fade: vec4<f32>,
+    // Index of the object's first joint matrix (x), for skinned meshes
+    skin: vec4<u32>,
+};
+
+@group(1) @binding(0)
+var<uniform> object: ObjectUniform;
+
+// Joint matrices of every skinned mesh drawn this frame
+@group(1) @binding(1)
+var<storage, read> joint_matrices: array<mat4x4<f32>>;
+
+@group(2) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(2) @binding(1)
+var s_diffuse: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) color: vec4<f32>,
+    // Tangent (xyz) and bitangent sign (w)
+    @location(4) tangent: vec4<f32>,
+};
+
+struct SkinInput {
+    @location(5) joints: vec4<u32>,
+    @location(6) weights: vec4<f32>,
+};
+
+struct VertexOutput {
+    // Invariant so the depth pre-pass computes exactly the same depths
+    @builtin(position) @invariant clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) world_position: vec3<f32>,
+    @location(4) tangent: vec4<f32>,
+};
+
+// Transform a mesh vertex by the camera and the object's model matrix
+fn mesh_vertex(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    let world_position = object.model * vec4<f32>(input.position, 1.0);
+    output.clip_position = camera.view_proj * world_position;
+    output.world_position = world_position.xyz;
+    output.tex_coords = input.tex_coords;
+    output.normal = (object.normal * vec4<f32>(input.normal, 0.0)).xyz;
+    // Tangents lie in the surface, so they transform with the model matrix
+    output.tangent = vec4<f32>((object.model * vec4<f32>(input.tangent.xyz, 0.0)).xyz, input.tangent.w);
+    output.color = input.color;
+    return output;
+}
+
+// Pose a mesh vertex by the weighted blend of its joint matrices, before mesh_vertex
+fn skin_vertex(input: VertexInput, skin: SkinInput) -> VertexInput {
+    let base = object.skin.x;
+    let matrix = joint_matrices[base + skin.joints.x] * skin.weights.x
+        + joint_matrices[base + skin.joints.y] * skin.weights.y
+        + joint_matrices[base + skin.joints.z] * skin.weights.z
+        + joint_matrices[base + skin.joints.w] * skin.weights.w;
+    var output = input;
+    output.position = (matrix * vec4<f32>(input.position, 1.0)).xyz;
+    output.normal = (matrix * vec4<f32>(input.normal, 0.0)).xyz;
+    output.tangent = vec4<f32>((matrix * vec4<f32>(input.tangent.xyz, 0.0)).xyz, input.tangent.w);
+    return output;
+}
+
+// Discard part of the pixels in a 4x4 ordered-dither pattern while an LOD level fades;
+// call at the start of fragment shaders
+fn lod_fade(clip_position: vec4<f32>) {
+    let pixel = vec2<u32>(clip_position.xy) % vec2<u32>(4u);
+    var bayer = array<f32, 16>(0.0, 8.0, 2.0, 10.0, 12.0, 4.0, 14.0, 6.0, 3.0, 11.0, 1.0, 9.0, 15.0, 7.0, 13.0, 5.0);
+    let threshold = (bayer[pixel.y * 4u + pixel.x] + 0.5) / 16.0;
+    if ((threshold < object.fade.x) == (object.fade.y > 0.5)) {
+        discard;
+    }
+}
+
+// Direction towards a point/spot light (xyz) and its attenuation times spot factor (w)
+fn point_light_incoming(l: PointLight, position: vec3<f32>) -> vec4<f32> {
+    let to_light = l.position_range.xyz - position;
+    let distance = length(to_light);
+    let range = l.position_range.w;
+    if (distance >= range) {
+        return vec4<f32>(0.0);
+    }
+    let dir = to_light / max(distance, 0.0001);
+    // Windowed inverse-square falloff reaching zero at the range
+    let window = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    let attenuation = window * window / (distance * distance + 1.0);
+    let spot = smoothstep(l.cone.y, l.cone.x, dot(-dir, l.direction.xyz));
+    return vec4<f32>(dir, attenuation * spot);
+}
+
+// Diffuse light from the point and spot lights at a surface point
+fn point_lighting(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    var total = vec3<f32>(0.0);
+    for (var i = 0u; i < point_lights.count; i++) {
+        let l = point_lights.lights[i];
+        let incoming = point_light_incoming(l, position);
+        total += l.color.rgb * max(dot(normal, incoming.xyz), 0.0) * incoming.w;
+    }
+    return total;
+}
+
+// Bend a surface normal by a tangent-space normal map sample (green up, range 0-1)
+fn perturb_normal(normal: vec3<f32>, tangent: vec4<f32>, map_sample: vec3<f32>, scale: f32) -> vec3<f32> {
+    let n = normalize(normal);
+    // Meshes without tangents keep their geometric normal
+    if (dot(tangent.xyz, tangent.xyz) < 1e-8) {
+        return n;
+    }
+    // Re-orthogonalize, as interpolation skews the tangent
+    let t = normalize(tangent.xyz - n * dot(n, tangent.xyz));
+    let b = cross(n, t) * tangent.w;
+    let m = map_sample * 2.0 - 1.0;
+    return normalize(mat3x3<f32>(t, b, n) * vec3<f32>(m.xy * scale, m.z));
+}
+
+// Reflected light arriving along `r` at a surface point (rgb), and how much of it the
+// surface sees (a): 0 without any probe or planar reflection
+fn reflected_light(position: vec3<f32>, normal: vec3<f32>, r: vec3<f32>, roughness: f32) -> vec4<f32> {
+    var result = vec4<f32>(0.0);
+    if (reflection.params.y > 0.5) {
+        // Probe faces hold the world mirrored along x (see reflection.rs)
+        let dir = vec3<f32>(-r.x, r.y, r.z);
+        let level = roughness * reflection.params.z;
+        result = vec4<f32>(textureSampleLevel(t_reflection_probe, s_reflection, dir, level).rgb, 1.0);
+    }
+    let plane = reflection.plane;
+    let on_plane = abs(dot(position, plane.xyz) - plane.w) < 0.01 && dot(normalize(normal), plane.xyz) > 0.99;
+    if (reflection.params.x > 0.5 && on_plane) {
+        let clip = reflection.mirror_view_proj * vec4<f32>(position, 1.0);
+        let uv = clip.xy / clip.w * vec2<f32>(0.5, -0.5) + 0.5;
+        let planar = textureSampleLevel(t_planar_reflection, s_reflection, uv, 0.0).rgb;
+        // The mirror image is sharp, so rough surfaces lean on the blurred probe instead
+        result = vec4<f32>(mix(planar, result.rgb, roughness * result.a), 1.0);
+    }
+    return result;
+}
@@ -0,0 +1,27 @@
+// Debug views (appended to common.wgsl and default.wgsl)
+
+@fragment
+fn fs_wireframe(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.1, 1.0, 0.4, 1.0);
+}
+
+// World-space normals mapped from [-1, 1] to [0, 1]
+@fragment
+fn fs_normals(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(normalize(input.normal) * 0.5 + 0.5, 1.0);
+}
+
+// UVs as red/green with a checker to show tiling and stretching
+@fragment
+fn fs_uv(input: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = fract(input.tex_coords);
+    let cell = vec2<i32>(floor(input.tex_coords * 8.0));
+    let checker = select(0.8, 1.0, ((cell.x + cell.y) & 1) == 0);
+    return vec4<f32>(uv * checker, 0.0, 1.0);
+}
+
+// Additively blended, so each layer of fragments brightens the pixel
+@fragment
+fn fs_overdraw(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.12, 0.05, 0.02, 1.0);
+}
@@ -1,43 +1,59 @@
-// Default shader for the engine
+// Default shader for the engine (appended to common.wgsl)
 
-struct CameraUniform {
-    view_proj: mat4x4<f32>,
-};
-
-@group(0) @binding(0)
-var<uniform> camera: CameraUniform;
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    return mesh_vertex(input);
+}
 
-struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) tex_coords: vec2<f32>,
-    @location(2) normal: vec3<f32>,
-    @location(3) color: vec4<f32>,
-};
+@vertex
+fn vs_skinned(input: VertexInput, skin: SkinInput) -> VertexOutput {
+    return mesh_vertex(skin_vertex(input, skin));
+}
 
-struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>,
-    @location(1) normal: vec3<f32>,
-    @location(2) color: vec4<f32>,
+struct InstanceInput {
+    @location(5) model_0: vec4<f32>,
+    @location(6) model_1: vec4<f32>,
+    @location(7) model_2: vec4<f32>,
+    @location(8) model_3: vec4<f32>,
+    @location(9) color: vec4<f32>,
 };
 
+// Instanced draws take the model matrix and a color tint from the instance buffer.
+// Normals use the model matrix directly, which is exact for uniform scale.
 @vertex
-fn vs_main(input: VertexInput) -> VertexOutput {
+fn vs_instanced(input: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
     var output: VertexOutput;
-    output.clip_position = camera.view_proj * vec4<f32>(input.position, 1.0);
+    let world_position = model * vec4<f32>(input.position, 1.0);
+    output.clip_position = camera.view_proj * world_position;
+    output.world_position = world_position.xyz;
     output.tex_coords = input.tex_coords;
-    output.normal = input.normal;
-    output.color = input.color;
+    output.normal = (model * vec4<f32>(input.normal, 0.0)).xyz;
+    output.tangent = vec4<f32>((model * vec4<f32>(input.tangent.xyz, 0.0)).xyz, input.tangent.w);
+    output.color = input.color * instance.color;
     return output;
 }
 
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    lod_fade(input.clip_position);
+    return shade(input);
+}
+
+// Instances have no object uniform of their own, so they skip the LOD fade
+@fragment
+fn fs_instanced(input: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(input);
+}
+
+fn shade(input: VertexOutput) -> vec4<f32> {
     // Simple lighting
-    let light_dir = normalize(vec3<f32>(1.0, 1.0, 1.0));
-    let ambient = 0.3;
-    let diffuse = max(dot(input.normal, light_dir), 0.0);
-    let lighting = ambient + diffuse * 0.7;
-    
-    return vec4<f32>(input.color.rgb * lighting, input.color.a);
+    let normal = normalize(input.normal);
+    let light_dir = normalize(light.direction.xyz);
+    let diffuse = max(dot(normal, light_dir), 0.0);
+    let lighting = light.ambient.rgb + light.color.rgb * diffuse + point_lighting(input.world_position, normal);
+
+    // Untextured meshes bind a white texture, leaving the vertex color unchanged
+    let albedo = textureSample(t_diffuse, s_diffuse, input.tex_coords) * input.color;
+    return vec4<f32>(albedo.rgb * lighting, albedo.a);
 }
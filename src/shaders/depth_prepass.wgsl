@@ -0,0 +1,17 @@
+// Depth-only pre-pass, appended to common.wgsl. The scene pass transforms vertices with
+// the same mesh_vertex, and the position is invariant, so both passes produce identical
+// depths and the scene pass's less-equal test keeps exactly the nearest surface.
+
+struct PrepassOutput {
+    @builtin(position) @invariant clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> PrepassOutput {
+    return PrepassOutput(mesh_vertex(input).clip_position);
+}
+
+@vertex
+fn vs_skinned(input: VertexInput, skin: SkinInput) -> PrepassOutput {
+    return PrepassOutput(mesh_vertex(skin_vertex(input, skin)).clip_position);
+}
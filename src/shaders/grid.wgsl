@@ -0,0 +1,108 @@
+// Editor ground grid on the y = 0 plane with origin axes, drawn as a fullscreen triangle
+// over the sky and under the scene. Lines are antialiased by their screen-space
+// derivatives and fade out with distance from the camera.
+
+struct GridUniform {
+    inv_view_proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    // Camera position (xyz) and the distance where the grid has faded out (w)
+    camera: vec4<f32>,
+    // Line color (rgb) and opacity (a)
+    color: vec4<f32>,
+    // Cell size (x), cells between major lines (y), and axis gizmo length (z)
+    params: vec4<f32>,
+    // Viewport size in pixels (xy)
+    viewport: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> grid: GridUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    let ndc = uv * 2.0 - 1.0;
+    var output: VertexOutput;
+    output.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    output.ndc = ndc;
+    return output;
+}
+
+// Coverage of lines every `spacing` units along both axes, about a pixel wide
+fn lines(coord: vec2<f32>, spacing: f32) -> f32 {
+    let cells = coord / spacing;
+    let distance = abs(fract(cells - 0.5) - 0.5) / max(fwidth(cells), vec2<f32>(1e-6));
+    return 1.0 - min(min(distance.x, distance.y), 1.0);
+}
+
+fn to_pixels(clip: vec4<f32>) -> vec2<f32> {
+    let ndc = clip.xy / clip.w;
+    return vec2<f32>(ndc.x + 1.0, 1.0 - ndc.y) * 0.5 * grid.viewport.xy;
+}
+
+// Coverage of the segment between two clip-space points, `width` pixels wide
+fn segment(pixel: vec2<f32>, a: vec4<f32>, b: vec4<f32>, width: f32) -> f32 {
+    // Cut the segment off in front of the camera before the perspective divide
+    let near = 1e-4;
+    if (a.w < near && b.w < near) {
+        return 0.0;
+    }
+    var start = a;
+    var end = b;
+    if (start.w < near) {
+        start = mix(start, end, (near - start.w) / (end.w - start.w));
+    }
+    if (end.w < near) {
+        end = mix(end, start, (near - end.w) / (start.w - end.w));
+    }
+    let p0 = to_pixels(start);
+    let d = to_pixels(end) - p0;
+    let t = clamp(dot(pixel - p0, d) / max(dot(d, d), 1e-6), 0.0, 1.0);
+    return clamp(width * 0.5 - length(pixel - p0 - d * t) + 0.5, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let near = grid.inv_view_proj * vec4<f32>(input.ndc, 0.0, 1.0);
+    let far = grid.inv_view_proj * vec4<f32>(input.ndc, 1.0, 1.0);
+    let origin = near.xyz / near.w;
+    let dir = far.xyz / far.w - origin;
+
+    // Where the view ray meets the ground; derivatives need every pixel to compute it
+    let t = -origin.y / select(dir.y, 1e-6, abs(dir.y) < 1e-6);
+    let ground = (origin + dir * t).xz;
+    let minor = lines(ground, grid.params.x) * 0.5;
+    let major = lines(ground, grid.params.x * grid.params.y);
+    let axis_width = fwidth(ground);
+
+    var color = vec4<f32>(grid.color.rgb, max(minor, major));
+    // The X axis (z = 0) in red and the Z axis (x = 0) in blue
+    if (abs(ground.y) < axis_width.y) {
+        color = vec4<f32>(1.0, 0.2, 0.2, 1.0);
+    }
+    if (abs(ground.x) < axis_width.x) {
+        color = vec4<f32>(0.2, 0.4, 1.0, 1.0);
+    }
+    let fade = 1.0 - smoothstep(grid.camera.w * 0.5, grid.camera.w, distance(ground, grid.camera.xz));
+    color.a *= grid.color.a * fade * f32(t > 0.0);
+
+    // Axis gizmo at the origin, on top of the grid
+    let axis_length = grid.params.z;
+    if (axis_length > 0.0) {
+        let pixel = vec2<f32>(input.ndc.x + 1.0, 1.0 - input.ndc.y) * 0.5 * grid.viewport.xy;
+        let center = grid.view_proj * vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        var axes = array<vec3<f32>, 3>(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(0.0, 0.0, 1.0));
+        var colors = array<vec3<f32>, 3>(vec3<f32>(1.0, 0.2, 0.2), vec3<f32>(0.2, 1.0, 0.2), vec3<f32>(0.2, 0.4, 1.0));
+        for (var i = 0; i < 3; i++) {
+            let tip = grid.view_proj * vec4<f32>(axes[i] * axis_length, 1.0);
+            let coverage = segment(pixel, center, tip, 3.0);
+            color = vec4<f32>(mix(color.rgb, colors[i], coverage), max(color.a, coverage));
+        }
+    }
+    return color;
+}
@@ -0,0 +1,76 @@
+// Lens effects: chromatic aberration, film grain, and vignette applied to the HDR scene
+// color in one pass into a scratch target, then copied back, each drawn as a fullscreen
+// triangle
+
+struct LensUniform {
+    // Intensity (x), radius (y), and smoothness (z)
+    vignette: vec4<f32>,
+    // Intensity (x), size in pixels (y), and per-frame seed (z)
+    grain: vec4<f32>,
+    // Channel separation at the corners in pixels (x)
+    aberration: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var s_source: sampler;
+
+@group(0) @binding(2)
+var<uniform> lens: LensUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: VertexOutput;
+    output.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return output;
+}
+
+// PCG hash of a grain cell, in 0..1
+fn hash(cell: vec2<u32>, seed: u32) -> f32 {
+    let v = (cell.x + cell.y * 7919u + seed * 104729u) * 747796405u + 2891336453u;
+    let w = ((v >> ((v >> 28u) + 4u)) ^ v) * 277803737u;
+    return f32((w >> 22u) ^ w) / 4294967295.0;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(t_source));
+    // -1..1 from the center, reaching the corners at length sqrt(2)
+    let centered = input.uv * 2.0 - 1.0;
+
+    // Red and blue sampled outwards and inwards, separating more towards the edges
+    let shift = centered * lens.aberration.x / size;
+    let center = textureSampleLevel(t_source, s_source, input.uv, 0.0);
+    var color = vec3<f32>(
+        textureSampleLevel(t_source, s_source, input.uv + shift, 0.0).r,
+        center.g,
+        textureSampleLevel(t_source, s_source, input.uv - shift, 0.0).b,
+    );
+
+    // Grain scales the color so it stays subtle whatever the exposure
+    let cell = vec2<u32>(input.clip_position.xy / max(lens.grain.y, 1.0));
+    let noise = hash(cell, u32(lens.grain.z)) * 2.0 - 1.0;
+    color *= max(1.0 + noise * lens.grain.x, 0.0);
+
+    // Distance from the center corrected for aspect, 1.0 at the corners
+    let aspect = vec2<f32>(size.x / size.y, 1.0);
+    let distance = length(centered * aspect) / length(aspect);
+    let falloff = smoothstep(lens.vignette.y, lens.vignette.y + lens.vignette.z, distance);
+    color *= 1.0 - lens.vignette.x * falloff;
+
+    return vec4<f32>(color, center.a);
+}
+
+@fragment
+fn fs_copy(input: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSampleLevel(t_source, s_source, input.uv, 0.0);
+}
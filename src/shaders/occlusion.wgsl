@@ -0,0 +1,33 @@
+// Bounding-box proxies for occlusion queries: a 14-vertex triangle strip covering a cube,
+// stretched over each instance's box. Only the depth test matters; color writes are off.
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct BoxInput {
+    @location(0) min: vec4<f32>,
+    @location(1) max: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, box: BoxInput) -> @builtin(position) vec4<f32> {
+    // Bit masks selecting the max corner per axis for each strip vertex
+    let bit = 1u << index;
+    let corner = vec3<f32>(
+        f32((0x287au & bit) != 0u),
+        f32((0x02afu & bit) != 0u),
+        f32((0x31e3u & bit) != 0u),
+    );
+    let position = mix(box.min.xyz, box.max.xyz, corner);
+    return camera.view_proj * vec4<f32>(position, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0);
+}
@@ -0,0 +1,49 @@
+// Selection outline, appended to common.wgsl. The outlined meshes first fill the
+// stencil buffer (vs_mask), then their silhouettes pushed out along the normals by a few
+// pixels are drawn in the outline color where the stencil is still clear (vs_outline).
+
+struct OutlineUniform {
+    color: vec4<f32>,
+    // Width in pixels (x) and viewport size in pixels (zw)
+    params: vec4<f32>,
+};
+
+@group(3) @binding(0)
+var<uniform> outline: OutlineUniform;
+
+// Move a vertex along its screen-space normal by the outline width
+fn expand(vertex: VertexOutput) -> vec4<f32> {
+    let clip = vertex.clip_position;
+    let normal = (camera.view_proj * vec4<f32>(vertex.normal, 0.0)).xy;
+    if (dot(normal, normal) < 1e-12) {
+        return clip;
+    }
+    // Clip space spans 2 units across the viewport; scaling by w keeps the width in pixels
+    let offset = normalize(normal) * outline.params.x * 2.0 / outline.params.zw;
+    return vec4<f32>(clip.xy + offset * clip.w, clip.zw);
+}
+
+@vertex
+fn vs_mask(input: VertexInput) -> @builtin(position) vec4<f32> {
+    return mesh_vertex(input).clip_position;
+}
+
+@vertex
+fn vs_mask_skinned(input: VertexInput, skin: SkinInput) -> @builtin(position) vec4<f32> {
+    return mesh_vertex(skin_vertex(input, skin)).clip_position;
+}
+
+@vertex
+fn vs_outline(input: VertexInput) -> @builtin(position) vec4<f32> {
+    return expand(mesh_vertex(input));
+}
+
+@vertex
+fn vs_outline_skinned(input: VertexInput, skin: SkinInput) -> @builtin(position) vec4<f32> {
+    return expand(mesh_vertex(skin_vertex(input, skin)));
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return outline.color;
+}
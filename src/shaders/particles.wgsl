@@ -0,0 +1,142 @@
+// Particles: a compute pass spawning and integrating particles in a storage buffer,
+// and instanced camera-facing billboards reading them back
+
+struct Particle {
+    position: vec3<f32>,
+    age: f32,
+    velocity: vec3<f32>,
+    lifetime: f32,
+};
+
+struct EmitterParams {
+    // xyz = world position, w = cone half-angle
+    origin: vec4<f32>,
+    direction: vec4<f32>,
+    // xyz = acceleration, w = time step
+    gravity: vec4<f32>,
+    start_color: vec4<f32>,
+    end_color: vec4<f32>,
+    // start size, end size, min speed, max speed
+    shape: vec4<f32>,
+    // min lifetime, max lifetime
+    lifetime: vec4<f32>,
+    // spawn start, spawn count, capacity, seed
+    spawn: vec4<u32>,
+};
+
+struct ParticleCamera {
+    view_proj: mat4x4<f32>,
+    right: vec4<f32>,
+    up: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> emitter: EmitterParams;
+
+@group(0) @binding(1)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(2)
+var<storage, read> particles_in: array<Particle>;
+
+@group(0) @binding(3)
+var<uniform> camera: ParticleCamera;
+
+// PCG hash
+fn hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn random(seed: ptr<function, u32>) -> f32 {
+    *seed = hash(*seed);
+    return f32(*seed) / 4294967295.0;
+}
+
+// Random direction within `spread` radians of `axis`
+fn cone_direction(axis: vec3<f32>, spread: f32, seed: ptr<function, u32>) -> vec3<f32> {
+    let cos_theta = mix(cos(spread), 1.0, random(seed));
+    let sin_theta = sqrt(max(1.0 - cos_theta * cos_theta, 0.0));
+    let phi = random(seed) * 6.2831853;
+    var helper = vec3<f32>(1.0, 0.0, 0.0);
+    if abs(axis.x) > 0.9 {
+        helper = vec3<f32>(0.0, 1.0, 0.0);
+    }
+    let tangent = normalize(cross(axis, helper));
+    let bitangent = cross(axis, tangent);
+    return (tangent * cos(phi) + bitangent * sin(phi)) * sin_theta + axis * cos_theta;
+}
+
+@compute @workgroup_size(64)
+fn cs_update(@builtin(global_invocation_id) id: vec3<u32>) {
+    let capacity = emitter.spawn.z;
+    let index = id.x;
+    if index >= capacity {
+        return;
+    }
+
+    var particle = particles[index];
+    let dt = emitter.gravity.w;
+    // Slots from spawn start (wrapping around the ring) are reborn this step
+    let slot = (index + capacity - emitter.spawn.x) % capacity;
+    if slot < emitter.spawn.y {
+        var seed = hash(index ^ hash(emitter.spawn.w));
+        var axis = emitter.direction.xyz;
+        if dot(axis, axis) == 0.0 {
+            axis = vec3<f32>(0.0, 1.0, 0.0);
+        }
+        let speed = mix(emitter.shape.z, emitter.shape.w, random(&seed));
+        particle.position = emitter.origin.xyz;
+        particle.velocity = cone_direction(axis, emitter.origin.w, &seed) * speed;
+        particle.age = 0.0;
+        particle.lifetime = mix(emitter.lifetime.x, emitter.lifetime.y, random(&seed));
+    } else if particle.age < particle.lifetime {
+        particle.velocity += emitter.gravity.xyz * dt;
+        particle.position += particle.velocity * dt;
+        particle.age += dt;
+    }
+    particles[index] = particle;
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32, @builtin(instance_index) instance: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[vertex];
+    let particle = particles_in[instance];
+
+    // Dead particles collapse to a zero-area quad
+    var size = 0.0;
+    var t = 1.0;
+    if particle.age < particle.lifetime {
+        t = particle.age / particle.lifetime;
+        size = mix(emitter.shape.x, emitter.shape.y, t);
+    }
+    let offset = (camera.right.xyz * corner.x + camera.up.xyz * corner.y) * size * 0.5;
+
+    var output: VertexOutput;
+    output.clip_position = camera.view_proj * vec4<f32>(particle.position + offset, 1.0);
+    output.uv = corner;
+    output.color = mix(emitter.start_color, emitter.end_color, t);
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Soft round sprite
+    let falloff = clamp(1.0 - length(input.uv), 0.0, 1.0);
+    return vec4<f32>(input.color.rgb, input.color.a * falloff);
+}
@@ -0,0 +1,109 @@
+// Metallic-roughness PBR shader (appended to common.wgsl)
+// Cook-Torrance specular with a GGX distribution, Smith geometry, and Schlick fresnel.
+
+struct PbrParams {
+    base_color: vec4<f32>,
+    // Emissive color (rgb)
+    emissive: vec4<f32>,
+    // Metallic (x), roughness (y), and normal scale (z)
+    factors: vec4<f32>,
+};
+
+@group(3) @binding(0)
+var<uniform> pbr: PbrParams;
+
+@group(3) @binding(1)
+var t_base_color: texture_2d<f32>;
+
+@group(3) @binding(2)
+var t_metallic_roughness: texture_2d<f32>;
+
+@group(3) @binding(3)
+var t_emissive: texture_2d<f32>;
+
+@group(3) @binding(4)
+var s_pbr: sampler;
+
+@group(3) @binding(5)
+var t_normal: texture_2d<f32>;
+
+const PI: f32 = 3.14159265;
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    let gv = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let gl = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return gv * gl;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Fresnel for light from the whole specular lobe, which rough surfaces spread out
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
+    return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Outgoing light towards `v` for light of `radiance` arriving from `l`
+fn brdf(n: vec3<f32>, v: vec3<f32>, l: vec3<f32>, radiance: vec3<f32>, albedo: vec3<f32>, metallic: f32, roughness: f32) -> vec3<f32> {
+    let n_dot_l = max(dot(n, l), 0.0);
+    if (n_dot_l <= 0.0) {
+        return vec3<f32>(0.0);
+    }
+    let h = normalize(v + l);
+    let n_dot_v = max(dot(n, v), 0.0001);
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+    let f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+    let specular = distribution_ggx(max(dot(n, h), 0.0), roughness) * geometry_smith(n_dot_v, n_dot_l, roughness) * f
+        / (4.0 * n_dot_v * n_dot_l + 0.0001);
+    let diffuse = (1.0 - f) * (1.0 - metallic) * albedo / PI;
+    return (diffuse + specular) * radiance * n_dot_l;
+}
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    return mesh_vertex(input);
+}
+
+@vertex
+fn vs_skinned(input: VertexInput, skin: SkinInput) -> VertexOutput {
+    return mesh_vertex(skin_vertex(input, skin));
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    lod_fade(input.clip_position);
+    let base = textureSample(t_base_color, s_pbr, input.tex_coords) * pbr.base_color * input.color;
+    let metallic_roughness = textureSample(t_metallic_roughness, s_pbr, input.tex_coords);
+    let emissive = textureSample(t_emissive, s_pbr, input.tex_coords).rgb * pbr.emissive.rgb;
+    let metallic = pbr.factors.x * metallic_roughness.b;
+    let roughness = clamp(pbr.factors.y * metallic_roughness.g, 0.04, 1.0);
+
+    let normal_sample = textureSample(t_normal, s_pbr, input.tex_coords).rgb;
+    let n = perturb_normal(input.normal, input.tangent, normal_sample, pbr.factors.z);
+    let v = normalize(camera.position.xyz - input.world_position);
+
+    // Light colors are scaled by PI so a white light matches the default shader's brightness
+    var color = brdf(n, v, normalize(light.direction.xyz), light.color.rgb * PI, base.rgb, metallic, roughness);
+    for (var i = 0u; i < point_lights.count; i++) {
+        let l = point_lights.lights[i];
+        let incoming = point_light_incoming(l, input.world_position);
+        color += brdf(n, v, incoming.xyz, l.color.rgb * incoming.w * PI, base.rgb, metallic, roughness);
+    }
+    color += light.ambient.rgb * base.rgb * (1.0 - metallic * 0.5) + emissive;
+
+    // Specular reflection of the planar mirror or reflection probe, if any
+    let reflected = reflected_light(input.world_position, input.normal, reflect(-v, n), roughness);
+    let f0 = mix(vec3<f32>(0.04), base.rgb, metallic);
+    color += reflected.rgb * fresnel_schlick_roughness(max(dot(n, v), 0.0), f0, roughness) * reflected.a;
+    return vec4<f32>(color, base.a);
+}
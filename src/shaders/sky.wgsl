@@ -0,0 +1,50 @@
+// Procedural sky: vertical gradient with a sun disk, drawn as a fullscreen triangle
+
+struct SkyUniform {
+    inv_view_proj: mat4x4<f32>,
+    // Direction towards the sun (xyz), cosine of the sun's angular radius (w)
+    sun_direction: vec4<f32>,
+    sun_color: vec4<f32>,
+    zenith: vec4<f32>,
+    horizon: vec4<f32>,
+    ground: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> sky: SkyUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    let ndc = uv * 2.0 - 1.0;
+    var output: VertexOutput;
+    output.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    output.ndc = ndc;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let near = sky.inv_view_proj * vec4<f32>(input.ndc, 0.0, 1.0);
+    let far = sky.inv_view_proj * vec4<f32>(input.ndc, 1.0, 1.0);
+    let dir = normalize(far.xyz / far.w - near.xyz / near.w);
+
+    var color: vec3<f32>;
+    if (dir.y >= 0.0) {
+        color = mix(sky.horizon.rgb, sky.zenith.rgb, pow(dir.y, 0.5));
+    } else {
+        color = mix(sky.horizon.rgb, sky.ground.rgb, pow(-dir.y, 0.4));
+    }
+
+    let sun_dot = dot(dir, normalize(sky.sun_direction.xyz));
+    let disk = smoothstep(sky.sun_direction.w, sky.sun_direction.w + 0.0005, sun_dot);
+    let glow = pow(max(sun_dot, 0.0), 64.0) * 0.35;
+    color = color + sky.sun_color.rgb * (disk + glow);
+
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,77 @@
+// Terrain shader: blends four tiled layers using splat weights stored in the vertex color
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+struct LightUniform {
+    direction: vec4<f32>,
+    color: vec4<f32>,
+    ambient: vec4<f32>,
+};
+
+struct TerrainUniform {
+    // Texture repeats across the whole terrain, per layer
+    tiling: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(0) @binding(1)
+var<uniform> light: LightUniform;
+
+@group(1) @binding(0)
+var<uniform> terrain: TerrainUniform;
+@group(1) @binding(1)
+var layer_sampler: sampler;
+@group(1) @binding(2)
+var layer0: texture_2d<f32>;
+@group(1) @binding(3)
+var layer1: texture_2d<f32>;
+@group(1) @binding(4)
+var layer2: texture_2d<f32>;
+@group(1) @binding(5)
+var layer3: texture_2d<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) weights: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.clip_position = camera.view_proj * vec4<f32>(input.position, 1.0);
+    output.tex_coords = input.tex_coords;
+    output.normal = input.normal;
+    output.weights = input.color;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = input.tex_coords;
+    let c0 = textureSample(layer0, layer_sampler, uv * terrain.tiling.x).rgb;
+    let c1 = textureSample(layer1, layer_sampler, uv * terrain.tiling.y).rgb;
+    let c2 = textureSample(layer2, layer_sampler, uv * terrain.tiling.z).rgb;
+    let c3 = textureSample(layer3, layer_sampler, uv * terrain.tiling.w).rgb;
+
+    let total = max(dot(input.weights, vec4<f32>(1.0)), 0.0001);
+    let w = input.weights / total;
+    let albedo = c0 * w.x + c1 * w.y + c2 * w.z + c3 * w.w;
+
+    let diffuse = max(dot(normalize(input.normal), normalize(light.direction.xyz)), 0.0);
+    let lighting = light.ambient.rgb + light.color.rgb * diffuse;
+
+    return vec4<f32>(albedo * lighting, 1.0);
+}
@@ -0,0 +1,97 @@
+// Tone mapping: resolves the HDR scene color to the surface, drawn as a fullscreen triangle,
+// then color grades it through two blended 3D LUTs
+
+struct ToneMapUniform {
+    exposure: f32,
+    // 0 = ACES, 1 = Reinhard, 2 = none (clamp)
+    mode: u32,
+    lut_blend: f32,
+    // 0 skips color grading
+    lut_intensity: f32,
+    // 1 when the surface isn't sRGB, so the shader encodes the output itself
+    encode_srgb: u32,
+    _padding0: u32,
+    _padding1: vec2<u32>,
+};
+
+@group(0) @binding(0)
+var t_hdr: texture_2d<f32>;
+
+@group(0) @binding(1)
+var s_hdr: sampler;
+
+@group(0) @binding(2)
+var<uniform> params: ToneMapUniform;
+
+@group(1) @binding(0)
+var t_lut_from: texture_3d<f32>;
+
+@group(1) @binding(1)
+var t_lut_to: texture_3d<f32>;
+
+@group(1) @binding(2)
+var s_lut: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: VertexOutput;
+    output.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return output;
+}
+
+// Narkowicz's fit of the ACES filmic curve
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn reinhard(x: vec3<f32>) -> vec3<f32> {
+    return x / (1.0 + x);
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+// Look up an sRGB-encoded color, sampling at texel centers so the table's ends map to 0 and 1
+fn sample_lut(lut: texture_3d<f32>, color: vec3<f32>) -> vec3<f32> {
+    let size = vec3<f32>(textureDimensions(lut));
+    let uvw = (color * (size - 1.0) + 0.5) / size;
+    return textureSampleLevel(lut, s_lut, uvw, 0.0).rgb;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = textureSample(t_hdr, s_hdr, input.uv).rgb * params.exposure;
+    var color: vec3<f32>;
+    switch params.mode {
+        case 0u: {
+            color = aces(hdr);
+        }
+        case 1u: {
+            color = reinhard(hdr);
+        }
+        default: {
+            color = clamp(hdr, vec3<f32>(0.0), vec3<f32>(1.0));
+        }
+    }
+    if (params.lut_intensity > 0.0) {
+        let encoded = linear_to_srgb(color);
+        let graded = mix(sample_lut(t_lut_from, encoded), sample_lut(t_lut_to, encoded), params.lut_blend);
+        color = srgb_to_linear(mix(encoded, graded, params.lut_intensity));
+    }
+    if (params.encode_srgb != 0u) {
+        color = linear_to_srgb(color);
+    }
+    return vec4<f32>(color, 1.0);
+}
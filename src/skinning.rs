@@ -0,0 +1,170 @@
+//! GPU skinning for skeletal animation
+//!
+//! A skinned mesh carries a [`SkinVertex`] per vertex (up to four joint indices and
+//! weights, glTF's `JOINTS_0`/`WEIGHTS_0`) next to its regular vertices. An entity drawing
+//! it with a [`MeshRenderer`](crate::renderer::MeshRenderer) also holds a [`Skeleton`]:
+//! animation code writes each joint's local pose, and every frame the renderer uploads
+//! the joint matrices to a storage buffer that the vertex shader blends.
+
+use crate::ecs::Component;
+use crate::math::Transform;
+use bytemuck::{Pod, Zeroable};
+use glam::Mat4;
+
+/// Joint influences of one vertex, indexing the [`Skeleton`]'s joints
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct SkinVertex {
+    pub joints: [u16; 4],
+    pub weights: [f32; 4],
+}
+
+impl SkinVertex {
+    /// Create skin weights, normalized to sum to 1
+    pub fn new(joints: [u16; 4], weights: [f32; 4]) -> Self {
+        let total: f32 = weights.iter().sum();
+        let weights = if total > f32::EPSILON {
+            weights.map(|w| w / total)
+        } else {
+            [1.0, 0.0, 0.0, 0.0]
+        };
+        Self { joints, weights }
+    }
+
+    /// Vertex buffer layout, bound in slot 1 after [`Vertex`](crate::renderer::Vertex)
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SkinVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Uint16x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[u16; 4]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// A bone of a [`Skeleton`]
+#[derive(Debug, Clone)]
+pub struct Joint {
+    pub name: String,
+    /// Index of the parent joint (`None` for roots)
+    pub parent: Option<usize>,
+    /// Transforms the mesh's bind pose into the joint's space
+    pub inverse_bind: Mat4,
+    /// Current transform relative to the parent (or the entity for roots)
+    pub pose: Transform,
+}
+
+/// Joint hierarchy posing a skinned mesh
+#[derive(Debug, Clone, Default)]
+pub struct Skeleton {
+    pub joints: Vec<Joint>,
+}
+
+impl Component for Skeleton {}
+
+impl Skeleton {
+    /// Create a skeleton; parents must not form cycles
+    pub fn new(joints: Vec<Joint>) -> Self {
+        Self { joints }
+    }
+
+    /// Find a joint by name
+    pub fn joint_index(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+
+    /// Set a joint's local pose
+    pub fn set_pose(&mut self, index: usize, pose: Transform) {
+        if let Some(joint) = self.joints.get_mut(index) {
+            joint.pose = pose;
+        }
+    }
+
+    /// Each joint's pose in the entity's space
+    pub fn global_poses(&self) -> Vec<Mat4> {
+        let mut globals: Vec<Option<Mat4>> = vec![None; self.joints.len()];
+        for index in 0..self.joints.len() {
+            self.global_pose(index, &mut globals);
+        }
+        globals.into_iter().map(Option::unwrap_or_default).collect()
+    }
+
+    fn global_pose(&self, index: usize, globals: &mut [Option<Mat4>]) -> Mat4 {
+        if let Some(global) = globals[index] {
+            return global;
+        }
+        let joint = &self.joints[index];
+        let local = joint.pose.matrix();
+        let global = match joint.parent.filter(|&parent| parent != index && parent < self.joints.len()) {
+            Some(parent) => self.global_pose(parent, globals) * local,
+            None => local,
+        };
+        globals[index] = Some(global);
+        global
+    }
+
+    /// Matrices moving bind-pose vertices to the current pose, as uploaded to the GPU
+    pub fn joint_matrices(&self) -> Vec<Mat4> {
+        self.global_poses()
+            .into_iter()
+            .zip(&self.joints)
+            .map(|(global, joint)| global * joint.inverse_bind)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::{Quat, Vec3};
+
+    fn chain() -> Skeleton {
+        // Two joints one unit apart along Y, posed at their bind positions
+        let joint = |name: &str, parent, y: f32| Joint {
+            name: name.to_string(),
+            parent,
+            inverse_bind: Mat4::from_translation(Vec3::new(0.0, -y, 0.0)),
+            pose: Transform::from_position(Vec3::new(0.0, if parent.is_some() { 1.0 } else { y }, 0.0)),
+        };
+        Skeleton::new(vec![joint("root", None, 0.0), joint("arm", Some(0), 1.0)])
+    }
+
+    #[test]
+    fn test_bind_pose_is_identity() {
+        let skeleton = chain();
+        for matrix in skeleton.joint_matrices() {
+            assert!(matrix.abs_diff_eq(Mat4::IDENTITY, 1e-6));
+        }
+        assert_eq!(skeleton.joint_index("arm"), Some(1));
+    }
+
+    #[test]
+    fn test_child_follows_parent() {
+        let mut skeleton = chain();
+        let turn = Quat::from_rotation_z(std::f32::consts::FRAC_PI_2);
+        skeleton.set_pose(0, Transform::from_prs(Vec3::ZERO, turn, Vec3::ONE));
+
+        // A vertex at the arm joint swings from +Y to -X with its parent
+        let matrices = skeleton.joint_matrices();
+        let moved = matrices[1].transform_point3(Vec3::new(0.0, 1.0, 0.0));
+        assert!(moved.abs_diff_eq(Vec3::new(-1.0, 0.0, 0.0), 1e-5));
+    }
+
+    #[test]
+    fn test_skin_vertex() {
+        assert_eq!(std::mem::size_of::<SkinVertex>(), 24);
+        let skin = SkinVertex::new([0, 1, 0, 0], [2.0, 2.0, 0.0, 0.0]);
+        assert_eq!(skin.weights, [0.5, 0.5, 0.0, 0.0]);
+        assert_eq!(SkinVertex::new([3, 0, 0, 0], [0.0; 4]).weights[0], 1.0);
+    }
+}
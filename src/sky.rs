@@ -0,0 +1,375 @@
+//! Day/night cycle and procedural sky
+//!
+//! [`DayNightCycle`] advances a time-of-day value and derives the sun
+//! direction, sky gradient, and directional/ambient lighting from it. The
+//! engine applies the result to the renderer every frame when a cycle is set
+//! with `Engine::set_day_night`, cross-fading between day and night color grading
+//! LUTs if the cycle has them.
+
+use crate::color_grading::{ColorGrading, ColorLutHandle};
+use crate::math::helpers::smoothstep;
+use crate::renderer::{Color, DirectionalLight, Renderer};
+use crate::utils::color_utils;
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Quat, Vec3};
+use wgpu::util::DeviceExt;
+
+/// Procedural sky drawn behind the scene
+#[derive(Debug, Clone, Copy)]
+pub struct Sky {
+    pub zenith: Color,
+    pub horizon: Color,
+    pub ground: Color,
+    /// Direction pointing towards the sun
+    pub sun_direction: Vec3,
+    pub sun_color: Color,
+    /// Angular radius of the sun disk, in degrees
+    pub sun_size: f32,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self {
+            zenith: Color::rgb(0.25, 0.5, 0.9),
+            horizon: Color::rgb(0.7, 0.8, 0.95),
+            ground: Color::rgb(0.3, 0.28, 0.25),
+            sun_direction: Vec3::new(0.3, 0.8, 0.2).normalize(),
+            sun_color: Color::rgb(1.0, 0.95, 0.85),
+            sun_size: 0.6,
+        }
+    }
+}
+
+/// Sky colors at one point of the cycle
+struct SkyKey {
+    elevation: f32,
+    zenith: Color,
+    horizon: Color,
+    sun: Color,
+}
+
+/// Sky colors by sun elevation (sine of the sun's angle above the horizon)
+const SKY_KEYS: [SkyKey; 4] = [
+    SkyKey {
+        elevation: -0.3,
+        zenith: Color { r: 0.01, g: 0.01, b: 0.04, a: 1.0 },
+        horizon: Color { r: 0.03, g: 0.04, b: 0.09, a: 1.0 },
+        sun: Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 },
+    },
+    SkyKey {
+        elevation: -0.05,
+        zenith: Color { r: 0.08, g: 0.1, b: 0.25, a: 1.0 },
+        horizon: Color { r: 0.45, g: 0.25, b: 0.2, a: 1.0 },
+        sun: Color { r: 1.0, g: 0.3, b: 0.1, a: 1.0 },
+    },
+    SkyKey {
+        elevation: 0.1,
+        zenith: Color { r: 0.2, g: 0.35, b: 0.7, a: 1.0 },
+        horizon: Color { r: 0.95, g: 0.6, b: 0.35, a: 1.0 },
+        sun: Color { r: 1.0, g: 0.6, b: 0.3, a: 1.0 },
+    },
+    SkyKey {
+        elevation: 0.5,
+        zenith: Color { r: 0.25, g: 0.5, b: 0.9, a: 1.0 },
+        horizon: Color { r: 0.7, g: 0.8, b: 0.95, a: 1.0 },
+        sun: Color { r: 1.0, g: 0.95, b: 0.85, a: 1.0 },
+    },
+];
+
+/// Time-of-day driven sun, sky, and lighting
+#[derive(Debug, Clone)]
+pub struct DayNightCycle {
+    /// Current time in hours (0.0..24.0); 6:00 is sunrise and 18:00 sunset
+    pub time_of_day: f32,
+    /// Real seconds for a full 24 hour cycle
+    pub day_length: f32,
+    /// Stop advancing time
+    pub paused: bool,
+    /// Tilt of the sun's path away from straight overhead, in degrees
+    pub sun_tilt: f32,
+    /// Peak sunlight intensity
+    pub sun_intensity: f32,
+    /// Moonlight intensity at night
+    pub moon_intensity: f32,
+    /// Ground color below the horizon
+    pub ground_color: Color,
+    /// Color grading LUTs for (day, night), blended as the sun rises and sets
+    pub color_luts: Option<(ColorLutHandle, ColorLutHandle)>,
+}
+
+impl DayNightCycle {
+    /// Create a new cycle at the given hour
+    pub fn new(time_of_day: f32) -> Self {
+        Self {
+            time_of_day: time_of_day.rem_euclid(24.0),
+            day_length: 600.0,
+            paused: false,
+            sun_tilt: 30.0,
+            sun_intensity: 1.0,
+            moon_intensity: 0.15,
+            ground_color: Color::rgb(0.3, 0.28, 0.25),
+            color_luts: None,
+        }
+    }
+
+    /// Advance time by `delta` real seconds
+    pub fn update(&mut self, delta: f32) {
+        if self.paused || self.day_length <= 0.0 {
+            return;
+        }
+        self.time_of_day = (self.time_of_day + delta * 24.0 / self.day_length).rem_euclid(24.0);
+    }
+
+    /// Set the time of day in hours
+    pub fn set_time(&mut self, hours: f32) {
+        self.time_of_day = hours.rem_euclid(24.0);
+    }
+
+    /// Direction pointing towards the sun
+    pub fn sun_direction(&self) -> Vec3 {
+        // The sun rises in +X at 6:00, peaks at 12:00, and sets in -X at 18:00
+        let angle = (self.time_of_day - 6.0) / 24.0 * std::f32::consts::TAU;
+        let path = Vec3::new(angle.cos(), angle.sin(), 0.0);
+        Quat::from_rotation_x(self.sun_tilt.to_radians()) * path
+    }
+
+    /// Sine of the sun's angle above the horizon (negative at night)
+    pub fn sun_elevation(&self) -> f32 {
+        self.sun_direction().y
+    }
+
+    /// Check if the sun is above the horizon
+    pub fn is_day(&self) -> bool {
+        self.sun_elevation() > 0.0
+    }
+
+    /// How much the sun lights the scene, fading in over the horizon (0.0 at night)
+    pub fn daylight(&self) -> f32 {
+        smoothstep(-0.05, 0.15, self.sun_elevation())
+    }
+
+    /// Interpolated (zenith, horizon, sun) colors for the current elevation
+    fn colors(&self) -> (Color, Color, Color) {
+        let elevation = self.sun_elevation();
+        let first = &SKY_KEYS[0];
+        if elevation <= first.elevation {
+            return (first.zenith, first.horizon, first.sun);
+        }
+        for pair in SKY_KEYS.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            if elevation <= b.elevation {
+                let t = (elevation - a.elevation) / (b.elevation - a.elevation);
+                return (
+                    color_utils::lerp(a.zenith, b.zenith, t),
+                    color_utils::lerp(a.horizon, b.horizon, t),
+                    color_utils::lerp(a.sun, b.sun, t),
+                );
+            }
+        }
+        let last = &SKY_KEYS[SKY_KEYS.len() - 1];
+        (last.zenith, last.horizon, last.sun)
+    }
+
+    /// Get the sky for the current time
+    pub fn sky(&self) -> Sky {
+        let (zenith, horizon, sun_color) = self.colors();
+        Sky {
+            zenith,
+            horizon,
+            ground: color_utils::lerp(Color::BLACK, self.ground_color, (self.sun_elevation() + 0.3).clamp(0.1, 1.0)),
+            sun_direction: self.sun_direction(),
+            sun_color,
+            sun_size: 0.6,
+        }
+    }
+
+    /// Get the directional light (sun by day, moon by night) and ambient term
+    pub fn light(&self) -> DirectionalLight {
+        let sun = self.sun_direction();
+        let (zenith, horizon, sun_color) = self.colors();
+
+        // Fade the sun in over the horizon and the moon out
+        let day = self.daylight();
+        let ambient_strength = 0.05 + 0.3 * smoothstep(-0.3, 0.3, sun.y);
+        // Ambient takes its hue from the sky, normalized to the target strength
+        let sky = color_utils::lerp(zenith, horizon, 0.5);
+        let scale = ambient_strength / sky.r.max(sky.g).max(sky.b).max(1e-4);
+        let ambient = Color::rgb(sky.r * scale, sky.g * scale, sky.b * scale);
+
+        if day > 0.0 {
+            DirectionalLight {
+                direction: sun,
+                color: sun_color,
+                intensity: self.sun_intensity * day,
+                ambient,
+            }
+        } else {
+            DirectionalLight {
+                direction: -sun,
+                color: Color::rgb(0.6, 0.7, 1.0),
+                intensity: self.moon_intensity,
+                ambient,
+            }
+        }
+    }
+
+    /// Apply the light, sky, clear color, and color grading to a renderer
+    pub fn apply(&self, renderer: &mut Renderer) {
+        let sky = self.sky();
+        renderer.set_clear_color(sky.horizon);
+        renderer.set_light(self.light());
+        renderer.set_sky(Some(sky));
+        if let Some((day, night)) = self.color_luts {
+            renderer.set_color_grading(Some(ColorGrading::new(night).blended(day, self.daylight())));
+        }
+    }
+}
+
+impl Default for DayNightCycle {
+    fn default() -> Self {
+        Self::new(12.0)
+    }
+}
+
+/// Sky uniform buffer data
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct SkyUniform {
+    inv_view_proj: [[f32; 4]; 4],
+    sun_direction: [f32; 4],
+    sun_color: [f32; 4],
+    zenith: [f32; 4],
+    horizon: [f32; 4],
+    ground: [f32; 4],
+}
+
+/// GPU pipeline drawing the procedural sky as a fullscreen triangle
+pub(crate) struct SkyPipeline {
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl SkyPipeline {
+    pub(crate) fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sky Buffer"),
+            contents: bytemuck::cast_slice(&[SkyUniform::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sky_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sky_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sky Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/sky.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sky Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Sky Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            buffer,
+            bind_group,
+        }
+    }
+
+    /// Upload the sky parameters for this frame
+    pub(crate) fn update(&self, queue: &wgpu::Queue, sky: &Sky, view_proj: Mat4) {
+        let direction = sky.sun_direction.normalize_or_zero();
+        let uniform = SkyUniform {
+            inv_view_proj: view_proj.inverse().to_cols_array_2d(),
+            sun_direction: [direction.x, direction.y, direction.z, sky.sun_size.to_radians().cos()],
+            sun_color: sky.sun_color.to_array(),
+            zenith: sky.zenith.to_array(),
+            horizon: sky.horizon.to_array(),
+            ground: sky.ground.to_array(),
+        };
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Record the sky draw into a pass targeting the surface
+    pub(crate) fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sun_follows_time_of_day() {
+        let mut cycle = DayNightCycle::new(6.0);
+        assert!(cycle.sun_elevation().abs() < 1e-5);
+
+        cycle.set_time(12.0);
+        assert!(cycle.is_day());
+        let noon = cycle.light();
+        cycle.set_time(0.0);
+        assert!(!cycle.is_day());
+        let midnight = cycle.light();
+        assert!(noon.intensity > midnight.intensity);
+        assert!(noon.ambient.r + noon.ambient.g + noon.ambient.b > midnight.ambient.r + midnight.ambient.g + midnight.ambient.b);
+        assert!(midnight.direction.y > 0.0);
+
+        cycle.day_length = 24.0;
+        cycle.update(30.0);
+        assert!((cycle.time_of_day - 6.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,249 @@
+//! Scene snapshots
+//!
+//! `scene.snapshot()` saves every entity of a scene with its name, active flag,
+//! children, and the components the scene can clone (built-ins and types registered
+//! with `register_clone`). `scene.restore(&snapshot)` puts the scene back the way it
+//! was, for rewind mechanics, editor undo, or rollback netcode: `Scene` implements
+//! [`RollbackState`], so it can be driven by a `RollbackSession` directly.
+//!
+//! Snapshots are copy-on-write. Component values are shared between snapshots, and a
+//! new snapshot only copies the components added or mutably accessed since the
+//! previous one; restoring only writes back components changed since the snapshot.
+//! Both go by change ticks, so they're cheapest when `clear_trackers` runs every
+//! frame, as the engine does.
+
+use crate::ecs::{EntityId, Scene};
+use crate::hierarchy;
+use crate::registry::ComponentValue;
+use crate::rollback::RollbackState;
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// A saved entity
+struct SnapshotEntity {
+    name: String,
+    active: bool,
+    children: Vec<EntityId>,
+    /// Component values, shared with other snapshots they didn't change between
+    components: Vec<(TypeId, Rc<dyn ComponentValue>)>,
+}
+
+struct SnapshotData {
+    /// Layout of the scene the snapshot was taken from; ticks only compare within one
+    layout_id: u64,
+    /// Change tick the snapshot was taken at
+    tick: u32,
+    next_entity_id: EntityId,
+    entities: BTreeMap<EntityId, SnapshotEntity>,
+}
+
+/// Saved state of a scene's entities and clonable components
+///
+/// Cheap to clone: clones share the same data.
+#[derive(Clone)]
+pub struct SceneSnapshot(Rc<SnapshotData>);
+
+impl SceneSnapshot {
+    /// Number of saved entities
+    pub fn entity_count(&self) -> usize {
+        self.0.entities.len()
+    }
+
+    /// Check if an entity was saved
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.0.entities.contains_key(&id)
+    }
+
+    fn component(&self, id: EntityId, type_id: TypeId) -> Option<Rc<dyn ComponentValue>> {
+        let entity = self.0.entities.get(&id)?;
+        entity
+            .components
+            .iter()
+            .find(|(t, _)| *t == type_id)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+impl Scene {
+    /// Save the entities and clonable components of the scene
+    pub fn snapshot(&self) -> SceneSnapshot {
+        let previous = self
+            .last_snapshot
+            .borrow()
+            .clone()
+            .filter(|previous| previous.0.layout_id == self.layout_id);
+
+        let mut entities = BTreeMap::new();
+        for entity in self.entities() {
+            let id = entity.id();
+            let components = self
+                .cloners
+                .iter()
+                .filter_map(|(&type_id, clone)| {
+                    let ticks = self.component_ticks_by_type(id, type_id)?;
+                    // Untouched since the previous snapshot: share its copy
+                    let shared = previous
+                        .as_ref()
+                        .filter(|previous| ticks.changed < previous.0.tick)
+                        .and_then(|previous| previous.component(id, type_id));
+                    let value = match shared {
+                        Some(value) => value,
+                        None => Rc::from(clone(&entity)?),
+                    };
+                    Some((type_id, value))
+                })
+                .collect();
+            entities.insert(
+                id,
+                SnapshotEntity {
+                    name: entity.name().to_string(),
+                    active: entity.is_self_active(),
+                    children: self.children(id).to_vec(),
+                    components,
+                },
+            );
+        }
+
+        let snapshot = SceneSnapshot(Rc::new(SnapshotData {
+            layout_id: self.layout_id,
+            tick: self.change_tick,
+            next_entity_id: self.next_entity_id,
+            entities,
+        }));
+        *self.last_snapshot.borrow_mut() = Some(snapshot.clone());
+        snapshot
+    }
+
+    /// Put the scene back the way it was when `snapshot` was taken
+    ///
+    /// Entities created since are removed (without running removal hooks) and removed
+    /// ones come back with the same IDs. Components that can't be cloned weren't saved,
+    /// so entities that still exist keep theirs as they are.
+    pub fn restore(&mut self, snapshot: &SceneSnapshot) {
+        let data = &snapshot.0;
+        let same_layout = data.layout_id == self.layout_id;
+
+        let current: Vec<EntityId> = self.entities().map(|entity| entity.id()).collect();
+        for id in current.into_iter().filter(|id| !data.entities.contains_key(id)) {
+            self.detach_from_hierarchy(id);
+            self.remove_row(id);
+        }
+
+        let types: Vec<TypeId> = self.cloners.keys().copied().collect();
+        for (&id, saved) in &data.entities {
+            if !self.create_entity_with_id(id, saved.name.clone()) {
+                self.rename_entity(id, saved.name.clone());
+            }
+            for &type_id in &types {
+                let value = saved.components.iter().find(|(t, _)| *t == type_id);
+                match (value, self.component_ticks_by_type(id, type_id)) {
+                    // Not changed since the snapshot, so it still holds the saved value
+                    (Some(_), Some(ticks)) if same_layout && ticks.changed < data.tick => {}
+                    (Some((_, value)), _) => {
+                        if let Some(mut entity) = self.get_entity_mut(id) {
+                            value.insert_into(&mut entity);
+                        }
+                    }
+                    (None, Some(_)) => {
+                        self.remove_component_by_type(id, type_id);
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+        self.next_entity_id = data.next_entity_id;
+
+        for (&id, saved) in &data.entities {
+            if self.children(id) != saved.children.as_slice() {
+                for child in self.children(id).to_vec() {
+                    self.remove_parent(child);
+                }
+                for &child in &saved.children {
+                    self.set_parent(child, id);
+                }
+            }
+        }
+        for (&id, saved) in &data.entities {
+            if let Some(mut entity) = self.get_entity_mut(id) {
+                if entity.is_self_active() != saved.active {
+                    entity.set_active(saved.active);
+                }
+            }
+        }
+        hierarchy::propagate_transforms(self);
+    }
+}
+
+impl RollbackState for Scene {
+    type Snapshot = SceneSnapshot;
+
+    fn save_state(&self) -> SceneSnapshot {
+        self.snapshot()
+    }
+
+    fn load_state(&mut self, snapshot: &SceneSnapshot) {
+        self.restore(snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::Component;
+    use crate::math::Transform;
+    use glam::Vec3;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Health(i32);
+    impl Component for Health {}
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let mut scene = Scene::new("Test".to_string());
+        scene.register_clone::<Health>();
+        let ship = scene.spawn_named("Ship", (Transform::from_position(Vec3::X), Health(10)));
+        let turret = scene.spawn_named("Turret", Transform::new());
+        scene.set_parent(turret, ship);
+        scene.clear_trackers();
+        let snapshot = scene.snapshot();
+
+        scene.get_component_mut::<Health>(ship).unwrap().0 = 3;
+        scene.get_entity_mut(ship).unwrap().remove_component::<Transform>();
+        scene.despawn_recursive(turret);
+        let bullet = scene.spawn_named("Bullet", Health(1));
+        scene.get_entity_mut(ship).unwrap().set_active(false);
+
+        scene.restore(&snapshot);
+        assert_eq!(scene.entity_count(), 2);
+        assert!(scene.get_entity(bullet).is_none());
+        assert_eq!(scene.get_component::<Health>(ship), Some(&Health(10)));
+        assert_eq!(scene.get_component::<Transform>(ship).unwrap().position, Vec3::X);
+        assert_eq!(scene.children(ship), [turret]);
+        assert!(scene.get_entity(turret).unwrap().is_active());
+        assert_eq!(scene.create_entity("Next".to_string()), bullet);
+    }
+
+    #[test]
+    fn test_snapshots_share_unchanged_components() {
+        let mut scene = Scene::new("Test".to_string());
+        scene.register_clone::<Health>();
+        let ship = scene.spawn_named("Ship", (Transform::new(), Health(10)));
+        scene.clear_trackers();
+        let first = scene.snapshot();
+
+        scene.get_component_mut::<Health>(ship).unwrap().0 = 9;
+        scene.clear_trackers();
+        let second = scene.snapshot();
+
+        let shared = |type_id| {
+            Rc::ptr_eq(
+                &first.component(ship, type_id).unwrap(),
+                &second.component(ship, type_id).unwrap(),
+            )
+        };
+        assert!(shared(TypeId::of::<Transform>()));
+        assert!(!shared(TypeId::of::<Health>()));
+        assert_eq!(second.entity_count(), 1);
+    }
+}
@@ -0,0 +1,269 @@
+//! Sparse-set component storage
+//!
+//! Components normally live in archetype tables, which are fast to iterate but move
+//! the entity's whole row whenever a component is added or removed. Components that
+//! few entities have, or that come and go often (`Selected`, `Burning`, ...), can opt
+//! into a sparse set instead: a dense array of values plus an entity-to-index map.
+//! Adding or removing them never changes the entity's table.
+//!
+//! ```ignore
+//! #[derive(Component)]
+//! #[component(storage = "sparse")]
+//! struct Burning;
+//!
+//! // or by hand
+//! impl Component for Selected {
+//!     const STORAGE: StorageType = StorageType::Sparse;
+//! }
+//! ```
+//!
+//! Sparse components work everywhere table components do (queries, filters, change
+//! detection); queries check them per entity, so iterating them is slower.
+
+use crate::ecs::{ComponentTicks, EntityId};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// Type-erased sparse set of one component type
+trait SparseColumn: Any {
+    /// Drop an entity's value
+    fn remove(&mut self, id: EntityId) -> bool;
+    /// Ticks of an entity's value
+    fn ticks(&self, id: EntityId) -> Option<ComponentTicks>;
+    /// Move an entity's value into another storage under a new ID
+    fn transfer(&mut self, id: EntityId, target: &mut SparseStorage, new_id: EntityId, tick: u32);
+    /// Refresh the pointers used while iterating mutably
+    fn refresh_ptrs(&mut self);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// Values of one component type keyed by entity
+pub(crate) struct SparseSet<T> {
+    values: Vec<T>,
+    ticks: Vec<ComponentTicks>,
+    entities: Vec<EntityId>,
+    rows: HashMap<EntityId, usize>,
+    /// Data pointers, refreshed before iterating mutably
+    values_ptr: *mut T,
+    ticks_ptr: *mut ComponentTicks,
+}
+
+impl<T: 'static> SparseSet<T> {
+    fn new() -> Self {
+        Self {
+            values: Vec::new(),
+            ticks: Vec::new(),
+            entities: Vec::new(),
+            rows: HashMap::new(),
+            values_ptr: std::ptr::null_mut(),
+            ticks_ptr: std::ptr::null_mut(),
+        }
+    }
+
+    /// Insert or replace an entity's value
+    pub(crate) fn insert(&mut self, id: EntityId, value: T, tick: u32) {
+        match self.rows.get(&id) {
+            Some(&index) => {
+                self.values[index] = value;
+                self.ticks[index].changed = tick;
+            }
+            None => {
+                self.rows.insert(id, self.values.len());
+                self.values.push(value);
+                self.ticks.push(ComponentTicks::new(tick));
+                self.entities.push(id);
+            }
+        }
+    }
+
+    /// Remove and return an entity's value
+    pub(crate) fn take(&mut self, id: EntityId) -> Option<T> {
+        let index = self.rows.remove(&id)?;
+        self.ticks.swap_remove(index);
+        self.entities.swap_remove(index);
+        if let Some(&moved) = self.entities.get(index) {
+            self.rows.insert(moved, index);
+        }
+        Some(self.values.swap_remove(index))
+    }
+
+    /// Position of an entity's value in the dense arrays
+    pub(crate) fn index(&self, id: EntityId) -> Option<usize> {
+        self.rows.get(&id).copied()
+    }
+
+    pub(crate) fn contains(&self, id: EntityId) -> bool {
+        self.rows.contains_key(&id)
+    }
+
+    pub(crate) fn get(&self, id: EntityId) -> Option<&T> {
+        self.rows.get(&id).map(|&index| &self.values[index])
+    }
+
+    /// Get an entity's value, marking it changed at `tick`
+    pub(crate) fn get_mut(&mut self, id: EntityId, tick: u32) -> Option<&mut T> {
+        let index = *self.rows.get(&id)?;
+        self.ticks[index].changed = tick;
+        Some(&mut self.values[index])
+    }
+
+    pub(crate) fn ticks_mut(&mut self, id: EntityId) -> Option<&mut ComponentTicks> {
+        self.rows.get(&id).map(|&index| &mut self.ticks[index])
+    }
+
+    /// Entities that have a value
+    pub(crate) fn entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    /// Entity-to-index map, for lookups through raw pointers while iterating
+    pub(crate) fn rows(&self) -> &HashMap<EntityId, usize> {
+        &self.rows
+    }
+
+    /// Pointer to the first value, for read-only access
+    pub(crate) fn values_ptr(&self) -> *const T {
+        self.values.as_ptr()
+    }
+
+    /// Pointer to the first value's ticks, for read-only access
+    pub(crate) fn ticks_ptr(&self) -> *const ComponentTicks {
+        self.ticks.as_ptr()
+    }
+
+    /// Pointers for mutable access (call `refresh_ptrs` first)
+    pub(crate) fn mut_ptrs(&self) -> (*mut T, *mut ComponentTicks) {
+        (self.values_ptr, self.ticks_ptr)
+    }
+}
+
+impl<T: 'static> SparseColumn for SparseSet<T> {
+    fn remove(&mut self, id: EntityId) -> bool {
+        self.take(id).is_some()
+    }
+
+    fn ticks(&self, id: EntityId) -> Option<ComponentTicks> {
+        self.rows.get(&id).map(|&index| self.ticks[index])
+    }
+
+    fn transfer(&mut self, id: EntityId, target: &mut SparseStorage, new_id: EntityId, tick: u32) {
+        if let Some(value) = self.take(id) {
+            target.get_or_create::<T>().insert(new_id, value, tick);
+        }
+    }
+
+    fn refresh_ptrs(&mut self) {
+        self.values_ptr = self.values.as_mut_ptr();
+        self.ticks_ptr = self.ticks.as_mut_ptr();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The sparse sets of a scene, one per sparse component type
+#[derive(Default)]
+pub struct SparseStorage {
+    sets: HashMap<TypeId, Box<dyn SparseColumn>>,
+}
+
+impl SparseStorage {
+    pub(crate) fn get<T: 'static>(&self) -> Option<&SparseSet<T>> {
+        self.sets.get(&TypeId::of::<T>())?.as_any().downcast_ref()
+    }
+
+    pub(crate) fn get_mut<T: 'static>(&mut self) -> Option<&mut SparseSet<T>> {
+        self.sets.get_mut(&TypeId::of::<T>())?.as_any_mut().downcast_mut()
+    }
+
+    /// Get the set of a type, creating it on first use
+    pub(crate) fn get_or_create<T: 'static>(&mut self) -> &mut SparseSet<T> {
+        self.sets
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(SparseSet::<T>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("sparse set type matches its key")
+    }
+
+    /// Number of sparse component types seen so far
+    pub(crate) fn set_count(&self) -> usize {
+        self.sets.len()
+    }
+
+    /// Drop an entity's component of a type; false if it has none or the type isn't sparse
+    pub(crate) fn remove(&mut self, type_id: TypeId, id: EntityId) -> bool {
+        self.sets.get_mut(&type_id).is_some_and(|set| set.remove(id))
+    }
+
+    /// Ticks of an entity's component of a type
+    pub(crate) fn ticks(&self, type_id: TypeId, id: EntityId) -> Option<ComponentTicks> {
+        self.sets.get(&type_id)?.ticks(id)
+    }
+
+    /// Drop every sparse component of an entity
+    pub(crate) fn remove_entity(&mut self, id: EntityId) {
+        for set in self.sets.values_mut() {
+            set.remove(id);
+        }
+    }
+
+    /// Move every sparse component of an entity into another storage
+    pub(crate) fn transfer_entity(&mut self, id: EntityId, target: &mut SparseStorage, new_id: EntityId, tick: u32) {
+        for set in self.sets.values_mut() {
+            set.transfer(id, target, new_id, tick);
+        }
+    }
+
+    pub(crate) fn refresh_ptrs(&mut self) {
+        for set in self.sets.values_mut() {
+            set.refresh_ptrs();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::{Changed, Component, EntityId, Scene, With, Without};
+    use crate::math::Transform;
+
+    #[derive(Component, Debug, PartialEq)]
+    #[component(storage = "sparse")]
+    struct Burning(f32);
+
+    #[test]
+    fn test_sparse_components_keep_tables() {
+        let mut scene = Scene::new("Test".to_string());
+        let ids: Vec<EntityId> = (0..4)
+            .map(|i| scene.spawn_named(&format!("Crate {}", i), Transform::new()))
+            .collect();
+        let tables = scene.archetype_count();
+
+        for &id in &ids[..3] {
+            scene.get_entity_mut(id).unwrap().add_component(Burning(1.0));
+        }
+        assert!(scene.get_entity_mut(ids[1]).unwrap().remove_component::<Burning>());
+        assert_eq!(scene.archetype_count(), tables);
+
+        scene.clear_trackers();
+        for (_, burning) in scene.query_mut::<(&Transform, &mut Burning)>() {
+            burning.0 += 1.0;
+        }
+        let burning: Vec<EntityId> = scene.query_filtered::<EntityId, Changed<Burning>>().collect();
+        assert_eq!(burning.len(), 2);
+        assert!(!burning.contains(&ids[1]));
+        assert_eq!(scene.query_filtered::<EntityId, Without<Burning>>().count(), 2);
+        assert_eq!(scene.query_filtered::<EntityId, With<Burning>>().count(), 2);
+        assert_eq!(scene.get_component::<Burning>(ids[0]), Some(&Burning(2.0)));
+        assert_eq!(scene.query::<Option<&Burning>>().flatten().count(), 2);
+
+        scene.remove_entity(ids[0]);
+        assert_eq!(scene.find_entities_with::<Burning>(), [ids[2]]);
+    }
+}
@@ -0,0 +1,362 @@
+//! Steering behaviors for autonomous movement
+//!
+//! A [`Steering`] component holds a weighted list of [`Behavior`]s. Each
+//! update, [`update_steering`] sums the weighted steering forces, limits them,
+//! and integrates the result into the component's velocity (and optionally
+//! the entity's [`Transform`]).
+
+use crate::ecs::{Component, EntityId, Scene};
+use crate::math::Transform;
+use crate::utils::Random;
+use glam::{Quat, Vec3};
+use std::collections::HashMap;
+
+/// A single steering behavior
+#[derive(Debug, Clone, PartialEq)]
+pub enum Behavior {
+    /// Move towards a point at full speed
+    Seek(Vec3),
+    /// Move away from a point while closer than `panic_distance`
+    Flee { target: Vec3, panic_distance: f32 },
+    /// Move towards a point, slowing down inside `slowing_radius`
+    Arrive { target: Vec3, slowing_radius: f32 },
+    /// Random meandering using a jittered target on a circle ahead of the agent
+    Wander { distance: f32, radius: f32, jitter: f32 },
+    /// Seek the predicted future position of another entity
+    Pursue(EntityId),
+    /// Flee from the predicted future position of another entity
+    Evade { target: EntityId, panic_distance: f32 },
+    /// Keep distance from neighbours within `radius`
+    Separation { radius: f32 },
+    /// Match the heading of neighbours within `radius`
+    Alignment { radius: f32 },
+    /// Move towards the center of neighbours within `radius`
+    Cohesion { radius: f32 },
+}
+
+/// Steering agent state and its weighted behaviors
+#[derive(Debug, Clone)]
+pub struct Steering {
+    /// Weighted behaviors combined each update
+    pub behaviors: Vec<(Behavior, f32)>,
+    /// Maximum speed in units per second
+    pub max_speed: f32,
+    /// Maximum steering force (acceleration) in units per second squared
+    pub max_force: f32,
+    /// Seconds over which a behavior's desired velocity change is applied
+    pub response_time: f32,
+    /// Ignore the Y axis (ground-based crowds)
+    pub planar: bool,
+    /// Apply the velocity to the entity's transform and face the movement direction
+    pub move_transform: bool,
+    /// Current velocity (the behavior output)
+    pub velocity: Vec3,
+    wander_target: Vec3,
+    rng: Random,
+}
+
+impl Component for Steering {}
+
+impl Steering {
+    /// Create a new steering agent with no behaviors
+    pub fn new(max_speed: f32, max_force: f32) -> Self {
+        Self {
+            behaviors: Vec::new(),
+            max_speed,
+            max_force,
+            response_time: 0.25,
+            planar: true,
+            move_transform: true,
+            velocity: Vec3::ZERO,
+            wander_target: Vec3::Z,
+            rng: Random::from_time(),
+        }
+    }
+
+    /// Add a behavior with a weight
+    pub fn with(mut self, behavior: Behavior, weight: f32) -> Self {
+        self.behaviors.push((behavior, weight));
+        self
+    }
+
+    /// Seed the random generator used by [`Behavior::Wander`]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Random::new(seed);
+        self
+    }
+
+    /// Replace the weight of all behaviors matching `predicate`
+    pub fn set_weight<F: Fn(&Behavior) -> bool>(&mut self, predicate: F, weight: f32) {
+        for (behavior, w) in &mut self.behaviors {
+            if predicate(behavior) {
+                *w = weight;
+            }
+        }
+    }
+
+    /// Remove all behaviors
+    pub fn clear(&mut self) {
+        self.behaviors.clear();
+    }
+}
+
+/// Position and velocity of an agent during an update
+#[derive(Debug, Clone, Copy)]
+struct Kinematic {
+    position: Vec3,
+    velocity: Vec3,
+}
+
+/// Desired velocity towards `target` at full speed, as a steering force
+pub fn seek(position: Vec3, velocity: Vec3, target: Vec3, max_speed: f32) -> Vec3 {
+    (target - position).normalize_or_zero() * max_speed - velocity
+}
+
+/// Steering force away from `target`
+pub fn flee(position: Vec3, velocity: Vec3, target: Vec3, max_speed: f32) -> Vec3 {
+    (position - target).normalize_or_zero() * max_speed - velocity
+}
+
+/// Steering force towards `target`, decelerating inside `slowing_radius`
+pub fn arrive(position: Vec3, velocity: Vec3, target: Vec3, max_speed: f32, slowing_radius: f32) -> Vec3 {
+    let offset = target - position;
+    let distance = offset.length();
+    if distance < 1e-4 {
+        return -velocity;
+    }
+    let speed = if distance < slowing_radius {
+        max_speed * distance / slowing_radius
+    } else {
+        max_speed
+    };
+    offset / distance * speed - velocity
+}
+
+fn neighbors(
+    agents: &HashMap<EntityId, Kinematic>,
+    id: EntityId,
+    position: Vec3,
+    radius: f32,
+) -> impl Iterator<Item = &Kinematic> {
+    agents
+        .iter()
+        .filter(move |(other, k)| **other != id && k.position.distance_squared(position) < radius * radius)
+        .map(|(_, k)| k)
+}
+
+/// Predicted position of a target moving at constant velocity
+fn predict(agent: &Kinematic, target: &Kinematic, max_speed: f32) -> Vec3 {
+    let distance = agent.position.distance(target.position);
+    let lookahead = distance / (max_speed + target.velocity.length()).max(1e-4);
+    target.position + target.velocity * lookahead
+}
+
+fn behavior_force(
+    behavior: &Behavior,
+    id: EntityId,
+    agent: Kinematic,
+    steering: &mut Steering,
+    agents: &HashMap<EntityId, Kinematic>,
+    positions: &HashMap<EntityId, Vec3>,
+) -> Vec3 {
+    let max_speed = steering.max_speed;
+    let Kinematic { position, velocity } = agent;
+
+    match behavior {
+        Behavior::Seek(target) => seek(position, velocity, *target, max_speed),
+        Behavior::Flee { target, panic_distance } => {
+            if position.distance(*target) < *panic_distance {
+                flee(position, velocity, *target, max_speed)
+            } else {
+                Vec3::ZERO
+            }
+        }
+        Behavior::Arrive { target, slowing_radius } => arrive(position, velocity, *target, max_speed, *slowing_radius),
+        Behavior::Wander { distance, radius, jitter } => {
+            let rng = &mut steering.rng;
+            let mut offset = Vec3::new(
+                rng.gen_range_f32(-1.0, 1.0),
+                rng.gen_range_f32(-1.0, 1.0),
+                rng.gen_range_f32(-1.0, 1.0),
+            ) * *jitter;
+            if steering.planar {
+                offset.y = 0.0;
+            }
+            steering.wander_target = (steering.wander_target + offset).normalize_or(Vec3::Z) * *radius;
+            let heading = velocity.normalize_or(Vec3::Z);
+            let target = position + heading * *distance + steering.wander_target;
+            seek(position, velocity, target, max_speed)
+        }
+        Behavior::Pursue(target) => match agents.get(target) {
+            Some(other) => seek(position, velocity, predict(&agent, other, max_speed), max_speed),
+            None => positions
+                .get(target)
+                .map_or(Vec3::ZERO, |p| seek(position, velocity, *p, max_speed)),
+        },
+        Behavior::Evade { target, panic_distance } => {
+            let predicted = match agents.get(target) {
+                Some(other) => predict(&agent, other, max_speed),
+                None => match positions.get(target) {
+                    Some(p) => *p,
+                    None => return Vec3::ZERO,
+                },
+            };
+            if position.distance(predicted) < *panic_distance {
+                flee(position, velocity, predicted, max_speed)
+            } else {
+                Vec3::ZERO
+            }
+        }
+        Behavior::Separation { radius } => {
+            let mut force = Vec3::ZERO;
+            for other in neighbors(agents, id, position, *radius) {
+                let away = position - other.position;
+                let distance = away.length().max(1e-4);
+                // Stronger push the closer the neighbour is
+                force += away / (distance * distance);
+            }
+            if force == Vec3::ZERO {
+                Vec3::ZERO
+            } else {
+                force.normalize() * max_speed - velocity
+            }
+        }
+        Behavior::Alignment { radius } => {
+            let (sum, count) = neighbors(agents, id, position, *radius)
+                .fold((Vec3::ZERO, 0), |(sum, count), other| (sum + other.velocity, count + 1));
+            if count == 0 {
+                Vec3::ZERO
+            } else {
+                (sum / count as f32).normalize_or_zero() * max_speed - velocity
+            }
+        }
+        Behavior::Cohesion { radius } => {
+            let (sum, count) = neighbors(agents, id, position, *radius)
+                .fold((Vec3::ZERO, 0), |(sum, count), other| (sum + other.position, count + 1));
+            if count == 0 {
+                Vec3::ZERO
+            } else {
+                seek(position, velocity, sum / count as f32, max_speed)
+            }
+        }
+    }
+}
+
+/// Update every active entity with a [`Steering`] and [`Transform`]
+pub fn update_steering(scene: &mut Scene, delta: f32) {
+    if delta <= 0.0 {
+        return;
+    }
+
+    // Snapshot of all agents so behaviors see a consistent world
+    let agents: HashMap<EntityId, Kinematic> = scene
+        .active_entities()
+        .filter_map(|e| {
+            let steering = e.get_component::<Steering>()?;
+            let transform = e.get_component::<Transform>()?;
+            Some((
+                e.id(),
+                Kinematic {
+                    position: transform.position,
+                    velocity: steering.velocity,
+                },
+            ))
+        })
+        .collect();
+
+    // Pursue/Evade may target entities without a Steering component
+    let positions: HashMap<EntityId, Vec3> = scene
+        .active_entities()
+        .filter_map(|e| Some((e.id(), e.get_component::<Transform>()?.position)))
+        .collect();
+
+    for (id, agent) in &agents {
+        let Some(mut entity) = scene.get_entity_mut(*id) else {
+            continue;
+        };
+        let Some(steering) = entity.get_component_mut::<Steering>() else {
+            continue;
+        };
+
+        let behaviors = std::mem::take(&mut steering.behaviors);
+        let mut force = Vec3::ZERO;
+        for (behavior, weight) in &behaviors {
+            force += behavior_force(behavior, *id, *agent, steering, &agents, &positions) * *weight;
+        }
+        steering.behaviors = behaviors;
+
+        if steering.planar {
+            force.y = 0.0;
+        }
+        let force = (force / steering.response_time.max(delta)).clamp_length_max(steering.max_force);
+        steering.velocity = (steering.velocity + force * delta).clamp_length_max(steering.max_speed);
+        if steering.planar {
+            steering.velocity.y = 0.0;
+        }
+
+        let velocity = steering.velocity;
+        if !steering.move_transform {
+            continue;
+        }
+
+        if let Some(transform) = entity.get_component_mut::<Transform>() {
+            transform.position += velocity * delta;
+            // Face the direction of travel (forward is -Z)
+            if let Some(heading) = Vec3::new(velocity.x, 0.0, velocity.z).try_normalize() {
+                transform.rotation = Quat::from_rotation_arc(Vec3::NEG_Z, heading);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arrive_stops_at_target() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Agent".to_string());
+        let mut entity = scene.get_entity_mut(id).unwrap();
+        entity.add_component(Transform::new());
+        let target = Vec3::new(10.0, 0.0, 5.0);
+        entity.add_component(
+            Steering::new(5.0, 20.0).with(
+                Behavior::Arrive {
+                    target,
+                    slowing_radius: 3.0,
+                },
+                1.0,
+            ),
+        );
+
+        for _ in 0..600 {
+            update_steering(&mut scene, 1.0 / 60.0);
+        }
+
+        let entity = scene.get_entity(id).unwrap();
+        let position = entity.get_component::<Transform>().unwrap().position;
+        assert!(position.distance(target) < 0.05);
+        assert!(entity.get_component::<Steering>().unwrap().velocity.length() < 0.1);
+    }
+
+    #[test]
+    fn test_separation_pushes_apart() {
+        let mut scene = Scene::new("Test".to_string());
+        let mut ids = Vec::new();
+        for x in [0.0, 0.5] {
+            let id = scene.create_entity("Boid".to_string());
+            let mut entity = scene.get_entity_mut(id).unwrap();
+            entity.add_component(Transform::from_position(Vec3::new(x, 0.0, 0.0)));
+            entity.add_component(Steering::new(3.0, 10.0).with(Behavior::Separation { radius: 2.0 }, 1.0));
+            ids.push(id);
+        }
+
+        for _ in 0..60 {
+            update_steering(&mut scene, 1.0 / 60.0);
+        }
+
+        let position = |id| scene.get_entity(id).unwrap().get_component::<Transform>().unwrap().position;
+        assert!(position(ids[0]).distance(position(ids[1])) > 2.0);
+    }
+}
@@ -0,0 +1,779 @@
+//! Heightmap terrain
+//!
+//! Loads heightmaps from images, answers height/normal queries for placing
+//! and grounding entities, and builds chunk meshes with quadtree LOD: nodes
+//! near the camera are subdivided into finer chunks, distant ones stay coarse.
+//! Every chunk mesh has the same vertex count, and skirts along its edges hide
+//! cracks between neighbouring LOD levels.
+//!
+//! Texture splatting stores four layer weights in the vertex color, which the
+//! bundled terrain shader ([`TERRAIN_SHADER`]) uses to blend tiled textures.
+//! [`Renderer::set_terrain`](crate::renderer::Renderer::set_terrain) updates and
+//! draws a terrain with it every frame.
+
+use crate::math::Frustum;
+use crate::renderer::{RenderStats, Vertex, DEPTH_FORMAT};
+use crate::resource::Mesh;
+use glam::{Vec2, Vec3};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use wgpu::util::DeviceExt;
+
+/// WGSL source of the splatting terrain shader
+pub const TERRAIN_SHADER: &str = include_str!("shaders/terrain.wgsl");
+
+/// A grid of height samples in the range 0.0..=1.0
+#[derive(Debug, Clone)]
+pub struct Heightmap {
+    width: u32,
+    depth: u32,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Create a heightmap from raw samples (row-major, `width` × `depth`)
+    pub fn new(width: u32, depth: u32, heights: Vec<f32>) -> Result<Self, String> {
+        if width < 2 || depth < 2 {
+            return Err(format!("Heightmap must be at least 2x2, got {}x{}", width, depth));
+        }
+        if heights.len() != (width * depth) as usize {
+            return Err(format!(
+                "Heightmap expects {} samples, got {}",
+                width * depth,
+                heights.len()
+            ));
+        }
+        Ok(Self { width, depth, heights })
+    }
+
+    /// Create a flat heightmap
+    pub fn flat(width: u32, depth: u32) -> Self {
+        Self {
+            width: width.max(2),
+            depth: depth.max(2),
+            heights: vec![0.0; (width.max(2) * depth.max(2)) as usize],
+        }
+    }
+
+    /// Create a heightmap by evaluating `f(x, z)` for each sample
+    pub fn from_fn<F: Fn(u32, u32) -> f32>(width: u32, depth: u32, f: F) -> Self {
+        let mut heightmap = Self::flat(width, depth);
+        for z in 0..heightmap.depth {
+            for x in 0..heightmap.width {
+                heightmap.set(x, z, f(x, z));
+            }
+        }
+        heightmap
+    }
+
+    /// Load a grayscale heightmap image (8 or 16 bit)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let image = image::open(path.as_ref())
+            .map_err(|e| format!("Failed to load heightmap: {}", e))?
+            .into_luma16();
+        let (width, depth) = image.dimensions();
+        let heights = image.pixels().map(|p| p.0[0] as f32 / u16::MAX as f32).collect();
+
+        log::info!("Loaded heightmap: {:?} ({}x{})", path.as_ref(), width, depth);
+        Self::new(width, depth, heights)
+    }
+
+    /// Get the number of samples along X
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the number of samples along Z
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Get a sample (coordinates are clamped to the edges)
+    pub fn get(&self, x: i64, z: i64) -> f32 {
+        let x = x.clamp(0, self.width as i64 - 1) as usize;
+        let z = z.clamp(0, self.depth as i64 - 1) as usize;
+        self.heights[z * self.width as usize + x]
+    }
+
+    /// Set a sample
+    pub fn set(&mut self, x: u32, z: u32, height: f32) {
+        if x < self.width && z < self.depth {
+            self.heights[(z * self.width + x) as usize] = height;
+        }
+    }
+
+    /// Bilinearly interpolate at fractional sample coordinates
+    pub fn sample(&self, x: f32, z: f32) -> f32 {
+        let x0 = x.floor();
+        let z0 = z.floor();
+        let fx = x - x0;
+        let fz = z - z0;
+        let (x0, z0) = (x0 as i64, z0 as i64);
+
+        let h00 = self.get(x0, z0);
+        let h10 = self.get(x0 + 1, z0);
+        let h01 = self.get(x0, z0 + 1);
+        let h11 = self.get(x0 + 1, z0 + 1);
+        let top = h00 + (h10 - h00) * fx;
+        let bottom = h01 + (h11 - h01) * fx;
+        top + (bottom - top) * fz
+    }
+}
+
+/// Per-sample weights for the four splat layers
+#[derive(Debug, Clone)]
+pub struct SplatMap {
+    width: u32,
+    depth: u32,
+    weights: Vec<[f32; 4]>,
+}
+
+/// Rule assigning a splat layer by height and slope
+#[derive(Debug, Clone, Copy)]
+pub struct SplatRule {
+    /// Layer index (0-3)
+    pub layer: usize,
+    /// World height range where the layer appears
+    pub min_height: f32,
+    pub max_height: f32,
+    /// Maximum slope in degrees
+    pub max_slope: f32,
+}
+
+impl SplatMap {
+    /// Load a splat map from an RGBA image (one channel per layer)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let image = image::open(path.as_ref())
+            .map_err(|e| format!("Failed to load splat map: {}", e))?
+            .into_rgba8();
+        let (width, depth) = image.dimensions();
+        let weights = image
+            .pixels()
+            .map(|p| p.0.map(|c| c as f32 / 255.0))
+            .collect();
+        Ok(Self { width, depth, weights })
+    }
+
+    /// Generate weights from height/slope rules; later rules take priority
+    pub fn from_rules(terrain: &Terrain, rules: &[SplatRule]) -> Self {
+        let width = terrain.heightmap.width;
+        let depth = terrain.heightmap.depth;
+        let mut weights = Vec::with_capacity((width * depth) as usize);
+
+        for z in 0..depth {
+            for x in 0..width {
+                let position = terrain.sample_position(x as f32, z as f32);
+                let slope = terrain.sample_normal(x as f32, z as f32).y.acos().to_degrees();
+                let mut weight = [0.0; 4];
+                weight[0] = 1.0;
+                for rule in rules {
+                    if rule.layer < 4
+                        && position.y >= rule.min_height
+                        && position.y <= rule.max_height
+                        && slope <= rule.max_slope
+                    {
+                        weight = [0.0; 4];
+                        weight[rule.layer] = 1.0;
+                    }
+                }
+                weights.push(weight);
+            }
+        }
+        Self { width, depth, weights }
+    }
+
+    /// Get the weights at normalized coordinates (0..1 across the terrain)
+    pub fn weights_at(&self, u: f32, v: f32) -> [f32; 4] {
+        let x = ((u * (self.width - 1) as f32).round() as u32).min(self.width - 1);
+        let z = ((v * (self.depth - 1) as f32).round() as u32).min(self.depth - 1);
+        self.weights[(z * self.width + x) as usize]
+    }
+}
+
+/// Terrain placement and LOD settings
+#[derive(Debug, Clone, Copy)]
+pub struct TerrainConfig {
+    /// World position of the heightmap's (0, 0) corner
+    pub origin: Vec3,
+    /// World size along X and Z
+    pub size: Vec2,
+    /// World height of a 1.0 heightmap sample
+    pub height_scale: f32,
+    /// Quads per chunk edge (power of two)
+    pub chunk_resolution: u32,
+    /// Subdivide a node while the camera is closer than its size times this factor
+    pub lod_factor: f32,
+    /// Depth of the skirts that hide cracks between LOD levels
+    pub skirt_depth: f32,
+}
+
+impl Default for TerrainConfig {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::ZERO,
+            size: Vec2::new(256.0, 256.0),
+            height_scale: 32.0,
+            chunk_resolution: 32,
+            lod_factor: 1.5,
+            skirt_depth: 2.0,
+        }
+    }
+}
+
+/// A quadtree node selected for rendering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TerrainNode {
+    /// First heightmap sample covered along X
+    pub x: u32,
+    /// First heightmap sample covered along Z
+    pub z: u32,
+    /// Samples covered along each axis
+    pub size: u32,
+    /// LOD level (0 = finest)
+    pub lod: u32,
+}
+
+/// Chunks that appeared or disappeared after an LOD update
+#[derive(Debug, Clone, Default)]
+pub struct TerrainLodChange {
+    pub added: Vec<TerrainNode>,
+    pub removed: Vec<TerrainNode>,
+}
+
+/// Heightmap terrain with height queries and LOD chunk meshes
+pub struct Terrain {
+    heightmap: Heightmap,
+    config: TerrainConfig,
+    splat: Option<SplatMap>,
+    visible: Vec<TerrainNode>,
+    meshes: HashMap<TerrainNode, Mesh>,
+}
+
+impl Terrain {
+    /// Create a terrain from a heightmap
+    pub fn new(heightmap: Heightmap, mut config: TerrainConfig) -> Self {
+        config.chunk_resolution = config.chunk_resolution.max(1).next_power_of_two();
+        Self {
+            heightmap,
+            config,
+            splat: None,
+            visible: Vec::new(),
+            meshes: HashMap::new(),
+        }
+    }
+
+    /// Get the heightmap
+    pub fn heightmap(&self) -> &Heightmap {
+        &self.heightmap
+    }
+
+    /// Get the terrain settings
+    pub fn config(&self) -> &TerrainConfig {
+        &self.config
+    }
+
+    /// Set the splat weights used for vertex colors (rebuilds chunk meshes)
+    pub fn set_splat_map(&mut self, splat: SplatMap) {
+        self.splat = Some(splat);
+        self.meshes.clear();
+    }
+
+    /// World size of one heightmap cell along X and Z
+    fn cell_size(&self) -> Vec2 {
+        Vec2::new(
+            self.config.size.x / (self.heightmap.width - 1) as f32,
+            self.config.size.y / (self.heightmap.depth - 1) as f32,
+        )
+    }
+
+    /// Convert world XZ to fractional sample coordinates
+    fn to_sample(&self, x: f32, z: f32) -> Vec2 {
+        (Vec2::new(x, z) - Vec2::new(self.config.origin.x, self.config.origin.z)) / self.cell_size()
+    }
+
+    /// Check if a world XZ position lies over the terrain
+    pub fn contains(&self, x: f32, z: f32) -> bool {
+        let local = Vec2::new(x - self.config.origin.x, z - self.config.origin.z);
+        local.x >= 0.0 && local.y >= 0.0 && local.x <= self.config.size.x && local.y <= self.config.size.y
+    }
+
+    /// Get the terrain height at a world XZ position
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        if !self.contains(x, z) {
+            return None;
+        }
+        let s = self.to_sample(x, z);
+        Some(self.config.origin.y + self.heightmap.sample(s.x, s.y) * self.config.height_scale)
+    }
+
+    /// Get the surface normal at a world XZ position
+    pub fn normal_at(&self, x: f32, z: f32) -> Option<Vec3> {
+        if !self.contains(x, z) {
+            return None;
+        }
+        let s = self.to_sample(x, z);
+        Some(self.sample_normal(s.x, s.y))
+    }
+
+    /// Move a position onto the terrain surface (unchanged if outside the terrain)
+    pub fn ground(&self, position: Vec3) -> Vec3 {
+        match self.height_at(position.x, position.z) {
+            Some(height) => Vec3::new(position.x, height, position.z),
+            None => position,
+        }
+    }
+
+    fn sample_position(&self, sx: f32, sz: f32) -> Vec3 {
+        let cell = self.cell_size();
+        Vec3::new(
+            self.config.origin.x + sx * cell.x,
+            self.config.origin.y + self.heightmap.sample(sx, sz) * self.config.height_scale,
+            self.config.origin.z + sz * cell.y,
+        )
+    }
+
+    fn sample_normal(&self, sx: f32, sz: f32) -> Vec3 {
+        // Central differences over one cell
+        let cell = self.cell_size();
+        let scale = self.config.height_scale;
+        let dx = (self.heightmap.sample(sx + 1.0, sz) - self.heightmap.sample(sx - 1.0, sz)) * scale;
+        let dz = (self.heightmap.sample(sx, sz + 1.0) - self.heightmap.sample(sx, sz - 1.0)) * scale;
+        Vec3::new(-dx / (2.0 * cell.x), 1.0, -dz / (2.0 * cell.y)).normalize()
+    }
+
+    /// Size in samples of the quadtree root node
+    fn root_size(&self) -> u32 {
+        let cells = (self.heightmap.width - 1).max(self.heightmap.depth - 1);
+        cells.max(self.config.chunk_resolution).next_power_of_two()
+    }
+
+    /// Select the quadtree nodes to draw for a camera position
+    pub fn select_lod(&self, camera: Vec3) -> Vec<TerrainNode> {
+        let root = self.root_size();
+        let levels = (root / self.config.chunk_resolution).trailing_zeros();
+        let mut nodes = Vec::new();
+        self.select_node(camera, 0, 0, root, levels, &mut nodes);
+        nodes
+    }
+
+    fn select_node(&self, camera: Vec3, x: u32, z: u32, size: u32, lod: u32, out: &mut Vec<TerrainNode>) {
+        // Skip nodes entirely outside the heightmap
+        if x >= self.heightmap.width - 1 || z >= self.heightmap.depth - 1 {
+            return;
+        }
+
+        let cell = self.cell_size();
+        let min = Vec2::new(x as f32 * cell.x, z as f32 * cell.y) + Vec2::new(self.config.origin.x, self.config.origin.z);
+        let max = min + Vec2::new(size as f32 * cell.x, size as f32 * cell.y);
+        let closest = Vec2::new(camera.x, camera.z).clamp(min, max);
+        let height = self.height_at(closest.x, closest.y).unwrap_or(self.config.origin.y);
+        let distance = Vec3::new(closest.x, height, closest.y).distance(camera);
+        let extent = (max - min).max_element();
+
+        if lod > 0 && distance < extent * self.config.lod_factor {
+            let half = size / 2;
+            for (dx, dz) in [(0, 0), (half, 0), (0, half), (half, half)] {
+                self.select_node(camera, x + dx, z + dz, half, lod - 1, out);
+            }
+        } else {
+            out.push(TerrainNode { x, z, size, lod });
+        }
+    }
+
+    /// Update the visible chunk set for a camera, building meshes for new chunks
+    pub fn update(&mut self, camera: Vec3) -> TerrainLodChange {
+        let selected = self.select_lod(camera);
+        let old: HashSet<TerrainNode> = self.visible.iter().copied().collect();
+        let new: HashSet<TerrainNode> = selected.iter().copied().collect();
+
+        let change = TerrainLodChange {
+            added: selected.iter().filter(|n| !old.contains(n)).copied().collect(),
+            removed: self.visible.iter().filter(|n| !new.contains(n)).copied().collect(),
+        };
+
+        for node in &change.removed {
+            self.meshes.remove(node);
+        }
+        // A new splat map drops every mesh, not just the added chunks'
+        for node in &selected {
+            if !self.meshes.contains_key(node) {
+                let mesh = self.build_chunk_mesh(node);
+                self.meshes.insert(*node, mesh);
+            }
+        }
+        self.visible = selected;
+        change
+    }
+
+    /// Create GPU buffers for the visible chunk meshes that don't have them yet
+    pub fn upload_chunks(&mut self, device: &wgpu::Device) {
+        for mesh in self.meshes.values_mut().filter(|m| m.vertex_buffer.is_none()) {
+            mesh.create_buffers(device);
+        }
+    }
+
+    /// Get the currently visible chunks
+    pub fn visible_chunks(&self) -> &[TerrainNode] {
+        &self.visible
+    }
+
+    /// Get the mesh of a visible chunk
+    pub fn chunk_mesh(&self, node: &TerrainNode) -> Option<&Mesh> {
+        self.meshes.get(node)
+    }
+
+    /// Get mutable access to a chunk mesh (e.g. to create GPU buffers)
+    pub fn chunk_mesh_mut(&mut self, node: &TerrainNode) -> Option<&mut Mesh> {
+        self.meshes.get_mut(node)
+    }
+
+    /// Build the mesh for a quadtree node, including edge skirts
+    pub fn build_chunk_mesh(&self, node: &TerrainNode) -> Mesh {
+        let resolution = self.config.chunk_resolution;
+        let step = node.size as f32 / resolution as f32;
+        let max_x = (self.heightmap.width - 1) as f32;
+        let max_z = (self.heightmap.depth - 1) as f32;
+        let row = resolution + 1;
+
+        let vertex = |i: u32, j: u32, drop: f32| {
+            let sx = (node.x as f32 + i as f32 * step).min(max_x);
+            let sz = (node.z as f32 + j as f32 * step).min(max_z);
+            let mut position = self.sample_position(sx, sz);
+            position.y -= drop;
+            let u = sx / max_x;
+            let v = sz / max_z;
+            let color = self.splat.as_ref().map_or([1.0, 0.0, 0.0, 0.0], |s| s.weights_at(u, v));
+            Vertex {
+                position: position.to_array(),
+                tex_coords: [u, v],
+                normal: self.sample_normal(sx, sz).to_array(),
+                color,
+                tangent: [1.0, 0.0, 0.0, 1.0],
+            }
+        };
+
+        let mut vertices = Vec::with_capacity((row * row + 4 * row) as usize);
+        let mut indices = Vec::with_capacity((resolution * resolution * 6 + 4 * resolution * 6) as usize);
+
+        for j in 0..row {
+            for i in 0..row {
+                vertices.push(vertex(i, j, 0.0));
+            }
+        }
+        for j in 0..resolution {
+            for i in 0..resolution {
+                let a = j * row + i;
+                let b = a + 1;
+                let c = a + row;
+                let d = c + 1;
+                indices.extend_from_slice(&[a, c, b, b, c, d]);
+            }
+        }
+
+        // Skirts: a strip hanging down from each edge
+        // Walk each edge of the chunk in order, by grid coordinates
+        let edge_point = |edge: u32, k: u32| match edge {
+            0 => (k, 0),
+            1 => (resolution, k),
+            2 => (resolution - k, resolution),
+            _ => (0, resolution - k),
+        };
+        for edge in 0..4 {
+            let base = vertices.len() as u32;
+            for k in 0..row {
+                let (i, j) = edge_point(edge, k);
+                vertices.push(vertex(i, j, self.config.skirt_depth));
+            }
+            for k in 0..resolution {
+                let (i0, j0) = edge_point(edge, k);
+                let (i1, j1) = edge_point(edge, k + 1);
+                let top0 = j0 * row + i0;
+                let top1 = j1 * row + i1;
+                let bottom0 = base + k;
+                let bottom1 = base + k + 1;
+                indices.extend_from_slice(&[top0, top1, bottom0, top1, bottom1, bottom0]);
+            }
+        }
+
+        Mesh::new(vertices, indices)
+    }
+}
+
+/// GPU resources for the splatting terrain shader (bind group 1)
+pub struct TerrainMaterial {
+    pub bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+}
+
+impl TerrainMaterial {
+    /// Create the bind group layout used by [`TERRAIN_SHADER`] at group 1
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Terrain Material Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture_entry(2),
+                texture_entry(3),
+                texture_entry(4),
+                texture_entry(5),
+            ],
+        })
+    }
+
+    /// Create a material from four layer textures and their tiling factors
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        layers: [&wgpu::TextureView; 4],
+        tiling: [f32; 4],
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Terrain Uniform Buffer"),
+            contents: bytemuck::cast_slice(&tiling),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Terrain Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Terrain Material"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(layers[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(layers[1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(layers[2]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(layers[3]),
+                },
+            ],
+        });
+
+        Self {
+            bind_group,
+            uniform_buffer,
+        }
+    }
+
+    /// Update the per-layer tiling factors
+    pub fn set_tiling(&self, queue: &wgpu::Queue, tiling: [f32; 4]) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&tiling));
+    }
+}
+
+/// Draws terrain chunks with [`TERRAIN_SHADER`], sharing the scene's camera bind group
+pub(crate) struct TerrainPipeline {
+    pipeline: wgpu::RenderPipeline,
+    material_layout: wgpu::BindGroupLayout,
+    material: TerrainMaterial,
+}
+
+impl TerrainPipeline {
+    /// Create the pipeline with a material of four `default_layer` textures
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        camera_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        default_layer: &wgpu::TextureView,
+    ) -> Self {
+        let material_layout = TerrainMaterial::bind_group_layout(device);
+        let material = TerrainMaterial::new(device, &material_layout, [default_layer; 4], [1.0; 4]);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Terrain Shader"),
+            source: wgpu::ShaderSource::Wgsl(TERRAIN_SHADER.into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Terrain Pipeline Layout"),
+            bind_group_layouts: &[camera_layout, &material_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Terrain Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            material_layout,
+            material,
+        }
+    }
+
+    /// Replace the layer textures and tiling factors
+    pub(crate) fn set_layers(&mut self, device: &wgpu::Device, layers: [&wgpu::TextureView; 4], tiling: [f32; 4]) {
+        self.material = TerrainMaterial::new(device, &self.material_layout, layers, tiling);
+    }
+
+    /// Draw the visible chunks with uploaded meshes; the camera must be bound at group 0
+    pub(crate) fn draw<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        terrain: &'a Terrain,
+        frustum: Option<&Frustum>,
+        stats: &mut RenderStats,
+    ) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(1, &self.material.bind_group, &[]);
+        for node in terrain.visible_chunks() {
+            let Some(mesh) = terrain.chunk_mesh(node) else {
+                continue;
+            };
+            let (Some(vertex_buffer), Some(index_buffer)) = (&mesh.vertex_buffer, &mesh.index_buffer) else {
+                continue;
+            };
+            if frustum.is_some_and(|f| !f.intersects_aabb(&mesh.bounds)) {
+                stats.culled += 1;
+                continue;
+            }
+            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..1);
+            stats.drawn += 1;
+            stats.triangles += (mesh.indices.len() / 3) as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slope_terrain() -> Terrain {
+        // Rises linearly along X from 0 to 1 across 65 samples
+        let heightmap = Heightmap::from_fn(65, 65, |x, _| x as f32 / 64.0);
+        Terrain::new(
+            heightmap,
+            TerrainConfig {
+                size: Vec2::new(64.0, 64.0),
+                height_scale: 16.0,
+                chunk_resolution: 8,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_height_and_normal_queries() {
+        let terrain = slope_terrain();
+        assert!((terrain.height_at(32.0, 10.0).unwrap() - 8.0).abs() < 1e-4);
+        assert!((terrain.height_at(16.5, 3.0).unwrap() - 4.125).abs() < 1e-4);
+        assert_eq!(terrain.height_at(-1.0, 0.0), None);
+
+        let normal = terrain.normal_at(32.0, 32.0).unwrap();
+        let expected = Vec3::new(-0.25, 1.0, 0.0).normalize();
+        assert!(normal.distance(expected) < 1e-4);
+    }
+
+    #[test]
+    fn test_lod_refines_near_camera() {
+        let mut terrain = slope_terrain();
+        let change = terrain.update(Vec3::new(1.0, 20.0, 1.0));
+        let nodes = terrain.visible_chunks();
+
+        // Chunks tile the heightmap exactly once
+        let covered: u32 = nodes.iter().map(|n| n.size * n.size).sum();
+        assert_eq!(covered, 64 * 64);
+        assert_eq!(change.added.len(), nodes.len());
+
+        let near = nodes.iter().find(|n| n.x == 0 && n.z == 0).unwrap();
+        let far = nodes.iter().find(|n| n.x + n.size == 64 && n.z + n.size == 64).unwrap();
+        assert!(near.lod < far.lod);
+
+        let mesh = terrain.chunk_mesh(near).unwrap();
+        assert_eq!(mesh.vertices.len(), 9 * 9 + 4 * 9);
+    }
+}
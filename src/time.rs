@@ -79,6 +79,11 @@ impl TimeManager {
         self.frame_count
     }
 
+    /// Ignore the time since the last frame (e.g. after the app was suspended)
+    pub fn skip_gap(&mut self) {
+        self.last_frame = Instant::now();
+    }
+
     /// Reset the time manager
     pub fn reset(&mut self) {
         let now = Instant::now();
@@ -0,0 +1,479 @@
+//! Line and trail renderer components
+//!
+//! [`LineRenderer`] draws a polyline and [`TrailRenderer`] leaves a fading trail
+//! behind an entity's [`Transform`]. Both are drawn as flat ribbons that always
+//! face the camera, e.g. for projectiles, sword swings, or skid marks.
+
+use crate::debug_draw::DebugVertex;
+use crate::ecs::{Component, Scene};
+use crate::math::Transform;
+use crate::renderer::{Color, VertexLayout, DEPTH_FORMAT};
+use crate::utils::color_utils;
+use glam::{Mat4, Vec3};
+use std::collections::VecDeque;
+use wgpu::util::DeviceExt;
+
+/// A point along a ribbon
+#[derive(Debug, Clone, Copy)]
+pub struct RibbonPoint {
+    pub position: Vec3,
+    pub width: f32,
+    pub color: Color,
+}
+
+/// Polyline with a width and a color gradient from the first to the last point
+#[derive(Debug, Clone)]
+pub struct LineRenderer {
+    pub points: Vec<Vec3>,
+    pub width: f32,
+    pub start_color: Color,
+    pub end_color: Color,
+    /// Treat points as relative to the entity's Transform
+    pub local_space: bool,
+}
+
+impl Component for LineRenderer {}
+
+impl LineRenderer {
+    /// Create a line with a single color
+    pub fn new(points: Vec<Vec3>, width: f32, color: Color) -> Self {
+        Self {
+            points,
+            width,
+            start_color: color,
+            end_color: color,
+            local_space: false,
+        }
+    }
+
+    /// Set the colors at the start and end of the line
+    pub fn with_gradient(mut self, start: Color, end: Color) -> Self {
+        self.start_color = start;
+        self.end_color = end;
+        self
+    }
+
+    /// Interpret points relative to the entity's Transform
+    pub fn with_local_space(mut self, local_space: bool) -> Self {
+        self.local_space = local_space;
+        self
+    }
+
+    /// Get the ribbon points, with the gradient spread by distance along the line
+    pub fn ribbon_points(&self, transform: Mat4) -> Vec<RibbonPoint> {
+        let positions: Vec<Vec3> = self.points.iter().map(|p| transform.transform_point3(*p)).collect();
+        let total: f32 = positions.windows(2).map(|w| w[0].distance(w[1])).sum();
+
+        let mut travelled = 0.0;
+        positions
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| {
+                if i > 0 {
+                    travelled += positions[i - 1].distance(position);
+                }
+                let t = if total > 0.0 { travelled / total } else { 0.0 };
+                RibbonPoint {
+                    position,
+                    width: self.width,
+                    color: color_utils::lerp(self.start_color, self.end_color, t),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TrailPoint {
+    position: Vec3,
+    age: f32,
+}
+
+/// Fading trail left behind an entity as it moves
+#[derive(Debug, Clone)]
+pub struct TrailRenderer {
+    /// Seconds a point stays in the trail
+    pub lifetime: f32,
+    pub start_width: f32,
+    pub end_width: f32,
+    pub start_color: Color,
+    pub end_color: Color,
+    /// Minimum distance moved before a new point is added
+    pub min_vertex_distance: f32,
+    /// Whether new points are added (existing ones still fade out when false)
+    pub emitting: bool,
+    points: VecDeque<TrailPoint>,
+    head: Option<Vec3>,
+}
+
+impl Component for TrailRenderer {}
+
+impl TrailRenderer {
+    /// Create a trail that fades from `color` to transparent over `lifetime` seconds
+    pub fn new(lifetime: f32, width: f32, color: Color) -> Self {
+        Self {
+            lifetime,
+            start_width: width,
+            end_width: width,
+            start_color: color,
+            end_color: Color::new(color.r, color.g, color.b, 0.0),
+            min_vertex_distance: 0.1,
+            emitting: true,
+            points: VecDeque::new(),
+            head: None,
+        }
+    }
+
+    /// Set the width at the head and tail of the trail
+    pub fn with_width(mut self, start: f32, end: f32) -> Self {
+        self.start_width = start;
+        self.end_width = end;
+        self
+    }
+
+    /// Set the colors at the head and tail of the trail
+    pub fn with_gradient(mut self, start: Color, end: Color) -> Self {
+        self.start_color = start;
+        self.end_color = end;
+        self
+    }
+
+    /// Age the trail and follow the emitter to `position`
+    pub fn update(&mut self, position: Vec3, delta: f32) {
+        for point in &mut self.points {
+            point.age += delta;
+        }
+        while self.points.front().is_some_and(|p| p.age >= self.lifetime) {
+            self.points.pop_front();
+        }
+
+        if !self.emitting {
+            self.head = None;
+            return;
+        }
+
+        let far_enough = self
+            .points
+            .back()
+            .is_none_or(|p| p.position.distance(position) >= self.min_vertex_distance);
+        if far_enough {
+            self.points.push_back(TrailPoint { position, age: 0.0 });
+        }
+        self.head = Some(position);
+    }
+
+    /// Remove all points (e.g. after teleporting)
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.head = None;
+    }
+
+    /// Get the number of stored points
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Check if the trail has no points
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Get the ribbon points from the head (newest) to the tail (oldest)
+    pub fn ribbon_points(&self) -> Vec<RibbonPoint> {
+        let lifetime = self.lifetime.max(f32::EPSILON);
+        let point = |position: Vec3, age: f32| {
+            let t = (age / lifetime).clamp(0.0, 1.0);
+            RibbonPoint {
+                position,
+                width: self.start_width + (self.end_width - self.start_width) * t,
+                color: color_utils::lerp(self.start_color, self.end_color, t),
+            }
+        };
+
+        let mut points = Vec::with_capacity(self.points.len() + 1);
+        if let Some(head) = self.head {
+            if self.points.back().is_none_or(|p| p.position != head) {
+                points.push(point(head, 0.0));
+            }
+        }
+        points.extend(self.points.iter().rev().map(|p| point(p.position, p.age)));
+        points
+    }
+}
+
+/// Move every trail to its entity's position (call once per frame)
+pub fn update_trails(scene: &mut Scene, delta: f32) {
+    for (transform, trail) in scene.query_mut::<(&Transform, &mut TrailRenderer)>() {
+        trail.update(transform.position, delta);
+    }
+}
+
+/// Camera-facing ribbons queued for the current frame
+#[derive(Debug, Default)]
+pub struct Ribbons {
+    vertices: Vec<DebugVertex>,
+}
+
+impl Ribbons {
+    /// Create an empty ribbon list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a ribbon through `points`, turned to face `camera_position`
+    pub fn ribbon(&mut self, points: &[RibbonPoint], camera_position: Vec3) {
+        if points.len() < 2 {
+            return;
+        }
+
+        let edges: Vec<(DebugVertex, DebugVertex)> = points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let prev = points[i.saturating_sub(1)].position;
+                let next = points[(i + 1).min(points.len() - 1)].position;
+                let tangent = next - prev;
+                let side = tangent
+                    .cross(camera_position - point.position)
+                    .normalize_or_zero()
+                    * (point.width * 0.5);
+                let color = point.color.to_array();
+                (
+                    DebugVertex {
+                        position: (point.position - side).to_array(),
+                        color,
+                    },
+                    DebugVertex {
+                        position: (point.position + side).to_array(),
+                        color,
+                    },
+                )
+            })
+            .collect();
+
+        for pair in edges.windows(2) {
+            let ((left0, right0), (left1, right1)) = (pair[0], pair[1]);
+            self.vertices.extend_from_slice(&[left0, right0, left1, right0, right1, left1]);
+        }
+    }
+
+    /// Queue the line and trail renderers of all active entities
+    pub fn queue_scene(&mut self, scene: &Scene, camera_position: Vec3) {
+        for entity in scene.active_entities() {
+            if let Some(line) = entity.get_component::<LineRenderer>() {
+                let transform = match entity.get_component::<Transform>() {
+                    Some(transform) if line.local_space => transform.matrix(),
+                    _ => Mat4::IDENTITY,
+                };
+                self.ribbon(&line.ribbon_points(transform), camera_position);
+            }
+            if let Some(trail) = entity.get_component::<TrailRenderer>() {
+                self.ribbon(&trail.ribbon_points(), camera_position);
+            }
+        }
+    }
+
+    /// Get the queued vertices (a triangle list)
+    pub fn vertices(&self) -> &[DebugVertex] {
+        &self.vertices
+    }
+
+    /// Check if nothing is queued
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// Remove all queued ribbons
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+/// GPU pipeline drawing alpha-blended ribbons, depth-tested against the scene
+pub(crate) struct RibbonPipeline {
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    capacity: usize,
+    vertex_count: u32,
+}
+
+impl RibbonPipeline {
+    const INITIAL_CAPACITY: usize = 4096;
+
+    pub(crate) fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Ribbon Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[Mat4::IDENTITY.to_cols_array_2d()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ribbon_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ribbon_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Ribbon Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/ribbon.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Ribbon Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Ribbon Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[DebugVertex::layout()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        let vertex_buffer = Self::create_vertex_buffer(device, Self::INITIAL_CAPACITY);
+
+        Self {
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            vertex_buffer,
+            capacity: Self::INITIAL_CAPACITY,
+            vertex_count: 0,
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Ribbon Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<DebugVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Upload this frame's ribbons, growing the vertex buffer if needed
+    pub(crate) fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, ribbons: &Ribbons, view_proj: Mat4) {
+        let vertices = ribbons.vertices();
+        if vertices.len() > self.capacity {
+            self.capacity = vertices.len().next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(device, self.capacity);
+        }
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[view_proj.to_cols_array_2d()]));
+        if !vertices.is_empty() {
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(vertices));
+        }
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    /// Record the ribbon draw into a pass targeting the surface
+    pub(crate) fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trail_fades_and_follows() {
+        let mut trail = TrailRenderer::new(1.0, 0.5, Color::WHITE).with_width(0.5, 0.0);
+        trail.min_vertex_distance = 1.0;
+
+        trail.update(Vec3::ZERO, 0.0);
+        trail.update(Vec3::new(0.5, 0.0, 0.0), 0.25);
+        assert_eq!(trail.len(), 1);
+        trail.update(Vec3::new(1.0, 0.0, 0.0), 0.25);
+        assert_eq!(trail.len(), 2);
+
+        // The head follows the emitter, the tail is oldest and most faded
+        trail.update(Vec3::new(1.5, 0.0, 0.0), 0.25);
+        let points = trail.ribbon_points();
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].position, Vec3::new(1.5, 0.0, 0.0));
+        assert_eq!(points[0].color.a, 1.0);
+        assert!((points[2].color.a - 0.25).abs() < 1e-5);
+        assert!((points[2].width - 0.125).abs() < 1e-5);
+
+        trail.emitting = false;
+        trail.update(Vec3::ZERO, 0.3);
+        assert_eq!(trail.len(), 1);
+        trail.update(Vec3::ZERO, 1.0);
+        assert!(trail.is_empty());
+    }
+
+    #[test]
+    fn test_ribbon_faces_camera() {
+        let line = LineRenderer::new(vec![Vec3::ZERO, Vec3::X, Vec3::X * 3.0], 2.0, Color::RED)
+            .with_gradient(Color::RED, Color::BLUE);
+        let points = line.ribbon_points(Mat4::IDENTITY);
+        assert!((points[1].color.b - 1.0 / 3.0).abs() < 1e-5);
+
+        let mut ribbons = Ribbons::new();
+        ribbons.ribbon(&points, Vec3::new(0.0, 0.0, 10.0));
+        assert_eq!(ribbons.vertices().len(), 12);
+
+        // Looking down Z at a line along X, the ribbon spreads along Y
+        for vertex in ribbons.vertices() {
+            assert!((vertex.position[1].abs() - 1.0).abs() < 1e-5);
+            assert!(vertex.position[2].abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Per-frame arena of dynamic uniform buffer memory
+//!
+//! Per-object uniforms are pushed into a CPU staging area at offsets aligned to the
+//! device's `min_uniform_buffer_offset_alignment`, then uploaded with a single buffer
+//! write. Draws bind one bind group with the returned dynamic offsets, so thousands of
+//! objects share one buffer instead of each owning one. The arena is reset every frame;
+//! wgpu applies queued writes before the next submission, so the buffer can be reused
+//! while the previous frame is still on the GPU.
+
+use bytemuck::Pod;
+
+/// Bytes reserved when the arena is created, enough for a small scene
+const INITIAL_CAPACITY: u64 = 64 * 256;
+
+/// Round `size` up to a multiple of `alignment`
+pub(crate) fn aligned_size(size: u64, alignment: u64) -> u64 {
+    size.next_multiple_of(alignment.max(1))
+}
+
+/// Growable uniform buffer filled once per frame and bound with dynamic offsets
+pub(crate) struct UniformRing {
+    label: &'static str,
+    buffer: wgpu::Buffer,
+    /// Staging bytes for this frame, kept between frames to avoid reallocating
+    data: Vec<u8>,
+    alignment: u64,
+}
+
+impl UniformRing {
+    pub(crate) fn new(device: &wgpu::Device, label: &'static str) -> Self {
+        Self {
+            label,
+            buffer: Self::create_buffer(device, label, INITIAL_CAPACITY),
+            data: Vec::new(),
+            alignment: device.limits().min_uniform_buffer_offset_alignment as u64,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, label: &str, size: u64) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Buffer to bind; recreated by `flush` when it grows
+    pub(crate) fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Bytes pushed this frame, including alignment padding
+    pub(crate) fn len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    /// Forget the previous frame's uniforms
+    pub(crate) fn reset(&mut self) {
+        self.data.clear();
+    }
+
+    /// Stage a uniform and return its dynamic offset
+    pub(crate) fn push<T: Pod>(&mut self, value: &T) -> u32 {
+        let offset = aligned_size(self.len(), self.alignment);
+        self.data.resize(offset as usize, 0);
+        self.data.extend_from_slice(bytemuck::bytes_of(value));
+        offset as u32
+    }
+
+    /// Upload the staged uniforms, growing the buffer to fit them; returns whether the
+    /// buffer was recreated, in which case bind groups using it must be rebuilt
+    pub(crate) fn flush(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        if self.data.is_empty() {
+            return false;
+        }
+        // Pad to the alignment so every offset can bind a full uniform
+        let size = aligned_size(self.len(), self.alignment);
+        self.data.resize(size as usize, 0);
+        let grown = size > self.buffer.size();
+        if grown {
+            let capacity = size.next_power_of_two().max(INITIAL_CAPACITY);
+            log::debug!("Growing {} to {} bytes", self.label, capacity);
+            self.buffer = Self::create_buffer(device, self.label, capacity);
+        }
+        queue.write_buffer(&self.buffer, 0, &self.data);
+        grown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::Renderer;
+
+    #[test]
+    fn test_aligned_size() {
+        assert_eq!(aligned_size(160, 256), 256);
+        assert_eq!(aligned_size(256, 256), 256);
+        assert_eq!(aligned_size(0, 256), 0);
+        assert_eq!(aligned_size(7, 0), 7);
+    }
+
+    #[test]
+    fn test_push_aligns_offsets_and_grows() {
+        let config = crate::config::EngineConfig::default().renderer;
+        let Ok(renderer) = pollster::block_on(Renderer::new_headless((4, 4), &config)) else {
+            // No GPU adapter on this machine
+            return;
+        };
+        let (device, queue) = (renderer.device(), renderer.queue());
+        let mut ring = UniformRing::new(device, "Test Uniform Ring");
+        let alignment = ring.alignment;
+
+        let offsets: Vec<u32> = (0..10_000u32).map(|i| ring.push(&[[i as f32; 4]; 10])).collect();
+        assert_eq!(offsets[1] as u64, aligned_size(160, alignment));
+        assert!(offsets.iter().all(|&offset| (offset as u64).is_multiple_of(alignment)));
+        assert!(ring.flush(device, queue));
+        assert!(ring.buffer().size() >= ring.len());
+
+        // The grown buffer is reused once the next frame fits
+        ring.reset();
+        assert_eq!(ring.push(&[0u32; 4]), 0);
+        assert!(!ring.flush(device, queue));
+    }
+}
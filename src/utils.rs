@@ -13,6 +13,7 @@ pub fn generate_seed() -> u64 {
 }
 
 /// Simple pseudo-random number generator (LCG)
+#[derive(Debug, Clone)]
 pub struct Random {
     state: u64,
 }
@@ -199,7 +200,7 @@ pub mod easing {
 pub mod color_utils {
     use crate::renderer::Color;
 
-    /// Convert HSV to RGB color
+    /// Convert HSV, as color pickers show it over sRGB values, to a (linear) color
     pub fn hsv_to_rgb(h: f32, s: f32, v: f32) -> Color {
         let h = h % 360.0;
         let c = v * s;
@@ -220,7 +221,7 @@ pub mod color_utils {
             (c, 0.0, x)
         };
 
-        Color::rgb(r + m, g + m, b + m)
+        Color::from_srgb(r + m, g + m, b + m)
     }
 
     /// Lerp between two colors
@@ -0,0 +1,187 @@
+//! Virtual file system for game assets
+//!
+//! Directories are mounted with a priority; when several mounts contain the
+//! same virtual path, the one with the highest priority wins. This lets mods
+//! and patches override shipped assets without touching the original files.
+//! Virtual paths use `/` separators and cannot escape a mount root.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// A directory mounted into the VFS
+#[derive(Debug, Clone)]
+pub struct Mount {
+    /// Name used to unmount (e.g. the mod id)
+    pub name: String,
+    /// Directory on disk
+    pub root: PathBuf,
+    /// Virtual directory the mount appears under ("" for the root)
+    pub prefix: String,
+    /// Higher priority mounts override lower ones
+    pub priority: i32,
+}
+
+/// Layered, read-only view over mounted asset directories
+#[derive(Debug, Clone, Default)]
+pub struct Vfs {
+    /// Sorted by descending priority; later mounts come first among equals
+    mounts: Vec<Mount>,
+}
+
+/// Normalize a virtual path, rejecting absolute paths and `..`
+pub fn normalize_path(path: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => return None,
+            part if part.contains(':') => return None,
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+impl Vfs {
+    /// Create an empty VFS
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mount a directory at the virtual root
+    pub fn mount<P: AsRef<Path>>(&mut self, name: &str, root: P, priority: i32) {
+        self.mount_at(name, root, "", priority);
+    }
+
+    /// Mount a directory under a virtual prefix (e.g. "textures")
+    pub fn mount_at<P: AsRef<Path>>(&mut self, name: &str, root: P, prefix: &str, priority: i32) {
+        let mount = Mount {
+            name: name.to_string(),
+            root: root.as_ref().to_path_buf(),
+            prefix: normalize_path(prefix).unwrap_or_default(),
+            priority,
+        };
+        let index = self
+            .mounts
+            .iter()
+            .position(|m| m.priority <= priority)
+            .unwrap_or(self.mounts.len());
+        log::info!("Mounted {:?} as '{}' (priority {})", mount.root, name, priority);
+        self.mounts.insert(index, mount);
+    }
+
+    /// Remove every mount with the given name
+    pub fn unmount(&mut self, name: &str) -> bool {
+        let before = self.mounts.len();
+        self.mounts.retain(|m| m.name != name);
+        self.mounts.len() != before
+    }
+
+    /// Get the mounts from highest to lowest priority
+    pub fn mounts(&self) -> &[Mount] {
+        &self.mounts
+    }
+
+    /// Path on disk relative to a mount, if the virtual path falls under its prefix
+    fn mount_path(mount: &Mount, path: &str) -> Option<PathBuf> {
+        let relative = if mount.prefix.is_empty() {
+            path
+        } else if path == mount.prefix {
+            ""
+        } else {
+            path.strip_prefix(&mount.prefix)?.strip_prefix('/')?
+        };
+        Some(mount.root.join(relative))
+    }
+
+    /// Resolve a virtual path to the highest priority file on disk
+    pub fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let path = normalize_path(path)?;
+        self.mounts
+            .iter()
+            .filter_map(|m| Self::mount_path(m, &path))
+            .find(|p| p.is_file())
+    }
+
+    /// Get the name of the mount providing a virtual path
+    pub fn provider(&self, path: &str) -> Option<&str> {
+        let path = normalize_path(path)?;
+        self.mounts
+            .iter()
+            .find(|m| Self::mount_path(m, &path).is_some_and(|p| p.is_file()))
+            .map(|m| m.name.as_str())
+    }
+
+    /// Check if a virtual file exists
+    pub fn exists(&self, path: &str) -> bool {
+        self.resolve(path).is_some()
+    }
+
+    /// Read a virtual file
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+        let resolved = self
+            .resolve(path)
+            .ok_or_else(|| format!("File not found in VFS: {}", path))?;
+        std::fs::read(&resolved).map_err(|e| format!("Failed to read {:?}: {}", resolved, e))
+    }
+
+    /// Read a virtual file as UTF-8 text
+    pub fn read_to_string(&self, path: &str) -> Result<String, String> {
+        let bytes = self.read(path)?;
+        String::from_utf8(bytes).map_err(|e| format!("File is not valid UTF-8: {}: {}", path, e))
+    }
+
+    /// List the files and directories in a virtual directory, merged across mounts
+    pub fn list(&self, dir: &str) -> Vec<String> {
+        let Some(dir) = normalize_path(dir) else {
+            return Vec::new();
+        };
+        let mut entries = BTreeSet::new();
+        for mount in &self.mounts {
+            let Some(path) = Self::mount_path(mount, &dir) else {
+                continue;
+            };
+            let Ok(read_dir) = std::fs::read_dir(&path) else {
+                continue;
+            };
+            for entry in read_dir.flatten() {
+                if let Some(name) = entry.file_name().to_str() {
+                    entries.insert(name.to_string());
+                }
+            }
+        }
+        entries.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mount_priority_overrides() {
+        let dir = std::env::temp_dir().join(format!("rgame_vfs_test_{}", std::process::id()));
+        let base = dir.join("base");
+        let patch = dir.join("patch");
+        std::fs::create_dir_all(base.join("textures")).unwrap();
+        std::fs::create_dir_all(patch.join("textures")).unwrap();
+        std::fs::write(base.join("textures/grass.png"), b"base").unwrap();
+        std::fs::write(base.join("textures/rock.png"), b"base").unwrap();
+        std::fs::write(patch.join("textures/grass.png"), b"patch").unwrap();
+
+        let mut vfs = Vfs::new();
+        vfs.mount("patch", &patch, 10);
+        vfs.mount("base", &base, 0);
+
+        assert_eq!(vfs.read("textures/grass.png").unwrap(), b"patch");
+        assert_eq!(vfs.read("/textures//rock.png").unwrap(), b"base");
+        assert_eq!(vfs.provider("textures/grass.png"), Some("patch"));
+        assert_eq!(vfs.list("textures"), vec!["grass.png", "rock.png"]);
+        assert!(vfs.resolve("../secret.txt").is_none());
+
+        vfs.unmount("patch");
+        assert_eq!(vfs.read("textures/grass.png").unwrap(), b"base");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
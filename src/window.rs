@@ -3,15 +3,40 @@
 //! Handles window creation, events, and surface management for rendering.
 
 use winit::{
-    event_loop::EventLoop,
-    window::{Window as WinitWindow, WindowBuilder},
-    dpi::PhysicalSize,
+    event_loop::EventLoopWindowTarget,
+    window::{CursorGrabMode, Window as WinitWindow, WindowBuilder},
+    dpi::{PhysicalPosition, PhysicalSize},
 };
 use crate::config::WindowConfig;
+use glam::Vec2;
+
+/// How the cursor is held to the window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CursorGrab {
+    /// The cursor moves freely
+    #[default]
+    None,
+    /// The cursor can't leave the window
+    Confined,
+    /// The cursor stays where it is; only raw mouse motion is reported
+    Locked,
+}
+
+impl CursorGrab {
+    /// Modes to try in order, as platforms support only one of confining and locking
+    fn attempts(self) -> &'static [CursorGrabMode] {
+        match self {
+            CursorGrab::None => &[CursorGrabMode::None],
+            CursorGrab::Confined => &[CursorGrabMode::Confined, CursorGrabMode::Locked],
+            CursorGrab::Locked => &[CursorGrabMode::Locked, CursorGrabMode::Confined],
+        }
+    }
+}
 
 /// Window wrapper for the engine
 pub struct Window {
     window: WinitWindow,
+    cursor_grab: CursorGrab,
 }
 
 impl Window {
@@ -19,8 +44,8 @@ impl Window {
     ///
     /// # Arguments
     /// * `config` - Window configuration
-    /// * `event_loop` - The event loop (from winit)
-    pub fn new(config: &WindowConfig, event_loop: &EventLoop<()>) -> Self {
+    /// * `event_loop` - The event loop or its window target (from winit)
+    pub fn new(config: &WindowConfig, event_loop: &EventLoopWindowTarget<()>) -> Self {
         let mut window_builder = WindowBuilder::new()
             .with_title(&config.title)
             .with_inner_size(PhysicalSize::new(config.width, config.height))
@@ -38,7 +63,10 @@ impl Window {
 
         log::info!("Window created: {}x{}", config.width, config.height);
 
-        Self { window }
+        Self {
+            window,
+            cursor_grab: CursorGrab::None,
+        }
     }
 
     /// Get reference to the inner winit window
@@ -72,4 +100,59 @@ impl Window {
     pub fn id(&self) -> winit::window::WindowId {
         self.window.id()
     }
+
+    /// Confine or lock the cursor, returning the grab the platform allowed
+    ///
+    /// Platforms that can't lock the cursor (Windows, X11) confine it instead, and those
+    /// that can't confine it (macOS, web) lock it.
+    pub fn set_cursor_grab(&mut self, grab: CursorGrab) -> CursorGrab {
+        let mut errors = Vec::new();
+        for &mode in grab.attempts() {
+            match self.window.set_cursor_grab(mode) {
+                Ok(()) => {
+                    self.cursor_grab = match mode {
+                        CursorGrabMode::None => CursorGrab::None,
+                        CursorGrabMode::Confined => CursorGrab::Confined,
+                        CursorGrabMode::Locked => CursorGrab::Locked,
+                    };
+                    return self.cursor_grab;
+                }
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        log::warn!("Failed to grab cursor ({:?}): {}", grab, errors.join("; "));
+        self.cursor_grab = CursorGrab::None;
+        self.cursor_grab
+    }
+
+    /// Get the grab applied by the last `set_cursor_grab`
+    pub fn cursor_grab(&self) -> CursorGrab {
+        self.cursor_grab
+    }
+
+    /// Show or hide the cursor while it's over the window
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.window.set_cursor_visible(visible);
+    }
+
+    /// Let an input method editor compose text in the window
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.window.set_ime_allowed(allowed);
+    }
+
+    /// Place the IME's candidate window next to a text field (in pixels)
+    pub fn set_ime_cursor_area(&self, position: Vec2, size: Vec2) {
+        self.window.set_ime_cursor_area(
+            PhysicalPosition::new(position.x, position.y),
+            PhysicalSize::new(size.x, size.y),
+        );
+    }
+
+    /// Move the cursor to the center of the window
+    pub fn center_cursor(&self) -> Result<(), String> {
+        let (width, height) = self.size();
+        self.window
+            .set_cursor_position(PhysicalPosition::new(width / 2, height / 2))
+            .map_err(|e| format!("Failed to move cursor: {}", e))
+    }
 }